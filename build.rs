@@ -0,0 +1,26 @@
+//! Build script of oneiromancer: links against IDA Pro when the `idalib` feature is enabled.
+
+fn main() {
+    println!("cargo::rerun-if-changed=build.rs");
+    #[cfg(feature = "idalib")]
+    link_idalib();
+}
+
+/// Configures the linkage against the idalib libraries of the IDA Pro installation, failing the
+/// build with a clear error if there is none.
+#[cfg(feature = "idalib")]
+fn link_idalib() {
+    println!("cargo::rerun-if-env-changed=IDADIR");
+    let (_, idalib, _) = idalib_build::idalib_install_paths_with(false);
+    if !idalib.exists() {
+        println!(
+            "cargo::error=the `idalib` feature requires IDA Pro 9.x, but `{}` does not exist (set \
+             `IDADIR` to the IDA installation directory)",
+            idalib.display()
+        );
+        return;
+    }
+    if let Err(err) = idalib_build::configure_linkage() {
+        println!("cargo::error=failed to configure the linkage against idalib: {err}");
+    }
+}
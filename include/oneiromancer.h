@@ -0,0 +1,74 @@
+#ifndef ONEIROMANCER_H
+#define ONEIROMANCER_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+#ifndef ONEIROMANCER_FFI
+#define ONEIROMANCER_FFI 1
+#endif
+
+#if defined(ONEIROMANCER_FFI)
+// The analysis succeeded, and the results are in `*out_json`.
+#define ONEIROMANCER_OK 0
+#endif
+
+#if defined(ONEIROMANCER_FFI)
+// An argument is a null pointer where one is not allowed, or not valid UTF-8.
+#define ONEIROMANCER_INVALID_ARGUMENT 1
+#endif
+
+#if defined(ONEIROMANCER_FFI)
+// The analysis failed (e.g., the Ollama server is unreachable or the response is malformed).
+#define ONEIROMANCER_ANALYSIS_FAILED 2
+#endif
+
+#if defined(ONEIROMANCER_FFI)
+// The analysis panicked (the panic was caught, and the host can keep running).
+#define ONEIROMANCER_PANICKED 3
+#endif
+
+#if defined(ONEIROMANCER_FFI)
+// Analyzes the NUL-terminated pseudocode in `code` with `model` via the Ollama API at `base_url`.
+//
+// On success, returns [`ONEIROMANCER_OK`] and stores in `*out_json` the results as a JSON object
+// with the `function_name`, `comment`, and `variables` fields (as serialized by
+// [`OneiromancerResults`](crate::OneiromancerResults)). On failure, returns the error code and
+// stores in `*out_err` the error message, if `out_err` is not null. Each output string that is
+// set must be released with [`oneiromancer_free_string`]; the others are set to null.
+//
+// `base_url` and `model` may be null, to use the `OLLAMA_BASEURL` and `OLLAMA_MODEL` environment
+// variables or the defaults of the command line tool.
+//
+// # Safety
+//
+// `code`, and `base_url` and `model` if not null, must point to NUL-terminated strings that stay
+// valid for the duration of the call. `out_json`, and `out_err` if not null, must point to
+// writable `char *` locations.
+int oneiromancer_analyze(const char *code,
+                         const char *base_url,
+                         const char *model,
+                         char **out_json,
+                         char **out_err);
+#endif
+
+#if defined(ONEIROMANCER_FFI)
+// Releases a string returned by the library. Does nothing if `string` is null.
+//
+// # Safety
+//
+// `string` must be null, or a string returned by the library that is not released yet.
+void oneiromancer_free_string(char *string);
+#endif
+
+#if defined(ONEIROMANCER_FFI)
+// Returns the version of the library as a static NUL-terminated string, which must not be
+// released.
+const char *oneiromancer_version(void);
+#endif
+
+#endif  /* ONEIROMANCER_H */
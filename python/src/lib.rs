@@ -0,0 +1,188 @@
+//! Python bindings of oneiromancer, built with PyO3 and maturin (see `pyproject.toml`).
+//!
+//! The `oneiromancer` Python module exposes [`analyze_code`], [`apply_renames`], and
+//! [`format_description`], so that IDAPython and other Python automation can call oneiromancer
+//! directly instead of running the binary and parsing its output. Results are plain dicts with the
+//! same fields as the JSON serialization of [`OneiromancerResults`], and errors are raised as
+//! subclasses of `oneiromancer.OneiromancerError`.
+
+use oneiromancer::{CommentOptions, CommentStyle, CommentWidth, Oneiromancer, OneiromancerResults};
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+create_exception!(
+    oneiromancer,
+    OneiromancerError,
+    PyException,
+    "Base class of the errors raised by oneiromancer."
+);
+create_exception!(
+    oneiromancer,
+    InvalidInputError,
+    OneiromancerError,
+    "The input is not valid (e.g., it is too large or has an unsupported file extension)."
+);
+create_exception!(
+    oneiromancer,
+    FileReadError,
+    OneiromancerError,
+    "The input could not be read."
+);
+create_exception!(
+    oneiromancer,
+    OllamaQueryError,
+    OneiromancerError,
+    "The Ollama API could not be queried (e.g., the server is down or the model is missing)."
+);
+create_exception!(
+    oneiromancer,
+    ResponseParseError,
+    OneiromancerError,
+    "The response of the model could not be parsed."
+);
+
+/// Analyzes the pseudocode in `code` with the local LLM, returning a dict with `function_name`,
+/// `comment`, and `variables` (a list of dicts with `original_name` and `new_name`).
+///
+/// `base_url` and `model` default to the `OLLAMA_BASEURL` and `OLLAMA_MODEL` environment variables,
+/// or to the defaults of the command line tool. Keyword arguments are passed through to the model
+/// as options (e.g., `temperature=0, seed=42`). The GIL is released while waiting for the model, so
+/// that other Python threads (e.g., the IDA user interface) keep running.
+#[pyfunction]
+#[pyo3(signature = (code, base_url = None, model = None, **options))]
+fn analyze_code(
+    py: Python<'_>,
+    code: String,
+    base_url: Option<String>,
+    model: Option<String>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<Py<PyAny>> {
+    let mut client = Oneiromancer::new();
+    if let Some(baseurl) = base_url {
+        client = client.baseurl(baseurl);
+    }
+    if let Some(name) = model {
+        client = client.model(name);
+    }
+    for (key, value) in options.into_iter().flat_map(PyDictMethods::iter) {
+        client = client.option(
+            key.extract::<String>()?,
+            from_python::<serde_json::Value>(&value)?,
+        );
+    }
+
+    let results = py
+        .detach(|| client.analyze_code(code))
+        .map_err(|err| analysis_error(&err))?;
+    python_object(py, &results)
+}
+
+/// Applies the variable renames in `renames` to `code`, replacing identifiers only (not names in
+/// comments or literals), and returns the renamed code.
+///
+/// `renames` is either a dict mapping original names to new names, or a list of dicts with
+/// `original_name` and `new_name` (e.g., the `variables` of the results of [`analyze_code`]).
+#[pyfunction]
+fn apply_renames(code: &str, renames: &Bound<'_, PyAny>) -> PyResult<String> {
+    let variables = if let Ok(dict) = renames.cast::<PyDict>() {
+        dict.iter()
+            .map(|(original, new)| {
+                Ok(oneiromancer::Variable::new(
+                    original.extract::<String>()?,
+                    new.extract::<String>()?,
+                ))
+            })
+            .collect::<PyResult<Vec<_>>>()?
+    } else {
+        from_python(renames)?
+    };
+    Ok(oneiromancer::rename_variables(code, &variables))
+}
+
+/// Formats the function description in `results` (as returned by [`analyze_code`]) as a comment
+/// to prepend to the pseudocode, in the given `style` (`block`, `line`, or `doxygen`) and wrapped
+/// to `width` columns (76 by default).
+#[pyfunction]
+#[pyo3(signature = (results, style = "block", width = None))]
+fn format_description(
+    results: &Bound<'_, PyAny>,
+    style: &str,
+    width: Option<usize>,
+) -> PyResult<String> {
+    let parsed: OneiromancerResults = from_python(results)?;
+    let comment_style = match style {
+        "block" => CommentStyle::Block,
+        "line" => CommentStyle::Line,
+        "doxygen" => CommentStyle::Doxygen,
+        _ => {
+            return Err(PyValueError::new_err(format!(
+                "Invalid comment style `{style}`: expected `block`, `line`, or `doxygen`"
+            )));
+        }
+    };
+    let mut options = CommentOptions::new().style(comment_style);
+    if let Some(columns) = width {
+        options = options.width(CommentWidth::Columns(columns));
+    }
+    Ok(oneiromancer::format_description(&parsed, &options))
+}
+
+/// Maps `err` to the Python exception of its kind.
+fn analysis_error(err: &oneiromancer::OneiromancerError) -> PyErr {
+    let message = err.to_string();
+    match *err {
+        oneiromancer::OneiromancerError::InvalidInput(_) => InvalidInputError::new_err(message),
+        oneiromancer::OneiromancerError::FileReadFailed(_) => FileReadError::new_err(message),
+        oneiromancer::OneiromancerError::OllamaQueryFailed(_)
+        | oneiromancer::OneiromancerError::Timeout(_)
+        | oneiromancer::OneiromancerError::ServerUnreachable { .. }
+        | oneiromancer::OneiromancerError::ModelNotFound { .. } => {
+            OllamaQueryError::new_err(message)
+        }
+        oneiromancer::OneiromancerError::ResponseParseFailed(_) => {
+            ResponseParseError::new_err(message)
+        }
+        oneiromancer::OneiromancerError::LowQualityResult(_) | _ => {
+            OneiromancerError::new_err(message)
+        }
+    }
+}
+
+/// Deserializes a Python object (lists, dicts, strings, numbers, booleans, and `None`) into `T`
+/// through the `json` module, so that nested values are converted as Python serializes them.
+fn from_python<T: DeserializeOwned>(object: &Bound<'_, PyAny>) -> PyResult<T> {
+    let json: String = object
+        .py()
+        .import("json")?
+        .call_method1("dumps", (object,))?
+        .extract()?;
+    serde_json::from_str(&json).map_err(|err| PyValueError::new_err(err.to_string()))
+}
+
+/// Serializes `value` into the equivalent Python object (dicts, lists, and scalars).
+fn python_object(py: Python<'_>, value: &impl Serialize) -> PyResult<Py<PyAny>> {
+    let json =
+        serde_json::to_string(value).map_err(|err| PyValueError::new_err(err.to_string()))?;
+    Ok(py.import("json")?.call_method1("loads", (json,))?.unbind())
+}
+
+/// Python module of oneiromancer.
+#[pymodule]
+#[pyo3(name = "oneiromancer")]
+fn oneiromancer_py(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = module.py();
+    module.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    module.add("OneiromancerError", py.get_type::<OneiromancerError>())?;
+    module.add("InvalidInputError", py.get_type::<InvalidInputError>())?;
+    module.add("FileReadError", py.get_type::<FileReadError>())?;
+    module.add("OllamaQueryError", py.get_type::<OllamaQueryError>())?;
+    module.add("ResponseParseError", py.get_type::<ResponseParseError>())?;
+    module.add_function(wrap_pyfunction!(analyze_code, module)?)?;
+    module.add_function(wrap_pyfunction!(apply_renames, module)?)?;
+    module.add_function(wrap_pyfunction!(format_description, module)?)?;
+    Ok(())
+}
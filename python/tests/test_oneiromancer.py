@@ -0,0 +1,132 @@
+"""Tests of the Python bindings against a mock Ollama API server (no Ollama needed).
+
+Build the module into the current virtual environment with `maturin develop` and run `pytest`.
+"""
+
+import json
+import threading
+import time
+from http.server import BaseHTTPRequestHandler, ThreadingHTTPServer
+
+import pytest
+
+import oneiromancer
+
+CODE = 'int main() { int v1 = 0; printf("Hello, world!"); }'
+RESULTS = {
+    "function_name": "main",
+    "comment": "Entry point of the program.",
+    "variables": [{"original_name": "v1", "new_name": "counter"}],
+}
+
+
+class MockOllama(ThreadingHTTPServer):
+    """Mock Ollama API server answering `POST /api/generate` with a configurable response."""
+
+    def __init__(self):
+        super().__init__(("127.0.0.1", 0), MockHandler)
+        self.requests = []
+        self.status = 200
+        self.response = {"response": json.dumps(RESULTS)}
+        self.delay = 0.0
+
+    @property
+    def base_url(self):
+        return "http://%s:%d" % self.server_address
+
+
+class MockHandler(BaseHTTPRequestHandler):
+    def do_POST(self):
+        length = int(self.headers.get("Content-Length", 0))
+        self.server.requests.append(json.loads(self.rfile.read(length)))
+        time.sleep(self.server.delay)
+        body = json.dumps(self.server.response).encode()
+        self.send_response(self.server.status)
+        self.send_header("Content-Type", "application/json")
+        self.send_header("Content-Length", str(len(body)))
+        self.end_headers()
+        self.wfile.write(body)
+
+    def log_message(self, *args):
+        pass
+
+
+@pytest.fixture
+def ollama():
+    server = MockOllama()
+    thread = threading.Thread(target=server.serve_forever, daemon=True)
+    thread.start()
+    yield server
+    server.shutdown()
+    server.server_close()
+
+
+def test_analyze_code_returns_results_dict(ollama):
+    results = oneiromancer.analyze_code(CODE, base_url=ollama.base_url, model="test-model")
+
+    assert results == RESULTS
+    assert ollama.requests[0]["model"] == "test-model"
+    assert CODE in ollama.requests[0]["prompt"]
+    assert "options" not in ollama.requests[0]
+
+
+def test_analyze_code_passes_keyword_arguments_as_model_options(ollama):
+    oneiromancer.analyze_code(
+        CODE, base_url=ollama.base_url, model="test-model", temperature=0, seed=42, stop=["}"]
+    )
+
+    assert ollama.requests[0]["options"] == {"seed": 42, "stop": ["}"], "temperature": 0}
+
+
+def test_analyze_code_raises_typed_exceptions(ollama):
+    ollama.response = {"response": "not valid json"}
+    with pytest.raises(oneiromancer.ResponseParseError):
+        oneiromancer.analyze_code(CODE, base_url=ollama.base_url, model="test-model")
+
+    ollama.status = 500
+    with pytest.raises(oneiromancer.OllamaQueryError) as excinfo:
+        oneiromancer.analyze_code(CODE, base_url=ollama.base_url, model="test-model")
+    assert isinstance(excinfo.value, oneiromancer.OneiromancerError)
+
+
+def test_analyze_code_releases_the_gil(ollama):
+    ollama.delay = 0.5
+    ticks = []
+
+    def tick():
+        while not ollama.requests:
+            time.sleep(0.01)
+        deadline = time.monotonic() + 0.3
+        while time.monotonic() < deadline:
+            ticks.append(time.monotonic())
+            time.sleep(0.01)
+
+    ticker = threading.Thread(target=tick)
+    ticker.start()
+    oneiromancer.analyze_code(CODE, base_url=ollama.base_url, model="test-model")
+    ticker.join()
+
+    assert len(ticks) > 10
+
+
+def test_apply_renames_replaces_whole_words():
+    code = "int f(int a1) { int v1 = a1; int v10 = v1; return v10; }"
+
+    assert (
+        oneiromancer.apply_renames(code, {"v1": "len", "a1": "size"})
+        == "int f(int size) { int len = size; int v10 = len; return v10; }"
+    )
+    assert oneiromancer.apply_renames(CODE, RESULTS["variables"]) == CODE.replace("v1", "counter")
+    with pytest.raises(ValueError):
+        oneiromancer.apply_renames(code, [{"original_name": "v1"}])
+
+
+def test_format_description_wraps_comment_in_style():
+    assert oneiromancer.format_description(RESULTS) == (
+        "/*\n * main()\n *\n * Entry point of the program.\n */\n\n"
+    )
+    assert oneiromancer.format_description(RESULTS, style="line", width=20) == (
+        "/// main()\n///\n/// Entry point of\n/// the program.\n\n"
+    )
+    with pytest.raises(ValueError):
+        oneiromancer.format_description(RESULTS, style="fancy")
@@ -0,0 +1,258 @@
+//! Parse payloads in the JSON exchange format of the aidapal IDA plugin.
+
+use std::path::Path;
+
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use crate::AnalysisContext;
+
+/// Format of input files.
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InputFormat {
+    /// Detect the format from the file extension (`.json` files are aidapal payloads).
+    #[default]
+    Auto,
+    /// Plain pseudocode.
+    Pseudocode,
+    /// JSON payload saved from the aidapal IDA plugin, with pseudocode and context.
+    AidapalJson,
+}
+
+impl InputFormat {
+    /// Returns the format of the `filepath` input file, detecting it from the extension if
+    /// [`InputFormat::Auto`].
+    #[must_use]
+    pub fn resolve(self, filepath: &Path) -> Self {
+        match self {
+            Self::Auto
+                if filepath
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("json")) =>
+            {
+                Self::AidapalJson
+            }
+            Self::Auto => Self::Pseudocode,
+            Self::Pseudocode | Self::AidapalJson => self,
+        }
+    }
+}
+
+/// Payload in the JSON exchange format of the aidapal IDA plugin.
+#[derive(Debug, Clone)]
+pub struct AidapalPayload {
+    /// Pseudocode of the function to analyze.
+    code: String,
+    /// String literals referenced by the function.
+    strings: Vec<String>,
+    /// Prototypes of the functions called by the function.
+    callees: Vec<String>,
+}
+
+impl AidapalPayload {
+    /// Gets the pseudocode of the function to analyze.
+    #[must_use]
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// Gets the string literals referenced by the function.
+    #[must_use]
+    pub fn strings(&self) -> &[String] {
+        &self.strings
+    }
+
+    /// Gets the prototypes of the functions called by the function.
+    #[must_use]
+    pub fn callees(&self) -> &[String] {
+        &self.callees
+    }
+
+    /// Returns `context` extended with the strings and callee prototypes in the payload.
+    #[must_use]
+    pub fn extend_context(&self, context: AnalysisContext) -> AnalysisContext {
+        context.strings(&self.strings).types(&self.callees)
+    }
+}
+
+/// aidapal payload parsing error type.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum AidapalError {
+    /// The payload is not valid JSON.
+    #[error("invalid JSON: {0}")]
+    InvalidJson(#[from] serde_json::Error),
+    /// The payload is not a JSON object.
+    #[error("payload is not a JSON object")]
+    NotAnObject,
+    /// A required field is missing.
+    #[error("missing field `{0}`")]
+    MissingField(&'static str),
+    /// A field has an unexpected type.
+    #[error("field `{field}` must be {expected}")]
+    InvalidField {
+        /// Name of the field.
+        field: &'static str,
+        /// Expected type of the field.
+        expected: &'static str,
+    },
+    /// The pseudocode is empty.
+    #[error("field `code` is empty")]
+    EmptyCode,
+}
+
+/// Parses a payload in the JSON exchange format of the aidapal IDA plugin.
+///
+/// The `code` field (or `prompt`, as sent to Ollama) holds the pseudocode, while the optional
+/// `strings` and `callees` arrays hold the string literals and callee prototypes, which can be
+/// added to the [`AnalysisContext`] of the analysis with [`AidapalPayload::extend_context`].
+///
+/// # Errors
+///
+/// Returns [`AidapalError`] naming the offending field in case the payload is malformed.
+pub fn parse_aidapal_json(json: &str) -> Result<AidapalPayload, AidapalError> {
+    let value: Value = serde_json::from_str(json)?;
+    let object = value.as_object().ok_or(AidapalError::NotAnObject)?;
+
+    let code = match (object.get("code"), object.get("prompt")) {
+        (Some(code), _) => code.as_str().ok_or(AidapalError::InvalidField {
+            field: "code",
+            expected: "a string",
+        })?,
+        (None, Some(prompt)) => prompt.as_str().ok_or(AidapalError::InvalidField {
+            field: "prompt",
+            expected: "a string",
+        })?,
+        (None, None) => return Err(AidapalError::MissingField("code")),
+    };
+    if code.trim().is_empty() {
+        return Err(AidapalError::EmptyCode);
+    }
+
+    Ok(AidapalPayload {
+        code: code.to_owned(),
+        strings: string_array(object, "strings")?,
+        callees: string_array(object, "callees")?,
+    })
+}
+
+/// Returns the optional `field` array of strings in `object` (empty if missing or `null`).
+fn string_array(
+    object: &Map<String, Value>,
+    field: &'static str,
+) -> Result<Vec<String>, AidapalError> {
+    let invalid = AidapalError::InvalidField {
+        field,
+        expected: "an array of strings",
+    };
+    object
+        .get(field)
+        .filter(|value| !value.is_null())
+        .map_or_else(
+            || Ok(Vec::new()),
+            |value| {
+                value
+                    .as_array()
+                    .and_then(|items| {
+                        items
+                            .iter()
+                            .map(|item| item.as_str().map(str::to_owned))
+                            .collect()
+                    })
+                    .ok_or(invalid)
+            },
+        )
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use super::*;
+
+    const PAYLOAD: &str = include_str!("../tests/data/aidapal.json");
+    const PARTIAL_PAYLOAD: &str = include_str!("../tests/data/aidapal_partial.json");
+
+    #[test]
+    fn parse_aidapal_json_extracts_code_and_context() -> anyhow::Result<()> {
+        let payload = parse_aidapal_json(PAYLOAD)?;
+
+        assert!(
+            payload
+                .code()
+                .starts_with("int __fastcall sub_140001000(const char *a1)\n{"),
+            "wrong code: {}",
+            payload.code()
+        );
+        let prompt = payload.extend_context(AnalysisContext::new()).append_to("");
+        assert!(prompt.contains("//   \"Length: %d\\n\""), "missing strings");
+        assert!(
+            prompt.contains("//   size_t __cdecl strlen(const char *Str);"),
+            "missing callees"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_aidapal_json_accepts_ollama_prompt_field() -> anyhow::Result<()> {
+        let payload = parse_aidapal_json(r#"{"model":"aidapal","prompt":"int f() {}"}"#)?;
+
+        assert_eq!(payload.code(), "int f() {}");
+        assert!(
+            payload.strings().is_empty() && payload.callees().is_empty(),
+            "unexpected context"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_aidapal_json_reports_offending_field() {
+        let partial = parse_aidapal_json(PARTIAL_PAYLOAD);
+        assert!(
+            matches!(
+                partial,
+                Err(AidapalError::InvalidField {
+                    field: "strings",
+                    ..
+                })
+            ),
+            "wrong error returned: {partial:?}"
+        );
+
+        for (json, expected) in [
+            (r#"{"strings":[]}"#, "missing field `code`"),
+            (r#"{"code":1}"#, "field `code` must be a string"),
+            (r#"{"code":" "}"#, "field `code` is empty"),
+            (r#"{"code":"x","callees":"f"}"#, "field `callees` must be"),
+            ("[]", "not a JSON object"),
+            ("{", "invalid JSON"),
+        ] {
+            let result = parse_aidapal_json(json);
+            assert!(
+                result
+                    .as_ref()
+                    .is_err_and(|err| err.to_string().contains(expected)),
+                "{json}: {result:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn input_format_is_detected_from_extension() {
+        assert_eq!(
+            InputFormat::Auto.resolve(Path::new("dump/f.JSON")),
+            InputFormat::AidapalJson
+        );
+        assert_eq!(
+            InputFormat::Auto.resolve(Path::new("dump/f.c")),
+            InputFormat::Pseudocode
+        );
+        assert_eq!(
+            InputFormat::Pseudocode.resolve(Path::new("f.json")),
+            InputFormat::Pseudocode
+        );
+    }
+}
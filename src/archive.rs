@@ -0,0 +1,412 @@
+//! Read pseudocode files from `.zip` and `.tar.gz` archives, and pack output files into archives.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Write as _};
+use std::path::{Path, PathBuf};
+
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use thiserror::Error;
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::discover::{Filters, check_code};
+use crate::{DiscoverError, DiscoverOptions, MAX_INPUT_SIZE, SkipReason};
+
+/// Format of an archive of pseudocode files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ArchiveFormat {
+    /// Zip archive (`.zip`).
+    Zip,
+    /// Gzip-compressed tar archive (`.tar.gz` or `.tgz`).
+    TarGz,
+}
+
+impl ArchiveFormat {
+    /// Extensions of each archive format, matched case-insensitively.
+    const EXTENSIONS: [(&str, Self); 3] = [
+        (".zip", Self::Zip),
+        (".tar.gz", Self::TarGz),
+        (".tgz", Self::TarGz),
+    ];
+
+    /// Returns the format of the `path` archive, according to its extension, or `None` if `path`
+    /// does not name an archive.
+    #[must_use]
+    pub fn detect(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        Self::EXTENSIONS
+            .iter()
+            .find(|&&(extension, _)| name.len() > extension.len() && name.ends_with(extension))
+            .map(|&(_, format)| format)
+    }
+
+    /// Returns the file name of the `path` archive without its extension (e.g., `bundle` for
+    /// `bundle.tar.gz`).
+    #[must_use]
+    pub fn stem(path: &Path) -> String {
+        let name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        let lowercase = name.to_lowercase();
+        Self::EXTENSIONS
+            .iter()
+            .find_map(|&(extension, _)| lowercase.strip_suffix(extension))
+            .and_then(|stem| name.get(..stem.len()))
+            .unwrap_or(&name)
+            .to_owned()
+    }
+}
+
+/// Archive error type.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum ArchiveError {
+    /// File that is not a `.zip`, `.tar.gz`, or `.tgz` archive.
+    #[error("Unsupported archive format (expected `.zip`, `.tar.gz`, or `.tgz`)")]
+    UnsupportedFormat,
+    /// Invalid include or exclude glob pattern.
+    #[error(transparent)]
+    InvalidPattern(#[from] DiscoverError),
+    /// Failure in reading or writing the archive.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// Invalid zip archive.
+    #[error(transparent)]
+    Zip(#[from] ZipError),
+    /// Entry whose path is absolute or escapes the archive (e.g., `../evil.c`).
+    #[error("Unsafe path `{0}` in archive")]
+    UnsafePath(String),
+}
+
+/// Pseudocode file read from an archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    /// Path of the file in the archive.
+    path: PathBuf,
+    /// Pseudocode in the file.
+    code: String,
+}
+
+impl ArchiveEntry {
+    /// Returns the path of the file in the archive.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the pseudocode in the file.
+    #[must_use]
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+}
+
+/// Pseudocode files read from an archive by [`read_archive`].
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveContents {
+    /// Files to analyze, in archive order.
+    entries: Vec<ArchiveEntry>,
+    /// Files that matched the filters but were skipped, with the corresponding reasons.
+    skipped: Vec<(PathBuf, SkipReason)>,
+}
+
+impl ArchiveContents {
+    /// Returns the files to analyze, in archive order.
+    #[must_use]
+    pub fn entries(&self) -> &[ArchiveEntry] {
+        &self.entries
+    }
+
+    /// Returns the files that matched the filters but were skipped, with the corresponding
+    /// reasons (paths are relative to the archive).
+    #[must_use]
+    pub fn skipped(&self) -> &[(PathBuf, SkipReason)] {
+        &self.skipped
+    }
+
+    /// Adds the `path` file with `contents` read from the archive (at most one byte more than
+    /// [`MAX_INPUT_SIZE`]), or skips it if it cannot be analyzed.
+    fn add(&mut self, path: PathBuf, contents: Vec<u8>) {
+        match check_code(contents) {
+            Ok(code) => self.entries.push(ArchiveEntry { path, code }),
+            Err(reason) => self.skipped.push((path, reason)),
+        }
+    }
+}
+
+/// Reads the pseudocode files in the `path` archive (`.zip`, `.tar.gz`, or `.tgz`).
+///
+/// All files in the archive, at any depth, are filtered according to the include and exclude
+/// patterns of `discover` (e.g., `*.c` also matches `sub/test.c`). Binary files, files larger
+/// than [`MAX_INPUT_SIZE`], and files that are not valid UTF-8 are skipped. Directories,
+/// symbolic links, and other special entries are ignored. Absolute paths and paths that escape
+/// the archive (e.g., `../test.c`) are rejected outright, whether they match the filters or not.
+///
+/// # Errors
+///
+/// Returns [`ArchiveError`] in case the archive format is not supported, the archive cannot be
+/// read or contains an unsafe path, or an include or exclude pattern is invalid.
+pub fn read_archive(
+    path: &Path,
+    discover: &DiscoverOptions,
+) -> Result<ArchiveContents, ArchiveError> {
+    let format = ArchiveFormat::detect(path).ok_or(ArchiveError::UnsupportedFormat)?;
+    let reader = BufReader::new(File::open(path)?);
+    let filters = discover.filters()?;
+    match format {
+        ArchiveFormat::Zip => read_zip(reader, &filters),
+        ArchiveFormat::TarGz => read_tar_gz(reader, &filters),
+    }
+}
+
+/// Packs the files in the `dir` directory (recursively, in file name order) into an archive in
+/// `format`, with paths relative to `dir`, and returns the archive.
+///
+/// # Errors
+///
+/// Returns [`ArchiveError`] in case the directory cannot be read or the archive cannot be built.
+pub fn pack_dir(dir: &Path, format: ArchiveFormat) -> Result<Vec<u8>, ArchiveError> {
+    let mut files = Vec::new();
+    collect_files(dir, Path::new(""), &mut files)?;
+    match format {
+        ArchiveFormat::Zip => {
+            let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+            let options =
+                SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+            for (path, contents) in files {
+                writer.start_file(archive_name(&path), options)?;
+                writer.write_all(&contents)?;
+            }
+            Ok(writer.finish()?.into_inner())
+        }
+        ArchiveFormat::TarGz => {
+            let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+            for (path, contents) in files {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(u64::try_from(contents.len()).unwrap_or(u64::MAX));
+                header.set_mode(0o644);
+                builder.append_data(&mut header, archive_name(&path), contents.as_slice())?;
+            }
+            Ok(builder.into_inner()?.finish()?)
+        }
+    }
+}
+
+/// Reads the pseudocode files in a zip archive.
+fn read_zip(
+    reader: impl Read + io::Seek,
+    filters: &Filters,
+) -> Result<ArchiveContents, ArchiveError> {
+    let mut archive = ZipArchive::new(reader)?;
+    // Check all paths first, so that an unsafe archive is rejected before anything is read.
+    let mut paths = Vec::with_capacity(archive.len());
+    for index in 0..archive.len() {
+        let file = archive.by_index_raw(index)?;
+        paths.push(safe_path(file.name())?);
+    }
+
+    let mut contents = ArchiveContents::default();
+    for (index, path) in paths.into_iter().enumerate() {
+        let file = archive.by_index(index)?;
+        if !file.is_file() || !filters.matches(&path) {
+            continue;
+        }
+        if file.size() > max_read_size() {
+            contents.skipped.push((path, SkipReason::TooLarge));
+            continue;
+        }
+        let data = read_limited(file)?;
+        contents.add(path, data);
+    }
+    Ok(contents)
+}
+
+/// Reads the pseudocode files in a gzip-compressed tar archive.
+fn read_tar_gz(reader: impl Read, filters: &Filters) -> Result<ArchiveContents, ArchiveError> {
+    let mut archive = tar::Archive::new(GzDecoder::new(reader));
+    let mut contents = ArchiveContents::default();
+    // Tar archives are read sequentially: an unsafe path rejects the whole archive when found.
+    for result in archive.entries()? {
+        let entry = result?;
+        let path = safe_path(&String::from_utf8_lossy(&entry.path_bytes()))?;
+        if !entry.header().entry_type().is_file() || !filters.matches(&path) {
+            continue;
+        }
+        if entry.header().size()? > max_read_size() {
+            contents.skipped.push((path, SkipReason::TooLarge));
+            continue;
+        }
+        let data = read_limited(entry)?;
+        contents.add(path, data);
+    }
+    Ok(contents)
+}
+
+/// Returns the relative path of the archive entry `name`, or [`ArchiveError::UnsafePath`] if
+/// `name` is absolute (including Windows drive letters) or has `..` components.
+fn safe_path(name: &str) -> Result<PathBuf, ArchiveError> {
+    let unsafe_path = || ArchiveError::UnsafePath(name.to_owned());
+    if name.starts_with(['/', '\\']) {
+        return Err(unsafe_path());
+    }
+    let mut path = PathBuf::new();
+    for (index, component) in name.split(['/', '\\']).enumerate() {
+        match component {
+            "" | "." => (),
+            ".." => return Err(unsafe_path()),
+            _ if index == 0 && component.contains(':') => return Err(unsafe_path()),
+            _ => path.push(component),
+        }
+    }
+    Ok(path)
+}
+
+/// Returns the maximum number of bytes read from an archive entry, i.e., enough to tell that the
+/// entry is larger than [`MAX_INPUT_SIZE`].
+fn max_read_size() -> u64 {
+    u64::try_from(MAX_INPUT_SIZE)
+        .unwrap_or(u64::MAX)
+        .saturating_add(1)
+}
+
+/// Reads at most [`max_read_size`] bytes from `reader`, as the size recorded in archive headers
+/// cannot be trusted.
+fn read_limited(reader: impl Read) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    reader.take(max_read_size()).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Adds the files in the `dir` directory (recursively, in file name order) to `files`, with their
+/// paths prefixed by `prefix`.
+fn collect_files(dir: &Path, prefix: &Path, files: &mut Vec<(PathBuf, Vec<u8>)>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(fs::DirEntry::file_name);
+    for entry in entries {
+        let path = prefix.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            collect_files(&entry.path(), &path, files)?;
+        } else {
+            files.push((path, fs::read(entry.path())?));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the name of the `path` file in an archive, with `/` as separator.
+fn archive_name(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use super::*;
+
+    /// Packs `files` into a temporary directory, then into an archive in `format` saved as
+    /// `name`, and returns the directory that holds the archive.
+    fn archive(
+        name: &str,
+        format: ArchiveFormat,
+        files: &[(&str, &[u8])],
+    ) -> anyhow::Result<tempfile::TempDir> {
+        let tmpdir = tempfile::tempdir()?;
+        let src = tmpdir.path().join("src");
+        for &(path, contents) in files {
+            let filepath = src.join(path);
+            fs::create_dir_all(filepath.parent().unwrap_or(&src))?;
+            fs::write(filepath, contents)?;
+        }
+        fs::write(tmpdir.path().join(name), pack_dir(&src, format)?)?;
+        Ok(tmpdir)
+    }
+
+    #[test]
+    fn detect_and_stem_recognize_archive_extensions() {
+        assert_eq!(
+            ArchiveFormat::detect(Path::new("a/b.ZIP")),
+            Some(ArchiveFormat::Zip)
+        );
+        assert_eq!(
+            ArchiveFormat::detect(Path::new("b.tar.gz")),
+            Some(ArchiveFormat::TarGz)
+        );
+        assert_eq!(
+            ArchiveFormat::detect(Path::new("b.tgz")),
+            Some(ArchiveFormat::TarGz)
+        );
+        assert_eq!(ArchiveFormat::detect(Path::new("b.gz")), None);
+        assert_eq!(ArchiveFormat::detect(Path::new(".zip")), None);
+        assert_eq!(
+            ArchiveFormat::stem(Path::new("dir/Bundle.Tar.GZ")),
+            "Bundle"
+        );
+        assert_eq!(ArchiveFormat::stem(Path::new("bundle.tgz")), "bundle");
+    }
+
+    #[test]
+    fn safe_path_rejects_escaping_paths() {
+        assert_eq!(
+            safe_path("./sub//test.c").ok(),
+            Some(PathBuf::from("sub/test.c"))
+        );
+        for name in [
+            "../test.c",
+            "sub/../../test.c",
+            "/etc/passwd",
+            "\\test.c",
+            "C:/test.c",
+        ] {
+            assert!(
+                matches!(safe_path(name), Err(ArchiveError::UnsafePath(_))),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn read_archive_filters_and_skips_entries_in_both_formats() -> anyhow::Result<()> {
+        let large = vec![b'a'; MAX_INPUT_SIZE.saturating_add(1)];
+        let files: [(&str, &[u8]); 6] = [
+            ("a.c", b"int a() {}"),
+            ("sub/b.c", b"int b() {}"),
+            ("a.out.c", b"int a() {}"),
+            ("notes.txt", b"notes"),
+            ("latin1.c", b"int caf\xe9() {}"),
+            ("large.c", &large),
+        ];
+        for (name, format) in [
+            ("test.zip", ArchiveFormat::Zip),
+            ("test.tar.gz", ArchiveFormat::TarGz),
+        ] {
+            let tmpdir = archive(name, format, &files)?;
+
+            let contents = read_archive(&tmpdir.path().join(name), &DiscoverOptions::new())?;
+
+            let entries: Vec<_> = contents.entries().iter().map(ArchiveEntry::path).collect();
+            assert_eq!(entries, [Path::new("a.c"), Path::new("sub/b.c")], "{name}");
+            assert_eq!(
+                contents.entries().first().map(ArchiveEntry::code),
+                Some("int a() {}")
+            );
+            assert_eq!(
+                contents.skipped(),
+                [
+                    (PathBuf::from("large.c"), SkipReason::TooLarge),
+                    (PathBuf::from("latin1.c"), SkipReason::InvalidUtf8),
+                ],
+                "{name}"
+            );
+        }
+        Ok(())
+    }
+}
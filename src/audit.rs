@@ -0,0 +1,378 @@
+//! Append an audit log of the decisions taken in each run to a JSON Lines file.
+
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write as _};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cache::content_hash;
+use crate::options::ModelOptions;
+use crate::report::Status;
+use crate::stats::millis;
+use crate::{AnalysisReport, Oneiromancer, Variable};
+
+/// Length of run identifiers, in hex digits.
+const RUN_ID_LEN: usize = 16;
+
+/// Audit log of runs, appended to a JSON Lines file as each decision is taken.
+///
+/// Each record carries the identifier of the run, so that the records of different runs (e.g.,
+/// of the files in a batch) appended to the same file can be grouped. Records are flushed as soon
+/// as they are written, so that an interrupted run still leaves a useful log. Unlike debug output,
+/// records hold no prompts or responses, only which files were analyzed, with which model and
+/// options, which renames were applied or skipped (and why), durations, and warnings.
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    /// Identifier of the run.
+    run_id: String,
+    /// Log file, opened for appending.
+    file: Arc<Mutex<File>>,
+}
+
+impl AuditLog {
+    /// Opens the audit log in the `filepath` file for a new run, creating the file if it does not
+    /// exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`io::Error`] in case the file cannot be opened for appending.
+    pub fn open(filepath: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(filepath)?;
+        Ok(Self {
+            run_id: new_run_id(),
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+
+    /// Gets the identifier of the run, shared by all its records.
+    #[must_use]
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Records the start of a run of `files` files analyzed with `client`.
+    pub(crate) fn run_started(&self, client: &Oneiromancer, files: usize, dry_run: bool) {
+        self.record(AuditEvent::RunStarted {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            model: client.configured_model().to_owned(),
+            baseurl: client.configured_baseurl().to_owned(),
+            options: client.configured_options().clone(),
+            files,
+            dry_run,
+        });
+    }
+
+    /// Records the outcome of the analysis of a file in `report`, with the rename decisions and
+    /// warnings it produced.
+    pub(crate) fn file_completed(
+        &self,
+        report: &AnalysisReport,
+        renames: &[RenameDecision],
+        warnings: &[String],
+    ) {
+        self.record(AuditEvent::FileCompleted {
+            input: report.input().to_path_buf(),
+            output: report.output().map(Path::to_path_buf),
+            status: report.status(),
+            duration_ms: millis(report.duration()),
+            renames: renames.to_vec(),
+            warnings: warnings.to_vec(),
+            error: report.error().map(str::to_owned),
+        });
+    }
+
+    /// Records the end of a run that took `wall`, with the number of files that `succeeded`,
+    /// `failed`, and were `skipped`.
+    pub(crate) fn run_finished(
+        &self,
+        succeeded: usize,
+        failed: usize,
+        skipped: usize,
+        wall: Duration,
+    ) {
+        self.record(AuditEvent::RunFinished {
+            succeeded,
+            failed,
+            skipped,
+            duration_ms: millis(wall),
+        });
+    }
+
+    /// Appends `event` to the log file. Failures are logged, but do not stop the run.
+    fn record(&self, event: AuditEvent) {
+        let record = AuditRecord {
+            run_id: self.run_id.clone(),
+            timestamp_ms: millis(
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default(),
+            ),
+            event,
+        };
+        let result = serde_json::to_string(&record)
+            .map_err(io::Error::from)
+            .and_then(|json| {
+                let mut file = self.file.lock().unwrap_or_else(PoisonError::into_inner);
+                writeln!(file, "{json}")?;
+                file.flush()
+            });
+        if let Err(err) = result {
+            log::warn!("Failed to write audit log: {err}");
+        }
+    }
+}
+
+/// Record of the audit log, stored as a line of the log file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AuditRecord {
+    /// Identifier of the run.
+    run_id: String,
+    /// Time of the record, in milliseconds since the Unix epoch.
+    timestamp_ms: u64,
+    /// Recorded event.
+    #[serde(flatten)]
+    event: AuditEvent,
+}
+
+/// Event recorded in the audit log, tagged by the `event` field.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AuditEvent {
+    /// Start of a run.
+    RunStarted {
+        /// Version of oneiromancer.
+        version: String,
+        /// Model used for the analysis.
+        model: String,
+        /// Ollama API base URL.
+        baseurl: String,
+        /// Options passed through to the model.
+        options: ModelOptions,
+        /// Number of input files.
+        files: usize,
+        /// Whether output files are not written.
+        dry_run: bool,
+    },
+    /// Completion of the analysis of a file.
+    FileCompleted {
+        /// Path of the input file (`-` for stdin).
+        input: PathBuf,
+        /// Path of the output file, if improved pseudocode was saved to a file.
+        output: Option<PathBuf>,
+        /// Outcome of the analysis.
+        status: Status,
+        /// Duration of the analysis, in milliseconds.
+        duration_ms: u64,
+        /// Variable renaming decisions.
+        #[serde(default)]
+        renames: Vec<RenameDecision>,
+        /// Warnings raised during the analysis.
+        #[serde(default)]
+        warnings: Vec<String>,
+        /// Error that caused the analysis to fail, if any.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    /// End of a run.
+    RunFinished {
+        /// Number of files analyzed successfully.
+        succeeded: usize,
+        /// Number of files whose analysis failed.
+        failed: usize,
+        /// Number of files skipped because they were up to date.
+        skipped: usize,
+        /// Duration of the run, in milliseconds.
+        duration_ms: u64,
+    },
+}
+
+/// Decision on a variable renaming suggestion.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RenameDecision {
+    /// Function the variable belongs to, if analyzed function by function.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    function: Option<String>,
+    /// Original variable name.
+    original: String,
+    /// Suggested variable name.
+    new: String,
+    /// Whether the rename was applied.
+    applied: bool,
+    /// Reason why the rename was skipped, if it was.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reason: Option<RenameSkip>,
+}
+
+impl RenameDecision {
+    /// Creates a new [`RenameDecision`] to apply the renaming suggestion of `variable`.
+    pub fn applied(variable: &Variable) -> Self {
+        Self {
+            function: None,
+            original: variable.original_name().to_owned(),
+            new: variable.new_name().to_owned(),
+            applied: true,
+            reason: None,
+        }
+    }
+
+    /// Creates a new [`RenameDecision`] to skip the renaming suggestion of `variable` because of
+    /// `reason`.
+    pub fn skipped(variable: &Variable, reason: RenameSkip) -> Self {
+        Self {
+            applied: false,
+            reason: Some(reason),
+            ..Self::applied(variable)
+        }
+    }
+
+    /// Sets the function the variable belongs to.
+    #[must_use]
+    pub fn function(mut self, name: &str) -> Self {
+        self.function = Some(name.to_owned());
+        self
+    }
+
+    /// Returns the function the variable belongs to, if analyzed function by function.
+    pub fn function_name(&self) -> Option<&str> {
+        self.function.as_deref()
+    }
+
+    /// Returns the original variable name.
+    pub fn original_name(&self) -> &str {
+        &self.original
+    }
+
+    /// Returns the suggested variable name.
+    pub fn new_name(&self) -> &str {
+        &self.new
+    }
+
+    /// Returns whether the rename was applied.
+    pub const fn is_applied(&self) -> bool {
+        self.applied
+    }
+
+    /// Returns the reason why the rename was skipped, if it was.
+    pub const fn reason(&self) -> Option<RenameSkip> {
+        self.reason
+    }
+}
+
+/// Reason why a variable renaming suggestion was skipped.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RenameSkip {
+    /// The suggested name is the same as the original name.
+    SameName,
+    /// The original name is not found in the pseudocode.
+    NotFound,
+    /// Renames are not applied (e.g., with `--no-rename`).
+    Disabled,
+    /// The original name was assigned by the user rather than generated by the decompiler, and
+    /// is protected (e.g., without `--rename-user-names`).
+    Protected,
+    /// The suggested name already exists in the pseudocode or was suggested for another variable
+    /// (e.g., with `--on-collision skip`, the default).
+    Collision,
+}
+
+impl fmt::Display for RenameSkip {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match *self {
+            Self::SameName => "same name",
+            Self::NotFound => "not found",
+            Self::Disabled => "renames disabled",
+            Self::Protected => "not generated by the decompiler",
+            Self::Collision => "name already exists",
+        })
+    }
+}
+
+/// Returns a new random-looking run identifier, based on the current time and process ID.
+fn new_run_id() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let mut id = content_hash(format!("{}-{}", now.as_nanos(), process::id()).as_bytes());
+    id.truncate(RUN_ID_LEN);
+    id
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn audit_records_round_trip_with_run_id() -> anyhow::Result<()> {
+        let tmpdir = tempfile::tempdir()?;
+        let filepath = tmpdir.path().join("runs.jsonl");
+        let variable: Variable = serde_json::from_str(r#"{"original_name":"v1","new_name":"i"}"#)?;
+        let renames = [
+            RenameDecision::applied(&variable).function("main"),
+            RenameDecision::skipped(&variable, RenameSkip::NotFound),
+        ];
+        let warnings = ["No function signature found".to_owned()];
+
+        let first = AuditLog::open(&filepath)?;
+        first.run_started(&Oneiromancer::new().model("test"), 1, false);
+        first.file_completed(
+            &AnalysisReport::failed("a.c", "test", Duration::ZERO, &anyhow::anyhow!("boom")),
+            &renames,
+            &warnings,
+        );
+        first.run_finished(0, 1, 0, Duration::ZERO);
+        let second = AuditLog::open(&filepath)?;
+        second.run_finished(0, 0, 0, Duration::ZERO);
+
+        let records = fs::read_to_string(&filepath)?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<AuditRecord>, _>>()?;
+
+        assert_eq!(records.len(), 4);
+        assert!(
+            records
+                .iter()
+                .take(3)
+                .all(|record| record.run_id == first.run_id()),
+            "run id not shared"
+        );
+        assert_ne!(first.run_id(), second.run_id(), "run id reused");
+        assert!(
+            matches!(
+                records.first().map(|record| &record.event),
+                Some(AuditEvent::RunStarted { model, files: 1, .. }) if model == "test"
+            ),
+            "unexpected first record"
+        );
+        assert_eq!(
+            records.get(1).map(|record| &record.event),
+            Some(&AuditEvent::FileCompleted {
+                input: PathBuf::from("a.c"),
+                output: None,
+                status: Status::Error,
+                duration_ms: 0,
+                renames: renames.to_vec(),
+                warnings: warnings.to_vec(),
+                error: Some("boom".to_owned()),
+            })
+        );
+        assert!(
+            fs::read_to_string(&filepath)?.contains(r#""reason":"not_found""#),
+            "unexpected reason"
+        );
+
+        Ok(())
+    }
+}
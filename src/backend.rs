@@ -0,0 +1,187 @@
+//! Plug in other LLM backends in place of the Ollama API.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde_json::Value;
+use ureq::Agent;
+
+use crate::ollama::OllamaRequest;
+use crate::options::ModelOptions;
+use crate::{OneiromancerError, Timeouts};
+
+/// Inference API that prompts are submitted to.
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Backend {
+    /// Ollama API (`/api/generate`).
+    #[default]
+    Ollama,
+    /// OpenAI-compatible chat completions API (see [`OpenAiBackend`](crate::OpenAiBackend)).
+    #[cfg_attr(feature = "cli", value(name = "openai"))]
+    OpenAi,
+    /// llama.cpp server native completion API (see [`LlamaCppBackend`](crate::LlamaCppBackend)).
+    #[cfg_attr(feature = "cli", value(name = "llama-cpp"))]
+    LlamaCpp,
+}
+
+/// Backend that submits prompts to an LLM, such as a local inference server other than Ollama.
+///
+/// Set a backend with [`Oneiromancer::backend`](crate::Oneiromancer::backend): prompts are then
+/// sent to it instead of the Ollama API, while the prompt preparation, the samples, the cache, and
+/// the parsing of the results stay the same. Backends are called from the threads that analyze
+/// files in parallel, hence the `Send + Sync` bound.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use oneiromancer::{BackendRequest, LlmBackend, Oneiromancer, OneiromancerError};
+///
+/// /// Backend that always suggests the same name, e.g., for tests.
+/// #[derive(Debug)]
+/// struct Canned;
+///
+/// impl LlmBackend for Canned {
+///     fn send(&self, request: &BackendRequest<'_>) -> Result<String, OneiromancerError> {
+///         assert_eq!(request.model(), "canned");
+///         Ok(r#"{"function_name":"main","comment":"Entry point.","variables":[]}"#.to_owned())
+///     }
+/// }
+///
+/// let results = Oneiromancer::new()
+///     .model("canned")
+///     .backend(Canned)
+///     .analyze_code("int main() { return 0; }")?;
+///
+/// assert_eq!(results.function_name(), "main");
+/// # Ok(())
+/// # }
+/// ```
+pub trait LlmBackend: fmt::Debug + Send + Sync {
+    /// Submits the prompt in `request` to the model, returning the raw text of the response,
+    /// which should be a JSON object with the `function_name`, `comment`, and `variables` fields.
+    ///
+    /// # Errors
+    ///
+    /// Returns the appropriate [`OneiromancerError`] in case the prompt cannot be submitted (e.g.,
+    /// [`OllamaQueryFailed`](OneiromancerError::OllamaQueryFailed) for HTTP failures).
+    fn send(&self, request: &BackendRequest<'_>) -> Result<String, OneiromancerError>;
+}
+
+/// Prompt submitted to an [`LlmBackend`], with the settings of the model.
+#[derive(Debug, Clone, Copy)]
+pub struct BackendRequest<'a> {
+    /// Name of the model to use.
+    model: &'a str,
+    /// Input prompt to send to the model.
+    prompt: &'a str,
+    /// Options passed through to the model.
+    options: &'a ModelOptions,
+    /// How long the model stays loaded after the request, if not the server default.
+    keep_alive: Option<&'a str>,
+}
+
+impl<'a> BackendRequest<'a> {
+    /// Creates a new [`BackendRequest`].
+    pub(crate) const fn new(
+        model: &'a str,
+        prompt: &'a str,
+        options: &'a ModelOptions,
+        keep_alive: Option<&'a str>,
+    ) -> Self {
+        Self {
+            model,
+            prompt,
+            options,
+            keep_alive,
+        }
+    }
+
+    /// Gets the name of the model to use.
+    #[must_use]
+    pub const fn model(&self) -> &'a str {
+        self.model
+    }
+
+    /// Gets the prompt to send to the model.
+    #[must_use]
+    pub const fn prompt(&self) -> &'a str {
+        self.prompt
+    }
+
+    /// Gets the options passed through to the model (e.g., `temperature` or `seed`), sorted by key.
+    #[must_use]
+    pub const fn options(&self) -> &'a BTreeMap<String, Value> {
+        self.options.as_map()
+    }
+
+    /// Gets how long the model stays loaded after the request (e.g., `10m`), or `None` for the
+    /// server default.
+    #[must_use]
+    pub const fn keep_alive(&self) -> Option<&'a str> {
+        self.keep_alive
+    }
+}
+
+/// [`LlmBackend`] that submits prompts to the `/api/generate` endpoint of an Ollama server, as
+/// [`Oneiromancer`](crate::Oneiromancer) does without a backend.
+///
+/// It is a building block for backends that wrap Ollama (e.g., to log or rewrite requests). The
+/// built-in Ollama client also reports the token and timing statistics of each response, which
+/// backends do not.
+#[derive(Debug, Clone)]
+pub struct OllamaBackend {
+    /// Ollama API base URL.
+    baseurl: String,
+    /// HTTP agent, shared by all clones.
+    agent: Agent,
+}
+
+impl OllamaBackend {
+    /// Creates a new [`OllamaBackend`] for the Ollama server at `baseurl` (e.g.,
+    /// [`OLLAMA_BASEURL`](crate::OLLAMA_BASEURL)).
+    #[must_use]
+    pub fn new(baseurl: impl Into<String>) -> Self {
+        Self {
+            baseurl: baseurl.into(),
+            agent: Agent::new_with_defaults(),
+        }
+    }
+
+    /// Sets the [`Timeouts`] of the requests to the server (none by default).
+    #[must_use]
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.agent = timeouts.agent();
+        self
+    }
+}
+
+impl LlmBackend for OllamaBackend {
+    fn send(&self, request: &BackendRequest<'_>) -> Result<String, OneiromancerError> {
+        let response = OllamaRequest::new(request.model, request.prompt)
+            .options(request.options)
+            .keep_alive(request.keep_alive)
+            .send(&self.agent, &self.baseurl)?;
+        Ok(response.response().to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ollama_backend_with_wrong_url_fails() {
+        let options = ModelOptions::default();
+        let request = BackendRequest::new("m", "int main();", &options, None);
+
+        let result = OllamaBackend::new("http://127.0.0.1:6666").send(&request);
+
+        assert!(
+            matches!(result, Err(OneiromancerError::OllamaQueryFailed(_))),
+            "wrong error type returned: {result:?}"
+        );
+    }
+}
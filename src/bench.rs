@@ -0,0 +1,436 @@
+//! Compare models on a corpus of pseudocode files.
+
+use std::fmt::Write as _;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::stats::{ResponseMetrics, millis};
+use crate::{Oneiromancer, OneiromancerError, OneiromancerResults};
+
+/// Seed passed to the model in benches, so that repeated runs are comparable.
+pub const BENCH_SEED: u64 = 42;
+/// Temperature passed to the model in benches, so that responses are as deterministic as possible.
+const BENCH_TEMPERATURE: u64 = 0;
+
+/// Outcome of a single analysis of a bench.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchSample {
+    /// Model used for the analysis.
+    model: String,
+    /// Path of the analyzed file.
+    input: PathBuf,
+    /// Wall-clock duration of the analysis.
+    duration: Duration,
+    /// Outcome of the analysis.
+    outcome: SampleOutcome,
+}
+
+/// Outcome of a single analysis of a bench.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SampleOutcome {
+    /// Successful analysis.
+    Succeeded {
+        /// Statistics reported by the Ollama API.
+        metrics: ResponseMetrics,
+        /// Number of variable renaming suggestions that change a name.
+        renames: usize,
+        /// Length of the function description, in characters.
+        comment_chars: usize,
+    },
+    /// Malformed response from the model.
+    ParseFailed,
+    /// Any other failure (e.g., server error).
+    Failed,
+}
+
+impl BenchSample {
+    /// Creates a new [`BenchSample`] of the successful analysis of `input` with `model`, which
+    /// took `duration` and returned `results`.
+    #[must_use]
+    pub fn succeeded(
+        model: &str,
+        input: &Path,
+        duration: Duration,
+        results: &OneiromancerResults,
+    ) -> Self {
+        Self {
+            model: model.to_owned(),
+            input: input.to_path_buf(),
+            duration,
+            outcome: SampleOutcome::Succeeded {
+                metrics: *results.metrics(),
+                renames: results
+                    .variables()
+                    .iter()
+                    .filter(|variable| variable.original_name() != variable.new_name())
+                    .count(),
+                comment_chars: results.comment().chars().count(),
+            },
+        }
+    }
+
+    /// Creates a new [`BenchSample`] of the failed analysis of `input` with `model`, which took
+    /// `duration` and returned `err`.
+    #[must_use]
+    pub fn failed(model: &str, input: &Path, duration: Duration, err: &OneiromancerError) -> Self {
+        let outcome = match *err {
+            OneiromancerError::ResponseParseFailed(_)
+            | OneiromancerError::OllamaQueryFailed(ureq::Error::Json(_)) => {
+                SampleOutcome::ParseFailed
+            }
+            OneiromancerError::InvalidInput(_)
+            | OneiromancerError::FileReadFailed(_)
+            | OneiromancerError::OllamaQueryFailed(_)
+            | OneiromancerError::LowQualityResult(_)
+            | OneiromancerError::Timeout(_)
+            | OneiromancerError::ServerUnreachable { .. }
+            | OneiromancerError::ModelNotFound { .. } => SampleOutcome::Failed,
+        };
+        Self {
+            model: model.to_owned(),
+            input: input.to_path_buf(),
+            duration,
+            outcome,
+        }
+    }
+
+    /// Gets the model used for the analysis.
+    #[must_use]
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Gets the path of the analyzed file.
+    #[must_use]
+    pub fn input(&self) -> &Path {
+        &self.input
+    }
+
+    /// Gets the wall-clock duration of the analysis.
+    #[must_use]
+    pub const fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Returns `true` if the analysis succeeded.
+    #[must_use]
+    pub const fn is_success(&self) -> bool {
+        matches!(self.outcome, SampleOutcome::Succeeded { .. })
+    }
+}
+
+/// Results of a model in a bench, aggregated by [`aggregate_bench`].
+///
+/// Token counts, renames, and comment lengths are totals over the successful runs, and latencies
+/// cover all runs, so that failures count against the model.
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModelBench {
+    /// Name of the model.
+    model: String,
+    /// Number of analyses.
+    runs: usize,
+    /// Number of successful analyses.
+    succeeded: usize,
+    /// Number of analyses that failed because of a malformed response.
+    parse_failures: usize,
+    /// Number of analyses that failed for any other reason.
+    failures: usize,
+    /// Average wall-clock duration of an analysis, in milliseconds.
+    average_latency_ms: u64,
+    /// Maximum wall-clock duration of an analysis, in milliseconds.
+    max_latency_ms: u64,
+    /// Total number of tokens in the prompts, if reported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prompt_tokens: Option<u64>,
+    /// Total number of tokens in the responses, if reported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    completion_tokens: Option<u64>,
+    /// Total number of variable renaming suggestions that change a name.
+    renames: usize,
+    /// Total length of the function descriptions, in characters.
+    comment_chars: usize,
+}
+
+/// Aggregates `samples` by model, in the order of `models`.
+#[must_use]
+pub fn aggregate_bench(samples: &[BenchSample], models: &[String]) -> Vec<ModelBench> {
+    models
+        .iter()
+        .map(|model| {
+            let mut bench = ModelBench {
+                model: model.clone(),
+                ..ModelBench::default()
+            };
+            let mut metrics = ResponseMetrics::default();
+            let mut total = Duration::ZERO;
+            for sample in samples.iter().filter(|sample| &sample.model == model) {
+                bench.runs = bench.runs.saturating_add(1);
+                total = total.saturating_add(sample.duration);
+                bench.max_latency_ms = bench.max_latency_ms.max(millis(sample.duration));
+                match sample.outcome {
+                    SampleOutcome::Succeeded {
+                        metrics: sample_metrics,
+                        renames,
+                        comment_chars,
+                    } => {
+                        bench.succeeded = bench.succeeded.saturating_add(1);
+                        metrics = metrics.merge(sample_metrics);
+                        bench.renames = bench.renames.saturating_add(renames);
+                        bench.comment_chars = bench.comment_chars.saturating_add(comment_chars);
+                    }
+                    SampleOutcome::ParseFailed => {
+                        bench.parse_failures = bench.parse_failures.saturating_add(1);
+                    }
+                    SampleOutcome::Failed => bench.failures = bench.failures.saturating_add(1),
+                }
+            }
+            bench.average_latency_ms = u32::try_from(bench.runs)
+                .ok()
+                .and_then(|runs| total.checked_div(runs))
+                .map_or(0, millis);
+            bench.prompt_tokens = metrics.prompt_tokens();
+            bench.completion_tokens = metrics.completion_tokens();
+            bench
+        })
+        .collect()
+}
+
+/// Formats `benches` as a comparison table, with one row per model. Token counts, renames, and
+/// comment lengths are averaged over the successful runs, and the parse failure rate over all runs.
+#[must_use]
+pub fn format_bench_table(benches: &[ModelBench]) -> String {
+    let header = [
+        "Model",
+        "Runs",
+        "OK",
+        "Parse failures",
+        "Other failures",
+        "Avg latency",
+        "Max latency",
+        "Prompt tokens",
+        "Completion tokens",
+        "Renames",
+        "Comment chars",
+    ];
+    let rows: Vec<[String; 11]> = benches
+        .iter()
+        .map(|bench| {
+            let per_success = |total: Option<u64>| {
+                total
+                    .and_then(|value| average(value, bench.succeeded))
+                    .unwrap_or_else(|| "-".to_owned())
+            };
+            [
+                bench.model.clone(),
+                bench.runs.to_string(),
+                bench.succeeded.to_string(),
+                format!(
+                    "{} ({}%)",
+                    bench.parse_failures,
+                    average(to_u64(bench.parse_failures).saturating_mul(100), bench.runs)
+                        .unwrap_or_else(|| "0.0".to_owned())
+                ),
+                bench.failures.to_string(),
+                format!("{:.2?}", Duration::from_millis(bench.average_latency_ms)),
+                format!("{:.2?}", Duration::from_millis(bench.max_latency_ms)),
+                per_success(bench.prompt_tokens),
+                per_success(bench.completion_tokens),
+                per_success(Some(to_u64(bench.renames))),
+                per_success(Some(to_u64(bench.comment_chars))),
+            ]
+        })
+        .collect();
+
+    let widths: Vec<usize> = header
+        .iter()
+        .enumerate()
+        .map(|(column, title)| {
+            rows.iter()
+                .filter_map(|row| row.get(column))
+                .map(|cell| cell.chars().count())
+                .fold(title.len(), usize::max)
+        })
+        .collect();
+    let mut table = String::new();
+    let mut push_row = |cells: &mut dyn Iterator<Item = &str>| {
+        let line = cells
+            .zip(&widths)
+            .enumerate()
+            .map(|(column, (cell, &width))| {
+                // Left-align model names, right-align numbers.
+                if column == 0 {
+                    format!("{cell:<width$}")
+                } else {
+                    format!("{cell:>width$}")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("  ");
+        _ = writeln!(table, "{}", line.trim_end());
+    };
+    push_row(&mut header.iter().copied());
+    for row in &rows {
+        push_row(&mut row.iter().map(String::as_str));
+    }
+    table
+}
+
+/// Analyzes each file in `files` with each model in `models`, `runs` times, calling `on_sample`
+/// as each analysis completes, and returns the outcome of each analysis.
+///
+/// Each model is queried through a copy of `client` with the [`BENCH_SEED`] seed and zero
+/// temperature, and without the response cache, so that the comparison is fair and repeatable.
+/// Failures are recorded and count against the model, instead of stopping the bench.
+pub fn run_bench(
+    client: &Oneiromancer,
+    files: &[PathBuf],
+    models: &[String],
+    runs: NonZeroUsize,
+    mut on_sample: impl FnMut(&BenchSample),
+) -> Vec<BenchSample> {
+    let mut samples = Vec::with_capacity(
+        files
+            .len()
+            .saturating_mul(models.len())
+            .saturating_mul(runs.get()),
+    );
+    for model in models {
+        let model_client = client
+            .clone()
+            .model(model)
+            .cache(None)
+            .option("seed", BENCH_SEED)
+            .option("temperature", BENCH_TEMPERATURE);
+        for filepath in files {
+            for _ in 0..runs.get() {
+                let started = Instant::now();
+                let sample = match model_client.analyze_file(filepath) {
+                    Ok(results) => {
+                        BenchSample::succeeded(model, filepath, started.elapsed(), &results)
+                    }
+                    Err(err) => BenchSample::failed(model, filepath, started.elapsed(), &err),
+                };
+                on_sample(&sample);
+                samples.push(sample);
+            }
+        }
+    }
+    samples
+}
+
+/// Returns `total` divided by `count` with one decimal digit, or `None` if `count` is zero.
+fn average(total: u64, count: usize) -> Option<String> {
+    let tenths = total.saturating_mul(10).checked_div(to_u64(count))?;
+    Some(format!(
+        "{}.{}",
+        tenths.checked_div(10).unwrap_or_default(),
+        tenths.checked_rem(10).unwrap_or_default()
+    ))
+}
+
+/// Returns `count` as [`u64`], saturating at [`u64::MAX`].
+fn to_u64(count: usize) -> u64 {
+    u64::try_from(count).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use std::io;
+
+    use super::*;
+
+    /// Returns a successful sample of `model` that took `ms` milliseconds.
+    fn success(model: &str, ms: u64, response: &str) -> serde_json::Result<BenchSample> {
+        let results: OneiromancerResults = serde_json::from_str(response)?;
+        Ok(BenchSample::succeeded(
+            model,
+            Path::new("a.c"),
+            Duration::from_millis(ms),
+            &results.attach_metrics(ResponseMetrics::new(Some(100), Some(20), None, None)),
+        ))
+    }
+
+    #[test]
+    fn aggregate_bench_counts_failures_against_each_model() -> anyhow::Result<()> {
+        let response = r#"{"function_name":"f","comment":"Adds.","variables":[{"original_name":"a1","new_name":"x"},{"original_name":"v1","new_name":"v1"}]}"#;
+        let parse_error = serde_json::from_str::<OneiromancerResults>("{").err();
+        let samples = [
+            success("good", 100, response)?,
+            success("good", 300, response)?,
+            success("bad", 50, response)?,
+            BenchSample::failed(
+                "bad",
+                Path::new("a.c"),
+                Duration::from_millis(250),
+                &OneiromancerError::ResponseParseFailed(
+                    parse_error.ok_or_else(|| anyhow::anyhow!("no parse error"))?,
+                ),
+            ),
+            BenchSample::failed(
+                "bad",
+                Path::new("b.c"),
+                Duration::ZERO,
+                &OneiromancerError::FileReadFailed(io::ErrorKind::NotFound.into()),
+            ),
+        ];
+        let models = ["good".to_owned(), "bad".to_owned(), "missing".to_owned()];
+
+        let benches = aggregate_bench(&samples, &models);
+
+        let good = benches.first().cloned().unwrap_or_default();
+        assert_eq!((good.runs, good.succeeded), (2, 2));
+        assert_eq!((good.average_latency_ms, good.max_latency_ms), (200, 300));
+        assert_eq!(
+            (good.prompt_tokens, good.completion_tokens),
+            (Some(200), Some(40))
+        );
+        assert_eq!((good.renames, good.comment_chars), (2, 10));
+        let bad = benches.get(1).cloned().unwrap_or_default();
+        assert_eq!(
+            (bad.runs, bad.succeeded, bad.parse_failures, bad.failures),
+            (3, 1, 1, 1)
+        );
+        assert_eq!(bad.average_latency_ms, 100);
+        assert_eq!(benches.get(2).map(|bench| bench.runs), Some(0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn format_bench_table_aligns_columns_and_averages_successes() -> anyhow::Result<()> {
+        let response = r#"{"function_name":"f","comment":"Adds.","variables":[{"original_name":"a1","new_name":"x"}]}"#;
+        let samples = [
+            success("aidapal", 1_500, response)?,
+            BenchSample::failed(
+                "aidapal",
+                Path::new("a.c"),
+                Duration::from_millis(500),
+                &OneiromancerError::ResponseParseFailed(serde_json::Error::io(io::Error::other(
+                    "boom",
+                ))),
+            ),
+            BenchSample::failed(
+                "aidapal-v2",
+                Path::new("a.c"),
+                Duration::from_millis(20),
+                &OneiromancerError::FileReadFailed(io::Error::other("boom")),
+            ),
+        ];
+        let models = ["aidapal".to_owned(), "aidapal-v2".to_owned()];
+
+        let table = format_bench_table(&aggregate_bench(&samples, &models));
+
+        assert_eq!(
+            table,
+            "Model       Runs  OK  Parse failures  Other failures  Avg latency  Max latency  Prompt tokens  Completion tokens  Renames  Comment chars\n\
+             aidapal        2   1       1 (50.0%)               0        1.00s        1.50s          100.0               20.0      1.0            5.0\n\
+             aidapal-v2     1   0        0 (0.0%)               1      20.00ms      20.00ms              -                  -        -              -\n"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,280 @@
+//! Decompile the functions of a binary or IDA database with idalib (behind the `idalib` feature),
+//! to analyze them without exporting their pseudocode first.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::split::SelectError;
+
+/// Functions of a binary to decompile with [`decompile_binary`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FunctionSelector {
+    /// All functions, except thunks and library functions.
+    #[default]
+    All,
+    /// The function with this name.
+    Name(String),
+    /// The function that starts at this address.
+    Address(u64),
+}
+
+impl FunctionSelector {
+    /// Parses a function selector in the syntax of `--function`: a function name (e.g.,
+    /// `parse_packet`), or an address with an `@0x` prefix (e.g., `@0x401A2F`).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SelectError::InvalidAddress`] in case the address is malformed.
+    pub fn parse(query: &str) -> Result<Self, SelectError> {
+        let Some(suffix) = query.strip_prefix('@') else {
+            return Ok(Self::Name(query.to_owned()));
+        };
+        suffix
+            .strip_prefix("0x")
+            .or_else(|| suffix.strip_prefix("0X"))
+            .and_then(|digits| u64::from_str_radix(digits, 16).ok())
+            .map(Self::Address)
+            .ok_or_else(|| SelectError::InvalidAddress(query.to_owned()))
+    }
+
+    /// Returns `true` if the function named `name` at `address` is selected.
+    #[must_use]
+    #[expect(
+        clippy::pattern_type_mismatch,
+        reason = "the wanted name is borrowed from the selector"
+    )]
+    pub fn matches(&self, name: &str, address: u64) -> bool {
+        match self {
+            Self::All => true,
+            Self::Name(wanted) => name == wanted,
+            Self::Address(wanted) => address == *wanted,
+        }
+    }
+}
+
+impl fmt::Display for FunctionSelector {
+    #[expect(
+        clippy::pattern_type_mismatch,
+        reason = "the name is borrowed from the selector"
+    )]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::All => write!(f, "all functions"),
+            Self::Name(name) => write!(f, "{name}"),
+            Self::Address(address) => write!(f, "@{address:#x}"),
+        }
+    }
+}
+
+/// Function decompiled from a binary by [`decompile_binary`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecompiledFunction {
+    /// Start address of the function.
+    address: u64,
+    /// Name of the function in the binary (e.g., `sub_401000` if it has none).
+    name: String,
+    /// Pseudocode of the function.
+    pseudocode: String,
+}
+
+impl DecompiledFunction {
+    /// Creates a new [`DecompiledFunction`] named `name` at `address`, with `pseudocode`.
+    #[must_use]
+    pub fn new(address: u64, name: impl Into<String>, pseudocode: impl Into<String>) -> Self {
+        Self {
+            address,
+            name: name.into(),
+            pseudocode: pseudocode.into(),
+        }
+    }
+
+    /// Gets the start address of the function.
+    #[must_use]
+    pub const fn address(&self) -> u64 {
+        self.address
+    }
+
+    /// Gets the name of the function in the binary.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the pseudocode of the function.
+    #[must_use]
+    pub fn pseudocode(&self) -> &str {
+        &self.pseudocode
+    }
+
+    /// Returns the filename the pseudocode is saved with in batches, i.e., the function name
+    /// (with characters that are not valid in C identifiers replaced by `_`) followed by the
+    /// address (e.g., `sub_401000@0x401000.c`).
+    #[must_use]
+    pub fn filename(&self) -> String {
+        let name: String = self
+            .name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        format!("{name}@{:#x}.c", self.address)
+    }
+}
+
+/// Failure in decompiling a binary with [`decompile_binary`].
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum BinaryError {
+    /// oneiromancer was built without the `idalib` feature.
+    #[error(
+        "Analyzing binaries requires oneiromancer built with the `idalib` feature (and IDA Pro 9.x)"
+    )]
+    Unsupported,
+    /// The binary or IDA database cannot be opened by IDA.
+    #[error("Failed to open `{0}` with idalib: {1}")]
+    OpenFailed(PathBuf, String),
+    /// The Hex-Rays decompiler is not available for the binary (e.g., for its architecture).
+    #[error("The Hex-Rays decompiler is not available for `{0}`")]
+    DecompilerUnavailable(PathBuf),
+    /// No function in the binary matches the selector.
+    #[error("Function `{0}` not found in `{1}`")]
+    NotFound(FunctionSelector, PathBuf),
+}
+
+/// Opens the `binarypath` binary or IDA database with idalib, and decompiles the functions
+/// selected by `selector`, in order of address. Functions that fail to decompile are skipped with
+/// a warning.
+///
+/// The database is closed before returning, without saving it.
+///
+/// # Errors
+///
+/// Returns [`BinaryError::Unsupported`] if oneiromancer was built without the `idalib` feature, or
+/// the appropriate [`BinaryError`] in case the binary cannot be opened or decompiled, or no
+/// function matches a name or address `selector`.
+pub fn decompile_binary(
+    binarypath: impl AsRef<Path>,
+    selector: &FunctionSelector,
+) -> Result<Vec<DecompiledFunction>, BinaryError> {
+    decompile(binarypath.as_ref(), selector)
+}
+
+/// Decompiles the functions of `path` selected by `selector` with idalib.
+#[cfg(feature = "idalib")]
+fn decompile(
+    path: &Path,
+    selector: &FunctionSelector,
+) -> Result<Vec<DecompiledFunction>, BinaryError> {
+    use idalib::func::FunctionFlags;
+    use idalib::idb::IDB;
+
+    let idb = IDB::open(path)
+        .map_err(|err| BinaryError::OpenFailed(path.to_path_buf(), err.to_string()))?;
+    if !idb.decompiler_available() {
+        return Err(BinaryError::DecompilerUnavailable(path.to_path_buf()));
+    }
+
+    let mut functions = Vec::new();
+    for (_, function) in idb.functions() {
+        let address = function.start_address();
+        let name = function
+            .name()
+            .unwrap_or_else(|| format!("sub_{address:X}"));
+        let skipped = *selector == FunctionSelector::All
+            && function
+                .flags()
+                .intersects(FunctionFlags::THUNK | FunctionFlags::LIB);
+        if skipped || !selector.matches(&name, address) {
+            continue;
+        }
+        match idb.decompile(&function) {
+            Ok(decompiled) => {
+                functions.push(DecompiledFunction::new(
+                    address,
+                    name,
+                    decompiled.pseudocode(),
+                ));
+            }
+            Err(err) => log::warn!("Failed to decompile `{name}` at {address:#x}: {err}"),
+        }
+    }
+    functions.sort_by_key(DecompiledFunction::address);
+
+    if functions.is_empty() && *selector != FunctionSelector::All {
+        return Err(BinaryError::NotFound(selector.clone(), path.to_path_buf()));
+    }
+    Ok(functions)
+}
+
+/// Fails with [`BinaryError::Unsupported`], since oneiromancer was built without idalib.
+#[cfg(not(feature = "idalib"))]
+const fn decompile(
+    _path: &Path,
+    _selector: &FunctionSelector,
+) -> Result<Vec<DecompiledFunction>, BinaryError> {
+    Err(BinaryError::Unsupported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn function_selector_parses_names_and_addresses() {
+        assert_eq!(
+            FunctionSelector::parse("parse_packet").ok(),
+            Some(FunctionSelector::Name("parse_packet".into()))
+        );
+        assert_eq!(
+            FunctionSelector::parse("@0x401A2F").ok(),
+            Some(FunctionSelector::Address(0x0040_1a2f))
+        );
+        assert!(
+            matches!(
+                FunctionSelector::parse("@401A2F"),
+                Err(SelectError::InvalidAddress(_))
+            ),
+            "address without prefix accepted"
+        );
+    }
+
+    #[test]
+    fn function_selector_matches_name_or_address() {
+        let name = FunctionSelector::Name("main".into());
+        let address = FunctionSelector::Address(0x1000);
+
+        assert!(FunctionSelector::All.matches("sub_2000", 0x2000));
+        assert!(name.matches("main", 0x2000));
+        assert!(!name.matches("sub_1000", 0x1000));
+        assert!(address.matches("sub_1000", 0x1000));
+        assert!(!address.matches("main", 0x2000));
+        assert_eq!(
+            address.to_string(),
+            "@0x1000",
+            "address not in `--function` syntax"
+        );
+    }
+
+    #[test]
+    fn decompiled_function_filename_keeps_identifier_characters() {
+        let function = DecompiledFunction::new(0x0040_1000, "std::vector<int>::push_back", "");
+
+        assert_eq!(
+            function.filename(),
+            "std__vector_int___push_back@0x401000.c"
+        );
+    }
+
+    #[cfg(not(feature = "idalib"))]
+    #[test]
+    fn decompile_binary_requires_idalib_feature() {
+        let result = decompile_binary("a.out", &FunctionSelector::All);
+
+        assert!(
+            matches!(result, Err(BinaryError::Unsupported)),
+            "decompiled without idalib: {result:?}"
+        );
+    }
+}
@@ -0,0 +1,149 @@
+//! Generate Binary Ninja scripts that apply the suggestions of analyses to a binary view.
+
+use crate::emit::{FunctionSuggestions, ScriptEmitter, format_python_table};
+
+/// Header of Binary Ninja scripts, before the table of suggestions.
+const SCRIPT_HEADER: &str =
+    "# Binary Ninja script generated by oneiromancer: applies the suggested function names, comments,
+# and variable renames to the current binary view. Run it with File > Run Script... or paste it in
+# the Python console, where `bv` is the current binary view.
+#
+# Functions are looked up by start address, or by their original name if the address is unknown.
+# Binary Ninja names variables differently from other decompilers, so variables are only matched
+# by their current name. Suggestions that cannot be applied are reported at the end.
+
+";
+
+/// Code of Binary Ninja scripts, after the table of suggestions.
+const SCRIPT_BODY: &str = r#"
+
+def attempt(failures, message, action):
+    """Runs action, appending message to failures if it raises."""
+    try:
+        action()
+    except Exception as err:  # keep applying the other suggestions
+        failures.append("%s: %s" % (message, err))
+
+
+def find_function(view, function):
+    """Returns the function starting at the suggested address, or with the original name."""
+    if function["address"] is not None:
+        return view.get_function_at(function["address"])
+    if not function["name"]:
+        return None
+    matches = view.get_functions_by_name(function["name"])
+    if not matches:
+        return None
+    return matches[0]
+
+
+def find_variable(func, name):
+    """Returns the variable of func with the current name, looking at HLIL variables last."""
+    for var in func.vars:
+        if var.name == name:
+            return var
+    try:
+        hlil_vars = func.hlil.vars
+    except Exception:  # HLIL not available for this function
+        return None
+    for var in hlil_vars:
+        if var.name == name:
+            return var
+    return None
+
+
+def apply(view, function, failures):
+    """Applies the suggestions for a function, appending the ones that fail to failures."""
+    label = function["name"] or function["new_name"]
+    func = find_function(view, function)
+    if func is None:
+        failures.append("%s: function not found" % label)
+        return
+    attempt(
+        failures,
+        "%s: cannot rename function to %s" % (label, function["new_name"]),
+        lambda: setattr(func, "name", function["new_name"]),
+    )
+    attempt(
+        failures,
+        "%s: cannot set function comment" % label,
+        lambda: setattr(func, "comment", function["comment"]),
+    )
+    for old, new in function["renames"]:
+        var = find_variable(func, old)
+        if var is None:
+            failures.append("%s: variable %s not found" % (label, old))
+            continue
+        attempt(
+            failures,
+            "%s: cannot rename variable %s to %s" % (label, old, new),
+            lambda: func.create_user_var(var, var.type, new),
+        )
+
+
+def main(view):
+    failures = []
+    for function in FUNCTIONS:
+        attempt(
+            failures,
+            function["name"] or function["new_name"],
+            lambda: apply(view, function, failures),
+        )
+    view.update_analysis()
+    if failures:
+        print("[!] oneiromancer: %d suggestions not applied:" % len(failures))
+        for failure in failures:
+            print("    " + failure)
+    else:
+        print("[+] oneiromancer: all suggestions applied")
+
+
+if "bv" in globals():
+    main(bv)
+else:
+    print("[!] oneiromancer: no binary view, run this script from Binary Ninja")
+"#;
+
+/// Emitter of Binary Ninja Python scripts that apply suggestions to a binary view: they rename each
+/// function and set its comment through the `Function` properties, and rename its variables with
+/// `Function.create_user_var`.
+///
+/// Variables are matched by their current name among the function variables and then the HLIL
+/// ones; the ones not found are reported rather than guessed.
+#[derive(Debug, Clone, Copy)]
+pub struct BinjaScript;
+
+impl ScriptEmitter for BinjaScript {
+    fn description(&self) -> &'static str {
+        "Binary Ninja script"
+    }
+
+    fn format_script(&self, suggestions: &[FunctionSuggestions], _model: &str) -> String {
+        format!(
+            "{SCRIPT_HEADER}{}{SCRIPT_BODY}",
+            format_python_table(suggestions)
+        )
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use super::*;
+    use crate::emit::collect_suggestions;
+    use crate::report::FunctionReport;
+
+    #[test]
+    fn format_script_matches_golden_file() -> anyhow::Result<()> {
+        let functions: Vec<FunctionReport> =
+            serde_json::from_str(include_str!("../tests/data/emit/functions.json"))?;
+        let suggestions = collect_suggestions("", None, &functions, None, None);
+
+        assert_eq!(
+            BinjaScript.format_script(&suggestions, "test-model"),
+            include_str!("../tests/data/emit/binja.py")
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,289 @@
+//! Cache LLM responses on disk, to avoid querying the model again for the same prompt.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+
+use crate::options::ModelOptions;
+use crate::{OneiromancerResults, ResponseMetrics};
+
+/// Extension of cache entry files.
+const ENTRY_EXTENSION: &str = "json";
+
+/// Cache entry, stored as an individual JSON file named after its key.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct CacheEntry {
+    /// Model that generated the response.
+    model: String,
+    /// Raw response of the model.
+    response: String,
+}
+
+/// On-disk cache of LLM responses, keyed by a hash of the model, the model options, and the prompt.
+///
+/// The prompt includes everything else that affects the response (i.e., the prepared pseudocode and
+/// the extra context), so a cache hit returns the same results that a query would. Corrupt entries are
+/// ignored, and the model is queried again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseCache {
+    /// Directory where cache entries are stored.
+    dir: PathBuf,
+}
+
+impl ResponseCache {
+    /// Creates a new [`ResponseCache`] that stores its entries in `dir`.
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Returns the default cache directory (e.g., `~/.cache/oneiromancer`).
+    #[must_use]
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join(env!("CARGO_PKG_NAME")))
+    }
+
+    /// Gets the directory where cache entries are stored.
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Removes all cache entries, returning the number of removed entries.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`io::Error`] in case the cache directory cannot be read or an entry cannot be
+    /// removed.
+    pub fn clear(&self) -> io::Result<usize> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => return Err(err),
+        };
+        let mut removed: usize = 0;
+        for result in entries {
+            let path = result?.path();
+            if path.is_file() && path.extension().is_some_and(|ext| ext == ENTRY_EXTENSION) {
+                fs::remove_file(path)?;
+                removed = removed.saturating_add(1);
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Returns the cached results for `prompt` submitted to `model` with `options`, or `None` if
+    /// there is no valid entry.
+    pub(crate) fn get(
+        &self,
+        model: &str,
+        options: &ModelOptions,
+        prompt: &str,
+    ) -> Option<OneiromancerResults> {
+        serde_json::from_str(&self.get_raw(model, options, prompt)?).ok()
+    }
+
+    /// Returns the cached raw response for `prompt` submitted to `model` with `options`, or `None`
+    /// if there is no entry.
+    pub(crate) fn get_raw(
+        &self,
+        model: &str,
+        options: &ModelOptions,
+        prompt: &str,
+    ) -> Option<String> {
+        let contents = fs::read_to_string(self.entry_path(model, options, prompt)).ok()?;
+        let entry: CacheEntry = serde_json::from_str(&contents).ok()?;
+        (entry.model == model).then_some(entry.response)
+    }
+
+    /// Stores the raw `response` of `model` with `options` to `prompt`. Failures are ignored, since
+    /// the cache is only an optimization.
+    pub(crate) fn put(&self, model: &str, options: &ModelOptions, prompt: &str, response: &str) {
+        let entry = CacheEntry {
+            model: model.to_owned(),
+            response: response.to_owned(),
+        };
+        if let Ok(contents) = serde_json::to_string(&entry) {
+            _ = fs::create_dir_all(&self.dir)
+                .and_then(|()| fs::write(self.entry_path(model, options, prompt), contents));
+        }
+    }
+
+    /// Returns the path of the entry for `prompt` submitted to `model` with `options`.
+    fn entry_path(&self, model: &str, options: &ModelOptions, prompt: &str) -> PathBuf {
+        self.dir
+            .join(cache_key(model, options, prompt))
+            .with_extension(ENTRY_EXTENSION)
+    }
+}
+
+/// In-memory memo of the results of the prompts submitted during a batch, keyed like the
+/// [`ResponseCache`], so that identical inputs are submitted to the model only once.
+///
+/// Clones share the same memo. Concurrent submissions of the same prompt wait for the first one
+/// to complete; if it fails, each of them queries the model on its own.
+#[derive(Debug, Clone, Default)]
+pub struct ResponseMemo {
+    /// Results of each prompt, or `None` if the query failed.
+    results: Arc<Mutex<HashMap<String, Memoized>>>,
+}
+
+/// Results of a prompt shared by its submissions, set once the first one completes.
+type Memoized = Arc<OnceLock<Option<OneiromancerResults>>>;
+
+impl ResponseMemo {
+    /// Returns the memoized results for `prompt` submitted to `model` with `options`, or the
+    /// results of `query` (memoized for the next submissions) if the prompt was not submitted yet.
+    /// Memoized results have no response metrics, as cached ones.
+    pub(crate) fn get_or_query<E>(
+        &self,
+        model: &str,
+        options: &ModelOptions,
+        prompt: &str,
+        query: impl Fn() -> Result<OneiromancerResults, E>,
+    ) -> Result<OneiromancerResults, E> {
+        let cell = Arc::clone(
+            self.results
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .entry(cache_key(model, options, prompt))
+                .or_default(),
+        );
+        let mut outcome = None;
+        let memoized = cell.get_or_init(|| {
+            let result = query();
+            let results = result
+                .as_ref()
+                .ok()
+                .map(|found| found.clone().attach_metrics(ResponseMetrics::default()));
+            outcome = Some(result);
+            results
+        });
+        if let Some(result) = outcome {
+            return result;
+        }
+        memoized.clone().map_or_else(query, |results| {
+            log::debug!("Reusing the results of an identical prompt to model `{model}`");
+            Ok(results)
+        })
+    }
+}
+
+/// Returns the key of the results of `prompt` submitted to `model` with `options`. Without
+/// options, the key is the same as before model options were supported.
+fn cache_key(model: &str, options: &ModelOptions, prompt: &str) -> String {
+    if options.is_empty() {
+        content_hash(format!("{model}\0{prompt}").as_bytes())
+    } else {
+        let serialized = serde_json::to_string(options).unwrap_or_default();
+        content_hash(format!("{model}\0{serialized}\0{prompt}").as_bytes())
+    }
+}
+
+/// Returns the hex-encoded SHA-256 hash of `content`.
+pub fn content_hash(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use super::*;
+
+    const RESPONSE: &str = r#"{"function_name":"main","comment":"Entry point.","variables":[]}"#;
+
+    #[test]
+    fn content_hash_is_hex_sha256() {
+        assert_eq!(
+            content_hash(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn cache_returns_stored_results_for_same_model_and_prompt() -> anyhow::Result<()> {
+        let tmpdir = tempfile::tempdir()?;
+        let cache = ResponseCache::new(tmpdir.path().join("cache"));
+        let none = ModelOptions::default();
+
+        assert!(
+            cache.get("aidapal", &none, "int main() {}").is_none(),
+            "empty cache"
+        );
+        cache.put("aidapal", &none, "int main() {}", RESPONSE);
+
+        let results = cache.get("aidapal", &none, "int main() {}");
+        assert_eq!(
+            results.as_ref().map(OneiromancerResults::function_name),
+            Some("main")
+        );
+        assert!(
+            cache.get("other", &none, "int main() {}").is_none(),
+            "model ignored"
+        );
+        assert!(
+            cache.get("aidapal", &none, "int f() {}").is_none(),
+            "prompt ignored"
+        );
+        let mut options = ModelOptions::default();
+        options.insert("stop", serde_json::json!(["}"]));
+        assert!(
+            cache.get("aidapal", &options, "int main() {}").is_none(),
+            "options ignored"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn cache_ignores_corrupt_entries() -> anyhow::Result<()> {
+        let tmpdir = tempfile::tempdir()?;
+        let cache = ResponseCache::new(tmpdir.path());
+        let none = ModelOptions::default();
+        cache.put("aidapal", &none, "int main() {}", "not valid json");
+        fs::write(
+            cache.entry_path("aidapal", &none, "int f() {}"),
+            "{\"model\":",
+        )?;
+
+        assert!(
+            cache.get("aidapal", &none, "int main() {}").is_none(),
+            "bad response"
+        );
+        assert!(
+            cache.get("aidapal", &none, "int f() {}").is_none(),
+            "bad entry"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn clear_removes_all_entries() -> anyhow::Result<()> {
+        let tmpdir = tempfile::tempdir()?;
+        let cache = ResponseCache::new(tmpdir.path().join("cache"));
+        let none = ModelOptions::default();
+        assert_eq!(cache.clear()?, 0, "missing directory not handled");
+        cache.put("aidapal", &none, "int main() {}", RESPONSE);
+        cache.put("aidapal", &none, "int f() {}", RESPONSE);
+
+        assert_eq!(cache.clear()?, 2);
+        assert!(
+            cache.get("aidapal", &none, "int main() {}").is_none(),
+            "not cleared"
+        );
+
+        Ok(())
+    }
+}
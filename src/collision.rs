@@ -0,0 +1,228 @@
+//! Detect renaming suggestions whose new name collides with an existing identifier or with the new
+//! name of another suggestion, which would silently merge two distinct variables.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::Variable;
+use crate::lexer::{TokenKind, tokenize};
+
+/// What to do with a renaming suggestion whose new name collides with an existing identifier or
+/// with the new name of an earlier suggestion.
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CollisionPolicy {
+    /// Skip the suggestion, keeping the original name.
+    #[default]
+    Skip,
+    /// Append the first free numeric suffix to the new name (e.g., `len_2` for `len`).
+    Suffix,
+    /// Apply the suggestion anyway, with a warning.
+    Warn,
+}
+
+/// Resolves the collisions of the renaming suggestions in `variables` within `pseudocode`
+/// according to `policy`, returning the suggestions to apply and, for each one, whether it was
+/// skipped because of a collision.
+///
+/// A new name collides if it is already an identifier in `pseudocode` (outside comments and
+/// literals) that no applied suggestion renames, or if an earlier suggestion renames a different
+/// variable to it. Skipped suggestions keep their original name, which may in turn make other
+/// suggestions collide, so collisions are resolved again until no more suggestions are skipped.
+/// Each collision is then logged as a warning, with how it was resolved.
+pub fn resolve_collisions(
+    pseudocode: &str,
+    variables: &[Variable],
+    policy: CollisionPolicy,
+) -> (Vec<Variable>, Vec<bool>) {
+    let identifiers: HashSet<&str> = tokenize(pseudocode)
+        .iter()
+        .filter(|token| token.kind == TokenKind::Identifier)
+        .map(|token| token.text(pseudocode))
+        .collect();
+    let mut renamed: HashSet<&str> = variables
+        .iter()
+        .filter(|variable| variable.original_name() != variable.new_name())
+        .map(Variable::original_name)
+        .collect();
+    loop {
+        let (resolved, collided) = resolve_once(&identifiers, &renamed, variables, policy);
+        // Names are only ever taken out of the renamed ones, so that this terminates.
+        let still_renamed: HashSet<&str> = renamed
+            .iter()
+            .copied()
+            .filter(|&original| {
+                resolved.iter().any(|variable| {
+                    variable.original_name() == original && variable.new_name() != original
+                })
+            })
+            .collect();
+        if still_renamed.len() == renamed.len() {
+            warn_collisions(variables, &resolved, &collided, policy);
+            let skipped = collided
+                .iter()
+                .map(|&collides| collides && policy == CollisionPolicy::Skip)
+                .collect();
+            return (resolved, skipped);
+        }
+        renamed = still_renamed;
+    }
+}
+
+/// Logs a warning for each suggestion in `variables` that `collided`, with the `resolved`
+/// suggestion applied in its place according to `policy`.
+fn warn_collisions(
+    variables: &[Variable],
+    resolved: &[Variable],
+    collided: &[bool],
+    policy: CollisionPolicy,
+) {
+    let collisions = variables
+        .iter()
+        .zip(resolved)
+        .zip(collided)
+        .filter_map(|(pair, &collides)| collides.then_some(pair));
+    for (variable, applied) in collisions {
+        let (original, new) = (variable.original_name(), variable.new_name());
+        match policy {
+            CollisionPolicy::Skip => {
+                log::warn!("Skipping rename `{original}` -> `{new}`: `{new}` already exists");
+            }
+            CollisionPolicy::Suffix => log::warn!(
+                "Renaming `{original}` to `{}` instead: `{new}` already exists",
+                applied.new_name()
+            ),
+            CollisionPolicy::Warn => log::warn!(
+                "Renaming `{original}` to `{new}`, which already exists, merges two variables"
+            ),
+        }
+    }
+}
+
+/// Resolves the collisions of the renaming suggestions in `variables` once, assuming that the
+/// `identifiers` of the pseudocode in `renamed` are renamed and all the others are kept, returning
+/// the suggestions to apply and, for each one, whether it collided.
+fn resolve_once(
+    identifiers: &HashSet<&str>,
+    renamed: &HashSet<&str>,
+    variables: &[Variable],
+    policy: CollisionPolicy,
+) -> (Vec<Variable>, Vec<bool>) {
+    let kept: HashSet<&str> = identifiers.difference(renamed).copied().collect();
+    // New names taken so far, with the original name of the variable that took them.
+    let mut claimed: HashMap<String, &str> = HashMap::new();
+
+    let mut resolved = Vec::with_capacity(variables.len());
+    let mut colliding = Vec::with_capacity(variables.len());
+    for variable in variables {
+        let (original, new) = (variable.original_name(), variable.new_name());
+        let collides = original != new
+            && identifiers.contains(original)
+            && (kept.contains(new) || claimed.get(new).is_some_and(|&other| other != original));
+        if !collides {
+            claimed.entry(new.to_owned()).or_insert(original);
+            resolved.push(variable.clone());
+            colliding.push(false);
+            continue;
+        }
+        match policy {
+            CollisionPolicy::Skip => resolved.push(Variable::new(original, original)),
+            CollisionPolicy::Suffix => {
+                let free = (2..=usize::MAX)
+                    .map(|number| format!("{new}_{number}"))
+                    .find(|name| !kept.contains(name.as_str()) && !claimed.contains_key(name))
+                    .unwrap_or_else(|| new.to_owned());
+                claimed.insert(free.clone(), original);
+                resolved.push(Variable::new(original, free));
+            }
+            CollisionPolicy::Warn => resolved.push(variable.clone()),
+        }
+        colliding.push(true);
+    }
+    (resolved, colliding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PSEUDOCODE: &str =
+        "int f(int a1)\n{\n  int v1; // len\n  int len;\n  v1 = a1 + len;\n  return v1;\n}\n";
+
+    /// Resolves the collisions of the renaming suggestions from `original` to `new` names in
+    /// [`PSEUDOCODE`] with `policy`, returning the new names to apply and the skipped flags.
+    fn resolve(renames: &[(&str, &str)], policy: CollisionPolicy) -> (Vec<String>, Vec<bool>) {
+        let variables: Vec<Variable> = renames
+            .iter()
+            .map(|&(original, new)| Variable::new(original, new))
+            .collect();
+        let (resolved, skipped) = resolve_collisions(PSEUDOCODE, &variables, policy);
+        (
+            resolved
+                .iter()
+                .map(|variable| variable.new_name().to_owned())
+                .collect(),
+            skipped,
+        )
+    }
+
+    #[test]
+    fn resolve_collisions_skips_existing_and_repeated_names() {
+        // `v2` is not in the pseudocode, so its suggestion cannot merge anything.
+        assert_eq!(
+            resolve(
+                &[("v1", "len"), ("a1", "size"), ("v2", "size"), ("v1", "v1")],
+                CollisionPolicy::Skip
+            ),
+            (
+                vec!["v1".into(), "size".into(), "size".into(), "v1".into()],
+                vec![true, false, false, false]
+            )
+        );
+        assert_eq!(
+            resolve(&[("a1", "size"), ("v1", "size")], CollisionPolicy::Skip),
+            (vec!["size".into(), "v1".into()], vec![false, true])
+        );
+    }
+
+    #[test]
+    fn resolve_collisions_allows_names_freed_by_other_renames() {
+        assert_eq!(
+            resolve(&[("v1", "len"), ("len", "total")], CollisionPolicy::Skip),
+            (vec!["len".into(), "total".into()], vec![false, false])
+        );
+    }
+
+    #[test]
+    fn resolve_collisions_keeps_names_whose_rename_is_skipped() {
+        // Skipping `v2` to `a1` keeps `v2`, so `v1` to `v2` would merge `v1` and `v2`.
+        let variables = [Variable::new("v1", "v2"), Variable::new("v2", "a1")];
+
+        let (resolved, skipped) = resolve_collisions(
+            "int f(int a1)\n{\n  int v1;\n  int v2;\n  v2 = a1 + v1;\n}\n",
+            &variables,
+            CollisionPolicy::Skip,
+        );
+
+        let renames: Vec<(&str, &str)> = resolved
+            .iter()
+            .map(|variable| (variable.original_name(), variable.new_name()))
+            .collect();
+        assert_eq!(renames, [("v1", "v1"), ("v2", "v2")]);
+        assert_eq!(skipped, [true, true]);
+    }
+
+    #[test]
+    fn resolve_collisions_suffixes_or_keeps_colliding_names() {
+        let renames = [("v1", "len"), ("a1", "len")];
+
+        assert_eq!(
+            resolve(&renames, CollisionPolicy::Suffix),
+            (vec!["len_2".into(), "len_3".into()], vec![false, false])
+        );
+        assert_eq!(
+            resolve(&renames, CollisionPolicy::Warn),
+            (vec!["len".into(), "len".into()], vec![false, false])
+        );
+    }
+}
@@ -0,0 +1,393 @@
+//! Load configuration from a TOML file.
+
+use std::fs;
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use thiserror::Error;
+use toml::de;
+
+use crate::CommentWidth;
+
+/// Configuration file name.
+const CONFIG_FILENAME: &str = "config.toml";
+
+/// Commented default configuration file, written by [`Config::init`]. All keys are commented out,
+/// so that the defaults apply until they are uncommented.
+pub const CONFIG_TEMPLATE: &str = r#"# oneiromancer configuration file.
+# Command line options take precedence over values set here.
+
+# Wrap width for generated comments (a number of columns or "auto").
+# width = 76
+
+# Accepted extensions of target files.
+# extensions = ["c", "cpp", "cc", "h", "txt", "json"]
+
+# Number of generations merged by majority vote for each prompt (use with a temperature above
+# zero).
+# samples = 1
+
+# Maximum number of target files analyzed concurrently in batch mode (e.g., as many as the Ollama
+# server can generate in parallel).
+# jobs = 1
+
+# Split target files into functions and analyze each function separately, e.g., for decompiler
+# exports with many functions per file.
+# chunk = false
+
+# Cache LLM responses on disk.
+# cache = false
+
+# Directory where LLM responses are cached (default: ~/.cache/oneiromancer or the equivalent path on
+# your platform).
+# cache_dir = "/path/to/cache"
+
+# File where an audit log of each run is appended.
+# log_file = "/path/to/runs.jsonl"
+
+# Record each analysis in the history, to query it with `oneiromancer history`.
+# history = false
+
+# History file (default: ~/.local/share/oneiromancer/history.jsonl or the equivalent path on your
+# platform).
+# history_file = "/path/to/history.jsonl"
+"#;
+
+/// Configuration loaded from a TOML file.
+///
+/// All keys are optional; command line options take precedence over values set here.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Wrap width for generated comments (a number of columns or `"auto"`).
+    width: Option<CommentWidth>,
+    /// Accepted extensions of input files (e.g., `["c", "txt"]`).
+    extensions: Option<Vec<String>>,
+    /// Number of generations merged by majority vote for each prompt.
+    samples: Option<NonZeroUsize>,
+    /// Maximum number of target files analyzed concurrently in batch mode.
+    jobs: Option<NonZeroUsize>,
+    /// Whether to analyze each function of the input files separately.
+    chunk: Option<bool>,
+    /// Whether to cache LLM responses on disk.
+    cache: Option<bool>,
+    /// Directory where LLM responses are cached.
+    cache_dir: Option<PathBuf>,
+    /// File where the audit log of each run is appended.
+    log_file: Option<PathBuf>,
+    /// Whether to record each analysis in the history.
+    history: Option<bool>,
+    /// File where the history of analyses is stored.
+    history_file: Option<PathBuf>,
+}
+
+impl Config {
+    /// Returns the default configuration file path (e.g., `~/.config/oneiromancer/config.toml`).
+    #[must_use]
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(env!("CARGO_PKG_NAME")).join(CONFIG_FILENAME))
+    }
+
+    /// Loads the configuration from the `filepath` file.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ConfigError`] in case something goes wrong with file I/O or parsing.
+    pub fn load(filepath: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let contents = fs::read_to_string(&filepath)?;
+        Ok(toml::from_str(&contents)?)
+    }
+
+    /// Writes the commented default configuration ([`CONFIG_TEMPLATE`]) to the `filepath` file,
+    /// creating its parent directories. An existing file is replaced only if `force` is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ConfigError`] in case the file already exists (and `force` is not set) or cannot
+    /// be written.
+    pub fn init(filepath: impl AsRef<Path>, force: bool) -> Result<(), ConfigError> {
+        let path = filepath.as_ref();
+        if !force && path.exists() {
+            return Err(ConfigError::AlreadyExists(path.to_path_buf()));
+        }
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(ConfigError::WriteFailed)?;
+        }
+        fs::write(path, CONFIG_TEMPLATE).map_err(ConfigError::WriteFailed)
+    }
+
+    /// Loads the configuration from the default path, if the file exists, or returns an empty
+    /// configuration otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ConfigError`] in case an existing configuration file cannot be read or parsed.
+    pub fn load_default() -> Result<Self, ConfigError> {
+        match Self::default_path() {
+            Some(filepath) if filepath.exists() => Self::load(filepath),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Gets the configured wrap width for generated comments.
+    #[must_use]
+    pub const fn width(&self) -> Option<CommentWidth> {
+        self.width
+    }
+
+    /// Gets the configured accepted extensions of input files.
+    #[must_use]
+    pub fn extensions(&self) -> Option<&[String]> {
+        self.extensions.as_deref()
+    }
+
+    /// Gets the configured number of generations merged by majority vote for each prompt.
+    #[must_use]
+    pub const fn samples(&self) -> Option<NonZeroUsize> {
+        self.samples
+    }
+
+    /// Gets the configured maximum number of target files analyzed concurrently in batch mode.
+    #[must_use]
+    pub const fn jobs(&self) -> Option<NonZeroUsize> {
+        self.jobs
+    }
+
+    /// Gets whether to analyze each function of the input files separately.
+    #[must_use]
+    pub const fn chunk(&self) -> Option<bool> {
+        self.chunk
+    }
+
+    /// Gets whether to cache LLM responses on disk.
+    #[must_use]
+    pub const fn cache(&self) -> Option<bool> {
+        self.cache
+    }
+
+    /// Gets the directory where LLM responses are cached.
+    #[must_use]
+    pub fn cache_dir(&self) -> Option<&Path> {
+        self.cache_dir.as_deref()
+    }
+
+    /// Gets the file where the audit log of each run is appended.
+    #[must_use]
+    pub fn log_file(&self) -> Option<&Path> {
+        self.log_file.as_deref()
+    }
+
+    /// Gets whether to record each analysis in the history.
+    #[must_use]
+    pub const fn history(&self) -> Option<bool> {
+        self.history
+    }
+
+    /// Gets the file where the history of analyses is stored.
+    #[must_use]
+    pub fn history_file(&self) -> Option<&Path> {
+        self.history_file.as_deref()
+    }
+}
+
+/// Configuration error type.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// Failure in reading the configuration file.
+    #[error(transparent)]
+    FileReadFailed(#[from] io::Error),
+    /// Failure in parsing the configuration file.
+    #[error(transparent)]
+    ParseFailed(#[from] de::Error),
+    /// Configuration file that already exists.
+    #[error("Configuration file `{}` already exists", .0.display())]
+    AlreadyExists(PathBuf),
+    /// Failure in writing the configuration file.
+    #[error("Failed to write configuration file")]
+    WriteFailed(#[source] io::Error),
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn config_load_parses_numeric_width() -> anyhow::Result<()> {
+        let tmpdir = tempfile::tempdir()?;
+        let filepath = tmpdir.path().join(CONFIG_FILENAME);
+        fs::write(&filepath, "width = 100\n")?;
+
+        let config = Config::load(&filepath)?;
+
+        assert_eq!(config.width(), Some(CommentWidth::Columns(100)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_load_parses_auto_width() -> anyhow::Result<()> {
+        let tmpdir = tempfile::tempdir()?;
+        let filepath = tmpdir.path().join(CONFIG_FILENAME);
+        fs::write(&filepath, "width = \"auto\"\n")?;
+
+        let config = Config::load(&filepath)?;
+
+        assert_eq!(config.width(), Some(CommentWidth::Auto));
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_load_parses_extensions() -> anyhow::Result<()> {
+        let tmpdir = tempfile::tempdir()?;
+        let filepath = tmpdir.path().join(CONFIG_FILENAME);
+        fs::write(&filepath, "extensions = [\"c\", \"pseudo\"]\n")?;
+
+        let config = Config::load(&filepath)?;
+
+        assert_eq!(
+            config.extensions(),
+            Some(&["c".to_owned(), "pseudo".to_owned()][..])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_load_parses_cache() -> anyhow::Result<()> {
+        let tmpdir = tempfile::tempdir()?;
+        let filepath = tmpdir.path().join(CONFIG_FILENAME);
+        fs::write(
+            &filepath,
+            "cache = true\ncache_dir = \"/tmp/responses\"\nlog_file = \"runs.jsonl\"\n\
+             history = true\nhistory_file = \"history.jsonl\"\n",
+        )?;
+
+        let config = Config::load(&filepath)?;
+
+        assert_eq!(config.cache(), Some(true));
+        assert_eq!(config.cache_dir(), Some(Path::new("/tmp/responses")));
+        assert_eq!(config.log_file(), Some(Path::new("runs.jsonl")));
+        assert_eq!(config.history(), Some(true));
+        assert_eq!(config.history_file(), Some(Path::new("history.jsonl")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_load_parses_chunk() -> anyhow::Result<()> {
+        let tmpdir = tempfile::tempdir()?;
+        let filepath = tmpdir.path().join(CONFIG_FILENAME);
+        fs::write(&filepath, "chunk = true\njobs = 4\n")?;
+
+        let config = Config::load(&filepath)?;
+
+        assert_eq!(config.chunk(), Some(true));
+        assert_eq!(config.jobs(), NonZeroUsize::new(4));
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_load_with_empty_file_uses_defaults() -> anyhow::Result<()> {
+        let tmpdir = tempfile::tempdir()?;
+        let filepath = tmpdir.path().join(CONFIG_FILENAME);
+        fs::write(&filepath, "")?;
+
+        let config = Config::load(&filepath)?;
+
+        assert_eq!(config.width(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_load_with_unknown_key_fails() -> anyhow::Result<()> {
+        let tmpdir = tempfile::tempdir()?;
+        let filepath = tmpdir.path().join(CONFIG_FILENAME);
+        fs::write(&filepath, "colour = true\n")?;
+
+        let result = Config::load(&filepath);
+
+        assert!(
+            matches!(result, Err(ConfigError::ParseFailed(_))),
+            "wrong error type returned: {result:?}"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_load_with_invalid_width_fails() -> anyhow::Result<()> {
+        let tmpdir = tempfile::tempdir()?;
+        let filepath = tmpdir.path().join(CONFIG_FILENAME);
+        fs::write(&filepath, "width = \"wide\"\n")?;
+
+        let result = Config::load(&filepath);
+
+        assert!(
+            matches!(result, Err(ConfigError::ParseFailed(_))),
+            "wrong error type returned: {result:?}"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_init_writes_loadable_template_without_overwriting() -> anyhow::Result<()> {
+        let tmpdir = tempfile::tempdir()?;
+        let filepath = tmpdir.path().join("nested").join(CONFIG_FILENAME);
+
+        Config::init(&filepath, false)?;
+        let config = Config::load(&filepath)?;
+        fs::write(&filepath, "width = 100\n")?;
+        let result = Config::init(&filepath, false);
+
+        assert_eq!(config.width(), None, "template sets values");
+        assert!(
+            matches!(result, Err(ConfigError::AlreadyExists(_))),
+            "wrong error type returned: {result:?}"
+        );
+        assert_eq!(fs::read_to_string(&filepath)?, "width = 100\n");
+        Config::init(&filepath, true)?;
+        assert_eq!(fs::read_to_string(&filepath)?, CONFIG_TEMPLATE);
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_template_keys_are_valid() -> anyhow::Result<()> {
+        let uncommented = CONFIG_TEMPLATE
+            .lines()
+            .filter_map(|line| line.strip_prefix("# "))
+            .filter(|line| line.contains(" = "))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let config: Config = toml::from_str(&uncommented)?;
+
+        assert_eq!(config.width(), Some(CommentWidth::Columns(76)));
+        assert_eq!(config.samples(), Some(NonZeroUsize::MIN));
+        assert_eq!(config.jobs(), Some(NonZeroUsize::MIN));
+        assert_eq!(config.chunk(), Some(false));
+        assert_eq!(config.cache(), Some(false));
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_load_with_missing_file_fails() {
+        let result = Config::load("./tests/data/invalid.toml");
+
+        assert!(
+            matches!(result, Err(ConfigError::FileReadFailed(_))),
+            "wrong error type returned: {result:?}"
+        );
+    }
+}
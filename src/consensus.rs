@@ -0,0 +1,256 @@
+//! Merge the results of multiple samples of the same prompt by majority vote (self-consistency).
+
+use std::fmt::Write as _;
+
+use crate::{OneiromancerResults, ResponseMetrics, Variable};
+
+/// Agreement among the samples merged by [`merge_samples`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Consensus {
+    /// Number of merged samples.
+    samples: usize,
+    /// Number of samples that suggested the winning function name.
+    function_votes: usize,
+    /// Agreement on the winning name of each variable, in order of first suggestion.
+    variables: Vec<VariableAgreement>,
+}
+
+impl Consensus {
+    /// Gets the number of merged samples.
+    #[must_use]
+    pub const fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// Gets the number of samples that suggested the winning function name.
+    #[must_use]
+    pub const fn function_votes(&self) -> usize {
+        self.function_votes
+    }
+
+    /// Gets the agreement on the winning name of each variable, in order of first suggestion.
+    #[must_use]
+    pub fn variables(&self) -> &[VariableAgreement] {
+        &self.variables
+    }
+}
+
+/// Agreement on the winning name of a variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariableAgreement {
+    /// Original name of the variable.
+    original_name: String,
+    /// Winning name of the variable.
+    new_name: String,
+    /// Number of samples that suggested the winning name.
+    votes: usize,
+}
+
+impl VariableAgreement {
+    /// Gets the original name of the variable.
+    #[must_use]
+    pub fn original_name(&self) -> &str {
+        &self.original_name
+    }
+
+    /// Gets the winning name of the variable.
+    #[must_use]
+    pub fn new_name(&self) -> &str {
+        &self.new_name
+    }
+
+    /// Gets the number of samples that suggested the winning name.
+    #[must_use]
+    pub const fn votes(&self) -> usize {
+        self.votes
+    }
+}
+
+/// Merges `samples` of the analysis of the same pseudocode by majority vote, returning `None` if
+/// there are no samples.
+///
+/// Each variable gets the `new_name` suggested by most samples, and the function gets the most
+/// common name (ties are broken by first occurrence). The comment is the longest one among the
+/// samples that suggested the winning function name. The merged results carry the [`Consensus`]
+/// and the sum of the [`ResponseMetrics`] of all samples.
+#[must_use]
+pub fn merge_samples(samples: &[OneiromancerResults]) -> Option<OneiromancerResults> {
+    let (function_name, function_votes) =
+        majority(samples.iter().map(OneiromancerResults::function_name))?;
+    let comment = samples
+        .iter()
+        .filter(|sample| sample.function_name() == function_name)
+        .map(OneiromancerResults::comment)
+        .fold("", |longest, comment| {
+            if comment.len() > longest.len() {
+                comment
+            } else {
+                longest
+            }
+        });
+
+    let mut originals: Vec<&str> = Vec::new();
+    for variable in samples.iter().flat_map(OneiromancerResults::variables) {
+        if !originals.contains(&variable.original_name()) {
+            originals.push(variable.original_name());
+        }
+    }
+    let (variables, agreements): (Vec<Variable>, Vec<VariableAgreement>) = originals
+        .iter()
+        .filter_map(|&original| {
+            let suggestions = samples.iter().filter_map(|sample| {
+                sample
+                    .variables()
+                    .iter()
+                    .find(|variable| variable.original_name() == original)
+            });
+            let (new_name, votes) = majority(suggestions.clone().map(Variable::new_name))?;
+            let winner = suggestions
+                .into_iter()
+                .find(|variable| variable.new_name() == new_name)?;
+            Some((
+                winner.clone(),
+                VariableAgreement {
+                    original_name: original.to_owned(),
+                    new_name: new_name.to_owned(),
+                    votes,
+                },
+            ))
+        })
+        .unzip();
+
+    let metrics = samples
+        .iter()
+        .fold(ResponseMetrics::default(), |acc, sample| {
+            acc.merge(*sample.metrics())
+        });
+    let consensus = Consensus {
+        samples: samples.len(),
+        function_votes,
+        variables: agreements,
+    };
+    Some(
+        OneiromancerResults::new(function_name, comment, variables)
+            .attach_metrics(metrics)
+            .attach_consensus(consensus),
+    )
+}
+
+/// Returns the most common of `candidates` with its number of occurrences, breaking ties by
+/// first occurrence, or `None` if there are no candidates.
+fn majority<'a>(candidates: impl Iterator<Item = &'a str>) -> Option<(&'a str, usize)> {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for candidate in candidates {
+        match counts.iter_mut().find(|count| count.0 == candidate) {
+            Some(count) => count.1 = count.1.saturating_add(1),
+            None => counts.push((candidate, 1)),
+        }
+    }
+    counts.into_iter().fold(None, |best, count| match best {
+        Some(winner) if winner.1 >= count.1 => Some(winner),
+        _ => Some(count),
+    })
+}
+
+/// Formats the agreement in `consensus` as a compact block of indented lines, for the
+/// statistics of an analysis.
+#[must_use]
+pub fn format_consensus(consensus: &Consensus, function_name: &str) -> String {
+    let samples = consensus.samples;
+    let mut block = format!(
+        "    Function name:      `{function_name}` ({} of {samples} samples)\n",
+        consensus.function_votes
+    );
+    for variable in &consensus.variables {
+        _ = writeln!(
+            block,
+            "    Variable `{}`: `{}` ({} of {samples} samples)",
+            variable.original_name, variable.new_name, variable.votes
+        );
+    }
+    block
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use super::*;
+
+    /// Parses a sample with `function_name`, `comment`, and the `(original, new)` `variables`.
+    fn sample(
+        function_name: &str,
+        comment: &str,
+        variables: &[(&str, &str)],
+    ) -> serde_json::Result<OneiromancerResults> {
+        serde_json::from_value(serde_json::json!({
+            "function_name": function_name,
+            "comment": comment,
+            "variables": variables
+                .iter()
+                .map(|variable| serde_json::json!({
+                    "original_name": variable.0,
+                    "new_name": variable.1,
+                }))
+                .collect::<Vec<_>>(),
+        }))
+    }
+
+    #[test]
+    fn merge_samples_takes_majority_names_and_longest_agreeing_comment() -> anyhow::Result<()> {
+        let samples = vec![
+            sample("init_ctx", "Initializes.", &[("a1", "ctx"), ("v1", "len")])?,
+            sample(
+                "setup",
+                "A much longer comment that loses.",
+                &[("a1", "ctx")],
+            )?,
+            sample(
+                "init_ctx",
+                "Initializes the context.",
+                &[("v1", "size"), ("a1", "state")],
+            )?,
+        ];
+
+        let merged = merge_samples(&samples).ok_or_else(|| anyhow::anyhow!("no results"))?;
+        assert_eq!(merged.function_name(), "init_ctx");
+        assert_eq!(merged.comment(), "Initializes the context.");
+        let renames: Vec<_> = merged
+            .variables()
+            .iter()
+            .map(|variable| (variable.original_name(), variable.new_name()))
+            .collect();
+        // `v1` is a tie between `len` and `size`, broken by first occurrence.
+        assert_eq!(renames, [("a1", "ctx"), ("v1", "len")]);
+
+        let consensus = merged
+            .consensus()
+            .ok_or_else(|| anyhow::anyhow!("no consensus"))?;
+        assert_eq!((consensus.samples(), consensus.function_votes()), (3, 2));
+        let votes: Vec<_> = consensus
+            .variables()
+            .iter()
+            .map(|variable| (variable.original_name(), variable.votes()))
+            .collect();
+        assert_eq!(votes, [("a1", 2), ("v1", 1)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge_samples_of_single_sample_is_unanimous() -> anyhow::Result<()> {
+        assert!(merge_samples(&[]).is_none(), "empty samples merged");
+
+        let merged = merge_samples(&[sample("main", "Entry.", &[("v1", "count")])?])
+            .ok_or_else(|| anyhow::anyhow!("no results"))?;
+        let consensus = merged
+            .consensus()
+            .ok_or_else(|| anyhow::anyhow!("no consensus"))?;
+        assert_eq!(
+            format_consensus(consensus, merged.function_name()),
+            "    Function name:      `main` (1 of 1 samples)\n    \
+             Variable `v1`: `count` (1 of 1 samples)\n"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+//! Supply extra context to the analysis.
+
+use std::borrow::Cow;
+use std::fmt::Write as _;
+
+/// Delimiter between the pseudocode and the extra context in the prompt.
+const CONTEXT_DELIMITER: &str = "// ----- Additional context (do not analyze) -----";
+
+/// Extra context supplied by the caller to improve the analysis (e.g., harvested from IDA).
+///
+/// The context is appended to the prompt in a delimited section, after the pseudocode. An empty
+/// context leaves the prompt unchanged.
+#[derive(Debug, Clone, Default)]
+pub struct AnalysisContext {
+    /// Disassembly excerpt of the analyzed function.
+    disassembly: String,
+    /// String literals referenced by the analyzed function.
+    strings: Vec<String>,
+    /// Definitions of the types referenced by the analyzed function.
+    types: Vec<String>,
+    /// Free-form notes.
+    notes: String,
+    /// Source lines that precede the analyzed function in its file.
+    preceding: String,
+}
+
+impl AnalysisContext {
+    /// Creates a new empty [`AnalysisContext`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets a disassembly excerpt of the analyzed function.
+    #[must_use]
+    pub fn disassembly(mut self, disassembly: impl Into<String>) -> Self {
+        self.disassembly = disassembly.into();
+        self
+    }
+
+    /// Sets the string literals referenced by the analyzed function.
+    #[must_use]
+    pub fn strings(mut self, strings: &[String]) -> Self {
+        self.strings = strings.to_vec();
+        self
+    }
+
+    /// Sets the definitions of the types referenced by the analyzed function (e.g., structs and
+    /// callee prototypes).
+    #[must_use]
+    pub fn types(mut self, types: &[String]) -> Self {
+        self.types = types.to_vec();
+        self
+    }
+
+    /// Sets free-form notes about the analyzed function.
+    #[must_use]
+    pub fn notes(mut self, notes: impl Into<String>) -> Self {
+        self.notes = notes.into();
+        self
+    }
+
+    /// Sets the source lines that precede the analyzed function in its file (e.g., declarations of
+    /// the globals it uses).
+    #[must_use]
+    pub fn preceding_code(mut self, code: impl Into<String>) -> Self {
+        self.preceding = code.into();
+        self
+    }
+
+    /// Returns `true` if the context does not contain anything.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.disassembly.trim().is_empty()
+            && self.strings.is_empty()
+            && self.types.iter().all(|ty| ty.trim().is_empty())
+            && self.notes.trim().is_empty()
+            && self.preceding.trim().is_empty()
+    }
+
+    /// Returns `prompt` with the context appended in a delimited section, or `prompt` unchanged if
+    /// the context is empty.
+    pub(crate) fn append_to<'a>(&self, prompt: &'a str) -> Cow<'a, str> {
+        if self.is_empty() {
+            return Cow::Borrowed(prompt);
+        }
+
+        let mut extended = prompt.trim_end().to_owned();
+        extended.push_str("\n\n");
+        extended.push_str(CONTEXT_DELIMITER);
+        extended.push('\n');
+        push_section(&mut extended, "Disassembly", &self.disassembly);
+        if !self.strings.is_empty() {
+            let strings = self
+                .strings
+                .iter()
+                .map(|string| format!("{string:?}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            push_section(&mut extended, "Strings", &strings);
+        }
+        push_section(&mut extended, "Types", &self.types.join("\n"));
+        push_section(&mut extended, "Preceding code", &self.preceding);
+        push_section(&mut extended, "Notes", &self.notes);
+        Cow::Owned(extended)
+    }
+}
+
+/// Appends the `body` section with the `title` heading to `prompt` as line comments, unless
+/// `body` is empty.
+fn push_section(prompt: &mut String, title: &str, body: &str) {
+    if body.trim().is_empty() {
+        return;
+    }
+    _ = writeln!(prompt, "// {title}:");
+    for line in body.trim_end().lines() {
+        _ = writeln!(prompt, "//   {line}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn append_to_with_empty_context_keeps_prompt() {
+        let prompt = "int main() {}\n";
+
+        let extended = AnalysisContext::new().notes(" \n").append_to(prompt);
+
+        assert!(matches!(extended, Cow::Borrowed(_)), "prompt copied");
+        assert_eq!(extended, prompt);
+    }
+
+    #[test]
+    fn append_to_appends_delimited_sections() {
+        let context = AnalysisContext::new()
+            .disassembly("push rbp\nmov rbp, rsp")
+            .strings(&["Hello, \"world\"".to_owned()])
+            .preceding_code("int counter;")
+            .notes("Called at startup.");
+
+        assert_eq!(
+            context.append_to("int main() {}\n"),
+            format!(
+                "int main() {{}}\n\n{CONTEXT_DELIMITER}\n// Disassembly:\n//   push rbp\n//   mov rbp, rsp\n// Strings:\n//   \"Hello, \\\"world\\\"\"\n// Preceding code:\n//   int counter;\n// Notes:\n//   Called at startup.\n"
+            )
+        );
+    }
+}
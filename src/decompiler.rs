@@ -0,0 +1,264 @@
+//! Presets for the identifier conventions and boilerplate of each decompiler.
+
+use regex::Regex;
+
+use crate::lexer::{TokenKind, tokenize};
+
+/// Decompiler that produced the pseudocode, which selects a [`Preset`].
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Decompiler {
+    /// IDA Pro Hex-Rays decompiler (`v1`, `a2`, `LOBYTE`).
+    #[cfg_attr(feature = "cli", value(name = "hexrays"))]
+    HexRays,
+    /// Ghidra decompiler (`local_8`, `param_1`, `uVar3`).
+    Ghidra,
+    /// Binary Ninja pseudo-C (`var_10`, `arg1`).
+    #[cfg_attr(feature = "cli", value(name = "binja"))]
+    BinaryNinja,
+}
+
+/// Conventions of a decompiler, matched as regular expressions against whole identifiers or lines.
+struct Preset {
+    /// Decompiler the preset applies to.
+    decompiler: Decompiler,
+    /// Display name of the decompiler.
+    name: &'static str,
+    /// Hint appended to the prompt, if any (none for Hex-Rays, the dialect the default model is
+    /// trained on).
+    hint: Option<&'static str>,
+    /// Identifiers that the decompiler generates, which are safe to rename.
+    generated: &'static [&'static str],
+    /// Identifiers typical of the decompiler, used to detect it.
+    telltales: &'static [&'static str],
+    /// Boilerplate lines stripped from the prompt along with the other noise.
+    noise: &'static [&'static str],
+}
+
+/// Presets of the supported decompilers, in order of detection priority. Add a row (and a
+/// [`Decompiler`] variant) to support another one.
+const PRESETS: &[Preset] = &[
+    Preset {
+        decompiler: Decompiler::HexRays,
+        name: "Hex-Rays",
+        hint: None,
+        generated: &[
+            r"[av]\d+",
+            "(i|j|k|m|n|ii|jj|kk)",
+            // Names taken from the prototypes of known functions, with the letter that Hex-Rays
+            // appends to repeated names (e.g., `desta`).
+            "(result|s|s1|s2|dest|src|buf|fd|fildes|stream|format|ptr|str|c|ch|size|len|nbytes|\
+             filename|file|path|name|mode|modes|nptr|endptr|base|haystack|needle|addr|flags|\
+             status|timer|argc|argv|envp|this)[a-z]?",
+            // Hungarian names from the prototypes of the Windows API (e.g., `lpBuffer`, `hFile`).
+            "(lp|lpsz|h|dw|cb|pv|psz|sz)[A-Z][A-Za-z0-9]*",
+        ],
+        telltales: &[
+            r"[av]\d+",
+            r"(LO|HI)(BYTE|WORD)|(S?LO|S?HI)DWORD|S?BYTE\d|WORD\d",
+            "_(BYTE|WORD|DWORD|QWORD|OWORD)|__int(8|16|32|64|128)",
+            "__(fastcall|cdecl|stdcall|thiscall|usercall|userpurge|noreturn)",
+            "(sub|loc|unk|off|byte|word|dword|qword)_[0-9A-F]+",
+        ],
+        noise: &[],
+    },
+    Preset {
+        decompiler: Decompiler::Ghidra,
+        name: "Ghidra",
+        hint: Some(
+            "The pseudocode above was produced by the Ghidra decompiler: `param_N` are the \
+             parameters, `local_X` are stack variables at offset X, `xVarN` are temporaries, and \
+             `undefinedN` are untyped values of N bytes.",
+        ),
+        generated: &[
+            r"param_\d+",
+            "local_[0-9a-f]+(_[0-9a-f]+)?",
+            r"[a-z]{1,3}Var\d+",
+            r"(in|extraout|unaff)_\w+",
+            "[a-z]{2}Stack_[0-9a-f]+",
+        ],
+        telltales: &[
+            r"param_\d+",
+            "local_[0-9a-f]+(_[0-9a-f]+)?",
+            r"[a-z]{1,3}Var\d+",
+            r"undefined\d*",
+            "(FUN|DAT|LAB|PTR|SUB|CONCAT|ZEXT|SEXT)_?[0-9a-f]+",
+            r"(in|extraout|unaff)_\w+",
+        ],
+        noise: &[r"typedef\s.*\bundefined\d*\s*;"],
+    },
+    Preset {
+        decompiler: Decompiler::BinaryNinja,
+        name: "Binary Ninja",
+        hint: Some(
+            "The pseudocode above was produced by Binary Ninja: `argN` are the parameters, \
+             `var_X` are stack variables at offset X, and names such as `rax_1` are versions of \
+             register values.",
+        ),
+        generated: &[
+            "var_[0-9a-f]+",
+            r"arg\d+",
+            r"([re][a-z]{2}|r\d+[dwb]?|[xw]\d+)(_\d+)?",
+        ],
+        telltales: &[
+            "var_[0-9a-f]+",
+            r"arg\d+",
+            r"([re][a-z]{2}|r\d+[dwb]?|[xw]\d+)_\d+",
+            "(sub|data|j_sub)_[0-9a-f]+",
+        ],
+        noise: &[],
+    },
+];
+
+impl Decompiler {
+    /// Detects the decompiler that produced `pseudocode` from its telltale identifiers, returning
+    /// the one with the most matches, or `None` if there are none or two decompilers tie.
+    #[must_use]
+    pub fn detect(pseudocode: &str) -> Option<Self> {
+        let matchers: Vec<(Self, Regex)> = PRESETS
+            .iter()
+            .filter_map(|preset| Some((preset.decompiler, whole(preset.telltales)?)))
+            .collect();
+        let mut scores: Vec<usize> = vec![0; matchers.len()];
+        for token in tokenize(pseudocode) {
+            if token.kind != TokenKind::Identifier {
+                continue;
+            }
+            let identifier = token.text(pseudocode);
+            for (score, matcher) in scores.iter_mut().zip(&matchers) {
+                if matcher.1.is_match(identifier) {
+                    *score = score.saturating_add(1);
+                }
+            }
+        }
+
+        let best = scores.iter().copied().max().filter(|&best| best > 0)?;
+        let mut winners = matchers
+            .iter()
+            .zip(&scores)
+            .filter(|&(_, &score)| score == best);
+        match (winners.next(), winners.next()) {
+            (Some(winner), None) => Some(winner.0.0),
+            _ => None,
+        }
+    }
+
+    /// Returns the display name of the decompiler (e.g., `Hex-Rays`).
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        self.preset()
+            .map_or("unknown decompiler", |preset| preset.name)
+    }
+
+    /// Returns the hint on the conventions of the decompiler appended to the prompt, if any.
+    #[must_use]
+    pub fn prompt_hint(self) -> Option<&'static str> {
+        self.preset()?.hint
+    }
+
+    /// Returns `true` if `identifier` is a name generated by the decompiler (e.g., `v1` or
+    /// `param_1`), which is safe to rename, rather than one assigned by the user.
+    #[must_use]
+    pub fn is_generated(self, identifier: &str) -> bool {
+        self.preset()
+            .and_then(|preset| whole(preset.generated))
+            .is_some_and(|re| re.is_match(identifier))
+    }
+
+    /// Returns `true` if `line` is boilerplate of the decompiler that can be stripped from the
+    /// prompt (e.g., Ghidra's `undefined8` typedefs).
+    #[must_use]
+    pub fn is_noise(self, line: &str) -> bool {
+        self.preset()
+            .and_then(|preset| whole(preset.noise))
+            .is_some_and(|re| re.is_match(line.trim()))
+    }
+
+    /// Returns the [`Preset`] of the decompiler, if any.
+    fn preset(self) -> Option<&'static Preset> {
+        PRESETS.iter().find(|preset| preset.decompiler == self)
+    }
+}
+
+/// Returns a regular expression that matches whole strings matching any of `patterns`, or `None`
+/// if there are no patterns.
+fn whole(patterns: &[&str]) -> Option<Regex> {
+    if patterns.is_empty() {
+        return None;
+    }
+    Regex::new(&format!("^(?:{})$", patterns.join("|"))).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEXRAYS: &str = "__int64 __fastcall sub_140001000(__int64 a1, int a2)\n{\n  __int64 result; // rax\n  int v3; // [rsp+20h] [rbp-18h]\n\n  v3 = a2;\n  LOBYTE(v3) = *(_BYTE *)(a1 + 8);\n  result = v3;\n  return result;\n}\n";
+    const GHIDRA: &str = "typedef unsigned char   undefined;\ntypedef unsigned long long    undefined8;\n\nundefined8 FUN_00401000(long param_1,int param_2)\n{\n  uint uVar1;\n  undefined8 local_10;\n  \n  uVar1 = *(uint *)(param_1 + 8);\n  local_10 = CONCAT44(uVar1,param_2);\n  return local_10;\n}\n";
+    const BINJA: &str = "int64_t sub_401000(int64_t arg1, int32_t arg2)\n{\n    int64_t var_10 = arg1;\n    int32_t rax_1 = *(uint32_t*)(arg1 + 8);\n    return data_404010 + rax_1 + arg2;\n}\n";
+
+    #[test]
+    fn detect_recognizes_each_decompiler() {
+        assert_eq!(Decompiler::detect(HEXRAYS), Some(Decompiler::HexRays));
+        assert_eq!(Decompiler::detect(GHIDRA), Some(Decompiler::Ghidra));
+        assert_eq!(Decompiler::detect(BINJA), Some(Decompiler::BinaryNinja));
+    }
+
+    #[test]
+    fn detect_without_telltales_is_undecided() {
+        assert_eq!(
+            Decompiler::detect("int add(int x, int y) { return x + y; }"),
+            None
+        );
+        assert_eq!(Decompiler::detect(""), None);
+        // One telltale each: a tie.
+        assert_eq!(Decompiler::detect("f(a1, param_1);"), None);
+    }
+
+    #[test]
+    fn is_generated_distinguishes_user_assigned_names() {
+        for (decompiler, generated, assigned) in [
+            (Decompiler::HexRays, ["v12", "a1", "result"], "buffer"),
+            (Decompiler::HexRays, ["dest", "sa", "stream"], "packet"),
+            (Decompiler::HexRays, ["lpBuffer", "hFile", "fd"], "header"),
+            (Decompiler::Ghidra, ["param_1", "local_10", "uVar3"], "v1"),
+            (
+                Decompiler::BinaryNinja,
+                ["var_10", "arg1", "rax_1"],
+                "param_1",
+            ),
+        ] {
+            for name in generated {
+                assert!(decompiler.is_generated(name), "{decompiler:?}: {name}");
+            }
+            assert!(
+                !decompiler.is_generated(assigned),
+                "{decompiler:?}: {assigned}"
+            );
+        }
+        assert!(
+            !Decompiler::HexRays.is_generated("av1"),
+            "partial match accepted"
+        );
+    }
+
+    #[test]
+    fn is_noise_strips_ghidra_typedef_banner() {
+        assert!(Decompiler::Ghidra.is_noise("typedef unsigned long long    undefined8;"));
+        assert!(!Decompiler::Ghidra.is_noise("typedef struct packet packet_t;"));
+        assert!(!Decompiler::HexRays.is_noise("typedef unsigned char undefined;"));
+    }
+
+    #[test]
+    fn prompt_hint_names_conventions() {
+        assert_eq!(Decompiler::HexRays.prompt_hint(), None);
+        assert!(
+            Decompiler::Ghidra
+                .prompt_hint()
+                .is_some_and(|hint| hint.contains("param_N")),
+            "missing Ghidra hint"
+        );
+        assert_eq!(Decompiler::BinaryNinja.name(), "Binary Ninja");
+    }
+}
@@ -0,0 +1,513 @@
+//! Discover pseudocode files to analyze in directories.
+
+use std::fs::File;
+use std::io::{self, Read as _};
+use std::path::{Path, PathBuf};
+
+use glob::{GlobError, PatternError};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use thiserror::Error;
+
+use crate::MAX_INPUT_SIZE;
+
+/// Default glob patterns of files to include.
+const DEFAULT_INCLUDE: &[&str] = &["*.c"];
+/// Default glob patterns of files to exclude (i.e., outputs of previous runs).
+const DEFAULT_EXCLUDE: &[&str] = &["*.out.c"];
+/// Number of leading bytes inspected to detect binary files.
+const BINARY_SNIFF_SIZE: u64 = 8192;
+
+/// Options that control how pseudocode files are discovered in directories.
+#[derive(Debug, Clone)]
+pub struct DiscoverOptions {
+    /// Whether to walk directories recursively.
+    recursive: bool,
+    /// Glob patterns of files to include.
+    include: Vec<String>,
+    /// Glob patterns of files to exclude.
+    exclude: Vec<String>,
+    /// Whether to respect `.gitignore` and similar files.
+    gitignore: bool,
+}
+
+impl DiscoverOptions {
+    /// Creates new [`DiscoverOptions`] with default values (non-recursive, include `*.c`, exclude
+    /// `*.out.c`, ignore `.gitignore` files).
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether to walk directories recursively.
+    #[must_use]
+    pub const fn recursive(mut self, recursive: bool) -> Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Sets the glob patterns of files to include, replacing the default ones.
+    #[must_use]
+    pub fn include(mut self, patterns: &[String]) -> Self {
+        self.include = patterns.to_vec();
+        self
+    }
+
+    /// Sets the glob patterns of files to exclude, replacing the default ones.
+    #[must_use]
+    pub fn exclude(mut self, patterns: &[String]) -> Self {
+        self.exclude = patterns.to_vec();
+        self
+    }
+
+    /// Sets whether to respect `.gitignore` and similar files.
+    #[must_use]
+    pub const fn gitignore(mut self, gitignore: bool) -> Self {
+        self.gitignore = gitignore;
+        self
+    }
+
+    /// Compiles the include and exclude patterns.
+    pub(crate) fn filters(&self) -> Result<Filters, DiscoverError> {
+        Ok(Filters {
+            include: glob_set(&self.include)?,
+            exclude: glob_set(&self.exclude)?,
+        })
+    }
+}
+
+impl Default for DiscoverOptions {
+    fn default() -> Self {
+        Self {
+            recursive: false,
+            include: DEFAULT_INCLUDE.iter().map(|&p| p.to_owned()).collect(),
+            exclude: DEFAULT_EXCLUDE.iter().map(|&p| p.to_owned()).collect(),
+            gitignore: false,
+        }
+    }
+}
+
+/// Reason why a discovered file was skipped.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SkipReason {
+    /// The file appears to be binary.
+    #[error("binary file")]
+    Binary,
+    /// The file exceeds [`MAX_INPUT_SIZE`].
+    #[error("larger than {MAX_INPUT_SIZE} bytes")]
+    TooLarge,
+    /// The file or directory could not be read (e.g., because of a symlink loop).
+    #[error("{0}")]
+    Unreadable(String),
+    /// The file is not valid UTF-8 (checked in archives only).
+    #[error("not valid UTF-8")]
+    InvalidUtf8,
+}
+
+/// Pseudocode files discovered by [`discover_files`].
+#[derive(Debug, Clone, Default)]
+pub struct Discovery {
+    /// Files to analyze, in processing order.
+    files: Vec<PathBuf>,
+    /// Files that matched the filters but were skipped, with the corresponding reasons.
+    skipped: Vec<(PathBuf, SkipReason)>,
+}
+
+impl Discovery {
+    /// Returns the files to analyze, in processing order.
+    #[must_use]
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// Returns the files that matched the filters but were skipped, with the corresponding
+    /// reasons.
+    #[must_use]
+    pub fn skipped(&self) -> &[(PathBuf, SkipReason)] {
+        &self.skipped
+    }
+}
+
+/// Compiled include and exclude patterns of [`DiscoverOptions`].
+#[derive(Debug, Clone)]
+pub struct Filters {
+    /// Patterns of files to include.
+    include: GlobSet,
+    /// Patterns of files to exclude.
+    exclude: GlobSet,
+}
+
+impl Filters {
+    /// Returns `true` if the `relative` path is included and not excluded.
+    pub fn matches(&self, relative: &Path) -> bool {
+        self.include.is_match(relative) && !self.exclude.is_match(relative)
+    }
+}
+
+/// Discovery error type.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum DiscoverError {
+    /// Invalid include or exclude glob pattern.
+    #[error(transparent)]
+    InvalidPattern(#[from] globset::Error),
+    /// Invalid input glob pattern.
+    #[error(transparent)]
+    InvalidInputPattern(#[from] PatternError),
+    /// Failure in expanding an input glob pattern.
+    #[error(transparent)]
+    ExpansionFailed(#[from] GlobError),
+    /// Input glob pattern that matches no files.
+    #[error("No files match the pattern `{0}`")]
+    NoMatches(String),
+}
+
+/// Expands glob patterns in `paths` (e.g., `dumps/**/*.c`), merging the results with literal
+/// paths.
+///
+/// Arguments that contain glob metacharacters (`*`, `?`, `[`) and do not name an existing path
+/// are treated as patterns. Both `/` and `\` are accepted as path separators in patterns. If any
+/// pattern is found, the resulting paths are deduplicated and sorted; otherwise, `paths` are
+/// returned as they are.
+///
+/// # Errors
+///
+/// Returns [`DiscoverError`] in case a pattern is invalid or matches no files.
+pub fn expand_patterns(paths: &[impl AsRef<Path>]) -> Result<Vec<PathBuf>, DiscoverError> {
+    let mut expanded = Vec::new();
+    let mut found_pattern = false;
+    for path in paths.iter().map(AsRef::as_ref) {
+        let arg = path.to_string_lossy();
+        if path.exists() || !arg.contains(['*', '?', '[']) {
+            expanded.push(path.to_path_buf());
+            continue;
+        }
+
+        found_pattern = true;
+        let matches = glob::glob(&arg.replace('\\', "/"))?.collect::<Result<Vec<_>, _>>()?;
+        if matches.is_empty() {
+            return Err(DiscoverError::NoMatches(arg.into_owned()));
+        }
+        expanded.extend(matches);
+    }
+
+    if found_pattern {
+        expanded.sort();
+        expanded.dedup();
+    }
+    Ok(expanded)
+}
+
+/// Discovers pseudocode files to analyze in `paths`.
+///
+/// Files are returned as they are, in order. Directories are walked (recursively, if enabled) in
+/// file name order, and the files they contain are filtered according to `options`: binary files
+/// and files larger than [`MAX_INPUT_SIZE`] are skipped. Symbolic links are followed, but loops
+/// are detected and skipped.
+///
+/// # Errors
+///
+/// Returns [`DiscoverError`] in case an include or exclude pattern is invalid.
+pub fn discover_files(
+    paths: &[impl AsRef<Path>],
+    options: &DiscoverOptions,
+) -> Result<Discovery, DiscoverError> {
+    let filters = options.filters()?;
+
+    let mut discovery = Discovery::default();
+    for path in paths.iter().map(AsRef::as_ref) {
+        if path.is_dir() {
+            walk_dir(path, &filters, options, &mut discovery);
+        } else {
+            discovery.files.push(path.to_path_buf());
+        }
+    }
+    Ok(discovery)
+}
+
+/// Walks the `dir` directory and adds the files that match the filters to `discovery`.
+fn walk_dir(dir: &Path, filters: &Filters, options: &DiscoverOptions, discovery: &mut Discovery) {
+    let walker = WalkBuilder::new(dir)
+        .standard_filters(false)
+        .git_ignore(options.gitignore)
+        .git_exclude(options.gitignore)
+        .git_global(options.gitignore)
+        .ignore(options.gitignore)
+        .parents(options.gitignore)
+        .require_git(false)
+        .follow_links(true)
+        .max_depth((!options.recursive).then_some(1))
+        .sort_by_file_name(Ord::cmp)
+        .build();
+
+    for result in walker {
+        let entry = match result {
+            Ok(entry) => entry,
+            Err(err) => {
+                discovery
+                    .skipped
+                    .push((dir.to_path_buf(), SkipReason::Unreadable(err.to_string())));
+                continue;
+            }
+        };
+        if entry.file_type().is_none_or(|ft| ft.is_dir()) {
+            continue;
+        }
+
+        let path = entry.path();
+        let relative = path.strip_prefix(dir).unwrap_or(path);
+        if !filters.matches(relative) {
+            continue;
+        }
+        match check_contents(path) {
+            Ok(()) => discovery.files.push(path.to_path_buf()),
+            Err(reason) => discovery.skipped.push((path.to_path_buf(), reason)),
+        }
+    }
+}
+
+/// Checks that the `path` file is neither binary nor too large to be analyzed.
+fn check_contents(path: &Path) -> Result<(), SkipReason> {
+    let unreadable = |err: io::Error| SkipReason::Unreadable(err.to_string());
+
+    let file = File::open(path).map_err(unreadable)?;
+    let len = file.metadata().map_err(unreadable)?.len();
+    if usize::try_from(len).map_or(true, |size| size > MAX_INPUT_SIZE) {
+        return Err(SkipReason::TooLarge);
+    }
+
+    let mut head = Vec::new();
+    file.take(BINARY_SNIFF_SIZE)
+        .read_to_end(&mut head)
+        .map_err(unreadable)?;
+    if head.contains(&0) {
+        return Err(SkipReason::Binary);
+    }
+    Ok(())
+}
+
+/// Checks that `contents` read from an archive are neither too large nor binary, and are valid
+/// UTF-8, returning the pseudocode.
+pub fn check_code(contents: Vec<u8>) -> Result<String, SkipReason> {
+    if contents.len() > MAX_INPUT_SIZE {
+        return Err(SkipReason::TooLarge);
+    }
+    if contents.contains(&0) {
+        return Err(SkipReason::Binary);
+    }
+    String::from_utf8(contents).map_err(|_err| SkipReason::InvalidUtf8)
+}
+
+/// Builds a case-insensitive [`GlobSet`] from `patterns`.
+fn glob_set(patterns: &[String]) -> Result<GlobSet, DiscoverError> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(GlobBuilder::new(pattern).case_insensitive(true).build()?);
+    }
+    Ok(builder.build()?)
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    /// Creates a directory tree with pseudocode files, outputs, and other files.
+    fn create_tree() -> anyhow::Result<tempfile::TempDir> {
+        let tmpdir = tempfile::tempdir()?;
+        let root = tmpdir.path();
+        fs::create_dir_all(root.join("sub/deeper"))?;
+        fs::write(root.join("b.c"), "int b() {}")?;
+        fs::write(root.join("a.C"), "int a() {}")?;
+        fs::write(root.join("a.out.c"), "int a() {}")?;
+        fs::write(root.join("notes.txt"), "notes")?;
+        fs::write(root.join("sub/c.c"), "int c() {}")?;
+        fs::write(root.join("sub/deeper/d.c"), "int d() {}")?;
+        fs::write(root.join("sub/blob.c"), b"\x7fELF\x00\x00")?;
+        Ok(tmpdir)
+    }
+
+    /// Returns the paths of `files` relative to `root`, with forward slashes.
+    fn relative(root: &Path, files: &[PathBuf]) -> Vec<String> {
+        files
+            .iter()
+            .filter_map(|file| file.strip_prefix(root).ok())
+            .map(|file| file.to_string_lossy().replace('\\', "/"))
+            .collect()
+    }
+
+    #[test]
+    fn discover_files_in_directory_is_not_recursive_by_default() -> anyhow::Result<()> {
+        let tree = create_tree()?;
+
+        let discovery = discover_files(&[tree.path()], &DiscoverOptions::default())?;
+
+        assert_eq!(relative(tree.path(), discovery.files()), ["a.C", "b.c"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn discover_files_recursive_walks_tree_in_order() -> anyhow::Result<()> {
+        let tree = create_tree()?;
+
+        let discovery = discover_files(&[tree.path()], &DiscoverOptions::new().recursive(true))?;
+
+        assert_eq!(
+            relative(tree.path(), discovery.files()),
+            ["a.C", "b.c", "sub/c.c", "sub/deeper/d.c"]
+        );
+        assert_eq!(
+            discovery.skipped(),
+            [(tree.path().join("sub/blob.c"), SkipReason::Binary)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn discover_files_applies_include_and_exclude_filters() -> anyhow::Result<()> {
+        let tree = create_tree()?;
+
+        let options = DiscoverOptions::new()
+            .recursive(true)
+            .include(&["*.txt".to_owned(), "*.c".to_owned()])
+            .exclude(&["sub/**".to_owned()]);
+        let discovery = discover_files(&[tree.path()], &options)?;
+
+        assert_eq!(
+            relative(tree.path(), discovery.files()),
+            ["a.C", "a.out.c", "b.c", "notes.txt"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn discover_files_respects_gitignore_if_enabled() -> anyhow::Result<()> {
+        let tree = create_tree()?;
+        fs::write(tree.path().join(".gitignore"), "sub/\n")?;
+
+        let options = DiscoverOptions::new().recursive(true).gitignore(true);
+        let discovery = discover_files(&[tree.path()], &options)?;
+
+        assert_eq!(relative(tree.path(), discovery.files()), ["a.C", "b.c"]);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn discover_files_does_not_follow_symlink_loops() -> anyhow::Result<()> {
+        use std::os::unix::fs::symlink;
+
+        let tree = create_tree()?;
+        symlink(tree.path(), tree.path().join("sub/loop"))?;
+
+        let discovery = discover_files(&[tree.path()], &DiscoverOptions::new().recursive(true))?;
+
+        assert_eq!(discovery.files().len(), 4, "wrong number of files");
+        assert!(
+            discovery
+                .skipped()
+                .iter()
+                .any(|skipped| matches!(skipped.1, SkipReason::Unreadable(_))),
+            "symlink loop not reported"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn discover_files_passes_files_through() -> anyhow::Result<()> {
+        let discovery = discover_files(&["b.c", "a.c"], &DiscoverOptions::default())?;
+
+        assert_eq!(
+            discovery.files(),
+            [PathBuf::from("b.c"), PathBuf::from("a.c")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn expand_patterns_merges_sorts_and_dedupes() -> anyhow::Result<()> {
+        let tree = create_tree()?;
+        let root = tree.path().to_string_lossy().into_owned();
+
+        let expanded = expand_patterns(&[
+            format!("{root}/sub/**/*.c"),
+            format!("{root}/b.c"),
+            format!("{root}/sub/c.c"),
+        ])?;
+
+        assert_eq!(
+            relative(tree.path(), &expanded),
+            ["b.c", "sub/blob.c", "sub/c.c", "sub/deeper/d.c"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn expand_patterns_accepts_backslash_separators() -> anyhow::Result<()> {
+        let tree = create_tree()?;
+        let root = tree.path().to_string_lossy().into_owned();
+
+        let expanded = expand_patterns(&[format!("{root}\\sub\\deeper\\*.c")])?;
+
+        assert_eq!(relative(tree.path(), &expanded), ["sub/deeper/d.c"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn expand_patterns_without_patterns_preserves_order() -> anyhow::Result<()> {
+        let expanded = expand_patterns(&["b.c", "a.c", "b.c"])?;
+
+        assert_eq!(
+            expanded,
+            [
+                PathBuf::from("b.c"),
+                PathBuf::from("a.c"),
+                PathBuf::from("b.c")
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn expand_patterns_without_matches_fails() -> anyhow::Result<()> {
+        let tree = create_tree()?;
+        let pattern = format!("{}/*.cpp", tree.path().to_string_lossy());
+
+        let result = expand_patterns(&[&pattern]);
+
+        assert!(
+            matches!(result, Err(DiscoverError::NoMatches(_))),
+            "wrong error type returned: {result:?}"
+        );
+        assert!(
+            result.is_err_and(|err| err.to_string().contains(&pattern)),
+            "pattern not named in the error"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn discover_files_with_invalid_pattern_fails() {
+        let options = DiscoverOptions::new().include(&["[".to_owned()]);
+
+        let result = discover_files(&["."], &options);
+
+        assert!(
+            matches!(result, Err(DiscoverError::InvalidPattern(_))),
+            "wrong error type returned: {result:?}"
+        );
+    }
+}
@@ -0,0 +1,276 @@
+//! Collect the suggestions of analyses for the scripts that apply them in disassemblers.
+//!
+//! Each target disassembler implements [`ScriptEmitter`] in its own module.
+
+use std::fmt::Write as _;
+use std::num::ParseIntError;
+
+use crate::report::FunctionReport;
+use crate::{FunctionSpan, OneiromancerResults, select_function, split_functions};
+
+/// Generator of scripts that apply suggestions in a disassembler.
+pub trait ScriptEmitter {
+    /// Returns a description of the generated scripts, for status messages.
+    fn description(&self) -> &'static str;
+
+    /// Formats a script that applies the `suggestions` made by `model`.
+    fn format_script(&self, suggestions: &[FunctionSuggestions], model: &str) -> String;
+}
+
+/// Suggestions for a function, to be applied by a disassembler script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionSuggestions {
+    /// Address of the function, if known.
+    address: Option<u64>,
+    /// Original name of the function, as it appears in the pseudocode (empty if not found).
+    original_name: String,
+    /// Recommended name of the function.
+    function_name: String,
+    /// Description of the function.
+    comment: String,
+    /// Original and new names of the variables to rename, in order.
+    renames: Vec<(String, String)>,
+}
+
+impl FunctionSuggestions {
+    /// Creates new [`FunctionSuggestions`] from the `results` of the analysis of the function
+    /// `original_name`, at `address` (or at the address in `original_name`, if any).
+    #[must_use]
+    pub fn new(results: &OneiromancerResults, original_name: &str, address: Option<u64>) -> Self {
+        Self {
+            address: address.or_else(|| address_in_name(original_name)),
+            original_name: original_name.to_owned(),
+            function_name: results.function_name().to_owned(),
+            comment: results.comment().trim().to_owned(),
+            renames: results
+                .variables()
+                .iter()
+                .filter(|variable| variable.original_name() != variable.new_name())
+                .map(|variable| {
+                    (
+                        variable.original_name().to_owned(),
+                        variable.new_name().to_owned(),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Gets the address of the function, if known.
+    #[must_use]
+    pub const fn address(&self) -> Option<u64> {
+        self.address
+    }
+
+    /// Gets the original name of the function (empty if not found).
+    #[must_use]
+    pub fn original_name(&self) -> &str {
+        &self.original_name
+    }
+
+    /// Gets the recommended name of the function.
+    #[must_use]
+    pub fn function_name(&self) -> &str {
+        &self.function_name
+    }
+
+    /// Gets the description of the function.
+    #[must_use]
+    pub fn comment(&self) -> &str {
+        &self.comment
+    }
+
+    /// Gets the original and new names of the variables to rename, in order.
+    #[must_use]
+    pub fn renames(&self) -> &[(String, String)] {
+        &self.renames
+    }
+}
+
+/// Collects the suggestions of the analysis of `pseudocode`: whole-file `results` apply to the
+/// function selected by `selected` (see [`select_function`]) or to the first function, at
+/// `address` if given, and each of the `functions` reports to the corresponding function.
+pub fn collect_suggestions(
+    pseudocode: &str,
+    results: Option<&OneiromancerResults>,
+    functions: &[FunctionReport],
+    selected: Option<&str>,
+    address: Option<u64>,
+) -> Vec<FunctionSuggestions> {
+    let mut suggestions = Vec::new();
+    if let Some(whole) = results {
+        let spans = split_functions(pseudocode);
+        let target = selected.map_or_else(
+            || spans.first(),
+            |query| select_function(&spans, query).ok(),
+        );
+        let original_name = target.map(FunctionSpan::name).unwrap_or_default();
+        suggestions.push(FunctionSuggestions::new(whole, original_name, address));
+    }
+    suggestions.extend(functions.iter().filter_map(|function| {
+        let analyzed = function.results()?;
+        Some(FunctionSuggestions::new(analyzed, function.name(), None))
+    }));
+    suggestions
+}
+
+/// Parses a function address in hex, with an optional `0x` prefix (e.g., `0x401000`).
+///
+/// # Errors
+///
+/// Returns [`ParseIntError`] in case `text` is not a valid 64-bit hex number.
+pub fn parse_address(text: &str) -> Result<u64, ParseIntError> {
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text);
+    u64::from_str_radix(digits, 16)
+}
+
+/// Formats the `suggestions` as the `FUNCTIONS` data table of Python scripts: a list with a dict
+/// for each function, holding its `address` (`None` if unknown), original `name`, `new_name`,
+/// `comment`, and the `(old, new)` pairs of its variable `renames`.
+pub fn format_python_table(suggestions: &[FunctionSuggestions]) -> String {
+    let mut table = String::from("FUNCTIONS = [\n");
+    for function in suggestions {
+        _ = writeln!(
+            table,
+            "    {{\n        \"address\": {},\n        \"name\": {},\n        \"new_name\": {},\n        \"comment\": {},\n        \"renames\": [",
+            python_address(function.address()),
+            python_string(function.original_name()),
+            python_string(function.function_name()),
+            python_string(function.comment()),
+        );
+        for rename in function.renames() {
+            _ = writeln!(
+                table,
+                "            ({}, {}),",
+                python_string(&rename.0),
+                python_string(&rename.1)
+            );
+        }
+        table.push_str("        ],\n    },\n");
+    }
+    table.push_str("]\n");
+    table
+}
+
+/// Returns `text` as a double-quoted Python string literal, with all non-printable and non-ASCII
+/// characters escaped, so that scripts stay valid whatever the model suggests.
+fn python_string(text: &str) -> String {
+    let mut literal = String::with_capacity(text.len().saturating_add(2));
+    literal.push('"');
+    for ch in text.chars() {
+        match ch {
+            '\\' => literal.push_str("\\\\"),
+            '"' => literal.push_str("\\\""),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            ' '..='~' => literal.push(ch),
+            '\0'..='\u{ff}' => _ = write!(literal, "\\x{:02x}", u32::from(ch)),
+            '\u{100}'..='\u{ffff}' => _ = write!(literal, "\\u{:04x}", u32::from(ch)),
+            _ => _ = write!(literal, "\\U{:08x}", u32::from(ch)),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// Returns `address` as a Python literal: a hex number, or `None` if unknown.
+fn python_address(address: Option<u64>) -> String {
+    address.map_or_else(|| "None".to_owned(), |known| format!("0x{known:X}"))
+}
+
+/// Returns the address in a default function or label name of IDA (e.g., `sub_401000` or
+/// `loc_401A2F`), Ghidra (e.g., `FUN_00401000`), or radare2/rizin (e.g., `fcn.00401000`), if any.
+fn address_in_name(name: &str) -> Option<u64> {
+    let digits = name
+        .strip_prefix("sub_")
+        .or_else(|| name.strip_prefix("loc_"))
+        .or_else(|| name.strip_prefix("FUN_"))
+        .or_else(|| name.strip_prefix("fcn."))
+        .filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))?;
+    parse_address(digits).ok()
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_address_accepts_hex_with_optional_prefix() {
+        assert_eq!(parse_address("0x401000"), Ok(0x0040_1000));
+        assert_eq!(parse_address("140001A2F"), Ok(0x0001_4000_1A2F));
+        assert!(parse_address("0x").is_err(), "empty address accepted");
+        assert!(parse_address("main").is_err(), "name accepted");
+
+        assert_eq!(address_in_name("sub_401A2F"), Some(0x0040_1A2F));
+        assert_eq!(address_in_name("loc_140001000"), Some(0x0001_4000_1000));
+        assert_eq!(address_in_name("fcn.08048a10"), Some(0x0804_8A10));
+        assert_eq!(address_in_name("FUN_00401a2f"), Some(0x0040_1A2F));
+        assert_eq!(address_in_name("sub_+1"), None);
+        assert_eq!(address_in_name("main"), None);
+    }
+
+    #[test]
+    fn python_string_escapes_quotes_controls_and_non_ascii() {
+        assert_eq!(python_string("plain name"), r#""plain name""#);
+        assert_eq!(
+            python_string("say \"hi\"\\n\n\t\u{7}caf\u{e9} \u{2192} \u{1f600}"),
+            r#""say \"hi\"\\n\n\t\x07caf\xe9 \u2192 \U0001f600""#
+        );
+        assert_eq!(python_address(Some(0x0040_1A2F)), "0x401A2F");
+        assert_eq!(python_address(None), "None");
+    }
+
+    #[test]
+    fn collect_suggestions_names_functions_and_their_addresses() -> anyhow::Result<()> {
+        let pseudocode =
+            "int sub_401000(int a1)\n{\n  return a1;\n}\n\nint main()\n{\n  return 0;\n}\n";
+        let results: OneiromancerResults = serde_json::from_str(
+            r#"{"function_name":"init","comment":" Initializes.\n","variables":[{"original_name":"a1","new_name":"ctx"},{"original_name":"v1","new_name":"v1"}]}"#,
+        )?;
+
+        let first = collect_suggestions(pseudocode, Some(&results), &[], None, None);
+        let suggested: Vec<_> = first
+            .iter()
+            .map(|function| {
+                (
+                    function.address(),
+                    function.original_name(),
+                    function.function_name(),
+                    function.comment(),
+                    function.renames().to_vec(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            suggested,
+            [(
+                Some(0x0040_1000),
+                "sub_401000",
+                "init",
+                "Initializes.",
+                vec![("a1".to_owned(), "ctx".to_owned())]
+            )]
+        );
+
+        let selected = collect_suggestions(pseudocode, Some(&results), &[], Some("main"), None);
+        assert_eq!(
+            selected
+                .iter()
+                .map(|function| (function.address(), function.original_name()))
+                .collect::<Vec<_>>(),
+            [(None, "main")]
+        );
+        let addressed = collect_suggestions(pseudocode, Some(&results), &[], None, Some(0x10));
+        assert_eq!(
+            addressed.first().and_then(FunctionSuggestions::address),
+            Some(0x10)
+        );
+
+        Ok(())
+    }
+}
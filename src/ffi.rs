@@ -0,0 +1,295 @@
+//! C ABI for native plugin hosts (e.g., IDA plugins written in C++), enabled by the `ffi` feature.
+//!
+//! Build the shared library with `cargo rustc --lib --features ffi --crate-type cdylib --profile
+//! release-ffi` and include the `include/oneiromancer.h` header generated by cbindgen. Strings
+//! returned to the host are allocated by the library and must be released with
+//! [`oneiromancer_free_string`]. Panics are caught at the boundary and reported as errors, as long
+//! as the library is built with `panic = "unwind"` (as in the `release-ffi` profile, unlike the
+//! `release` profile).
+
+use std::any::Any;
+use std::ffi::{CStr, CString, c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use crate::Oneiromancer;
+
+/// The analysis succeeded, and the results are in `*out_json`.
+pub const ONEIROMANCER_OK: c_int = 0;
+/// An argument is a null pointer where one is not allowed, or not valid UTF-8.
+pub const ONEIROMANCER_INVALID_ARGUMENT: c_int = 1;
+/// The analysis failed (e.g., the Ollama server is unreachable or the response is malformed).
+pub const ONEIROMANCER_ANALYSIS_FAILED: c_int = 2;
+/// The analysis panicked (the panic was caught, and the host can keep running).
+pub const ONEIROMANCER_PANICKED: c_int = 3;
+
+/// Version of the library, as a NUL-terminated string.
+const VERSION: &CStr =
+    match CStr::from_bytes_with_nul(concat!(env!("CARGO_PKG_VERSION"), "\0").as_bytes()) {
+        Ok(version) => version,
+        Err(_) => c"unknown",
+    };
+
+/// Analyzes the NUL-terminated pseudocode in `code` with `model` via the Ollama API at `base_url`.
+///
+/// On success, returns [`ONEIROMANCER_OK`] and stores in `*out_json` the results as a JSON object
+/// with the `function_name`, `comment`, and `variables` fields (as serialized by
+/// [`OneiromancerResults`](crate::OneiromancerResults)). On failure, returns the error code and
+/// stores in `*out_err` the error message, if `out_err` is not null. Each output string that is
+/// set must be released with [`oneiromancer_free_string`]; the others are set to null.
+///
+/// `base_url` and `model` may be null, to use the `OLLAMA_BASEURL` and `OLLAMA_MODEL` environment
+/// variables or the defaults of the command line tool.
+///
+/// # Safety
+///
+/// `code`, and `base_url` and `model` if not null, must point to NUL-terminated strings that stay
+/// valid for the duration of the call. `out_json`, and `out_err` if not null, must point to
+/// writable `char *` locations.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oneiromancer_analyze(
+    code: *const c_char,
+    base_url: *const c_char,
+    model: *const c_char,
+    out_json: *mut *mut c_char,
+    out_err: *mut *mut c_char,
+) -> c_int {
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+        if out_json.is_null() {
+            return Err((
+                ONEIROMANCER_INVALID_ARGUMENT,
+                "`out_json` is null".to_owned(),
+            ));
+        }
+        // SAFETY: the caller guarantees that non-null input pointers are NUL-terminated strings.
+        let pseudocode = unsafe { required_str(code, "code") }?;
+        // SAFETY: as above.
+        let server = unsafe { optional_str(base_url, "base_url") }?;
+        // SAFETY: as above.
+        let name = unsafe { optional_str(model, "model") }?;
+        analyze(pseudocode, server, name)
+    }));
+    let (status, json, err) = match outcome {
+        Ok(Ok(json)) => (ONEIROMANCER_OK, Some(json), None),
+        Ok(Err((status, message))) => (status, None, Some(message)),
+        Err(payload) => (
+            ONEIROMANCER_PANICKED,
+            None,
+            Some(format!("Analysis panicked: {}", panic_message(&*payload))),
+        ),
+    };
+    let json_ptr = json.as_deref().map_or(ptr::null_mut(), into_raw);
+    if !out_json.is_null() {
+        // SAFETY: the caller guarantees that `out_json` is writable.
+        unsafe { out_json.write(json_ptr) }
+    }
+    if !out_err.is_null() {
+        let err_ptr = err.as_deref().map_or(ptr::null_mut(), into_raw);
+        // SAFETY: the caller guarantees that `out_err` is writable if not null.
+        unsafe { out_err.write(err_ptr) }
+    }
+    status
+}
+
+/// Releases a string returned by the library. Does nothing if `string` is null.
+///
+/// # Safety
+///
+/// `string` must be null, or a string returned by the library that is not released yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oneiromancer_free_string(string: *mut c_char) {
+    if !string.is_null() {
+        // SAFETY: the caller guarantees that `string` was allocated by `CString::into_raw`.
+        drop(unsafe { CString::from_raw(string) });
+    }
+}
+
+/// Returns the version of the library as a static NUL-terminated string, which must not be
+/// released.
+#[unsafe(no_mangle)]
+pub const extern "C" fn oneiromancer_version() -> *const c_char {
+    VERSION.as_ptr()
+}
+
+/// Analyzes `pseudocode` with the Ollama API at `baseurl` with `model` (or the defaults), returning
+/// the results as JSON, or the error code and message.
+fn analyze(
+    pseudocode: &str,
+    baseurl: Option<&str>,
+    model: Option<&str>,
+) -> Result<String, (c_int, String)> {
+    let mut client = Oneiromancer::new();
+    if let Some(url) = baseurl {
+        client = client.baseurl(url);
+    }
+    if let Some(name) = model {
+        client = client.model(name);
+    }
+    client
+        .analyze_code(pseudocode)
+        .map_err(|err| err.to_string())
+        .and_then(|results| serde_json::to_string(&results).map_err(|err| err.to_string()))
+        .map_err(|message| (ONEIROMANCER_ANALYSIS_FAILED, message))
+}
+
+/// Returns the string at `string`, named `name` in error messages.
+///
+/// # Safety
+///
+/// `string` must be null or point to a NUL-terminated string.
+unsafe fn required_str<'a>(string: *const c_char, name: &str) -> Result<&'a str, (c_int, String)> {
+    // SAFETY: the caller guarantees that `string` is null or a NUL-terminated string.
+    unsafe { optional_str(string, name) }?
+        .ok_or_else(|| (ONEIROMANCER_INVALID_ARGUMENT, format!("`{name}` is null")))
+}
+
+/// Returns the string at `string`, named `name` in error messages, or `None` if `string` is null.
+///
+/// # Safety
+///
+/// `string` must be null or point to a NUL-terminated string.
+unsafe fn optional_str<'a>(
+    string: *const c_char,
+    name: &str,
+) -> Result<Option<&'a str>, (c_int, String)> {
+    if string.is_null() {
+        return Ok(None);
+    }
+    // SAFETY: the caller guarantees that `string` is a NUL-terminated string.
+    unsafe { CStr::from_ptr(string) }
+        .to_str()
+        .map(Some)
+        .map_err(|err| {
+            (
+                ONEIROMANCER_INVALID_ARGUMENT,
+                format!("`{name}` is not valid UTF-8: {err}"),
+            )
+        })
+}
+
+/// Returns `string` as a NUL-terminated string allocated by the library, dropping any NUL bytes.
+fn into_raw(string: &str) -> *mut c_char {
+    CString::new(string.replace('\0', ""))
+        .unwrap_or_default()
+        .into_raw()
+}
+
+/// Returns the message of a panic with `payload`, if it is a string.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Output strings of a call to [`oneiromancer_analyze`], released when dropped.
+    struct Outputs {
+        /// Results as JSON.
+        json: *mut c_char,
+        /// Error message.
+        err: *mut c_char,
+    }
+
+    impl Outputs {
+        /// Calls [`oneiromancer_analyze`] with `code`, `base_url`, and `model`, returning the status
+        /// and the outputs.
+        fn analyze(code: *const c_char, base_url: *const c_char) -> (c_int, Self) {
+            let mut outputs = Self {
+                json: c"stale".as_ptr().cast_mut(),
+                err: c"stale".as_ptr().cast_mut(),
+            };
+            // SAFETY: all pointers are null or valid, and the outputs are writable.
+            let status = unsafe {
+                oneiromancer_analyze(
+                    code,
+                    base_url,
+                    c"test-model".as_ptr(),
+                    &raw mut outputs.json,
+                    &raw mut outputs.err,
+                )
+            };
+            (status, outputs)
+        }
+
+        /// Returns the error message, if any.
+        fn error(&self) -> Option<String> {
+            (!self.err.is_null()).then(|| {
+                // SAFETY: the error message is a string returned by the library.
+                unsafe { CStr::from_ptr(self.err) }
+                    .to_string_lossy()
+                    .into_owned()
+            })
+        }
+    }
+
+    impl Drop for Outputs {
+        fn drop(&mut self) {
+            for string in [self.json, self.err] {
+                // SAFETY: the outputs are null or strings returned by the library.
+                unsafe { oneiromancer_free_string(string) }
+            }
+        }
+    }
+
+    #[test]
+    fn analyze_rejects_null_and_non_utf8_arguments() {
+        let (null_status, null_code) = Outputs::analyze(ptr::null(), ptr::null());
+        assert_eq!(null_status, ONEIROMANCER_INVALID_ARGUMENT);
+        assert!(null_code.json.is_null(), "results returned");
+        assert_eq!(null_code.error().as_deref(), Some("`code` is null"));
+
+        let (utf8_status, bad_url) = Outputs::analyze(c"int main() {}".as_ptr(), c"\xff".as_ptr());
+        assert_eq!(utf8_status, ONEIROMANCER_INVALID_ARGUMENT);
+        assert!(
+            bad_url
+                .error()
+                .is_some_and(|err| err.starts_with("`base_url` is not valid UTF-8")),
+            "wrong error: {:?}",
+            bad_url.error()
+        );
+
+        // SAFETY: all pointers are valid, and a null `out_json` must be rejected.
+        let status = unsafe {
+            oneiromancer_analyze(
+                c"int main() {}".as_ptr(),
+                ptr::null(),
+                ptr::null(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+        assert_eq!(status, ONEIROMANCER_INVALID_ARGUMENT);
+    }
+
+    #[test]
+    fn analyze_reports_unreachable_server() {
+        let (status, outputs) =
+            Outputs::analyze(c"int main() {}".as_ptr(), c"http://127.0.0.1:1".as_ptr());
+
+        assert_eq!(status, ONEIROMANCER_ANALYSIS_FAILED);
+        assert!(outputs.json.is_null(), "results returned");
+        assert!(outputs.error().is_some(), "error message missing");
+    }
+
+    #[test]
+    fn version_matches_package() {
+        // SAFETY: the version is a static NUL-terminated string.
+        let version = unsafe { CStr::from_ptr(oneiromancer_version()) };
+
+        assert_eq!(version.to_str(), Ok(env!("CARGO_PKG_VERSION")));
+        // SAFETY: releasing null is allowed.
+        unsafe { oneiromancer_free_string(ptr::null_mut()) }
+    }
+
+    #[test]
+    fn panic_message_handles_string_payloads() {
+        assert_eq!(panic_message(&"boom"), "boom");
+        assert_eq!(panic_message(&"boom".to_owned()), "boom");
+        assert_eq!(panic_message(&()), "unknown panic");
+    }
+}
@@ -0,0 +1,740 @@
+//! Format analysis results as pseudocode comments.
+
+use std::fmt;
+#[cfg(feature = "cli")]
+use std::io::{self, IsTerminal as _};
+use std::str::FromStr;
+
+use serde::Deserialize;
+#[cfg(feature = "cli")]
+use ureq::http::Uri;
+
+#[cfg(feature = "format")]
+use crate::OneiromancerResults;
+#[cfg(feature = "cli")]
+use crate::signature::Signature;
+
+/// Default wrap width for generated comments.
+pub const COMMENT_WIDTH: usize = 76;
+
+/// Comment style used to render the function description.
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CommentStyle {
+    /// Phrack-style `/* ... */` block comment.
+    #[default]
+    Block,
+    /// `///`-style line comments.
+    Line,
+    /// Doxygen-style `/** ... */` block comment.
+    Doxygen,
+}
+
+#[cfg(feature = "format")]
+impl CommentStyle {
+    /// Returns the opening delimiter line, if any.
+    const fn opening(self) -> Option<&'static str> {
+        match self {
+            Self::Block => Some("/*"),
+            Self::Line => None,
+            Self::Doxygen => Some("/**"),
+        }
+    }
+
+    /// Returns the closing delimiter line, if any.
+    const fn closing(self) -> Option<&'static str> {
+        match self {
+            Self::Block | Self::Doxygen => Some(" */"),
+            Self::Line => None,
+        }
+    }
+
+    /// Returns the prefix of each content line.
+    const fn prefix(self) -> &'static str {
+        match self {
+            Self::Block | Self::Doxygen => " * ",
+            Self::Line => "/// ",
+        }
+    }
+}
+
+/// Wrap width for generated comments.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "WidthValue")]
+#[non_exhaustive]
+pub enum CommentWidth {
+    /// Detect the terminal width when stdout is a TTY, falling back to [`COMMENT_WIDTH`] otherwise
+    /// (always without the `cli` feature).
+    Auto,
+    /// Fixed number of columns.
+    Columns(usize),
+}
+
+impl CommentWidth {
+    /// Resolves the wrap width to a number of columns.
+    #[must_use]
+    pub fn columns(self) -> usize {
+        match self {
+            Self::Columns(columns) => columns,
+            Self::Auto => terminal_width().unwrap_or(COMMENT_WIDTH),
+        }
+    }
+}
+
+/// Returns the width of the terminal, if stdout is a terminal.
+#[cfg(feature = "cli")]
+fn terminal_width() -> Option<usize> {
+    let stdout = io::stdout();
+    if !stdout.is_terminal() {
+        return None;
+    }
+    terminal_size::terminal_size_of(&stdout).map(|(width, _)| usize::from(width.0))
+}
+
+/// Returns no terminal width: terminal detection requires the `cli` feature.
+#[cfg(not(feature = "cli"))]
+const fn terminal_width() -> Option<usize> {
+    None
+}
+
+impl Default for CommentWidth {
+    fn default() -> Self {
+        Self::Columns(COMMENT_WIDTH)
+    }
+}
+
+impl FromStr for CommentWidth {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(Self::Auto);
+        }
+        s.parse().map(Self::Columns).map_err(|err| {
+            format!("invalid width `{s}` ({err}, expected a number of columns or `auto`)")
+        })
+    }
+}
+
+impl fmt::Display for CommentWidth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Auto => write!(f, "auto"),
+            Self::Columns(columns) => write!(f, "{columns}"),
+        }
+    }
+}
+
+/// Raw width value as found in the configuration file (either a number or `"auto"`).
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum WidthValue {
+    /// Number of columns.
+    Columns(usize),
+    /// Width keyword.
+    Keyword(String),
+}
+
+impl TryFrom<WidthValue> for CommentWidth {
+    type Error = String;
+
+    fn try_from(value: WidthValue) -> Result<Self, Self::Error> {
+        match value {
+            WidthValue::Columns(columns) => Ok(Self::Columns(columns)),
+            WidthValue::Keyword(keyword) => keyword.parse(),
+        }
+    }
+}
+
+/// Options that control how the function description is rendered.
+#[derive(Debug, Clone, Default)]
+pub struct CommentOptions {
+    /// Comment style.
+    style: CommentStyle,
+    /// Wrap width.
+    width: CommentWidth,
+    /// Whether to generate Doxygen tags.
+    doxygen_tags: bool,
+}
+
+impl CommentOptions {
+    /// Creates new [`CommentOptions`] with default values.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the comment style.
+    #[must_use]
+    pub const fn style(mut self, style: CommentStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Sets the wrap width (defaults to [`COMMENT_WIDTH`]).
+    #[must_use]
+    pub const fn width(mut self, width: CommentWidth) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Sets whether to generate a Doxygen header with `@brief` and `@param` tags.
+    ///
+    /// The `block` style is rendered with Doxygen delimiters when tags are enabled.
+    #[must_use]
+    pub const fn doxygen_tags(mut self, doxygen_tags: bool) -> Self {
+        self.doxygen_tags = doxygen_tags;
+        self
+    }
+
+    /// Returns the effective comment style.
+    #[cfg(feature = "cli")]
+    pub(crate) const fn effective_style(&self) -> CommentStyle {
+        if self.doxygen_tags && matches!(self.style, CommentStyle::Block) {
+            CommentStyle::Doxygen
+        } else {
+            self.style
+        }
+    }
+
+    /// Returns the effective wrap width for the `prefix` of each comment line.
+    ///
+    /// The width includes the comment prefix. Widths too narrow to fit any text are raised to the
+    /// prefix length plus one column, so that words are broken instead of overflowing.
+    #[cfg(feature = "format")]
+    fn effective_width(&self, prefix: &str) -> usize {
+        self.width.columns().max(prefix.len().saturating_add(1))
+    }
+}
+
+/// Formats the header comment for the function defined in `pseudocode`, according to `options`.
+///
+/// When Doxygen tags are enabled, the function signature is parsed from `pseudocode` to generate
+/// `@param` tags, so `pseudocode` should be the one written to the output file (i.e., with
+/// renames applied, if any).
+#[cfg(feature = "cli")]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(style = ?options.style, doxygen_tags = options.doxygen_tags)
+    )
+)]
+pub fn format_header(
+    results: &OneiromancerResults,
+    pseudocode: &str,
+    options: &CommentOptions,
+) -> String {
+    if options.doxygen_tags {
+        format_doxygen(results, Signature::parse(pseudocode).as_ref(), options)
+    } else {
+        format_description(results, options)
+    }
+}
+
+/// Formats `results` as a comment in the style selected by `options`, wrapping to the configured width.
+#[cfg(feature = "format")]
+#[must_use]
+pub fn format_description(results: &OneiromancerResults, options: &CommentOptions) -> String {
+    let style = options.style;
+    let prefix = style.prefix();
+    let wrap = textwrap::Options::new(options.effective_width(prefix))
+        .initial_indent(prefix)
+        .subsequent_indent(prefix);
+
+    render(
+        style,
+        &[
+            format!("{prefix}{}()", results.function_name()),
+            prefix.trim_end().to_owned(),
+            textwrap::fill(results.comment(), &wrap),
+        ],
+    )
+}
+
+/// Formats `results` as a Doxygen header, with `@param` tags for each named parameter in
+/// `signature`.
+#[cfg(feature = "cli")]
+fn format_doxygen(
+    results: &OneiromancerResults,
+    signature: Option<&Signature>,
+    options: &CommentOptions,
+) -> String {
+    let style = options.effective_style();
+    let prefix = style.prefix();
+    let blank = prefix.trim_end().to_owned();
+    let wrap = textwrap::Options::new(options.effective_width(prefix))
+        .initial_indent(prefix)
+        .subsequent_indent(prefix);
+
+    let (brief, details) = split_first_sentence(results.comment());
+    let mut lines = vec![textwrap::fill(&format!("@brief {brief}"), &wrap)];
+    if !details.is_empty() {
+        lines.push(blank.clone());
+        lines.push(textwrap::fill(details, &wrap));
+    }
+
+    let params: Vec<String> = signature
+        .into_iter()
+        .flat_map(Signature::param_names)
+        .map(|name| format!("{prefix}@param {name}"))
+        .collect();
+    if !params.is_empty() {
+        lines.push(blank);
+        lines.extend(params);
+    }
+
+    render(style, &lines)
+}
+
+/// Renders `lines` (already prefixed) between the delimiters of `style`, followed by an empty line.
+#[cfg(feature = "format")]
+fn render(style: CommentStyle, lines: &[String]) -> String {
+    let mut out = String::new();
+    for line in style
+        .opening()
+        .into_iter()
+        .chain(lines.iter().map(String::as_str))
+        .chain(style.closing())
+    {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.push('\n');
+    out
+}
+
+/// Provenance field that can be included in the output file annotation.
+#[cfg(feature = "cli")]
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AnnotationField {
+    /// Oneiromancer version.
+    Version,
+    /// Model used for the analysis.
+    Model,
+    /// Date of the analysis (UTC).
+    Date,
+    /// Host of the Ollama base URL.
+    Host,
+}
+
+#[cfg(feature = "cli")]
+impl AnnotationField {
+    /// All provenance fields.
+    pub const ALL: [Self; 4] = [Self::Version, Self::Model, Self::Date, Self::Host];
+}
+
+/// Provenance information about how an output file was produced.
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone)]
+pub struct Provenance {
+    /// Oneiromancer version.
+    version: String,
+    /// Model used for the analysis.
+    model: String,
+    /// Date of the analysis (UTC).
+    date: String,
+    /// Host of the Ollama base URL, if it can be determined.
+    host: Option<String>,
+}
+
+#[cfg(feature = "cli")]
+impl Provenance {
+    /// Creates a new [`Provenance`] for an analysis made today with `model` at `baseurl`.
+    pub fn new(model: &str, baseurl: &str) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            model: model.to_owned(),
+            date: time::OffsetDateTime::now_utc().date().to_string(),
+            host: baseurl
+                .parse::<Uri>()
+                .ok()
+                .and_then(|uri| uri.host().map(str::to_owned)),
+        }
+    }
+}
+
+/// Formats `provenance` as a one-line annotation in `style`, including only the selected `fields`.
+#[cfg(feature = "cli")]
+pub fn format_provenance(
+    provenance: &Provenance,
+    fields: &[AnnotationField],
+    style: CommentStyle,
+) -> String {
+    let mut text = format!("generated by {}", env!("CARGO_PKG_NAME"));
+    for field in AnnotationField::ALL {
+        if !fields.contains(&field) {
+            continue;
+        }
+        let part = match field {
+            AnnotationField::Version => Some(format!(" {}", provenance.version)),
+            AnnotationField::Model => Some(format!(" using {}", provenance.model)),
+            AnnotationField::Date => Some(format!(" on {}", provenance.date)),
+            AnnotationField::Host => provenance.host.as_ref().map(|host| format!(" via {host}")),
+        };
+        text.push_str(&part.unwrap_or_default());
+    }
+
+    match style {
+        CommentStyle::Line => format!("// {text}\n"),
+        CommentStyle::Block | CommentStyle::Doxygen => format!("/* {text} */\n"),
+    }
+}
+
+/// Splits `text` after its first sentence, returning the sentence and the (trimmed) rest.
+#[cfg(feature = "cli")]
+fn split_first_sentence(text: &str) -> (&str, &str) {
+    let trimmed = text.trim();
+    trimmed
+        .char_indices()
+        .find(|&(pos, c)| {
+            matches!(c, '.' | '!' | '?')
+                && trimmed
+                    .get(pos.saturating_add(1)..)
+                    .is_none_or(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+        })
+        .and_then(|(pos, _)| {
+            let (first, rest) = trimmed.split_at_checked(pos.saturating_add(1))?;
+            Some((first, rest.trim_start()))
+        })
+        .unwrap_or((trimmed, ""))
+}
+
+#[cfg(test)]
+#[cfg_attr(
+    feature = "format",
+    expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")
+)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "format")]
+    const SAMPLE_RESULTS: &str = r#"{"function_name":"parse_header","comment":"Parses the packet header from the input buffer and validates its checksum, returning the payload length or a negative value on error.","variables":[]}"#;
+
+    #[test]
+    #[cfg(feature = "format")]
+    fn format_description_block_style_snapshot() -> anyhow::Result<()> {
+        let results: OneiromancerResults = serde_json::from_str(SAMPLE_RESULTS)?;
+
+        let desc = format_description(&results, &CommentOptions::new());
+
+        assert_eq!(
+            desc,
+            "/*\n \
+             * parse_header()\n \
+             *\n \
+             * Parses the packet header from the input buffer and validates its\n \
+             * checksum, returning the payload length or a negative value on error.\n \
+             */\n\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "format")]
+    fn format_description_line_style_snapshot() -> anyhow::Result<()> {
+        let results: OneiromancerResults = serde_json::from_str(SAMPLE_RESULTS)?;
+
+        let desc = format_description(&results, &CommentOptions::new().style(CommentStyle::Line));
+
+        assert_eq!(
+            desc,
+            "/// parse_header()\n\
+             ///\n\
+             /// Parses the packet header from the input buffer and validates its\n\
+             /// checksum, returning the payload length or a negative value on error.\n\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "format")]
+    fn format_description_doxygen_style_snapshot() -> anyhow::Result<()> {
+        let results: OneiromancerResults = serde_json::from_str(SAMPLE_RESULTS)?;
+
+        let desc = format_description(
+            &results,
+            &CommentOptions::new().style(CommentStyle::Doxygen),
+        );
+
+        assert_eq!(
+            desc,
+            "/**\n \
+             * parse_header()\n \
+             *\n \
+             * Parses the packet header from the input buffer and validates its\n \
+             * checksum, returning the payload length or a negative value on error.\n \
+             */\n\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "format")]
+    fn format_description_respects_width_for_every_style() -> anyhow::Result<()> {
+        let results: OneiromancerResults = serde_json::from_str(SAMPLE_RESULTS)?;
+
+        for style in [
+            CommentStyle::Block,
+            CommentStyle::Line,
+            CommentStyle::Doxygen,
+        ] {
+            let desc = format_description(&results, &CommentOptions::new().style(style));
+            for line in desc.lines() {
+                assert!(
+                    line.len() <= COMMENT_WIDTH,
+                    "{style:?} line exceeds {COMMENT_WIDTH} columns: {line:?}"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "format")]
+    fn format_description_wraps_to_custom_width() -> anyhow::Result<()> {
+        let results: OneiromancerResults = serde_json::from_str(SAMPLE_RESULTS)?;
+
+        let desc = format_description(
+            &results,
+            &CommentOptions::new().width(CommentWidth::Columns(40)),
+        );
+
+        for line in desc.lines() {
+            assert!(line.len() <= 40, "line exceeds 40 columns: {line:?}");
+        }
+        assert!(desc.lines().count() > 7, "comment was not wrapped");
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "format")]
+    fn format_description_wraps_non_ascii_comment_by_characters() -> anyhow::Result<()> {
+        let results: OneiromancerResults = serde_json::from_str(
+            r#"{"function_name":"parse_header","comment":"Analiza la cabecera del paquete desde el b\u00fafer de entrada y valida su suma de comprobaci\u00f3n, devolviendo la longitud \u00fatil o un valor negativo en caso de error. Per\u00f2 \u00e8 cos\u00ec gi\u00e0 pi\u00f9 facile?","variables":[]}"#,
+        )?;
+
+        let desc = format_description(
+            &results,
+            &CommentOptions::new().width(CommentWidth::Columns(30)),
+        );
+
+        for line in desc.lines() {
+            assert!(
+                line.chars().count() <= 30,
+                "line exceeds 30 characters: {line:?}"
+            );
+        }
+        assert!(
+            desc.lines().any(|line| line.len() > line.chars().count()),
+            "no multibyte characters in output"
+        );
+        assert_eq!(
+            desc.split_whitespace()
+                .filter(|word| *word != "*" && *word != "/*" && *word != "*/")
+                .skip(1)
+                .collect::<Vec<_>>(),
+            results.comment().split_whitespace().collect::<Vec<_>>(),
+            "words were altered"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "format")]
+    fn format_description_with_tiny_width_does_not_panic() -> anyhow::Result<()> {
+        let results: OneiromancerResults = serde_json::from_str(SAMPLE_RESULTS)?;
+
+        for columns in [0, 1, 3] {
+            let desc = format_description(
+                &results,
+                &CommentOptions::new().width(CommentWidth::Columns(columns)),
+            );
+            assert!(
+                desc.contains(" * P\n * a\n * r\n"),
+                "comment was not broken into single columns: {desc:?}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn format_header_generates_doxygen_tags() -> anyhow::Result<()> {
+        let results: OneiromancerResults = serde_json::from_str(
+            r#"{"function_name":"parse_header","comment":"Parses the packet header. Returns the payload length.","variables":[{"original_name":"a1","new_name":"buf"},{"original_name":"v3","new_name":"checksum"}]}"#,
+        )?;
+        let pseudocode = "int __fastcall sub_401000(char *buf, int a2)\n{\n  int checksum;\n}\n";
+
+        let desc = format_header(
+            &results,
+            pseudocode,
+            &CommentOptions::new().doxygen_tags(true),
+        );
+
+        assert_eq!(
+            desc,
+            "/**\n \
+             * @brief Parses the packet header.\n \
+             *\n \
+             * Returns the payload length.\n \
+             *\n \
+             * @param buf\n \
+             * @param a2\n \
+             */\n\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn format_header_with_doxygen_tags_and_line_style() -> anyhow::Result<()> {
+        let results: OneiromancerResults = serde_json::from_str(
+            r#"{"function_name":"init","comment":"Initializes the context","variables":[]}"#,
+        )?;
+
+        let desc = format_header(
+            &results,
+            "void init(void)\n{\n}\n",
+            &CommentOptions::new()
+                .style(CommentStyle::Line)
+                .doxygen_tags(true),
+        );
+
+        assert_eq!(desc, "/// @brief Initializes the context\n\n");
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn format_header_without_doxygen_tags_matches_description() -> anyhow::Result<()> {
+        let results: OneiromancerResults = serde_json::from_str(SAMPLE_RESULTS)?;
+        let options = CommentOptions::new();
+
+        assert_eq!(
+            format_header(&results, "int f(int a1)\n{\n}\n", &options),
+            format_description(&results, &options)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn split_first_sentence_handles_edge_cases() {
+        assert_eq!(
+            split_first_sentence("One. Two. Three."),
+            ("One.", "Two. Three.")
+        );
+        assert_eq!(
+            split_first_sentence("Version 1.2 is parsed"),
+            ("Version 1.2 is parsed", "")
+        );
+        assert_eq!(split_first_sentence("  Done!  "), ("Done!", ""));
+        assert_eq!(split_first_sentence(""), ("", ""));
+    }
+
+    /// Returns a [`Provenance`] with fixed values.
+    #[cfg(feature = "cli")]
+    fn sample_provenance() -> Provenance {
+        Provenance {
+            version: "0.9.1".to_owned(),
+            model: "aidapal".to_owned(),
+            date: "2026-06-01".to_owned(),
+            host: Some("gpu.example.com".to_owned()),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn format_provenance_with_all_fields() {
+        let annotation = format_provenance(
+            &sample_provenance(),
+            &AnnotationField::ALL,
+            CommentStyle::Block,
+        );
+
+        assert_eq!(
+            annotation,
+            "/* generated by oneiromancer 0.9.1 using aidapal on 2026-06-01 via gpu.example.com */\n"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn format_provenance_can_omit_host() {
+        let annotation = format_provenance(
+            &sample_provenance(),
+            &[
+                AnnotationField::Version,
+                AnnotationField::Model,
+                AnnotationField::Date,
+            ],
+            CommentStyle::Block,
+        );
+
+        assert_eq!(
+            annotation,
+            "/* generated by oneiromancer 0.9.1 using aidapal on 2026-06-01 */\n"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn format_provenance_follows_field_order_and_style() {
+        let annotation = format_provenance(
+            &sample_provenance(),
+            &[AnnotationField::Date, AnnotationField::Model],
+            CommentStyle::Line,
+        );
+
+        assert_eq!(
+            annotation,
+            "// generated by oneiromancer using aidapal on 2026-06-01\n"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn provenance_new_extracts_host_from_baseurl() {
+        let provenance = Provenance::new("aidapal", "http://127.0.0.1:11434/");
+
+        assert_eq!(provenance.host.as_deref(), Some("127.0.0.1"));
+        assert_eq!(provenance.version, env!("CARGO_PKG_VERSION"));
+
+        let no_host = Provenance::new("aidapal", "");
+        assert_eq!(no_host.host, None);
+    }
+
+    #[test]
+    fn comment_width_parses_numbers_and_auto() {
+        assert_eq!("100".parse(), Ok(CommentWidth::Columns(100)));
+        assert_eq!("auto".parse(), Ok(CommentWidth::Auto));
+        assert_eq!("AUTO".parse(), Ok(CommentWidth::Auto));
+        assert!(
+            "wide".parse::<CommentWidth>().is_err(),
+            "invalid width parsed"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn comment_width_auto_falls_back_when_not_a_tty() {
+        // Test output is captured, so stdout is not a terminal.
+        if !io::stdout().is_terminal() {
+            assert_eq!(CommentWidth::Auto.columns(), COMMENT_WIDTH);
+        }
+    }
+}
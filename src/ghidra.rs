@@ -0,0 +1,186 @@
+//! Generate Ghidra scripts that apply the suggestions of analyses to a Ghidra program.
+
+use crate::emit::{FunctionSuggestions, ScriptEmitter, format_python_table};
+
+/// Header of Ghidra scripts, before the table of suggestions.
+const SCRIPT_HEADER: &str =
+    "# Ghidra script generated by oneiromancer: applies the suggested function names, comments,
+# and variable renames to the current program. Run it from the Script Manager (Jython or PyGhidra).
+#
+# Functions are looked up by address, or by their current name if the address is unknown.
+# Suggestions that cannot be applied are reported at the end instead of stopping the script.
+#@category oneiromancer
+
+from __future__ import print_function, unicode_literals
+
+from ghidra.app.decompiler import DecompInterface
+from ghidra.program.model.pcode import HighFunctionDBUtil
+from ghidra.program.model.symbol import SourceType
+from ghidra.util.task import ConsoleTaskMonitor
+from java.lang import Throwable
+
+";
+
+/// Code of Ghidra scripts, after the table of suggestions.
+const SCRIPT_BODY: &str = r#"
+DECOMPILE_TIMEOUT = 60
+
+
+def attempt(failures, message, action):
+    """Runs action, appending message to failures if it returns False or raises."""
+    try:
+        if action() is False:
+            failures.append(message)
+    except (Exception, Throwable) as err:
+        failures.append("%s: %s" % (message, err))
+
+
+def find_function(function):
+    """Returns the function at the suggested address, or with the current name."""
+    if function["address"] is not None:
+        return getFunctionAt(toAddr(function["address"]))
+    if not function["name"]:
+        return None
+    matches = getGlobalFunctions(function["name"])
+    if not matches:
+        return None
+    return matches[0]
+
+
+def rename_variables(func, function, failures, label):
+    """Renames the local variables of func through the decompiler, reporting missing ones."""
+    decompiler = DecompInterface()
+    decompiler.openProgram(currentProgram)
+    try:
+        results = decompiler.decompileFunction(func, DECOMPILE_TIMEOUT, ConsoleTaskMonitor())
+        high = results.getHighFunction() if results is not None else None
+        if high is None:
+            failures.append("%s: cannot decompile function, variables not renamed" % label)
+            return
+        symbols = {}
+        for symbol in high.getLocalSymbolMap().getSymbols():
+            symbols[symbol.getName()] = symbol
+        for old, new in function["renames"]:
+            symbol = symbols.get(old)
+            if symbol is None:
+                failures.append("%s: variable %s not found" % (label, old))
+                continue
+            attempt(
+                failures,
+                "%s: cannot rename variable %s to %s" % (label, old, new),
+                lambda: HighFunctionDBUtil.updateDBVariable(
+                    symbol, new, None, SourceType.USER_DEFINED
+                ),
+            )
+    finally:
+        decompiler.dispose()
+
+
+def apply_suggestions(function, failures):
+    """Applies the suggestions for a function, appending the ones that fail to failures."""
+    label = function["name"] or function["new_name"]
+    func = find_function(function)
+    if func is None:
+        failures.append("%s: function not found" % label)
+        return
+    attempt(
+        failures,
+        "%s: cannot set plate comment" % label,
+        lambda: setPlateComment(func.getEntryPoint(), function["comment"]),
+    )
+    attempt(
+        failures,
+        "%s: cannot rename function to %s" % (label, function["new_name"]),
+        lambda: func.setName(function["new_name"], SourceType.USER_DEFINED),
+    )
+    if function["renames"]:
+        rename_variables(func, function, failures, label)
+
+
+def main():
+    failures = []
+    for function in FUNCTIONS:
+        attempt(
+            failures,
+            function["name"] or function["new_name"],
+            lambda: apply_suggestions(function, failures),
+        )
+    if failures:
+        print("[!] oneiromancer: %d suggestions not applied:" % len(failures))
+        for failure in failures:
+            print("    " + failure)
+    else:
+        print("[+] oneiromancer: all suggestions applied")
+
+
+main()
+"#;
+
+/// Emitter of Ghidra Python scripts that apply suggestions to a Ghidra program: they set the plate
+/// comment of each function, rename it with `Function.setName`, and rename its local variables
+/// with `HighFunctionDBUtil.updateDBVariable`, all as user-defined.
+///
+/// The script runs in both Jython and PyGhidra. Missing functions and variables are reported and
+/// skipped, and no exception escapes the script.
+#[derive(Debug, Clone, Copy)]
+pub struct GhidraScript;
+
+impl ScriptEmitter for GhidraScript {
+    fn description(&self) -> &'static str {
+        "Ghidra script"
+    }
+
+    fn format_script(&self, suggestions: &[FunctionSuggestions], _model: &str) -> String {
+        format!(
+            "{SCRIPT_HEADER}{}{SCRIPT_BODY}",
+            format_python_table(suggestions)
+        )
+    }
+}
+
+/// Formats a Ghidra Python script that applies the `suggestions` to a Ghidra program.
+///
+/// The script is the one emitted with `--emit-ghidra`: it sets the plate comment of each function,
+/// renames it, and renames its local variables through the decompiler.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use oneiromancer::{FunctionSuggestions, OneiromancerResults, format_ghidra_script};
+///
+/// let results: OneiromancerResults = serde_json::from_str(
+///     r#"{"function_name":"parse_header","comment":"Parses a header.","variables":[{"original_name":"local_10","new_name":"length"}]}"#,
+/// )?;
+/// let script = format_ghidra_script(&[FunctionSuggestions::new(&results, "FUN_00401000", None)]);
+///
+/// assert!(script.contains(r#"("local_10", "length")"#));
+/// # Ok(())
+/// # }
+/// ```
+#[must_use]
+pub fn format_ghidra_script(suggestions: &[FunctionSuggestions]) -> String {
+    GhidraScript.format_script(suggestions, "")
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use super::*;
+    use crate::emit::collect_suggestions;
+    use crate::report::FunctionReport;
+
+    #[test]
+    fn format_script_matches_golden_file() -> anyhow::Result<()> {
+        let functions: Vec<FunctionReport> =
+            serde_json::from_str(include_str!("../tests/data/emit/functions.json"))?;
+        let suggestions = collect_suggestions("", None, &functions, None, None);
+
+        assert_eq!(
+            GhidraScript.format_script(&suggestions, "test-model"),
+            include_str!("../tests/data/emit/ghidra.py")
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,225 @@
+//! Handle directories of pseudocode files extracted by haruspex.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+/// Name of the directory where improved pseudocode is saved, inside the haruspex directory.
+pub const IMPROVED_DIRNAME: &str = "improved";
+/// Name of the index file that maps original function names to recommended names.
+pub const INDEX_FILENAME: &str = "index.tsv";
+
+/// Pseudocode file extracted by haruspex, named `<index>_<function_name>.c`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HaruspexEntry {
+    /// Index of the function in the haruspex export.
+    index: usize,
+    /// Original function name, as parsed from the filename.
+    name: String,
+    /// Path of the pseudocode file.
+    path: PathBuf,
+}
+
+impl HaruspexEntry {
+    /// Gets the index of the function in the haruspex export.
+    #[must_use]
+    pub const fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Gets the original function name, as parsed from the filename.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the path of the pseudocode file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Reason why a file in a haruspex directory is skipped.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HaruspexSkip {
+    /// The filename does not follow the `<index>_<function_name>.c` convention.
+    #[error("filename does not match `<index>_<function_name>.c`")]
+    UnexpectedName,
+    /// The file is empty, as haruspex leaves it when decompilation fails.
+    #[error("empty pseudocode (decompilation failed?)")]
+    Empty,
+}
+
+/// Pseudocode files found in a haruspex directory by [`read_haruspex_dir`].
+#[derive(Debug, Default)]
+pub struct HaruspexDir {
+    /// Pseudocode files to analyze, in index order.
+    entries: Vec<HaruspexEntry>,
+    /// Files that are skipped, with the corresponding reasons.
+    skipped: Vec<(PathBuf, HaruspexSkip)>,
+}
+
+impl HaruspexDir {
+    /// Returns the pseudocode files to analyze, in index order.
+    #[must_use]
+    pub fn entries(&self) -> &[HaruspexEntry] {
+        &self.entries
+    }
+
+    /// Returns the files that are skipped, with the corresponding reasons.
+    #[must_use]
+    pub fn skipped(&self) -> &[(PathBuf, HaruspexSkip)] {
+        &self.skipped
+    }
+}
+
+/// Parses a haruspex filename (`<index>_<function_name>.c`) into the function index and name.
+///
+/// # Examples
+///
+/// ```
+/// use oneiromancer::parse_haruspex_filename;
+///
+/// assert_eq!(parse_haruspex_filename("12_sub_401000.c"), Some((12, "sub_401000")));
+/// assert_eq!(parse_haruspex_filename("main.c"), None);
+/// ```
+#[must_use]
+pub fn parse_haruspex_filename(filename: &str) -> Option<(usize, &str)> {
+    let (index, name) = filename.strip_suffix(".c")?.split_once('_')?;
+    if name.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((index.parse().ok()?, name))
+}
+
+/// Reads the `dirpath` directory written by haruspex, returning its pseudocode files in order.
+///
+/// Files with unexpected names and empty files are skipped; subdirectories (e.g., a previous
+/// [`IMPROVED_DIRNAME`] directory) are ignored.
+///
+/// # Errors
+///
+/// Returns [`io::Error`] in case the directory cannot be read.
+pub fn read_haruspex_dir(dirpath: impl AsRef<Path>) -> io::Result<HaruspexDir> {
+    let mut dir = HaruspexDir::default();
+    for result in fs::read_dir(dirpath)? {
+        let dirent = result?;
+        let path = dirent.path();
+        if dirent.file_type()?.is_dir() {
+            continue;
+        }
+
+        let filename = dirent.file_name();
+        let Some((index, name)) = filename.to_str().and_then(parse_haruspex_filename) else {
+            dir.skipped.push((path, HaruspexSkip::UnexpectedName));
+            continue;
+        };
+        if fs::read(&path)?.iter().all(u8::is_ascii_whitespace) {
+            dir.skipped.push((path, HaruspexSkip::Empty));
+            continue;
+        }
+        dir.entries.push(HaruspexEntry {
+            index,
+            name: name.to_owned(),
+            path,
+        });
+    }
+
+    dir.entries
+        .sort_by(|a, b| a.index.cmp(&b.index).then_with(|| a.name.cmp(&b.name)));
+    dir.skipped.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(dir)
+}
+
+/// Formats the index that maps original function names to the names recommended for them, as
+/// tab-separated values.
+pub fn format_index<'a>(
+    mapping: impl IntoIterator<Item = (&'a HaruspexEntry, &'a [String])>,
+) -> String {
+    let mut index = String::from("index\toriginal_name\trecommended_name\tfile\n");
+    for (entry, recommended) in mapping {
+        let filename = entry
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        _ = writeln!(
+            index,
+            "{}\t{}\t{}\t{filename}",
+            entry.index,
+            entry.name,
+            recommended.join(",")
+        );
+    }
+    index
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_haruspex_filename_accepts_haruspex_convention() {
+        assert_eq!(parse_haruspex_filename("0_main.c"), Some((0, "main")));
+        assert_eq!(
+            parse_haruspex_filename("42_sub_140001000.c"),
+            Some((42, "sub_140001000"))
+        );
+        assert_eq!(
+            parse_haruspex_filename("7_std__vector_int___push_back.c"),
+            Some((7, "std__vector_int___push_back"))
+        );
+    }
+
+    #[test]
+    fn parse_haruspex_filename_rejects_other_names() {
+        for filename in [
+            "main.c",
+            "_main.c",
+            "1_.c",
+            "x1_main.c",
+            "1_main.cpp",
+            "1_main.out",
+        ] {
+            assert_eq!(parse_haruspex_filename(filename), None, "{filename}");
+        }
+    }
+
+    #[test]
+    fn read_haruspex_dir_sorts_by_index_and_skips_empty_files() -> anyhow::Result<()> {
+        let tmpdir = tempfile::tempdir()?;
+        fs::write(tmpdir.path().join("10_last.c"), "int last() {}")?;
+        fs::write(tmpdir.path().join("2_second.c"), "int second() {}")?;
+        fs::write(tmpdir.path().join("1_failed.c"), "\n")?;
+        fs::write(tmpdir.path().join("notes.txt"), "notes")?;
+        fs::create_dir_all(tmpdir.path().join(IMPROVED_DIRNAME))?;
+
+        let dir = read_haruspex_dir(tmpdir.path())?;
+
+        assert_eq!(
+            dir.entries()
+                .iter()
+                .map(|entry| (entry.index(), entry.name()))
+                .collect::<Vec<_>>(),
+            [(2, "second"), (10, "last")]
+        );
+        assert_eq!(
+            dir.skipped(),
+            [
+                (tmpdir.path().join("1_failed.c"), HaruspexSkip::Empty),
+                (
+                    tmpdir.path().join("notes.txt"),
+                    HaruspexSkip::UnexpectedName
+                ),
+            ]
+        );
+
+        Ok(())
+    }
+}
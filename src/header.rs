@@ -0,0 +1,129 @@
+//! Generate C header files with the suggested function prototypes.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::format::format_header;
+use crate::signature::Signature;
+use crate::{CommentOptions, OneiromancerResults};
+
+/// Function declaration to be emitted in a C header file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Declaration {
+    /// Generated comment to be placed above the prototype.
+    comment: String,
+    /// Function prototype, with the suggested function name.
+    prototype: String,
+}
+
+impl Declaration {
+    /// Creates a [`Declaration`] for the function defined in `pseudocode` (with renames applied),
+    /// using the function name and comment suggested in `results`.
+    ///
+    /// Returns `None` if no function definition can be found in `pseudocode`.
+    pub fn new(
+        results: &OneiromancerResults,
+        pseudocode: &str,
+        options: &CommentOptions,
+    ) -> Option<Self> {
+        let signature = Signature::parse(pseudocode)?;
+        Some(Self {
+            comment: format_header(results, pseudocode, options)
+                .trim_end()
+                .to_owned(),
+            prototype: signature.prototype(results.function_name()),
+        })
+    }
+}
+
+/// Formats a C header file that declares all `declarations`, with include guards derived from
+/// `filepath`.
+pub fn format_c_header(filepath: &Path, declarations: &[Declaration]) -> String {
+    let guard = include_guard(filepath);
+    let mut header = format!("#ifndef {guard}\n#define {guard}\n");
+    for declaration in declarations {
+        header.push('\n');
+        header.push_str(&declaration.comment);
+        header.push('\n');
+        header.push_str(&declaration.prototype);
+        header.push_str(";\n");
+    }
+    _ = writeln!(header, "\n#endif /* {guard} */");
+    header
+}
+
+/// Derives an include guard name from `filepath` (e.g., `out/hello.h` -> `HELLO_H`).
+fn include_guard(filepath: &Path) -> String {
+    let filename = filepath
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let guard: String = filename
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    match guard.chars().next() {
+        None => "ONEIROMANCER_H".to_owned(),
+        Some(c) if c.is_ascii_digit() => format!("_{guard}"),
+        Some(_) => guard,
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn include_guard_is_derived_from_filename() {
+        assert_eq!(include_guard(Path::new("out/hello.h")), "HELLO_H");
+        assert_eq!(include_guard(Path::new("my-lib.v2.h")), "MY_LIB_V2_H");
+        assert_eq!(include_guard(Path::new("1st.h")), "_1ST_H");
+    }
+
+    #[test]
+    fn format_c_header_declares_renamed_prototypes() -> anyhow::Result<()> {
+        let results: OneiromancerResults = serde_json::from_str(
+            r#"{"function_name":"sum","comment":"Adds two numbers.","variables":[]}"#,
+        )?;
+        let declaration = Declaration::new(
+            &results,
+            "int __fastcall sub_401000(int a, int b)\n{\n  return a + b;\n}\n",
+            &CommentOptions::default(),
+        )
+        .ok_or_else(|| anyhow::anyhow!("no signature"))?;
+
+        let header = format_c_header(Path::new("math.h"), &[declaration]);
+
+        assert_eq!(
+            header,
+            "#ifndef MATH_H\n#define MATH_H\n\n\
+             /*\n * sum()\n *\n * Adds two numbers.\n */\n\
+             int __fastcall sum(int a, int b);\n\n\
+             #endif /* MATH_H */\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn declaration_without_definition_returns_none() -> anyhow::Result<()> {
+        let results: OneiromancerResults = serde_json::from_str(
+            r#"{"function_name":"sum","comment":"Adds two numbers.","variables":[]}"#,
+        )?;
+
+        assert!(
+            Declaration::new(&results, "int v1 = 0;", &CommentOptions::default()).is_none(),
+            "declaration created"
+        );
+
+        Ok(())
+    }
+}
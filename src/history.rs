@@ -0,0 +1,497 @@
+//! Keep a history of analyses in a JSON Lines file, to find out what the model said in the past.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead as _, BufReader, Write as _};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use time::OffsetDateTime;
+
+use crate::cache::content_hash;
+use crate::state::canonical;
+use crate::{AnalysisReport, OneiromancerResults};
+
+/// Name of the history file in the default data directory.
+const HISTORY_FILENAME: &str = "history.jsonl";
+/// Length of entry identifiers, in hex digits.
+const ENTRY_ID_LEN: usize = 12;
+
+/// Analysis recorded in the history.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    /// Identifier of the entry.
+    id: String,
+    /// Time of the analysis, in milliseconds since the Unix epoch.
+    timestamp_ms: u64,
+    /// Canonical path of the input file (`-` for stdin).
+    input: PathBuf,
+    /// SHA-256 hash of the content of the input file.
+    hash: String,
+    /// Model used for the analysis.
+    model: String,
+    /// Function name, as it appears in the pseudocode, if analyzed function by function.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    function: Option<String>,
+    /// Recommended function name.
+    name: String,
+    /// Function description.
+    comment: String,
+    /// Variable renaming suggestions, from original to new name.
+    #[serde(default)]
+    renames: BTreeMap<String, String>,
+}
+
+impl HistoryEntry {
+    /// Creates a new [`HistoryEntry`] of the analysis of the `input` file with `hash` content
+    /// (or of its `function`) with `model`, returning `results`.
+    fn new(
+        input: &Path,
+        hash: &str,
+        model: &str,
+        function: Option<&str>,
+        results: &OneiromancerResults,
+    ) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut id = content_hash(
+            format!(
+                "{}-{}-{}-{}",
+                now.as_nanos(),
+                process::id(),
+                input.display(),
+                function.unwrap_or_default()
+            )
+            .as_bytes(),
+        );
+        id.truncate(ENTRY_ID_LEN);
+        Self {
+            id,
+            timestamp_ms: u64::try_from(now.as_millis()).unwrap_or(u64::MAX),
+            input: input.to_path_buf(),
+            hash: hash.to_owned(),
+            model: model.to_owned(),
+            function: function.map(str::to_owned),
+            name: results.function_name().to_owned(),
+            comment: results.comment().to_owned(),
+            renames: results
+                .variables()
+                .iter()
+                .map(|variable| {
+                    (
+                        variable.original_name().to_owned(),
+                        variable.new_name().to_owned(),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Gets the identifier of the entry.
+    #[must_use]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Gets the canonical path of the input file.
+    #[must_use]
+    pub fn input(&self) -> &Path {
+        &self.input
+    }
+
+    /// Gets the function name, as it appears in the pseudocode, if analyzed function by function.
+    #[must_use]
+    pub fn function(&self) -> Option<&str> {
+        self.function.as_deref()
+    }
+
+    /// Gets the recommended function name.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the time of the analysis as `YYYY-MM-DD HH:MM` (UTC).
+    fn date(&self) -> String {
+        i64::try_from(Duration::from_millis(self.timestamp_ms).as_secs())
+            .ok()
+            .and_then(|secs| OffsetDateTime::from_unix_timestamp(secs).ok())
+            .map_or_else(
+                || "unknown".to_owned(),
+                |time| format!("{} {:02}:{:02}", time.date(), time.hour(), time.minute()),
+            )
+    }
+
+    /// Returns the input path, followed by the function name (if any).
+    fn target(&self) -> String {
+        let input = self.input.display();
+        self.function.as_deref().map_or_else(
+            || input.to_string(),
+            |function| format!("{input}::{function}"),
+        )
+    }
+}
+
+/// History of analyses, appended to a JSON Lines file.
+///
+/// The history is best effort: failures in recording analyses are logged without stopping them,
+/// and a missing or unreadable history file, as well as malformed lines (e.g., truncated by an
+/// interrupted run), are treated as no entries.
+#[derive(Debug, Clone)]
+pub struct History {
+    /// Path of the history file.
+    filepath: PathBuf,
+}
+
+impl History {
+    /// Creates a new [`History`] stored in the `filepath` file, which is created on the first
+    /// recorded analysis.
+    #[must_use]
+    pub fn new(filepath: impl Into<PathBuf>) -> Self {
+        Self {
+            filepath: filepath.into(),
+        }
+    }
+
+    /// Returns the default history file path (e.g., `~/.local/share/oneiromancer/history.jsonl`).
+    #[must_use]
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join(env!("CARGO_PKG_NAME")).join(HISTORY_FILENAME))
+    }
+
+    /// Gets the path of the history file.
+    #[must_use]
+    pub fn filepath(&self) -> &Path {
+        &self.filepath
+    }
+
+    /// Records the successful analysis in `report` of an input file with `hash` content, as one
+    /// entry per analyzed function. Failures are logged, but do not stop the analysis.
+    pub(crate) fn record(&self, report: &AnalysisReport, hash: &str) {
+        let input = canonical(report.input());
+        let mut entries = Vec::new();
+        if let Some(results) = report.results() {
+            entries.push(HistoryEntry::new(
+                &input,
+                hash,
+                report.model(),
+                None,
+                results,
+            ));
+        }
+        for function in report.functions() {
+            if let Some(results) = function.results() {
+                entries.push(HistoryEntry::new(
+                    &input,
+                    hash,
+                    report.model(),
+                    Some(function.name()),
+                    results,
+                ));
+            }
+        }
+        if let Err(err) = self.append(&entries) {
+            log::warn!(
+                "Failed to update history file `{}`: {err}",
+                self.filepath.display()
+            );
+        }
+    }
+
+    /// Appends `entries` to the history file with a single write, so that concurrent analyses do
+    /// not interleave their lines.
+    fn append(&self, entries: &[HistoryEntry]) -> io::Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let mut lines = String::new();
+        for entry in entries {
+            _ = writeln!(lines, "{}", serde_json::to_string(entry)?);
+        }
+        if let Some(dir) = self
+            .filepath
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+        {
+            fs::create_dir_all(dir)?;
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.filepath)?
+            .write_all(lines.as_bytes())
+    }
+
+    /// Returns all entries, oldest first, or only those of the `input` file if specified.
+    #[must_use]
+    pub fn entries(&self, input: Option<&Path>) -> Vec<HistoryEntry> {
+        let wanted = input.map(canonical);
+        let file = match File::open(&self.filepath) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Vec::new(),
+            Err(err) => {
+                log::warn!(
+                    "Failed to read history file `{}`: {err}",
+                    self.filepath.display()
+                );
+                return Vec::new();
+            }
+        };
+        BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str::<HistoryEntry>(&line).ok())
+            .filter(|entry| wanted.as_ref().is_none_or(|path| entry.input == *path))
+            .collect()
+    }
+
+    /// Finds the entry whose identifier is `id`, or starts with `id`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HistoryError`] in case no entry, or more than one entry, matches `id`.
+    pub fn find(&self, id: &str) -> Result<HistoryEntry, HistoryError> {
+        let mut matches = self
+            .entries(None)
+            .into_iter()
+            .filter(|entry| !id.is_empty() && entry.id.starts_with(id));
+        match (matches.next(), matches.next()) {
+            (Some(entry), None) => Ok(entry),
+            (None, _) => Err(HistoryError::NotFound(id.to_owned())),
+            (Some(_), Some(_)) => Err(HistoryError::Ambiguous(id.to_owned())),
+        }
+    }
+}
+
+/// History error type.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum HistoryError {
+    /// No entry matches the identifier.
+    #[error("No history entry matches `{0}`")]
+    NotFound(String),
+    /// More than one entry matches the identifier.
+    #[error("More than one history entry matches `{0}`, use a longer identifier")]
+    Ambiguous(String),
+}
+
+/// Formats `entries` as a table with one line per entry (identifier, date, model, recommended
+/// name, and input file).
+#[must_use]
+pub fn format_history_list(entries: &[HistoryEntry]) -> String {
+    let dates: Vec<String> = entries.iter().map(HistoryEntry::date).collect();
+    let model_width = entries
+        .iter()
+        .map(|entry| entry.model.chars().count())
+        .chain(["Model".len()])
+        .max()
+        .unwrap_or_default();
+    let name_width = entries
+        .iter()
+        .map(|entry| entry.name.chars().count())
+        .chain(["Name".len()])
+        .max()
+        .unwrap_or_default();
+
+    let mut table = format!(
+        "{:<ENTRY_ID_LEN$}  {:<16}  {:<model_width$}  {:<name_width$}  Input\n",
+        "ID", "Date (UTC)", "Model", "Name"
+    );
+    for (entry, date) in entries.iter().zip(&dates) {
+        _ = writeln!(
+            table,
+            "{:<ENTRY_ID_LEN$}  {date:<16}  {:<model_width$}  {:<name_width$}  {}",
+            entry.id,
+            entry.model,
+            entry.name,
+            entry.target()
+        );
+    }
+    table
+}
+
+/// Formats the details of `entry`: input file, model, recommended name, description, and variable
+/// renaming suggestions.
+#[must_use]
+pub fn format_history_entry(entry: &HistoryEntry) -> String {
+    let mut details = format!(
+        "ID:      {}\nDate:    {} UTC\nInput:   {}\nHash:    {}\nModel:   {}\nName:    {}\n\n{}\n",
+        entry.id,
+        entry.date(),
+        entry.target(),
+        entry.hash,
+        entry.model,
+        entry.name,
+        entry.comment
+    );
+    if !entry.renames.is_empty() {
+        details.push_str("\nRenames:\n");
+        for (original, new) in &entry.renames {
+            _ = writeln!(details, "    {original}\t-> {new}");
+        }
+    }
+    details
+}
+
+/// Formats the differences between the `old` and `new` analyses.
+///
+/// The recommended names, descriptions, and variable renaming suggestions are compared, and each
+/// rename is marked as added (`+`), removed (`-`), changed (`~`), or unchanged (` `).
+#[must_use]
+pub fn format_history_diff(old: &HistoryEntry, new: &HistoryEntry) -> String {
+    let mut diff = format!(
+        "--- {} {} ({}, {} UTC)\n+++ {} {} ({}, {} UTC)\n",
+        old.id,
+        old.target(),
+        old.model,
+        old.date(),
+        new.id,
+        new.target(),
+        new.model,
+        new.date()
+    );
+    let same_function = old.input == new.input && old.function == new.function;
+    match (same_function, old.hash == new.hash) {
+        (false, _) => diff.push_str("[!] The entries refer to different functions\n"),
+        (true, false) => diff.push_str("[*] The input file changed between the analyses\n"),
+        (true, true) => (),
+    }
+
+    diff.push_str("\nName:\n");
+    push_change(&mut diff, &old.name, &new.name);
+    diff.push_str("\nComment:\n");
+    push_change(&mut diff, &old.comment, &new.comment);
+    diff.push_str("\nRenames:\n");
+    let originals: BTreeSet<&String> = old.renames.keys().chain(new.renames.keys()).collect();
+    for original in originals {
+        match (old.renames.get(original), new.renames.get(original)) {
+            (Some(before), Some(after)) if before == after => {
+                _ = writeln!(diff, "      {original}\t-> {after}");
+            }
+            (Some(before), Some(after)) => {
+                _ = writeln!(diff, "    ~ {original}\t-> {before} => {after}");
+            }
+            (Some(before), None) => _ = writeln!(diff, "    - {original}\t-> {before}"),
+            (None, Some(after)) => _ = writeln!(diff, "    + {original}\t-> {after}"),
+            (None, None) => (),
+        }
+    }
+    diff
+}
+
+/// Appends the change from `old` to `new` to `diff`, or `old` alone if unchanged.
+fn push_change(diff: &mut String, old: &str, new: &str) {
+    if old == new {
+        _ = writeln!(diff, "      {old}");
+    } else {
+        _ = writeln!(diff, "    - {old}\n    + {new}");
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use super::*;
+
+    /// Returns an entry of the `function` function in `a.c`, recorded at `timestamp_ms`.
+    fn entry(id: &str, timestamp_ms: u64, name: &str, renames: &[(&str, &str)]) -> HistoryEntry {
+        HistoryEntry {
+            id: id.to_owned(),
+            timestamp_ms,
+            input: PathBuf::from("/re/a.c"),
+            hash: "abc".to_owned(),
+            model: "aidapal".to_owned(),
+            function: Some("sub_401000".to_owned()),
+            name: name.to_owned(),
+            comment: "Parses a packet.".to_owned(),
+            renames: renames
+                .iter()
+                .map(|&(original, new)| (original.to_owned(), new.to_owned()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn history_records_entries_and_skips_corrupt_lines() -> anyhow::Result<()> {
+        let tmpdir = tempfile::tempdir()?;
+        let input = tmpdir.path().join("a.c");
+        fs::write(&input, "int main() { int v1; }")?;
+        let history = History::new(tmpdir.path().join("nested").join(HISTORY_FILENAME));
+        assert!(history.entries(None).is_empty(), "missing file has entries");
+
+        let results: OneiromancerResults = serde_json::from_str(
+            r#"{"function_name":"main","comment":"Entry point.","variables":[{"original_name":"v1","new_name":"counter"}]}"#,
+        )?;
+        let report = AnalysisReport::succeeded(
+            &input,
+            None,
+            "aidapal",
+            Duration::ZERO,
+            Some(results),
+            Vec::new(),
+            None,
+        );
+        history.record(&report, "abc");
+        let mut file = OpenOptions::new().append(true).open(history.filepath())?;
+        writeln!(file, "{{\"id\":\"truncated")?;
+        history.record(&report, "def");
+
+        let entries = history.entries(Some(&input));
+        assert_eq!(entries.len(), 2);
+        let first = entries
+            .first()
+            .cloned()
+            .unwrap_or_else(|| entry("", 0, "", &[]));
+        assert_eq!(first.name(), "main");
+        assert_eq!(first.input(), canonical(&input));
+        assert_eq!(first.renames.get("v1").map(String::as_str), Some("counter"));
+        assert!(history.entries(Some(Path::new("b.c"))).is_empty());
+        assert_eq!(history.find(first.id())?, first);
+        assert!(matches!(history.find(""), Err(HistoryError::NotFound(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn history_diff_marks_changed_names_and_renames() {
+        let old = entry("aaa", 0, "parse_packet", &[("v1", "len"), ("v2", "buf")]);
+        let new = entry(
+            "bbb",
+            86_400_000,
+            "parse_msg",
+            &[("v1", "len"), ("v2", "data"), ("v3", "i")],
+        );
+
+        assert_eq!(
+            format_history_diff(&old, &new),
+            "--- aaa /re/a.c::sub_401000 (aidapal, 1970-01-01 00:00 UTC)\n\
+             +++ bbb /re/a.c::sub_401000 (aidapal, 1970-01-02 00:00 UTC)\n\
+             \n\
+             Name:\n    - parse_packet\n    + parse_msg\n\
+             \n\
+             Comment:\n      Parses a packet.\n\
+             \n\
+             Renames:\n      v1\t-> len\n    ~ v2\t-> buf => data\n    + v3\t-> i\n"
+        );
+    }
+
+    #[test]
+    fn history_list_aligns_columns() {
+        let entries = [
+            entry("aaaaaaaaaaaa", 0, "parse_packet", &[]),
+            entry("bbbbbbbbbbbb", 60_000, "f", &[]),
+        ];
+
+        assert_eq!(
+            format_history_list(&entries),
+            "ID            Date (UTC)        Model    Name          Input\n\
+             aaaaaaaaaaaa  1970-01-01 00:00  aidapal  parse_packet  /re/a.c::sub_401000\n\
+             bbbbbbbbbbbb  1970-01-01 00:01  aidapal  f             /re/a.c::sub_401000\n"
+        );
+    }
+}
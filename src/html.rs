@@ -0,0 +1,391 @@
+//! Render the results of analyses as self-contained HTML reports, with a before/after view of the
+//! pseudocode.
+
+use std::fmt::Write as _;
+use std::path::{Component, Path};
+
+use crate::OneiromancerResults;
+use crate::lexer::{TokenKind, tokenize};
+use crate::sarif::percent_encode;
+
+/// Name of the HTML file that links all reports of a batch.
+pub const HTML_INDEX_FILENAME: &str = "index.html";
+
+/// Template of a report, with `{{placeholders}}` for escaped HTML fragments.
+const REPORT_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{{title}}</title>
+<style>
+{{style}}
+</style>
+</head>
+<body>
+<h1>{{title}}</h1>
+{{functions}}
+<section class="diff">
+<figure>
+<figcaption>Original pseudocode</figcaption>
+<pre><code>{{before}}</code></pre>
+</figure>
+<figure>
+<figcaption>Improved pseudocode</figcaption>
+<pre><code>{{after}}</code></pre>
+</figure>
+</section>
+<footer>Generated by oneiromancer {{version}}</footer>
+</body>
+</html>
+"#;
+
+/// Template of the index of the reports of a batch, with `{{placeholders}}` for escaped HTML
+/// fragments.
+const INDEX_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Oneiromancer reports</title>
+<style>
+{{style}}
+</style>
+</head>
+<body>
+<h1>Oneiromancer reports</h1>
+<ul class="index">
+{{entries}}</ul>
+<footer>Generated by oneiromancer {{version}}</footer>
+</body>
+</html>
+"#;
+
+/// Inline style sheet shared by reports and indexes.
+const STYLE: &str = "body { font-family: system-ui, sans-serif; margin: 2em auto; max-width: 110em; padding: 0 1em; color: #222; }
+h2 code, td code { background: #f3f3f3; padding: 0 .2em; }
+.description { white-space: pre-line; }
+table { border-collapse: collapse; margin: 1em 0; }
+th, td { border: 1px solid #ccc; padding: .3em .6em; text-align: left; }
+.diff { display: grid; grid-template-columns: 1fr 1fr; gap: 1em; }
+@media (max-width: 60em) { .diff { grid-template-columns: 1fr; } }
+figure { margin: 0; min-width: 0; }
+figcaption { font-weight: bold; margin-bottom: .3em; }
+pre { background: #f8f8f8; border: 1px solid #ddd; overflow-x: auto; padding: .6em; }
+mark.old { background: #fdd; }
+mark.new { background: #dfd; }
+footer { color: #888; font-size: .8em; margin-top: 2em; }
+.index li { margin: .3em 0; }
+.index .path { color: #888; font-size: .9em; }";
+
+/// Formats a self-contained HTML report of the analyses in `results`, with the `original`
+/// pseudocode and the `improved` pseudocode side by side (stacked on narrow screens).
+///
+/// Each analysis gets a heading with the recommended function name, its description, and a table
+/// of variable renames. Renamed identifiers are highlighted in both views, with a `title` that
+/// shows the other name. All model-generated and code content is escaped.
+#[must_use]
+pub fn format_html_report(
+    results: &[&OneiromancerResults],
+    original: &str,
+    improved: &str,
+) -> String {
+    let names: Vec<&str> = results
+        .iter()
+        .map(|analysis| analysis.function_name())
+        .collect();
+    let title = if names.is_empty() {
+        "Oneiromancer report".to_owned()
+    } else {
+        escape_html(&names.join(", "))
+    };
+
+    let mut functions = String::new();
+    for analysis in results {
+        _ = writeln!(
+            functions,
+            "<article class=\"function\">\n<h2><code>{}</code></h2>\n<p class=\"description\">{}</p>",
+            escape_html(analysis.function_name()),
+            escape_html(analysis.comment().trim())
+        );
+        let renames = renamed_variables(analysis);
+        if !renames.is_empty() {
+            functions.push_str(
+                "<table>\n<thead><tr><th>Variable</th><th>Suggested name</th></tr></thead>\n<tbody>\n",
+            );
+            for (original_name, new_name) in renames {
+                _ = writeln!(
+                    functions,
+                    "<tr><td><code>{}</code></td><td><code>{}</code></td></tr>",
+                    escape_html(original_name),
+                    escape_html(new_name)
+                );
+            }
+            functions.push_str("</tbody>\n</table>\n");
+        }
+        functions.push_str("</article>\n");
+    }
+
+    let renames: Vec<(&str, &str)> = results
+        .iter()
+        .flat_map(|analysis| renamed_variables(analysis))
+        .collect();
+    let swapped: Vec<(&str, &str)> = renames.iter().map(|rename| (rename.1, rename.0)).collect();
+    render(
+        REPORT_TEMPLATE,
+        &[
+            ("title", &title),
+            ("style", STYLE),
+            ("functions", &functions),
+            (
+                "before",
+                &highlight(original, &renames, "old", "Renamed to"),
+            ),
+            (
+                "after",
+                &highlight(improved, &swapped, "new", "Renamed from"),
+            ),
+            ("version", env!("CARGO_PKG_VERSION")),
+        ],
+    )
+}
+
+/// Formats the HTML index of the reports of a batch, linking each report (with a path relative to
+/// the index) with the names recommended for its functions as link text.
+#[must_use]
+pub fn format_html_index<'a>(
+    reports: impl IntoIterator<Item = (&'a Path, &'a [String])>,
+) -> String {
+    let mut entries = String::new();
+    for (path, names) in reports {
+        let text = if names.is_empty() {
+            path.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        } else {
+            names.join(", ")
+        };
+        _ = writeln!(
+            entries,
+            "<li><a href=\"{}\">{}</a> <span class=\"path\">{}</span></li>",
+            escape_html(&relative_href(path)),
+            escape_html(&text),
+            escape_html(&path.to_string_lossy())
+        );
+    }
+    render(
+        INDEX_TEMPLATE,
+        &[
+            ("style", STYLE),
+            ("entries", &entries),
+            ("version", env!("CARGO_PKG_VERSION")),
+        ],
+    )
+}
+
+/// Returns the `(original, new)` names of the variables that `analysis` suggests to rename.
+fn renamed_variables(analysis: &OneiromancerResults) -> Vec<(&str, &str)> {
+    analysis
+        .variables()
+        .iter()
+        .filter(|variable| variable.original_name() != variable.new_name())
+        .map(|variable| (variable.original_name(), variable.new_name()))
+        .collect()
+}
+
+/// Escapes `code` as HTML, wrapping the identifiers that match the first name of a pair in
+/// `names` in a `<mark>` element of the `class` class, titled with the `label` and the second
+/// name of the pair.
+fn highlight(code: &str, names: &[(&str, &str)], class: &str, label: &str) -> String {
+    let mut highlighted = String::with_capacity(code.len());
+    for token in tokenize(code) {
+        let text = token.text(code);
+        let other = (token.kind == TokenKind::Identifier)
+            .then(|| names.iter().find(|pair| pair.0 == text))
+            .flatten();
+        if let Some(pair) = other {
+            _ = write!(
+                highlighted,
+                "<mark class=\"{class}\" title=\"{label} {}\">{}</mark>",
+                escape_html(pair.1),
+                escape_html(text)
+            );
+        } else {
+            highlighted.push_str(&escape_html(text));
+        }
+    }
+    highlighted
+}
+
+/// Fills the `{{key}}` placeholders in `template` with the corresponding `values` in a single
+/// pass, so that placeholders within values are left alone. Unknown placeholders are kept.
+fn render(template: &str, values: &[(&str, &str)]) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let (before, placeholder) = rest.split_at(start);
+        rendered.push_str(before);
+        let value = placeholder.find("}}").and_then(|end| {
+            let key = placeholder.get(2..end)?;
+            let found = values.iter().find(|pair| pair.0 == key)?;
+            Some((found.1, end.saturating_add(2)))
+        });
+        let (text, len) = value.unwrap_or(("{{", 2));
+        rendered.push_str(text);
+        rest = placeholder.get(len..).unwrap_or_default();
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/// Escapes the characters of `text` that are significant in HTML text and attribute values.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Returns the relative URL of the `path` relative path, with reserved characters percent-encoded.
+pub fn relative_href(path: &Path) -> String {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(segment) => Some(percent_encode(&segment.to_string_lossy())),
+            Component::ParentDir => Some("..".to_owned()),
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use super::*;
+
+    /// Parses results with `function_name`, `comment`, and the `(original, new)` `variables`.
+    fn results(
+        function_name: &str,
+        comment: &str,
+        variables: &[(&str, &str)],
+    ) -> serde_json::Result<OneiromancerResults> {
+        serde_json::from_value(serde_json::json!({
+            "function_name": function_name,
+            "comment": comment,
+            "variables": variables
+                .iter()
+                .map(|variable| serde_json::json!({
+                    "original_name": variable.0,
+                    "new_name": variable.1,
+                }))
+                .collect::<Vec<_>>(),
+        }))
+    }
+
+    #[test]
+    fn format_html_report_highlights_renamed_identifiers() -> anyhow::Result<()> {
+        let analysis = results("init_ctx", "Initializes.", &[("a1", "ctx"), ("v1", "v1")])?;
+
+        let report = format_html_report(
+            &[&analysis],
+            "int f(int a1)\n{\n  // a1\n  return a1 + v1;\n}\n",
+            "int f(int ctx)\n{\n  // a1\n  return ctx + v1;\n}\n",
+        );
+        assert!(report.starts_with("<!DOCTYPE html>\n"), "not a document");
+        assert!(
+            report.contains("<title>init_ctx</title>"),
+            "missing title: {report}"
+        );
+        assert!(
+            report.contains(
+                "<pre><code>int f(int <mark class=\"old\" title=\"Renamed to ctx\">a1</mark>)\n{\n  // a1\n  return <mark class=\"old\" title=\"Renamed to ctx\">a1</mark> + v1;"
+            ),
+            "original not highlighted: {report}"
+        );
+        assert!(
+            report
+                .contains("return <mark class=\"new\" title=\"Renamed from a1\">ctx</mark> + v1;"),
+            "improved not highlighted: {report}"
+        );
+        assert_eq!(report.matches("<mark ").count(), 4, "unexpected highlights");
+        assert!(
+            report.contains("<tr><td><code>a1</code></td><td><code>ctx</code></td></tr>"),
+            "missing rename table"
+        );
+        assert!(
+            !report.contains("src=") && !report.contains("href="),
+            "external assets"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn format_html_report_escapes_model_output_and_code() -> anyhow::Result<()> {
+        let analysis = results(
+            "</title><script>alert(1)</script>",
+            "Compares a < b && b > \"c\" {{after}}.",
+            &[("v1", "x\"><img onerror=1>")],
+        )?;
+
+        let report = format_html_report(
+            &[&analysis],
+            "if (v1 < 2) puts(\"<b>\");",
+            "if (x < 2) puts(\"<b>\");",
+        );
+        assert!(!report.contains("<script>"), "script injected: {report}");
+        assert!(!report.contains("<img"), "element injected: {report}");
+        assert!(!report.contains("<b>"), "code not escaped: {report}");
+        assert!(
+            report.contains("<title>&lt;/title&gt;&lt;script&gt;alert(1)&lt;/script&gt;</title>"),
+            "title not escaped: {report}"
+        );
+        assert!(
+            report.contains(
+                "<p class=\"description\">Compares a &lt; b &amp;&amp; b &gt; &quot;c&quot; {{after}}.</p>"
+            ),
+            "description not escaped: {report}"
+        );
+        assert!(
+            report.contains(
+                "if (<mark class=\"old\" title=\"Renamed to x&quot;&gt;&lt;img onerror=1&gt;\">v1</mark> &lt; 2) puts(&quot;&lt;b&gt;&quot;);"
+            ),
+            "attribute not escaped: {report}"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn format_html_index_links_reports_by_recommended_names() {
+        let names = ["init_ctx".to_owned(), "a<b".to_owned()];
+
+        let index = format_html_index([
+            (Path::new("dir/a b.report.html"), names.as_slice()),
+            (Path::new("c.report.html"), [].as_slice()),
+        ]);
+        assert!(
+            index.contains(
+                "<li><a href=\"dir/a%20b.report.html\">init_ctx, a&lt;b</a> <span class=\"path\">dir/a b.report.html</span></li>\n\
+                 <li><a href=\"c.report.html\">c.report.html</a>"
+            ),
+            "unexpected index: {index}"
+        );
+    }
+
+    #[test]
+    fn render_fills_placeholders_in_a_single_pass() {
+        assert_eq!(
+            render("{{a}} {{b}} {{unknown}} {{", &[("a", "{{b}}"), ("b", "2")]),
+            "{{b}} 2 {{unknown}} {{"
+        );
+    }
+}
@@ -0,0 +1,144 @@
+//! Generate IDAPython scripts that apply the suggestions of analyses to an IDA database.
+
+use crate::emit::{FunctionSuggestions, ScriptEmitter, format_python_table};
+
+/// Header of IDAPython scripts, before the table of suggestions.
+const SCRIPT_HEADER: &str =
+    "# IDAPython script generated by oneiromancer: applies the suggested function names, comments,
+# and variable renames to the database. Run it with File > Script file... in IDA.
+#
+# Functions are looked up by address, or by their original name if the address is unknown.
+# Suggestions that cannot be applied are reported at the end instead of stopping the script.
+
+import ida_hexrays
+import idaapi
+import idc
+
+";
+
+/// Code of IDAPython scripts, after the table of suggestions.
+const SCRIPT_BODY: &str = r#"
+
+def find_function(function):
+    """Returns the function at the suggested address, or with the original name."""
+    address = function["address"]
+    if address is None:
+        if not function["name"]:
+            return None
+        address = idc.get_name_ea_simple(function["name"])
+    if address == idc.BADADDR:
+        return None
+    return idaapi.get_func(address)
+
+
+def apply(function, failures):
+    """Applies the suggestions for a function, appending the ones that fail to failures."""
+    label = function["name"] or function["new_name"]
+    func = find_function(function)
+    if func is None:
+        failures.append("%s: function not found" % label)
+        return
+    if not idc.set_name(func.start_ea, function["new_name"], idc.SN_CHECK | idc.SN_NOWARN):
+        failures.append("%s: cannot rename function to %s" % (label, function["new_name"]))
+    if not idaapi.set_func_cmt(func, function["comment"], False):
+        failures.append("%s: cannot set function comment" % label)
+    if not function["renames"]:
+        return
+    if not ida_hexrays.init_hexrays_plugin():
+        failures.append("%s: Hex-Rays decompiler not available, variables not renamed" % label)
+        return
+    for old, new in function["renames"]:
+        if not ida_hexrays.rename_lvar(func.start_ea, old, new):
+            failures.append("%s: cannot rename variable %s to %s" % (label, old, new))
+
+
+def main():
+    failures = []
+    for function in FUNCTIONS:
+        try:
+            apply(function, failures)
+        except Exception as err:  # keep applying the other suggestions
+            failures.append("%s: %s" % (function["name"] or function["new_name"], err))
+    if failures:
+        print("[!] oneiromancer: %d suggestions not applied:" % len(failures))
+        for failure in failures:
+            print("    " + failure)
+    else:
+        print("[+] oneiromancer: all suggestions applied")
+
+
+main()
+"#;
+
+/// Emitter of IDAPython scripts that apply suggestions to an IDA database: they rename each
+/// function with `idc.set_name`, set its comment with `idaapi.set_func_cmt`, and rename its local
+/// variables with the Hex-Rays `rename_lvar` API.
+///
+/// All names and comments are emitted as escaped Python string literals (see
+/// [`format_python_table`]). The script checks the outcome of each change and prints the ones that
+/// failed.
+#[derive(Debug, Clone, Copy)]
+pub struct IdaScript;
+
+impl ScriptEmitter for IdaScript {
+    fn description(&self) -> &'static str {
+        "IDAPython script"
+    }
+
+    fn format_script(&self, suggestions: &[FunctionSuggestions], _model: &str) -> String {
+        format!(
+            "{SCRIPT_HEADER}{}{SCRIPT_BODY}",
+            format_python_table(suggestions)
+        )
+    }
+}
+
+/// Formats an IDAPython script that applies the `suggestions` to an IDA database.
+///
+/// The script is the one emitted with `--emit-ida`: it renames each function, sets its comment,
+/// and renames its local variables through Hex-Rays.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use oneiromancer::{FunctionSuggestions, OneiromancerResults, format_ida_script};
+///
+/// let results: OneiromancerResults = serde_json::from_str(
+///     r#"{"function_name":"parse_header","comment":"Parses a header.","variables":[{"original_name":"v1","new_name":"length"}]}"#,
+/// )?;
+/// let script = format_ida_script(&[FunctionSuggestions::new(&results, "sub_401000", None)]);
+///
+/// assert!(script.contains(r#"("v1", "length")"#));
+/// # Ok(())
+/// # }
+/// ```
+#[must_use]
+pub fn format_ida_script(suggestions: &[FunctionSuggestions]) -> String {
+    IdaScript.format_script(suggestions, "")
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use super::*;
+    use crate::emit::collect_suggestions;
+    use crate::report::FunctionReport;
+
+    /// Fixture with the suggestions for a function with an IDA-style name and for a named
+    /// function, with characters that need escaping in names and comments.
+    const FIXTURE: &str = include_str!("../tests/data/emit/functions.json");
+
+    #[test]
+    fn format_script_matches_golden_file() -> anyhow::Result<()> {
+        let functions: Vec<FunctionReport> = serde_json::from_str(FIXTURE)?;
+        let suggestions = collect_suggestions("", None, &functions, None, None);
+
+        assert_eq!(
+            IdaScript.format_script(&suggestions, "test-model"),
+            include_str!("../tests/data/emit/ida.py")
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+//! Generate IDC scripts that apply the suggestions of analyses to an IDA database without Python.
+
+use std::fmt::Write as _;
+
+use crate::emit::{FunctionSuggestions, ScriptEmitter};
+
+/// Code of IDC scripts, between the header and the calls that apply the suggestions.
+const SCRIPT_PRELUDE: &str = r#"//
+// Functions are located by address, or by their original name if the address is unknown. IDC
+// cannot rename Hex-Rays local variables, so the suggested variable renames are listed in comments
+// for manual application (press N on each variable in the pseudocode view).
+
+#include <idc.idc>
+
+// Applies the suggested name and comment to the function at ea, returning the number of failures.
+static apply(ea, label, name, comment)
+{
+  auto failures;
+  failures = 0;
+  if (ea == BADADDR) {
+    msg("[!] oneiromancer: %s: function not found\n", label);
+    return 1;
+  }
+  if (!set_name(ea, name, SN_CHECK | SN_NOWARN)) {
+    msg("[!] oneiromancer: %s: cannot rename function to %s\n", label, name);
+    failures = failures + 1;
+  }
+  if (!set_func_cmt(ea, comment, 0)) {
+    msg("[!] oneiromancer: %s: cannot set function comment\n", label);
+    failures = failures + 1;
+  }
+  return failures;
+}
+
+static main()
+{
+  auto failures;
+  failures = 0;
+"#;
+
+/// Code of IDC scripts, after the calls that apply the suggestions.
+const SCRIPT_EPILOGUE: &str = r#"
+  if (failures > 0) {
+    msg("[!] oneiromancer: %d suggestions not applied\n", failures);
+  } else {
+    msg("[+] oneiromancer: all suggestions applied\n");
+  }
+}
+"#;
+
+/// Emitter of classic IDC scripts that apply suggestions to an IDA database with no Python
+/// support: they rename each function with `set_name` and set its comment with `set_func_cmt`,
+/// listing the variable renames in comments.
+///
+/// All names and comments are emitted as escaped IDC string literals (see [`idc_string`]), also
+/// in the comments, so that no suggestion can break out of them.
+#[derive(Debug, Clone, Copy)]
+pub struct IdcScript;
+
+impl ScriptEmitter for IdcScript {
+    fn description(&self) -> &'static str {
+        "IDC script"
+    }
+
+    fn format_script(&self, suggestions: &[FunctionSuggestions], model: &str) -> String {
+        let mut script = format!(
+            "// IDC script generated by oneiromancer {} with model {}: applies the suggested\n\
+             // function names and comments to the database. Run it with File > Script file... in IDA.\n",
+            env!("CARGO_PKG_VERSION"),
+            idc_string(model)
+        );
+        script.push_str(SCRIPT_PRELUDE);
+        for function in suggestions {
+            let label = if function.original_name().is_empty() {
+                function.function_name()
+            } else {
+                function.original_name()
+            };
+            let ea = match function.address() {
+                Some(address) => format!("0x{address:X}"),
+                None if function.original_name().is_empty() => "BADADDR".to_owned(),
+                None => format!(
+                    "get_name_ea_simple({})",
+                    idc_string(function.original_name())
+                ),
+            };
+            _ = writeln!(
+                script,
+                "\n  // {} -> {}",
+                idc_string(label),
+                idc_string(function.function_name())
+            );
+            if !function.renames().is_empty() {
+                script.push_str("  // Variable renames to apply manually:\n");
+                for rename in function.renames() {
+                    _ = writeln!(
+                        script,
+                        "  //   {} -> {}",
+                        idc_string(&rename.0),
+                        idc_string(&rename.1)
+                    );
+                }
+            }
+            _ = writeln!(
+                script,
+                "  failures = failures + apply({ea}, {}, {}, {});",
+                idc_string(label),
+                idc_string(function.function_name()),
+                idc_string(function.comment())
+            );
+        }
+        script.push_str(SCRIPT_EPILOGUE);
+        script
+    }
+}
+
+/// Formats an IDC script that applies the `suggestions` made by `model` to an IDA database.
+///
+/// The script is the one emitted with `--emit-idc`: it renames each function and sets its comment.
+/// IDC cannot rename the local variables of the decompiler, see
+/// [`format_ida_script`](crate::format_ida_script).
+#[must_use]
+pub fn format_idc_script(suggestions: &[FunctionSuggestions], model: &str) -> String {
+    IdcScript.format_script(suggestions, model)
+}
+
+/// Returns `text` as a double-quoted IDC string literal. Quotes, backslashes, and common control
+/// characters use C escapes, while other non-printable and non-ASCII bytes use three-digit octal
+/// escapes, which (unlike hex escapes) cannot absorb the characters that follow them.
+fn idc_string(text: &str) -> String {
+    let mut literal = String::with_capacity(text.len().saturating_add(2));
+    literal.push('"');
+    for byte in text.bytes() {
+        match byte {
+            b'\\' => literal.push_str("\\\\"),
+            b'"' => literal.push_str("\\\""),
+            b'\n' => literal.push_str("\\n"),
+            b'\r' => literal.push_str("\\r"),
+            b'\t' => literal.push_str("\\t"),
+            b' '..=b'~' => literal.push(char::from(byte)),
+            _ => _ = write!(literal, "\\{byte:03o}"),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use super::*;
+    use crate::emit::collect_suggestions;
+    use crate::report::FunctionReport;
+
+    #[test]
+    fn idc_string_escapes_quotes_controls_and_non_ascii() {
+        assert_eq!(idc_string("plain name"), r#""plain name""#);
+        assert_eq!(
+            idc_string("say \"hi\"\\n\n\t\u{7}caf\u{e9}1"),
+            r#""say \"hi\"\\n\n\t\007caf\303\2511""#
+        );
+    }
+
+    #[test]
+    fn format_script_matches_golden_file() -> anyhow::Result<()> {
+        let functions: Vec<FunctionReport> =
+            serde_json::from_str(include_str!("../tests/data/emit/functions.json"))?;
+        let suggestions = collect_suggestions("", None, &functions, None, None);
+
+        assert_eq!(
+            IdcScript.format_script(&suggestions, "test-model"),
+            include_str!("../tests/data/emit/idc.idc")
+                .replace("@VERSION@", env!("CARGO_PKG_VERSION"))
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,302 @@
+//! Per-batch index of the analyzed files, saved as JSON and optionally as a Markdown table.
+
+use std::fmt::Write as _;
+
+use serde::{Deserialize, Serialize};
+
+use crate::html::relative_href;
+use crate::markdown::{escape_text, table_cell};
+use crate::{AnalysisReport, OneiromancerResults};
+
+/// Name of the JSON index saved in the output directory of a batch.
+pub const BATCH_REPORT_FILENAME: &str = "report.json";
+/// Name of the Markdown index saved in the output directory of a batch, if requested.
+pub const BATCH_INDEX_FILENAME: &str = "INDEX.md";
+
+/// Summary of the analysis of a file in a batch.
+///
+/// Entries are serialized as the JSON Lines record of the file (see [`AnalysisReport`]) with two
+/// more fields: `summary`, the first sentence of the description (if any), and `renames_applied`,
+/// the number of variable renames applied to the output file.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IndexEntry {
+    /// Report of the analysis of the file.
+    #[serde(flatten)]
+    report: AnalysisReport,
+    /// First sentence of the description of the (first) analyzed function, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    summary: Option<String>,
+    /// Number of variable renames applied to the output file.
+    renames_applied: usize,
+}
+
+impl IndexEntry {
+    /// Creates a new [`IndexEntry`] of the analysis in `report`, with `renames_applied` renames.
+    #[must_use]
+    pub fn new(report: AnalysisReport, renames_applied: usize) -> Self {
+        let summary = analyses(&report)
+            .first()
+            .and_then(|analysis| first_sentence(analysis.comment()));
+        Self {
+            report,
+            summary,
+            renames_applied,
+        }
+    }
+
+    /// Gets the report of the analysis of the file.
+    #[must_use]
+    pub const fn report(&self) -> &AnalysisReport {
+        &self.report
+    }
+
+    /// Gets the first sentence of the description of the (first) analyzed function, if any.
+    #[must_use]
+    pub fn summary(&self) -> Option<&str> {
+        self.summary.as_deref()
+    }
+
+    /// Gets the number of variable renames applied to the output file.
+    #[must_use]
+    pub const fn renames_applied(&self) -> usize {
+        self.renames_applied
+    }
+
+    /// Returns the names recommended for the analyzed functions, separated by commas.
+    fn function_names(&self) -> String {
+        analyses(&self.report)
+            .iter()
+            .map(|analysis| analysis.function_name())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Index of the files analyzed in a batch, sorted by input path.
+///
+/// The index is saved as [`BATCH_REPORT_FILENAME`] (and optionally [`BATCH_INDEX_FILENAME`])
+/// after each analysis completes, with `complete` set to `false`, and once more at the end of the
+/// batch, so that an interrupted batch still leaves a partial index behind.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct BatchIndex {
+    /// Whether all the files of the batch were analyzed.
+    complete: bool,
+    /// Summaries of the analyzed files.
+    files: Vec<IndexEntry>,
+}
+
+impl BatchIndex {
+    /// Creates a new [`BatchIndex`] of the `files`, which are sorted by input path.
+    #[must_use]
+    pub fn new(mut files: Vec<IndexEntry>, complete: bool) -> Self {
+        files.sort_by(|a, b| a.report.input().cmp(b.report.input()));
+        Self { complete, files }
+    }
+
+    /// Returns `true` if all the files of the batch were analyzed.
+    #[must_use]
+    pub const fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Gets the summaries of the analyzed files, sorted by input path.
+    #[must_use]
+    pub fn files(&self) -> &[IndexEntry] {
+        &self.files
+    }
+
+    /// Adds the summary of a file, keeping the files sorted by input path.
+    pub(crate) fn push(&mut self, entry: IndexEntry) {
+        let position = self
+            .files
+            .partition_point(|file| file.report.input() <= entry.report.input());
+        self.files.insert(position, entry);
+    }
+
+    /// Sets whether all the files of the batch were analyzed.
+    pub(crate) const fn set_complete(&mut self, complete: bool) {
+        self.complete = complete;
+    }
+
+    /// Serializes the index as pretty-printed JSON.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let mut json = serde_json::to_string_pretty(self).unwrap_or_default();
+        json.push('\n');
+        json
+    }
+
+    /// Formats the index as a Markdown table, with a row for each file that links the input path
+    /// to the output file (if saved at a relative path, i.e., within the directory of the index).
+    #[must_use]
+    pub fn to_markdown(&self) -> String {
+        let mut index = String::from("# Batch index\n\n");
+        if !self.complete {
+            index.push_str("Incomplete: the batch was interrupted or is still running.\n\n");
+        }
+        index.push_str("| File | Function | Summary | Renames | Status |\n");
+        index.push_str("| --- | --- | --- | --- | --- |\n");
+        for entry in &self.files {
+            let input = table_cell(&entry.report.input().to_string_lossy());
+            let file = match entry.report.output() {
+                Some(output) if output.is_relative() => {
+                    format!("[{input}]({})", relative_href(output))
+                }
+                _ => input,
+            };
+            let names = entry.function_names();
+            let function = if names.is_empty() {
+                String::new()
+            } else {
+                table_cell(&names)
+            };
+            let summary = entry
+                .summary()
+                .or_else(|| entry.report.error())
+                .map(|text| escape_text(&text.replace(['\r', '\n'], " ")))
+                .unwrap_or_default();
+            _ = writeln!(
+                index,
+                "| {file} | {function} | {summary} | {} | {} |",
+                entry.renames_applied,
+                entry.report.status()
+            );
+        }
+        index
+    }
+}
+
+/// Returns the analysis results in `report`, of the whole file or of each function.
+fn analyses(report: &AnalysisReport) -> Vec<&OneiromancerResults> {
+    report.results().map_or_else(
+        || {
+            report
+                .functions()
+                .iter()
+                .filter_map(|function| function.results())
+                .collect()
+        },
+        |results| vec![results],
+    )
+}
+
+/// Returns the first sentence of `text`, with whitespace collapsed, or `None` if `text` is blank.
+fn first_sentence(text: &str) -> Option<String> {
+    let flat = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let end = flat
+        .char_indices()
+        .find(|&(offset, ch)| {
+            matches!(ch, '.' | '!' | '?')
+                && flat
+                    .get(offset.saturating_add(1)..)
+                    .is_some_and(|rest| rest.starts_with(' '))
+        })
+        .map_or(flat.len(), |found| found.0.saturating_add(1));
+    flat.get(..end)
+        .filter(|sentence| !sentence.is_empty())
+        .map(ToOwned::to_owned)
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    use super::*;
+
+    /// Returns the report of the successful analysis of `input` into `output`, with `comment`.
+    fn report(input: &str, output: &str, comment: &str) -> serde_json::Result<AnalysisReport> {
+        let results = serde_json::from_value(serde_json::json!({
+            "function_name": "parse_header",
+            "comment": comment,
+            "variables": [],
+        }))?;
+        Ok(AnalysisReport::succeeded(
+            Path::new(input),
+            Some(PathBuf::from(output)),
+            "aidapal",
+            Duration::from_millis(5),
+            Some(results),
+            Vec::new(),
+            None,
+        ))
+    }
+
+    #[test]
+    fn first_sentence_stops_at_the_first_sentence_end() {
+        assert_eq!(
+            first_sentence("Parses the header.\nReturns 0 on success.").as_deref(),
+            Some("Parses the header.")
+        );
+        assert_eq!(
+            first_sentence("  Version 1.2 parser  ").as_deref(),
+            Some("Version 1.2 parser")
+        );
+        assert_eq!(first_sentence(" \n "), None);
+    }
+
+    #[test]
+    fn batch_index_shares_the_schema_of_json_lines_records() -> anyhow::Result<()> {
+        let renames: usize = 2;
+        let entry = IndexEntry::new(report("a.c", "a.out.c", "Parses. More.")?, renames);
+        let index = BatchIndex::new(vec![entry], false);
+
+        let json: serde_json::Value = serde_json::from_str(&index.to_json())?;
+
+        assert_eq!(json.get("complete"), Some(&serde_json::json!(false)));
+        let fields = [
+            ("/files/0/status", serde_json::json!("ok")),
+            ("/files/0/input", serde_json::json!("a.c")),
+            ("/files/0/function_name", serde_json::json!("parse_header")),
+            ("/files/0/summary", serde_json::json!("Parses.")),
+            ("/files/0/renames_applied", serde_json::json!(renames)),
+        ];
+        for (pointer, value) in fields {
+            assert_eq!(json.pointer(pointer), Some(&value), "{pointer}");
+        }
+        let parsed: BatchIndex = serde_json::from_value(json)?;
+        assert_eq!(
+            parsed.files().first().map(|file| file.report().input()),
+            Some(Path::new("a.c"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn to_markdown_sorts_rows_and_links_relative_outputs() -> anyhow::Result<()> {
+        let failed = AnalysisReport::failed(
+            "/in/c.c",
+            "aidapal",
+            Duration::ZERO,
+            &anyhow::anyhow!("Model | not found"),
+        );
+        let index = BatchIndex::new(
+            vec![
+                IndexEntry::new(report("b.c", "/elsewhere/b.out.c", "Second.")?, 0),
+                IndexEntry::new(failed, 0),
+                IndexEntry::new(report("a b.c", "sub/a b.out.c", "First.")?, 1),
+            ],
+            true,
+        );
+
+        let markdown = index.to_markdown();
+
+        let rows: Vec<&str> = markdown
+            .lines()
+            .filter(|line| line.starts_with("| "))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                "| File | Function | Summary | Renames | Status |",
+                "| --- | --- | --- | --- | --- |",
+                "| `/in/c.c` |  | Model \\| not found | 0 | error |",
+                "| [`a b.c`](sub/a%20b.out.c) | `parse_header` | First. | 1 | ok |",
+                "| `b.c` | `parse_header` | Second. | 0 | ok |",
+            ]
+        );
+        assert!(!markdown.contains("Incomplete"));
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+//! Request the graceful interruption of a batch (e.g., on Ctrl-C).
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Request to stop a batch gracefully, shared by all its clones.
+///
+/// Once requested, no new files are submitted for analysis, while the analyses in flight are
+/// completed and recorded as usual, so that the next run resumes where the batch stopped.
+#[derive(Debug, Clone, Default)]
+pub struct Interrupt(Arc<AtomicBool>);
+
+impl Interrupt {
+    /// Creates a new [`Interrupt`] that is not requested yet.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests the interruption, returning `true` if it was already requested (e.g., to force
+    /// quit on a second Ctrl-C).
+    #[must_use]
+    pub fn request(&self) -> bool {
+        self.0.swap(true, Ordering::SeqCst)
+    }
+
+    /// Returns `true` if the interruption was requested.
+    #[must_use]
+    pub fn is_requested(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,203 @@
+//! Analyze all functions in a file with a single prompt.
+
+use serde::Deserialize;
+
+use crate::{FunctionSpan, OneiromancerResults, ResponseMetrics};
+
+/// Response to a joint analysis: a JSON array of per-function objects, or an object that wraps
+/// the array in a `functions` field (as models constrained to answer with an object tend to do).
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum JointResponse {
+    /// Bare array of per-function objects.
+    Entries(Vec<serde_json::Value>),
+    /// Array of per-function objects wrapped in an object.
+    Wrapped {
+        /// Per-function objects.
+        functions: Vec<serde_json::Value>,
+    },
+}
+
+/// Results of a single function in a joint analysis response.
+#[derive(Deserialize, Debug)]
+struct JointEntry {
+    /// Name of the function as it appears in the pseudocode.
+    original_name: String,
+    /// Analysis results of the function.
+    #[serde(flatten)]
+    results: OneiromancerResults,
+}
+
+/// Parses the response to a joint analysis into results keyed by original function name.
+///
+/// The response is a JSON array of objects with the `original_name` of each function, and its
+/// `function_name`, `comment`, and `variables`. Results are returned in the order of the response.
+/// Malformed objects are left out, so that the functions they refer to are reported as missing.
+///
+/// # Errors
+///
+/// Returns [`serde_json::Error`] in case the response is not valid JSON, or not an array.
+pub fn parse_joint_response(
+    json: &str,
+) -> Result<Vec<(String, OneiromancerResults)>, serde_json::Error> {
+    let items = match serde_json::from_str(json)? {
+        JointResponse::Entries(items) | JointResponse::Wrapped { functions: items } => items,
+    };
+    Ok(items
+        .into_iter()
+        .filter_map(|item| serde_json::from_value::<JointEntry>(item).ok())
+        .map(|entry| (entry.original_name, entry.results))
+        .collect())
+}
+
+/// Results of the analysis of all functions in a file with a single prompt, as returned by
+/// [`analyze_functions_jointly`](`crate::Oneiromancer::analyze_functions_jointly`).
+#[derive(Debug, Clone)]
+pub struct JointAnalysis {
+    /// Function definitions in the analyzed pseudocode, with their results if the response covers
+    /// them.
+    functions: Vec<(FunctionSpan, Option<OneiromancerResults>)>,
+    /// Names of the functions in the response that match no definition in the pseudocode.
+    unknown: Vec<String>,
+    /// Timing and token statistics of the response.
+    metrics: ResponseMetrics,
+}
+
+impl JointAnalysis {
+    /// Matches the `entries` parsed from a response with `metrics` to the `spans` of the function
+    /// definitions, by original name. Each entry goes to the first definition with its name that
+    /// has no results yet; the others (e.g., duplicates) are unknown.
+    pub(crate) fn new(
+        spans: Vec<FunctionSpan>,
+        entries: Vec<(String, OneiromancerResults)>,
+        metrics: ResponseMetrics,
+    ) -> Self {
+        let mut functions: Vec<(FunctionSpan, Option<OneiromancerResults>)> =
+            spans.into_iter().map(|span| (span, None)).collect();
+        let mut unknown = Vec::new();
+        for (name, results) in entries {
+            match functions
+                .iter_mut()
+                .find(|function| function.1.is_none() && function.0.name() == name.trim())
+            {
+                Some(function) => function.1 = Some(results),
+                None => unknown.push(name),
+            }
+        }
+        Self {
+            functions,
+            unknown,
+            metrics,
+        }
+    }
+
+    /// Gets the function definitions in the analyzed pseudocode, in order, with their results if
+    /// the response covers them.
+    #[must_use]
+    pub fn functions(&self) -> &[(FunctionSpan, Option<OneiromancerResults>)] {
+        &self.functions
+    }
+
+    /// Returns the function definitions that the response does not cover.
+    pub fn missing(&self) -> impl Iterator<Item = &FunctionSpan> {
+        self.functions
+            .iter()
+            .filter(|function| function.1.is_none())
+            .map(|function| &function.0)
+    }
+
+    /// Gets the names of the functions in the response that match no definition in the analyzed
+    /// pseudocode.
+    #[must_use]
+    pub fn unknown(&self) -> &[String] {
+        &self.unknown
+    }
+
+    /// Gets the timing and token statistics reported by the Ollama API for the response (empty
+    /// for cached responses).
+    #[must_use]
+    pub const fn metrics(&self) -> &ResponseMetrics {
+        &self.metrics
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use super::*;
+    use crate::split_functions;
+
+    const RESPONSE: &str = r#"[
+        {"original_name": "sub_1000", "function_name": "add_one", "comment": "Adds one.", "variables": [{"original_name": "a1", "new_name": "value"}]},
+        {"original_name": "sub_3000", "function_name": "ghost", "comment": "Not there.", "variables": []},
+        {"original_name": "sub_2000", "comment": "Missing the function name."}
+    ]"#;
+
+    #[test]
+    fn parse_joint_response_skips_malformed_entries() -> anyhow::Result<()> {
+        let entries = parse_joint_response(RESPONSE)?;
+
+        let names: Vec<(&str, &str)> = entries
+            .iter()
+            .map(|entry| (entry.0.as_str(), entry.1.function_name()))
+            .collect();
+        assert_eq!(names, [("sub_1000", "add_one"), ("sub_3000", "ghost")]);
+        assert_eq!(
+            entries.first().map(|entry| entry.1.variables().len()),
+            Some(1)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_joint_response_accepts_wrapped_array() -> anyhow::Result<()> {
+        let wrapped = format!(r#"{{"functions": {RESPONSE}}}"#);
+
+        assert_eq!(parse_joint_response(&wrapped)?.len(), 2);
+        assert!(
+            parse_joint_response(r#"{"function_name": "f", "comment": "", "variables": []}"#)
+                .is_err(),
+            "single object accepted"
+        );
+        assert!(
+            parse_joint_response("not json").is_err(),
+            "garbage accepted"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn joint_analysis_reports_missing_and_unknown_functions() -> anyhow::Result<()> {
+        let pseudocode = "int sub_1000(int a1) { return a1 + 1; }\nint sub_2000() { return 2; }\n";
+
+        let analysis = JointAnalysis::new(
+            split_functions(pseudocode),
+            parse_joint_response(RESPONSE)?,
+            ResponseMetrics::default(),
+        );
+
+        let matched: Vec<(&str, Option<&str>)> = analysis
+            .functions()
+            .iter()
+            .map(|function| {
+                (
+                    function.0.name(),
+                    function.1.as_ref().map(OneiromancerResults::function_name),
+                )
+            })
+            .collect();
+        assert_eq!(matched, [("sub_1000", Some("add_one")), ("sub_2000", None)]);
+        assert_eq!(
+            analysis
+                .missing()
+                .map(FunctionSpan::name)
+                .collect::<Vec<_>>(),
+            ["sub_2000"]
+        );
+        assert_eq!(analysis.unknown(), ["sub_3000"]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,260 @@
+//! Tokenize C-like pseudocode.
+//!
+//! The tokenizer is deliberately lightweight: it only needs to tell identifiers apart from
+//! literals, comments, and preprocessor directives, and to keep track of byte offsets so that
+//! callers can slice and rewrite the original text.
+
+use std::ops::Range;
+
+/// Kind of a [`Token`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Identifier or keyword (e.g., `v1`, `__fastcall`, `return`).
+    Identifier,
+    /// Numeric literal (e.g., `0x10`, `1.5e-3`).
+    Number,
+    /// String literal, including its prefix (e.g., `"hello"`, `L"wide"`).
+    String,
+    /// Character literal (e.g., `'\n'`).
+    Char,
+    /// Line comment, up to (but excluding) the newline.
+    LineComment,
+    /// Block comment, including delimiters.
+    BlockComment,
+    /// Preprocessor directive, including line continuations.
+    Preprocessor,
+    /// Single punctuation character (e.g., `{`, `;`, `*`).
+    Punct,
+    /// Run of whitespace.
+    Whitespace,
+}
+
+/// Token of C-like pseudocode, identified by its kind and byte range in the source text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    /// Token kind.
+    pub kind: TokenKind,
+    /// Byte range of the token in the source text.
+    pub range: Range<usize>,
+}
+
+impl Token {
+    /// Returns the text of the token in `source`.
+    pub fn text<'a>(&self, source: &'a str) -> &'a str {
+        source.get(self.range.clone()).unwrap_or_default()
+    }
+
+    /// Returns `true` if the token is whitespace, a comment, or a preprocessor directive.
+    pub const fn is_trivia(&self) -> bool {
+        matches!(
+            self.kind,
+            TokenKind::Whitespace
+                | TokenKind::LineComment
+                | TokenKind::BlockComment
+                | TokenKind::Preprocessor
+        )
+    }
+
+    /// Returns `true` if the token is the `c` punctuation character.
+    pub fn is_punct(&self, source: &str, c: char) -> bool {
+        self.kind == TokenKind::Punct && self.text(source).starts_with(c)
+    }
+}
+
+/// Splits `source` into tokens. The concatenation of all token ranges covers `source` entirely.
+pub fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut pos: usize = 0;
+    let mut at_line_start = true;
+
+    while let Some(c) = source.get(pos..).and_then(|rest| rest.chars().next()) {
+        let rest = source.get(pos..).unwrap_or_default();
+        let (kind, len) = if c.is_whitespace() {
+            (TokenKind::Whitespace, take_while(rest, char::is_whitespace))
+        } else if c == '#' && at_line_start {
+            (TokenKind::Preprocessor, preprocessor_len(rest))
+        } else if rest.starts_with("//") {
+            (
+                TokenKind::LineComment,
+                rest.find('\n').unwrap_or(rest.len()),
+            )
+        } else if rest.starts_with("/*") {
+            let len = rest
+                .get(2..)
+                .and_then(|body| body.find("*/"))
+                .map_or(rest.len(), |end| end.saturating_add(4));
+            (TokenKind::BlockComment, len)
+        } else if c == '"' {
+            (TokenKind::String, quoted_len(rest, '"'))
+        } else if c == '\'' {
+            (TokenKind::Char, quoted_len(rest, '\''))
+        } else if is_ident_start(c) {
+            let ident_len = take_while(rest, is_ident_continue);
+            let ident = rest.get(..ident_len).unwrap_or_default();
+            match rest.get(ident_len..).and_then(|after| after.chars().next()) {
+                // Prefixed string or character literal (e.g., `L"wide"`, `u8"utf8"`).
+                Some(quote @ ('"' | '\'')) if matches!(ident, "L" | "u" | "U" | "u8") => {
+                    let kind = if quote == '"' {
+                        TokenKind::String
+                    } else {
+                        TokenKind::Char
+                    };
+                    let literal = rest.get(ident_len..).unwrap_or_default();
+                    (kind, ident_len.saturating_add(quoted_len(literal, quote)))
+                }
+                _ => (TokenKind::Identifier, ident_len),
+            }
+        } else if c.is_ascii_digit() {
+            (TokenKind::Number, number_len(rest))
+        } else {
+            (TokenKind::Punct, c.len_utf8())
+        };
+
+        let end = pos.saturating_add(len.max(c.len_utf8()));
+        if kind == TokenKind::Whitespace {
+            if source.get(pos..end).is_some_and(|ws| ws.contains('\n')) {
+                at_line_start = true;
+            }
+        } else {
+            at_line_start = false;
+        }
+        tokens.push(Token {
+            kind,
+            range: pos..end,
+        });
+        pos = end;
+    }
+    tokens
+}
+
+/// Returns `true` if `c` can start an identifier.
+const fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || c == '$'
+}
+
+/// Returns `true` if `c` can continue an identifier.
+const fn is_ident_continue(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '$'
+}
+
+/// Returns the length in bytes of the longest prefix of `text` whose characters satisfy `pred`.
+fn take_while(text: &str, pred: impl Fn(char) -> bool) -> usize {
+    text.char_indices()
+        .find(|&(_, c)| !pred(c))
+        .map_or(text.len(), |(pos, _)| pos)
+}
+
+/// Returns the length of the quoted literal at the start of `text`, handling escapes. Unterminated
+/// literals end at the end of the line.
+fn quoted_len(text: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (pos, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '\n' => return pos,
+            _ if c == quote => return pos.saturating_add(c.len_utf8()),
+            _ => (),
+        }
+    }
+    text.len()
+}
+
+/// Returns the length of the preprocessor directive at the start of `text`, including line
+/// continuations.
+fn preprocessor_len(text: &str) -> usize {
+    text.match_indices('\n')
+        .map(|(pos, _)| pos)
+        .find(|&pos| {
+            !text
+                .get(..pos)
+                .is_some_and(|line| line.trim_end_matches('\r').ends_with('\\'))
+        })
+        .unwrap_or(text.len())
+}
+
+/// Returns the length of the numeric literal at the start of `text`.
+fn number_len(text: &str) -> usize {
+    let mut prev = '\0';
+    for (pos, c) in text.char_indices() {
+        let exponent_sign = matches!(c, '+' | '-') && matches!(prev, 'e' | 'E' | 'p' | 'P');
+        if !(c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '\'') || exponent_sign) {
+            return pos;
+        }
+        prev = c;
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the kinds and texts of the non-whitespace tokens in `source`.
+    fn lex(source: &str) -> Vec<(TokenKind, &str)> {
+        tokenize(source)
+            .into_iter()
+            .filter(|token| token.kind != TokenKind::Whitespace)
+            .map(|token| (token.kind, token.text(source)))
+            .collect()
+    }
+
+    #[test]
+    fn tokenize_covers_source_entirely() {
+        let source = "int v1 = 0; // count\n/* x */ printf(\"%d\\n\", v1);";
+
+        let joined: String = tokenize(source)
+            .iter()
+            .map(|token| token.text(source))
+            .collect();
+
+        assert_eq!(joined, source);
+    }
+
+    #[test]
+    fn tokenize_recognizes_literals_and_comments() {
+        assert_eq!(
+            lex("v1 = \"v1 \\\" v1\" + 'v' + L\"w\"; // v1\n/* v1 */"),
+            [
+                (TokenKind::Identifier, "v1"),
+                (TokenKind::Punct, "="),
+                (TokenKind::String, "\"v1 \\\" v1\""),
+                (TokenKind::Punct, "+"),
+                (TokenKind::Char, "'v'"),
+                (TokenKind::Punct, "+"),
+                (TokenKind::String, "L\"w\""),
+                (TokenKind::Punct, ";"),
+                (TokenKind::LineComment, "// v1"),
+                (TokenKind::BlockComment, "/* v1 */"),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_recognizes_preprocessor_directives_at_line_start() {
+        assert_eq!(
+            lex("#define X \\\n  1\n  #include <a.h>\nx # y"),
+            [
+                (TokenKind::Preprocessor, "#define X \\\n  1"),
+                (TokenKind::Preprocessor, "#include <a.h>"),
+                (TokenKind::Identifier, "x"),
+                (TokenKind::Punct, "#"),
+                (TokenKind::Identifier, "y"),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_recognizes_numbers() {
+        assert_eq!(
+            lex("0x10u + 1.5e-3 - 7"),
+            [
+                (TokenKind::Number, "0x10u"),
+                (TokenKind::Punct, "+"),
+                (TokenKind::Number, "1.5e-3"),
+                (TokenKind::Punct, "-"),
+                (TokenKind::Number, "7"),
+            ]
+        );
+    }
+}
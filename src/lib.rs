@@ -3,14 +3,7 @@
 #![cfg_attr(doc, doc = include_str!("../README.md"))]
 #![doc(html_logo_url = "https://raw.githubusercontent.com/0xdea/oneiromancer/master/.img/logo.png")]
 
-use std::fs::File;
-use std::io::{BufReader, BufWriter, Read as _, Write as _};
-use std::path::Path;
-
-use anyhow::Context as _;
-use regex::Regex;
-use spinners::{Spinner, Spinners};
-
+#[cfg(feature = "ffi")]
 #[expect(
     clippy::pub_use,
     reason = "`pub use` is the idiomatic way to flatten a public API"
@@ -19,116 +12,252 @@ use spinners::{Spinner, Spinners};
     clippy::useless_attribute,
     reason = "the `expect` attribute is actually useful here..."
 )]
-pub use crate::oneiromancer::{Oneiromancer, OneiromancerError, OneiromancerResults, Variable};
-
+pub use crate::ffi::{
+    ONEIROMANCER_ANALYSIS_FAILED, ONEIROMANCER_INVALID_ARGUMENT, ONEIROMANCER_OK,
+    ONEIROMANCER_PANICKED, oneiromancer_analyze, oneiromancer_free_string, oneiromancer_version,
+};
+#[cfg(feature = "format")]
+#[expect(
+    clippy::pub_use,
+    reason = "`pub use` is the idiomatic way to flatten a public API"
+)]
+#[expect(
+    clippy::useless_attribute,
+    reason = "the `expect` attribute is actually useful here..."
+)]
+pub use crate::format::format_description;
+#[cfg(feature = "cli")]
+#[expect(
+    clippy::pub_use,
+    reason = "`pub use` is the idiomatic way to flatten a public API"
+)]
+#[expect(
+    clippy::useless_attribute,
+    reason = "the `expect` attribute is actually useful here..."
+)]
+pub use crate::repl::{ReplCommand, ReplSession, run_repl};
+#[expect(
+    clippy::pub_use,
+    reason = "`pub use` is the idiomatic way to flatten a public API"
+)]
+#[expect(
+    clippy::useless_attribute,
+    reason = "the `expect` attribute is actually useful here..."
+)]
+pub use crate::{
+    aidapal::{AidapalError, AidapalPayload, InputFormat, parse_aidapal_json},
+    backend::{Backend, BackendRequest, LlmBackend, OllamaBackend},
+    binary::{BinaryError, DecompiledFunction, FunctionSelector, decompile_binary},
+    cache::ResponseCache,
+    config::{CONFIG_TEMPLATE, Config, ConfigError},
+    consensus::{Consensus, VariableAgreement, format_consensus, merge_samples},
+    context::AnalysisContext,
+    decompiler::Decompiler,
+    format::{COMMENT_WIDTH, CommentOptions, CommentStyle, CommentWidth},
+    joint::{JointAnalysis, parse_joint_response},
+    llamacpp::{LLAMACPP_BASEURL, LlamaCppBackend},
+    occurrence::{RenameOccurrence, locate_renames},
+    ollama::PullProgress,
+    oneiromancer::{
+        FunctionAnalysis, OLLAMA_BASEURL, OLLAMA_MODEL, Oneiromancer, OneiromancerError,
+        OneiromancerResults, Variable,
+    },
+    openai::{OPENAI_BASEURL, OpenAiBackend},
+    options::{OptionError, parse_option},
+    prompt::PromptOptions,
+    quality::{MIN_COMMENT_LENGTH, MIN_RENAMES, QualityIssue, QualityPolicy},
+    retry::RetryPolicy,
+    split::{FunctionSpan, SelectError, select_function, split_functions},
+    stats::ResponseMetrics,
+    timeouts::Timeouts,
+    validate::{DEFAULT_EXTENSIONS, InputError, InputPolicy, validate_input},
+};
+#[cfg(feature = "cli")]
+#[expect(
+    clippy::pub_use,
+    reason = "`pub use` is the idiomatic way to flatten a public API"
+)]
+#[expect(
+    clippy::useless_attribute,
+    reason = "the `expect` attribute is actually useful here..."
+)]
+pub use crate::{
+    archive::{ArchiveContents, ArchiveEntry, ArchiveError, ArchiveFormat, pack_dir, read_archive},
+    audit::AuditLog,
+    bench::{BENCH_SEED, BenchSample, ModelBench, aggregate_bench, format_bench_table, run_bench},
+    collision::CollisionPolicy,
+    discover::{
+        DiscoverError, DiscoverOptions, Discovery, SkipReason, discover_files, expand_patterns,
+    },
+    emit::{FunctionSuggestions, parse_address},
+    format::AnnotationField,
+    ghidra::format_ghidra_script,
+    haruspex::{
+        HaruspexDir, HaruspexEntry, HaruspexSkip, IMPROVED_DIRNAME, INDEX_FILENAME,
+        parse_haruspex_filename, read_haruspex_dir,
+    },
+    history::{
+        History, HistoryEntry, HistoryError, format_history_diff, format_history_entry,
+        format_history_list,
+    },
+    html::{HTML_INDEX_FILENAME, format_html_index, format_html_report},
+    ida::format_ida_script,
+    idc::format_idc_script,
+    index::{BATCH_INDEX_FILENAME, BATCH_REPORT_FILENAME, BatchIndex, IndexEntry},
+    interrupt::Interrupt,
+    markdown::{COLLAPSE_LINES, format_markdown_report},
+    mcp::{MCP_PROTOCOL_VERSION, serve_mcp},
+    report::{AnalysisReport, BatchReport, FailureReport, FunctionReport, ReportFormat},
+    run::{
+        ApplyOptions, BACKUP_SUFFIX, OutputError, RunOptions, RunSummary, analyze_binary, run,
+        run_all, run_archive, run_binary, run_haruspex, run_with_options, run_with_report,
+    },
+    sarif::SARIF_SCHEMA,
+    serve::{DEFAULT_CONCURRENCY, DEFAULT_TIMEOUT, MAX_HEADER_SIZE, ServeOptions, serve},
+    settings::{Setting, SettingSource, format_settings},
+    state::STATE_FILENAME,
+    style::{ColorChoice, NO_COLOR_VAR, Tone, paint, paint_status},
+    undo::{AppliedRename, RENAMES_EXTENSION, RenameMap, undo_renames},
+};
+
+use crate::occurrence::{RenameMatch, find_renames};
+
+/// Input file path that stands for stdin.
+pub const STDIN_FILEPATH: &str = "-";
+/// Maximum size of the pseudocode to analyze, in bytes.
+pub const MAX_INPUT_SIZE: usize = 16 * 1024 * 1024;
+
+mod aidapal;
+#[cfg(feature = "cli")]
+mod archive;
+#[cfg(feature = "cli")]
+mod audit;
+mod backend;
+#[cfg(feature = "cli")]
+mod bench;
+mod binary;
+#[cfg(feature = "cli")]
+mod binja;
+mod cache;
+#[cfg(feature = "cli")]
+mod collision;
+mod config;
+mod consensus;
+mod context;
+mod decompiler;
+#[cfg(feature = "cli")]
+mod discover;
+#[cfg(feature = "cli")]
+mod emit;
+#[cfg(feature = "ffi")]
+mod ffi;
+mod format;
+#[cfg(feature = "cli")]
+mod ghidra;
+#[cfg(feature = "cli")]
+mod haruspex;
+#[cfg(feature = "cli")]
+mod header;
+#[cfg(feature = "cli")]
+mod history;
+#[cfg(feature = "cli")]
+mod html;
+#[cfg(feature = "cli")]
+mod ida;
+#[cfg(feature = "cli")]
+mod idc;
+#[cfg(feature = "cli")]
+mod index;
+#[cfg(feature = "cli")]
+mod interrupt;
+mod joint;
+mod lexer;
+mod llamacpp;
+mod location;
+#[cfg(feature = "cli")]
+mod markdown;
+#[cfg(feature = "cli")]
+mod mcp;
+#[cfg(feature = "cli")]
+mod merge;
+mod occurrence;
 mod ollama;
 mod oneiromancer;
-
-/// Submits pseudocode in the `filepath` file to the local LLM for analysis. Outputs analysis results to
-/// terminal and saves improved pseudocode in `filepath` with an `out.c` extension.
+mod openai;
+mod options;
+#[cfg(feature = "cli")]
+mod patch;
+mod prompt;
+mod quality;
+#[cfg(feature = "cli")]
+mod r2;
+#[cfg(feature = "cli")]
+mod repl;
+#[cfg(feature = "cli")]
+mod report;
+#[cfg(feature = "cli")]
+mod reporter;
+mod retry;
+#[cfg(feature = "cli")]
+mod run;
+#[cfg(feature = "cli")]
+mod sarif;
+#[cfg(feature = "cli")]
+mod serve;
+#[cfg(feature = "cli")]
+mod settings;
+#[cfg(feature = "cli")]
+mod signature;
+mod split;
+#[cfg(feature = "cli")]
+mod state;
+mod stats;
+#[cfg(feature = "cli")]
+mod style;
+mod timeouts;
+#[cfg(feature = "cli")]
+mod undo;
+mod validate;
+mod window;
+
+/// Applies variable renaming suggestions to `pseudocode`, as done when improving pseudocode.
 ///
-/// # Errors
-///
-/// Returns [`anyhow::Error`] in case something goes wrong with file I/O or analysis.
-pub fn run(filepath: impl AsRef<Path>) -> anyhow::Result<()> {
-    // Open the target pseudocode file for reading.
-    println!(
-        "[*] Analyzing pseudocode in `{}`",
-        filepath.as_ref().display()
-    );
-    let file = File::open(&filepath)
-        .with_context(|| format!("Failed to open `{}`", filepath.as_ref().display()))?;
-    let mut pseudocode = String::new();
-    BufReader::new(file)
-        .read_to_string(&mut pseudocode)
-        .with_context(|| format!("Failed to read from `{}`", filepath.as_ref().display()))?;
-
-    // Submit pseudocode to the local LLM for analysis.
-    let mut sp = Spinner::new(
-        Spinners::SimpleDotsScrolling,
-        "Querying the Oneiromancer".into(),
-    );
-    let analysis_results = Oneiromancer::new()
-        .analyze_code(&pseudocode)
-        .context("Failed to analyze pseudocode")?;
-    sp.stop_with_message("[+] Successfully analyzed pseudocode".into());
-    println!();
-
-    // Create a function description.
-    let function_description = format_description(&analysis_results);
-    print!("{function_description}");
-
-    // Apply variable renaming suggestions.
-    println!("[-] Variable renaming suggestions:");
-    for variable in analysis_results.variables() {
-        println!(
-            "    {}\t-> {}",
-            variable.original_name(),
-            variable.new_name()
-        );
-    }
-    pseudocode = apply_renames(&pseudocode, analysis_results.variables())
-        .context("Failed to apply variable renames")?;
-
-    // Save the improved pseudocode to an output file.
-    let outfilepath = filepath.as_ref().with_extension("out.c");
-    println!();
-    println!(
-        "[*] Saving improved pseudocode in `{}`",
-        outfilepath.display()
-    );
-
-    let mut writer = BufWriter::new(
-        File::create_new(&outfilepath)
-            .with_context(|| format!("Failed to create `{}`", outfilepath.display()))?,
-    );
-    writer
-        .write_all(function_description.as_bytes())
-        .context("Failed to write to file")?;
-    writer
-        .write_all(pseudocode.as_bytes())
-        .context("Failed to write to file")?;
-    writer
-        .flush()
-        .context("Failed to flush the output stream")?;
-
-    println!("[+] Done analyzing pseudocode");
-    Ok(())
+/// Each original name is replaced where it occurs as an identifier (not in comments, string and
+/// character literals, or preprocessor directives), and suggestions whose original name is not
+/// found are skipped.
+#[must_use]
+pub fn rename_variables(pseudocode: &str, variables: &[Variable]) -> String {
+    splice_renames(pseudocode, variables, &find_renames(pseudocode, variables))
 }
 
-/// Formats `results` as a Phrack-style block comment, wrapping to 76 columns.
-fn format_description(results: &OneiromancerResults) -> String {
-    let options = textwrap::Options::new(76)
-        .initial_indent(" * ")
-        .subsequent_indent(" * ");
-    format!(
-        "/*\n * {}()\n *\n{}\n */\n\n",
-        results.function_name(),
-        textwrap::fill(results.comment(), &options)
-    )
-}
-
-/// Applies variable renaming suggestions to `pseudocode` using whole-word regex substitution.
-///
-/// Assumes LLM-suggested names are collision-safe so renaming order cannot corrupt later replacements.
-fn apply_renames(pseudocode: &str, variables: &[Variable]) -> anyhow::Result<String> {
-    let mut result = pseudocode.to_owned();
-    for variable in variables {
-        let re = Regex::new(&format!(r"\b{}\b", regex::escape(variable.original_name())))
-            .context("Failed to compile regex")?;
-        result = re.replace_all(&result, variable.new_name()).into();
+/// Replaces the `matches` found by [`find_renames`] in `pseudocode` with the new names of the
+/// corresponding `variables`.
+fn splice_renames(pseudocode: &str, variables: &[Variable], matches: &[RenameMatch]) -> String {
+    let mut result = String::with_capacity(pseudocode.len());
+    let mut last: usize = 0;
+    for found in matches {
+        let range = found.range();
+        result.push_str(pseudocode.get(last..range.start).unwrap_or_default());
+        result.push_str(
+            variables
+                .get(found.variable())
+                .map(Variable::new_name)
+                .unwrap_or_default(),
+        );
+        last = range.end;
     }
-    Ok(result)
+    result.push_str(pseudocode.get(last..).unwrap_or_default());
+    result
 }
 
 #[cfg(test)]
 #[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
-#[expect(clippy::expect_used, reason = "tests can use `expect`")]
 mod tests {
     /// Pure logic tests.
+    #[cfg(feature = "format")]
     mod helpers {
-        use super::super::*;
-        use crate::{OneiromancerResults, Variable};
+        use crate::format::format_description;
+        use crate::{CommentOptions, OneiromancerResults};
 
         #[test]
         fn format_description_produces_phrack_style_header() -> anyhow::Result<()> {
@@ -136,7 +265,7 @@ mod tests {
                 r#"{"function_name":"main","comment":"Entry point of the program.","variables":[]}"#,
             )?;
 
-            let desc = format_description(&results);
+            let desc = format_description(&results, &CommentOptions::default());
 
             assert!(desc.starts_with("/*\n * main()\n *\n"), "unexpected header");
             assert!(desc.ends_with(" */\n\n"), "unexpected footer");
@@ -154,7 +283,7 @@ mod tests {
                 format!(r#"{{"function_name":"foo","comment":"{long_comment}","variables":[]}}"#);
             let results: OneiromancerResults = serde_json::from_str(&json)?;
 
-            let desc = format_description(&results);
+            let desc = format_description(&results, &CommentOptions::default());
 
             for line in desc.lines() {
                 assert!(line.len() <= 76, "line exceeds 76 columns: {line:?}");
@@ -167,63 +296,14 @@ mod tests {
 
             Ok(())
         }
-
-        #[test]
-        fn apply_renames_substitutes_whole_words() -> anyhow::Result<()> {
-            let variables: Vec<Variable> =
-                serde_json::from_str(r#"[{"original_name":"v1","new_name":"counter"}]"#)?;
-
-            let result = apply_renames("int v1 = 0; v1++;", &variables)?;
-
-            assert_eq!(result, "int counter = 0; counter++;");
-
-            Ok(())
-        }
-
-        #[test]
-        fn apply_renames_does_not_match_substrings() -> anyhow::Result<()> {
-            let variables: Vec<Variable> =
-                serde_json::from_str(r#"[{"original_name":"len","new_name":"length"}]"#)?;
-
-            let result = apply_renames("strlen(len)", &variables)?;
-
-            assert_eq!(result, "strlen(length)");
-
-            Ok(())
-        }
-
-        #[test]
-        fn apply_renames_with_no_variables_is_identity() -> anyhow::Result<()> {
-            let pseudocode = "int v1 = 0;";
-
-            let result = apply_renames(pseudocode, &[])?;
-
-            assert_eq!(result, pseudocode);
-
-            Ok(())
-        }
-
-        #[test]
-        fn apply_renames_applies_multiple_variables_independently() -> anyhow::Result<()> {
-            let variables: Vec<Variable> = serde_json::from_str(
-                r#"[{"original_name":"v1","new_name":"index"},{"original_name":"v2","new_name":"count"}]"#,
-            )?;
-
-            let result = apply_renames("int v1 = 0; int v2 = 0; v1 += v2;", &variables)?;
-
-            assert_eq!(result, "int index = 0; int count = 0; index += count;");
-
-            Ok(())
-        }
     }
 
     /// Tests for the Ollama-dependent public API.
     mod api {
         use std::env;
-        use std::fs::{self, File};
 
         use crate::oneiromancer::{OLLAMA_BASEURL, OLLAMA_MODEL};
-        use crate::{Oneiromancer, OneiromancerError, run};
+        use crate::{Oneiromancer, OneiromancerError};
 
         const VALID_PSEUDOCODE: &str = r#"int main() { int v1 = 0; printf("Hello, world!"); }"#;
         const VALID_PSEUDOCODE_FILEPATH: &str = "./tests/data/hello.c";
@@ -327,70 +407,5 @@ mod tests {
                 "wrong error type returned: {result:?}"
             );
         }
-
-        #[test]
-        #[ignore = "requires a live Ollama instance"]
-        fn run_works() -> anyhow::Result<()> {
-            let tmpdir = tempfile::tempdir()?;
-            let filepath = tmpdir.path().join("test.c");
-            fs::copy(VALID_PSEUDOCODE_FILEPATH, &filepath)?;
-            let outfile = tmpdir.path().join("test.out.c");
-
-            run(&filepath)?;
-
-            assert!(outfile.exists(), "output file {outfile:?} does not exist");
-            assert!(
-                outfile.metadata()?.len() > 0,
-                "output file {outfile:?} is empty"
-            );
-
-            Ok(())
-        }
-
-        #[test]
-        #[ignore = "requires a live Ollama instance"]
-        fn run_fails_if_output_file_already_exists() -> anyhow::Result<()> {
-            let tmpdir = tempfile::tempdir()?;
-            let filepath = tmpdir.path().join("test.c");
-            fs::copy(VALID_PSEUDOCODE_FILEPATH, &filepath)?;
-            let outfile = tmpdir.path().join("test.out.c");
-            File::create(&outfile)?;
-
-            let result = run(&filepath);
-
-            assert!(result.is_err(), "run succeeded unexpectedly");
-            assert!(
-                outfile.metadata()?.len() == 0,
-                "output file was overwritten"
-            );
-
-            Ok(())
-        }
-
-        #[test]
-        #[ignore = "requires a live Ollama instance"]
-        fn run_with_empty_file_fails() {
-            let tmpdir = tempfile::tempdir().expect("failed to create temporary directory");
-            let filepath = tmpdir.path().join("test.c");
-            File::create(&filepath).expect("failed to create test file");
-            let outfile = tmpdir.path().join("test.out.c");
-
-            let result = run(&filepath);
-
-            assert!(result.is_err(), "run succeeded unexpectedly");
-            assert!(!outfile.exists(), "output file {outfile:?} exists");
-        }
-
-        #[test]
-        fn run_with_invalid_input_filepath_fails() {
-            let tmpdir = tempfile::tempdir().expect("failed to create temporary directory");
-            let filepath = tmpdir.path().join("test.c");
-            let outfile = tmpdir.path().join("test.out.c");
-
-            let result = run(&filepath);
-
-            assert!(result.is_err(), "run succeeded unexpectedly");
-            assert!(!outfile.exists(), "output file {outfile:?} exists");
-        }
     }
 }
@@ -0,0 +1,316 @@
+//! Submit prompts to the native `/completion` endpoint of llama.cpp server, with its grammar and
+//! JSON schema constraints.
+
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use ureq::Agent;
+
+use crate::{BackendRequest, LlmBackend, OneiromancerError, Timeouts};
+
+/// Default base URL of llama.cpp server.
+pub const LLAMACPP_BASEURL: &str = "http://127.0.0.1:8080";
+
+/// Model options passed through to completion requests, with their name in the llama.cpp API.
+/// The other options are specific to Ollama (e.g., `num_ctx`, set when starting the server) and
+/// are left out.
+const COMPLETION_OPTIONS: [(&str, &str); 16] = [
+    ("temperature", "temperature"),
+    ("top_k", "top_k"),
+    ("top_p", "top_p"),
+    ("min_p", "min_p"),
+    ("typical_p", "typical_p"),
+    ("seed", "seed"),
+    ("stop", "stop"),
+    ("num_predict", "n_predict"),
+    ("num_keep", "n_keep"),
+    ("repeat_penalty", "repeat_penalty"),
+    ("repeat_last_n", "repeat_last_n"),
+    ("presence_penalty", "presence_penalty"),
+    ("frequency_penalty", "frequency_penalty"),
+    ("mirostat", "mirostat"),
+    ("mirostat_tau", "mirostat_tau"),
+    ("mirostat_eta", "mirostat_eta"),
+];
+
+/// [`LlmBackend`] that submits prompts to the native `/completion` endpoint of llama.cpp server,
+/// constraining the response with a grammar or a JSON schema.
+///
+/// By default, responses are constrained to the JSON schema of the analysis results (or of the
+/// array of results of a joint analysis), so that even small models return well-formed JSON. Set
+/// another schema with [`json_schema`](LlamaCppBackend::json_schema), or a GBNF grammar with
+/// [`grammar`](LlamaCppBackend::grammar). The server serves the model it was started with, so the
+/// model name is not sent; the model options that llama.cpp supports (e.g., `temperature`,
+/// `seed`, and `num_predict` as `n_predict`) are passed through, while the others are ignored.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> anyhow::Result<()> {
+/// use oneiromancer::{LLAMACPP_BASEURL, LlamaCppBackend, Oneiromancer};
+///
+/// let results = Oneiromancer::new()
+///     .option("temperature", 0)
+///     .backend(LlamaCppBackend::new(LLAMACPP_BASEURL))
+///     .analyze_code("int main() { return 0; }")?;
+///
+/// dbg!(results.function_name());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct LlamaCppBackend {
+    /// Base URL of llama.cpp server.
+    baseurl: String,
+    /// JSON schema the generated text must conform to, if any (converted to a grammar by the
+    /// server).
+    json_schema: Option<Value>,
+    /// GBNF grammar the generated text must conform to, if any.
+    grammar: Option<String>,
+    /// HTTP agent, shared by all clones.
+    agent: Agent,
+}
+
+impl LlamaCppBackend {
+    /// Creates a new [`LlamaCppBackend`] for llama.cpp server at `baseurl` (e.g.,
+    /// [`LLAMACPP_BASEURL`]), constraining responses to the schema of the analysis results.
+    #[must_use]
+    pub fn new(baseurl: impl Into<String>) -> Self {
+        Self {
+            baseurl: baseurl.into(),
+            json_schema: Some(results_schema()),
+            grammar: None,
+            agent: Agent::new_with_defaults(),
+        }
+    }
+
+    /// Constrains responses to the JSON `schema`, replacing the default schema or any grammar.
+    /// `null` disables the constraint.
+    #[must_use]
+    pub fn json_schema(mut self, schema: Value) -> Self {
+        self.json_schema = Some(schema).filter(|value| !value.is_null());
+        self.grammar = None;
+        self
+    }
+
+    /// Constrains responses to the GBNF `grammar`, replacing the JSON schema. An empty grammar
+    /// disables the constraint.
+    #[must_use]
+    pub fn grammar(mut self, gbnf: impl Into<String>) -> Self {
+        self.grammar = Some(gbnf.into()).filter(|grammar| !grammar.is_empty());
+        self.json_schema = None;
+        self
+    }
+
+    /// Sets the [`Timeouts`] of the requests to the server (none by default).
+    #[must_use]
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.agent = timeouts.agent();
+        self
+    }
+}
+
+impl LlmBackend for LlamaCppBackend {
+    fn send(&self, request: &BackendRequest<'_>) -> Result<String, OneiromancerError> {
+        let url = format!("{}{}", self.baseurl.trim_end_matches('/'), "/completion");
+        log::debug!(
+            "Sending completion request to `{url}` ({} bytes of prompt)",
+            request.prompt().len()
+        );
+        let body = CompletionRequest {
+            prompt: request.prompt(),
+            stream: false,
+            json_schema: self.json_schema.as_ref(),
+            grammar: self.grammar.as_deref(),
+            options: completion_options(request.options()),
+        };
+        let started = Instant::now();
+        let response = self
+            .agent
+            .post(url)
+            .send_json(&body)?
+            .body_mut()
+            .read_json::<CompletionResponse>()?;
+        log::debug!(
+            "Received completion in {} ms",
+            started.elapsed().as_millis()
+        );
+        response.content()
+    }
+}
+
+/// llama.cpp server completion request.
+#[derive(Serialize, Debug)]
+struct CompletionRequest<'a> {
+    /// Input prompt to complete.
+    prompt: &'a str,
+    /// Whether to stream the response.
+    stream: bool,
+    /// JSON schema the response must conform to, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    json_schema: Option<&'a Value>,
+    /// GBNF grammar the response must conform to, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    grammar: Option<&'a str>,
+    /// Model options, at the top level of the request.
+    #[serde(flatten)]
+    options: BTreeMap<&'static str, &'a Value>,
+}
+
+/// llama.cpp server completion response.
+#[derive(Deserialize, Debug)]
+struct CompletionResponse {
+    /// Generated text.
+    content: Option<String>,
+}
+
+impl CompletionResponse {
+    /// Returns the generated text.
+    fn content(self) -> Result<String, OneiromancerError> {
+        self.content
+            .ok_or_else(|| ureq::Error::Other("no content in completion response".into()))
+            .map_err(OneiromancerError::from)
+    }
+}
+
+/// Returns the model `options` supported by llama.cpp server, with their names in its API.
+fn completion_options(options: &BTreeMap<String, Value>) -> BTreeMap<&'static str, &Value> {
+    COMPLETION_OPTIONS
+        .into_iter()
+        .filter_map(|(ollama, llamacpp)| options.get(ollama).map(|value| (llamacpp, value)))
+        .collect()
+}
+
+/// Returns the JSON schema of the analysis results, or of the array of results of a joint
+/// analysis, each with the `original_name` of its function.
+fn results_schema() -> Value {
+    let variables = json!({
+        "type": "array",
+        "items": {
+            "type": "object",
+            "properties": {
+                "original_name": {"type": "string"},
+                "new_name": {"type": "string"},
+            },
+            "required": ["original_name", "new_name"],
+        },
+    });
+    json!({
+        "anyOf": [
+            {
+                "type": "object",
+                "properties": {
+                    "function_name": {"type": "string"},
+                    "comment": {"type": "string"},
+                    "variables": variables,
+                },
+                "required": ["function_name", "comment", "variables"],
+            },
+            {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "original_name": {"type": "string"},
+                        "function_name": {"type": "string"},
+                        "comment": {"type": "string"},
+                        "variables": variables,
+                    },
+                    "required": ["original_name", "function_name", "comment", "variables"],
+                },
+            },
+        ],
+    })
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use super::*;
+    use crate::options::ModelOptions;
+    use crate::{OneiromancerResults, parse_joint_response};
+
+    #[test]
+    fn completion_request_maps_shared_options() -> anyhow::Result<()> {
+        let options: BTreeMap<String, Value> =
+            serde_json::from_str(r#"{"temperature":0,"num_predict":256,"num_ctx":8192}"#)?;
+        let request = CompletionRequest {
+            prompt: "p",
+            stream: false,
+            json_schema: None,
+            grammar: Some("root ::= \"{}\""),
+            options: completion_options(&options),
+        };
+
+        assert_eq!(
+            serde_json::to_value(&request)?,
+            serde_json::from_str::<Value>(
+                r#"{"prompt":"p","stream":false,"grammar":"root ::= \"{}\"","n_predict":256,"temperature":0}"#
+            )?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn backend_builders_replace_the_constraint() {
+        let default = LlamaCppBackend::new(LLAMACPP_BASEURL);
+        assert!(default.json_schema.is_some(), "no default schema");
+
+        let grammar = default.grammar("root ::= \"{}\"");
+        assert!(
+            grammar.grammar.is_some() && grammar.json_schema.is_none(),
+            "grammar not set: {grammar:?}"
+        );
+        let unconstrained = grammar.json_schema(Value::Null);
+        assert!(
+            unconstrained.grammar.is_none() && unconstrained.json_schema.is_none(),
+            "constraint not disabled: {unconstrained:?}"
+        );
+    }
+
+    #[test]
+    fn results_schema_describes_both_response_shapes() -> anyhow::Result<()> {
+        let schema = results_schema();
+        let shapes = schema
+            .get("anyOf")
+            .and_then(Value::as_array)
+            .map(|shapes| {
+                shapes
+                    .iter()
+                    .filter_map(|shape| shape.get("type").and_then(Value::as_str))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        assert_eq!(shapes, ["object", "array"]);
+
+        // The examples of both shapes are parsed as the schema requires.
+        serde_json::from_str::<OneiromancerResults>(
+            r#"{"function_name":"f","comment":"c","variables":[]}"#,
+        )?;
+        assert_eq!(
+            parse_joint_response(
+                r#"[{"original_name":"sub_1","function_name":"f","comment":"c","variables":[]}]"#
+            )?
+            .len(),
+            1
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn llamacpp_backend_with_wrong_url_fails() {
+        let options = ModelOptions::default();
+        let request = BackendRequest::new("m", "int main();", &options, None);
+
+        let result = LlamaCppBackend::new("http://127.0.0.1:6666").send(&request);
+
+        assert!(
+            matches!(result, Err(OneiromancerError::OllamaQueryFailed(_))),
+            "wrong error type returned: {result:?}"
+        );
+    }
+}
@@ -0,0 +1,102 @@
+//! Convert byte offsets in source text to line and column positions.
+
+use std::iter;
+
+/// Position in source text, with 1-based line and column numbers. Columns are counted in Unicode
+/// code points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    /// Line number, starting from 1.
+    line: usize,
+    /// Column number, starting from 1.
+    column: usize,
+}
+
+impl Position {
+    /// Gets the line number, starting from 1.
+    pub const fn line(self) -> usize {
+        self.line
+    }
+
+    /// Gets the column number, starting from 1.
+    pub const fn column(self) -> usize {
+        self.column
+    }
+}
+
+/// Index of the starts of the lines in source text, to convert byte offsets to [`Position`]s
+/// without scanning the text again for each offset.
+#[derive(Debug, Clone)]
+pub struct LineIndex<'a> {
+    /// Indexed source text.
+    text: &'a str,
+    /// Byte offsets of the starts of the lines, in order.
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    /// Indexes the lines of `text`.
+    pub fn new(text: &'a str) -> Self {
+        let starts = iter::once(0)
+            .chain(
+                text.match_indices('\n')
+                    .map(|(offset, _)| offset.saturating_add(1)),
+            )
+            .collect();
+        Self { text, starts }
+    }
+
+    /// Returns the [`Position`] of the byte `offset` in the text (offsets past the end are
+    /// clamped to the end of the text).
+    pub fn position(&self, offset: usize) -> Position {
+        let clamped = offset.min(self.text.len());
+        let index = self
+            .starts
+            .partition_point(|&start| start <= clamped)
+            .saturating_sub(1);
+        let start = self.starts.get(index).copied().unwrap_or_default();
+        let column = self
+            .text
+            .get(start..clamped)
+            .map_or(0, |prefix| prefix.chars().count());
+        Position {
+            line: index.saturating_add(1),
+            column: column.saturating_add(1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_index_converts_offsets_to_positions() {
+        let text = "int main()\n{\n  int v1;\n}\n";
+        let index = LineIndex::new(text);
+        let at = |offset| {
+            let position = index.position(offset);
+            (position.line(), position.column())
+        };
+
+        assert_eq!(at(0), (1, 1));
+        assert_eq!(at(4), (1, 5));
+        assert_eq!(at(10), (1, 11), "newline belongs to its line");
+        assert_eq!(at(11), (2, 1));
+        assert_eq!(text.find("v1").map(at), Some((3, 7)));
+        assert_eq!(at(text.len()), (5, 1));
+        assert_eq!(at(usize::MAX), (5, 1), "offset not clamped");
+    }
+
+    #[test]
+    fn line_index_counts_columns_in_code_points() {
+        let text = "/* caf\u{e9} */ int v1;";
+        let index = LineIndex::new(text);
+
+        let position = text.find("v1").map(|offset| index.position(offset));
+        assert_eq!(
+            position.map(|found| (found.line(), found.column())),
+            Some((1, 16))
+        );
+    }
+}
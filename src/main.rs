@@ -1,9 +1,37 @@
 //! main.rs.
 
+use std::borrow::Cow;
 use std::env;
-use std::ffi::OsStr;
-use std::path::Path;
-use std::process::ExitCode;
+use std::error;
+use std::ffi::OsString;
+use std::fmt::Display;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::net::TcpListener;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process::{self, ExitCode};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use anyhow::Context as _;
+use clap::builder::NonEmptyStringValueParser;
+use clap::{ArgAction, CommandFactory as _, Parser, Subcommand, ValueEnum as _};
+use clap_complete::engine::{ArgValueCandidates, CompletionCandidate};
+use clap_complete::{CompleteEnv, Shell};
+use env_logger::filter::{Builder, Filter};
+use indicatif::{ProgressBar, ProgressStyle};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use oneiromancer::{
+    AidapalError, AnalysisContext, AnalysisReport, AnnotationField, ApplyOptions, ArchiveError,
+    ArchiveFormat, AuditLog, Backend, BinaryError, CollisionPolicy, ColorChoice, CommentOptions,
+    CommentStyle, CommentWidth, Config, ConfigError, Decompiler, DiscoverError, DiscoverOptions,
+    FunctionSelector, History, HistoryError, IMPROVED_DIRNAME, InputError, InputFormat,
+    InputPolicy, Interrupt, LlamaCppBackend, MIN_COMMENT_LENGTH, MIN_RENAMES, Oneiromancer,
+    OneiromancerError, OpenAiBackend, OutputError, PromptOptions, QualityPolicy, RENAMES_EXTENSION,
+    RenameMap, ReportFormat, ResponseCache, RetryPolicy, RunOptions, RunSummary, STATE_FILENAME,
+    SelectError, ServeOptions, Setting, SettingSource, Timeouts,
+};
 
 /// Package name.
 const PROGRAM: &str = env!("CARGO_PKG_NAME");
@@ -12,40 +40,2400 @@ const VERSION: &str = env!("CARGO_PKG_VERSION");
 /// Package authors.
 const AUTHORS: &str = env!("CARGO_PKG_AUTHORS");
 
-fn main() -> ExitCode {
-    eprintln!("{PROGRAM} {VERSION} - GenAI assistant for pseudocode analysis");
-    eprintln!("Copyright (c) 2025-2026 {AUTHORS}");
-    eprintln!();
+/// Subcommands recognized on the command line, besides `help`.
+const SUBCOMMANDS: &[&str] = &[
+    "analyze",
+    "bench",
+    "cache",
+    "completions",
+    "config",
+    "doctor",
+    "help",
+    "history",
+    "mcp",
+    "models",
+    "repl",
+    "serve",
+    "undo",
+];
+/// Template of the progress bar of model downloads.
+const PULL_TEMPLATE: &str =
+    "[{bar:30}] {bytes}/{total_bytes} ({bytes_per_sec}, ETA {eta}) {wide_msg}";
+/// Global options that take a value, which may precede the subcommand.
+const GLOBAL_OPTIONS: &[&str] = &[
+    "--backend",
+    "--base-url",
+    "--model",
+    "--retries",
+    "--connect-timeout",
+    "--read-timeout",
+    "--config",
+    "--color",
+];
+/// Global flags that may precede the subcommand, besides clusters of short flags (e.g., `-vv`).
+const GLOBAL_FLAGS: &[&str] = &["--preflight", "--quiet", "--verbose"];
 
-    // Parse command line arguments.
-    let mut args = env::args_os();
-    let argv0 = args.next().unwrap_or_else(|| PROGRAM.into());
-    let is_help = |a: &OsStr| a == OsStr::new("-h") || a == OsStr::new("--help");
-
-    let prog = Path::new(&argv0)
-        .file_name()
-        .and_then(|s| s.to_str())
-        .unwrap_or(PROGRAM);
-
-    let filename = match (args.next(), args.next()) {
-        (Some(arg), None) if !is_help(&arg) => arg,
-        _ => return usage(prog),
+/// Exit codes, listed at the end of `--help`.
+const EXIT_CODES_HELP: &str = "\
+Exit codes:
+  0  Success
+  1  Generic failure (e.g., files in a batch failed for different reasons)
+  2  Usage or configuration error
+  3  Input file problem (e.g., missing, unreadable, or invalid target file)
+  4  Ollama server unreachable, timed out, or failed
+  5  Model missing on the Ollama server
+  6  Malformed or low-quality (with --strict) response from the model
+  7  Failure in writing output files
+130  Batch interrupted by Ctrl-C (run again to resume)";
+
+/// Outcome of a run, mapped to a distinct exit code for scripting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Exit {
+    /// Success.
+    Success,
+    /// Generic failure.
+    Failure,
+    /// Usage or configuration error.
+    Usage,
+    /// Input file problem.
+    Input,
+    /// Ollama server unreachable, timed out, or failed.
+    Server,
+    /// Model missing on the Ollama server.
+    ModelMissing,
+    /// Malformed response from the model.
+    Parse,
+    /// Failure in writing output files.
+    Output,
+    /// Batch interrupted by Ctrl-C.
+    Interrupted,
+}
+
+impl Exit {
+    /// Returns [`Exit::Success`] if `ok` is set, or [`Exit::Failure`] otherwise.
+    const fn from_success(ok: bool) -> Self {
+        if ok { Self::Success } else { Self::Failure }
+    }
+
+    /// Returns the exit code, as documented in [`EXIT_CODES_HELP`].
+    const fn code(self) -> u8 {
+        match self {
+            Self::Success => 0,
+            Self::Failure => 1,
+            Self::Usage => 2,
+            Self::Input => 3,
+            Self::Server => 4,
+            Self::ModelMissing => 5,
+            Self::Parse => 6,
+            Self::Output => 7,
+            Self::Interrupted => 130,
+        }
+    }
+
+    /// Returns the outcome that corresponds to `err`, based on the first error in its chain with a
+    /// known type, or [`Exit::Failure`] if there is none.
+    fn from_error(err: &anyhow::Error) -> Self {
+        err.chain()
+            .find_map(Self::from_cause)
+            .unwrap_or(Self::Failure)
+    }
+
+    /// Returns the outcome that corresponds to `cause`, if its type is known.
+    fn from_cause(cause: &(dyn error::Error + 'static)) -> Option<Self> {
+        if let Some(err) = cause.downcast_ref::<OneiromancerError>() {
+            return Some(Self::from_analysis_error(err));
+        }
+        if let Some(err) = cause.downcast_ref::<DiscoverError>() {
+            return Some(Self::from_discover_error(err));
+        }
+        if let Some(err) = cause.downcast_ref::<ArchiveError>() {
+            return Some(Self::from_archive_error(err));
+        }
+        if let Some(err) = cause.downcast_ref::<BinaryError>() {
+            return Some(Self::from_binary_error(err));
+        }
+        if cause.is::<InputError>() || cause.is::<AidapalError>() || cause.is::<SelectError>() {
+            return Some(Self::Input);
+        }
+        if cause.is::<ConfigError>() || cause.is::<HistoryError>() {
+            return Some(Self::Usage);
+        }
+        if cause.is::<OutputError>() {
+            return Some(Self::Output);
+        }
+        cause.downcast_ref::<CliError>().map(|err| match *err {
+            CliError::Usage(_) => Self::Usage,
+            CliError::NoInput => Self::Input,
+        })
+    }
+
+    /// Returns the outcome that corresponds to a discovery `err`.
+    const fn from_discover_error(err: &DiscoverError) -> Self {
+        match *err {
+            DiscoverError::InvalidPattern(_) | DiscoverError::InvalidInputPattern(_) => Self::Usage,
+            DiscoverError::ExpansionFailed(_) | DiscoverError::NoMatches(_) => Self::Input,
+            // New variants must be mapped above (see the unit tests).
+            _ => Self::Failure,
+        }
+    }
+
+    /// Returns the outcome that corresponds to an archive `err`.
+    const fn from_archive_error(err: &ArchiveError) -> Self {
+        match *err {
+            ArchiveError::InvalidPattern(_) => Self::Usage,
+            ArchiveError::UnsupportedFormat
+            | ArchiveError::Io(_)
+            | ArchiveError::Zip(_)
+            | ArchiveError::UnsafePath(_) => Self::Input,
+            // New variants must be mapped above (see the unit tests).
+            _ => Self::Failure,
+        }
+    }
+
+    /// Returns the outcome that corresponds to a binary decompilation `err`.
+    const fn from_binary_error(err: &BinaryError) -> Self {
+        match *err {
+            BinaryError::Unsupported => Self::Usage,
+            BinaryError::OpenFailed(..)
+            | BinaryError::DecompilerUnavailable(_)
+            | BinaryError::NotFound(..) => Self::Input,
+            // New variants must be mapped above (see the unit tests).
+            _ => Self::Failure,
+        }
+    }
+
+    /// Returns the outcome that corresponds to an analysis `err`.
+    const fn from_analysis_error(err: &OneiromancerError) -> Self {
+        match *err {
+            OneiromancerError::InvalidInput(_) | OneiromancerError::FileReadFailed(_) => {
+                Self::Input
+            }
+            OneiromancerError::OllamaQueryFailed(ureq::Error::StatusCode(404))
+            | OneiromancerError::ModelNotFound { .. } => Self::ModelMissing,
+            OneiromancerError::OllamaQueryFailed(ureq::Error::Json(_))
+            | OneiromancerError::ResponseParseFailed(_)
+            | OneiromancerError::LowQualityResult(_) => Self::Parse,
+            OneiromancerError::OllamaQueryFailed(_)
+            | OneiromancerError::Timeout(_)
+            | OneiromancerError::ServerUnreachable { .. } => Self::Server,
+            // New variants must be mapped above (see the unit tests).
+            _ => Self::Failure,
+        }
+    }
+
+    /// Returns the outcome of a batch with `summary`: [`Exit::Interrupted`] if it was interrupted,
+    /// the outcome shared by all failures, if any, or [`Exit::Failure`] if failures differ.
+    fn from_summary(summary: &RunSummary) -> Self {
+        if summary.is_interrupted() {
+            return Self::Interrupted;
+        }
+        let mut outcomes = summary
+            .failed()
+            .iter()
+            .map(|failure| Self::from_error(&failure.1));
+        match outcomes.next() {
+            None => Self::Success,
+            Some(first) if outcomes.all(|outcome| outcome == first) => first,
+            Some(_) => Self::Failure,
+        }
+    }
+}
+
+/// Command line errors.
+#[derive(thiserror::Error, Debug)]
+enum CliError {
+    /// Invalid combination of arguments.
+    #[error("{0}")]
+    Usage(&'static str),
+    /// No pseudocode files found among the target files.
+    #[error("No pseudocode files found")]
+    NoInput,
+}
+
+/// Command line arguments.
+#[derive(Parser, Debug)]
+#[command(
+    name = PROGRAM,
+    version,
+    about = "GenAI assistant for pseudocode analysis",
+    after_help = EXIT_CODES_HELP
+)]
+struct Cli {
+    /// Arguments shared by all subcommands.
+    #[command(flatten)]
+    global: GlobalArgs,
+
+    /// Subcommand to run (`analyze` if omitted).
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// Command line arguments shared by all subcommands.
+#[derive(clap::Args, Debug)]
+struct GlobalArgs {
+    /// Inference API to submit prompts to (`openai` for OpenAI-compatible servers, with the API
+    /// key in `$OPENAI_API_KEY`, if needed; `llama-cpp` for the native API of llama.cpp server,
+    /// with responses constrained to valid results) [default: `ollama`].
+    #[arg(long, global = true, value_enum, value_name = "API")]
+    backend: Option<Backend>,
+
+    /// API base URL [default: `$OLLAMA_BASEURL`, or `http://127.0.0.1:11434`; with `--backend
+    /// openai`, `$OPENAI_BASE_URL`, or `http://127.0.0.1:8080/v1`; with `--backend llama-cpp`,
+    /// `$LLAMACPP_BASEURL`, or `http://127.0.0.1:8080`].
+    #[arg(long, global = true, value_name = "URL")]
+    base_url: Option<String>,
+
+    /// Ollama model [default: `$OLLAMA_MODEL`, or `aidapal`].
+    #[arg(
+        long,
+        global = true,
+        value_name = "NAME",
+        add = ArgValueCandidates::new(model_candidates)
+    )]
+    model: Option<String>,
+
+    /// Number of times a request that fails for a transient reason (connection reset, `5xx`
+    /// response, or timeout) is retried, waiting longer after each failure.
+    #[arg(long, global = true, value_name = "N", default_value_t = 0)]
+    retries: usize,
+
+    /// Maximum time to connect to the API, in seconds [default: none].
+    #[arg(long, global = true, value_name = "SECS")]
+    connect_timeout: Option<u64>,
+
+    /// Maximum time to receive a response once a request is sent, including the generation, in
+    /// seconds (exit code 4 if exceeded) [default: none].
+    #[arg(long, global = true, value_name = "SECS")]
+    read_timeout: Option<u64>,
+
+    /// Check that the Ollama server is reachable and the model is available before the first
+    /// request, failing with a hint on how to fix the setup (exit code 4 or 5).
+    #[arg(long, global = true)]
+    preflight: bool,
+
+    /// Configuration file [default: platform-specific, e.g., `~/.config/oneiromancer/config.toml`].
+    #[arg(long, global = true, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Print errors only (no banner, status messages, or spinners).
+    #[arg(long, short, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Print more diagnostics (`-v` for info, `-vv` for debug, `-vvv` for trace).
+    #[arg(long, short, global = true, action = ArgAction::Count)]
+    verbose: u8,
+
+    /// When to color status messages (`auto` disables colors if `NO_COLOR` is set).
+    #[arg(long, global = true, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+}
+
+impl GlobalArgs {
+    /// Returns the maximum level of diagnostics to print, based on `--quiet` and `--verbose`.
+    const fn log_level(&self) -> LevelFilter {
+        match (self.quiet, self.verbose) {
+            (true, _) => LevelFilter::Error,
+            (false, 0) => LevelFilter::Warn,
+            (false, 1) => LevelFilter::Info,
+            (false, 2) => LevelFilter::Debug,
+            (false, _) => LevelFilter::Trace,
+        }
+    }
+
+    /// Returns the environment variable and the default value of the base URL of the backend.
+    fn base_url_defaults(&self) -> (&'static str, &'static str) {
+        if self.backend == Some(Backend::OpenAi) {
+            ("OPENAI_BASE_URL", oneiromancer::OPENAI_BASEURL)
+        } else if self.backend == Some(Backend::LlamaCpp) {
+            ("LLAMACPP_BASEURL", oneiromancer::LLAMACPP_BASEURL)
+        } else {
+            ("OLLAMA_BASEURL", oneiromancer::OLLAMA_BASEURL)
+        }
+    }
+
+    /// Returns the base URL of a backend other than Ollama, from `--base-url`, the environment, or
+    /// the default.
+    fn backend_base_url(&self) -> String {
+        let (url_var, default_url) = self.base_url_defaults();
+        self.base_url
+            .clone()
+            .or_else(|| env::var(url_var).ok())
+            .unwrap_or_else(|| default_url.to_owned())
+    }
+
+    /// Returns the [`Timeouts`] of the requests to the API set on the command line.
+    fn timeouts(&self) -> Timeouts {
+        let mut timeouts = Timeouts::new();
+        if let Some(secs) = self.connect_timeout {
+            timeouts = timeouts.connect(Duration::from_secs(secs));
+        }
+        if let Some(secs) = self.read_timeout {
+            timeouts = timeouts.read(Duration::from_secs(secs));
+        }
+        timeouts
+    }
+
+    /// Builds the [`Oneiromancer`] client for the backend, overriding the base URL and model if
+    /// specified, and retrying transient failures, enforcing timeouts, and checking the model
+    /// before the first request as requested.
+    fn client(&self) -> Oneiromancer {
+        let timeouts = self.timeouts();
+        let base = Oneiromancer::new()
+            .retries(
+                RetryPolicy::new().max_attempts(NonZeroUsize::MIN.saturating_add(self.retries)),
+            )
+            .timeouts(timeouts)
+            .preflight(self.preflight);
+        let client = if self.backend == Some(Backend::OpenAi) {
+            let backend = OpenAiBackend::new(self.backend_base_url())
+                .api_key(env::var("OPENAI_API_KEY").unwrap_or_default())
+                .timeouts(timeouts);
+            base.backend(backend)
+        } else if self.backend == Some(Backend::LlamaCpp) {
+            base.backend(LlamaCppBackend::new(self.backend_base_url()).timeouts(timeouts))
+        } else {
+            match self.base_url.as_deref() {
+                Some(baseurl) => base.baseurl(baseurl),
+                None => base,
+            }
+        };
+        match self.model.as_deref() {
+            Some(model) => client.model(model),
+            None => client,
+        }
+    }
+
+    /// Loads the configuration file specified on the command line, or the default one if it
+    /// exists.
+    fn load_config(&self) -> anyhow::Result<Config> {
+        let config = self.config.as_ref().map_or_else(
+            || Config::load_default().context("Failed to load default config"),
+            |path| {
+                Config::load(path)
+                    .with_context(|| format!("Failed to load config from `{}`", path.display()))
+            },
+        )?;
+        log::debug!("Resolved configuration: {config:?}");
+        Ok(config)
+    }
+}
+
+/// Subcommands.
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Analyze pseudocode files (the default if no subcommand is given).
+    Analyze(Box<AnalyzeArgs>),
+    /// Compare models on a corpus of pseudocode files (latency, tokens, parse failures, renames,
+    /// and comment length), with deterministic options (fixed seed, zero temperature).
+    Bench(BenchArgs),
+    /// Manage the on-disk cache of LLM responses.
+    #[command(subcommand)]
+    Cache(CacheCommand),
+    /// Initialize and inspect the configuration file.
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Diagnose setup problems (Ollama server, model, environment, and permissions).
+    Doctor,
+    /// Query the history of analyses, recorded with `--history` (or `history = true` in the
+    /// configuration file).
+    #[command(subcommand)]
+    History(HistoryCommand),
+    /// List the models available on the Ollama server, and download the configured one.
+    #[command(subcommand)]
+    Models(ModelsCommand),
+    /// Analyze snippets of pseudocode interactively, each terminated by a line with just `.` or by
+    /// EOF (type `:help` for commands). Nothing is written to disk unless saved with `:save`.
+    Repl(ReplArgs),
+    /// Run an HTTP server for editor plugins and web front ends: `POST /analyze` returns the
+    /// analysis of the pseudocode in the body as JSON, `POST /api/generate` answers Ollama requests
+    /// of the aidapal IDA plugin, and `GET /healthz` checks that the Ollama server is reachable and
+    /// the model is available.
+    Serve(ServeArgs),
+    /// Run a Model Context Protocol server on stdin and stdout, exposing the `analyze_pseudocode`
+    /// and `analyze_file` tools to LLM agents and IDE assistants.
+    Mcp,
+    /// Undo the variable renames applied to an output file, saved with `--emit-renames`, and write
+    /// the restored pseudocode to stdout (or to the `--output` file).
+    Undo(UndoArgs),
+    /// Generate a shell completion script, written to stdout.
+    ///
+    /// Installation:
+    ///
+    ///     bash:       oneiromancer completions bash > ~/.local/share/bash-completion/completions/oneiromancer
+    ///     zsh:        oneiromancer completions zsh > ~/.zfunc/_oneiromancer
+    ///                 (with `fpath+=~/.zfunc` before `compinit` in ~/.zshrc)
+    ///     fish:       oneiromancer completions fish > ~/.config/fish/completions/oneiromancer.fish
+    ///     powershell: oneiromancer completions powershell >> $PROFILE
+    ///
+    /// To also complete the model names available on the Ollama server for `--model`, register the
+    /// dynamic completer in your shell startup file instead:
+    ///
+    ///     bash:       source <(COMPLETE=bash oneiromancer)
+    ///     zsh:        source <(COMPLETE=zsh oneiromancer)
+    ///     fish:       COMPLETE=fish oneiromancer | source
+    ///     powershell: $env:COMPLETE = "powershell"; oneiromancer | Out-String | Invoke-Expression;
+    ///                 Remove-Item Env:\COMPLETE
+    #[command(verbatim_doc_comment)]
+    Completions {
+        /// Shell to generate the completion script for.
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+}
+
+/// Subcommands of the `cache` subcommand.
+#[derive(Subcommand, Debug)]
+enum CacheCommand {
+    /// Remove all cached LLM responses.
+    Clear {
+        /// Directory where LLM responses are cached [config: `cache_dir`].
+        #[arg(long, value_name = "DIR")]
+        cache_dir: Option<PathBuf>,
+    },
+}
+
+/// Subcommands of the `config` subcommand.
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    /// Write a commented default configuration file to the expected location.
+    Init {
+        /// Overwrite an existing configuration file.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Print the resolved configuration, with the source of each value (flag, env, file, or
+    /// default). Secrets are masked.
+    Show,
+    /// Print where the configuration file is expected.
+    Path,
+}
+
+/// Subcommands of the `history` subcommand. Identifiers can be abbreviated to a unique prefix.
+#[derive(Subcommand, Debug)]
+enum HistoryCommand {
+    /// List the recorded analyses, oldest first.
+    List {
+        /// List only the analyses of this target file.
+        #[arg(long, value_name = "FILE")]
+        path: Option<PathBuf>,
+    },
+    /// Print a recorded analysis (recommended name, description, and renames).
+    Show {
+        /// Identifier of the analysis.
+        id: String,
+    },
+    /// Compare two recorded analyses, e.g., of the same function at different times or with
+    /// different models.
+    Diff {
+        /// Identifier of the older analysis.
+        old: String,
+        /// Identifier of the newer analysis.
+        new: String,
+    },
+}
+
+/// Subcommands of the `models` subcommand.
+#[derive(Subcommand, Debug)]
+enum ModelsCommand {
+    /// List the models available on the Ollama server, marking the configured one with `*`.
+    List,
+    /// Download the configured model (`aidapal` by default, or the `--model`) from the Ollama
+    /// library, showing the progress.
+    Pull,
+}
+
+/// Command line arguments of the `bench` subcommand.
+#[derive(clap::Args, Debug)]
+struct BenchArgs {
+    /// Directory of pseudocode files to analyze with each model (walked recursively).
+    #[arg(long, value_name = "DIR")]
+    corpus: PathBuf,
+
+    /// Comma-separated names of the models to compare.
+    #[arg(long, value_name = "MODELS", value_delimiter = ',', required = true,
+        value_parser = NonEmptyStringValueParser::new())]
+    models: Vec<String>,
+
+    /// Number of times each file is analyzed with each model.
+    #[arg(long, value_name = "N", default_value = "1")]
+    runs: NonZeroUsize,
+
+    /// Print the comparison as JSON to stdout instead of a table.
+    #[arg(long)]
+    json: bool,
+}
+
+/// Command line arguments of the `repl` subcommand.
+#[derive(clap::Args, Debug)]
+struct ReplArgs {
+    /// How long the model stays loaded between snippets (e.g., `10m`, or `-1` for indefinitely).
+    #[arg(long, value_name = "DURATION", default_value = "30m")]
+    keep_alive: String,
+
+    /// Comment style used for the function description.
+    #[arg(long, value_enum, value_name = "STYLE", default_value = "block")]
+    comment_style: CommentStyle,
+
+    /// Wrap width for the function description (number of columns or `auto`) [default: 76].
+    #[arg(long, value_name = "WIDTH")]
+    width: Option<CommentWidth>,
+}
+
+/// Command line arguments of the `serve` subcommand.
+#[derive(clap::Args, Debug)]
+struct ServeArgs {
+    /// Address and port to listen on (use port 0 to pick a free port).
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8787")]
+    listen: String,
+
+    /// Number of requests handled at the same time (as many more wait in a queue, the rest are
+    /// rejected with `503 Service Unavailable`).
+    #[arg(long, value_name = "N", default_value_t = NonZeroUsize::new(oneiromancer::DEFAULT_CONCURRENCY).unwrap_or(NonZeroUsize::MIN))]
+    concurrency: NonZeroUsize,
+
+    /// Maximum size of request bodies, in bytes.
+    #[arg(long, value_name = "BYTES", default_value_t = oneiromancer::MAX_INPUT_SIZE)]
+    max_body_size: usize,
+
+    /// Seconds allowed to clients for sending a request and for receiving the response (the
+    /// analysis itself is not limited).
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = oneiromancer::DEFAULT_TIMEOUT.as_secs(),
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    timeout: u64,
+}
+
+/// Command line arguments of the `undo` subcommand.
+#[derive(clap::Args, Debug)]
+struct UndoArgs {
+    /// Output file with the renames to undo (e.g., `test.out.c`).
+    #[arg(value_name = "FILE")]
+    file: PathBuf,
+
+    /// Rename map saved with the output file [default: `<FILE>.renames.json`, replacing the
+    /// extension of `<FILE>`].
+    #[arg(long, value_name = "PATH")]
+    renames: Option<PathBuf>,
+
+    /// File where the restored pseudocode is saved [default: stdout].
+    #[arg(long, short, value_name = "FILE")]
+    output: Option<PathBuf>,
+}
+
+/// Command line arguments of the `analyze` subcommand.
+#[derive(clap::Args, Debug)]
+#[expect(
+    clippy::struct_excessive_bools,
+    reason = "command line flags are naturally modeled as bools"
+)]
+struct AnalyzeArgs {
+    /// Pseudocode files, directories, or glob patterns to analyze (`-` to read from stdin).
+    #[arg(
+        value_name = "TARGET_FILE",
+        required_unless_present_any = ["haruspex", "binary"]
+    )]
+    filepaths: Vec<PathBuf>,
+
+    /// Analyze a directory of `<index>_<function_name>.c` files extracted by haruspex, saving
+    /// improved pseudocode and a function name index in its `improved` subdirectory.
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with_all = ["filepaths", "output", "stdout", "json", "format"]
+    )]
+    haruspex: Option<PathBuf>,
+
+    /// Decompile the functions of a binary or IDA database with idalib (all of them, or the one
+    /// selected by `--function`) and analyze them, saving improved pseudocode in the
+    /// `<NAME>_improved` directory next to it (requires the `idalib` feature and IDA Pro 9.x).
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["filepaths", "haruspex", "stdout", "json", "format", "in_place"]
+    )]
+    binary: Option<PathBuf>,
+
+    /// Comment style used for the function description.
+    #[arg(long, value_enum, value_name = "STYLE", default_value = "block")]
+    comment_style: CommentStyle,
+
+    /// Generate a Doxygen header (`@brief` and `@param` tags) instead of a plain description.
+    #[arg(long)]
+    doxygen: bool,
+
+    /// Wrap width for the function description (number of columns or `auto`) [default: 76].
+    #[arg(long, value_name = "WIDTH")]
+    width: Option<CommentWidth>,
+
+    /// Do not write the function description to the output file (only apply renames).
+    #[arg(long, conflicts_with = "no_rename")]
+    no_comment: bool,
+
+    /// Do not apply variable renaming suggestions to the output file (only write the description).
+    #[arg(long)]
+    no_rename: bool,
+
+    /// Replace the description generated by a previous run instead of adding a new one, keeping
+    /// other comments (generated descriptions are delimited by `// oneiromancer:begin|end` lines).
+    #[arg(long, conflicts_with = "no_comment")]
+    merge_comment: bool,
+
+    /// Save the map of the applied variable renames next to each output file (as
+    /// `<OUTPUT>.renames.json`), to undo them later with the `undo` subcommand.
+    #[arg(long, conflicts_with_all = ["stdout", "no_rename"])]
+    emit_renames: bool,
+
+    /// Append a provenance annotation to the output file, optionally limited to a comma-separated
+    /// list of fields [default: all fields].
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FIELDS",
+        value_delimiter = ',',
+        num_args = 0..,
+        require_equals = true
+    )]
+    annotate: Option<Vec<AnnotationField>>,
+
+    /// Output file, or output directory when analyzing directories or multiple files [default:
+    /// `<TARGET_FILE>.out.c` (or `<TARGET_FILE>.report.<FORMAT>` with `--report`), or stdout when
+    /// reading from stdin].
+    #[arg(long, short, value_name = "FILE", conflicts_with = "stdout")]
+    output: Option<PathBuf>,
+
+    /// Write the improved pseudocode to stdout instead of an output file (status messages are sent
+    /// to stderr).
+    #[arg(long)]
+    stdout: bool,
+
+    /// Extension of output files, replacing the extension of target files (e.g., `improved.c` for
+    /// `test.improved.c`; outputs with this extension are skipped when walking directories)
+    /// [default: `out.c`].
+    #[arg(
+        long,
+        value_name = "EXT",
+        value_parser = parse_output_extension,
+        conflicts_with_all = ["output", "stdout", "report"]
+    )]
+    output_extension: Option<String>,
+
+    /// Replace existing output files, instead of failing.
+    #[arg(long)]
+    force: bool,
+
+    /// Save output files that would replace existing ones with the first free number before their
+    /// extension (e.g., `test.out.1.c`).
+    #[arg(long, conflicts_with_all = ["force", "in_place"])]
+    auto_number: bool,
+
+    /// Rewrite each target file with its improved pseudocode instead of saving a new output file,
+    /// after backing it up to `<TARGET_FILE>.bak`.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "output",
+            "stdout",
+            "report",
+            "output_extension",
+            "haruspex",
+            "json",
+            "format"
+        ]
+    )]
+    in_place: bool,
+
+    /// Write a human-readable report of the analysis, with the improved pseudocode, instead of
+    /// the improved pseudocode alone (HTML reports of multiple target files are linked from an
+    /// `index.html` file).
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    report: Option<ReportFormat>,
+
+    /// Perform the analysis and print its results, but do not write output files, C headers, or
+    /// batch state.
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Print the prompts that would be sent to the model for each target file, and the files that
+    /// would be written, without contacting the server (implies `--dry-run`).
+    #[arg(long, conflicts_with_all = ["json", "format", "diff"])]
+    show_prompt: bool,
+
+    /// Print a JSON report of the analysis results of a single target file to stdout (status
+    /// messages are sent to stderr). With `--stdout`, or with stdin input and no `--output`, the
+    /// report takes the place of the improved pseudocode and no files are written.
+    #[arg(long)]
+    json: bool,
+
+    /// Format of the analysis results printed to stdout, instead of the terminal report (`json` is
+    /// the same as `--json`).
+    #[arg(long, value_enum, value_name = "FORMAT", conflicts_with = "json")]
+    format: Option<OutputFormat>,
+
+    /// Print a JSON report of each target file to stdout as soon as its analysis completes, one per
+    /// line (status messages are sent to stderr).
+    #[arg(long, conflicts_with_all = ["stdout", "json", "format"])]
+    jsonl: bool,
+
+    /// Include in the `--json` and `--jsonl` reports the line, column (in Unicode code points), and
+    /// byte range of each occurrence of the renamed variables in the target file.
+    #[arg(long)]
+    locations: bool,
+
+    /// Emit a C header file with the suggested function prototype.
+    #[arg(long, value_name = "PATH")]
+    emit_header: Option<PathBuf>,
+
+    /// Emit a SARIF 2.1.0 log with the function descriptions and renaming suggestions, located in
+    /// the target files.
+    #[arg(long, value_name = "PATH")]
+    sarif: Option<PathBuf>,
+
+    /// Emit an IDAPython script that applies the suggested names, comments, and variable renames
+    /// in IDA.
+    #[arg(long, value_name = "PATH")]
+    emit_ida: Option<PathBuf>,
+
+    /// Emit a Ghidra Python script that applies the suggested names, comments, and variable
+    /// renames in Ghidra.
+    #[arg(long, value_name = "PATH")]
+    emit_ghidra: Option<PathBuf>,
+
+    /// Emit a Binary Ninja Python script that applies the suggested names, comments, and variable
+    /// renames in Binary Ninja.
+    #[arg(long, value_name = "PATH")]
+    emit_binja: Option<PathBuf>,
+
+    /// Emit a classic IDC script that applies the suggested names and comments in IDA without
+    /// Python, listing the variable renames in comments.
+    #[arg(long, value_name = "PATH")]
+    emit_idc: Option<PathBuf>,
+
+    /// Emit a radare2/rizin script that applies the suggested names, comments, and variable
+    /// renames with r2 commands.
+    #[arg(long, value_name = "PATH")]
+    emit_r2: Option<PathBuf>,
+
+    /// Emit a unified diff from each target file to its improved pseudocode, to apply with
+    /// `git apply` or `patch -p1` (all files go into a single patch).
+    #[arg(long, value_name = "PATH")]
+    emit_patch: Option<PathBuf>,
+
+    /// Directory that file names in the patch are relative to [default: the current directory].
+    #[arg(long, value_name = "DIR", requires = "emit_patch")]
+    patch_root: Option<PathBuf>,
+
+    /// Print a colorized unified diff from each target file to its improved pseudocode, to review
+    /// the changes (e.g., with `--dry-run` to save nothing).
+    #[arg(long, conflicts_with = "stdout")]
+    diff: bool,
+
+    /// Address of the analyzed function in hex, for disassembler scripts (default: parsed from
+    /// default names such as `sub_401000`, `FUN_00401000`, or `fcn.00401000`, or looked up by name).
+    #[arg(
+        long,
+        value_name = "ADDR",
+        value_parser = oneiromancer::parse_address,
+        conflicts_with_all = ["chunk", "joint", "with_callees", "haruspex"]
+    )]
+    address: Option<u64>,
+
+    /// Walk directories recursively.
+    #[arg(long, short)]
+    recursive: bool,
+
+    /// Glob pattern of files to include when walking directories (can be repeated) [default:
+    /// `*.c`].
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Glob pattern of files to exclude when walking directories (can be repeated) [default:
+    /// `*.out.c`].
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Respect `.gitignore` files when walking directories.
+    #[arg(long)]
+    gitignore: bool,
+
+    /// Comma-separated list of accepted extensions of target files [default: c,cpp,cc,h,txt,json].
+    #[arg(long, value_name = "EXTS", value_delimiter = ',')]
+    extensions: Vec<String>,
+
+    /// Format of target files (`auto` treats `.json` files as aidapal IDA plugin payloads).
+    #[arg(long, value_enum, default_value_t = InputFormat::Auto)]
+    input_format: InputFormat,
+
+    /// Accept target files with any extension.
+    #[arg(long)]
+    force_ext: bool,
+
+    /// Stop at the first file that fails to be analyzed.
+    #[arg(long)]
+    fail_fast: bool,
+
+    /// Fail on low-quality analysis results (short description, too few renames, or placeholder
+    /// function name) instead of applying them with a warning.
+    #[arg(long)]
+    strict: bool,
+
+    /// Minimum length of the description of acceptable results, in characters.
+    #[arg(long, value_name = "N", default_value_t = MIN_COMMENT_LENGTH)]
+    min_comment_length: usize,
+
+    /// Minimum number of variable renames of acceptable results.
+    #[arg(long, value_name = "N", default_value_t = MIN_RENAMES)]
+    min_renames: usize,
+
+    /// Accept placeholder function names (e.g., `sub_401000`) in analysis results.
+    #[arg(long)]
+    allow_placeholder_names: bool,
+
+    /// Maximum number of target files analyzed concurrently in batch mode [default: 1].
+    #[arg(long, short, value_name = "N")]
+    jobs: Option<NonZeroUsize>,
+
+    /// Analyze target files again, even if they are already up to date in batch mode.
+    #[arg(long)]
+    re_analyze: bool,
+
+    /// Resume an interrupted batch, skipping the files it already analyzed; fails if the batch
+    /// has no state file from an earlier run.
+    #[arg(long, conflicts_with = "re_analyze")]
+    resume: bool,
+
+    /// Analyze identical target files separately, instead of reusing the results of the first
+    /// one in batch mode.
+    #[arg(long)]
+    no_dedupe: bool,
+
+    /// Do not save the `report.json` index of the files analyzed in batch mode.
+    #[arg(long)]
+    no_index: bool,
+
+    /// Also save the index of the files analyzed in batch mode as an `INDEX.md` Markdown table.
+    #[arg(long, conflicts_with = "no_index")]
+    index_md: bool,
+
+    /// Split target files into functions and analyze each function separately [config: `chunk`].
+    #[arg(long, overrides_with = "no_chunk")]
+    chunk: bool,
+
+    /// Analyze each target file with a single prompt, even if `chunk` is enabled in the
+    /// configuration file.
+    #[arg(long, conflicts_with = "with_callees")]
+    no_chunk: bool,
+
+    /// Analyze all functions in each target file with a single prompt, which is cheaper than
+    /// `--chunk` for files with several small functions.
+    #[arg(long, conflicts_with_all = ["chunk", "with_callees"])]
+    joint: bool,
+
+    /// Analyze only this function in each target file, by name (e.g., `parse_packet`) or address
+    /// suffix (e.g., `@0x401A2F` matches `sub_401A2F`); the rest of the file is left untouched.
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["chunk", "joint", "with_callees"])]
+    function: Option<String>,
+
+    /// Number of lines before the function selected by `--function` sent as context.
+    #[arg(long, value_name = "LINES", default_value_t = 0, requires = "function")]
+    function_context: usize,
+
+    /// Strip preprocessor directives, comment banners, and blank lines from the prompt.
+    #[arg(long)]
+    strip_noise: bool,
+
+    /// Decompiler that produced the pseudocode, which selects the prompt hint, the boilerplate
+    /// stripped by `--strip-noise`, and the names safe to rename [default: detected from the
+    /// pseudocode].
+    #[arg(long, value_enum, value_name = "DECOMPILER")]
+    decompiler: Option<Decompiler>,
+
+    /// Also rename variables whose names were not generated by the decompiler (e.g., names
+    /// assigned by the user), which are protected by default.
+    #[arg(long, conflicts_with = "no_rename")]
+    rename_user_names: bool,
+
+    /// What to do with renames whose new name already exists in the pseudocode or was suggested
+    /// for another variable, which would merge two distinct variables.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "POLICY",
+        default_value_t = CollisionPolicy::Skip,
+        conflicts_with = "no_rename"
+    )]
+    on_collision: CollisionPolicy,
+
+    /// Append the definitions of same-file callees to the prompt of each function (implies
+    /// `--chunk`).
+    #[arg(long)]
+    with_callees: bool,
+
+    /// Token budget for the callee definitions appended by `--with-callees`.
+    #[arg(
+        long,
+        value_name = "TOKENS",
+        default_value_t = 2048,
+        requires = "with_callees"
+    )]
+    callee_budget: usize,
+
+    /// Model option passed through to Ollama, such as `num_ctx=8192` or `stop=["}"]` (can be
+    /// repeated).
+    #[arg(
+        long = "option",
+        value_name = "KEY=VALUE",
+        value_parser = oneiromancer::parse_option
+    )]
+    options: Vec<(String, serde_json::Value)>,
+
+    /// Natural language of the generated comment, such as `Spanish` or `Italian` (function and
+    /// variable names stay in English) [default: English].
+    #[arg(long, value_name = "LANG", value_parser = NonEmptyStringValueParser::new())]
+    language: Option<String>,
+
+    /// Number of generations merged by majority vote for each prompt, with different seeds (use
+    /// with a temperature above zero; `--stats` reports the agreement) [default: 1].
+    #[arg(long, value_name = "N")]
+    samples: Option<NonZeroUsize>,
+
+    /// File with free-form notes appended to the prompt as extra context.
+    #[arg(long, value_name = "FILE")]
+    context_file: Option<PathBuf>,
+
+    /// Do not show a spinner while querying the LLM (spinners are always suppressed when stdout is
+    /// not a terminal).
+    #[arg(long)]
+    no_spinner: bool,
+
+    /// Print timing and token statistics at the end of the run (included in the report with
+    /// `--json` and `--jsonl`).
+    #[arg(long)]
+    stats: bool,
+
+    /// Cache LLM responses on disk and reuse them for identical prompts [config: `cache`].
+    #[arg(long, overrides_with = "no_cache")]
+    cache: bool,
+
+    /// Do not cache LLM responses, even if enabled in the configuration file.
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Directory where LLM responses are cached (implies `--cache`) [config: `cache_dir`; default:
+    /// platform-specific, e.g., `~/.cache/oneiromancer`].
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+
+    /// Append an audit log of the run (analyzed files, model and options, rename decisions,
+    /// durations, and warnings) to this file as JSON Lines [config: `log_file`].
+    #[arg(long, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+
+    /// Record each analysis in the history, to query it with `oneiromancer history` [config:
+    /// `history`].
+    #[arg(long, overrides_with = "no_history")]
+    history: bool,
+
+    /// Do not record analyses in the history, even if enabled in the configuration file.
+    #[arg(long)]
+    no_history: bool,
+}
+
+/// Format of the analysis results printed by `analyze`, as set with `--format`.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    /// JSON report of the analysis results of a single target file.
+    Json,
+}
+
+impl AnalyzeArgs {
+    /// Returns whether to print a JSON report, as set with `--json` or `--format json`.
+    fn json(&self) -> bool {
+        self.json || self.format == Some(OutputFormat::Json)
+    }
+
+    /// Builds [`InputPolicy`] from command line arguments, falling back to values in `config`.
+    fn input_policy(&self, config: &Config) -> InputPolicy {
+        let policy = InputPolicy::new().force_ext(self.force_ext);
+        match (self.extensions.as_slice(), config.extensions()) {
+            (&[], None) => policy,
+            (&[], Some(extensions)) | (extensions, _) => policy.extensions(extensions),
+        }
+    }
+
+    /// Returns whether to analyze each function separately. The `chunk` setting of `config` does
+    /// not apply to runs that select another mode (e.g., `--joint` or `--function`).
+    fn chunk(&self, config: &Config) -> bool {
+        let other_mode = self.joint || self.function.is_some() || self.address.is_some();
+        self.chunk
+            || self.with_callees
+            || (!self.no_chunk && !other_mode && config.chunk().unwrap_or_default())
+    }
+
+    /// Builds the [`ResponseCache`] from command line arguments and `config`, or returns `None` if
+    /// caching is disabled.
+    fn response_cache(&self, config: &Config) -> Option<ResponseCache> {
+        let enabled = !self.no_cache
+            && (self.cache || self.cache_dir.is_some() || config.cache().unwrap_or_default());
+        enabled
+            .then(|| cache_dir(self.cache_dir.as_deref(), config))
+            .flatten()
+            .map(ResponseCache::new)
+    }
+
+    /// Returns the [`History`] where analyses are recorded, as set on the command line and in
+    /// `config`, or `None` if the history is disabled.
+    fn history(&self, config: &Config) -> Option<History> {
+        let enabled = !self.no_history && (self.history || config.history().unwrap_or_default());
+        enabled
+            .then(|| history_file(config))
+            .flatten()
+            .map(History::new)
+    }
+
+    /// Opens the audit log specified on the command line or in `config`, or returns `None` if no
+    /// log file is specified.
+    fn audit_log(&self, config: &Config) -> anyhow::Result<Option<AuditLog>> {
+        self.log_file
+            .as_deref()
+            .or_else(|| config.log_file())
+            .map(|filepath| {
+                AuditLog::open(filepath)
+                    .map_err(|err| OutputError::CreateFailed(filepath.to_path_buf(), err).into())
+            })
+            .transpose()
+    }
+
+    /// Builds [`DiscoverOptions`] from command line arguments.
+    fn discover_options(&self) -> DiscoverOptions {
+        let mut options = DiscoverOptions::new()
+            .recursive(self.recursive)
+            .gitignore(self.gitignore);
+        if !self.include.is_empty() {
+            options = options.include(&self.include);
+        }
+        if !self.exclude.is_empty() {
+            options.exclude(&self.exclude)
+        } else if let Some(extension) = self.output_extension.as_deref() {
+            // Skip previous outputs with the custom extension, along with the default ones.
+            options.exclude(&["*.out.c".to_owned(), format!("*.{extension}")])
+        } else {
+            options
+        }
+    }
+
+    /// Builds the client from global and command line arguments, falling back to values in
+    /// `config`.
+    fn client(&self, global: &GlobalArgs, config: &Config) -> Oneiromancer {
+        let mut client = self.options.iter().fold(global.client(), |client, option| {
+            client.option(option.0.clone(), option.1.clone())
+        });
+        if let Some(language) = self.language.as_deref() {
+            client = client.language(language);
+        }
+        if let Some(samples) = self.samples.or_else(|| config.samples()) {
+            client = client.samples(samples);
+        }
+        client
+            .prompt_options(
+                PromptOptions::new()
+                    .strip_preprocessor(self.strip_noise)
+                    .decompiler(self.decompiler)
+                    .callee_budget(self.with_callees.then_some(self.callee_budget)),
+            )
+            .cache(self.response_cache(config))
+    }
+
+    /// Builds [`RunOptions`] from global and command line arguments, falling back to values in
+    /// `config`. In `batch` mode, the output path is used as an output directory that mirrors
+    /// `inputs`, where the state file is saved (unless output goes to stdout).
+    fn run_options(
+        &self,
+        global: &GlobalArgs,
+        config: &Config,
+        inputs: &[PathBuf],
+        batch: bool,
+    ) -> RunOptions {
+        let width = self.width.or_else(|| config.width()).unwrap_or_default();
+        let annotation = match self.annotate.as_deref() {
+            Some(&[]) => &AnnotationField::ALL[..],
+            Some(fields) => fields,
+            None => &[],
+        };
+        RunOptions::new()
+            .client(self.client(global, config))
+            .comment_options(
+                CommentOptions::new()
+                    .style(self.comment_style)
+                    .width(width)
+                    .doxygen_tags(self.doxygen),
+            )
+            .apply_options(
+                ApplyOptions::new()
+                    .write_comment(!self.no_comment)
+                    .apply_renames(!self.no_rename)
+                    .merge_comment(self.merge_comment)
+                    .rename_user_names(self.rename_user_names)
+                    .collisions(self.on_collision),
+            )
+            .annotation(annotation)
+            .output(self.output.clone().filter(|_| !batch))
+            .output_dir(self.output.clone().filter(|_| batch))
+            .input_root(common_root(inputs))
+            .state_file((batch && !self.stdout).then(|| {
+                self.output
+                    .clone()
+                    .or_else(|| common_root(inputs))
+                    .unwrap_or_default()
+                    .join(STATE_FILENAME)
+            }))
+            .re_analyze(self.re_analyze)
+            .resume(self.resume)
+            .dedupe(!self.no_dedupe)
+            .batch_index((batch && !self.stdout && !self.no_index).then(|| {
+                self.output
+                    .clone()
+                    .or_else(|| common_root(inputs))
+                    .unwrap_or_default()
+            }))
+            .index_markdown(self.index_md)
+            .jsonl(self.jsonl)
+            .locations(self.locations)
+            .stdout(self.stdout)
+            .write_output(!self.dry_run)
+            .show_prompt(self.show_prompt)
+            .output_extension(self.output_extension.clone())
+            .in_place(self.in_place)
+            .force(self.force)
+            .auto_number(self.auto_number)
+            .report(self.report)
+            .emit_header(self.emit_header.clone())
+            .sarif(self.sarif.clone())
+            .emit_renames(self.emit_renames)
+            .emit_ida(self.emit_ida.clone())
+            .emit_ghidra(self.emit_ghidra.clone())
+            .emit_binja(self.emit_binja.clone())
+            .emit_idc(self.emit_idc.clone())
+            .emit_r2(self.emit_r2.clone())
+            .emit_patch(self.emit_patch.clone())
+            .patch_root(self.patch_root.clone())
+            .diff(self.diff)
+            .address(self.address)
+            .fail_fast(self.fail_fast)
+            .quality(
+                QualityPolicy::new()
+                    .min_comment_length(self.min_comment_length)
+                    .min_renames(self.min_renames)
+                    .reject_placeholder_names(!self.allow_placeholder_names),
+            )
+            .strict(self.strict)
+            .jobs(
+                self.jobs
+                    .or_else(|| config.jobs())
+                    .unwrap_or(NonZeroUsize::MIN),
+            )
+            .chunk(self.chunk(config))
+            .joint(self.joint)
+            .function(self.function.clone())
+            .function_context(self.function_context)
+            .input_format(self.input_format)
+            .quiet(global.quiet)
+            .color(global.color)
+            .spinner(!self.no_spinner)
+            .stats(self.stats)
+            .history(self.history(config))
+    }
+}
+
+/// Returns the deepest directory that contains all `paths`, if any.
+fn common_root(paths: &[PathBuf]) -> Option<PathBuf> {
+    paths
+        .iter()
+        .map(|path| {
+            if path.is_dir() {
+                path.as_path()
+            } else {
+                path.parent().unwrap_or_else(|| Path::new(""))
+            }
+        })
+        .map(Path::to_path_buf)
+        .reduce(|root, dir| {
+            root.components()
+                .zip(dir.components())
+                .take_while(|&(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect()
+        })
+}
+
+/// Returns the directory where LLM responses are cached, as set on the command line with `flag`,
+/// in `config`, or the default one.
+fn cache_dir(flag: Option<&Path>, config: &Config) -> Option<PathBuf> {
+    flag.or_else(|| config.cache_dir())
+        .map(Path::to_path_buf)
+        .or_else(ResponseCache::default_dir)
+}
+
+/// Returns the file where the history of analyses is stored, as set in `config` or the default one.
+fn history_file(config: &Config) -> Option<PathBuf> {
+    config
+        .history_file()
+        .map(Path::to_path_buf)
+        .or_else(History::default_path)
+}
+
+/// Queries the history of analyses.
+fn history(config: &Config, command: HistoryCommand) -> anyhow::Result<()> {
+    let history = History::new(history_file(config).context("Failed to find the history file")?);
+    match command {
+        HistoryCommand::List { path } => {
+            let entries = history.entries(path.as_deref());
+            if entries.is_empty() {
+                eprintln!(
+                    "{}",
+                    status(
+                        &format!(
+                            "[*] No analyses recorded in `{}`",
+                            history.filepath().display()
+                        ),
+                        &io::stderr()
+                    )
+                );
+            } else {
+                print!("{}", oneiromancer::format_history_list(&entries));
+            }
+        }
+        HistoryCommand::Show { id } => {
+            print!(
+                "{}",
+                oneiromancer::format_history_entry(&history.find(&id)?)
+            );
+        }
+        HistoryCommand::Diff { old, new } => print!(
+            "{}",
+            oneiromancer::format_history_diff(&history.find(&old)?, &history.find(&new)?)
+        ),
+    }
+    Ok(())
+}
+
+/// Lists the models available on the Ollama server, or downloads the configured one.
+fn models(global: &GlobalArgs, command: &ModelsCommand) -> anyhow::Result<()> {
+    if global
+        .backend
+        .is_some_and(|backend| backend != Backend::Ollama)
+    {
+        return Err(CliError::Usage("`models` requires the Ollama backend").into());
+    }
+    let client = global.client();
+    let model = client.configured_model();
+    match *command {
+        ModelsCommand::List => {
+            let names = client.list_models().with_context(|| {
+                format!(
+                    "Failed to list the models at `{}`",
+                    client.configured_baseurl()
+                )
+            })?;
+            for name in &names {
+                let marker = if client.is_configured_model(name) {
+                    '*'
+                } else {
+                    ' '
+                };
+                println!("{marker} {name}");
+            }
+            if !global.quiet && !names.iter().any(|name| client.is_configured_model(name)) {
+                eprintln!(
+                    "{}",
+                    status(
+                        &format!(
+                            "[!] Model `{model}` is not available, download it with `{PROGRAM} models pull`"
+                        ),
+                        &io::stderr()
+                    )
+                );
+            }
+        }
+        ModelsCommand::Pull => {
+            pull_model(&client, global.quiet)
+                .with_context(|| format!("Failed to pull model `{model}`"))?;
+            if !global.quiet {
+                eprintln!(
+                    "{}",
+                    status(&format!("[+] Model `{model}` is ready"), &io::stderr())
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Downloads the model of `client`, showing the progress on stderr (a progress bar on a terminal,
+/// or a line per step otherwise) unless `quiet` is set.
+fn pull_model(client: &Oneiromancer, quiet: bool) -> Result<(), OneiromancerError> {
+    let bar = (!quiet && io::stderr().is_terminal()).then(|| {
+        ProgressBar::new(0).with_style(
+            ProgressStyle::with_template(PULL_TEMPLATE)
+                .unwrap_or_else(|_| ProgressStyle::default_bar())
+                .progress_chars("=> "),
+        )
+    });
+    let mut last_status = String::new();
+    let result = client.pull_model(|progress| match bar.as_ref() {
+        Some(pulling) => {
+            pulling.set_message(progress.status().to_owned());
+            pulling.set_length(progress.total().unwrap_or_default());
+            pulling.set_position(progress.completed().unwrap_or_default());
+        }
+        None if !quiet && progress.status() != last_status => {
+            eprintln!(
+                "{}",
+                status(&format!("[*] {}", progress.status()), &io::stderr())
+            );
+            progress.status().clone_into(&mut last_status);
+        }
+        None => (),
+    });
+    if let Some(pulling) = bar {
+        pulling.finish_and_clear();
+    }
+    result
+}
+
+/// Removes all cached LLM responses from the directory set on the command line with `flag`, in
+/// `config`, or the default one.
+fn clear_cache(flag: Option<&Path>, config: &Config) -> anyhow::Result<()> {
+    let dir = cache_dir(flag, config).context("Failed to find the cache directory")?;
+    let removed = ResponseCache::new(&dir)
+        .clear()
+        .with_context(|| format!("Failed to clear cache in `{}`", dir.display()))?;
+    eprintln!(
+        "[+] Removed {removed} cached responses from `{}`",
+        dir.display()
+    );
+    Ok(())
+}
+
+/// Runs the `command` subcommand of `config`.
+fn config(global: &GlobalArgs, command: &ConfigCommand) -> anyhow::Result<()> {
+    let filepath = global
+        .config
+        .clone()
+        .or_else(Config::default_path)
+        .context("Failed to find the configuration directory")?;
+    match *command {
+        ConfigCommand::Init { force } => {
+            Config::init(&filepath, force).context("Failed to initialize configuration")?;
+            eprintln!(
+                "{}",
+                status(
+                    &format!("[+] Configuration written to `{}`", filepath.display()),
+                    &io::stderr()
+                )
+            );
+        }
+        ConfigCommand::Show => print!("{}", oneiromancer::format_settings(&settings(global)?)),
+        ConfigCommand::Path => println!("{}", filepath.display()),
+    }
+    Ok(())
+}
+
+/// Resolves the settings shown by `config show` from the command line, the environment, the
+/// configuration file, and the defaults, in order of precedence.
+fn settings(global: &GlobalArgs) -> anyhow::Result<Vec<Setting>> {
+    let loaded = global.load_config()?;
+    let path = global.config.clone().or_else(Config::default_path);
+    let from_file = |value: Option<String>| [(value, SettingSource::File)];
+
+    let mut resolved = vec![Setting::resolve(
+        "config",
+        [(
+            global
+                .config
+                .as_ref()
+                .map(|filepath| filepath.display().to_string()),
+            SettingSource::Flag("--config"),
+        )],
+        path.map(|filepath| {
+            let missing = if filepath.exists() {
+                ""
+            } else {
+                " (not found)"
+            };
+            format!("{}{missing}", filepath.display())
+        }),
+    )];
+    resolved.extend(connection_settings(global));
+    resolved.extend([
+        Setting::resolve(
+            "width",
+            from_file(loaded.width().map(|width| width.to_string())),
+            Some(CommentWidth::default().to_string()),
+        ),
+        Setting::resolve(
+            "extensions",
+            from_file(loaded.extensions().map(|extensions| extensions.join(", "))),
+            Some(oneiromancer::DEFAULT_EXTENSIONS.join(", ")),
+        ),
+        Setting::resolve(
+            "samples",
+            from_file(loaded.samples().map(|samples| samples.to_string())),
+            Some(NonZeroUsize::MIN.to_string()),
+        ),
+        Setting::resolve(
+            "jobs",
+            from_file(loaded.jobs().map(|jobs| jobs.to_string())),
+            Some(NonZeroUsize::MIN.to_string()),
+        ),
+        Setting::resolve(
+            "chunk",
+            from_file(loaded.chunk().map(|chunk| chunk.to_string())),
+            Some(false.to_string()),
+        ),
+        Setting::resolve(
+            "cache",
+            from_file(loaded.cache().map(|cache| cache.to_string())),
+            Some(false.to_string()),
+        ),
+        Setting::resolve(
+            "cache_dir",
+            from_file(loaded.cache_dir().map(|dir| dir.display().to_string())),
+            ResponseCache::default_dir().map(|dir| dir.display().to_string()),
+        ),
+        Setting::resolve(
+            "log_file",
+            from_file(
+                loaded
+                    .log_file()
+                    .map(|filepath| filepath.display().to_string()),
+            ),
+            None,
+        ),
+        Setting::resolve(
+            "history",
+            from_file(loaded.history().map(|history| history.to_string())),
+            Some(false.to_string()),
+        ),
+        Setting::resolve(
+            "history_file",
+            from_file(
+                loaded
+                    .history_file()
+                    .map(|filepath| filepath.display().to_string()),
+            ),
+            History::default_path().map(|filepath| filepath.display().to_string()),
+        ),
+    ]);
+    Ok(resolved)
+}
+
+/// Resolves the settings of the connection to the inference API: the backend, its base URL, and
+/// the model.
+fn connection_settings(global: &GlobalArgs) -> [Setting; 3] {
+    let env_var = |var| env::var(var).ok();
+    let (url_var, default_url) = global.base_url_defaults();
+
+    [
+        Setting::resolve(
+            "backend",
+            [(
+                global
+                    .backend
+                    .and_then(|backend| backend.to_possible_value())
+                    .map(|value| value.get_name().to_owned()),
+                SettingSource::Flag("--backend"),
+            )],
+            Some("ollama".to_owned()),
+        ),
+        Setting::resolve(
+            "base_url",
+            [
+                (global.base_url.clone(), SettingSource::Flag("--base-url")),
+                (env_var(url_var), SettingSource::Env(url_var)),
+            ],
+            Some(default_url.to_owned()),
+        ),
+        Setting::resolve(
+            "model",
+            [
+                (global.model.clone(), SettingSource::Flag("--model")),
+                (env_var("OLLAMA_MODEL"), SettingSource::Env("OLLAMA_MODEL")),
+            ],
+            Some(oneiromancer::OLLAMA_MODEL.to_owned()),
+        ),
+    ]
+}
+
+/// Analyzes the corpus with each model and prints the comparison. Failed analyses count against
+/// the corresponding model, and do not stop the bench.
+fn bench(global: &GlobalArgs, args: &BenchArgs) -> anyhow::Result<()> {
+    let discovery = oneiromancer::discover_files(
+        &[args.corpus.as_path()],
+        &DiscoverOptions::new().recursive(true),
+    )
+    .context("Failed to discover pseudocode files")?;
+    for skipped in discovery.skipped() {
+        log::warn!("Skipping `{}`: {}", skipped.0.display(), skipped.1);
+    }
+    if discovery.files().is_empty() {
+        return Err(CliError::NoInput.into());
+    }
+
+    // Progress lines go to stderr when stdout is reserved for JSON output.
+    let progress = |line: &str| match (global.quiet, args.json) {
+        (true, _) => (),
+        (false, true) => eprintln!("{}", status(line, &io::stderr())),
+        (false, false) => println!("{}", status(line, &io::stdout())),
     };
+    progress(&format!(
+        "[*] Benchmarking {} models on {} files, {} runs each",
+        args.models.len(),
+        discovery.files().len(),
+        args.runs
+    ));
+    let samples = oneiromancer::run_bench(
+        &global.client(),
+        discovery.files(),
+        &args.models,
+        args.runs,
+        |sample| {
+            let (marker, verb) = if sample.is_success() {
+                ("[+]", "Analyzed")
+            } else {
+                ("[!]", "Failed to analyze")
+            };
+            progress(&format!(
+                "{marker} {verb} `{}` with `{}` in {:.2?}",
+                sample.input().display(),
+                sample.model(),
+                sample.duration()
+            ));
+        },
+    );
 
-    // Let's do it.
-    match oneiromancer::run(Path::new(&filename)) {
-        Ok(()) => ExitCode::SUCCESS,
+    let benches = oneiromancer::aggregate_bench(&samples, &args.models);
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&benches)?);
+    } else {
+        println!("\n{}", oneiromancer::format_bench_table(&benches));
+    }
+    Ok(())
+}
+
+/// Runs the REPL with settings from global and command line arguments, falling back to values in
+/// the configuration file.
+fn repl(global: &GlobalArgs, args: &ReplArgs) -> anyhow::Result<()> {
+    let config = global.load_config()?;
+    let width = args.width.or_else(|| config.width()).unwrap_or_default();
+    let options = RunOptions::new()
+        .client(global.client().keep_alive(args.keep_alive.as_str()))
+        .comment_options(CommentOptions::new().style(args.comment_style).width(width))
+        .quiet(global.quiet)
+        .color(global.color);
+    oneiromancer::run_repl(options)
+}
+
+/// Builds the client of the `serve` and `mcp` servers from global arguments and the configuration
+/// file, as for analyses on the command line: samples, response cache, and accepted extensions.
+fn server_client(global: &GlobalArgs) -> anyhow::Result<Oneiromancer> {
+    let config = global.load_config()?;
+    let mut client = global.client();
+    if let Some(samples) = config.samples() {
+        client = client.samples(samples);
+    }
+    if let Some(extensions) = config.extensions() {
+        client = client.input_policy(InputPolicy::new().extensions(extensions));
+    }
+    let cache = config
+        .cache()
+        .unwrap_or_default()
+        .then(|| cache_dir(None, &config))
+        .flatten()
+        .map(ResponseCache::new);
+    log::info!(
+        "Using model `{}` at `{}`",
+        client.configured_model(),
+        client.configured_baseurl()
+    );
+    Ok(client.cache(cache))
+}
+
+/// Runs the HTTP server with the client built by [`server_client`].
+fn serve(global: &GlobalArgs, args: &ServeArgs) -> anyhow::Result<()> {
+    let client = server_client(global)?;
+    let listener = TcpListener::bind(&args.listen)
+        .with_context(|| format!("Failed to listen on `{}`", args.listen))?;
+    let address = listener.local_addr()?;
+    println!(
+        "{}",
+        status(&format!("[+] Listening on http://{address}"), &io::stdout())
+    );
+
+    oneiromancer::serve(
+        &listener,
+        &ServeOptions::new()
+            .client(client)
+            .concurrency(args.concurrency)
+            .max_body_size(args.max_body_size)
+            .timeout(Duration::from_secs(args.timeout)),
+    );
+    Ok(())
+}
+
+/// Undoes the variable renames applied to an output file, with the rename map saved next to it.
+fn undo(args: &UndoArgs) -> anyhow::Result<()> {
+    let mappath = args
+        .renames
+        .clone()
+        .unwrap_or_else(|| args.file.with_extension(RENAMES_EXTENSION));
+    let map = RenameMap::load(&mappath)?;
+    let pseudocode = fs::read_to_string(&args.file)
+        .with_context(|| format!("Failed to read `{}`", args.file.display()))?;
+    let restored = oneiromancer::undo_renames(&pseudocode, &map)
+        .with_context(|| format!("Failed to undo renames in `{}`", args.file.display()))?;
+    if let Some(path) = args.output.as_deref() {
+        fs::write(path, restored).with_context(|| format!("Failed to write `{}`", path.display()))
+    } else {
+        print!("{restored}");
+        Ok(())
+    }
+}
+
+/// Checks that all input files have an extension accepted by `policy`, before any analysis starts.
+///
+/// Other problems (e.g., a missing, unreadable, or too large file) are left to the analysis of
+/// each file, so that a bad file does not abort the whole batch.
+fn validate_inputs(filepaths: &[PathBuf], policy: &InputPolicy) -> anyhow::Result<()> {
+    for filepath in filepaths {
+        if filepath.as_os_str() == oneiromancer::STDIN_FILEPATH || filepath.is_dir() {
+            continue;
+        }
+        match oneiromancer::validate_input(filepath, policy) {
+            Err(err) if matches!(err, InputError::UnsupportedExtension { .. }) => {
+                return Err(err).context("Invalid target file");
+            }
+            Ok(()) | Err(_) => (),
+        }
+    }
+    Ok(())
+}
+
+/// Parses the extension of output files, with an optional leading dot (e.g., `.improved.c`).
+///
+/// Extensions need at least two components, like `out.c`, so that output files never replace
+/// target files.
+fn parse_output_extension(text: &str) -> Result<String, &'static str> {
+    let extension = text.strip_prefix('.').unwrap_or(text);
+    if extension.contains(['/', '\\']) {
+        return Err("the extension must not contain path separators");
+    }
+    let components = extension.split('.').collect::<Vec<_>>();
+    if components.len() < 2 || components.contains(&"") {
+        return Err("the extension needs at least two non-empty components (e.g., `improved.c`)");
+    }
+    Ok(extension.to_owned())
+}
+
+/// Loads the free-form notes in the context file specified on the command line, if any.
+fn load_context(filepath: Option<&PathBuf>) -> anyhow::Result<AnalysisContext> {
+    filepath.map_or_else(
+        || Ok(AnalysisContext::new()),
+        |path| {
+            let notes = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read context from `{}`", path.display()))?;
+            Ok(AnalysisContext::new().notes(notes))
+        },
+    )
+}
+
+/// Returns the command line `args` with the `analyze` subcommand inserted if no subcommand is
+/// given (e.g., `oneiromancer foo.c`), skipping the global options that precede it.
+fn with_default_subcommand(mut args: Vec<OsString>) -> Vec<OsString> {
+    let mut i: usize = 1;
+    while let Some(arg) = args.get(i).and_then(|arg| arg.to_str()) {
+        if GLOBAL_OPTIONS.contains(&arg) {
+            i = i.saturating_add(2);
+        } else if GLOBAL_FLAGS.contains(&arg)
+            || arg
+                .strip_prefix('-')
+                .is_some_and(|flags| !flags.is_empty() && flags.chars().all(|c| "qv".contains(c)))
+            || GLOBAL_OPTIONS.iter().any(|option| {
+                arg.strip_prefix(option)
+                    .is_some_and(|rest| rest.starts_with('='))
+            })
+        {
+            i = i.saturating_add(1);
+        } else {
+            break;
+        }
+    }
+
+    let explicit = args.get(i).is_none_or(|arg| {
+        arg.to_str().is_some_and(|text| {
+            SUBCOMMANDS.contains(&text) || matches!(text, "-h" | "--help" | "-V" | "--version")
+        })
+    });
+    if !explicit {
+        args.insert(1, OsString::from("analyze"));
+    }
+    args
+}
+
+/// Runs the subcommand specified on the command line, returning its outcome.
+fn run(cli: Cli) -> anyhow::Result<Exit> {
+    match cli.command {
+        Command::Analyze(args) => analyze(&cli.global, &args),
+        Command::Bench(args) => {
+            bench(&cli.global, &args)?;
+            Ok(Exit::Success)
+        }
+        Command::Cache(CacheCommand::Clear { cache_dir }) => {
+            clear_cache(cache_dir.as_deref(), &cli.global.load_config()?)?;
+            Ok(Exit::Success)
+        }
+        Command::Config(command) => {
+            config(&cli.global, &command)?;
+            Ok(Exit::Success)
+        }
+        Command::Doctor => Ok(Exit::from_success(doctor(&cli.global))),
+        Command::History(command) => {
+            history(&cli.global.load_config()?, command)?;
+            Ok(Exit::Success)
+        }
+        Command::Models(command) => {
+            models(&cli.global, &command)?;
+            Ok(Exit::Success)
+        }
+        Command::Repl(args) => {
+            repl(&cli.global, &args)?;
+            Ok(Exit::Success)
+        }
+        Command::Serve(args) => {
+            serve(&cli.global, &args)?;
+            Ok(Exit::Success)
+        }
+        Command::Mcp => {
+            let client = server_client(&cli.global)?;
+            oneiromancer::serve_mcp(&client, io::stdin().lock(), io::stdout().lock())
+                .context("Failed to communicate with the MCP client")?;
+            Ok(Exit::Success)
+        }
+        Command::Undo(args) => {
+            undo(&args)?;
+            Ok(Exit::Success)
+        }
+        Command::Completions { shell } => {
+            clap_complete::generate(shell, &mut Cli::command(), PROGRAM, &mut io::stdout());
+            Ok(Exit::Success)
+        }
+    }
+}
+
+/// Returns the names of the models available on the Ollama server, to complete `--model`, or no
+/// names if the server is unreachable.
+fn model_candidates() -> Vec<CompletionCandidate> {
+    Oneiromancer::new()
+        .list_models()
+        .unwrap_or_default()
+        .into_iter()
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// Prints the outcome of the `name` check with `detail`, and `hint` if it failed. Returns `ok`.
+fn check(ok: bool, name: &str, detail: impl Display, hint: impl Display) -> bool {
+    if ok {
+        println!(
+            "{}",
+            status(&format!("[+] PASS {name}: {detail}"), &io::stdout())
+        );
+    } else {
+        println!(
+            "{}",
+            status(&format!("[!] FAIL {name}: {detail}"), &io::stdout())
+        );
+        println!("    Hint: {hint}");
+    }
+    ok
+}
+
+/// Returns the source of a setting overridden by the `flag` command line option or the `var`
+/// environment variable.
+fn setting_source(flag: Option<&str>, name: &str, var: &str) -> String {
+    match (flag, env::var(var)) {
+        (Some(_), _) => format!("`{name}` command line option"),
+        (None, Ok(_)) => format!("`{var}` environment variable"),
+        (None, Err(_)) => "default value".to_owned(),
+    }
+}
+
+/// Checks that the Ollama server of `client` is reachable and that its model is available.
+fn check_server(client: &Oneiromancer) -> bool {
+    let baseurl = client.configured_baseurl();
+    let model = client.configured_model();
+    match client.server_version() {
+        Ok(version) => {
+            check(
+                true,
+                "Ollama server",
+                format_args!("version {version} at `{baseurl}`"),
+                "",
+            );
+            match client.has_model() {
+                Ok(found) => check(
+                    found,
+                    "model",
+                    format_args!(
+                        "`{model}` {}",
+                        if found { "is available" } else { "not found" }
+                    ),
+                    format_args!(
+                        "run `ollama pull {model}` (or `ollama create {model}` from a modelfile)"
+                    ),
+                ),
+                Err(err) => check(
+                    false,
+                    "model",
+                    format_args!("failed to list models: {err}"),
+                    "check that the server at the base URL is an Ollama server",
+                ),
+            }
+        }
+        Err(err) => {
+            check(
+                false,
+                "Ollama server",
+                format_args!("`{baseurl}` is unreachable: {err}"),
+                "start Ollama with `ollama serve`, or point `--base-url` or `OLLAMA_BASEURL` to the right host",
+            );
+            check(
+                false,
+                "model",
+                "skipped, the Ollama server is unreachable",
+                "fix the Ollama server check first",
+            )
+        }
+    }
+}
+
+/// Diagnoses common setup problems, printing a line per check. Returns `true` if all checks passed.
+fn doctor(global: &GlobalArgs) -> bool {
+    let mut ok = true;
+
+    // Configuration file.
+    let config_path = global.config.clone().or_else(Config::default_path);
+    ok &= match global.load_config() {
+        Ok(_) => check(
+            true,
+            "configuration",
+            config_path.filter(|path| path.exists()).map_or_else(
+                || "no configuration file, using defaults".to_owned(),
+                |path| format!("loaded from `{}`", path.display()),
+            ),
+            "",
+        ),
+        Err(err) => check(
+            false,
+            "configuration",
+            format_args!("{err:#}"),
+            "fix or remove the configuration file",
+        ),
+    };
+
+    // Environment variables and settings.
+    for var in ["OLLAMA_BASEURL", "OLLAMA_MODEL"] {
+        let value = env::var(var).unwrap_or_else(|_| "(unset)".to_owned());
+        println!("[*] {var}={value}");
+    }
+    let client = global.client();
+    let baseurl = client.configured_baseurl();
+    let model = client.configured_model();
+    ok &= check(
+        baseurl.starts_with("http://") || baseurl.starts_with("https://"),
+        "base URL",
+        format_args!(
+            "`{baseurl}` (from {})",
+            setting_source(global.base_url.as_deref(), "--base-url", "OLLAMA_BASEURL")
+        ),
+        "set `--base-url` or `OLLAMA_BASEURL` to a URL such as `http://127.0.0.1:11434`",
+    );
+    ok &= check(
+        !model.trim().is_empty(),
+        "model name",
+        format_args!(
+            "`{model}` (from {})",
+            setting_source(global.model.as_deref(), "--model", "OLLAMA_MODEL")
+        ),
+        "set `--model` or `OLLAMA_MODEL` to the name of the model, such as `aidapal`",
+    );
+
+    ok &= check_server(&client);
+
+    // Write permissions in the working directory, where output files are saved by default.
+    let probe = PathBuf::from(format!(".{PROGRAM}-doctor-{}", process::id()));
+    let writable = fs::write(&probe, "").and_then(|()| fs::remove_file(&probe));
+    ok &= check(
+        writable.is_ok(),
+        "working directory",
+        writable.map_or_else(
+            |err| format!("not writable: {err}"),
+            |()| "writable".to_owned(),
+        ),
+        "run oneiromancer from a writable directory, or save output files elsewhere with `--output`",
+    );
+
+    println!();
+    if ok {
+        println!("{}", status("[+] All checks passed", &io::stdout()));
+    } else {
+        println!("{}", status("[!] Some checks failed", &io::stdout()));
+    }
+    ok
+}
+
+/// Discovers and analyzes all pseudocode files, returning the outcome of the analyses.
+fn analyze(global: &GlobalArgs, args: &AnalyzeArgs) -> anyhow::Result<Exit> {
+    let client = global.client();
+    log::info!(
+        "Using model `{}` at `{}`",
+        client.configured_model(),
+        client.configured_baseurl()
+    );
+    if args.json() {
+        return Ok(analyze_json(global, args));
+    }
+    if let Some(dirpath) = args.haruspex.as_ref() {
+        let config = global.load_config()?;
+        let context = load_context(args.context_file.as_ref())?;
+        let summary = oneiromancer::run_haruspex(
+            dirpath,
+            &args
+                .run_options(global, &config, &[], true)
+                .state_file(Some(dirpath.join(IMPROVED_DIRNAME).join(STATE_FILENAME)))
+                .context(context)
+                .audit_log(args.audit_log(&config)?)
+                .interrupt(handle_interrupt()),
+        )?;
+        return Ok(Exit::from_summary(&summary));
+    }
+    if let Some(binarypath) = args.binary.as_ref() {
+        let config = global.load_config()?;
+        let selector = args
+            .function
+            .as_deref()
+            .map(FunctionSelector::parse)
+            .transpose()?
+            .unwrap_or_default();
+        let context = load_context(args.context_file.as_ref())?;
+        let summary = oneiromancer::run_binary(
+            binarypath,
+            &selector,
+            &args
+                .run_options(global, &config, &[], true)
+                .context(context)
+                .audit_log(args.audit_log(&config)?)
+                .interrupt(handle_interrupt()),
+        )?;
+        return Ok(Exit::from_summary(&summary));
+    }
+
+    let config = global.load_config()?;
+    if let Some(archivepath) = archive_input(&args.filepaths)? {
+        let context = load_context(args.context_file.as_ref())?;
+        let summary = oneiromancer::run_archive(
+            archivepath,
+            &args.discover_options(),
+            &args
+                .run_options(global, &config, &[], true)
+                .context(context)
+                .audit_log(args.audit_log(&config)?)
+                .interrupt(handle_interrupt()),
+        )?;
+        return Ok(Exit::from_summary(&summary));
+    }
+    let (inputs, files) = discover_inputs(args, &config)?;
+    let context = load_context(args.context_file.as_ref())?;
+
+    let batch = files.len() > 1 || inputs.iter().any(|path| path.is_dir());
+    if batch && args.address.is_some() {
+        return Err(CliError::Usage("`--address` requires a single target file").into());
+    }
+    let summary = oneiromancer::run_all(
+        &files,
+        &args
+            .run_options(global, &config, &inputs, batch)
+            .context(context)
+            .audit_log(args.audit_log(&config)?)
+            .interrupt(handle_interrupt()),
+    )?;
+    Ok(Exit::from_summary(&summary))
+}
+
+/// Installs a Ctrl-C handler that requests the returned [`Interrupt`] on the first press, so that
+/// a batch stops gracefully, and force quits on the second press.
+fn handle_interrupt() -> Interrupt {
+    let interrupt = Interrupt::new();
+    let requested = interrupt.clone();
+    let result = ctrlc::set_handler(move || {
+        if requested.request() {
+            #[expect(clippy::exit, reason = "a second Ctrl-C must quit right away")]
+            process::exit(Exit::Interrupted.code().into());
+        }
+        eprintln!(
+            "\n{}",
+            status(
+                "[!] Interrupted: finishing the analyses in progress (press Ctrl-C again to quit)",
+                &io::stderr()
+            )
+        );
+    });
+    if let Err(err) = result {
+        log::warn!("Failed to install the Ctrl-C handler: {err}");
+    }
+    interrupt
+}
+
+/// Analyzes a single pseudocode file and prints a JSON report to stdout, returning the outcome of
+/// the analysis. Failures are reported as JSON objects with an `error` field.
+fn analyze_json(global: &GlobalArgs, args: &AnalyzeArgs) -> Exit {
+    let started = Instant::now();
+    let (report, exit) = match analyze_report(global, args) {
+        Ok(report) => (report, Exit::Success),
         Err(err) => {
-            eprintln!("\n[!] Error: {err:#}");
-            ExitCode::FAILURE
+            eprintln!(
+                "\n{}",
+                status(&format!("[!] Error: {err:#}"), &io::stderr())
+            );
+            let report = AnalysisReport::failed(
+                args.filepaths.first().cloned().unwrap_or_default(),
+                global.client().configured_model(),
+                started.elapsed(),
+                &err,
+            );
+            (report, Exit::from_error(&err))
         }
+    };
+    match serde_json::to_string(&report) {
+        Ok(json) => println!("{json}"),
+        Err(err) => println!(
+            "{}",
+            serde_json::json!({
+                "status": "error",
+                "error": format!("Failed to serialize the report: {err}"),
+            })
+        ),
+    }
+    exit
+}
+
+/// Analyzes a single pseudocode file, keeping stdout free, and returns the [`AnalysisReport`].
+fn analyze_report(global: &GlobalArgs, args: &AnalyzeArgs) -> anyhow::Result<AnalysisReport> {
+    let config = global.load_config()?;
+    let (inputs, files) = discover_inputs(args, &config)?;
+    let context = load_context(args.context_file.as_ref())?;
+
+    let Ok([filepath]) = <[PathBuf; 1]>::try_from(files) else {
+        return Err(CliError::Usage("`--json` requires a single target file").into());
+    };
+    // Improved pseudocode that would go to stdout is not written at all, since stdout is reserved
+    // for the report.
+    let report_only = args.stdout
+        || (filepath.as_os_str() == oneiromancer::STDIN_FILEPATH && args.output.is_none());
+    oneiromancer::run_with_report(
+        &filepath,
+        &args
+            .run_options(global, &config, &inputs, false)
+            .write_output(!args.dry_run && !report_only)
+            .reserve_stdout(true)
+            .context(context)
+            .audit_log(args.audit_log(&config)?),
+    )
+}
+
+/// Returns the archive in `filepaths`, if the only target file is an archive (`.zip`, `.tar.gz`,
+/// or `.tgz`).
+fn archive_input(filepaths: &[PathBuf]) -> anyhow::Result<Option<&Path>> {
+    let archives = filepaths
+        .iter()
+        .filter(|path| ArchiveFormat::detect(path).is_some() && path.is_file())
+        .count();
+    match (archives, filepaths.first()) {
+        (0, _) => Ok(None),
+        (1, Some(path)) if filepaths.len() == 1 => Ok(Some(path)),
+        _ => Err(CliError::Usage("Archives must be the only target file").into()),
     }
 }
 
-/// Prints usage information and exits.
-fn usage(prog: &str) -> ExitCode {
-    eprintln!("Usage:");
-    eprintln!("{prog} <target_file>.c");
+/// Expands, validates, and discovers the target files specified on the command line, returning
+/// the expanded inputs and the discovered pseudocode files.
+fn discover_inputs(
+    args: &AnalyzeArgs,
+    config: &Config,
+) -> anyhow::Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+    let inputs = oneiromancer::expand_patterns(&args.filepaths)
+        .context("Failed to expand input patterns")?;
+    validate_inputs(&inputs, &args.input_policy(config))?;
 
-    ExitCode::FAILURE
+    let discovery = oneiromancer::discover_files(&inputs, &args.discover_options())
+        .context("Failed to discover pseudocode files")?;
+    for skipped in discovery.skipped() {
+        log::warn!("Skipping `{}`: {}", skipped.0.display(), skipped.1);
+    }
+    if discovery.files().is_empty() {
+        return Err(CliError::NoInput.into());
+    }
+    Ok((inputs, discovery.files().to_vec()))
+}
+
+/// Terminal logger that prints diagnostics on stderr.
+///
+/// Debug and trace diagnostics of dependencies are only printed at the trace level, unless a
+/// `RUST_LOG` filter is set.
+struct TerminalLogger {
+    /// Filter set with `RUST_LOG`, if any, which replaces `--quiet` and `--verbose`.
+    filter: Option<Filter>,
+}
+
+impl Log for TerminalLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        if let Some(filter) = self.filter.as_ref() {
+            return filter.enabled(metadata);
+        }
+        metadata.level() <= log::max_level()
+            && (metadata.level() <= Level::Info
+                || metadata.target().starts_with(PROGRAM)
+                || log::max_level() == LevelFilter::Trace)
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if !self.enabled(record.metadata())
+            || self
+                .filter
+                .as_ref()
+                .is_some_and(|filter| !filter.matches(record))
+        {
+            return;
+        }
+        let line = match record.level() {
+            Level::Error => format!("[!] Error: {}", record.args()),
+            Level::Warn => format!("[!] {}", record.args()),
+            Level::Info => format!("[*] {}", record.args()),
+            Level::Debug => format!("[debug] {}", record.args()),
+            Level::Trace => format!("[trace] {}: {}", record.target(), record.args()),
+        };
+        eprintln!("{}", status(&line, &io::stderr()));
+    }
+
+    fn flush(&self) {}
+}
+
+/// Terminal logger installed by [`main`].
+static LOGGER: OnceLock<TerminalLogger> = OnceLock::new();
+
+/// Installs the terminal logger, with the `RUST_LOG` filter unless `--quiet` or `--verbose` is
+/// set.
+///
+/// `RUST_LOG` is parsed by `env_logger`, which warns about invalid directives on stderr.
+fn init_logger(global: &GlobalArgs) {
+    let filter = env::var("RUST_LOG")
+        .ok()
+        .filter(|_| !global.quiet && global.verbose == 0)
+        .map(|rust_log| Builder::new().parse(&rust_log).build());
+    let max_level = filter
+        .as_ref()
+        .map_or_else(|| global.log_level(), Filter::filter);
+    if log::set_logger(LOGGER.get_or_init(|| TerminalLogger { filter })).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+/// When to color status messages, as set on the command line.
+static COLOR: OnceLock<ColorChoice> = OnceLock::new();
+
+/// Paints the marker of a status `line` printed on `stream`, according to `--color`.
+fn status<'a>(line: &'a str, stream: &impl IsTerminal) -> Cow<'a, str> {
+    let choice = COLOR.get().copied().unwrap_or_default();
+    oneiromancer::paint_status(line, choice.enabled(stream.is_terminal()))
+}
+
+fn main() -> ExitCode {
+    // Answer dynamic completion requests from the shell, if any.
+    CompleteEnv::with_factory(Cli::command).complete();
+
+    // Parse command line arguments.
+    let cli = Cli::parse_from(with_default_subcommand(env::args_os().collect()));
+    _ = COLOR.set(cli.global.color);
+
+    if !cli.global.quiet {
+        eprintln!("{PROGRAM} {VERSION} - GenAI assistant for pseudocode analysis");
+        eprintln!("Copyright (c) 2025-2026 {AUTHORS}");
+        eprintln!();
+    }
+    init_logger(&cli.global);
+
+    // Let's do it.
+    let exit = run(cli).unwrap_or_else(|err| {
+        eprintln!(
+            "\n{}",
+            status(&format!("[!] Error: {err:#}"), &io::stderr())
+        );
+        Exit::from_error(&err)
+    });
+    ExitCode::from(exit.code())
+}
+
+#[cfg(test)]
+mod tests {
+    use oneiromancer::QualityIssue;
+
+    use super::*;
+
+    /// Returns the exit code of an `err` wrapped in a context, as returned by the library.
+    fn code_of(err: impl error::Error + Send + Sync + 'static) -> u8 {
+        Exit::from_error(&anyhow::Error::new(err).context("Failed to analyze")).code()
+    }
+
+    #[test]
+    fn terminal_logger_applies_rust_log_filter() {
+        let logger = TerminalLogger {
+            filter: Some(Builder::new().parse("warn,oneiromancer=debug").build()),
+        };
+        let metadata = |level, target| Metadata::builder().level(level).target(target).build();
+
+        assert!(logger.enabled(&metadata(Level::Debug, "oneiromancer::ollama")));
+        assert!(!logger.enabled(&metadata(Level::Trace, "oneiromancer::ollama")));
+        assert!(logger.enabled(&metadata(Level::Warn, "ureq::unversioned")));
+        assert!(!logger.enabled(&metadata(Level::Info, "ureq::unversioned")));
+    }
+
+    #[test]
+    fn analysis_errors_map_to_distinct_exit_codes() {
+        let parse_error = || serde_json::Error::io(io::Error::other("truncated"));
+        let cases = [
+            (
+                OneiromancerError::InvalidInput(InputError::NotAFile(PathBuf::from("a.c"))),
+                3,
+            ),
+            (
+                OneiromancerError::FileReadFailed(io::Error::other("boom")),
+                3,
+            ),
+            (
+                OneiromancerError::OllamaQueryFailed(ureq::Error::ConnectionFailed),
+                4,
+            ),
+            (
+                OneiromancerError::OllamaQueryFailed(ureq::Error::StatusCode(500)),
+                4,
+            ),
+            (OneiromancerError::Timeout(ureq::Timeout::RecvResponse), 4),
+            (
+                OneiromancerError::ServerUnreachable {
+                    baseurl: "http://127.0.0.1:6666".to_owned(),
+                    source: ureq::Error::ConnectionFailed,
+                },
+                4,
+            ),
+            (
+                OneiromancerError::OllamaQueryFailed(ureq::Error::StatusCode(404)),
+                5,
+            ),
+            (
+                OneiromancerError::ModelNotFound {
+                    model: "aidapal".to_owned(),
+                    baseurl: "http://127.0.0.1:11434".to_owned(),
+                },
+                5,
+            ),
+            (
+                OneiromancerError::OllamaQueryFailed(ureq::Error::Json(parse_error())),
+                6,
+            ),
+            (OneiromancerError::ResponseParseFailed(parse_error()), 6),
+            (
+                OneiromancerError::LowQualityResult(QualityIssue::PlaceholderName(
+                    "sub_401000".to_owned(),
+                )),
+                6,
+            ),
+        ];
+        for (err, code) in cases {
+            let description = format!("{err:?}");
+            assert_eq!(code_of(err), code, "{description}");
+        }
+    }
+
+    #[test]
+    fn other_errors_map_to_distinct_exit_codes() {
+        assert_eq!(code_of(CliError::Usage("bad arguments")), 2);
+        assert_eq!(
+            code_of(ConfigError::FileReadFailed(io::Error::other("boom"))),
+            2
+        );
+        assert_eq!(code_of(CliError::NoInput), 3);
+        assert_eq!(code_of(DiscoverError::NoMatches("*.c".to_owned())), 3);
+        assert_eq!(code_of(ArchiveError::UnsafePath("../a.c".to_owned())), 3);
+        assert_eq!(code_of(BinaryError::Unsupported), 2);
+        assert_eq!(
+            code_of(BinaryError::NotFound(
+                FunctionSelector::Name("main".to_owned()),
+                PathBuf::from("a.out")
+            )),
+            3
+        );
+        assert_eq!(code_of(AidapalError::NotAnObject), 3);
+        assert_eq!(
+            code_of(SelectError::NotFound("f".to_owned(), Vec::new())),
+            3
+        );
+        assert_eq!(
+            code_of(OutputError::StdoutFailed(io::Error::other("boom"))),
+            7
+        );
+        assert_eq!(
+            code_of(OutputError::OutputExists(PathBuf::from("test.out.c"))),
+            7
+        );
+        assert_eq!(code_of(io::Error::other("unknown")), 1);
+        assert_eq!(Exit::from_success(true).code(), 0);
+        assert_eq!(Exit::Interrupted.code(), 130);
+    }
+
+    #[test]
+    fn output_extension_needs_two_components() {
+        assert_eq!(
+            parse_output_extension("improved.c"),
+            Ok("improved.c".to_owned())
+        );
+        assert_eq!(parse_output_extension(".ai.cpp"), Ok("ai.cpp".to_owned()));
+        for invalid in ["c", ".c", "", "a..c", "out.", "sub/out.c"] {
+            assert!(
+                parse_output_extension(invalid).is_err(),
+                "{invalid} accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn default_subcommand_follows_global_options() {
+        let args = |line: &str| line.split(' ').map(OsString::from).collect::<Vec<_>>();
+        for line in [
+            "oneiromancer --backend openai --retries 2 --read-timeout=60 --preflight -vv foo.c",
+            "oneiromancer --connect-timeout 5 --model=aidapal foo.c",
+        ] {
+            let completed = with_default_subcommand(args(line));
+            assert_eq!(
+                completed.get(1).and_then(|arg| arg.to_str()),
+                Some("analyze"),
+                "{line}"
+            );
+        }
+        assert_eq!(
+            with_default_subcommand(args("oneiromancer --retries 2 doctor")),
+            args("oneiromancer --retries 2 doctor")
+        );
+    }
 }
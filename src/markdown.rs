@@ -0,0 +1,223 @@
+//! Render the results of analyses as Markdown reports, for sharing with readers of raw C.
+
+use std::fmt::Write as _;
+
+use crate::OneiromancerResults;
+
+/// Number of lines of improved pseudocode above which the code block is collapsed in a
+/// `<details>` element.
+pub const COLLAPSE_LINES: usize = 40;
+
+/// Formats a Markdown report of the analyses in `results` and the improved `pseudocode`.
+///
+/// Each analysis gets a heading with the recommended function name, its description as prose,
+/// and a table of variable renames (omitted if there are none). The improved pseudocode follows
+/// in a fenced code block, collapsed in a `<details>` element if it is longer than
+/// [`COLLAPSE_LINES`] lines. Markdown-significant characters in model output are escaped.
+#[must_use]
+pub fn format_markdown_report(results: &[&OneiromancerResults], pseudocode: &str) -> String {
+    let mut report = String::new();
+    for analysis in results {
+        _ = writeln!(report, "# {}\n", code_span(analysis.function_name()));
+        let description = escape_text(analysis.comment());
+        if !description.is_empty() {
+            _ = writeln!(report, "{description}\n");
+        }
+
+        let renames: Vec<_> = analysis
+            .variables()
+            .iter()
+            .filter(|variable| variable.original_name() != variable.new_name())
+            .collect();
+        if !renames.is_empty() {
+            report.push_str("| Variable | Suggested name |\n| --- | --- |\n");
+            for variable in renames {
+                _ = writeln!(
+                    report,
+                    "| {} | {} |",
+                    table_cell(variable.original_name()),
+                    table_cell(variable.new_name())
+                );
+            }
+            report.push('\n');
+        }
+    }
+
+    report.push_str("# Improved pseudocode\n\n");
+    let lines = pseudocode.lines().count();
+    let block = code_block(pseudocode);
+    if lines > COLLAPSE_LINES {
+        _ = writeln!(
+            report,
+            "<details>\n<summary>Show {lines} lines</summary>\n\n{block}\n</details>"
+        );
+    } else {
+        report.push_str(&block);
+    }
+    report
+}
+
+/// Returns a fenced C code block with `code`, delimited by a fence longer than any run of
+/// backticks in `code`.
+fn code_block(code: &str) -> String {
+    let fence = "`".repeat(longest_backtick_run(code).saturating_add(1).max(3));
+    let newline = if code.ends_with('\n') || code.is_empty() {
+        ""
+    } else {
+        "\n"
+    };
+    format!("{fence}c\n{code}{newline}{fence}\n")
+}
+
+/// Returns an inline code span with `text`, delimited by more backticks than any run in `text`.
+/// Line breaks are turned into spaces, since code spans cannot span table rows or headings.
+fn code_span(text: &str) -> String {
+    let flat = text.replace(['\r', '\n'], " ");
+    if flat.is_empty() {
+        return String::new();
+    }
+    let delimiter = "`".repeat(longest_backtick_run(&flat).saturating_add(1));
+    // A single leading and trailing space is stripped from code spans, so pad content that
+    // starts or ends with a backtick or a space.
+    let padding = if flat.starts_with(['`', ' ']) || flat.ends_with(['`', ' ']) {
+        " "
+    } else {
+        ""
+    };
+    format!("{delimiter}{padding}{flat}{padding}{delimiter}")
+}
+
+/// Returns a table cell with `text` in a code span, with pipes escaped so that they do not split
+/// the cell (GFM table rows are split on unescaped pipes even within code spans).
+pub fn table_cell(text: &str) -> String {
+    code_span(text).replace('|', "\\|")
+}
+
+/// Escapes `text` so that it renders as prose, with no inline markup, HTML, or block structure.
+pub fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for (index, line) in text.trim().lines().enumerate() {
+        if index > 0 {
+            escaped.push('\n');
+        }
+        // Leading spaces would turn the line into an indented code block.
+        let trimmed = line.trim_start();
+        // Escape list and setext heading markers at the start of a line.
+        let digits = trimmed.len().saturating_sub(
+            trimmed
+                .trim_start_matches(|ch: char| ch.is_ascii_digit())
+                .len(),
+        );
+        for (offset, ch) in trimmed.char_indices() {
+            let list_marker = (offset == 0 && matches!(ch, '-' | '+' | '='))
+                || (digits > 0 && offset == digits && matches!(ch, '.' | ')'));
+            if list_marker || "\\`*_[]<>|#&~".contains(ch) {
+                escaped.push('\\');
+            }
+            escaped.push(ch);
+        }
+    }
+    escaped
+}
+
+/// Returns the length of the longest run of backticks in `text`.
+fn longest_backtick_run(text: &str) -> usize {
+    text.split(|ch| ch != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use super::*;
+
+    /// Parses results with `function_name`, `comment`, and the `(original, new)` `variables`.
+    fn results(
+        function_name: &str,
+        comment: &str,
+        variables: &[(&str, &str)],
+    ) -> serde_json::Result<OneiromancerResults> {
+        serde_json::from_value(serde_json::json!({
+            "function_name": function_name,
+            "comment": comment,
+            "variables": variables
+                .iter()
+                .map(|variable| serde_json::json!({
+                    "original_name": variable.0,
+                    "new_name": variable.1,
+                }))
+                .collect::<Vec<_>>(),
+        }))
+    }
+
+    #[test]
+    fn format_markdown_report_renders_heading_prose_table_and_code() -> anyhow::Result<()> {
+        let analysis = results(
+            "init_ctx",
+            "Initializes the context.\nReturns 0 on success.",
+            &[("a1", "ctx"), ("v1", "v1"), ("v2", "len")],
+        )?;
+
+        assert_eq!(
+            format_markdown_report(&[&analysis], "int init_ctx(int ctx)\n{\n  return 0;\n}\n"),
+            "# `init_ctx`\n\n\
+             Initializes the context.\nReturns 0 on success.\n\n\
+             | Variable | Suggested name |\n\
+             | --- | --- |\n\
+             | `a1` | `ctx` |\n\
+             | `v2` | `len` |\n\n\
+             # Improved pseudocode\n\n\
+             ```c\nint init_ctx(int ctx)\n{\n  return 0;\n}\n```\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn format_markdown_report_escapes_model_output() -> anyhow::Result<()> {
+        let analysis = results(
+            "a`b",
+            "Uses `a|b` and *stars*, <b>tags</b> & _underscores_.\n- not a list\n1. nor this",
+            &[("a|1", "x`|`y"), ("v1", "`tick")],
+        )?;
+
+        let report = format_markdown_report(&[&analysis], "char *s = \"```\";");
+        assert_eq!(
+            report,
+            "# ``a`b``\n\n\
+             Uses \\`a\\|b\\` and \\*stars\\*, \\<b\\>tags\\</b\\> \\& \\_underscores\\_.\n\
+             \\- not a list\n1\\. nor this\n\n\
+             | Variable | Suggested name |\n\
+             | --- | --- |\n\
+             | `a\\|1` | ``x`\\|`y`` |\n\
+             | `v1` | `` `tick `` |\n\n\
+             # Improved pseudocode\n\n\
+             ````c\nchar *s = \"```\";\n````\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn format_markdown_report_collapses_long_code() {
+        let pseudocode = "x;\n".repeat(COLLAPSE_LINES.saturating_add(1));
+
+        let report = format_markdown_report(&[], &pseudocode);
+        assert!(
+            report.starts_with(
+                "# Improved pseudocode\n\n<details>\n<summary>Show 41 lines</summary>\n\n```c\nx;\n"
+            ),
+            "code not collapsed: {report}"
+        );
+        assert!(
+            report.ends_with("x;\n```\n\n</details>\n"),
+            "details not closed: {report}"
+        );
+        assert!(
+            !format_markdown_report(&[], "x;\n").contains("<details>"),
+            "short code collapsed"
+        );
+    }
+}
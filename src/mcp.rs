@@ -0,0 +1,472 @@
+//! Serve analyses as Model Context Protocol (MCP) tools over stdio, for LLM agents and IDE
+//! assistants.
+
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+use thiserror::Error;
+
+use crate::{AnalysisContext, MAX_INPUT_SIZE, Oneiromancer, OneiromancerResults};
+
+/// Latest version of the Model Context Protocol supported by [`serve_mcp`].
+pub const MCP_PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// Versions of the Model Context Protocol supported by [`serve_mcp`], latest first.
+const SUPPORTED_VERSIONS: [&str; 3] = [MCP_PROTOCOL_VERSION, "2025-03-26", "2024-11-05"];
+
+/// Instructions for the client, returned by `initialize`.
+const INSTRUCTIONS: &str = "Use `analyze_pseudocode` or `analyze_file` on decompiled pseudocode \
+    (e.g., from IDA Hex-Rays) to get a suggested function name, a description of the function, \
+    and suggested variable names.";
+
+/// JSON-RPC message received from the client: a request if it has an `id`, a notification
+/// otherwise. Responses from the client are ignored, since the server sends no requests.
+#[derive(Deserialize, Debug)]
+struct Message {
+    /// Identifier of the request.
+    id: Option<Value>,
+    /// Method to call.
+    method: Option<String>,
+    /// Parameters of the method.
+    #[serde(default)]
+    params: Value,
+}
+
+/// Parameters of `tools/call`.
+#[derive(Deserialize, Debug)]
+struct ToolCall {
+    /// Name of the tool.
+    name: String,
+    /// Arguments of the tool.
+    #[serde(default)]
+    arguments: Map<String, Value>,
+}
+
+/// Arguments of the `analyze_pseudocode` tool.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct PseudocodeArguments {
+    /// Pseudocode to analyze.
+    code: String,
+    /// Language of the generated description, if not the model default.
+    #[serde(default)]
+    language: Option<String>,
+    /// Extra context appended to the prompt.
+    #[serde(default)]
+    context: ToolContext,
+}
+
+/// Extra context in the arguments of `analyze_pseudocode` (see [`AnalysisContext`]).
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ToolContext {
+    /// Disassembly excerpt of the analyzed function.
+    disassembly: String,
+    /// String literals referenced by the analyzed function.
+    strings: Vec<String>,
+    /// Definitions of the types referenced by the analyzed function.
+    types: Vec<String>,
+    /// Free-form notes.
+    notes: String,
+}
+
+/// Arguments of the `analyze_file` tool.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct FileArguments {
+    /// Path of the pseudocode file to analyze.
+    path: PathBuf,
+}
+
+/// Protocol-level error, answered with a JSON-RPC error response. Failures of the tools are not
+/// protocol errors: they are returned as tool results with `isError` set.
+#[derive(Error, Debug)]
+enum RpcError {
+    /// Message that is not valid JSON.
+    #[error("Parse error: {0}")]
+    Parse(#[from] serde_json::Error),
+    /// Message that is not a valid JSON-RPC request.
+    #[error("Invalid request: {0}")]
+    InvalidRequest(&'static str),
+    /// Request for a method that is not supported.
+    #[error("Method not found: {0}")]
+    MethodNotFound(String),
+    /// Request with invalid parameters.
+    #[error("Invalid params: {0}")]
+    InvalidParams(String),
+    /// Call of a tool that does not exist.
+    #[error("Unknown tool: {0}")]
+    UnknownTool(String),
+}
+
+impl RpcError {
+    /// Returns the JSON-RPC error code.
+    const fn code(&self) -> i64 {
+        match *self {
+            Self::Parse(_) => -32700,
+            Self::InvalidRequest(_) => -32600,
+            Self::MethodNotFound(_) => -32601,
+            Self::InvalidParams(_) | Self::UnknownTool(_) => -32602,
+        }
+    }
+}
+
+/// Runs a Model Context Protocol server on `input` and `output`, until `input` ends.
+///
+/// Messages are newline-delimited JSON-RPC messages, as in the stdio transport of MCP: nothing else
+/// must be written to `output`, while logs can go to stderr.
+///
+/// The server exposes two tools that analyze pseudocode with `client`: `analyze_pseudocode`, which
+/// takes the `code` to analyze and optionally the `language` of the description and extra
+/// `context` (`disassembly`, `strings`, `types`, and `notes`), and `analyze_file`, which takes the `path` of a pseudocode file
+/// (relative to the working directory of the server). Both return the analysis results as
+/// structured content, and failures (e.g., an unreachable Ollama server) as tool errors, so that
+/// the agent can see what went wrong. Requests are handled one at a time, in order.
+///
+/// # Errors
+///
+/// Returns [`io::Error`] in case reading from `input` or writing to `output` fails.
+pub fn serve_mcp(
+    client: &Oneiromancer,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> io::Result<()> {
+    for line in input.lines() {
+        let message = line?;
+        if message.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = handle_message(&message, client) {
+            serde_json::to_writer(&mut output, &response)?;
+            output.write_all(b"\n")?;
+            output.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Handles a single JSON-RPC message, returning the response to send, if any.
+fn handle_message(message: &str, client: &Oneiromancer) -> Option<Value> {
+    let parsed = match serde_json::from_str::<Value>(message) {
+        Ok(value) if value.is_object() => serde_json::from_value::<Message>(value),
+        Ok(_) => {
+            let err = RpcError::InvalidRequest("expected a JSON object");
+            return Some(error_response(&Value::Null, &err));
+        }
+        Err(err) => return Some(error_response(&Value::Null, &RpcError::Parse(err))),
+    };
+    let Ok(request) = parsed else {
+        let err = RpcError::InvalidRequest("malformed message");
+        return Some(error_response(&Value::Null, &err));
+    };
+    let Some(method) = request.method else {
+        // Responses to requests of the server, which sends none.
+        return None;
+    };
+
+    let result = dispatch(&method, request.params, client);
+    // Notifications get no response, not even in case of errors.
+    let id = request.id?;
+    Some(match result {
+        Ok(value) => json!({ "jsonrpc": "2.0", "id": id, "result": value }),
+        Err(err) => error_response(&id, &err),
+    })
+}
+
+/// Returns the JSON-RPC error response for `err` to the request with `id`.
+fn error_response(id: &Value, err: &RpcError) -> Value {
+    log::debug!("MCP error: {err}");
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": err.code(), "message": err.to_string() },
+    })
+}
+
+/// Calls `method` with `params`.
+fn dispatch(method: &str, params: Value, client: &Oneiromancer) -> Result<Value, RpcError> {
+    match method {
+        "initialize" => Ok(initialize(&params)),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": tools() })),
+        "tools/call" => {
+            let call: ToolCall = serde_json::from_value(params)
+                .map_err(|err| RpcError::InvalidParams(err.to_string()))?;
+            call_tool(&call, client)
+        }
+        _ if method.starts_with("notifications/") => Ok(Value::Null),
+        _ => Err(RpcError::MethodNotFound(method.to_owned())),
+    }
+}
+
+/// Returns the result of `initialize`, agreeing on the protocol version requested by the client if
+/// supported, or on the latest supported version otherwise.
+fn initialize(params: &Value) -> Value {
+    let requested = params.get("protocolVersion").and_then(Value::as_str);
+    let version = SUPPORTED_VERSIONS
+        .into_iter()
+        .find(|supported| Some(*supported) == requested)
+        .unwrap_or(MCP_PROTOCOL_VERSION);
+    json!({
+        "protocolVersion": version,
+        "capabilities": { "tools": { "listChanged": false } },
+        "serverInfo": { "name": "oneiromancer", "version": env!("CARGO_PKG_VERSION") },
+        "instructions": INSTRUCTIONS,
+    })
+}
+
+/// Returns the definitions of the tools.
+fn tools() -> Value {
+    let output_schema = json!({
+        "type": "object",
+        "properties": {
+            "function_name": { "type": "string", "description": "Suggested function name." },
+            "comment": { "type": "string", "description": "Description of the function." },
+            "variables": {
+                "type": "array",
+                "description": "Suggested variable renames.",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "original_name": { "type": "string" },
+                        "new_name": { "type": "string" },
+                    },
+                    "required": ["original_name", "new_name"],
+                },
+            },
+        },
+        "required": ["function_name", "comment", "variables"],
+    });
+    json!([
+        {
+            "name": "analyze_pseudocode",
+            "title": "Analyze pseudocode",
+            "description": "Analyze decompiled pseudocode (e.g., a Hex-Rays function) with a local LLM, returning a suggested function name, a description of the function, and suggested variable renames.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "code": { "type": "string", "description": "Pseudocode to analyze, ideally a single function." },
+                    "language": { "type": "string", "description": "Language of the description of the function (e.g., Italian), if not English." },
+                    "context": {
+                        "type": "object",
+                        "description": "Extra context on the function, appended to the prompt without being analyzed.",
+                        "properties": {
+                            "disassembly": { "type": "string", "description": "Disassembly excerpt of the function." },
+                            "strings": { "type": "array", "items": { "type": "string" }, "description": "String literals referenced by the function." },
+                            "types": { "type": "array", "items": { "type": "string" }, "description": "Definitions of the referenced types and callee prototypes." },
+                            "notes": { "type": "string", "description": "Free-form notes, such as what is already known about the function." },
+                        },
+                        "additionalProperties": false,
+                    },
+                },
+                "required": ["code"],
+                "additionalProperties": false,
+            },
+            "outputSchema": output_schema,
+        },
+        {
+            "name": "analyze_file",
+            "title": "Analyze pseudocode file",
+            "description": "Analyze a file of decompiled pseudocode with a local LLM, returning a suggested function name, a description of the function, and suggested variable renames.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Path of the pseudocode file, relative to the working directory of the server." },
+                },
+                "required": ["path"],
+                "additionalProperties": false,
+            },
+            "outputSchema": output_schema,
+        },
+    ])
+}
+
+/// Calls the tool requested by `call`, returning its result, including tool errors.
+fn call_tool(call: &ToolCall, client: &Oneiromancer) -> Result<Value, RpcError> {
+    let arguments = Value::Object(call.arguments.clone());
+    let outcome = match call.name.as_str() {
+        "analyze_pseudocode" => serde_json::from_value::<PseudocodeArguments>(arguments)
+            .map_err(|err| format!("Invalid arguments: {err}"))
+            .and_then(|args| analyze_pseudocode(args, client)),
+        "analyze_file" => serde_json::from_value::<FileArguments>(arguments)
+            .map_err(|err| format!("Invalid arguments: {err}"))
+            .and_then(|args| {
+                log::info!("MCP: analyzing `{}`", args.path.display());
+                client
+                    .analyze_file(&args.path)
+                    .map_err(|err| format!("Failed to analyze `{}`: {err}", args.path.display()))
+            }),
+        _ => return Err(RpcError::UnknownTool(call.name.clone())),
+    };
+    Ok(tool_result(outcome))
+}
+
+/// Analyzes the pseudocode in `args` with `client`, returning the error message to report on
+/// failure.
+fn analyze_pseudocode(
+    args: PseudocodeArguments,
+    client: &Oneiromancer,
+) -> Result<OneiromancerResults, String> {
+    let code = args.code;
+    if code.trim().is_empty() {
+        return Err("No pseudocode to analyze".to_owned());
+    }
+    if code.len() > MAX_INPUT_SIZE {
+        return Err(format!(
+            "Pseudocode exceeds the maximum size of {MAX_INPUT_SIZE} bytes"
+        ));
+    }
+    log::info!("MCP: analyzing {} bytes of pseudocode", code.len());
+    let context = AnalysisContext::new()
+        .disassembly(args.context.disassembly)
+        .strings(&args.context.strings)
+        .types(&args.context.types)
+        .notes(args.context.notes);
+    let configured = args.language.map_or_else(
+        || client.clone(),
+        |language| client.clone().language(language),
+    );
+    configured
+        .analyze_code_with_context(&code, &context)
+        .map_err(|err| format!("Failed to analyze pseudocode: {err}"))
+}
+
+/// Returns the `tools/call` result for `outcome`: the analysis results as structured content (and
+/// as serialized JSON text for clients without structured content), or the error message as a tool
+/// error.
+fn tool_result(outcome: Result<OneiromancerResults, String>) -> Value {
+    let serialized = outcome.and_then(|results| {
+        serde_json::to_value(&results)
+            .map_err(|err| format!("Failed to serialize the results: {err}"))
+    });
+    match serialized {
+        Ok(results) => json!({
+            "content": [{ "type": "text", "text": results.to_string() }],
+            "structuredContent": results,
+            "isError": false,
+        }),
+        Err(message) => {
+            log::warn!("MCP: {message}");
+            json!({
+                "content": [{ "type": "text", "text": message }],
+                "isError": true,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use super::*;
+
+    /// Sends `messages` to a server with a client that cannot reach any Ollama server, returning
+    /// the responses.
+    fn exchange(messages: &[&str]) -> anyhow::Result<Vec<Value>> {
+        let client = Oneiromancer::new().baseurl("http://127.0.0.1:9");
+        let input = messages.join("\n");
+        let mut output = Vec::new();
+        serve_mcp(&client, input.as_bytes(), &mut output)?;
+        String::from_utf8(output)?
+            .lines()
+            .map(|line| Ok(serde_json::from_str(line)?))
+            .collect()
+    }
+
+    #[test]
+    fn serve_mcp_answers_handshake_and_lists_tools() -> anyhow::Result<()> {
+        let responses = exchange(&[
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2025-03-26","capabilities":{},"clientInfo":{"name":"test","version":"1.0"}}}"#,
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+            "",
+            r#"{"jsonrpc":"2.0","id":"two","method":"tools/list"}"#,
+            r#"{"jsonrpc":"2.0","id":3,"method":"ping"}"#,
+        ])?;
+
+        let Ok([handshake, list, ping]) = <[Value; 3]>::try_from(responses) else {
+            anyhow::bail!("unexpected number of responses");
+        };
+        assert_eq!(
+            handshake.pointer("/result/protocolVersion"),
+            Some(&json!("2025-03-26"))
+        );
+        assert_eq!(
+            handshake.pointer("/result/capabilities"),
+            Some(&json!({"tools": {"listChanged": false}}))
+        );
+        let names: Vec<&str> = list
+            .pointer("/result/tools")
+            .and_then(Value::as_array)
+            .ok_or_else(|| anyhow::anyhow!("no tools: {list}"))?
+            .iter()
+            .filter_map(|tool| tool.get("name").and_then(Value::as_str))
+            .collect();
+        assert_eq!(names, ["analyze_pseudocode", "analyze_file"]);
+        assert_eq!(list.get("id"), Some(&json!("two")));
+        assert_eq!(ping.get("id").and_then(Value::as_i64), Some(3));
+        assert_eq!(ping.get("result"), Some(&json!({})));
+
+        Ok(())
+    }
+
+    #[test]
+    fn serve_mcp_reports_failed_calls_without_stopping() -> anyhow::Result<()> {
+        let responses = exchange(&[
+            r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"analyze_file","arguments":{"path":"/nonexistent/hello.c"}}}"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"tools/call","params":{"name":"analyze_pseudocode","arguments":{"code":"int main() { return 0; }"}}}"#,
+            r#"{"jsonrpc":"2.0","id":3,"method":"tools/call","params":{"name":"analyze_pseudocode","arguments":{"source":"int main();"}}}"#,
+            r#"{"jsonrpc":"2.0","id":4,"method":"tools/call","params":{"name":"disassemble","arguments":{}}}"#,
+            r#"{"jsonrpc":"2.0","id":5,"method":"resources/list"}"#,
+            r#"{"jsonrpc":"2.0","method":"tools/call","params":{"name":"disassemble"}}"#,
+            "not json",
+            r#"{"jsonrpc":"2.0","id":6,"method":"ping"}"#,
+        ])?;
+
+        let Ok([file, unreachable, arguments, tool, method, parse, ping]) =
+            <[Value; 7]>::try_from(responses)
+        else {
+            anyhow::bail!("unexpected number of responses");
+        };
+        for response in [&file, &unreachable, &arguments] {
+            assert_eq!(response.pointer("/result/isError"), Some(&json!(true)));
+            assert_eq!(response.pointer("/result/structuredContent"), None);
+        }
+        let text = |response: &Value| {
+            response
+                .pointer("/result/content/0/text")
+                .and_then(Value::as_str)
+                .map(str::to_owned)
+                .unwrap_or_default()
+        };
+        assert!(
+            text(&file).starts_with("Failed to analyze `/nonexistent/hello.c`"),
+            "{file}"
+        );
+        assert!(
+            text(&unreachable).starts_with("Failed to analyze pseudocode"),
+            "{unreachable}"
+        );
+        assert!(
+            text(&arguments).starts_with("Invalid arguments"),
+            "{arguments}"
+        );
+        assert_eq!(
+            tool.pointer("/error/code").and_then(Value::as_i64),
+            Some(-32602)
+        );
+        assert_eq!(
+            method.pointer("/error/code").and_then(Value::as_i64),
+            Some(-32601)
+        );
+        assert_eq!(
+            parse.pointer("/error/code").and_then(Value::as_i64),
+            Some(-32700)
+        );
+        assert_eq!(parse.get("id"), Some(&Value::Null));
+        assert_eq!(ping.get("id").and_then(Value::as_i64), Some(6));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,291 @@
+//! Merge the generated header comment with the comments already above a function.
+//!
+//! Re-analyzing improved pseudocode (or pseudocode with a hand-written header) would otherwise
+//! stack a new description on top of the previous one at every run. Headers written in merge mode
+//! are delimited by sentinel line comments, so that they can be told apart from manually added
+//! comments and replaced reliably; headers written without sentinels are recognized by their
+//! first line (the `name()` line of plain descriptions, or the `@brief` tag of Doxygen headers).
+
+use std::ops::Range;
+
+use crate::lexer::{Token, TokenKind, tokenize};
+
+/// Line comment that marks the beginning of a generated header.
+pub const BEGIN_SENTINEL: &str = "// oneiromancer:begin";
+/// Line comment that marks the end of a generated header.
+pub const END_SENTINEL: &str = "// oneiromancer:end";
+
+/// Returns the offset in `text` where the comments at its end start, i.e., the comments (and the
+/// whitespace between them) immediately above whatever follows `text`. Returns the length of
+/// `text` if it does not end with comments.
+pub fn comments_start(text: &str) -> usize {
+    tokenize(text)
+        .iter()
+        .rev()
+        .take_while(|token| is_comment_or_space(token))
+        .filter(|token| token.kind != TokenKind::Whitespace)
+        .last()
+        .map_or(text.len(), |token| token.range.start)
+}
+
+/// Places the generated `header` above the function in `pseudocode`, which may start with the
+/// comments immediately above the function.
+///
+/// If `merge` is set, the header is delimited by sentinels and replaces the previously generated
+/// headers found among these comments, while the other comments are preserved. Otherwise, a
+/// previous header identical to `header` is kept as is instead of being duplicated. In all other
+/// cases, `header` is inserted directly above the function, below the existing comments.
+pub fn place_header(pseudocode: &str, header: &str, merge: bool) -> String {
+    let code_start = tokenize(pseudocode)
+        .iter()
+        .take_while(|token| is_comment_or_space(token))
+        .last()
+        .map_or(0, |token| token.range.end);
+    let (comments, code) = pseudocode
+        .split_at_checked(code_start)
+        .unwrap_or(("", pseudocode));
+    let previous = generated_headers(comments);
+
+    if !merge {
+        let duplicate = previous
+            .last()
+            .is_some_and(|found| found.text(comments) == header.trim());
+        if duplicate {
+            log::debug!("Keeping the previous header comment, which is up to date");
+            return pseudocode.to_owned();
+        }
+        return format!("{comments}{header}{code}");
+    }
+
+    let section = format!(
+        "{BEGIN_SENTINEL}\n{}\n{END_SENTINEL}\n\n",
+        header.trim_end()
+    );
+    let Some(last) = previous.last() else {
+        return format!("{comments}{section}{code}");
+    };
+    let mut merged = String::with_capacity(pseudocode.len().saturating_add(section.len()));
+    let mut pos: usize = 0;
+    for found in &previous {
+        merged.push_str(comments.get(pos..found.range.start).unwrap_or_default());
+        if found.range == last.range {
+            merged.push_str(&section);
+        }
+        // Also drop the whitespace after the previous header, which ends with its own.
+        pos = comments
+            .get(found.range.end..)
+            .and_then(|rest| rest.find(|c: char| !c.is_whitespace()))
+            .map_or(comments.len(), |offset| {
+                found.range.end.saturating_add(offset)
+            });
+    }
+    merged.push_str(comments.get(pos..).unwrap_or_default());
+    merged.push_str(code);
+    merged
+}
+
+/// Header comment generated by a previous run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GeneratedHeader {
+    /// Byte range of the header, including its sentinels (if any).
+    range: Range<usize>,
+    /// Byte range of the comment of the header, excluding its sentinels.
+    comment: Range<usize>,
+}
+
+impl GeneratedHeader {
+    /// Returns the comment of the header in `comments`, without sentinels and surrounding
+    /// whitespace.
+    fn text<'a>(&self, comments: &'a str) -> &'a str {
+        comments
+            .get(self.comment.clone())
+            .unwrap_or_default()
+            .trim()
+    }
+}
+
+/// Returns the headers generated by previous runs in `comments`, in order: the sections delimited
+/// by sentinels, and the comments outside of them that look like generated descriptions.
+fn generated_headers(comments: &str) -> Vec<GeneratedHeader> {
+    let tokens = tokenize(comments);
+    let mut headers = Vec::new();
+    let mut index: usize = 0;
+    while let Some(token) = tokens.get(index) {
+        let next = index.saturating_add(1);
+        match token.kind {
+            TokenKind::LineComment if is_sentinel(token, comments, BEGIN_SENTINEL) => {
+                let end_index = tokens
+                    .iter()
+                    .skip(next)
+                    .position(|candidate| is_sentinel(candidate, comments, END_SENTINEL))
+                    .map(|offset| next.saturating_add(offset));
+                if let Some(end) = end_index.and_then(|end| tokens.get(end)) {
+                    headers.push(GeneratedHeader {
+                        range: token.range.start..end.range.end,
+                        comment: token.range.end..end.range.start,
+                    });
+                }
+                index = end_index.unwrap_or(index);
+            }
+            TokenKind::LineComment | TokenKind::BlockComment => {
+                let last = comment_end(&tokens, index, comments);
+                let range = token.range.start
+                    ..tokens
+                        .get(last)
+                        .map_or(token.range.end, |last_token| last_token.range.end);
+                if is_generated(comments.get(range.clone()).unwrap_or_default()) {
+                    headers.push(GeneratedHeader {
+                        range: range.clone(),
+                        comment: range,
+                    });
+                }
+                index = last;
+            }
+            TokenKind::Whitespace
+            | TokenKind::Identifier
+            | TokenKind::Number
+            | TokenKind::String
+            | TokenKind::Char
+            | TokenKind::Preprocessor
+            | TokenKind::Punct => (),
+        }
+        index = index.saturating_add(1);
+    }
+    headers
+}
+
+/// Returns the index of the last token of the comment starting at `tokens[index]`: the token
+/// itself for block comments, or the last of the line comments on consecutive lines.
+fn comment_end(tokens: &[Token], index: usize, source: &str) -> usize {
+    let mut last = index;
+    while tokens
+        .get(last)
+        .is_some_and(|token| token.kind == TokenKind::LineComment)
+    {
+        let (Some(space), Some(following)) = (
+            tokens.get(last.saturating_add(1)),
+            tokens.get(last.saturating_add(2)),
+        ) else {
+            break;
+        };
+        let same_comment = space.kind == TokenKind::Whitespace
+            && space.text(source).matches('\n').count() == 1
+            && following.kind == TokenKind::LineComment
+            && !is_sentinel(following, source, BEGIN_SENTINEL)
+            && !is_sentinel(following, source, END_SENTINEL);
+        if !same_comment {
+            break;
+        }
+        last = last.saturating_add(2);
+    }
+    last
+}
+
+/// Returns `true` if `comment` looks like a generated header, i.e., its first line of text is
+/// either a `name()` line or a Doxygen `@brief` tag.
+fn is_generated(comment: &str) -> bool {
+    comment
+        .lines()
+        .map(|line| {
+            line.trim()
+                .trim_start_matches('/')
+                .trim_start_matches('*')
+                .trim()
+        })
+        .find(|text| !text.is_empty())
+        .is_some_and(|first| {
+            first.starts_with("@brief ")
+                || first.strip_suffix("()").is_some_and(|name| {
+                    !name.is_empty()
+                        && name
+                            .chars()
+                            .all(|c| c.is_alphanumeric() || matches!(c, '_' | ':' | '~'))
+                })
+        })
+}
+
+/// Returns `true` if `token` is the `sentinel` line comment.
+fn is_sentinel(token: &Token, source: &str, sentinel: &str) -> bool {
+    token.kind == TokenKind::LineComment && token.text(source).trim_end() == sentinel
+}
+
+/// Returns `true` if `token` is a comment or whitespace.
+const fn is_comment_or_space(token: &Token) -> bool {
+    matches!(
+        token.kind,
+        TokenKind::Whitespace | TokenKind::LineComment | TokenKind::BlockComment
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "/*\n * main()\n *\n * Entry point of the program.\n */\n\n";
+    const NEW_HEADER: &str = "/*\n * main()\n *\n * Prints a greeting.\n */\n\n";
+    const CODE: &str = "int main()\n{\n  return 0;\n}\n";
+
+    #[test]
+    fn comments_start_finds_comments_above_the_end() {
+        let text = "int x;\n// banner\n/* note */\n\n";
+
+        assert_eq!(comments_start(text), 7);
+        assert_eq!(comments_start("int x;\n\n"), 8);
+        assert_eq!(comments_start(""), 0);
+    }
+
+    #[test]
+    fn place_header_without_previous_header_inserts_below_comments() {
+        let pseudocode = format!("// banner\n{CODE}");
+
+        assert_eq!(
+            place_header(&pseudocode, HEADER, false),
+            format!("// banner\n{HEADER}{CODE}")
+        );
+        assert_eq!(
+            place_header(&pseudocode, HEADER, true),
+            format!(
+                "// banner\n{BEGIN_SENTINEL}\n{}\n{END_SENTINEL}\n\n{CODE}",
+                HEADER.trim_end()
+            )
+        );
+    }
+
+    #[test]
+    fn place_header_does_not_duplicate_identical_header() {
+        let pseudocode = format!("{HEADER}{CODE}");
+
+        assert_eq!(place_header(&pseudocode, HEADER, false), pseudocode);
+        assert_eq!(
+            place_header(&pseudocode, NEW_HEADER, false),
+            format!("{HEADER}{NEW_HEADER}{CODE}")
+        );
+    }
+
+    #[test]
+    fn place_header_with_merge_replaces_generated_headers_and_keeps_manual_comments() {
+        let manual = "/* Reviewed: handles the empty input. */\n";
+        let pseudocode = format!("// banner\n{HEADER}{manual}{NEW_HEADER}{CODE}");
+
+        let merged = place_header(&pseudocode, NEW_HEADER, true);
+
+        assert_eq!(
+            merged,
+            format!(
+                "// banner\n{manual}{BEGIN_SENTINEL}\n{}\n{END_SENTINEL}\n\n{CODE}",
+                NEW_HEADER.trim_end()
+            )
+        );
+        assert_eq!(place_header(&merged, NEW_HEADER, true), merged);
+        assert_eq!(place_header(&merged, NEW_HEADER, false), merged);
+    }
+
+    #[test]
+    fn is_generated_recognizes_generated_styles() {
+        assert!(is_generated(HEADER));
+        assert!(is_generated("/// parse_header()\n///\n/// Parses."));
+        assert!(is_generated("/**\n * @brief Parses the header.\n */"));
+        assert!(!is_generated("/* Copyright (c) 2025 */"));
+        assert!(!is_generated("// TODO: check()"));
+    }
+}
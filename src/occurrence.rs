@@ -0,0 +1,279 @@
+//! Find the occurrences of the original names of variable renaming suggestions.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Variable;
+use crate::lexer::{TokenKind, tokenize};
+use crate::location::LineIndex;
+
+/// Occurrence of the original name of a variable renaming suggestion in pseudocode, i.e., a
+/// match that is replaced when the rename is applied.
+///
+/// Lines and columns are 1-based, and columns are counted in Unicode code points (a CR before a
+/// LF line ending is part of its line, and does not shift the columns of the next line). Byte
+/// offsets index the pseudocode as UTF-8.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RenameOccurrence {
+    /// Function the variable belongs to, if analyzed function by function.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    function: Option<String>,
+    /// Index of the suggestion in the variable renaming suggestions.
+    variable: usize,
+    /// Line number of the start of the match, starting from 1.
+    line: usize,
+    /// Column number of the start of the match, in code points, starting from 1.
+    column: usize,
+    /// Byte offset of the start of the match.
+    start: usize,
+    /// Byte offset right after the end of the match.
+    end: usize,
+}
+
+impl RenameOccurrence {
+    /// Creates a new [`RenameOccurrence`] of `found` in the text indexed by `lines`.
+    fn new(found: &RenameMatch, lines: &LineIndex<'_>) -> Self {
+        let position = lines.position(found.range.start);
+        Self {
+            function: found.function.clone(),
+            variable: found.variable,
+            line: position.line(),
+            column: position.column(),
+            start: found.range.start,
+            end: found.range.end,
+        }
+    }
+
+    /// Gets the name of the function the variable belongs to, if analyzed function by function
+    /// (the index of the suggestion then refers to the suggestions for that function).
+    #[must_use]
+    pub fn function(&self) -> Option<&str> {
+        self.function.as_deref()
+    }
+
+    /// Gets the index of the renaming suggestion whose original name occurs here.
+    #[must_use]
+    pub const fn variable(&self) -> usize {
+        self.variable
+    }
+
+    /// Gets the line number of the start of the occurrence, starting from 1.
+    #[must_use]
+    pub const fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Gets the column number of the start of the occurrence, in Unicode code points, starting
+    /// from 1.
+    #[must_use]
+    pub const fn column(&self) -> usize {
+        self.column
+    }
+
+    /// Gets the byte range of the occurrence.
+    #[must_use]
+    pub const fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+}
+
+/// Match of the original name of a renaming suggestion, found by [`find_renames`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenameMatch {
+    /// Function the variable belongs to, if analyzed function by function.
+    function: Option<String>,
+    /// Index of the suggestion in the variable renaming suggestions.
+    variable: usize,
+    /// Byte range of the match.
+    range: Range<usize>,
+}
+
+impl RenameMatch {
+    /// Gets the index of the renaming suggestion whose original name matches.
+    pub const fn variable(&self) -> usize {
+        self.variable
+    }
+
+    /// Gets the byte range of the match.
+    pub const fn range(&self) -> Range<usize> {
+        self.range.start..self.range.end
+    }
+
+    /// Sets the function the variable belongs to.
+    #[cfg(feature = "cli")]
+    pub fn function(mut self, name: &str) -> Self {
+        self.function = Some(name.to_owned());
+        self
+    }
+
+    /// Shifts the match by `offset` bytes, e.g., from a function to the file that contains it.
+    #[cfg(feature = "cli")]
+    pub const fn offset(mut self, offset: usize) -> Self {
+        self.range.start = self.range.start.saturating_add(offset);
+        self.range.end = self.range.end.saturating_add(offset);
+        self
+    }
+}
+
+/// Finds the matches of the original names of the renaming suggestions in `variables` within
+/// `pseudocode`, in order of position: each original name is matched against the identifier
+/// tokens of `pseudocode`, so that names in comments, string and character literals, and
+/// preprocessor directives are left alone, and suggestions that keep the original name are left
+/// out. All names are matched against the original text, so that a rename never affects the
+/// matches of the others; where suggestions share an original name, the earlier one wins.
+///
+/// This is the matcher used to apply renames, so that located and applied renames always agree.
+pub fn find_renames(pseudocode: &str, variables: &[Variable]) -> Vec<RenameMatch> {
+    // Index of the first suggestion that renames each original name.
+    let mut suggestions: HashMap<&str, usize> = HashMap::new();
+    for (index, variable) in variables.iter().enumerate() {
+        if variable.original_name() != variable.new_name() {
+            suggestions.entry(variable.original_name()).or_insert(index);
+        }
+    }
+    tokenize(pseudocode)
+        .into_iter()
+        .filter(|token| token.kind == TokenKind::Identifier)
+        .filter_map(|token| {
+            suggestions
+                .get(token.text(pseudocode))
+                .map(|&variable| RenameMatch {
+                    function: None,
+                    variable,
+                    range: token.range,
+                })
+        })
+        .collect()
+}
+
+/// Locates the occurrences of the original names of the renaming suggestions in `variables`
+/// within `pseudocode`, in order of position.
+///
+/// The occurrences are exactly the matches that are replaced when the renames are applied (e.g.,
+/// with [`rename_variables`](crate::rename_variables)): identifiers only, outside comments and
+/// literals.
+#[must_use]
+pub fn locate_renames(pseudocode: &str, variables: &[Variable]) -> Vec<RenameOccurrence> {
+    locate_matches(pseudocode, &find_renames(pseudocode, variables))
+}
+
+/// Returns the [`RenameOccurrence`]s of the `matches` found in `pseudocode`.
+pub fn locate_matches(pseudocode: &str, matches: &[RenameMatch]) -> Vec<RenameOccurrence> {
+    let lines = LineIndex::new(pseudocode);
+    matches
+        .iter()
+        .map(|found| RenameOccurrence::new(found, &lines))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the renaming suggestions `renames`.
+    fn variables(renames: &[(&str, &str)]) -> Vec<Variable> {
+        renames
+            .iter()
+            .map(|&(old, new)| Variable::new(old, new))
+            .collect()
+    }
+
+    /// Returns the variable index, line, column, and text of each occurrence in `pseudocode`.
+    fn located<'a>(
+        pseudocode: &'a str,
+        renames: &[(&str, &str)],
+    ) -> Vec<(usize, usize, usize, &'a str)> {
+        locate_renames(pseudocode, &variables(renames))
+            .iter()
+            .map(|occurrence| {
+                (
+                    occurrence.variable(),
+                    occurrence.line(),
+                    occurrence.column(),
+                    pseudocode.get(occurrence.range()).unwrap_or_default(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn locate_renames_finds_every_whole_word_match() {
+        let pseudocode = "int f(int a1)\n{\n  int v1 = strlen(a1);\n  return v1 + a1;\n}\n";
+
+        assert_eq!(
+            located(pseudocode, &[("a1", "str"), ("v1", "len"), ("v9", "gone")]),
+            [
+                (0, 1, 11, "a1"),
+                (1, 3, 7, "v1"),
+                (0, 3, 19, "a1"),
+                (1, 4, 10, "v1"),
+                (0, 4, 15, "a1"),
+            ]
+        );
+        assert!(
+            located(pseudocode, &[("a1", "a1"), ("len", "length")]).is_empty(),
+            "same names or substrings located"
+        );
+    }
+
+    #[test]
+    fn locate_renames_counts_columns_in_code_points() {
+        let pseudocode = "// caf\u{e9} \u{1f600}\nchar *v1 = \"\u{e9}\u{e9}\"; puts(v1);\n";
+
+        assert_eq!(
+            located(pseudocode, &[("v1", "greeting")]),
+            [(0, 2, 7, "v1"), (0, 2, 23, "v1")]
+        );
+        let offsets: Vec<Range<usize>> = locate_renames(pseudocode, &variables(&[("v1", "s")]))
+            .iter()
+            .map(RenameOccurrence::range)
+            .collect();
+        assert_eq!(offsets, [20..22, 38..40], "byte offsets not in UTF-8");
+    }
+
+    #[test]
+    fn locate_renames_handles_crlf_line_endings() {
+        let pseudocode = "int f(int a1)\r\n{\r\n  return a1;\r\n}\r\n";
+
+        assert_eq!(
+            located(pseudocode, &[("a1", "value")]),
+            [(0, 1, 11, "a1"), (0, 3, 10, "a1")]
+        );
+    }
+
+    #[test]
+    fn find_renames_matches_all_names_against_the_original_text() {
+        // Chained suggestions: renaming `a` to `b` must not make `b` match the renamed `a`.
+        let found = find_renames("a + b", &variables(&[("a", "b"), ("b", "c")]));
+
+        let ranges: Vec<(usize, Range<usize>)> = found
+            .into_iter()
+            .map(|rename| (rename.variable(), rename.range()))
+            .collect();
+        assert_eq!(ranges, [(0, 0..1), (1, 4..5)]);
+    }
+
+    #[test]
+    fn find_renames_gives_shared_names_to_earlier_suggestions() {
+        let found = find_renames("v1 v1", &variables(&[("v1", "x"), ("v1", "y")]));
+
+        assert!(
+            found.iter().all(|rename| rename.variable() == 0),
+            "later suggestion won: {found:?}"
+        );
+        assert_eq!(found.len(), 2);
+    }
+
+    #[test]
+    fn find_renames_skips_comments_literals_and_directives() {
+        let pseudocode =
+            "#define v1 4\n// v1 is a1\nputs(\"v1\"); c = 'v'; /* a1 */ v1 = a1; // v1\n";
+
+        assert_eq!(
+            located(pseudocode, &[("v1", "len"), ("a1", "str")]),
+            [(0, 3, 31, "v1"), (1, 3, 36, "a1")]
+        );
+    }
+}
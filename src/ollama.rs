@@ -1,8 +1,14 @@
 //! Handle interactions with the Ollama API.
 
+use std::io::{self, BufRead as _, BufReader, Read};
+use std::iter;
+use std::time::{Duration, Instant};
+
 use serde::{Deserialize, Serialize};
+use ureq::Agent;
 
-use crate::{OneiromancerError, OneiromancerResults};
+use crate::options::ModelOptions;
+use crate::{OneiromancerError, OneiromancerResults, ResponseMetrics};
 
 /// Ollama API request content.
 #[derive(Serialize, Debug, Clone)]
@@ -11,10 +17,16 @@ pub struct OllamaRequest<'a> {
     model: &'a str,
     /// Input prompt to send to the model.
     prompt: &'a str,
-    /// Whether to stream the response or not (should be `false` for our purposes).
+    /// Whether to stream the response as a sequence of chunks, one JSON object per line.
     stream: bool,
     /// Response format to use (should be `json` for our purposes).
     format: &'a str,
+    /// Options passed through to the model, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<&'a ModelOptions>,
+    /// How long the model stays loaded after the request, if not the server default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<&'a str>,
 }
 
 impl<'a> OllamaRequest<'a> {
@@ -25,22 +37,244 @@ impl<'a> OllamaRequest<'a> {
             prompt,
             stream: false,
             format: "json",
+            options: None,
+            keep_alive: None,
         }
     }
 
-    /// Sends an [`OllamaRequest`] to the `/api/generate` endpoint at `baseurl`.
+    /// Sets the `options` passed through to the model (omitted from the request if empty).
+    pub(crate) fn options(mut self, options: &'a ModelOptions) -> Self {
+        self.options = Some(options).filter(|opts| !opts.is_empty());
+        self
+    }
+
+    /// Sets how long the model stays loaded after the request (e.g., `10m`), or the server
+    /// default if `None`.
+    pub(crate) const fn keep_alive(mut self, keep_alive: Option<&'a str>) -> Self {
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    /// Sets whether the response is streamed as it is generated (`false` by default).
+    pub(crate) const fn stream(mut self, stream: bool) -> Self {
+        self.stream = stream;
+        self
+    }
+
+    /// Sends an [`OllamaRequest`] to the `/api/generate` endpoint at `baseurl` through `agent`.
     ///
     /// Returns an [`OllamaResponse`] which contains the LLM response.
     ///
     /// # Errors
     ///
     /// Returns the appropriate [`OneiromancerError`] in case something goes wrong with the request.
-    pub(crate) fn send(&self, baseurl: &str) -> Result<OllamaResponse, OneiromancerError> {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "ollama_request",
+            skip_all,
+            fields(model = self.model, prompt_bytes = self.prompt.len())
+        )
+    )]
+    pub(crate) fn send(
+        &self,
+        agent: &Agent,
+        baseurl: &str,
+    ) -> Result<OllamaResponse, OneiromancerError> {
+        let url = format!("{}{}", baseurl.trim_end_matches('/'), "/api/generate");
+        log::debug!(
+            "Sending request to `{url}` with model `{}` ({} bytes of prompt)",
+            self.model,
+            self.prompt.len()
+        );
+        #[cfg(feature = "tracing")]
+        tracing::debug!("request sent");
+        let started = Instant::now();
+        let result = agent.post(url).send_json(self).and_then(|mut http| {
+            let status = http.status().as_u16();
+            http.body_mut()
+                .read_json::<OllamaResponse>()
+                .map(|response| (status, response))
+        });
+        let duration_ms = started.elapsed().as_millis();
+        #[cfg(feature = "tracing")]
+        trace_response(&result, duration_ms);
+        let (_, response) = result?;
+        log::debug!(
+            "Received response in {duration_ms} ms ({})",
+            response.summary()
+        );
+        Ok(response)
+    }
+
+    /// Sends a streaming [`OllamaRequest`] to the `/api/generate` endpoint at `baseurl` through
+    /// `agent`, like [`send`](Self::send), calling `on_token` with each piece of the response as
+    /// it arrives.
+    ///
+    /// Returns an [`OllamaResponse`] assembled from the chunks, with the statistics of the final
+    /// one.
+    ///
+    /// # Errors
+    ///
+    /// Returns the appropriate [`OneiromancerError`] in case something goes wrong with the request,
+    /// including an error reported by the server in the middle of the stream or a stream that ends
+    /// before the final chunk.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "ollama_request",
+            skip_all,
+            fields(model = self.model, prompt_bytes = self.prompt.len())
+        )
+    )]
+    pub(crate) fn send_streaming(
+        &self,
+        agent: &Agent,
+        baseurl: &str,
+        on_token: &mut dyn FnMut(&str),
+    ) -> Result<OllamaResponse, OneiromancerError> {
         let url = format!("{}{}", baseurl.trim_end_matches('/'), "/api/generate");
-        Ok(ureq::post(url)
-            .send_json(self)?
-            .body_mut()
-            .read_json::<OllamaResponse>()?)
+        log::debug!(
+            "Sending streaming request to `{url}` with model `{}` ({} bytes of prompt)",
+            self.model,
+            self.prompt.len()
+        );
+        #[cfg(feature = "tracing")]
+        tracing::debug!("request sent");
+        let started = Instant::now();
+        let result = agent.post(url).send_json(self).and_then(|mut http| {
+            let status = http.status().as_u16();
+            read_stream(http.body_mut().as_reader(), on_token).map(|response| (status, response))
+        });
+        let duration_ms = started.elapsed().as_millis();
+        #[cfg(feature = "tracing")]
+        trace_response(&result, duration_ms);
+        let (_, response) = result?;
+        log::debug!(
+            "Received streamed response in {duration_ms} ms ({} bytes)",
+            response.response.len()
+        );
+        Ok(response)
+    }
+
+    /// Sends an [`OllamaRequest`] to the `/api/generate` endpoint at `baseurl` through the async
+    /// `client`, like [`send`](Self::send).
+    ///
+    /// Returns an [`OllamaResponse`] which contains the LLM response.
+    ///
+    /// # Errors
+    ///
+    /// Returns the appropriate [`OneiromancerError`] in case something goes wrong with the request.
+    /// Errors of `reqwest` are converted to the [`ureq::Error`] of
+    /// [`OllamaQueryFailed`](OneiromancerError::OllamaQueryFailed) that `send` would return (e.g.,
+    /// [`ureq::Error::StatusCode`]), so that callers handle both the same way.
+    #[cfg(feature = "async")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "ollama_request",
+            skip_all,
+            fields(model = self.model, prompt_bytes = self.prompt.len())
+        )
+    )]
+    pub(crate) async fn send_async(
+        &self,
+        client: &reqwest::Client,
+        baseurl: &str,
+    ) -> Result<OllamaResponse, OneiromancerError> {
+        let url = format!("{}{}", baseurl.trim_end_matches('/'), "/api/generate");
+        log::debug!(
+            "Sending async request to `{url}` with model `{}` ({} bytes of prompt)",
+            self.model,
+            self.prompt.len()
+        );
+        #[cfg(feature = "tracing")]
+        tracing::debug!("request sent");
+        let started = Instant::now();
+        let result = match client.post(url).json(self).send().await {
+            Ok(http) if http.status().is_success() => {
+                let status = http.status().as_u16();
+                match http.bytes().await {
+                    Ok(body) => serde_json::from_slice::<OllamaResponse>(&body)
+                        .map(|response| (status, response))
+                        .map_err(ureq::Error::Json),
+                    Err(err) => Err(from_reqwest(err)),
+                }
+            }
+            Ok(http) => Err(ureq::Error::StatusCode(http.status().as_u16())),
+            Err(err) => Err(from_reqwest(err)),
+        };
+        let duration_ms = started.elapsed().as_millis();
+        #[cfg(feature = "tracing")]
+        trace_response(&result, duration_ms);
+        let (_, response) = result?;
+        log::debug!(
+            "Received response in {duration_ms} ms ({})",
+            response.summary()
+        );
+        Ok(response)
+    }
+}
+
+/// Reads the chunks of a streamed response from `reader`, calling `on_token` with the text of each
+/// one, and returns the response assembled from them.
+fn read_stream(
+    reader: impl Read,
+    on_token: &mut dyn FnMut(&str),
+) -> Result<OllamaResponse, ureq::Error> {
+    let mut text = String::new();
+    for read in BufReader::new(reader).lines() {
+        let line = read?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let chunk = serde_json::from_str::<OllamaChunk>(&line).map_err(ureq::Error::Json)?;
+        if let Some(message) = chunk.error {
+            return Err(ureq::Error::Other(message.into()));
+        }
+        if !chunk.response.is_empty() {
+            on_token(&chunk.response);
+            text.push_str(&chunk.response);
+        }
+        if chunk.done {
+            // The final chunk carries the statistics of the whole response.
+            let mut last =
+                serde_json::from_str::<OllamaResponse>(&line).map_err(ureq::Error::Json)?;
+            last.response = text;
+            return Ok(last);
+        }
+    }
+    Err(io::Error::from(io::ErrorKind::UnexpectedEof).into())
+}
+
+/// Converts a `reqwest` error into the closest [`ureq::Error`].
+#[cfg(feature = "async")]
+fn from_reqwest(err: reqwest::Error) -> ureq::Error {
+    match err.status() {
+        Some(status) => ureq::Error::StatusCode(status.as_u16()),
+        None if err.is_connect() => ureq::Error::ConnectionFailed,
+        None if err.is_timeout() => ureq::Error::Timeout(ureq::Timeout::Global),
+        None => ureq::Error::Other(Box::new(err)),
+    }
+}
+
+/// Emits the `tracing` event of the outcome of a request that took `duration_ms` milliseconds: its
+/// HTTP status and response size, or the reason of the failure.
+#[cfg(feature = "tracing")]
+fn trace_response(result: &Result<(u16, OllamaResponse), ureq::Error>, duration_ms: u128) {
+    match result.as_ref() {
+        Ok(received) => tracing::debug!(
+            status = received.0,
+            duration_ms,
+            response_bytes = received.1.response.len(),
+            prompt_tokens = received.1.prompt_eval_count,
+            response_tokens = received.1.eval_count,
+            "response received"
+        ),
+        Err(&ureq::Error::StatusCode(status)) => {
+            tracing::warn!(status, duration_ms, "request failed");
+        }
+        Err(err) => tracing::warn!(error = %err, duration_ms, "request failed"),
     }
 }
 
@@ -49,14 +283,63 @@ impl<'a> OllamaRequest<'a> {
 pub struct OllamaResponse {
     /// Ollama API response content.
     response: String,
+    /// Number of tokens in the prompt, if reported.
+    prompt_eval_count: Option<u64>,
+    /// Number of tokens in the response, if reported.
+    eval_count: Option<u64>,
+    /// Time spent loading the model, in nanoseconds, if reported.
+    load_duration: Option<u64>,
+    /// Time spent generating the response, in nanoseconds, if reported.
+    eval_duration: Option<u64>,
+}
+
+/// Chunk of a streamed Ollama API response.
+#[derive(Deserialize, Debug, Clone)]
+struct OllamaChunk {
+    /// Text generated since the previous chunk.
+    #[serde(default)]
+    response: String,
+    /// Whether this is the final chunk.
+    #[serde(default)]
+    done: bool,
+    /// Error reported by the server in the middle of the stream, if any.
+    error: Option<String>,
 }
 
 impl OllamaResponse {
+    /// Creates an [`OllamaResponse`] with the raw `response` of a model and no statistics, e.g.,
+    /// for the response of an [`LlmBackend`](crate::LlmBackend).
+    pub(crate) const fn from_text(response: String) -> Self {
+        Self {
+            response,
+            prompt_eval_count: None,
+            eval_count: None,
+            load_duration: None,
+            eval_duration: None,
+        }
+    }
+
     /// Returns the raw response string from the Ollama API.
     pub(crate) fn response(&self) -> &str {
         &self.response
     }
 
+    /// Returns the size of the response and the token counts reported by the Ollama API, for
+    /// diagnostics (e.g., `512 bytes, 300 prompt tokens, 120 response tokens`).
+    fn summary(&self) -> String {
+        iter::once(format!("{} bytes", self.response.len()))
+            .chain(
+                self.prompt_eval_count
+                    .map(|tokens| format!("{tokens} prompt tokens")),
+            )
+            .chain(
+                self.eval_count
+                    .map(|tokens| format!("{tokens} response tokens")),
+            )
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
     /// Parses an [`OllamaResponse`] into an [`OneiromancerResults`] struct.
     ///
     /// Returns [`OneiromancerResults`] which contains the parsed LLM response.
@@ -65,18 +348,219 @@ impl OllamaResponse {
     ///
     /// Returns the appropriate [`OneiromancerError`] in case something goes wrong with parsing.
     pub(crate) fn parse(&self) -> Result<OneiromancerResults, OneiromancerError> {
-        Ok(serde_json::from_str(self.response())?)
+        let parsed = serde_json::from_str::<OneiromancerResults>(self.response());
+        #[cfg(feature = "tracing")]
+        if let Err(err) = parsed.as_ref() {
+            tracing::warn!(error = %err, response_bytes = self.response.len(), "response parse failed");
+        }
+        Ok(parsed?.attach_metrics(self.metrics()))
+    }
+
+    /// Returns the timing and token statistics reported in the response, if any.
+    pub(crate) fn metrics(&self) -> ResponseMetrics {
+        ResponseMetrics::new(
+            self.prompt_eval_count,
+            self.eval_count,
+            self.load_duration.map(Duration::from_nanos),
+            self.eval_duration.map(Duration::from_nanos),
+        )
+    }
+}
+
+/// Ollama API version response.
+#[derive(Deserialize, Debug, Clone)]
+struct OllamaVersion {
+    /// Version of the Ollama server.
+    version: String,
+}
+
+/// Ollama API list of local models.
+#[derive(Deserialize, Debug, Clone)]
+struct OllamaTags {
+    /// Models available on the Ollama server.
+    models: Vec<OllamaModel>,
+}
+
+/// Model available on the Ollama server.
+#[derive(Deserialize, Debug, Clone)]
+struct OllamaModel {
+    /// Name of the model, including its tag (e.g., `aidapal:latest`).
+    name: String,
+}
+
+/// Ollama API request to download a model from the Ollama library.
+#[derive(Serialize, Debug)]
+struct PullRequest<'a> {
+    /// Name of the model to download.
+    model: &'a str,
+    /// Whether to stream the progress.
+    stream: bool,
+}
+
+/// Progress of the download of a model from the Ollama library, as reported by the server.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct PullProgress {
+    /// Current step (e.g., `pulling manifest`, `pulling <digest>`, or `success`).
+    #[serde(default)]
+    status: String,
+    /// Digest of the layer being downloaded, if any.
+    digest: Option<String>,
+    /// Size of the layer being downloaded, in bytes, if known.
+    total: Option<u64>,
+    /// Bytes of the layer downloaded so far, if known.
+    completed: Option<u64>,
+}
+
+impl PullProgress {
+    /// Gets the current step (e.g., `pulling manifest`, `verifying sha256 digest`, or `success`).
+    #[must_use]
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+
+    /// Gets the digest of the layer being downloaded, if any.
+    #[must_use]
+    pub fn digest(&self) -> Option<&str> {
+        self.digest.as_deref()
+    }
+
+    /// Gets the size of the layer being downloaded, in bytes, if known.
+    #[must_use]
+    pub const fn total(&self) -> Option<u64> {
+        self.total
+    }
+
+    /// Gets the number of bytes of the layer downloaded so far, if known.
+    #[must_use]
+    pub const fn completed(&self) -> Option<u64> {
+        self.completed
+    }
+}
+
+/// Line of the streamed progress of a download.
+#[derive(Deserialize, Debug, Clone)]
+struct PullChunk {
+    /// Progress of the download.
+    #[serde(flatten)]
+    progress: PullProgress,
+    /// Error reported by the server in the middle of the stream, if any.
+    error: Option<String>,
+}
+
+/// Queries the `/api/version` endpoint at `baseurl` through `agent`, returning the version of the
+/// Ollama server.
+///
+/// # Errors
+///
+/// Returns the appropriate [`OneiromancerError`] in case something goes wrong with the request.
+pub fn version(agent: &Agent, baseurl: &str) -> Result<String, OneiromancerError> {
+    let url = format!("{}{}", baseurl.trim_end_matches('/'), "/api/version");
+    Ok(agent
+        .get(url)
+        .call()?
+        .body_mut()
+        .read_json::<OllamaVersion>()?
+        .version)
+}
+
+/// Queries the `/api/tags` endpoint at `baseurl` through `agent`, returning the names of the local
+/// models.
+///
+/// # Errors
+///
+/// Returns the appropriate [`OneiromancerError`] in case something goes wrong with the request.
+pub fn tags(agent: &Agent, baseurl: &str) -> Result<Vec<String>, OneiromancerError> {
+    let url = format!("{}{}", baseurl.trim_end_matches('/'), "/api/tags");
+    Ok(agent
+        .get(url)
+        .call()?
+        .body_mut()
+        .read_json::<OllamaTags>()?
+        .models
+        .into_iter()
+        .map(|model| model.name)
+        .collect())
+}
+
+/// Queries the `/api/tags` endpoint at `baseurl` like [`tags`], through the async `client`.
+///
+/// # Errors
+///
+/// Returns the appropriate [`OneiromancerError`] in case something goes wrong with the request.
+#[cfg(feature = "async")]
+pub async fn tags_async(
+    client: &reqwest::Client,
+    baseurl: &str,
+) -> Result<Vec<String>, OneiromancerError> {
+    let url = format!("{}{}", baseurl.trim_end_matches('/'), "/api/tags");
+    let http = client
+        .get(url)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(from_reqwest)?;
+    let body = http.bytes().await.map_err(from_reqwest)?;
+    let tags = serde_json::from_slice::<OllamaTags>(&body).map_err(ureq::Error::Json)?;
+    Ok(tags.models.into_iter().map(|model| model.name).collect())
+}
+
+/// Downloads `model` from the Ollama library through the `/api/pull` endpoint at `baseurl`,
+/// calling `on_progress` with each progress update streamed by the server.
+///
+/// # Errors
+///
+/// Returns the appropriate [`OneiromancerError`] in case something goes wrong with the request or
+/// the download.
+pub fn pull(
+    agent: &Agent,
+    baseurl: &str,
+    model: &str,
+    on_progress: &mut dyn FnMut(&PullProgress),
+) -> Result<(), OneiromancerError> {
+    let url = format!("{}{}", baseurl.trim_end_matches('/'), "/api/pull");
+    log::debug!("Pulling model `{model}` through `{url}`");
+    let mut http = agent.post(url).send_json(PullRequest {
+        model,
+        stream: true,
+    })?;
+    Ok(read_pull_stream(http.body_mut().as_reader(), on_progress)?)
+}
+
+/// Reads the streamed progress of a download from `reader`, calling `on_progress` with each
+/// update, until the server reports success.
+fn read_pull_stream(
+    reader: impl Read,
+    on_progress: &mut dyn FnMut(&PullProgress),
+) -> Result<(), ureq::Error> {
+    for read in BufReader::new(reader).lines() {
+        let line = read?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let chunk = serde_json::from_str::<PullChunk>(&line).map_err(ureq::Error::Json)?;
+        if let Some(message) = chunk.error {
+            return Err(ureq::Error::Other(message.into()));
+        }
+        on_progress(&chunk.progress);
+        if chunk.progress.status == "success" {
+            return Ok(());
+        }
     }
+    Err(io::Error::from(io::ErrorKind::UnexpectedEof).into())
 }
 
 #[cfg(test)]
 #[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
 mod tests {
     use std::env;
+    use std::io::ErrorKind;
 
-    use super::OllamaRequest;
-    use crate::OneiromancerError;
+    use ureq::Agent;
+
+    use super::{OllamaRequest, OllamaResponse, PullProgress, read_pull_stream, read_stream};
     use crate::oneiromancer::{OLLAMA_BASEURL, OLLAMA_MODEL};
+    use crate::options::ModelOptions;
+    use crate::{OneiromancerError, parse_option};
 
     const VALID_PSEUDOCODE: &str = r#"int main() { int v1 = 0; printf("Hello, world!"); }"#;
 
@@ -88,13 +572,183 @@ mod tests {
         let pseudocode = VALID_PSEUDOCODE;
 
         let request = OllamaRequest::new(&model, pseudocode);
-        let response = request.send(&baseurl)?;
+        let response = request.send(&Agent::new_with_defaults(), &baseurl)?;
 
         assert!(!response.response().is_empty(), "response is empty");
 
         Ok(())
     }
 
+    #[test]
+    fn ollama_response_summary_includes_reported_token_counts() -> anyhow::Result<()> {
+        let response: OllamaResponse =
+            serde_json::from_str(r#"{"response":"{}","prompt_eval_count":300,"eval_count":120}"#)?;
+
+        assert_eq!(
+            response.summary(),
+            "2 bytes, 300 prompt tokens, 120 response tokens"
+        );
+        assert_eq!(
+            OllamaResponse::from_text("{}".to_owned()).summary(),
+            "2 bytes"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn ollama_request_serializes_model_options() -> anyhow::Result<()> {
+        let mut options = ModelOptions::default();
+        for option in [
+            "num_gpu=1",
+            "repeat_penalty=1.1",
+            "mirostat=true",
+            r#"stop=["}","\n\n"]"#,
+            "tag=debug",
+            "num_gpu=2",
+        ] {
+            let (key, value) = parse_option(option)?;
+            options.insert(&key, value);
+        }
+
+        let request = serde_json::to_value(OllamaRequest::new("m", "p").options(&options))?;
+        assert_eq!(
+            request.get("options"),
+            Some(&serde_json::from_str(
+                r#"{"mirostat":true,"num_gpu":2,"repeat_penalty":1.1,"stop":["}","\n\n"],"tag":"debug"}"#
+            )?)
+        );
+        let plain =
+            serde_json::to_value(OllamaRequest::new("m", "p").options(&ModelOptions::default()))?;
+        assert_eq!(plain.get("options"), None, "empty options serialized");
+        assert_eq!(
+            plain.get("keep_alive"),
+            None,
+            "default keep_alive serialized"
+        );
+        let warm = serde_json::to_value(OllamaRequest::new("m", "p").keep_alive(Some("10m")))?;
+        assert_eq!(warm.get("keep_alive"), Some(&"10m".into()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_stream_assembles_chunks_and_reports_tokens() -> anyhow::Result<()> {
+        let stream = concat!(
+            r#"{"response":"{\"function_name\":","done":false}"#,
+            "\n\n",
+            r#"{"response":"\"f\"}","done":false}"#,
+            "\n",
+            r#"{"response":"","done":true,"eval_count":7}"#,
+            "\n",
+        );
+        let mut tokens = Vec::new();
+        let response = read_stream(stream.as_bytes(), &mut |token| {
+            tokens.push(token.to_owned());
+        })?;
+
+        assert_eq!(tokens, ["{\"function_name\":", "\"f\"}"]);
+        assert_eq!(response.response(), "{\"function_name\":\"f\"}");
+        assert_eq!(response.metrics().completion_tokens(), Some(7));
+        assert!(
+            serde_json::to_value(OllamaRequest::new("m", "p").stream(true))?
+                .get("stream")
+                .is_some_and(|stream_flag| stream_flag == true),
+            "stream not requested"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_stream_reports_errors_and_truncation() {
+        let mut ignore = |_: &str| {};
+        let failed = read_stream(
+            concat!(
+                r#"{"response":"{","done":false}"#,
+                "\n",
+                r#"{"error":"out of memory"}"#
+            )
+            .as_bytes(),
+            &mut ignore,
+        );
+        assert!(
+            matches!(&failed, Err(ureq::Error::Other(err)) if err.to_string() == "out of memory"),
+            "{failed:?}"
+        );
+        let truncated = read_stream(&br#"{"response":"{","done":false}"#[..], &mut ignore);
+        assert!(
+            matches!(&truncated, Err(ureq::Error::Io(err)) if err.kind() == ErrorKind::UnexpectedEof),
+            "{truncated:?}"
+        );
+        let garbled = read_stream(&b"not json\n"[..], &mut ignore);
+        assert!(matches!(garbled, Err(ureq::Error::Json(_))), "{garbled:?}");
+    }
+
+    #[test]
+    fn read_pull_stream_reports_progress_until_success() -> anyhow::Result<()> {
+        let stream = concat!(
+            r#"{"status":"pulling manifest"}"#,
+            "\n",
+            r#"{"status":"pulling 8eeb52dfb3bb","digest":"sha256:8eeb52dfb3bb","total":100,"completed":40}"#,
+            "\n\n",
+            r#"{"status":"success"}"#,
+            "\n",
+        );
+        let mut updates: Vec<PullProgress> = Vec::new();
+        read_pull_stream(stream.as_bytes(), &mut |progress| {
+            updates.push(progress.clone());
+        })?;
+
+        let statuses = updates.iter().map(PullProgress::status).collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            ["pulling manifest", "pulling 8eeb52dfb3bb", "success"]
+        );
+        let layer = updates.get(1).cloned().unwrap_or_default();
+        assert_eq!(layer.digest(), Some("sha256:8eeb52dfb3bb"));
+        assert_eq!((layer.completed(), layer.total()), (Some(40), Some(100)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_pull_stream_reports_errors_and_truncation() {
+        let mut ignore = |_: &PullProgress| {};
+        let failed = read_pull_stream(
+            &br#"{"error":"pull model manifest: file does not exist"}"#[..],
+            &mut ignore,
+        );
+        assert!(
+            matches!(&failed, Err(ureq::Error::Other(err)) if err.to_string().contains("does not exist")),
+            "{failed:?}"
+        );
+        let truncated = read_pull_stream(&br#"{"status":"pulling manifest"}"#[..], &mut ignore);
+        assert!(
+            matches!(&truncated, Err(ureq::Error::Io(err)) if err.kind() == ErrorKind::UnexpectedEof),
+            "{truncated:?}"
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn ollama_request_async_with_wrong_url_fails() {
+        let request = OllamaRequest::new("m", VALID_PSEUDOCODE);
+        let result = request
+            .send_async(&reqwest::Client::new(), "http://127.0.0.1:6666")
+            .await;
+
+        assert!(
+            matches!(
+                result,
+                Err(OneiromancerError::OllamaQueryFailed(
+                    ureq::Error::ConnectionFailed
+                ))
+            ),
+            "wrong error type returned: {result:?}"
+        );
+    }
+
     #[test]
     fn ollama_request_with_wrong_url_fails() {
         let baseurl = "http://127.0.0.1:6666";
@@ -102,7 +756,7 @@ mod tests {
         let pseudocode = VALID_PSEUDOCODE;
 
         let request = OllamaRequest::new(&model, pseudocode);
-        let result = request.send(baseurl);
+        let result = request.send(&Agent::new_with_defaults(), baseurl);
 
         assert!(result.is_err(), "request succeeded unexpectedly");
         assert!(
@@ -118,7 +772,7 @@ mod tests {
         let pseudocode = VALID_PSEUDOCODE;
 
         let request = OllamaRequest::new(&model, pseudocode);
-        let result = request.send(baseurl);
+        let result = request.send(&Agent::new_with_defaults(), baseurl);
 
         assert!(result.is_err(), "request succeeded unexpectedly");
         assert!(
@@ -135,7 +789,7 @@ mod tests {
         let pseudocode = VALID_PSEUDOCODE;
 
         let request = OllamaRequest::new(model, pseudocode);
-        let result = request.send(&baseurl);
+        let result = request.send(&Agent::new_with_defaults(), &baseurl);
 
         assert!(result.is_err(), "request succeeded unexpectedly");
         assert!(
@@ -152,7 +806,7 @@ mod tests {
         let pseudocode = VALID_PSEUDOCODE;
 
         let request = OllamaRequest::new(model, pseudocode);
-        let result = request.send(&baseurl);
+        let result = request.send(&Agent::new_with_defaults(), &baseurl);
 
         assert!(result.is_err(), "request succeeded unexpectedly");
         assert!(
@@ -169,7 +823,7 @@ mod tests {
         let pseudocode = "";
 
         let request = OllamaRequest::new(&model, pseudocode);
-        let response = request.send(&baseurl)?;
+        let response = request.send(&Agent::new_with_defaults(), &baseurl)?;
 
         assert!(response.response().is_empty(), "response is not empty");
 
@@ -1,20 +1,49 @@
 //! Analyze pseudocode and handle results and errors.
 
+use std::borrow::Cow;
 use std::env;
 use std::fs::File;
 use std::io::{self, BufReader, Read as _};
+use std::iter;
+use std::num::NonZeroUsize;
 use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
+#[cfg(feature = "async")]
+use tokio::fs;
+use ureq::Agent;
 
-use crate::ollama::OllamaRequest;
+use crate::cache::ResponseMemo;
+use crate::consensus::{Consensus, merge_samples};
+use crate::joint::{JointAnalysis, parse_joint_response};
+use crate::ollama::{self, OllamaRequest, OllamaResponse, PullProgress};
+use crate::options::ModelOptions;
+use crate::prompt::{
+    append_callees, append_decompiler_hint, append_joint_instruction, append_language,
+    estimated_tokens,
+};
+use crate::quality::QualityIssue;
+use crate::split::direct_callees;
+use crate::window::{merge_windows, split_windows};
+use crate::{
+    AnalysisContext, FunctionSpan, InputError, InputPolicy, PromptOptions, ResponseMetrics,
+    split_functions, validate_input,
+};
+use crate::{BackendRequest, LlmBackend, ResponseCache, RetryPolicy, Timeouts};
 
 /// Default Ollama URL.
 pub const OLLAMA_BASEURL: &str = "http://127.0.0.1:11434";
 /// Default Ollama model.
 pub const OLLAMA_MODEL: &str = "aidapal";
 
+/// Tokens of the context window kept free for the response, unless `num_predict` is set.
+const RESPONSE_TOKENS: usize = 1024;
+/// Lines repeated at the start of each window of a function that exceeds the context window.
+const WINDOW_OVERLAP: usize = 8;
+
 /// Oneiromancer client for analyzing pseudocode via the Ollama API.
 #[derive(Debug, Clone)]
 pub struct Oneiromancer {
@@ -22,6 +51,36 @@ pub struct Oneiromancer {
     baseurl: String,
     /// Ollama model to use for analysis.
     model: String,
+    /// Policy that input files must comply with.
+    input_policy: InputPolicy,
+    /// Options that control how pseudocode is prepared before it is sent to the LLM.
+    prompt_options: PromptOptions,
+    /// On-disk cache of LLM responses, if enabled.
+    cache: Option<ResponseCache>,
+    /// In-memory memo of the results of the prompts submitted during a batch, if enabled.
+    memo: Option<ResponseMemo>,
+    /// Options passed through to the model.
+    options: ModelOptions,
+    /// Language of the generated description, if not the model default (English).
+    language: Option<String>,
+    /// How long the model stays loaded after each request, if not the server default.
+    keep_alive: Option<String>,
+    /// Number of generations merged by majority vote for each prompt.
+    samples: NonZeroUsize,
+    /// HTTP agent, shared by all clones so that connections to the Ollama server are reused.
+    agent: Agent,
+    /// Async HTTP client, shared by all clones like the agent.
+    #[cfg(feature = "async")]
+    http: reqwest::Client,
+    /// Backend that prompts are submitted to instead of the Ollama API, if any.
+    backend: Option<Arc<dyn LlmBackend>>,
+    /// Policy for retrying requests that fail for transient reasons.
+    retries: RetryPolicy,
+    /// Whether to check that the server is reachable and the model is available before the first
+    /// request to the Ollama API.
+    preflight: bool,
+    /// Whether the preflight check passed, shared by all clones with the same server and model.
+    preflighted: Arc<AtomicBool>,
 }
 
 impl Oneiromancer {
@@ -35,6 +94,7 @@ impl Oneiromancer {
     #[must_use]
     pub fn baseurl(mut self, baseurl: impl Into<String>) -> Self {
         self.baseurl = baseurl.into();
+        self.preflighted = Arc::default();
         self
     }
 
@@ -42,9 +102,328 @@ impl Oneiromancer {
     #[must_use]
     pub fn model(mut self, model: impl Into<String>) -> Self {
         self.model = model.into();
+        self.preflighted = Arc::default();
+        self
+    }
+
+    /// Sets the policy that input files passed to [`Oneiromancer::analyze_file`] must comply
+    /// with (e.g., accepted extensions).
+    #[must_use]
+    pub fn input_policy(mut self, policy: InputPolicy) -> Self {
+        self.input_policy = policy;
+        self
+    }
+
+    /// Sets the options that control how pseudocode is prepared before it is sent to the LLM
+    /// (e.g., stripping preprocessor noise to save tokens).
+    #[must_use]
+    pub const fn prompt_options(mut self, options: PromptOptions) -> Self {
+        self.prompt_options = options;
+        self
+    }
+
+    /// Sets the on-disk cache of LLM responses (`None` to disable the cache, the default).
+    ///
+    /// With a cache, prompts that were already submitted to the same model are answered from
+    /// the cache, without querying the Ollama API.
+    #[must_use]
+    pub fn cache(mut self, cache: Option<ResponseCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Sets the in-memory memo of the results of the prompts submitted during a batch (`None` to
+    /// disable it, the default), checked before the on-disk cache.
+    #[must_use]
+    #[cfg(feature = "cli")]
+    pub(crate) fn memo(mut self, memo: Option<ResponseMemo>) -> Self {
+        self.memo = memo;
+        self
+    }
+
+    /// Sets the model option `key` to `value` (e.g., `num_ctx` or `stop`), passed through to the
+    /// model in the `options` object of Ollama API requests. Options can be parsed from `key=value`
+    /// strings with [`parse_option`](crate::parse_option).
+    ///
+    /// Setting the same option again replaces its value, and logs a warning.
+    #[must_use]
+    pub fn option(mut self, key: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
+        self.options.insert(&key.into(), value.into());
+        self
+    }
+
+    /// Sets the `temperature` model option: lower values (e.g., `0`) make the results more
+    /// deterministic, higher values more creative.
+    #[must_use]
+    pub fn temperature(self, temperature: f64) -> Self {
+        self.option("temperature", temperature)
+    }
+
+    /// Sets the `num_ctx` model option, the size of the context window in tokens, which must fit
+    /// the prompt and the response (e.g., `16384` for large functions).
+    #[must_use]
+    pub fn num_ctx(self, num_ctx: u32) -> Self {
+        self.option("num_ctx", num_ctx)
+    }
+
+    /// Sets the `seed` model option, so that the same prompt yields the same results (with
+    /// [`samples`](Self::samples), consecutive seeds are used from this one).
+    #[must_use]
+    pub fn seed(self, seed: u64) -> Self {
+        self.option("seed", seed)
+    }
+
+    /// Sets the `num_predict` model option, the maximum number of tokens to generate (`-1` for no
+    /// limit).
+    #[must_use]
+    pub fn num_predict(self, num_predict: i64) -> Self {
+        self.option("num_predict", num_predict)
+    }
+
+    /// Sets the natural language of the generated description (e.g., `Spanish` or `Italian`).
+    ///
+    /// An instruction to write the `comment` field in `language`, while keeping the function and
+    /// variable names in English and ASCII, is appended to each prompt. Without a language (the
+    /// default), prompts are sent unchanged and the model answers in English. An empty `language`
+    /// resets the default.
+    #[must_use]
+    pub fn language(mut self, language: impl Into<String>) -> Self {
+        let name: String = language.into();
+        self.language = Some(name.trim().to_owned()).filter(|trimmed| !trimmed.is_empty());
+        self
+    }
+
+    /// Sets how long the model stays loaded in memory after each request (e.g., `10m`, or `-1` to
+    /// keep it loaded indefinitely), so that consecutive queries do not wait for the model to be
+    /// loaded again. Without it (the default), the server default applies (5 minutes).
+    #[must_use]
+    pub fn keep_alive(mut self, keep_alive: impl Into<String>) -> Self {
+        self.keep_alive = Some(keep_alive.into());
+        self
+    }
+
+    /// Sets the number of generations requested for each prompt (1, the default, disables
+    /// sampling).
+    ///
+    /// With multiple samples, each generation uses a different seed (counting up from the `seed`
+    /// model option, if set, or from 0), and the results are merged by majority vote with
+    /// [`merge_samples`]. Sampling only makes sense with a temperature above zero.
+    #[must_use]
+    pub const fn samples(mut self, samples: NonZeroUsize) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    /// Sets the [`LlmBackend`] that prompts are submitted to instead of the Ollama API (e.g., another
+    /// local inference server).
+    ///
+    /// Everything else works as with Ollama: the prompts, the samples, the cache, and the parsing of
+    /// the results. The responses of backends carry no token and timing statistics, streaming
+    /// analyses get the whole response as a single piece, and the async API calls the backend on
+    /// the current task. [`server_version`](Oneiromancer::server_version) and
+    /// [`list_models`](Oneiromancer::list_models) still query the Ollama API.
+    #[must_use]
+    pub fn backend(mut self, backend: impl LlmBackend + 'static) -> Self {
+        self.backend = Some(Arc::new(backend));
+        self
+    }
+
+    /// Sets the [`RetryPolicy`] of requests that fail for transient reasons, such as connection
+    /// resets, `5xx` responses, and timeouts (no retries by default).
+    ///
+    /// Retries apply to the requests sent to the Ollama API and to the backend, if set, except for
+    /// streaming requests, whose tokens may have been delivered already.
+    #[must_use]
+    pub const fn retries(mut self, policy: RetryPolicy) -> Self {
+        self.retries = policy;
         self
     }
 
+    /// Sets the [`Timeouts`] of the requests to the Ollama API (none by default), after which they
+    /// fail with [`OneiromancerError::Timeout`]. Set the timeouts of a backend, if any, when
+    /// creating it (e.g., with [`OllamaBackend::timeouts`](crate::OllamaBackend::timeouts)).
+    #[must_use]
+    pub fn timeouts(self, timeouts: Timeouts) -> Self {
+        Self {
+            agent: timeouts.agent(),
+            #[cfg(feature = "async")]
+            http: timeouts.http_client(),
+            ..self
+        }
+    }
+
+    /// Sets whether to check that the Ollama server is reachable and the model is available (with
+    /// [`check_model`](Self::check_model)) before the first request is sent (`false` by default).
+    ///
+    /// The check runs once for the client and its clones, and fails with
+    /// [`OneiromancerError::ServerUnreachable`] or [`OneiromancerError::ModelNotFound`], which
+    /// suggest how to fix the setup, instead of the HTTP error of the request. It is skipped for
+    /// backends and for responses answered from the cache.
+    #[must_use]
+    pub const fn preflight(mut self, preflight: bool) -> Self {
+        self.preflight = preflight;
+        self
+    }
+
+    /// Gets the configured Ollama API base URL.
+    #[must_use]
+    pub fn configured_baseurl(&self) -> &str {
+        &self.baseurl
+    }
+
+    /// Gets the configured Ollama model.
+    #[must_use]
+    pub fn configured_model(&self) -> &str {
+        &self.model
+    }
+
+    /// Gets the configured model options.
+    #[cfg(feature = "cli")]
+    pub(crate) const fn configured_options(&self) -> &ModelOptions {
+        &self.options
+    }
+
+    /// Gets the configured prompt options.
+    #[cfg(feature = "cli")]
+    pub(crate) const fn configured_prompt_options(&self) -> PromptOptions {
+        self.prompt_options
+    }
+
+    /// Queries the Ollama API for the version of the server, e.g., to check that it is reachable.
+    ///
+    /// # Errors
+    ///
+    /// Returns the appropriate [`OneiromancerError`] in case the server cannot be queried.
+    pub fn server_version(&self) -> Result<String, OneiromancerError> {
+        ollama::version(&self.agent, &self.baseurl)
+    }
+
+    /// Queries the Ollama API for the names of the models available on the server, including their
+    /// tags (e.g., `aidapal:latest`).
+    ///
+    /// # Errors
+    ///
+    /// Returns the appropriate [`OneiromancerError`] in case the server cannot be queried.
+    pub fn list_models(&self) -> Result<Vec<String>, OneiromancerError> {
+        ollama::tags(&self.agent, &self.baseurl)
+    }
+
+    /// Returns `true` if the configured model is available on the server. A model name without a
+    /// tag matches the `latest` tag.
+    ///
+    /// # Errors
+    ///
+    /// Returns the appropriate [`OneiromancerError`] in case the server cannot be queried.
+    pub fn has_model(&self) -> Result<bool, OneiromancerError> {
+        Ok(self.is_listed(&self.list_models()?))
+    }
+
+    /// Checks that the Ollama server is reachable and that the configured model is available on
+    /// it, as [`preflight`](Self::preflight) does before the first request.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OneiromancerError::ServerUnreachable`] in case the server cannot be queried, or
+    /// [`OneiromancerError::ModelNotFound`] in case the model is not available.
+    pub fn check_model(&self) -> Result<(), OneiromancerError> {
+        self.check_listed(self.list_models())
+    }
+
+    /// Returns `true` if `name`, as listed by [`list_models`](Self::list_models), is the
+    /// configured model. A model name without a tag matches the `latest` tag.
+    #[must_use]
+    pub fn is_configured_model(&self, name: &str) -> bool {
+        if self.model.contains(':') {
+            name == self.model
+        } else {
+            name == self.model
+                || name
+                    .strip_prefix(self.model.as_str())
+                    .is_some_and(|tag| tag == ":latest")
+        }
+    }
+
+    /// Returns `true` if the configured model is among the `models` listed by the server.
+    fn is_listed(&self, models: &[String]) -> bool {
+        models.iter().any(|name| self.is_configured_model(name))
+    }
+
+    /// Downloads the configured model from the Ollama library (e.g., `aidapal`), calling
+    /// `on_progress` with each progress update reported by the server. Models that are already
+    /// available are only verified.
+    ///
+    /// # Errors
+    ///
+    /// Returns the appropriate [`OneiromancerError`] in case the server cannot be queried or the
+    /// download fails (e.g., because the model does not exist in the library).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use oneiromancer::Oneiromancer;
+    ///
+    /// Oneiromancer::new().model("aidapal").pull_model(|progress| {
+    ///     eprintln!("{}", progress.status());
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn pull_model(
+        &self,
+        mut on_progress: impl FnMut(&PullProgress),
+    ) -> Result<(), OneiromancerError> {
+        ollama::pull(&self.agent, &self.baseurl, &self.model, &mut on_progress)
+    }
+
+    /// Returns the outcome of the check of the `models` listed by the server.
+    fn check_listed(
+        &self,
+        models: Result<Vec<String>, OneiromancerError>,
+    ) -> Result<(), OneiromancerError> {
+        let listed = models.map_err(|err| {
+            if let OneiromancerError::OllamaQueryFailed(source) = err {
+                OneiromancerError::ServerUnreachable {
+                    baseurl: self.baseurl.clone(),
+                    source,
+                }
+            } else {
+                err
+            }
+        })?;
+        if self.is_listed(&listed) {
+            Ok(())
+        } else {
+            Err(OneiromancerError::ModelNotFound {
+                model: self.model.clone(),
+                baseurl: self.baseurl.clone(),
+            })
+        }
+    }
+
+    /// Runs the preflight check before the first request to the Ollama API, if enabled.
+    fn run_preflight(&self) -> Result<(), OneiromancerError> {
+        if !self.preflight || self.preflighted.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        log::debug!("Checking that model `{}` is available", self.model);
+        self.check_model()?;
+        self.preflighted.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    /// Runs the preflight check like [`run_preflight`](Self::run_preflight), without blocking.
+    #[cfg(feature = "async")]
+    async fn run_preflight_async(&self) -> Result<(), OneiromancerError> {
+        if !self.preflight || self.preflighted.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        log::debug!("Checking that model `{}` is available", self.model);
+        self.check_listed(ollama::tags_async(&self.http, &self.baseurl).await)?;
+        self.preflighted.store(true, Ordering::Release);
+        Ok(())
+    }
+
     /// Submits `pseudocode` to the local LLM via the Ollama API.
     ///
     /// Returns [`OneiromancerResults`] which contains the parsed LLM response.
@@ -94,8 +473,482 @@ impl Oneiromancer {
         &self,
         pseudocode: impl AsRef<str>,
     ) -> Result<OneiromancerResults, OneiromancerError> {
-        let request = OllamaRequest::new(&self.model, pseudocode.as_ref());
-        request.send(&self.baseurl)?.parse()
+        self.analyze_code_with_context(pseudocode, &AnalysisContext::default())
+    }
+
+    /// Submits `pseudocode` to the local LLM via the Ollama API, with the caller-supplied
+    /// `context` (e.g., disassembly, strings, and types harvested from IDA) appended to the prompt
+    /// in a delimited section.
+    ///
+    /// With an empty `context`, the request is identical to the one sent by
+    /// [`analyze_code`](`Oneiromancer::analyze_code`).
+    ///
+    /// If the `num_ctx` model option is set (e.g., with [`num_ctx`](`Oneiromancer::num_ctx`)) and
+    /// the estimated size of the prompt and of the response exceeds it, the pseudocode is split
+    /// into overlapping windows of whole lines that fit, each window is analyzed separately, and
+    /// the results are merged: the function name and the comment come from the first window, and
+    /// each variable gets the first name suggested for it. Otherwise, the model would silently
+    /// see a truncated prompt.
+    ///
+    /// # Errors
+    ///
+    /// Returns the appropriate [`OneiromancerError`] in case something goes wrong with the analysis.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use oneiromancer::{AnalysisContext, Oneiromancer};
+    ///
+    /// let pseudocode = r#"int main() { int v1 = 0; printf("Hello, world!"); }"#;
+    /// let context = AnalysisContext::new()
+    ///     .strings(&["Hello, world!".to_owned()])
+    ///     .notes("Entry point of the program.");
+    ///
+    /// let results = Oneiromancer::new().analyze_code_with_context(pseudocode, &context)?;
+    ///
+    /// dbg!(results.function_name());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn analyze_code_with_context(
+        &self,
+        pseudocode: impl AsRef<str>,
+        context: &AnalysisContext,
+    ) -> Result<OneiromancerResults, OneiromancerError> {
+        let code = pseudocode.as_ref();
+        let prompt = self.prompt(code, context);
+        if let Some(windows) = self.windows(code, &prompt) {
+            log::warn!(
+                "Prompt of about {} tokens exceeds the context window of {} tokens, analyzing \
+                 it in {} overlapping windows",
+                estimated_tokens(prompt.len()),
+                self.options.num_ctx().unwrap_or_default(),
+                windows.len()
+            );
+            let results = windows
+                .iter()
+                .map(|window| self.analyze_prompt(&self.prompt(window, context)))
+                .collect::<Result<Vec<_>, _>>()?;
+            return merge_windows(&results).map_or_else(|| self.analyze_prompt(&prompt), Ok);
+        }
+        self.analyze_prompt(&prompt)
+    }
+
+    /// Returns the prompts that [`analyze_code_with_context`](`Oneiromancer::analyze_code_with_context`)
+    /// would submit for `pseudocode` with `context`, without contacting the server: a single
+    /// prompt, or one prompt for each overlapping window if the pseudocode does not fit in the
+    /// context window of the model. Useful to debug prompt or content issues offline.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oneiromancer::{AnalysisContext, Oneiromancer};
+    ///
+    /// let pseudocode = r#"int main() { int v1 = 0; printf("Hello, world!"); }"#;
+    ///
+    /// let prompts = Oneiromancer::new().prompts(pseudocode, &AnalysisContext::new());
+    ///
+    /// assert_eq!(prompts.len(), 1);
+    /// assert!(prompts[0].contains("Hello, world!"));
+    /// ```
+    #[must_use]
+    pub fn prompts(&self, pseudocode: impl AsRef<str>, context: &AnalysisContext) -> Vec<String> {
+        let code = pseudocode.as_ref();
+        let prompt = self.prompt(code, context);
+        self.windows(code, &prompt).map_or_else(
+            || vec![prompt.clone()],
+            |windows| {
+                windows
+                    .iter()
+                    .map(|window| self.prompt(window, context))
+                    .collect()
+            },
+        )
+    }
+
+    /// Submits `prompt` to the local LLM, merging multiple samples by majority vote if enabled.
+    fn analyze_prompt(&self, prompt: &str) -> Result<OneiromancerResults, OneiromancerError> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!(
+            "analyze",
+            model = %self.model,
+            samples = self.samples.get(),
+            prompt_bytes = prompt.len()
+        )
+        .entered();
+        if self.samples.get() == 1 {
+            return self.generate(prompt, &self.options);
+        }
+
+        log::debug!(
+            "Sampling {} generations of model `{}`",
+            self.samples,
+            self.model
+        );
+        let seeds = iter::successors(Some(self.options.seed().unwrap_or_default()), |seed| {
+            seed.checked_add(1)
+        });
+        let samples = seeds
+            .take(self.samples.get())
+            .map(|seed| self.generate(prompt, &self.options.with_seed(seed)))
+            .collect::<Result<Vec<_>, _>>()?;
+        merge_samples(&samples).map_or_else(|| self.generate(prompt, &self.options), Ok)
+    }
+
+    /// Returns the overlapping windows of `pseudocode` to analyze separately if `prompt` does not
+    /// fit in the context window set with the `num_ctx` model option, together with the response,
+    /// or `None` if it fits (or no context window is set).
+    fn windows<'a>(&self, pseudocode: &'a str, prompt: &str) -> Option<Vec<&'a str>> {
+        let num_ctx = usize::try_from(self.options.num_ctx()?).ok()?;
+        let response = self
+            .options
+            .num_predict()
+            .and_then(|tokens| usize::try_from(tokens).ok())
+            .unwrap_or(RESPONSE_TOKENS);
+        let budget = num_ctx.saturating_sub(response);
+        if estimated_tokens(prompt.len()) <= budget {
+            return None;
+        }
+        // The context, hints, and instructions are repeated in the prompt of each window.
+        let overhead = prompt
+            .len()
+            .saturating_sub(self.prompt_options.prepare(pseudocode).len());
+        let windows = split_windows(
+            pseudocode,
+            budget.saturating_sub(estimated_tokens(overhead)),
+            WINDOW_OVERLAP,
+        );
+        (windows.len() > 1).then_some(windows)
+    }
+
+    /// Returns the prompt for `pseudocode` with `context`: the pseudocode prepared according to
+    /// the [`PromptOptions`], followed by the context, the decompiler hint, and the language
+    /// instruction, if any.
+    fn prompt(&self, pseudocode: &str, context: &AnalysisContext) -> String {
+        let prepared = self.prompt_options.prepare(pseudocode);
+        let contextual = append_decompiler_hint(
+            context.append_to(&prepared),
+            self.prompt_options.decompiler_of(pseudocode),
+        );
+        match self.language.as_deref() {
+            Some(language) => append_language(&contextual, language),
+            None => contextual.into_owned(),
+        }
+    }
+
+    /// Submits `prompt` to the local LLM with the model `options`, or answers it from the memo or
+    /// the cache.
+    fn generate(
+        &self,
+        prompt: &str,
+        options: &ModelOptions,
+    ) -> Result<OneiromancerResults, OneiromancerError> {
+        self.memo.as_ref().map_or_else(
+            || self.query(prompt, options),
+            |memo| memo.get_or_query(&self.model, options, prompt, || self.query(prompt, options)),
+        )
+    }
+
+    /// Submits `prompt` to the local LLM with the model `options`, or answers it from the on-disk
+    /// cache.
+    fn query(
+        &self,
+        prompt: &str,
+        options: &ModelOptions,
+    ) -> Result<OneiromancerResults, OneiromancerError> {
+        if let Some(results) = self
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.get(&self.model, options, prompt))
+        {
+            log::debug!("Using cached response of model `{}`", self.model);
+            #[cfg(feature = "tracing")]
+            tracing::debug!("cache hit");
+            return Ok(results);
+        }
+
+        let response = self.send(prompt, options)?;
+        let results = response.parse()?;
+        if let Some(cache) = self.cache.as_ref() {
+            cache.put(&self.model, options, prompt, response.response());
+        }
+        Ok(results)
+    }
+
+    /// Submits `prompt` with the model `options` to the backend, if set, or to the Ollama API,
+    /// retrying transient failures.
+    fn send(
+        &self,
+        prompt: &str,
+        options: &ModelOptions,
+    ) -> Result<OllamaResponse, OneiromancerError> {
+        if let Some(backend) = self.backend.as_deref() {
+            let request =
+                BackendRequest::new(&self.model, prompt, options, self.keep_alive.as_deref());
+            return self
+                .retries
+                .run(|| backend.send(&request))
+                .map(OllamaResponse::from_text);
+        }
+        self.run_preflight()?;
+        let request = OllamaRequest::new(&self.model, prompt)
+            .options(options)
+            .keep_alive(self.keep_alive.as_deref());
+        self.retries
+            .run(|| request.send(&self.agent, &self.baseurl))
+    }
+
+    /// Submits `pseudocode` to the local LLM via the Ollama API like
+    /// [`analyze_code`](`Oneiromancer::analyze_code`), streaming the response: `on_token` is called
+    /// with each piece of text as the model generates it, so that long analyses can show their
+    /// progress.
+    ///
+    /// The pieces are fragments of the JSON response, which is parsed once complete. With multiple
+    /// samples, each one is streamed in turn. Responses answered from the on-disk cache are
+    /// returned without calling `on_token`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the appropriate [`OneiromancerError`] in case something goes wrong with the analysis.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::io::{self, Write as _};
+    ///
+    /// use oneiromancer::Oneiromancer;
+    ///
+    /// let pseudocode = r#"int main() { int v1 = 0; printf("Hello, world!"); }"#;
+    ///
+    /// let results = Oneiromancer::new().analyze_code_streaming(pseudocode, |token| {
+    ///     _ = io::stderr().write_all(token.as_bytes());
+    /// })?;
+    ///
+    /// dbg!(results.function_name());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn analyze_code_streaming(
+        &self,
+        pseudocode: impl AsRef<str>,
+        mut on_token: impl FnMut(&str),
+    ) -> Result<OneiromancerResults, OneiromancerError> {
+        let prompt = self.prompt(pseudocode.as_ref(), &AnalysisContext::default());
+        self.generate_streaming(&prompt, &mut on_token)
+    }
+
+    /// Submits `prompt` to the local LLM with a streaming request, merging the configured number
+    /// of samples, like the blocking analysis.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "analyze",
+            skip_all,
+            fields(model = %self.model, samples = self.samples.get(), prompt_bytes = prompt.len())
+        )
+    )]
+    fn generate_streaming(
+        &self,
+        prompt: &str,
+        on_token: &mut dyn FnMut(&str),
+    ) -> Result<OneiromancerResults, OneiromancerError> {
+        if self.samples.get() == 1 {
+            return self.query_streaming(prompt, &self.options, on_token);
+        }
+
+        log::debug!(
+            "Sampling {} generations of model `{}`",
+            self.samples,
+            self.model
+        );
+        let seeds = iter::successors(Some(self.options.seed().unwrap_or_default()), |seed| {
+            seed.checked_add(1)
+        });
+        let samples = seeds
+            .take(self.samples.get())
+            .map(|seed| self.query_streaming(prompt, &self.options.with_seed(seed), on_token))
+            .collect::<Result<Vec<_>, _>>()?;
+        merge_samples(&samples)
+            .map_or_else(|| self.query_streaming(prompt, &self.options, on_token), Ok)
+    }
+
+    /// Submits `prompt` to the local LLM with the model `options` and a streaming request, or
+    /// answers it from the on-disk cache.
+    fn query_streaming(
+        &self,
+        prompt: &str,
+        options: &ModelOptions,
+        on_token: &mut dyn FnMut(&str),
+    ) -> Result<OneiromancerResults, OneiromancerError> {
+        if let Some(results) = self
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.get(&self.model, options, prompt))
+        {
+            log::debug!("Using cached response of model `{}`", self.model);
+            #[cfg(feature = "tracing")]
+            tracing::debug!("cache hit");
+            return Ok(results);
+        }
+
+        let response = if self.backend.is_some() {
+            let whole = self.send(prompt, options)?;
+            on_token(whole.response());
+            whole
+        } else {
+            self.run_preflight()?;
+            OllamaRequest::new(&self.model, prompt)
+                .options(options)
+                .keep_alive(self.keep_alive.as_deref())
+                .stream(true)
+                .send_streaming(&self.agent, &self.baseurl, on_token)?
+        };
+        let results = response.parse()?;
+        if let Some(cache) = self.cache.as_ref() {
+            cache.put(&self.model, options, prompt, response.response());
+        }
+        Ok(results)
+    }
+
+    /// Submits `pseudocode` to the local LLM via the Ollama API without blocking, like
+    /// [`analyze_code`](`Oneiromancer::analyze_code`), for use in async services (e.g., on a tokio
+    /// runtime).
+    ///
+    /// The prompt, the samples, and the on-disk cache work as in the blocking API, and errors are
+    /// reported the same way.
+    ///
+    /// # Errors
+    ///
+    /// Returns the appropriate [`OneiromancerError`] in case something goes wrong with the analysis.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # async fn analyze() -> anyhow::Result<()> {
+    /// use oneiromancer::Oneiromancer;
+    ///
+    /// let pseudocode = r#"int main() { int v1 = 0; printf("Hello, world!"); }"#;
+    ///
+    /// let results = Oneiromancer::new().analyze_code_async(pseudocode).await?;
+    ///
+    /// dbg!(results.function_name());
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "async")]
+    pub async fn analyze_code_async(
+        &self,
+        pseudocode: impl AsRef<str>,
+    ) -> Result<OneiromancerResults, OneiromancerError> {
+        let prompt = self.prompt(pseudocode.as_ref(), &AnalysisContext::default());
+        self.generate_async(&prompt).await
+    }
+
+    /// Submits `prompt` to the local LLM without blocking, merging the configured number of
+    /// samples, like the blocking analysis.
+    #[cfg(feature = "async")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "analyze",
+            skip_all,
+            fields(model = %self.model, samples = self.samples.get(), prompt_bytes = prompt.len())
+        )
+    )]
+    async fn generate_async(&self, prompt: &str) -> Result<OneiromancerResults, OneiromancerError> {
+        if self.samples.get() == 1 {
+            return self.query_async(prompt, &self.options).await;
+        }
+
+        log::debug!(
+            "Sampling {} generations of model `{}`",
+            self.samples,
+            self.model
+        );
+        let seeds = iter::successors(Some(self.options.seed().unwrap_or_default()), |seed| {
+            seed.checked_add(1)
+        });
+        let mut samples = Vec::with_capacity(self.samples.get());
+        for seed in seeds.take(self.samples.get()) {
+            samples.push(
+                self.query_async(prompt, &self.options.with_seed(seed))
+                    .await?,
+            );
+        }
+        match merge_samples(&samples) {
+            Some(merged) => Ok(merged),
+            None => self.query_async(prompt, &self.options).await,
+        }
+    }
+
+    /// Submits `prompt` to the local LLM with the model `options` without blocking, or answers it
+    /// from the on-disk cache.
+    #[cfg(feature = "async")]
+    async fn query_async(
+        &self,
+        prompt: &str,
+        options: &ModelOptions,
+    ) -> Result<OneiromancerResults, OneiromancerError> {
+        if let Some(results) = self
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.get(&self.model, options, prompt))
+        {
+            log::debug!("Using cached response of model `{}`", self.model);
+            #[cfg(feature = "tracing")]
+            tracing::debug!("cache hit");
+            return Ok(results);
+        }
+
+        let response = if self.backend.is_some() {
+            self.send(prompt, options)?
+        } else {
+            self.run_preflight_async().await?;
+            let request = OllamaRequest::new(&self.model, prompt)
+                .options(options)
+                .keep_alive(self.keep_alive.as_deref());
+            self.retries
+                .run_async(|| request.send_async(&self.http, &self.baseurl))
+                .await?
+        };
+        let results = response.parse()?;
+        if let Some(cache) = self.cache.as_ref() {
+            cache.put(&self.model, options, prompt, response.response());
+        }
+        Ok(results)
+    }
+
+    /// Submits pseudocode in the `filepath` file to the local LLM via the Ollama API without
+    /// blocking, like [`analyze_file`](`Oneiromancer::analyze_file`).
+    ///
+    /// # Errors
+    ///
+    /// Returns the appropriate [`OneiromancerError`] in case something goes wrong with file I/O or analysis.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # async fn analyze() -> anyhow::Result<()> {
+    /// use oneiromancer::Oneiromancer;
+    ///
+    /// let results = Oneiromancer::new()
+    ///     .analyze_file_async("./tests/data/hello.c")
+    ///     .await?;
+    ///
+    /// dbg!(results.function_name());
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "async")]
+    pub async fn analyze_file_async(
+        &self,
+        filepath: impl AsRef<Path>,
+    ) -> Result<OneiromancerResults, OneiromancerError> {
+        validate_input(&filepath, &self.input_policy)?;
+        let pseudocode = fs::read_to_string(filepath).await?;
+        self.analyze_code_async(&pseudocode).await
     }
 
     /// Submits pseudocode in the `filepath` file to the local LLM via the Ollama API.
@@ -148,10 +1001,197 @@ impl Oneiromancer {
         filepath: impl AsRef<Path>,
     ) -> Result<OneiromancerResults, OneiromancerError> {
         let file = File::open(&filepath)?;
+        validate_input(&filepath, &self.input_policy)?;
         let mut pseudocode = String::new();
         BufReader::new(file).read_to_string(&mut pseudocode)?;
         self.analyze_code(&pseudocode)
     }
+
+    /// Splits `pseudocode` into function definitions with [`split_functions`] and submits each
+    /// function separately to the local LLM for analysis, in order.
+    ///
+    /// A failure in analyzing one function does not stop the analysis of the others. Returns an
+    /// empty vector if no function definitions are found. If a callee budget is set in
+    /// [`PromptOptions`], the definitions of the same-file functions called by each function are
+    /// appended to its prompt as context.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use oneiromancer::Oneiromancer;
+    ///
+    /// let pseudocode = r#"int f(int a1) { return a1; }
+    /// int g(int a1) { return f(a1); }"#;
+    ///
+    /// let client = Oneiromancer::new();
+    /// for analysis in client.analyze_functions(pseudocode) {
+    ///     match analysis.results() {
+    ///         Ok(results) => println!("{}: {}", analysis.span().name(), results.function_name()),
+    ///         Err(err) => eprintln!("{}: {err}", analysis.span().name()),
+    ///     }
+    /// }
+    /// ```
+    #[must_use]
+    pub fn analyze_functions(&self, pseudocode: &str) -> Vec<FunctionAnalysis> {
+        self.analyze_functions_with_context(pseudocode, &AnalysisContext::default())
+    }
+
+    /// Same as [`analyze_functions`](`Oneiromancer::analyze_functions`), with the caller-supplied
+    /// `context` appended to the prompt of each function (see
+    /// [`analyze_code_with_context`](`Oneiromancer::analyze_code_with_context`)).
+    #[must_use]
+    pub fn analyze_functions_with_context(
+        &self,
+        pseudocode: &str,
+        context: &AnalysisContext,
+    ) -> Vec<FunctionAnalysis> {
+        let functions = split_functions(pseudocode);
+        functions
+            .iter()
+            .map(|span| {
+                let results = self.analyze_code_with_context(
+                    self.function_prompt(pseudocode, span, &functions),
+                    context,
+                );
+                FunctionAnalysis {
+                    span: span.clone(),
+                    results,
+                }
+            })
+            .collect()
+    }
+
+    /// Submits all function definitions found in `pseudocode` by [`split_functions`] to the local
+    /// LLM in a single prompt, with the caller-supplied `context` appended, asking for a JSON array
+    /// of per-function results keyed by the original function name.
+    ///
+    /// Much cheaper than [`analyze_functions`](`Oneiromancer::analyze_functions`) for files with
+    /// several small functions, as long as the whole file fits in the context window of the
+    /// model. Functions that the response does not cover and functions in the response that are
+    /// not in `pseudocode` are reported in the returned [`JointAnalysis`], rather than as errors.
+    /// Multiple samples are not supported: a single generation is requested.
+    ///
+    /// # Errors
+    ///
+    /// Returns the appropriate [`OneiromancerError`] in case something goes wrong with the request,
+    /// or if the response is not a JSON array.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use oneiromancer::Oneiromancer;
+    ///
+    /// let pseudocode = r#"int f(int a1) { return a1; }
+    /// int g(int a1) { return f(a1); }"#;
+    ///
+    /// let analysis = Oneiromancer::new().analyze_functions_jointly(pseudocode, &Default::default())?;
+    /// for (span, results) in analysis.functions() {
+    ///     match results {
+    ///         Some(results) => println!("{}: {}", span.name(), results.function_name()),
+    ///         None => eprintln!("{}: missing from the response", span.name()),
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn analyze_functions_jointly(
+        &self,
+        pseudocode: &str,
+        context: &AnalysisContext,
+    ) -> Result<JointAnalysis, OneiromancerError> {
+        let functions = split_functions(pseudocode);
+        let prompt = self.joint_prompt(pseudocode, context);
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!(
+            "analyze_jointly",
+            model = %self.model,
+            functions = functions.len(),
+            prompt_bytes = prompt.len()
+        )
+        .entered();
+
+        if let Some(response) = self
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.get_raw(&self.model, &self.options, &prompt))
+            && let Ok(entries) = parse_joint_response(&response)
+        {
+            log::debug!("Using cached response of model `{}`", self.model);
+            return Ok(JointAnalysis::new(
+                functions,
+                entries,
+                ResponseMetrics::default(),
+            ));
+        }
+
+        let response = self.send(&prompt, &self.options)?;
+        let entries = parse_joint_response(response.response())?;
+        if let Some(cache) = self.cache.as_ref() {
+            cache.put(&self.model, &self.options, &prompt, response.response());
+        }
+        Ok(JointAnalysis::new(functions, entries, response.metrics()))
+    }
+
+    /// Returns the prompts that
+    /// [`analyze_functions_with_context`](`Oneiromancer::analyze_functions_with_context`) would
+    /// submit for each function definition in `pseudocode`, in order, without contacting the
+    /// server (see [`prompts`](`Oneiromancer::prompts`)).
+    #[must_use]
+    pub fn function_prompts(
+        &self,
+        pseudocode: &str,
+        context: &AnalysisContext,
+    ) -> Vec<(FunctionSpan, Vec<String>)> {
+        let functions = split_functions(pseudocode);
+        functions
+            .iter()
+            .map(|span| {
+                let prompts =
+                    self.prompts(self.function_prompt(pseudocode, span, &functions), context);
+                (span.clone(), prompts)
+            })
+            .collect()
+    }
+
+    /// Returns the single prompt that
+    /// [`analyze_functions_jointly`](`Oneiromancer::analyze_functions_jointly`) would submit for
+    /// all function definitions in `pseudocode`, without contacting the server.
+    #[must_use]
+    pub fn joint_prompt(&self, pseudocode: &str, context: &AnalysisContext) -> String {
+        let functions = split_functions(pseudocode);
+        let names: Vec<&str> = functions.iter().map(FunctionSpan::name).collect();
+        let prepared = self.prompt_options.prepare(pseudocode);
+        let contextual = append_decompiler_hint(
+            context.append_to(&prepared),
+            self.prompt_options.decompiler_of(pseudocode),
+        );
+        let joint = append_joint_instruction(&contextual, &names);
+        match self.language.as_deref() {
+            Some(language) => append_language(&joint, language),
+            None => joint,
+        }
+    }
+
+    /// Returns the prompt for the `span` function in `pseudocode`, with the definitions of its
+    /// direct callees among `functions` appended, if enabled in [`PromptOptions`].
+    fn function_prompt<'a>(
+        &self,
+        pseudocode: &'a str,
+        span: &FunctionSpan,
+        functions: &[FunctionSpan],
+    ) -> Cow<'a, str> {
+        let target = span.text(pseudocode);
+        self.prompt_options
+            .configured_callee_budget()
+            .map_or(Cow::Borrowed(target), |budget| {
+                let callees: Vec<&str> = direct_callees(pseudocode, span, functions)
+                    .into_iter()
+                    .map(|callee| callee.text(pseudocode))
+                    .collect();
+                Cow::Owned(append_callees(target, &callees, budget))
+            })
+    }
 }
 
 /// Sets `baseurl` and `model` to the value of `OLLAMA_BASEURL` and `OLLAMA_MODEL`
@@ -161,6 +1201,21 @@ impl Default for Oneiromancer {
         Self {
             baseurl: env::var("OLLAMA_BASEURL").unwrap_or_else(|_| OLLAMA_BASEURL.to_owned()),
             model: env::var("OLLAMA_MODEL").unwrap_or_else(|_| OLLAMA_MODEL.to_owned()),
+            input_policy: InputPolicy::default(),
+            prompt_options: PromptOptions::default(),
+            cache: None,
+            memo: None,
+            options: ModelOptions::default(),
+            language: None,
+            keep_alive: None,
+            samples: NonZeroUsize::MIN,
+            agent: Agent::new_with_defaults(),
+            #[cfg(feature = "async")]
+            http: reqwest::Client::new(),
+            backend: None,
+            retries: RetryPolicy::default(),
+            preflight: false,
+            preflighted: Arc::default(),
         }
     }
 }
@@ -169,19 +1224,61 @@ impl Default for Oneiromancer {
 #[derive(Error, Debug)]
 #[non_exhaustive]
 pub enum OneiromancerError {
+    /// Invalid input file.
+    #[error(transparent)]
+    InvalidInput(#[from] InputError),
     /// Failure in reading the input file.
     #[error(transparent)]
     FileReadFailed(#[from] io::Error),
     /// Failure in querying the Ollama API.
     #[error(transparent)]
-    OllamaQueryFailed(#[from] ureq::Error),
+    OllamaQueryFailed(ureq::Error),
     /// Failure in parsing the Ollama response.
     #[error(transparent)]
     ResponseParseFailed(#[from] serde_json::Error),
+    /// Analysis results rejected by the [`QualityPolicy`](crate::QualityPolicy).
+    #[error("Low-quality analysis result: {0}")]
+    LowQualityResult(#[from] QualityIssue),
+    /// Request that exceeded one of the configured [`Timeouts`](crate::Timeouts).
+    #[error("Request timed out ({0} timeout)")]
+    Timeout(ureq::Timeout),
+    /// Ollama server that cannot be queried by the preflight check.
+    #[error(
+        "Ollama server at `{baseurl}` is unreachable (start it with `ollama serve`, or set its URL with `--base-url` or `OLLAMA_BASEURL`)"
+    )]
+    ServerUnreachable {
+        /// Base URL of the server.
+        baseurl: String,
+        /// Failure in querying the server.
+        #[source]
+        source: ureq::Error,
+    },
+    /// Model not available on the Ollama server, found by the preflight check.
+    #[error(
+        "Model `{model}` not found on the Ollama server at `{baseurl}` (run `ollama pull {model}`, or `ollama create {model}` from a modelfile)"
+    )]
+    ModelNotFound {
+        /// Name of the model.
+        model: String,
+        /// Base URL of the server.
+        baseurl: String,
+    },
+}
+
+/// Converts timeouts to [`OneiromancerError::Timeout`], and the other errors to
+/// [`OneiromancerError::OllamaQueryFailed`].
+impl From<ureq::Error> for OneiromancerError {
+    fn from(err: ureq::Error) -> Self {
+        if let ureq::Error::Timeout(timeout) = err {
+            Self::Timeout(timeout)
+        } else {
+            Self::OllamaQueryFailed(err)
+        }
+    }
 }
 
 /// Pseudocode analysis results.
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct OneiromancerResults {
     /// Recommended function name.
     function_name: String,
@@ -189,6 +1286,12 @@ pub struct OneiromancerResults {
     comment: String,
     /// Variable renaming suggestions.
     variables: Vec<Variable>,
+    /// Timing and token statistics of the response (empty for cached responses).
+    #[serde(skip)]
+    metrics: ResponseMetrics,
+    /// Agreement among the samples, if merged from multiple samples.
+    #[serde(skip)]
+    consensus: Option<Consensus>,
 }
 
 impl OneiromancerResults {
@@ -209,10 +1312,74 @@ impl OneiromancerResults {
     pub fn variables(&self) -> &[Variable] {
         &self.variables
     }
+
+    /// Gets the timing and token statistics reported by the Ollama API for the response, if any.
+    #[must_use]
+    pub const fn metrics(&self) -> &ResponseMetrics {
+        &self.metrics
+    }
+
+    /// Gets the agreement among the samples, if the results were merged from multiple samples
+    /// (see [`Oneiromancer::samples`]).
+    #[must_use]
+    pub const fn consensus(&self) -> Option<&Consensus> {
+        self.consensus.as_ref()
+    }
+
+    /// Creates new [`OneiromancerResults`] from a function name, comment, and variable renaming
+    /// suggestions.
+    pub(crate) fn new(function_name: &str, comment: &str, variables: Vec<Variable>) -> Self {
+        Self {
+            function_name: function_name.to_owned(),
+            comment: comment.to_owned(),
+            variables,
+            metrics: ResponseMetrics::default(),
+            consensus: None,
+        }
+    }
+
+    /// Attaches the agreement among the merged samples to the results.
+    pub(crate) fn attach_consensus(mut self, consensus: Consensus) -> Self {
+        self.consensus = Some(consensus);
+        self
+    }
+
+    /// Attaches the timing and token statistics of the response to the results.
+    pub(crate) const fn attach_metrics(mut self, metrics: ResponseMetrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+}
+
+/// Analysis results of a single function, as returned by
+/// [`analyze_functions`](`Oneiromancer::analyze_functions`).
+#[derive(Debug)]
+pub struct FunctionAnalysis {
+    /// Function definition in the analyzed pseudocode.
+    span: FunctionSpan,
+    /// Analysis results, or the error that occurred while analyzing the function.
+    results: Result<OneiromancerResults, OneiromancerError>,
+}
+
+impl FunctionAnalysis {
+    /// Gets the function definition in the analyzed pseudocode.
+    #[must_use]
+    pub const fn span(&self) -> &FunctionSpan {
+        &self.span
+    }
+
+    /// Gets the analysis results.
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`OneiromancerError`] that occurred while analyzing the function.
+    pub const fn results(&self) -> Result<&OneiromancerResults, &OneiromancerError> {
+        self.results.as_ref()
+    }
 }
 
 /// Variable renaming suggestion.
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Variable {
     /// Original name of the variable.
     original_name: String,
@@ -221,6 +1388,15 @@ pub struct Variable {
 }
 
 impl Variable {
+    /// Creates a renaming suggestion from `original_name` to `new_name`.
+    #[must_use]
+    pub fn new(original_name: impl Into<String>, new_name: impl Into<String>) -> Self {
+        Self {
+            original_name: original_name.into(),
+            new_name: new_name.into(),
+        }
+    }
+
     /// Gets the original name of the variable.
     #[must_use]
     pub fn original_name(&self) -> &str {
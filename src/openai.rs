@@ -0,0 +1,277 @@
+//! Submit prompts to servers that expose the OpenAI chat completions API (e.g., llama.cpp server,
+//! vLLM, LM Studio, and many gateways).
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ureq::Agent;
+
+use crate::{BackendRequest, LlmBackend, OneiromancerError, Timeouts};
+
+/// Default base URL of the OpenAI-compatible API (the default address of llama.cpp server).
+pub const OPENAI_BASEURL: &str = "http://127.0.0.1:8080/v1";
+
+/// Model options passed through to chat completion requests, with their name in the OpenAI API.
+/// The other options are specific to Ollama and are left out.
+const CHAT_OPTIONS: [(&str, &str); 7] = [
+    ("temperature", "temperature"),
+    ("top_p", "top_p"),
+    ("seed", "seed"),
+    ("stop", "stop"),
+    ("num_predict", "max_tokens"),
+    ("presence_penalty", "presence_penalty"),
+    ("frequency_penalty", "frequency_penalty"),
+];
+
+/// [`LlmBackend`] that submits prompts to the `/chat/completions` endpoint of an OpenAI-compatible
+/// API, as a single user message, and asks for a JSON object in response.
+///
+/// The model options that the OpenAI API shares with Ollama (`temperature`, `top_p`, `seed`,
+/// `stop`, `presence_penalty`, and `frequency_penalty`, plus `num_predict` as `max_tokens`) are
+/// passed through, while the others are ignored.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> anyhow::Result<()> {
+/// use oneiromancer::{OPENAI_BASEURL, OpenAiBackend, Oneiromancer};
+///
+/// let backend = OpenAiBackend::new(OPENAI_BASEURL).api_key("sk-...");
+/// let results = Oneiromancer::new()
+///     .model("qwen2.5-coder")
+///     .backend(backend)
+///     .analyze_code("int main() { return 0; }")?;
+///
+/// dbg!(results.function_name());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct OpenAiBackend {
+    /// API base URL, including the version (e.g., `http://127.0.0.1:8080/v1`).
+    baseurl: String,
+    /// API key sent as a bearer token, if any.
+    api_key: Option<String>,
+    /// HTTP agent, shared by all clones.
+    agent: Agent,
+}
+
+impl OpenAiBackend {
+    /// Creates a new [`OpenAiBackend`] for the API at `baseurl`, including the version (e.g.,
+    /// [`OPENAI_BASEURL`]).
+    #[must_use]
+    pub fn new(baseurl: impl Into<String>) -> Self {
+        Self {
+            baseurl: baseurl.into(),
+            api_key: None,
+            agent: Agent::new_with_defaults(),
+        }
+    }
+
+    /// Sets the API key sent in the `Authorization` header as a bearer token (none by default, as
+    /// local servers usually do not need one). An empty key is not sent.
+    #[must_use]
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into()).filter(|key| !key.is_empty());
+        self
+    }
+
+    /// Sets the [`Timeouts`] of the requests to the server (none by default).
+    #[must_use]
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.agent = timeouts.agent();
+        self
+    }
+}
+
+impl fmt::Debug for OpenAiBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OpenAiBackend")
+            .field("baseurl", &self.baseurl)
+            .field("api_key", &self.api_key.as_ref().map(|_| "<redacted>"))
+            .finish_non_exhaustive()
+    }
+}
+
+impl LlmBackend for OpenAiBackend {
+    fn send(&self, request: &BackendRequest<'_>) -> Result<String, OneiromancerError> {
+        let url = format!(
+            "{}{}",
+            self.baseurl.trim_end_matches('/'),
+            "/chat/completions"
+        );
+        log::debug!(
+            "Sending chat completion request to `{url}` with model `{}` ({} bytes of prompt)",
+            request.model(),
+            request.prompt().len()
+        );
+        let body = ChatRequest {
+            model: request.model(),
+            messages: [ChatMessage {
+                role: "user",
+                content: request.prompt(),
+            }],
+            response_format: ResponseFormat {
+                kind: "json_object",
+            },
+            stream: false,
+            options: chat_options(request.options()),
+        };
+        let started = Instant::now();
+        let mut post = self.agent.post(url);
+        if let Some(key) = self.api_key.as_deref() {
+            post = post.header("Authorization", format!("Bearer {key}"));
+        }
+        let response = post
+            .send_json(&body)?
+            .body_mut()
+            .read_json::<ChatResponse>()?;
+        log::debug!(
+            "Received chat completion in {} ms",
+            started.elapsed().as_millis()
+        );
+        response.content()
+    }
+}
+
+/// OpenAI chat completion request.
+#[derive(Serialize, Debug)]
+struct ChatRequest<'a> {
+    /// Name of the model to use.
+    model: &'a str,
+    /// Conversation to complete: the prompt as a single user message.
+    messages: [ChatMessage<'a>; 1],
+    /// Format of the response (a JSON object).
+    response_format: ResponseFormat,
+    /// Whether to stream the response.
+    stream: bool,
+    /// Model options, at the top level of the request.
+    #[serde(flatten)]
+    options: BTreeMap<&'static str, &'a Value>,
+}
+
+/// Message of a chat completion request.
+#[derive(Serialize, Debug)]
+struct ChatMessage<'a> {
+    /// Role of the author of the message.
+    role: &'static str,
+    /// Content of the message.
+    content: &'a str,
+}
+
+/// Response format of a chat completion request.
+#[derive(Serialize, Debug)]
+struct ResponseFormat {
+    /// Kind of format (`json_object`).
+    #[serde(rename = "type")]
+    kind: &'static str,
+}
+
+/// OpenAI chat completion response.
+#[derive(Deserialize, Debug)]
+struct ChatResponse {
+    /// Completions generated by the model (one, unless more were requested).
+    #[serde(default)]
+    choices: Vec<ChatChoice>,
+}
+
+/// Completion in a chat completion response.
+#[derive(Deserialize, Debug)]
+struct ChatChoice {
+    /// Message generated by the model.
+    message: ChatReply,
+}
+
+/// Message generated by the model.
+#[derive(Deserialize, Debug)]
+struct ChatReply {
+    /// Content of the message (`null` for refusals and tool calls).
+    content: Option<String>,
+}
+
+impl ChatResponse {
+    /// Returns the content of the first completion.
+    fn content(self) -> Result<String, OneiromancerError> {
+        self.choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .ok_or_else(|| ureq::Error::Other("no content in chat completion response".into()))
+            .map_err(OneiromancerError::from)
+    }
+}
+
+/// Returns the model `options` supported by the OpenAI API, with their names in the API.
+fn chat_options(options: &BTreeMap<String, Value>) -> BTreeMap<&'static str, &Value> {
+    CHAT_OPTIONS
+        .into_iter()
+        .filter_map(|(ollama, openai)| options.get(ollama).map(|value| (openai, value)))
+        .collect()
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn chat_request_maps_shared_options() -> anyhow::Result<()> {
+        let options: BTreeMap<String, Value> = serde_json::from_str(
+            r#"{"temperature":0,"num_predict":256,"num_ctx":8192,"stop":["}"]}"#,
+        )?;
+        let request = ChatRequest {
+            model: "m",
+            messages: [ChatMessage {
+                role: "user",
+                content: "p",
+            }],
+            response_format: ResponseFormat {
+                kind: "json_object",
+            },
+            stream: false,
+            options: chat_options(&options),
+        };
+
+        assert_eq!(
+            serde_json::to_value(&request)?,
+            serde_json::from_str::<Value>(
+                r#"{"model":"m","messages":[{"role":"user","content":"p"}],"response_format":{"type":"json_object"},"stream":false,"max_tokens":256,"stop":["}"],"temperature":0}"#
+            )?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn chat_response_yields_first_content() -> anyhow::Result<()> {
+        let response: ChatResponse = serde_json::from_str(
+            r#"{"choices":[{"index":0,"message":{"role":"assistant","content":"{}"}}],"usage":{"prompt_tokens":3}}"#,
+        )?;
+        assert_eq!(response.content()?, "{}");
+
+        let empty: ChatResponse = serde_json::from_value(json!({"choices": []}))?;
+        assert!(
+            matches!(
+                empty.content(),
+                Err(OneiromancerError::OllamaQueryFailed(_))
+            ),
+            "empty response accepted"
+        );
+        let refusal: ChatResponse =
+            serde_json::from_value(json!({"choices": [{"message": {"content": null}}]}))?;
+        assert!(refusal.content().is_err(), "refusal accepted");
+
+        Ok(())
+    }
+
+    #[test]
+    fn debug_redacts_api_key() {
+        let backend = OpenAiBackend::new(OPENAI_BASEURL).api_key("sk-secret");
+        assert!(!format!("{backend:?}").contains("sk-secret"), "key leaked");
+    }
+}
@@ -0,0 +1,206 @@
+//! Pass arbitrary options through to the model (e.g., `num_ctx`, `repeat_penalty`, or `stop`).
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+/// Options passed through to the model in the `options` object of Ollama API requests, sorted by
+/// key so that the serialized object is stable.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct ModelOptions(BTreeMap<String, Value>);
+
+impl ModelOptions {
+    /// Sets the option `key` to `value`, replacing (and reporting) any previous value.
+    pub fn insert(&mut self, key: &str, value: Value) {
+        if let Some(previous) = self.0.insert(key.to_owned(), value) {
+            log::warn!("Option `{key}` set more than once, replacing `{previous}`");
+        }
+    }
+
+    /// Returns the options, sorted by key.
+    pub const fn as_map(&self) -> &BTreeMap<String, Value> {
+        &self.0
+    }
+
+    /// Returns `true` if no options are set.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the `seed` option, if set to an unsigned integer.
+    pub fn seed(&self) -> Option<u64> {
+        self.0.get("seed").and_then(Value::as_u64)
+    }
+
+    /// Returns the `num_ctx` option (the size of the context window in tokens), if set to an
+    /// unsigned integer.
+    pub fn num_ctx(&self) -> Option<u64> {
+        self.0.get("num_ctx").and_then(Value::as_u64)
+    }
+
+    /// Returns the `num_predict` option (the maximum number of tokens to generate), if set to a
+    /// positive integer.
+    pub fn num_predict(&self) -> Option<u64> {
+        self.0
+            .get("num_predict")
+            .and_then(Value::as_u64)
+            .filter(|&tokens| tokens > 0)
+    }
+
+    /// Returns a copy of these options with the `seed` option set to `seed`.
+    pub fn with_seed(&self, seed: u64) -> Self {
+        let mut options = self.clone();
+        options.0.insert("seed".to_owned(), seed.into());
+        options
+    }
+}
+
+/// Failure in parsing a `key=value` model option.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum OptionError {
+    /// Missing `=` between key and value.
+    #[error("Invalid option `{0}`: expected `key=value`")]
+    MissingSeparator(String),
+    /// Empty key.
+    #[error("Invalid option `{0}`: empty key")]
+    EmptyKey(String),
+    /// Value that looks like JSON (e.g., an array) but cannot be parsed.
+    #[error("Invalid value of option `{0}`")]
+    InvalidJson(String, #[source] serde_json::Error),
+}
+
+/// Parses a model option in `key=value` form, such as `num_ctx=8192`, `mirostat=true`, or
+/// `stop=["}","\n\n"]`.
+///
+/// The value is parsed heuristically: booleans and numbers are passed as such, arrays, objects,
+/// and quoted strings are parsed as JSON, and anything else is passed as a string.
+///
+/// # Errors
+///
+/// Returns the appropriate [`OptionError`] in case the option is malformed.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use oneiromancer::parse_option;
+///
+/// let (key, value) = parse_option("repeat_penalty=1.1")?;
+///
+/// assert_eq!(key, "repeat_penalty");
+/// assert_eq!(value, serde_json::json!(1.1));
+/// # Ok(())
+/// # }
+/// ```
+pub fn parse_option(option: &str) -> Result<(String, Value), OptionError> {
+    let Some((raw_key, raw_value)) = option.split_once('=') else {
+        return Err(OptionError::MissingSeparator(option.to_owned()));
+    };
+    let key = raw_key.trim();
+    if key.is_empty() {
+        return Err(OptionError::EmptyKey(option.to_owned()));
+    }
+
+    let text = raw_value.trim();
+    let value = if text.starts_with(['[', '{', '"']) {
+        serde_json::from_str(text).map_err(|err| OptionError::InvalidJson(key.to_owned(), err))?
+    } else {
+        match serde_json::from_str(text) {
+            Ok(value @ (Value::Bool(_) | Value::Number(_))) => value,
+            _ => Value::String(text.to_owned()),
+        }
+    };
+    Ok((key.to_owned(), value))
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parse_option_infers_value_types() -> anyhow::Result<()> {
+        let cases = [
+            ("num_gpu=1", "1"),
+            ("repeat_penalty=1.1", "1.1"),
+            ("mirostat_eta=-0.5", "-0.5"),
+            ("use_mmap=false", "false"),
+            (r#"stop=["}","\n\n"]"#, r#"["}","\n\n"]"#),
+            (r#"tag="42""#, r#""42""#),
+            ("tag=debug", r#""debug""#),
+            ("tag=", r#""""#),
+            ("expr=a=b", r#""a=b""#),
+        ];
+        for (option, expected) in cases {
+            let (_, value) = parse_option(option)?;
+            assert_eq!(value, serde_json::from_str::<Value>(expected)?, "{option}");
+        }
+        assert_eq!(parse_option(" num_ctx = 8192 ")?.0, "num_ctx");
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_option_rejects_malformed_options() {
+        assert!(
+            matches!(
+                parse_option("num_ctx"),
+                Err(OptionError::MissingSeparator(_))
+            ),
+            "missing separator accepted"
+        );
+        assert!(
+            matches!(parse_option("=1"), Err(OptionError::EmptyKey(_))),
+            "empty key accepted"
+        );
+        assert!(
+            matches!(
+                parse_option("stop=[\"a\","),
+                Err(OptionError::InvalidJson(..))
+            ),
+            "malformed array accepted"
+        );
+    }
+
+    #[test]
+    fn model_options_keep_the_last_value() -> anyhow::Result<()> {
+        let mut options = ModelOptions::default();
+        options.insert("seed", json!("first"));
+        options.insert("num_ctx", json!(true));
+        options.insert("seed", json!("last"));
+
+        assert_eq!(
+            serde_json::to_string(&options)?,
+            r#"{"num_ctx":true,"seed":"last"}"#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn model_options_read_context_window_sizes() -> anyhow::Result<()> {
+        let mut options = ModelOptions::default();
+        assert_eq!((options.num_ctx(), options.num_predict()), (None, None));
+
+        for option in ["num_ctx=4096", "num_predict=-1"] {
+            let (key, value) = parse_option(option)?;
+            options.insert(&key, value);
+        }
+        assert_eq!(
+            (options.num_ctx(), options.num_predict()),
+            (Some(4096), None)
+        );
+
+        let (key, value) = parse_option("num_predict=256")?;
+        options.insert(&key, value);
+        assert_eq!(options.num_predict(), Some(256));
+
+        Ok(())
+    }
+}
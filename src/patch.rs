@@ -0,0 +1,96 @@
+//! Format unified diffs of the improved pseudocode, to be applied with `git apply` or `patch`.
+
+use std::fmt::Write as _;
+
+use similar::TextDiff;
+
+/// Number of unchanged lines of context around each hunk.
+const CONTEXT_LINES: usize = 3;
+
+/// Formats a unified diff that turns `original` into `improved` in the file at `relpath` (relative
+/// to the patch root, with `/` separators), with `a/` and `b/` prefixes so that it applies with
+/// `git apply` or `patch -p1`. Returns an empty string if the contents are the same.
+///
+/// Missing newlines at the end of either file are marked with `\ No newline at end of file`, and
+/// file names are quoted as Git does (see [`patch_path`]), so that patches of several files can be
+/// concatenated into a single multi-file patch.
+#[must_use]
+pub fn format_file_patch(relpath: &str, original: &str, improved: &str) -> String {
+    if original == improved {
+        return String::new();
+    }
+    TextDiff::from_lines(original, improved)
+        .unified_diff()
+        .context_radius(CONTEXT_LINES)
+        .missing_newline_hint(true)
+        .header(&patch_path("a/", relpath), &patch_path("b/", relpath))
+        .to_string()
+}
+
+/// Returns the file name for a patch header with `prefix` and `relpath`. As in Git, names with
+/// quotes, backslashes, control characters, or non-ASCII bytes are C-quoted with octal escapes, and
+/// unquoted names with spaces end with a tab, so that their end is unambiguous.
+fn patch_path(prefix: &str, relpath: &str) -> String {
+    let name = format!("{prefix}{relpath}");
+    let needs_quoting = name
+        .bytes()
+        .any(|byte| matches!(byte, b'"' | b'\\') || !(b' '..=b'~').contains(&byte));
+    if !needs_quoting {
+        let tab = if name.contains(' ') { "\t" } else { "" };
+        return format!("{name}{tab}");
+    }
+    let mut quoted = String::with_capacity(name.len().saturating_add(2));
+    quoted.push('"');
+    for byte in name.bytes() {
+        match byte {
+            b'"' => quoted.push_str("\\\""),
+            b'\\' => quoted.push_str("\\\\"),
+            b'\t' => quoted.push_str("\\t"),
+            b'\n' => quoted.push_str("\\n"),
+            b' '..=b'~' => quoted.push(char::from(byte)),
+            _ => _ = write!(quoted, "\\{byte:03o}"),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_file_patch_writes_headers_and_hunks() {
+        let original = "int f(int a1)\n{\n  int v1;\n\n  v1 = a1;\n  return v1;\n}\n";
+        let improved = "int f(int a1)\n{\n  int len;\n\n  len = a1;\n  return len;\n}\n";
+
+        assert_eq!(
+            format_file_patch("src/f.c", original, improved),
+            "--- a/src/f.c\n+++ b/src/f.c\n@@ -1,7 +1,7 @@\n int f(int a1)\n {\n\
+             -  int v1;\n+  int len;\n \n-  v1 = a1;\n-  return v1;\n+  len = a1;\n+  return len;\n }\n"
+        );
+        assert_eq!(format_file_patch("src/f.c", original, original), "");
+    }
+
+    #[test]
+    fn format_file_patch_marks_missing_newlines() {
+        assert_eq!(
+            format_file_patch("f.c", "a\nb", "a\nc"),
+            "--- a/f.c\n+++ b/f.c\n@@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n+c\n\\ No newline at end of file\n"
+        );
+        assert_eq!(
+            format_file_patch("f.c", "a\nb", "a\nb\n"),
+            "--- a/f.c\n+++ b/f.c\n@@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n+b\n"
+        );
+    }
+
+    #[test]
+    fn patch_path_quotes_names_as_git_does() {
+        assert_eq!(patch_path("a/", "dir/f.c"), "a/dir/f.c");
+        assert_eq!(patch_path("b/", "my dir/f.c"), "b/my dir/f.c\t");
+        assert_eq!(
+            patch_path("a/", "caf\u{e9} \"x\"\\.c"),
+            r#""a/caf\303\251 \"x\"\\.c""#
+        );
+    }
+}
@@ -0,0 +1,326 @@
+//! Prepare pseudocode before it is sent to the LLM.
+
+use std::borrow::Cow;
+
+use crate::Decompiler;
+use crate::lexer::{TokenKind, tokenize};
+
+/// Average number of bytes per token, used to estimate token counts.
+const BYTES_PER_TOKEN: usize = 4;
+/// Delimiter between the target function and the definitions of its callees in the prompt.
+const CALLEES_DELIMITER: &str =
+    "// ----- Definitions of called functions (context only, do not analyze) -----";
+/// Marker appended to callee definitions truncated to fit the token budget.
+const TRUNCATED_MARKER: &str = "// ... (truncated)";
+/// Delimiter between the pseudocode and the output language instruction in the prompt.
+const LANGUAGE_DELIMITER: &str = "// ----- Output language (instruction, do not analyze) -----";
+/// Delimiter between the pseudocode and the output format instruction of joint analyses.
+const JOINT_DELIMITER: &str = "// ----- Output format (instruction, do not analyze) -----";
+/// Delimiter between the pseudocode and the hint on the conventions of its decompiler.
+const DECOMPILER_DELIMITER: &str = "// ----- Decompiler (context, do not analyze) -----";
+
+/// Options that control how pseudocode is prepared before it is sent to the LLM.
+///
+/// Preparation only affects the prompt: renames and descriptions are still applied to the
+/// original pseudocode, so the output file keeps everything.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PromptOptions {
+    /// Whether to strip preprocessor directives, comment banners, and blank lines.
+    strip_preprocessor: bool,
+    /// Token budget for the definitions of callees appended to the prompt, if enabled.
+    callee_budget: Option<usize>,
+    /// Decompiler that produced the pseudocode, or `None` to detect it.
+    decompiler: Option<Decompiler>,
+}
+
+impl PromptOptions {
+    /// Creates a new [`PromptOptions`] that sends pseudocode verbatim.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether to strip preprocessor directives (e.g., `#include` lines), comment banners
+    /// outside function bodies (e.g., IDA's `// attributes:` lines), and blank lines from the
+    /// prompt, to save tokens.
+    #[must_use]
+    pub const fn strip_preprocessor(mut self, strip_preprocessor: bool) -> Self {
+        self.strip_preprocessor = strip_preprocessor;
+        self
+    }
+
+    /// Sets the token budget for the definitions of same-file callees that are appended to the
+    /// prompt of each function analyzed by
+    /// [`analyze_functions`](`crate::Oneiromancer::analyze_functions`), or `None` to send each
+    /// function alone. Definitions that do not fit the budget are truncated.
+    #[must_use]
+    pub const fn callee_budget(mut self, callee_budget: Option<usize>) -> Self {
+        self.callee_budget = callee_budget;
+        self
+    }
+
+    /// Sets the decompiler that produced the pseudocode, or `None` to detect it from telltale
+    /// identifiers. Its preset selects the hint appended to the prompt, the boilerplate stripped
+    /// along with the other noise, and the names that are safe to rename.
+    #[must_use]
+    pub const fn decompiler(mut self, decompiler: Option<Decompiler>) -> Self {
+        self.decompiler = decompiler;
+        self
+    }
+
+    /// Returns the decompiler that produced `pseudocode`, as configured or detected, if any.
+    #[must_use]
+    pub fn decompiler_of(&self, pseudocode: &str) -> Option<Decompiler> {
+        self.decompiler.or_else(|| Decompiler::detect(pseudocode))
+    }
+
+    /// Gets the token budget for the definitions of callees, if enabled.
+    pub(crate) const fn configured_callee_budget(&self) -> Option<usize> {
+        self.callee_budget
+    }
+
+    /// Returns `pseudocode` prepared according to these options.
+    #[must_use]
+    pub fn prepare<'a>(&self, pseudocode: &'a str) -> Cow<'a, str> {
+        if self.strip_preprocessor {
+            Cow::Owned(strip_noise(pseudocode, self.decompiler_of(pseudocode)))
+        } else {
+            Cow::Borrowed(pseudocode)
+        }
+    }
+}
+
+/// Removes preprocessor directives, comments outside function bodies, blank lines, and the
+/// boilerplate of `decompiler` from `pseudocode`. Comments inside function bodies are kept, as
+/// they may help the analysis.
+fn strip_noise(pseudocode: &str, decompiler: Option<Decompiler>) -> String {
+    let mut stripped = String::with_capacity(pseudocode.len());
+    let mut depth: usize = 0;
+    for token in tokenize(pseudocode) {
+        let text = token.text(pseudocode);
+        match token.kind {
+            TokenKind::Preprocessor => continue,
+            TokenKind::LineComment | TokenKind::BlockComment if depth == 0 => continue,
+            TokenKind::Punct if text == "{" => depth = depth.saturating_add(1),
+            TokenKind::Punct if text == "}" => depth = depth.saturating_sub(1),
+            TokenKind::Identifier
+            | TokenKind::Number
+            | TokenKind::String
+            | TokenKind::Char
+            | TokenKind::LineComment
+            | TokenKind::BlockComment
+            | TokenKind::Punct
+            | TokenKind::Whitespace => (),
+        }
+        stripped.push_str(text);
+    }
+
+    stripped
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty() && !decompiler.is_some_and(|known| known.is_noise(line)))
+        .fold(String::with_capacity(stripped.len()), |mut prompt, line| {
+            prompt.push_str(line);
+            prompt.push('\n');
+            prompt
+        })
+}
+
+/// Appends the `callees` definitions to the `target` function, after a delimiter, truncating them
+/// to fit the `budget` in tokens.
+pub fn append_callees(target: &str, callees: &[&str], budget: usize) -> String {
+    let mut prompt = target.to_owned();
+    let mut remaining = budget.saturating_mul(BYTES_PER_TOKEN);
+    if callees.is_empty() || remaining == 0 {
+        return prompt;
+    }
+
+    prompt.push_str("\n\n");
+    prompt.push_str(CALLEES_DELIMITER);
+    prompt.push('\n');
+    for callee in callees {
+        // Truncate on a line boundary, so that the model does not see partial tokens.
+        let end = if callee.len() <= remaining {
+            callee.len()
+        } else {
+            callee
+                .get(..remaining)
+                .and_then(|prefix| prefix.rfind('\n'))
+                .unwrap_or_default()
+        };
+        let Some(body) = callee.get(..end).filter(|body| !body.is_empty()) else {
+            break;
+        };
+        prompt.push('\n');
+        prompt.push_str(body);
+        prompt.push('\n');
+        if end < callee.len() {
+            prompt.push_str(TRUNCATED_MARKER);
+            prompt.push('\n');
+            break;
+        }
+        remaining = remaining.saturating_sub(end);
+    }
+    prompt
+}
+
+/// Appends to `prompt`, after a delimiter, the hint on the conventions of `decompiler`, if any.
+pub fn append_decompiler_hint(
+    prompt: Cow<'_, str>,
+    decompiler: Option<Decompiler>,
+) -> Cow<'_, str> {
+    match decompiler.and_then(Decompiler::prompt_hint) {
+        Some(hint) => Cow::Owned(format!(
+            "{}\n\n{DECOMPILER_DELIMITER}\n{hint}\n",
+            prompt.trim_end()
+        )),
+        None => prompt,
+    }
+}
+
+/// Appends to `prompt`, after a delimiter, an instruction to write the description in `language`,
+/// while keeping the function and variable names in English and ASCII.
+pub fn append_language(prompt: &str, language: &str) -> String {
+    format!(
+        "{}\n\n{LANGUAGE_DELIMITER}\nWrite the `comment` field in {language}. Keep `function_name`, \
+         `original_name`, and `new_name` in English, using only ASCII letters, digits, and \
+         underscores.\n",
+        prompt.trim_end()
+    )
+}
+
+/// Appends to `prompt`, after a delimiter, an instruction to analyze each of the functions named
+/// `names` separately and answer with a JSON array of per-function results.
+pub fn append_joint_instruction(prompt: &str, names: &[&str]) -> String {
+    let list = names
+        .iter()
+        .map(|name| format!("`{name}`"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "{}\n\n{JOINT_DELIMITER}\nAnalyze each of the {} functions above ({list}) separately. \
+         Answer with a JSON array holding one object per function, with the `original_name` of the \
+         function as it appears above, and its `function_name`, `comment`, and `variables`.\n",
+        prompt.trim_end(),
+        names.len()
+    )
+}
+
+/// Returns the estimated number of tokens in a text of `bytes` bytes.
+pub const fn estimated_tokens(bytes: usize) -> usize {
+    bytes.div_ceil(BYTES_PER_TOKEN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prepare_strips_preprocessor_banners_and_blank_lines() {
+        let pseudocode = "#include <stdio.h>\n#define X \\\n  1\n\n\n//----- (0000000140001000) ----\n// attributes: thunk\nint main(int argc)\n{\n  // keep me\n  puts(\"#not a directive\");\n\n  return 0;\n}\n";
+
+        let prompt = PromptOptions::new()
+            .strip_preprocessor(true)
+            .prepare(pseudocode);
+
+        assert_eq!(
+            prompt,
+            "int main(int argc)\n{\n  // keep me\n  puts(\"#not a directive\");\n  return 0;\n}\n"
+        );
+    }
+
+    #[test]
+    fn prepare_strips_decompiler_boilerplate() {
+        let pseudocode = "typedef unsigned char   undefined;\ntypedef unsigned long long    undefined8;\ntypedef struct packet packet_t;\n\nundefined8 FUN_00401000(long param_1)\n{\n  return *(undefined8 *)(param_1 + 8);\n}\n";
+        let options = PromptOptions::new().strip_preprocessor(true);
+
+        assert_eq!(
+            options.prepare(pseudocode),
+            "typedef struct packet packet_t;\nundefined8 FUN_00401000(long param_1)\n{\n  return *(undefined8 *)(param_1 + 8);\n}\n"
+        );
+        assert!(
+            options
+                .decompiler(Some(Decompiler::HexRays))
+                .prepare(pseudocode)
+                .starts_with("typedef unsigned char   undefined;\n"),
+            "Ghidra boilerplate stripped for Hex-Rays"
+        );
+    }
+
+    #[test]
+    fn append_decompiler_hint_skips_decompilers_without_hints() {
+        let prompt =
+            append_decompiler_hint(Cow::Borrowed("int f() {}\n"), Some(Decompiler::Ghidra));
+
+        assert!(
+            prompt.starts_with(&format!("int f() {{}}\n\n{DECOMPILER_DELIMITER}\nThe pseudocode above was produced by the Ghidra decompiler")),
+            "{prompt}"
+        );
+        assert_eq!(
+            append_decompiler_hint(Cow::Borrowed("int f() {}\n"), Some(Decompiler::HexRays)),
+            "int f() {}\n"
+        );
+        assert_eq!(
+            append_decompiler_hint(Cow::Borrowed("int f() {}\n"), None),
+            "int f() {}\n"
+        );
+    }
+
+    #[test]
+    fn prepare_keeps_pseudocode_by_default() {
+        let pseudocode = "#include <stdio.h>\n\nint main() {}\n";
+
+        assert_eq!(PromptOptions::new().prepare(pseudocode), pseudocode);
+    }
+
+    #[test]
+    fn append_callees_truncates_to_budget() {
+        let prompt = append_callees(
+            "int main() { return f() + g(); }",
+            &["int f()\n{\n  return 1;\n}", "int g()\n{\n  return 2;\n}"],
+            9,
+        );
+
+        assert_eq!(
+            prompt,
+            format!(
+                "int main() {{ return f() + g(); }}\n\n{CALLEES_DELIMITER}\n\nint f()\n{{\n  return 1;\n}}\n\nint g()\n{{\n{TRUNCATED_MARKER}\n"
+            )
+        );
+    }
+
+    #[test]
+    fn append_callees_without_callees_keeps_target() {
+        assert_eq!(append_callees("int main() {}", &[], 100), "int main() {}");
+    }
+
+    #[test]
+    fn append_language_keeps_identifiers_in_english() {
+        let prompt = append_language("int main() {}\n", "Italian");
+
+        assert_eq!(
+            prompt,
+            format!(
+                "int main() {{}}\n\n{LANGUAGE_DELIMITER}\nWrite the `comment` field in Italian. Keep `function_name`, `original_name`, and `new_name` in English, using only ASCII letters, digits, and underscores.\n"
+            )
+        );
+    }
+
+    #[test]
+    fn append_joint_instruction_lists_functions() {
+        let prompt = append_joint_instruction("int f() {}\nint g() {}\n", &["f", "g"]);
+
+        assert_eq!(
+            prompt,
+            format!(
+                "int f() {{}}\nint g() {{}}\n\n{JOINT_DELIMITER}\nAnalyze each of the 2 functions above (`f`, `g`) separately. Answer with a JSON array holding one object per function, with the `original_name` of the function as it appears above, and its `function_name`, `comment`, and `variables`.\n"
+            )
+        );
+    }
+
+    #[test]
+    fn estimated_tokens_rounds_up() {
+        assert_eq!(estimated_tokens(0), 0);
+        assert_eq!(estimated_tokens(9), 3);
+    }
+}
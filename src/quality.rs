@@ -0,0 +1,246 @@
+//! Check analysis results against a minimum quality bar.
+
+use thiserror::Error;
+
+use crate::OneiromancerResults;
+
+/// Default minimum length of the function description, in characters.
+pub const MIN_COMMENT_LENGTH: usize = 10;
+/// Default minimum number of variable renames.
+pub const MIN_RENAMES: usize = 1;
+
+/// Prefixes of the default function names assigned by disassemblers and decompilers, followed by
+/// the address in hex (e.g., `sub_401000` in IDA, `FUN_00401000` in Ghidra, `fcn.00401000` in
+/// radare2/rizin).
+const PLACEHOLDER_PREFIXES: &[&str] = &["sub_", "nullsub_", "loc_", "FUN_", "fcn.", "func_"];
+
+/// Policy that analysis results must comply with to be accepted, e.g., to reject the nearly empty
+/// results that the model sometimes returns.
+///
+/// By default, the description must be at least [`MIN_COMMENT_LENGTH`] characters long, at least
+/// [`MIN_RENAMES`] variables must be renamed, and the recommended function name must not be a
+/// placeholder (e.g., `sub_401000`, typically the original name of the function).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QualityPolicy {
+    /// Minimum length of the function description, in characters.
+    min_comment_length: usize,
+    /// Minimum number of variable renames.
+    min_renames: usize,
+    /// Whether to reject placeholder function names.
+    reject_placeholder_names: bool,
+}
+
+impl QualityPolicy {
+    /// Creates a new [`QualityPolicy`] with default thresholds.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the minimum length of the function description, in characters (leading and trailing
+    /// whitespace excluded).
+    #[must_use]
+    pub const fn min_comment_length(mut self, length: usize) -> Self {
+        self.min_comment_length = length;
+        self
+    }
+
+    /// Sets the minimum number of variable renames (suggestions that keep the original name do
+    /// not count).
+    #[must_use]
+    pub const fn min_renames(mut self, count: usize) -> Self {
+        self.min_renames = count;
+        self
+    }
+
+    /// Sets whether to reject recommended function names that are placeholders assigned by
+    /// disassemblers (e.g., `sub_401000`, `FUN_00401000`, or `fcn.00401000`), or empty.
+    #[must_use]
+    pub const fn reject_placeholder_names(mut self, reject: bool) -> Self {
+        self.reject_placeholder_names = reject;
+        self
+    }
+
+    /// Checks analysis `results` against the policy.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first [`QualityIssue`] found in `results`, checking the function name first,
+    /// then the description, and then the variable renames.
+    pub fn check(&self, results: &OneiromancerResults) -> Result<(), QualityIssue> {
+        let name = results.function_name().trim();
+        if self.reject_placeholder_names && is_placeholder_name(name) {
+            return Err(QualityIssue::PlaceholderName(name.to_owned()));
+        }
+
+        let length = results.comment().trim().chars().count();
+        if length < self.min_comment_length {
+            return Err(QualityIssue::ShortComment {
+                length,
+                minimum: self.min_comment_length,
+            });
+        }
+
+        let count = results
+            .variables()
+            .iter()
+            .filter(|variable| variable.new_name() != variable.original_name())
+            .count();
+        if count < self.min_renames {
+            return Err(QualityIssue::TooFewRenames {
+                count,
+                minimum: self.min_renames,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Default for QualityPolicy {
+    fn default() -> Self {
+        Self {
+            min_comment_length: MIN_COMMENT_LENGTH,
+            min_renames: MIN_RENAMES,
+            reject_placeholder_names: true,
+        }
+    }
+}
+
+/// Reason why analysis results do not comply with a [`QualityPolicy`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum QualityIssue {
+    /// The recommended function name is a placeholder.
+    #[error("function name `{0}` is a placeholder")]
+    PlaceholderName(String),
+    /// The function description is too short.
+    #[error("description is too short ({length} characters, at least {minimum} required)")]
+    ShortComment {
+        /// Length of the description, in characters.
+        length: usize,
+        /// Minimum length required by the policy.
+        minimum: usize,
+    },
+    /// Too few variables are renamed.
+    #[error("too few variable renames ({count}, at least {minimum} required)")]
+    TooFewRenames {
+        /// Number of variable renames.
+        count: usize,
+        /// Minimum number required by the policy.
+        minimum: usize,
+    },
+}
+
+/// Returns `true` if `name` is a default function name assigned by a disassembler (e.g.,
+/// `sub_401000`), or empty.
+fn is_placeholder_name(name: &str) -> bool {
+    name.is_empty()
+        || PLACEHOLDER_PREFIXES.iter().any(|prefix| {
+            name.strip_prefix(prefix).is_some_and(|digits| {
+                !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_hexdigit())
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Variable;
+
+    /// Returns results with the function `name`, the `comment`, and the `renames`.
+    fn results(name: &str, comment: &str, renames: &[(&str, &str)]) -> OneiromancerResults {
+        OneiromancerResults::new(
+            name,
+            comment,
+            renames
+                .iter()
+                .map(|&(old, new)| Variable::new(old, new))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn check_accepts_complete_results() {
+        let complete = results(
+            "parse_packet",
+            "Parses a network packet.",
+            &[("a1", "packet")],
+        );
+
+        assert_eq!(QualityPolicy::new().check(&complete), Ok(()));
+    }
+
+    #[test]
+    fn check_rejects_placeholder_names() {
+        let policy = QualityPolicy::new();
+        let renames = [("a1", "packet")];
+
+        for name in ["sub_401000", "FUN_00401000", "fcn.00401000", " "] {
+            assert_eq!(
+                policy.check(&results(name, "Parses a network packet.", &renames)),
+                Err(QualityIssue::PlaceholderName(name.trim().to_owned())),
+                "{name}"
+            );
+        }
+        assert_eq!(
+            policy.reject_placeholder_names(false).check(&results(
+                "sub_401000",
+                "Parses a network packet.",
+                &renames
+            )),
+            Ok(())
+        );
+        // Names that merely start like placeholders are fine.
+        assert_eq!(
+            policy.check(&results(
+                "sub_handler",
+                "Parses a network packet.",
+                &renames
+            )),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn check_rejects_short_comments() {
+        let terse = results("parse_packet", "  Parses.\n", &[("a1", "packet")]);
+
+        assert_eq!(
+            QualityPolicy::new().check(&terse),
+            Err(QualityIssue::ShortComment {
+                length: 7,
+                minimum: MIN_COMMENT_LENGTH,
+            })
+        );
+        assert_eq!(
+            QualityPolicy::new().min_comment_length(7).check(&terse),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn check_rejects_too_few_renames() {
+        let policy = QualityPolicy::new().min_renames(2);
+        let unchanged = results(
+            "parse_packet",
+            "Parses a network packet.",
+            &[("a1", "packet"), ("v2", "v2")],
+        );
+
+        assert_eq!(
+            policy.check(&unchanged),
+            Err(QualityIssue::TooFewRenames {
+                count: 1,
+                minimum: 2,
+            })
+        );
+        assert_eq!(
+            QualityPolicy::new().check(&results("parse_packet", "Parses a network packet.", &[])),
+            Err(QualityIssue::TooFewRenames {
+                count: 0,
+                minimum: MIN_RENAMES,
+            })
+        );
+        assert_eq!(policy.min_renames(0).check(&unchanged), Ok(()));
+    }
+}
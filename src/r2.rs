@@ -0,0 +1,127 @@
+//! Generate radare2/rizin scripts that apply the suggestions of analyses to an analyzed binary.
+
+use std::fmt::Write as _;
+
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD;
+
+use crate::emit::{FunctionSuggestions, ScriptEmitter};
+
+/// Header of radare2/rizin scripts, before the commands that apply the suggestions.
+const SCRIPT_HEADER: &str = "\
+# radare2/rizin script generated by oneiromancer: applies the suggested function names, comments,
+# and variable renames to the analyzed binary. Run it with `. <script>` in r2 or rizin, or pass it
+# with `-i <script>` on the command line, after the functions have been analyzed (e.g., with `aaa`).
+#
+# Functions are located by address only: the ones whose address is unknown are skipped. Names that
+# are not safe to use in commands are skipped as well, and comments are base64-encoded.
+";
+
+/// Emitter of radare2/rizin scripts that apply suggestions to an analyzed binary: they rename each
+/// function with `afn`, set its comment with `CCu base64:`, and rename its variables with `afvn`
+/// (which takes the new name first), all at the address of the function.
+#[derive(Debug, Clone, Copy)]
+pub struct R2Script;
+
+impl ScriptEmitter for R2Script {
+    fn description(&self) -> &'static str {
+        "radare2/rizin script"
+    }
+
+    fn format_script(&self, suggestions: &[FunctionSuggestions], _model: &str) -> String {
+        let mut script = String::from(SCRIPT_HEADER);
+        for function in suggestions {
+            let name = if function.original_name().is_empty() {
+                function.function_name()
+            } else {
+                function.original_name()
+            };
+            let label = if is_safe_name(name) {
+                name
+            } else {
+                "(unnamed)"
+            };
+            let Some(address) = function.address() else {
+                _ = writeln!(script, "\n# {label}: address unknown, skipped");
+                continue;
+            };
+            _ = writeln!(script, "\n# {label}");
+            if is_safe_name(function.function_name()) {
+                _ = writeln!(script, "afn {} @ 0x{address:x}", function.function_name());
+            } else {
+                script.push_str("# function name not safe in commands, skipped\n");
+            }
+            _ = writeln!(
+                script,
+                "CCu base64:{} @ 0x{address:x}",
+                STANDARD.encode(function.comment())
+            );
+            for rename in function.renames() {
+                if is_safe_name(&rename.0) && is_safe_name(&rename.1) {
+                    _ = writeln!(script, "afvn {} {} @ 0x{address:x}", rename.1, rename.0);
+                } else {
+                    script.push_str("# variable rename not safe in commands, skipped\n");
+                }
+            }
+        }
+        script
+    }
+}
+
+/// Returns whether `name` is not empty and only contains characters that have no special meaning
+/// in r2/rizin commands (letters, digits, `_`, `.`, and `$`).
+fn is_safe_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '_' | '.' | '$'))
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use super::*;
+    use crate::emit::collect_suggestions;
+    use crate::report::FunctionReport;
+
+    #[test]
+    fn format_script_matches_golden_file() -> anyhow::Result<()> {
+        let functions: Vec<FunctionReport> =
+            serde_json::from_str(include_str!("../tests/data/emit/functions.json"))?;
+        let suggestions = collect_suggestions("", None, &functions, None, None);
+
+        assert_eq!(
+            R2Script.format_script(&suggestions, "test-model"),
+            include_str!("../tests/data/emit/r2.r2")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn format_script_encodes_comments_and_skips_unsafe_names() -> anyhow::Result<()> {
+        let functions: Vec<FunctionReport> = serde_json::from_str(
+            r#"[{"name":"fcn.08048a10","function_name":"x; rm -rf /","comment":"Says \"hi\";\nthen | exits @ 0.","variables":[{"original_name":"a1","new_name":"ok_name"},{"original_name":"v1","new_name":"bad`name`"}]}]"#,
+        )?;
+        let suggestions = collect_suggestions("", None, &functions, None, None);
+
+        let script = R2Script.format_script(&suggestions, "test-model");
+        let commands = script
+            .strip_prefix(SCRIPT_HEADER)
+            .ok_or_else(|| anyhow::anyhow!("missing header: {script}"))?;
+        assert_eq!(
+            commands,
+            "\n# fcn.08048a10\n\
+             # function name not safe in commands, skipped\n\
+             CCu base64:U2F5cyAiaGkiOwp0aGVuIHwgZXhpdHMgQCAwLg== @ 0x8048a10\n\
+             afvn ok_name a1 @ 0x8048a10\n\
+             # variable rename not safe in commands, skipped\n"
+        );
+        assert_eq!(
+            STANDARD.decode("U2F5cyAiaGkiOwp0aGVuIHwgZXhpdHMgQCAwLg==")?,
+            b"Says \"hi\";\nthen | exits @ 0."
+        );
+
+        Ok(())
+    }
+}
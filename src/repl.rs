@@ -0,0 +1,317 @@
+//! Analyze snippets of pseudocode interactively, one at a time.
+
+use std::mem;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::Context as _;
+use rustyline::DefaultEditor;
+use rustyline::error::ReadlineError;
+
+use crate::reporter::Reporter;
+use crate::run::{improve_whole, write_output};
+use crate::{CommentWidth, RunOptions};
+
+/// Prompt shown before each snippet.
+const PROMPT: &str = "oneiromancer> ";
+/// Prompt shown before the continuation lines of a snippet.
+const CONTINUATION_PROMPT: &str = "... ";
+/// Line that terminates a snippet.
+const TERMINATOR: &str = ".";
+/// Help on the commands of the REPL.
+const REPL_HELP: &str = "\
+[*] Paste pseudocode and end it with a line with just `.` (or Ctrl-D) to analyze it. Commands:
+    :model NAME     switch to the NAME model
+    :width WIDTH    set the wrap width of descriptions (number of columns or `auto`)
+    :show           print the last improved pseudocode
+    :save PATH      save the last improved pseudocode in PATH (`:save!` to overwrite)
+    :help           print this help
+    :quit           exit (or press Ctrl-D)";
+
+/// Command of the REPL, entered as a line that starts with `:`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ReplCommand {
+    /// Switches to another model (`:model NAME`).
+    Model(String),
+    /// Sets the wrap width of descriptions (`:width WIDTH`).
+    Width(CommentWidth),
+    /// Prints the last improved pseudocode (`:show`).
+    Show,
+    /// Saves the last improved pseudocode (`:save PATH`), replacing an existing file only if
+    /// `overwrite` is set (`:save! PATH`).
+    Save {
+        /// Path of the output file.
+        path: PathBuf,
+        /// Whether to replace an existing file.
+        overwrite: bool,
+    },
+    /// Prints help on the commands (`:help`).
+    Help,
+    /// Exits the REPL (`:quit`).
+    Quit,
+}
+
+impl FromStr for ReplCommand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let line = s.trim();
+        let (name, argument) = line
+            .split_once(char::is_whitespace)
+            .map_or((line, ""), |(name, argument)| (name, argument.trim()));
+        let required = |usage: &str| {
+            if argument.is_empty() {
+                Err(format!("missing argument (usage: `{usage}`)"))
+            } else {
+                Ok(argument)
+            }
+        };
+        match name {
+            ":model" | ":m" => required(":model NAME").map(|model| Self::Model(model.to_owned())),
+            ":width" | ":w" => required(":width WIDTH")?.parse().map(Self::Width),
+            ":show" => Ok(Self::Show),
+            ":save" | ":save!" => required(":save PATH").map(|path| Self::Save {
+                path: PathBuf::from(path),
+                overwrite: name == ":save!",
+            }),
+            ":help" | ":h" | ":?" => Ok(Self::Help),
+            ":quit" | ":q" | ":exit" => Ok(Self::Quit),
+            _ => Err(format!("unknown command `{name}` (type `:help` for help)")),
+        }
+    }
+}
+
+/// Interactive session that analyzes snippets of pseudocode with the same client, keeping the last
+/// improved pseudocode in memory until it is saved.
+#[derive(Debug, Clone)]
+pub struct ReplSession {
+    /// Options used to analyze snippets and render descriptions.
+    options: RunOptions,
+    /// Last improved pseudocode, if any.
+    last: Option<String>,
+}
+
+impl ReplSession {
+    /// Creates a new [`ReplSession`] that analyzes snippets according to `options`.
+    #[must_use]
+    pub const fn new(options: RunOptions) -> Self {
+        Self {
+            options,
+            last: None,
+        }
+    }
+
+    /// Gets the last improved pseudocode, if any.
+    #[must_use]
+    pub fn last(&self) -> Option<&str> {
+        self.last.as_deref()
+    }
+
+    /// Returns the [`Reporter`] of status messages.
+    fn reporter(&self) -> Reporter {
+        self.options.reporter(false, true)
+    }
+
+    /// Analyzes `snippet`, printing the function description and the variable renaming
+    /// suggestions, and keeps the improved pseudocode as the last result.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error in case something goes wrong with the analysis.
+    pub fn analyze(&mut self, snippet: &str) -> anyhow::Result<()> {
+        let improved = improve_whole(snippet, &self.options, self.reporter())?;
+        self.last = Some(improved.into_pseudocode());
+        Ok(())
+    }
+
+    /// Executes `command`, returning `false` if the REPL must exit.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error in case `command` fails (e.g., if there is nothing to save).
+    pub fn execute(&mut self, command: ReplCommand) -> anyhow::Result<bool> {
+        let reporter = self.reporter();
+        match command {
+            ReplCommand::Model(model) => {
+                let client = self.options.switch_model(model);
+                reporter.line(format_args!(
+                    "[*] Using model `{}`",
+                    client.configured_model()
+                ));
+            }
+            ReplCommand::Width(width) => {
+                self.options.change_width(width);
+                reporter.line(format_args!("[*] Comment width set to `{width}`"));
+            }
+            ReplCommand::Show => {
+                let last = self.last.as_deref().context("Nothing analyzed yet")?;
+                reporter.text(last);
+            }
+            ReplCommand::Save { path, overwrite } => {
+                let last = self.last.as_deref().context("Nothing analyzed yet")?;
+                write_output(
+                    Some(&path),
+                    last,
+                    "improved pseudocode",
+                    overwrite,
+                    reporter,
+                )?;
+                reporter.line("[+] Saved improved pseudocode");
+            }
+            ReplCommand::Help => reporter.line(REPL_HELP),
+            ReplCommand::Quit => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+/// Runs a REPL that reads snippets of pseudocode, each terminated by a line with just `.` or by
+/// EOF, analyzes them with the settings in `options`, and pretty-prints the results.
+///
+/// Lines that start with `:` are commands (see [`ReplCommand`]). Ctrl-C discards the snippet being
+/// entered, while Ctrl-D exits. The history of entered lines is kept in memory only: nothing is
+/// written to disk unless saved with `:save`. Errors in analyzing snippets or executing commands
+/// are logged, and the REPL goes on.
+///
+/// # Errors
+///
+/// Returns an error in case the terminal cannot be read.
+pub fn run_repl(options: RunOptions) -> anyhow::Result<()> {
+    let mut session = ReplSession::new(options);
+    let reporter = session.reporter();
+    let mut editor = DefaultEditor::new().context("Failed to initialize the line editor")?;
+    reporter.line(REPL_HELP);
+
+    let mut snippet = String::new();
+    loop {
+        let prompt = if snippet.is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        };
+        match editor.readline(prompt) {
+            Ok(line) if snippet.is_empty() && line.trim_start().starts_with(':') => {
+                _ = editor.add_history_entry(line.as_str());
+                let result = line
+                    .parse::<ReplCommand>()
+                    .map_err(anyhow::Error::msg)
+                    .and_then(|command| session.execute(command));
+                match result {
+                    Ok(true) => (),
+                    Ok(false) => break,
+                    Err(err) => log::error!("{err:#}"),
+                }
+            }
+            Ok(line) if line.trim() == TERMINATOR => {
+                analyze(&mut session, &mem::take(&mut snippet));
+            }
+            Ok(line) => {
+                if !line.trim().is_empty() {
+                    _ = editor.add_history_entry(line.as_str());
+                }
+                snippet.push_str(&line);
+                snippet.push('\n');
+            }
+            Err(ReadlineError::Interrupted) if snippet.is_empty() => {
+                reporter.line("[*] Type `:quit` or press Ctrl-D to exit");
+            }
+            Err(ReadlineError::Interrupted) => {
+                snippet.clear();
+                reporter.line("[*] Snippet discarded");
+            }
+            Err(ReadlineError::Eof) if snippet.trim().is_empty() => break,
+            Err(ReadlineError::Eof) => analyze(&mut session, &mem::take(&mut snippet)),
+            Err(err) => return Err(err).context("Failed to read from the terminal"),
+        }
+    }
+    Ok(())
+}
+
+/// Analyzes `snippet` in `session`, logging errors (if any).
+fn analyze(session: &mut ReplSession, snippet: &str) {
+    if snippet.trim().is_empty() {
+        log::warn!("Nothing to analyze: paste pseudocode before the terminating `.`");
+        return;
+    }
+    if let Err(err) = session.analyze(snippet) {
+        log::error!("{err:#}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repl_commands_parse_with_arguments_and_aliases() {
+        assert_eq!(
+            " :model  qwen2.5-coder ".parse(),
+            Ok(ReplCommand::Model("qwen2.5-coder".to_owned()))
+        );
+        assert_eq!(
+            ":w 100".parse(),
+            Ok(ReplCommand::Width(CommentWidth::Columns(100)))
+        );
+        assert_eq!(
+            ":width auto".parse(),
+            Ok(ReplCommand::Width(CommentWidth::Auto))
+        );
+        assert_eq!(
+            ":save last.out.c".parse(),
+            Ok(ReplCommand::Save {
+                path: PathBuf::from("last.out.c"),
+                overwrite: false
+            })
+        );
+        assert_eq!(
+            ":save! out dir/last.out.c".parse(),
+            Ok(ReplCommand::Save {
+                path: PathBuf::from("out dir/last.out.c"),
+                overwrite: true
+            })
+        );
+        assert_eq!(":q".parse(), Ok(ReplCommand::Quit));
+        assert_eq!(":show".parse(), Ok(ReplCommand::Show));
+    }
+
+    #[test]
+    fn repl_commands_reject_unknown_names_and_missing_arguments() {
+        assert!(
+            ":model"
+                .parse::<ReplCommand>()
+                .is_err_and(|err| err.contains("usage: `:model NAME`"))
+        );
+        assert!(
+            ":width wide"
+                .parse::<ReplCommand>()
+                .is_err_and(|err| err.contains("invalid width `wide`"))
+        );
+        assert!(
+            ":load x.c"
+                .parse::<ReplCommand>()
+                .is_err_and(|err| err.contains("unknown command `:load`"))
+        );
+    }
+
+    #[test]
+    fn repl_session_refuses_to_show_or_save_before_analyzing() {
+        let mut session = ReplSession::new(RunOptions::new().quiet(true));
+        assert!(
+            session
+                .execute(ReplCommand::Show)
+                .is_err_and(|err| err.to_string() == "Nothing analyzed yet")
+        );
+        assert!(
+            session
+                .execute(ReplCommand::Save {
+                    path: PathBuf::from("never-written.out.c"),
+                    overwrite: true
+                })
+                .is_err_and(|err| err.to_string() == "Nothing analyzed yet")
+        );
+        assert!(!PathBuf::from("never-written.out.c").exists());
+        assert_eq!(session.execute(ReplCommand::Quit).ok(), Some(false));
+        assert_eq!(session.last(), None);
+    }
+}
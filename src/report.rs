@@ -0,0 +1,533 @@
+//! Machine-readable reports of the analysis of pseudocode files.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::stats::millis;
+use crate::{OneiromancerResults, RenameOccurrence, ResponseMetrics, RunSummary};
+
+/// Outcome of the analysis of a pseudocode file.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    /// The file was analyzed successfully.
+    Ok,
+    /// The file was skipped, because it was already analyzed.
+    Skipped,
+    /// The analysis of the file failed.
+    Error,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match *self {
+            Self::Ok => "ok",
+            Self::Skipped => "skipped",
+            Self::Error => "error",
+        })
+    }
+}
+
+/// Report of the analysis of a pseudocode file, serialized as a JSON object with a stable schema.
+///
+/// The `status` field is `"ok"`, `"skipped"`, or `"error"`. When the file is analyzed as a whole,
+/// the `function_name`, `comment`, and `variables` fields of [`OneiromancerResults`] are inlined
+/// in the report; when it is analyzed function by function, the `functions` array holds a
+/// [`FunctionReport`] for each function instead. The `error` field is only present on failure,
+/// and the `stats` field only if requested with [`RunOptions::stats`](crate::RunOptions::stats).
+///
+/// Reports can be deserialized back, e.g., to consume the JSON Lines printed with
+/// [`RunOptions::jsonl`](crate::RunOptions::jsonl).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AnalysisReport {
+    /// Outcome of the analysis.
+    status: Status,
+    /// Path of the input file (`-` for stdin).
+    input: PathBuf,
+    /// Path of the output file, or `None` if improved pseudocode was written to stdout.
+    output: Option<PathBuf>,
+    /// Model used for the analysis.
+    model: String,
+    /// Duration of the analysis, in milliseconds.
+    duration_ms: u64,
+    /// Analysis results of the whole file, if analyzed as a whole.
+    #[serde(flatten)]
+    results: Option<OneiromancerResults>,
+    /// Analysis results of each function, if analyzed function by function.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    functions: Vec<FunctionReport>,
+    /// Timing and token statistics reported by the Ollama API, if requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stats: Option<ResponseMetrics>,
+    /// Occurrences of the original names replaced by the renames, if requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    locations: Option<Vec<RenameOccurrence>>,
+    /// Error that caused the analysis to fail, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl AnalysisReport {
+    /// Creates a new [`AnalysisReport`] of a successful analysis.
+    pub(crate) fn succeeded(
+        input: &Path,
+        output: Option<PathBuf>,
+        model: &str,
+        duration: Duration,
+        results: Option<OneiromancerResults>,
+        functions: Vec<FunctionReport>,
+        stats: Option<ResponseMetrics>,
+    ) -> Self {
+        Self {
+            status: Status::Ok,
+            input: input.to_path_buf(),
+            output,
+            model: model.to_owned(),
+            duration_ms: millis(duration),
+            results,
+            functions,
+            stats,
+            locations: None,
+            error: None,
+        }
+    }
+
+    /// Creates a new [`AnalysisReport`] of a file skipped because it was already analyzed.
+    pub(crate) fn skipped(input: &Path, model: &str) -> Self {
+        Self {
+            status: Status::Skipped,
+            input: input.to_path_buf(),
+            output: None,
+            model: model.to_owned(),
+            duration_ms: 0,
+            results: None,
+            functions: Vec::new(),
+            stats: None,
+            locations: None,
+            error: None,
+        }
+    }
+
+    /// Creates a new [`AnalysisReport`] of the analysis of `input` with `model` that failed
+    /// after `duration` because of `error`.
+    #[must_use]
+    pub fn failed(
+        input: impl Into<PathBuf>,
+        model: impl Into<String>,
+        duration: Duration,
+        error: &anyhow::Error,
+    ) -> Self {
+        Self {
+            status: Status::Error,
+            input: input.into(),
+            output: None,
+            model: model.into(),
+            duration_ms: millis(duration),
+            results: None,
+            functions: Vec::new(),
+            stats: None,
+            locations: None,
+            error: Some(format!("{error:#}")),
+        }
+    }
+
+    /// Returns the report with the `input` and `output` paths replaced.
+    pub(crate) fn with_paths(mut self, input: PathBuf, output: Option<PathBuf>) -> Self {
+        self.input = input;
+        self.output = output;
+        self
+    }
+
+    /// Sets the occurrences of the original variable names replaced by the renames.
+    pub(crate) fn with_locations(mut self, locations: Option<Vec<RenameOccurrence>>) -> Self {
+        self.locations = locations;
+        self
+    }
+
+    /// Gets the outcome of the analysis.
+    pub(crate) const fn status(&self) -> Status {
+        self.status
+    }
+
+    /// Returns `true` if the file was skipped because it was already analyzed.
+    #[must_use]
+    pub fn is_skipped(&self) -> bool {
+        self.status == Status::Skipped
+    }
+
+    /// Returns `true` if the file was analyzed successfully or skipped.
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        self.status != Status::Error
+    }
+
+    /// Gets the path of the input file.
+    #[must_use]
+    pub fn input(&self) -> &Path {
+        &self.input
+    }
+
+    /// Gets the path of the output file, if improved pseudocode was saved to a file.
+    #[must_use]
+    pub fn output(&self) -> Option<&Path> {
+        self.output.as_deref()
+    }
+
+    /// Gets the model used for the analysis.
+    #[must_use]
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Gets the duration of the analysis.
+    #[must_use]
+    pub const fn duration(&self) -> Duration {
+        Duration::from_millis(self.duration_ms)
+    }
+
+    /// Gets the analysis results of the whole file, if analyzed as a whole.
+    #[must_use]
+    pub const fn results(&self) -> Option<&OneiromancerResults> {
+        self.results.as_ref()
+    }
+
+    /// Gets the analysis results of each function, if analyzed function by function.
+    #[must_use]
+    pub fn functions(&self) -> &[FunctionReport] {
+        &self.functions
+    }
+
+    /// Gets the timing and token statistics reported by the Ollama API, if requested.
+    #[must_use]
+    pub const fn stats(&self) -> Option<&ResponseMetrics> {
+        self.stats.as_ref()
+    }
+
+    /// Gets the occurrences of the original variable names replaced by the renames in the input
+    /// pseudocode, in order of position, if requested with
+    /// [`RunOptions::locations`](crate::RunOptions::locations).
+    #[must_use]
+    pub fn locations(&self) -> Option<&[RenameOccurrence]> {
+        self.locations.as_deref()
+    }
+
+    /// Gets the error that caused the analysis to fail, if any.
+    #[must_use]
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+/// Report of the analysis of a single function, serialized as a JSON object with the original
+/// `name` of the function and either the inlined [`OneiromancerResults`] or an `error` field.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FunctionReport {
+    /// Function name, as it appears in the pseudocode.
+    name: String,
+    /// Analysis results, if the analysis succeeded.
+    #[serde(flatten)]
+    results: Option<OneiromancerResults>,
+    /// Error that caused the analysis to fail, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl FunctionReport {
+    /// Creates a new [`FunctionReport`] of the function `name`, with the outcome of its analysis.
+    pub(crate) fn new(name: &str, outcome: Result<&OneiromancerResults, String>) -> Self {
+        let (results, error) = match outcome {
+            Ok(results) => (Some(results.clone()), None),
+            Err(err) => (None, Some(err)),
+        };
+        Self {
+            name: name.to_owned(),
+            results,
+            error,
+        }
+    }
+
+    /// Gets the function name, as it appears in the pseudocode.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the analysis results, if the analysis succeeded.
+    #[must_use]
+    pub const fn results(&self) -> Option<&OneiromancerResults> {
+        self.results.as_ref()
+    }
+
+    /// Gets the error that caused the analysis to fail, if any.
+    #[must_use]
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+/// Report of a batch, printed as the last line of the JSON Lines output with
+/// [`RunOptions::jsonl`](crate::RunOptions::jsonl).
+///
+/// The `status` field is always `"summary"`, so that the report can be told apart from the
+/// [`AnalysisReport`] of each file. The `errors` array holds a [`FailureReport`] for each file
+/// whose analysis failed.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BatchReport {
+    /// Tag of the report.
+    status: SummaryTag,
+    /// Number of files analyzed successfully.
+    succeeded: usize,
+    /// Number of files whose analysis failed.
+    failed: usize,
+    /// Number of files skipped because they are already up to date.
+    skipped: usize,
+    /// Number of files analyzed successfully that reused the results of an identical earlier file.
+    #[serde(default)]
+    deduplicated: usize,
+    /// Number of files not analyzed because the batch was interrupted.
+    interrupted: usize,
+    /// Errors of the files whose analysis failed.
+    errors: Vec<FailureReport>,
+}
+
+/// Tag of a [`BatchReport`], serialized as `"summary"`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum SummaryTag {
+    /// Summary of a batch.
+    Summary,
+}
+
+impl BatchReport {
+    /// Creates a new [`BatchReport`] of a batch with `summary`.
+    pub(crate) fn new(summary: &RunSummary) -> Self {
+        Self {
+            status: SummaryTag::Summary,
+            succeeded: summary.succeeded().len(),
+            failed: summary.failed().len(),
+            skipped: summary.skipped().len(),
+            deduplicated: summary.deduplicated().len(),
+            interrupted: summary.interrupted().len(),
+            errors: summary
+                .failed()
+                .iter()
+                .map(|failure| FailureReport::new(&failure.0, &failure.1))
+                .collect(),
+        }
+    }
+
+    /// Gets the number of files analyzed successfully.
+    #[must_use]
+    pub const fn succeeded(&self) -> usize {
+        self.succeeded
+    }
+
+    /// Gets the number of files whose analysis failed.
+    #[must_use]
+    pub const fn failed(&self) -> usize {
+        self.failed
+    }
+
+    /// Gets the number of files skipped because they are already up to date.
+    #[must_use]
+    pub const fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// Gets the number of files analyzed successfully (also counted in
+    /// [`BatchReport::succeeded`]) that reused the results of an identical earlier file.
+    #[must_use]
+    pub const fn deduplicated(&self) -> usize {
+        self.deduplicated
+    }
+
+    /// Gets the number of files not analyzed because the batch was interrupted.
+    #[must_use]
+    pub const fn interrupted(&self) -> usize {
+        self.interrupted
+    }
+
+    /// Gets the errors of the files whose analysis failed.
+    #[must_use]
+    pub fn errors(&self) -> &[FailureReport] {
+        &self.errors
+    }
+}
+
+/// Error of a file whose analysis failed, serialized as a JSON object with the `input` path, the
+/// whole `error` message, and the `causes` in its chain (outermost first).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FailureReport {
+    /// Path of the input file (`-` for stdin).
+    input: PathBuf,
+    /// Error message, including its causes.
+    error: String,
+    /// Messages of the errors in the chain, outermost first.
+    causes: Vec<String>,
+}
+
+impl FailureReport {
+    /// Creates a new [`FailureReport`] of the `input` file whose analysis failed with `err`.
+    pub(crate) fn new(input: impl Into<PathBuf>, err: &anyhow::Error) -> Self {
+        Self {
+            input: input.into(),
+            error: format!("{err:#}"),
+            causes: err.chain().map(ToString::to_string).collect(),
+        }
+    }
+
+    /// Gets the path of the input file.
+    #[must_use]
+    pub fn input(&self) -> &Path {
+        &self.input
+    }
+
+    /// Gets the error message, including its causes.
+    #[must_use]
+    pub fn error(&self) -> &str {
+        &self.error
+    }
+
+    /// Gets the messages of the errors in the chain, outermost first.
+    #[must_use]
+    pub fn causes(&self) -> &[String] {
+        &self.causes
+    }
+}
+
+/// Format of the human-readable report written in place of improved pseudocode.
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ReportFormat {
+    /// Markdown report, with the description, a table of renames, and the improved pseudocode.
+    #[cfg_attr(feature = "cli", value(name = "md", alias = "markdown"))]
+    Markdown,
+    /// Self-contained HTML report, with the description and a side-by-side before/after view of
+    /// the pseudocode with renamed identifiers highlighted.
+    Html,
+}
+
+impl ReportFormat {
+    /// Returns the extension of report files, replacing the extension of input files.
+    #[must_use]
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "report.md",
+            Self::Html => "report.html",
+        }
+    }
+
+    /// Returns the description of the report, for status messages.
+    #[must_use]
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::Markdown => "Markdown report",
+            Self::Html => "HTML report",
+        }
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_inlines_whole_file_results() -> anyhow::Result<()> {
+        let results: OneiromancerResults = serde_json::from_str(
+            r#"{"function_name":"init","comment":"Initializes.","variables":[{"original_name":"a1","new_name":"ctx"}]}"#,
+        )?;
+        let duration_ms: u64 = 1_500;
+        let report = AnalysisReport::succeeded(
+            Path::new("a.c"),
+            Some(PathBuf::from("a.out.c")),
+            "aidapal",
+            Duration::from_millis(duration_ms),
+            Some(results),
+            Vec::new(),
+            None,
+        );
+
+        let value = serde_json::to_value(&report)?;
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "status": "ok",
+                "input": "a.c",
+                "output": "a.out.c",
+                "model": "aidapal",
+                "duration_ms": duration_ms,
+                "function_name": "init",
+                "comment": "Initializes.",
+                "variables": [{"original_name": "a1", "new_name": "ctx"}],
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn failed_report_has_error_field() -> anyhow::Result<()> {
+        let report =
+            AnalysisReport::failed("a.c", "aidapal", Duration::ZERO, &anyhow::anyhow!("boom"));
+        assert!(!report.is_success(), "failure not reported");
+
+        let value = serde_json::to_value(&report)?;
+        assert_eq!(value.get("status"), Some(&serde_json::json!("error")));
+        assert_eq!(value.get("error"), Some(&serde_json::json!("boom")));
+        assert_eq!(value.get("variables"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn report_round_trips_function_reports() -> anyhow::Result<()> {
+        let results: OneiromancerResults = serde_json::from_str(
+            r#"{"function_name":"init","comment":"Initializes.","variables":[]}"#,
+        )?;
+        let functions = vec![
+            FunctionReport::new("sub_1000", Ok(&results)),
+            FunctionReport::new("sub_2000", Err("timeout".to_owned())),
+        ];
+        let report = AnalysisReport::succeeded(
+            Path::new("a.c"),
+            None,
+            "aidapal",
+            Duration::ZERO,
+            None,
+            functions,
+            None,
+        );
+
+        let parsed: AnalysisReport = serde_json::from_str(&serde_json::to_string(&report)?)?;
+        assert!(parsed.is_success(), "status not preserved");
+        assert!(
+            parsed.results().is_none(),
+            "whole-file results not expected"
+        );
+        let names: Vec<_> = parsed
+            .functions()
+            .iter()
+            .map(|function| {
+                (
+                    function.name(),
+                    function.results().map(OneiromancerResults::function_name),
+                    function.error(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("sub_1000", Some("init"), None),
+                ("sub_2000", None, Some("timeout")),
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,385 @@
+//! Report progress on the terminal.
+//!
+//! Diagnostics (errors, warnings, and debugging information) are emitted through the `log` facade
+//! instead, so that their verbosity can be controlled by the application.
+
+use std::fmt::Display;
+use std::io::{self, IsTerminal as _};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "cli")]
+use std::time::Duration;
+use std::time::Instant;
+
+#[cfg(feature = "cli")]
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+#[cfg(feature = "cli")]
+use spinners::{Spinner, Spinners};
+
+#[cfg(not(feature = "cli"))]
+use self::plain::{ProgressBar, Spinner};
+use crate::style::{ColorChoice, Tone, paint, paint_diff, paint_status};
+
+/// Template of the progress bar of batches.
+#[cfg(feature = "cli")]
+const PROGRESS_TEMPLATE: &str =
+    "[{bar:30}] {pos}/{len} files, {prefix} [{elapsed_precise} elapsed, ETA {eta}] {wide_msg}";
+/// Number of plain progress lines printed over a batch, when the progress bar is disabled.
+const PROGRESS_LINES: usize = 10;
+/// Interval between redraws of the progress bar, so that the elapsed time keeps updating.
+#[cfg(feature = "cli")]
+const PROGRESS_TICK: Duration = Duration::from_millis(250);
+
+/// Reports status messages on stdout, or on stderr when stdout is reserved for program output.
+#[derive(Debug, Clone, Copy, Default)]
+#[expect(
+    clippy::struct_excessive_bools,
+    reason = "reporter settings are naturally modeled as bools"
+)]
+pub struct Reporter {
+    /// Whether stdout is reserved for program output.
+    stdout_reserved: bool,
+    /// Whether spinners are enabled.
+    spinner: bool,
+    /// Whether status messages are suppressed.
+    quiet: bool,
+    /// Whether status markers and renaming suggestions are colored.
+    color: bool,
+}
+
+impl Reporter {
+    /// Creates a new [`Reporter`]. If `stdout_reserved` is set, status messages are sent to stderr
+    /// and spinners are suppressed, regardless of `spinner`. Spinners are also suppressed when
+    /// stdout is not a terminal (e.g., in CI or when output is redirected to a file), and without
+    /// the `cli` feature.
+    pub fn new(stdout_reserved: bool, spinner: bool) -> Self {
+        Self {
+            stdout_reserved,
+            spinner: cfg!(feature = "cli")
+                && spinner
+                && !stdout_reserved
+                && io::stdout().is_terminal(),
+            quiet: false,
+            color: false,
+        }
+    }
+
+    /// Sets whether status messages and spinners are suppressed.
+    pub const fn quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self.spinner = self.spinner && !quiet;
+        self
+    }
+
+    /// Sets when status markers and renaming suggestions are colored, based on whether the stream
+    /// that receives status messages is a terminal.
+    pub fn color(mut self, choice: ColorChoice) -> Self {
+        let terminal = if self.stdout_reserved {
+            io::stderr().is_terminal()
+        } else {
+            io::stdout().is_terminal()
+        };
+        self.color = choice.enabled(terminal);
+        self
+    }
+
+    /// Prints a status line, coloring its marker (e.g., `[+]`) if enabled.
+    pub fn line(self, line: impl Display) {
+        if self.quiet {
+            return;
+        }
+        let text = line.to_string();
+        let painted = paint_status(&text, self.color);
+        if self.stdout_reserved {
+            eprintln!("{painted}");
+        } else {
+            println!("{painted}");
+        }
+    }
+
+    /// Prints a renaming suggestion from `original` to `new` as a line of the rename table.
+    pub fn rename(self, original: &str, new: &str) {
+        self.line(format_args!(
+            "    {}\t-> {}",
+            paint(original, Tone::OldName, self.color),
+            paint(new, Tone::NewName, self.color)
+        ));
+    }
+
+    /// Prints a renaming suggestion from `original` to `new` that was skipped because of `reason`
+    /// as a line of the rename table, marked as skipped.
+    pub fn skipped_rename(self, original: &str, new: &str, reason: impl Display) {
+        let note = format!("(skipped: {reason})");
+        self.line(format_args!(
+            "    {}\t-> {} {}",
+            paint(original, Tone::OldName, self.color),
+            paint(new, Tone::NewName, self.color),
+            paint(&note, Tone::Warning, self.color)
+        ));
+    }
+
+    /// Prints a unified `diff`, coloring its changed lines if enabled. The diff is printed even if
+    /// status messages are suppressed, since it was requested as output.
+    pub fn diff(self, diff: &str) {
+        let painted = paint_diff(diff, self.color);
+        if self.stdout_reserved {
+            eprint!("{painted}");
+        } else {
+            print!("{painted}");
+        }
+    }
+
+    /// Prints a `prompt` as is, followed by a newline if it does not end with one. The prompt is
+    /// printed even if status messages are suppressed, since it was requested as output.
+    pub fn prompt(self, prompt: &str) {
+        let newline = if prompt.ends_with('\n') { "" } else { "\n" };
+        if self.stdout_reserved {
+            eprint!("{prompt}{newline}");
+        } else {
+            print!("{prompt}{newline}");
+        }
+    }
+
+    /// Prints status text as is.
+    pub fn text(self, text: impl Display) {
+        if self.quiet {
+            return;
+        }
+        if self.stdout_reserved {
+            eprint!("{text}");
+        } else {
+            print!("{text}");
+        }
+    }
+
+    /// Starts a long-running activity described by `message`, showing a spinner if enabled, or
+    /// printing `message` as a single status line otherwise.
+    pub fn start(self, message: &str) -> Activity {
+        let spinner = start_spinner(self.spinner, message);
+        if spinner.is_none() {
+            self.line(format_args!("{message}..."));
+        }
+        Activity {
+            spinner,
+            reporter: self,
+        }
+    }
+
+    /// Starts tracking the progress of a batch of `total` files, showing a progress bar if
+    /// spinners are enabled and there is more than one file, or printing a plain progress line
+    /// every tenth of the batch otherwise.
+    pub fn progress(self, total: usize) -> Progress {
+        Progress {
+            bar: start_progress_bar(self.spinner && total > 1, total),
+            reporter: self,
+            total,
+            done: AtomicUsize::new(0),
+            failed: AtomicUsize::new(0),
+            started: Instant::now(),
+        }
+    }
+}
+
+/// Progress of a batch started by [`Reporter::progress`].
+pub struct Progress {
+    /// Progress bar, if enabled.
+    bar: Option<ProgressBar>,
+    /// Reporter that started the batch.
+    reporter: Reporter,
+    /// Number of files in the batch.
+    total: usize,
+    /// Number of completed files.
+    done: AtomicUsize,
+    /// Number of failed files.
+    failed: AtomicUsize,
+    /// Start time of the batch.
+    started: Instant,
+}
+
+impl Progress {
+    /// Returns `true` if a progress bar is shown, in which case status messages must be printed
+    /// through [`Progress::suspend`] or [`Progress::complete`] so that they do not garble the bar.
+    pub const fn has_bar(&self) -> bool {
+        self.bar.is_some()
+    }
+
+    /// Shows `filepath` as the file currently being analyzed.
+    pub fn start(&self, filepath: &Path) {
+        if let Some(bar) = self.bar.as_ref() {
+            bar.set_message(format!("`{}`", filepath.display()));
+        }
+    }
+
+    /// Calls `report` to print status messages above the progress bar (if any), returning its
+    /// result.
+    pub fn suspend<T>(&self, report: impl FnOnce() -> T) -> T {
+        match self.bar.as_ref() {
+            Some(bar) => bar.suspend(report),
+            None => report(),
+        }
+    }
+
+    /// Completes the analysis of a file that `failed` or not, calling `report` to print its
+    /// outcome above the progress bar (if any) and returning its result. Without a progress bar,
+    /// a plain progress line is printed every tenth of the batch.
+    pub fn complete<T>(&self, failed: bool, report: impl FnOnce() -> T) -> T {
+        let result = self.suspend(report);
+        let done = self.done.fetch_add(1, Ordering::Relaxed).saturating_add(1);
+        let failures = if failed {
+            self.failed
+                .fetch_add(1, Ordering::Relaxed)
+                .saturating_add(1)
+        } else {
+            self.failed.load(Ordering::Relaxed)
+        };
+
+        match self.bar.as_ref() {
+            Some(bar) => {
+                bar.set_prefix(format!("{failures} failed"));
+                bar.inc(1);
+            }
+            None if done < self.total
+                && done.checked_rem(self.total.div_ceil(PROGRESS_LINES)) == Some(0) =>
+            {
+                let elapsed = self.started.elapsed();
+                let eta = u32::try_from(done)
+                    .ok()
+                    .and_then(|count| elapsed.checked_div(count))
+                    .zip(u32::try_from(self.total.saturating_sub(done)).ok())
+                    .and_then(|(average, remaining)| average.checked_mul(remaining))
+                    .unwrap_or_default();
+                self.reporter.line(format_args!(
+                    "\n[*] Progress: {done} of {} files done ({failures} failed), {elapsed:.1?} \
+                     elapsed, ETA {eta:.1?}",
+                    self.total
+                ));
+            }
+            None => (),
+        }
+        result
+    }
+
+    /// Finishes the batch, clearing the progress bar (if any).
+    #[cfg_attr(
+        not(feature = "cli"),
+        expect(
+            clippy::missing_const_for_fn,
+            reason = "progress bars with the `cli` feature cannot be finished in const context"
+        )
+    )]
+    pub fn finish(self) {
+        if let Some(bar) = self.bar {
+            bar.finish_and_clear();
+        }
+    }
+}
+
+/// Long-running activity started by [`Reporter::start`].
+pub struct Activity {
+    /// Spinner shown while the activity is running, if enabled.
+    spinner: Option<Spinner>,
+    /// Reporter that started the activity.
+    reporter: Reporter,
+}
+
+impl Activity {
+    /// Finishes the activity, replacing the spinner (if any) with `message`.
+    pub fn finish(self, message: &str) {
+        match self.spinner {
+            Some(mut spinner) => {
+                spinner.stop_with_message(paint_status(message, self.reporter.color).into_owned());
+            }
+            None => self.reporter.line(message),
+        }
+    }
+}
+
+/// Returns a spinner showing `message`, if `enabled`.
+#[cfg(feature = "cli")]
+fn start_spinner(enabled: bool, message: &str) -> Option<Spinner> {
+    enabled.then(|| Spinner::new(Spinners::SimpleDotsScrolling, message.into()))
+}
+
+/// Returns no spinner: spinners require the `cli` feature.
+#[cfg(not(feature = "cli"))]
+const fn start_spinner(_enabled: bool, _message: &str) -> Option<Spinner> {
+    None
+}
+
+/// Returns a progress bar for a batch of `total` files, if `enabled`.
+#[cfg(feature = "cli")]
+fn start_progress_bar(enabled: bool, total: usize) -> Option<ProgressBar> {
+    enabled.then(|| {
+        let bar = ProgressBar::with_draw_target(
+            Some(u64::try_from(total).unwrap_or(u64::MAX)),
+            ProgressDrawTarget::stdout(),
+        )
+        .with_style(
+            ProgressStyle::with_template(PROGRESS_TEMPLATE)
+                .unwrap_or_else(|_| ProgressStyle::default_bar())
+                .progress_chars("=> "),
+        )
+        .with_prefix("0 failed");
+        bar.enable_steady_tick(PROGRESS_TICK);
+        bar
+    })
+}
+
+/// Returns no progress bar: progress bars require the `cli` feature.
+#[cfg(not(feature = "cli"))]
+const fn start_progress_bar(_enabled: bool, _total: usize) -> Option<ProgressBar> {
+    None
+}
+
+/// Stand-ins for the spinner and progress bar without the `cli` feature, where status messages are
+/// always printed as plain lines. They have no values, so their methods are never called.
+#[cfg(not(feature = "cli"))]
+#[expect(
+    clippy::uninhabited_references,
+    reason = "values of these types cannot exist, so these references are never dereferenced"
+)]
+mod plain {
+    /// Spinner that cannot be created.
+    pub enum Spinner {}
+
+    impl Spinner {
+        /// Stops the spinner.
+        #[expect(
+            clippy::needless_pass_by_ref_mut,
+            reason = "same signature as the spinner of the `cli` feature"
+        )]
+        pub const fn stop_with_message(&mut self, _message: String) {
+            match *self {}
+        }
+    }
+
+    /// Progress bar that cannot be created.
+    pub enum ProgressBar {}
+
+    impl ProgressBar {
+        /// Sets the message of the progress bar.
+        pub const fn set_message(&self, _message: String) {
+            match *self {}
+        }
+
+        /// Sets the prefix of the progress bar.
+        pub const fn set_prefix(&self, _prefix: String) {
+            match *self {}
+        }
+
+        /// Advances the progress bar.
+        pub const fn inc(&self, _delta: u64) {
+            match *self {}
+        }
+
+        /// Calls `report` with the progress bar hidden.
+        pub const fn suspend<T>(&self, _report: impl FnOnce() -> T) -> T {
+            match *self {}
+        }
+
+        /// Clears the progress bar.
+        pub const fn finish_and_clear(&self) {
+            match *self {}
+        }
+    }
+}
@@ -0,0 +1,289 @@
+//! Retry requests that fail for transient reasons, with exponential backoff.
+
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher as _;
+use std::io::ErrorKind;
+use std::num::NonZeroUsize;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "async")]
+use tokio::time;
+
+use crate::OneiromancerError;
+
+/// Default delay before the first retry.
+const BASE_DELAY: Duration = Duration::from_millis(500);
+/// Factor by which the delay grows with each retry.
+const BACKOFF_FACTOR: u32 = 2;
+/// Maximum delay between two attempts, however many retries came before.
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Policy for retrying requests to the LLM that fail for transient reasons: connection resets,
+/// `5xx` responses (e.g., while the server is loading the model), and timeouts.
+///
+/// The delay before each retry doubles from the base delay, up to 30 seconds, and with jitter it
+/// is a random value between half and all of it, so that concurrent analyses do not retry in
+/// lockstep. Other failures, such as a refused connection, a `4xx` response, or a response that
+/// cannot be parsed, are returned at once.
+///
+/// # Examples
+///
+/// ```
+/// use std::num::NonZeroUsize;
+/// use std::time::Duration;
+///
+/// use oneiromancer::{Oneiromancer, RetryPolicy};
+///
+/// let policy = RetryPolicy::new()
+///     .max_attempts(NonZeroUsize::new(4).unwrap())
+///     .base_delay(Duration::from_secs(1));
+/// let client = Oneiromancer::new().retries(policy);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first one.
+    max_attempts: NonZeroUsize,
+    /// Delay before the first retry, doubled for each subsequent one.
+    base_delay: Duration,
+    /// Whether to randomize the delays.
+    jitter: bool,
+}
+
+impl RetryPolicy {
+    /// Creates a new [`RetryPolicy`] with default values (a single attempt, so no retries).
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of attempts of each request, including the first one (1, the
+    /// default, disables retries).
+    #[must_use]
+    pub const fn max_attempts(mut self, max_attempts: NonZeroUsize) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Sets the delay before the first retry (500 ms by default), doubled for each subsequent one.
+    #[must_use]
+    pub const fn base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// Sets whether to randomize the delays between half and all of their value (`true` by
+    /// default).
+    #[must_use]
+    pub const fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Runs `attempt` until it succeeds, fails for a reason that is not transient, or the maximum
+    /// number of attempts is reached, sleeping between attempts.
+    pub(crate) fn run<T>(
+        &self,
+        mut attempt: impl FnMut() -> Result<T, OneiromancerError>,
+    ) -> Result<T, OneiromancerError> {
+        let mut retries: usize = 0;
+        loop {
+            match attempt() {
+                Err(err) if self.should_retry(&err, retries) => {
+                    let delay = self.delay(retries);
+                    retries = retries.saturating_add(1);
+                    self.log_retry(&err, retries, delay);
+                    thread::sleep(delay);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Runs `attempt` like [`run`](Self::run), without blocking the calling thread between
+    /// attempts.
+    #[cfg(feature = "async")]
+    pub(crate) async fn run_async<T, F>(
+        &self,
+        mut attempt: impl FnMut() -> F,
+    ) -> Result<T, OneiromancerError>
+    where
+        F: Future<Output = Result<T, OneiromancerError>>,
+    {
+        let mut retries: usize = 0;
+        loop {
+            match attempt().await {
+                Err(err) if self.should_retry(&err, retries) => {
+                    let delay = self.delay(retries);
+                    retries = retries.saturating_add(1);
+                    self.log_retry(&err, retries, delay);
+                    time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Returns `true` if a request that failed with `err` after `retries` retries is retried.
+    fn should_retry(&self, err: &OneiromancerError, retries: usize) -> bool {
+        retries.saturating_add(1) < self.max_attempts.get() && is_transient(err)
+    }
+
+    /// Returns the delay before the retry that follows `retries` retries.
+    fn delay(&self, retries: usize) -> Duration {
+        let factor = u32::try_from(retries)
+            .ok()
+            .and_then(|exponent| BACKOFF_FACTOR.checked_pow(exponent))
+            .unwrap_or(u32::MAX);
+        let delay = self.base_delay.saturating_mul(factor).min(MAX_DELAY);
+        if !self.jitter {
+            return delay;
+        }
+        let millis = u64::try_from(delay.as_millis()).unwrap_or(u64::MAX);
+        let half = millis.div_ceil(2);
+        let random = RandomState::new().hash_one(retries);
+        Duration::from_millis(
+            half.saturating_add(
+                random
+                    .checked_rem(half.saturating_add(1))
+                    .unwrap_or_default(),
+            ),
+        )
+        .min(delay)
+    }
+
+    /// Logs the `retries`-th retry of a request that failed with `err`, after `delay`.
+    fn log_retry(&self, err: &OneiromancerError, retries: usize, delay: Duration) {
+        log::warn!(
+            "Request failed ({err}), retrying in {} ms (retry {retries} of {})",
+            delay.as_millis(),
+            self.max_attempts.get().saturating_sub(1)
+        );
+        #[cfg(feature = "tracing")]
+        tracing::warn!(error = %err, retries, delay_ms = delay.as_millis(), "request retried");
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: NonZeroUsize::MIN,
+            base_delay: BASE_DELAY,
+            jitter: true,
+        }
+    }
+}
+
+/// Returns `true` if `err` is a transient failure of a request, which may succeed if retried.
+fn is_transient(err: &OneiromancerError) -> bool {
+    matches!(
+        *err,
+        OneiromancerError::OllamaQueryFailed(ureq::Error::StatusCode(500..=599))
+            | OneiromancerError::Timeout(_)
+    ) || matches!(
+        err,
+        OneiromancerError::OllamaQueryFailed(ureq::Error::Io(io)) if matches!(
+            io.kind(),
+            ErrorKind::ConnectionReset
+                | ErrorKind::ConnectionAborted
+                | ErrorKind::BrokenPipe
+                | ErrorKind::UnexpectedEof
+                | ErrorKind::TimedOut
+        )
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+
+    fn policy(attempts: usize) -> RetryPolicy {
+        RetryPolicy::new()
+            .max_attempts(NonZeroUsize::new(attempts).unwrap_or(NonZeroUsize::MIN))
+            .base_delay(Duration::from_millis(1))
+    }
+
+    #[test]
+    fn run_retries_transient_failures_up_to_max_attempts() {
+        let mut calls: usize = 0;
+        let result: Result<(), _> = policy(3).run(|| {
+            calls = calls.saturating_add(1);
+            Err(ureq::Error::StatusCode(503).into())
+        });
+
+        assert!(
+            matches!(
+                result,
+                Err(OneiromancerError::OllamaQueryFailed(
+                    ureq::Error::StatusCode(503)
+                ))
+            ),
+            "wrong error returned: {result:?}"
+        );
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn run_returns_first_success_or_permanent_failure() {
+        let mut calls: usize = 0;
+        let result = policy(5).run(|| {
+            calls = calls.saturating_add(1);
+            if calls < 2 {
+                Err(ureq::Error::Io(io::Error::from(ErrorKind::ConnectionReset)).into())
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result.ok(), Some(2));
+
+        let mut permanent_calls: usize = 0;
+        let failure: Result<(), _> = policy(5).run(|| {
+            permanent_calls = permanent_calls.saturating_add(1);
+            Err(ureq::Error::StatusCode(404).into())
+        });
+        assert!(failure.is_err(), "failure not returned");
+        assert_eq!(permanent_calls, 1);
+    }
+
+    #[test]
+    fn is_transient_classifies_failures() {
+        for transient in [
+            ureq::Error::StatusCode(500),
+            ureq::Error::Timeout(ureq::Timeout::RecvResponse),
+            ureq::Error::Io(io::Error::from(ErrorKind::ConnectionReset)),
+        ] {
+            let err = OneiromancerError::from(transient);
+            assert!(is_transient(&err), "{err:?}");
+        }
+        for permanent in [
+            ureq::Error::StatusCode(400),
+            ureq::Error::ConnectionFailed,
+            ureq::Error::Io(io::Error::from(ErrorKind::ConnectionRefused)),
+        ] {
+            let err = OneiromancerError::from(permanent);
+            assert!(!is_transient(&err), "{err:?}");
+        }
+        assert!(
+            !is_transient(&OneiromancerError::FileReadFailed(io::Error::from(
+                ErrorKind::TimedOut
+            ))),
+            "file error retried"
+        );
+    }
+
+    #[test]
+    fn delay_doubles_up_to_the_maximum() {
+        let policy = RetryPolicy::new().jitter(false);
+        assert_eq!(policy.delay(0), BASE_DELAY);
+        assert_eq!(policy.delay(2), BASE_DELAY.saturating_mul(4));
+        assert_eq!(policy.delay(usize::MAX), MAX_DELAY);
+
+        let jittered = RetryPolicy::new().delay(1);
+        assert!(
+            jittered >= BASE_DELAY && jittered <= BASE_DELAY.saturating_mul(2),
+            "delay out of range: {jittered:?}"
+        );
+    }
+}
@@ -0,0 +1,3806 @@
+//! Runs of analyses that save improved pseudocode and the other outputs, for single files,
+//! batches, haruspex directories, archives, and binaries (behind the `cli` feature).
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, IsTerminal as _, Read, Write as _};
+use std::iter;
+use std::num::NonZeroUsize;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt as _;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Context as _;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::audit::{RenameDecision, RenameSkip};
+use crate::binja::BinjaScript;
+use crate::cache::{ResponseMemo, content_hash};
+use crate::collision::resolve_collisions;
+use crate::emit::{ScriptEmitter, collect_suggestions};
+use crate::format::{Provenance, format_header, format_provenance};
+use crate::ghidra::GhidraScript;
+use crate::haruspex::format_index;
+use crate::header::{Declaration, format_c_header};
+use crate::ida::IdaScript;
+use crate::idc::IdcScript;
+use crate::merge::{comments_start, place_header};
+use crate::occurrence::{RenameMatch, find_renames, locate_matches};
+use crate::patch::format_file_patch;
+use crate::prompt::estimated_tokens;
+use crate::r2::R2Script;
+use crate::reporter::{Progress, Reporter};
+use crate::sarif::{Finding, format_sarif, locate_findings};
+use crate::split::leading_lines;
+use crate::state::RunState;
+use crate::stats::{FileStats, RunStats, format_stats};
+use crate::{
+    AnalysisContext, AnalysisReport, AnnotationField, ArchiveFormat, AuditLog,
+    BATCH_INDEX_FILENAME, BATCH_REPORT_FILENAME, BatchIndex, BatchReport, CollisionPolicy,
+    ColorChoice, CommentOptions, CommentWidth, DiscoverOptions, FunctionReport, FunctionSelector,
+    FunctionSpan, FunctionSuggestions, HTML_INDEX_FILENAME, History, IMPROVED_DIRNAME,
+    INDEX_FILENAME, IndexEntry, InputFormat, InputPolicy, Interrupt, MAX_INPUT_SIZE, Oneiromancer,
+    OneiromancerError, OneiromancerResults, QualityPolicy, RENAMES_EXTENSION, RenameMap,
+    ReportFormat, ResponseMetrics, STDIN_FILEPATH, Variable, decompile_binary, format_consensus,
+    format_html_index, format_html_report, format_markdown_report, pack_dir, parse_aidapal_json,
+    read_archive, read_haruspex_dir, select_function, splice_renames, split_functions,
+    validate_input,
+};
+
+/// Suffix appended to the name of input files to back them up before they are rewritten in place.
+pub const BACKUP_SUFFIX: &str = ".bak";
+
+/// Failure in writing the output of an analysis.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum OutputError {
+    /// Failure in creating an output file or directory.
+    #[error("Failed to create `{}`", .0.display())]
+    CreateFailed(PathBuf, #[source] io::Error),
+    /// Failure in writing an output file.
+    #[error("Failed to write `{}`", .0.display())]
+    WriteFailed(PathBuf, #[source] io::Error),
+    /// Failure in writing to stdout.
+    #[error("Failed to write to stdout")]
+    StdoutFailed(#[source] io::Error),
+    /// Output file that already exists and is not replaced.
+    #[error("Output file `{}` already exists", .0.display())]
+    OutputExists(PathBuf),
+    /// Output file that two input files of a batch would be saved as (e.g., `foo.c` and `foo.h`).
+    #[error(
+        "Input files `{}` and `{}` would both be saved as `{}`",
+        .0.display(),
+        .1.display(),
+        .2.display()
+    )]
+    SharedOutput(PathBuf, PathBuf, PathBuf),
+}
+
+/// Options that control the behavior of [`run_with_options`].
+#[derive(Debug, Clone, Default)]
+#[expect(
+    clippy::struct_excessive_bools,
+    reason = "run options are naturally modeled as bools"
+)]
+pub struct RunOptions {
+    /// Client used to analyze pseudocode.
+    client: Oneiromancer,
+    /// Options used to render the function description.
+    comment: CommentOptions,
+    /// Options that control which suggestions are applied to the output file.
+    apply: ApplyOptions,
+    /// Provenance fields to annotate the output file with (none to disable the annotation).
+    annotation: Vec<AnnotationField>,
+    /// Whether to write the improved pseudocode to stdout instead of an output file.
+    stdout: bool,
+    /// Path of the output file, if different from the default.
+    output: Option<PathBuf>,
+    /// Directory where output files are saved, mirroring the input directory tree, if any.
+    output_dir: Option<PathBuf>,
+    /// Base directory of the input files, mirrored under the output directory.
+    input_root: Option<PathBuf>,
+    /// Extension of output files with improved pseudocode, if different from `out.c`.
+    output_extension: Option<String>,
+    /// Whether to rewrite input files in place, after backing them up.
+    in_place: bool,
+    /// Whether to replace existing output files.
+    force: bool,
+    /// Whether to number output files that would replace existing ones.
+    auto_number: bool,
+    /// Format of the report written in place of improved pseudocode, if any.
+    report: Option<ReportFormat>,
+    /// Path of the C header file to emit with the suggested function prototype, if any.
+    emit_header: Option<PathBuf>,
+    /// Path of the SARIF log to emit with the findings of the analyses, if any.
+    sarif: Option<PathBuf>,
+    /// Whether to save the map of the applied renames next to each output file.
+    emit_renames: bool,
+    /// Path of the IDAPython script to emit with the suggestions of the analyses, if any.
+    emit_ida: Option<PathBuf>,
+    /// Path of the Ghidra script to emit with the suggestions of the analyses, if any.
+    emit_ghidra: Option<PathBuf>,
+    /// Path of the Binary Ninja script to emit with the suggestions of the analyses, if any.
+    emit_binja: Option<PathBuf>,
+    /// Path of the IDC script to emit with the suggestions of the analyses, if any.
+    emit_idc: Option<PathBuf>,
+    /// Path of the radare2/rizin script to emit with the suggestions of the analyses, if any.
+    emit_r2: Option<PathBuf>,
+    /// Path of the unified diff to emit with the changes to the input files, if any.
+    emit_patch: Option<PathBuf>,
+    /// Directory that file names in the unified diff are relative to (default: the current
+    /// directory).
+    patch_root: Option<PathBuf>,
+    /// Whether to print a diff from each input file to its improved pseudocode.
+    diff: bool,
+    /// Address of the analyzed function, if known.
+    address: Option<u64>,
+    /// Whether to stop at the first failure when analyzing multiple files.
+    fail_fast: bool,
+    /// Whether to analyze each function in the input files separately.
+    chunk: bool,
+    /// Whether to analyze all functions in the input files with a single prompt.
+    joint: bool,
+    /// Name (or address suffix) of the only function to analyze in the input files, if any.
+    function: Option<String>,
+    /// Number of lines before the selected function sent as context.
+    function_context: usize,
+    /// Extra context appended to the prompt.
+    context: AnalysisContext,
+    /// Policy that analysis results are checked against.
+    quality: QualityPolicy,
+    /// Whether analysis results that do not comply with the quality policy are failures.
+    strict: bool,
+    /// Format of the input files.
+    input_format: InputFormat,
+    /// Path of the state file that records the files analyzed in a batch, if any.
+    state_file: Option<PathBuf>,
+    /// Whether to analyze files again even if the state file records them as up to date.
+    re_analyze: bool,
+    /// Whether the state file of an earlier batch must exist.
+    resume: bool,
+    /// Whether to analyze identical files in a batch separately.
+    no_dedupe: bool,
+    /// Whether to locate the occurrences of the renamed variables in the reports.
+    locations: bool,
+    /// Directory where the index of the files analyzed in a batch is saved, if any.
+    batch_index: Option<PathBuf>,
+    /// Whether to also save the index of a batch as a Markdown table.
+    index_markdown: bool,
+    /// Whether stdout is reserved for machine-readable output.
+    reserve_stdout: bool,
+    /// Whether to print a JSON Lines report of each file as soon as its analysis completes.
+    jsonl: bool,
+    /// Whether to suppress status messages and spinners.
+    quiet: bool,
+    /// When to color status messages.
+    color: ColorChoice,
+    /// Whether to suppress spinners.
+    no_spinner: bool,
+    /// Whether to skip writing output files (dry run).
+    dry_run: bool,
+    /// Whether to print the prompts instead of submitting them (implies a dry run).
+    show_prompt: bool,
+    /// Whether to collect and print timing and token statistics.
+    stats: bool,
+    /// Maximum number of files analyzed concurrently in a batch, if more than one.
+    jobs: Option<NonZeroUsize>,
+    /// Audit log of the run, if any.
+    audit_log: Option<AuditLog>,
+    /// Request to stop a batch gracefully.
+    interrupt: Interrupt,
+    /// History where successful analyses are recorded, if any.
+    history: Option<History>,
+}
+
+impl RunOptions {
+    /// Creates new [`RunOptions`] with default values.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the options used to render the function description.
+    #[must_use]
+    pub const fn comment_options(mut self, comment: CommentOptions) -> Self {
+        self.comment = comment;
+        self
+    }
+
+    /// Sets the [`Oneiromancer`] client used to analyze pseudocode.
+    #[must_use]
+    pub fn client(mut self, client: Oneiromancer) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Switches the client to `model`, returning the updated client (for the `:model` command of
+    /// the REPL).
+    pub(crate) fn switch_model(&mut self, model: String) -> &Oneiromancer {
+        self.client = self.client.clone().model(model);
+        &self.client
+    }
+
+    /// Changes the wrap width of the function description (for the `:width` command of the REPL).
+    pub(crate) fn change_width(&mut self, width: CommentWidth) {
+        self.comment = self.comment.clone().width(width);
+    }
+
+    /// Sets the options that control which suggestions are applied to the output file.
+    #[must_use]
+    pub const fn apply_options(mut self, apply: ApplyOptions) -> Self {
+        self.apply = apply;
+        self
+    }
+
+    /// Sets the provenance fields to annotate the output file with, such as
+    /// `/* generated by oneiromancer 0.9.1 using aidapal on 2026-06-01 via 127.0.0.1 */`.
+    ///
+    /// The annotation is disabled if `fields` is empty (the default).
+    #[must_use]
+    pub fn annotation(mut self, fields: &[AnnotationField]) -> Self {
+        self.annotation = fields.to_vec();
+        self
+    }
+
+    /// Sets whether to write the improved pseudocode to stdout instead of an output file.
+    ///
+    /// In this mode, status messages are sent to stderr, the spinner is suppressed, and no files
+    /// are created.
+    #[must_use]
+    pub const fn stdout(mut self, stdout: bool) -> Self {
+        self.stdout = stdout;
+        self
+    }
+
+    /// Sets the path of the output file (`None` to use the default, i.e., the input file path with an
+    /// `out.c` extension, or stdout if pseudocode is read from stdin).
+    #[must_use]
+    pub fn output(mut self, filepath: Option<PathBuf>) -> Self {
+        self.output = filepath;
+        self
+    }
+
+    /// Sets the directory where output files are saved (`None` to save them next to input files).
+    ///
+    /// Output files mirror the directory tree of input files under [`RunOptions::input_root`], or
+    /// are saved directly in the output directory for input files outside of it.
+    #[must_use]
+    pub fn output_dir(mut self, dirpath: Option<PathBuf>) -> Self {
+        self.output_dir = dirpath;
+        self
+    }
+
+    /// Sets whether to rewrite each input file with its improved pseudocode, instead of saving it
+    /// in a new output file (e.g., for files in a workspace that other tools watch).
+    ///
+    /// The original content is first copied next to the input file, with the [`BACKUP_SUFFIX`]
+    /// appended to its name (e.g., `test.c.bak`), replacing any previous backup. An explicit
+    /// [`RunOptions::output`] path takes precedence, and pseudocode read from stdin is still
+    /// written to stdout.
+    #[must_use]
+    pub const fn in_place(mut self, in_place: bool) -> Self {
+        self.in_place = in_place;
+        self
+    }
+
+    /// Sets whether to replace existing output files. By default, an analysis whose output file
+    /// already exists fails with [`OutputError::OutputExists`], unless the output file was saved
+    /// by an earlier run of a batch with a state file.
+    #[must_use]
+    pub const fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Sets whether to save output files that would replace existing ones with the first free
+    /// number before their extension instead (e.g., `test.out.1.c` if `test.out.c` exists).
+    /// Ignored if [`RunOptions::force`] is set.
+    #[must_use]
+    pub const fn auto_number(mut self, auto_number: bool) -> Self {
+        self.auto_number = auto_number;
+        self
+    }
+
+    /// Sets the base directory of input files, whose tree is mirrored under the output directory.
+    #[must_use]
+    pub fn input_root(mut self, dirpath: Option<PathBuf>) -> Self {
+        self.input_root = dirpath;
+        self
+    }
+
+    /// Sets the extension of output files with improved pseudocode, which replaces the extension of
+    /// input files (`None` to use `out.c`, the default), e.g., `improved.c` for `test.improved.c`.
+    ///
+    /// The extension should have at least two components, as the default does, so that output
+    /// files never replace input files. Reports keep the extension of their format.
+    #[must_use]
+    pub fn output_extension(mut self, extension: Option<String>) -> Self {
+        self.output_extension = extension;
+        self
+    }
+
+    /// Sets the format of the human-readable report written in place of improved pseudocode
+    /// (`None` to write improved pseudocode, the default).
+    ///
+    /// The report holds the description and renames of each analyzed function, followed by the
+    /// improved pseudocode. It is saved with the extension of the format (e.g., `report.md`)
+    /// instead of `out.c`, or in the output file or stdout as usual.
+    #[must_use]
+    pub const fn report(mut self, report: Option<ReportFormat>) -> Self {
+        self.report = report;
+        self
+    }
+
+    /// Sets the path of the C header file to emit with the suggested function prototype (`None`
+    /// to disable the header).
+    #[must_use]
+    pub fn emit_header(mut self, filepath: Option<PathBuf>) -> Self {
+        self.emit_header = filepath;
+        self
+    }
+
+    /// Sets the path of the SARIF 2.1.0 log to emit with the findings of the analyses (`None` to
+    /// disable the log): the description of each function and each variable renaming suggestion,
+    /// located at the corresponding identifier in the input file.
+    #[must_use]
+    pub fn sarif(mut self, filepath: Option<PathBuf>) -> Self {
+        self.sarif = filepath;
+        self
+    }
+
+    /// Sets whether to save a [`RenameMap`] of the variable renames applied to each output file
+    /// next to it, with the `.renames.json` extension (e.g., `test.out.renames.json`), so that
+    /// the renames can be undone later with [`undo_renames`]. No rename map is saved for improved
+    /// pseudocode written to stdout.
+    #[must_use]
+    pub const fn emit_renames(mut self, emit_renames: bool) -> Self {
+        self.emit_renames = emit_renames;
+        self
+    }
+
+    /// Sets the path of the IDAPython script to emit with the suggestions of the analyses (`None`
+    /// to disable the script): when run in IDA, it renames each analyzed function, sets its
+    /// comment, and renames its local variables, reporting the changes that fail.
+    ///
+    /// Functions are located by the address set with [`RunOptions::address`], by the address in
+    /// IDA-style default names (e.g., `sub_401000` or `loc_401A2F`), or by their original name.
+    #[must_use]
+    pub fn emit_ida(mut self, filepath: Option<PathBuf>) -> Self {
+        self.emit_ida = filepath;
+        self
+    }
+
+    /// Sets the path of the Ghidra Python script to emit with the suggestions of the analyses
+    /// (`None` to disable the script): when run in Ghidra, it sets the plate comment of each
+    /// analyzed function, renames it, and renames its local variables through the decompiler,
+    /// reporting the changes that fail.
+    ///
+    /// Functions are located as with [`RunOptions::emit_ida`].
+    #[must_use]
+    pub fn emit_ghidra(mut self, filepath: Option<PathBuf>) -> Self {
+        self.emit_ghidra = filepath;
+        self
+    }
+
+    /// Sets the path of the Binary Ninja Python script to emit with the suggestions of the
+    /// analyses (`None` to disable the script): when run in Binary Ninja, it renames each analyzed
+    /// function, sets its comment, and renames the variables that it finds by their current name,
+    /// reporting the changes that fail.
+    ///
+    /// Functions are located as with [`RunOptions::emit_ida`].
+    #[must_use]
+    pub fn emit_binja(mut self, filepath: Option<PathBuf>) -> Self {
+        self.emit_binja = filepath;
+        self
+    }
+
+    /// Sets the path of the IDC script to emit with the suggestions of the analyses (`None` to
+    /// disable the script), for IDA installs without Python: it renames each analyzed function and
+    /// sets its comment, and lists the variable renames in comments for manual application.
+    ///
+    /// Functions are located as with [`RunOptions::emit_ida`], also parsing `loc_` names.
+    #[must_use]
+    pub fn emit_idc(mut self, filepath: Option<PathBuf>) -> Self {
+        self.emit_idc = filepath;
+        self
+    }
+
+    /// Sets the path of the radare2/rizin script to emit with the suggestions of the analyses
+    /// (`None` to disable the script): when run in r2 or rizin, it renames each analyzed function,
+    /// sets its comment, and renames its variables.
+    ///
+    /// Functions are located only by address: the one set with [`RunOptions::address`], or the one
+    /// in default names such as `sub_401000` or `fcn.00401000`.
+    #[must_use]
+    pub fn emit_r2(mut self, filepath: Option<PathBuf>) -> Self {
+        self.emit_r2 = filepath;
+        self
+    }
+
+    /// Sets the path of the unified diff to emit with the changes from each input file to its
+    /// improved pseudocode (`None` to disable the patch), to be reviewed and applied with
+    /// `git apply` or `patch -p1`. The patches of all files in a batch are concatenated.
+    #[must_use]
+    pub fn emit_patch(mut self, filepath: Option<PathBuf>) -> Self {
+        self.emit_patch = filepath;
+        self
+    }
+
+    /// Sets the directory that the `a/` and `b/` file names in the unified diff are relative to
+    /// (`None` for the current directory, the default). Input files must be within it.
+    #[must_use]
+    pub fn patch_root(mut self, dirpath: Option<PathBuf>) -> Self {
+        self.patch_root = dirpath;
+        self
+    }
+
+    /// Sets whether to print a unified diff from each input file to its improved pseudocode, to
+    /// review the changes on the terminal (e.g., in a dry run before saving them). Changed lines
+    /// are colored as set with [`RunOptions::color`].
+    #[must_use]
+    pub const fn diff(mut self, diff: bool) -> Self {
+        self.diff = diff;
+        self
+    }
+
+    /// Sets the address of the function analyzed as a whole (`None` if unknown, the default), for
+    /// the disassembler scripts.
+    #[must_use]
+    pub const fn address(mut self, address: Option<u64>) -> Self {
+        self.address = address;
+        self
+    }
+
+    /// Sets whether to stop at the first failure when analyzing multiple files with [`run_all`].
+    #[must_use]
+    pub const fn fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    /// Sets whether to split input files into functions and analyze each function separately
+    /// (e.g., for files that exceed the context window of the model).
+    ///
+    /// Each function gets its own description and renames, text between functions is preserved,
+    /// and functions whose analysis fails are left untouched.
+    #[must_use]
+    pub const fn chunk(mut self, chunk: bool) -> Self {
+        self.chunk = chunk;
+        self
+    }
+
+    /// Sets whether to split input files into functions and analyze all of them with a single
+    /// prompt, asking for an array of per-function results (e.g., for files with several small
+    /// helpers, where one request per function would be wasteful).
+    ///
+    /// Results are applied as in [`RunOptions::chunk`] mode: functions that the response does not
+    /// cover are left untouched with a warning, and results for functions that are not in the
+    /// file are ignored with a warning. Ignored in [`RunOptions::chunk`] mode.
+    #[must_use]
+    pub const fn joint(mut self, joint: bool) -> Self {
+        self.joint = joint;
+        self
+    }
+
+    /// Sets the only function to analyze in each input file, by name (e.g., `parse_packet`) or
+    /// address suffix (e.g., `@0x401A2F`, see [`select_function`]), or `None` to analyze whole
+    /// files (the default).
+    ///
+    /// Only the definition of the function is submitted for analysis, and the description and
+    /// renames are applied only within it, while the rest of the file is written unchanged. The
+    /// analysis fails if the function is not found. Takes precedence over [`RunOptions::chunk`].
+    #[must_use]
+    pub fn function(mut self, function: Option<String>) -> Self {
+        self.function = function;
+        self
+    }
+
+    /// Sets the number of lines before the function selected with [`RunOptions::function`] that
+    /// are sent to the LLM as context (none by default).
+    #[must_use]
+    pub const fn function_context(mut self, lines: usize) -> Self {
+        self.function_context = lines;
+        self
+    }
+
+    /// Sets extra context (e.g., free-form notes) appended to the prompt of each analysis.
+    #[must_use]
+    pub fn context(mut self, context: AnalysisContext) -> Self {
+        self.context = context;
+        self
+    }
+
+    /// Sets the policy that analysis results are checked against (see [`QualityPolicy`] for the
+    /// default thresholds).
+    #[must_use]
+    pub const fn quality(mut self, policy: QualityPolicy) -> Self {
+        self.quality = policy;
+        self
+    }
+
+    /// Sets whether analysis results that do not comply with the [`RunOptions::quality`] policy
+    /// fail with [`OneiromancerError::LowQualityResult`] instead of being applied with a warning
+    /// (the default). In [`RunOptions::chunk`] mode, only the offending functions fail.
+    #[must_use]
+    pub const fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Sets the format of the input files (e.g., aidapal JSON payloads, whose strings and callee
+    /// prototypes are added to the context). By default, the format is detected from the extension.
+    #[must_use]
+    pub const fn input_format(mut self, input_format: InputFormat) -> Self {
+        self.input_format = input_format;
+        self
+    }
+
+    /// Sets the path of the state file that records the files analyzed in a batch (`None` to
+    /// disable state tracking).
+    ///
+    /// Each successful analysis is appended to the state file right away, together with the hash
+    /// of the input file and the model. Files whose content was already analyzed with the same
+    /// model are skipped, so that interrupted batches can be resumed, unless their output file (or
+    /// their rename map, if emitted) was deleted since. Skipped files do not contribute prototypes
+    /// to the C header.
+    #[must_use]
+    pub fn state_file(mut self, filepath: Option<PathBuf>) -> Self {
+        self.state_file = filepath;
+        self
+    }
+
+    /// Sets whether to analyze files again even if the state file records them as up to date,
+    /// replacing existing output files.
+    #[must_use]
+    pub const fn re_analyze(mut self, re_analyze: bool) -> Self {
+        self.re_analyze = re_analyze;
+        self
+    }
+
+    /// Sets whether the batch resumes an earlier, interrupted one: the state file set with
+    /// [`RunOptions::state_file`] must then exist, so that a mistyped output directory does not
+    /// silently start the batch from scratch.
+    ///
+    /// Files are skipped based on the state file whether or not this is set, and the number of
+    /// skipped files is reported when the batch starts.
+    #[must_use]
+    pub const fn resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    /// Sets whether to analyze identical inputs in a batch only once (the default).
+    ///
+    /// Prompts are memoized for the duration of the batch, with the same key as the
+    /// [`ResponseCache`], so that the results of a file are reused for later files with the same
+    /// content (whose output files are written as usual) without querying the model again. Such
+    /// files are listed in [`RunSummary::deduplicated`]. Single files are never deduplicated.
+    #[must_use]
+    pub const fn dedupe(mut self, dedupe: bool) -> Self {
+        self.no_dedupe = !dedupe;
+        self
+    }
+
+    /// Sets the directory where the index of the files analyzed in a batch is saved as
+    /// [`BATCH_REPORT_FILENAME`] (`None` to disable the index, the default).
+    ///
+    /// The index lists the outcome of each file (see [`BatchIndex`]), and is saved again as each
+    /// analysis completes, so that interrupted batches leave a partial index. Input paths are
+    /// recorded relative to [`RunOptions::input_root`], and output paths relative to the index
+    /// directory, when they are within them. [`run_haruspex`] and [`run_archive`] save the index
+    /// in their own output directory instead.
+    #[must_use]
+    pub fn batch_index(mut self, dirpath: Option<PathBuf>) -> Self {
+        self.batch_index = dirpath;
+        self
+    }
+
+    /// Sets whether to also save the index of a batch as a Markdown table in
+    /// [`BATCH_INDEX_FILENAME`], with links to the output files.
+    #[must_use]
+    pub const fn index_markdown(mut self, index_markdown: bool) -> Self {
+        self.index_markdown = index_markdown;
+        self
+    }
+
+    /// Sets whether stdout is reserved for machine-readable output (e.g., a JSON report), in which
+    /// case all status messages are sent to stderr, spinners are suppressed, and dry runs do not
+    /// print the improved pseudocode of stdin to stdout.
+    #[must_use]
+    pub const fn reserve_stdout(mut self, reserve_stdout: bool) -> Self {
+        self.reserve_stdout = reserve_stdout;
+        self
+    }
+
+    /// Sets whether [`run_all`] and [`run_haruspex`] print an [`AnalysisReport`] of each file to
+    /// stdout as a line of JSON, as soon as its analysis completes (implies
+    /// [`RunOptions::reserve_stdout`]).
+    ///
+    /// Each line is flushed right away, so that downstream consumers can process reports
+    /// incrementally, and interrupted runs leave a partial log of the completed analyses.
+    #[must_use]
+    pub const fn jsonl(mut self, jsonl: bool) -> Self {
+        self.jsonl = jsonl;
+        self
+    }
+
+    /// Sets whether to include in each [`AnalysisReport`] the locations of the occurrences of the
+    /// original variable names replaced by the renames, as found by [`locate_renames`] (see
+    /// [`AnalysisReport::locations`]), e.g., to drive editor integrations.
+    #[must_use]
+    pub const fn locations(mut self, locations: bool) -> Self {
+        self.locations = locations;
+        self
+    }
+
+    /// Sets whether to suppress status messages, analysis results printed on the terminal, and
+    /// spinners. Errors and warnings are still emitted through the `log` facade.
+    #[must_use]
+    pub const fn quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Sets when to color the markers of status messages and the renaming suggestions printed on
+    /// the terminal. Improved pseudocode and JSON reports are never colored.
+    #[must_use]
+    pub const fn color(mut self, color: ColorChoice) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Sets whether to show a spinner while querying the LLM (the default). Spinners are always
+    /// suppressed when stdout is not a terminal or is reserved for program output, and replaced
+    /// by a single status line.
+    #[must_use]
+    pub const fn spinner(mut self, spinner: bool) -> Self {
+        self.no_spinner = !spinner;
+        self
+    }
+
+    /// Sets whether to write output files (the default). When disabled (i.e., in a dry run), the
+    /// analysis results are printed as usual, but improved pseudocode is not saved, and no C
+    /// headers, state file entries, or haruspex indexes are written. Improved pseudocode that
+    /// would go to stdout is still written to stdout.
+    #[must_use]
+    pub const fn write_output(mut self, write_output: bool) -> Self {
+        self.dry_run = !write_output;
+        self
+    }
+
+    /// Sets whether to print the prompts that would be submitted to the model for each input
+    /// file, and the output files that would be saved, instead of analyzing it. The server is never
+    /// contacted, which helps debug prompt or content issues offline. Enabling it implies a dry
+    /// run (see [`RunOptions::write_output`]).
+    #[must_use]
+    pub const fn show_prompt(mut self, show_prompt: bool) -> Self {
+        self.show_prompt = show_prompt;
+        if show_prompt {
+            self.dry_run = true;
+        }
+        self
+    }
+
+    /// Sets whether to collect timing and token statistics of each analysis (included in
+    /// [`AnalysisReport`]s), and print a block of statistics at the end of the run, aggregated over
+    /// all analyzed files. Statistics not reported by the Ollama API are omitted.
+    #[must_use]
+    pub const fn stats(mut self, stats: bool) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// Sets the maximum number of files analyzed concurrently by [`run_all`] and [`run_haruspex`]
+    /// (one by default).
+    ///
+    /// With concurrent jobs, the detailed status messages of each file are replaced by a single
+    /// line when its analysis completes, and JSON Lines reports are printed in the order of
+    /// completion. The returned [`RunSummary`] is still in the order of the input files.
+    #[must_use]
+    pub const fn jobs(mut self, jobs: NonZeroUsize) -> Self {
+        self.jobs = Some(jobs);
+        self
+    }
+
+    /// Sets the audit log where the run records which files were analyzed, with which model and
+    /// options, the rename decisions, durations, and warnings (`None` to disable the audit log,
+    /// the default).
+    #[must_use]
+    pub fn audit_log(mut self, audit_log: Option<AuditLog>) -> Self {
+        self.audit_log = audit_log;
+        self
+    }
+
+    /// Sets the request to stop a batch gracefully (e.g., on Ctrl-C): once requested, no new files
+    /// are analyzed, while the analyses in flight are completed, recorded in the state file, and
+    /// reported as usual.
+    #[must_use]
+    pub fn interrupt(mut self, interrupt: Interrupt) -> Self {
+        self.interrupt = interrupt;
+        self
+    }
+
+    /// Sets the [`History`] where each successful analysis is recorded (`None` to disable the
+    /// history, the default).
+    #[must_use]
+    pub fn history(mut self, history: Option<History>) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Returns the maximum number of files analyzed concurrently in a batch.
+    fn concurrency(&self) -> usize {
+        self.jobs.map_or(1, NonZeroUsize::get)
+    }
+
+    /// Returns the directory where the batch index is saved, or `None` if disabled or in dry run
+    /// mode.
+    fn index_dir(&self) -> Option<&Path> {
+        self.batch_index.as_deref().filter(|_| !self.dry_run)
+    }
+
+    /// Returns the [`Reporter`] of status messages, with stdout reserved for program output if
+    /// `stdout_reserved` is set, and spinners enabled if `spinner` is set (and not suppressed).
+    pub(crate) fn reporter(&self, stdout_reserved: bool, spinner: bool) -> Reporter {
+        Reporter::new(stdout_reserved, spinner && !self.no_spinner)
+            .quiet(self.quiet)
+            .color(self.color)
+    }
+
+    /// Returns `true` if stdout is not available for status messages while analyzing the
+    /// `filepath` input file.
+    fn stdout_reserved(&self, filepath: &Path) -> bool {
+        self.reserve_stdout || self.jsonl || self.outfilepath(filepath).is_none()
+    }
+
+    /// Returns the path of the output file for the `filepath` input file, or `None` if improved
+    /// pseudocode is written to stdout.
+    fn outfilepath(&self, filepath: &Path) -> Option<PathBuf> {
+        match (self.output.as_deref(), self.output_dir.as_deref()) {
+            _ if self.stdout => None,
+            (Some(path), _) => Some(path.to_path_buf()),
+            _ if self.in_place && filepath != Path::new(STDIN_FILEPATH) => {
+                Some(filepath.to_path_buf())
+            }
+            (None, Some(dir)) if filepath != Path::new(STDIN_FILEPATH) => {
+                let relative = self
+                    .input_root
+                    .as_deref()
+                    .and_then(|root| filepath.strip_prefix(root).ok())
+                    .or_else(|| filepath.file_name().map(Path::new))
+                    .unwrap_or(filepath);
+                Some(dir.join(relative).with_extension(self.outfile_extension()))
+            }
+            (None, _) if filepath == Path::new(STDIN_FILEPATH) => None,
+            (None, _) => Some(filepath.with_extension(self.outfile_extension())),
+        }
+    }
+
+    /// Returns `true` if the output file of the `filepath` input file and, if emitted, its rename map
+    /// exist (or if improved pseudocode is written to stdout), so that the file can be skipped.
+    fn outputs_exist(&self, filepath: &Path) -> bool {
+        self.outfilepath(filepath).is_none_or(|path| {
+            path.exists() && (!self.emit_renames || path.with_extension(RENAMES_EXTENSION).exists())
+        })
+    }
+
+    /// Returns the extension of output files, replacing the extension of input files.
+    fn outfile_extension(&self) -> &str {
+        if let Some(format) = self.report {
+            return format.extension();
+        }
+        self.output_extension
+            .as_deref()
+            .map_or("out.c", |extension| extension.trim_start_matches('.'))
+    }
+
+    /// Returns the description of the output, for status messages.
+    fn output_description(&self) -> &'static str {
+        self.report
+            .map_or("improved pseudocode", ReportFormat::description)
+    }
+
+    /// Returns the paths of the disassembler scripts to emit, with the corresponding emitters.
+    fn script_emitters(&self) -> [(Option<&Path>, &'static dyn ScriptEmitter); 5] {
+        [
+            (self.emit_ida.as_deref(), &IdaScript),
+            (self.emit_idc.as_deref(), &IdcScript),
+            (self.emit_ghidra.as_deref(), &GhidraScript),
+            (self.emit_binja.as_deref(), &BinjaScript),
+            (self.emit_r2.as_deref(), &R2Script),
+        ]
+    }
+}
+
+/// Outcome of the analysis of multiple pseudocode files with [`run_all`].
+#[derive(Debug, Default)]
+pub struct RunSummary {
+    /// Files that were analyzed successfully.
+    succeeded: Vec<PathBuf>,
+    /// Files whose analysis failed, with the corresponding errors.
+    failed: Vec<(PathBuf, anyhow::Error)>,
+    /// Files that were skipped because they are already up to date.
+    skipped: Vec<PathBuf>,
+    /// Files analyzed successfully that reused the results of an identical earlier file.
+    deduplicated: Vec<PathBuf>,
+    /// Files that were not analyzed because the batch was interrupted.
+    interrupted: Vec<PathBuf>,
+}
+
+impl RunSummary {
+    /// Returns the files that were analyzed successfully.
+    #[must_use]
+    pub fn succeeded(&self) -> &[PathBuf] {
+        &self.succeeded
+    }
+
+    /// Returns the files whose analysis failed, with the corresponding errors.
+    #[must_use]
+    pub fn failed(&self) -> &[(PathBuf, anyhow::Error)] {
+        &self.failed
+    }
+
+    /// Returns the files that were skipped because the state file records them as up to date.
+    #[must_use]
+    pub fn skipped(&self) -> &[PathBuf] {
+        &self.skipped
+    }
+
+    /// Returns the files analyzed successfully (also listed in [`RunSummary::succeeded`]) that
+    /// reused the results of an identical earlier file in the batch.
+    #[must_use]
+    pub fn deduplicated(&self) -> &[PathBuf] {
+        &self.deduplicated
+    }
+
+    /// Returns the files that were not analyzed because the batch was interrupted.
+    #[must_use]
+    pub fn interrupted(&self) -> &[PathBuf] {
+        &self.interrupted
+    }
+
+    /// Returns `true` if the batch was interrupted before all files were analyzed.
+    #[must_use]
+    pub const fn is_interrupted(&self) -> bool {
+        !self.interrupted.is_empty()
+    }
+
+    /// Returns the number of files that were processed (i.e., analyzed or failed).
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.succeeded.len().saturating_add(self.failed.len())
+    }
+
+    /// Returns `true` if no files were processed.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if no analysis failed.
+    #[must_use]
+    pub const fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    /// Replaces the path of each file with the result of `map`.
+    fn map_paths(self, map: impl Fn(&Path) -> PathBuf) -> Self {
+        Self {
+            succeeded: self.succeeded.iter().map(|path| map(path)).collect(),
+            failed: self
+                .failed
+                .into_iter()
+                .map(|(path, err)| (map(&path), err))
+                .collect(),
+            skipped: self.skipped.iter().map(|path| map(path)).collect(),
+            deduplicated: self.deduplicated.iter().map(|path| map(path)).collect(),
+            interrupted: self.interrupted.iter().map(|path| map(path)).collect(),
+        }
+    }
+}
+
+/// Options that control which suggestions are applied to the output file.
+///
+/// All suggestions are still printed on the terminal.
+#[derive(Debug, Clone, Copy)]
+#[expect(
+    clippy::struct_excessive_bools,
+    reason = "apply options are naturally modeled as bools"
+)]
+pub struct ApplyOptions {
+    /// Whether to write the function description to the output file.
+    write_comment: bool,
+    /// Whether to apply variable renaming suggestions to the output file.
+    apply_renames: bool,
+    /// Whether to merge the function description with the previously generated one.
+    merge_comment: bool,
+    /// Whether to rename variables whose names were not generated by the decompiler.
+    rename_user_names: bool,
+    /// What to do with renames whose new name collides with an existing one.
+    collisions: CollisionPolicy,
+}
+
+impl ApplyOptions {
+    /// Creates new [`ApplyOptions`] with default values (apply all suggestions).
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether to write the function description to the output file.
+    #[must_use]
+    pub const fn write_comment(mut self, write_comment: bool) -> Self {
+        self.write_comment = write_comment;
+        self
+    }
+
+    /// Sets whether to apply variable renaming suggestions to the output file.
+    #[must_use]
+    pub const fn apply_renames(mut self, apply_renames: bool) -> Self {
+        self.apply_renames = apply_renames;
+        self
+    }
+
+    /// Sets whether to merge the function description with the one generated by a previous run
+    /// (e.g., when re-analyzing improved pseudocode), instead of adding a new one.
+    ///
+    /// Merged descriptions are delimited by `// oneiromancer:begin` and `// oneiromancer:end`
+    /// sentinels and replace the previously generated descriptions among the comments above the
+    /// function, while other comments (e.g., manually added notes) are preserved. Without merging,
+    /// a previous description identical to the new one is kept instead of being duplicated.
+    #[must_use]
+    pub const fn merge_comment(mut self, merge_comment: bool) -> Self {
+        self.merge_comment = merge_comment;
+        self
+    }
+
+    /// Sets whether to rename variables whose names were assigned by the user rather than
+    /// generated by the decompiler (e.g., `buffer` instead of `v1` in Hex-Rays pseudocode).
+    ///
+    /// By default, such names are protected when the decompiler is known (see
+    /// [`PromptOptions::decompiler`]), and their renaming suggestions are skipped. Without a known
+    /// decompiler, all names are renamed.
+    #[must_use]
+    pub const fn rename_user_names(mut self, rename_user_names: bool) -> Self {
+        self.rename_user_names = rename_user_names;
+        self
+    }
+
+    /// Sets what to do with variable renaming suggestions whose new name already exists as an
+    /// identifier in the pseudocode, or was suggested for another variable, which would merge two
+    /// distinct variables. By default, such suggestions are skipped (see [`CollisionPolicy`]).
+    #[must_use]
+    pub const fn collisions(mut self, policy: CollisionPolicy) -> Self {
+        self.collisions = policy;
+        self
+    }
+}
+
+impl Default for ApplyOptions {
+    fn default() -> Self {
+        Self {
+            write_comment: true,
+            apply_renames: true,
+            merge_comment: false,
+            rename_user_names: false,
+            collisions: CollisionPolicy::Skip,
+        }
+    }
+}
+
+/// Submits pseudocode in the `filepath` file to the local LLM for analysis. Outputs analysis results to
+/// terminal and saves improved pseudocode in `filepath` with an `out.c` extension.
+///
+/// If `filepath` is [`STDIN_FILEPATH`] (i.e., `-`), pseudocode is read from stdin and improved
+/// pseudocode is written to stdout.
+///
+/// # Errors
+///
+/// Returns [`anyhow::Error`] in case something goes wrong with file I/O or analysis.
+pub fn run(filepath: impl AsRef<Path>) -> anyhow::Result<()> {
+    run_with_options(filepath, &RunOptions::default())
+}
+
+/// Same as [`run`], but uses the specified [`RunOptions`].
+///
+/// # Errors
+///
+/// Returns [`anyhow::Error`] in case something goes wrong with file I/O or analysis.
+pub fn run_with_options(filepath: impl AsRef<Path>, options: &RunOptions) -> anyhow::Result<()> {
+    run_with_report(filepath, options).map(drop)
+}
+
+/// Same as [`run_with_options`], but returns an [`AnalysisReport`] with the analysis results,
+/// e.g., to be serialized as JSON.
+///
+/// # Errors
+///
+/// Returns [`anyhow::Error`] in case something goes wrong with file I/O or analysis.
+pub fn run_with_report(
+    filepath: impl AsRef<Path>,
+    options: &RunOptions,
+) -> anyhow::Result<AnalysisReport> {
+    let started = Instant::now();
+    let path = filepath.as_ref();
+    let reporter = options.reporter(options.stdout_reserved(path), false);
+    if let Some(audit_log) = options.audit_log.as_ref() {
+        audit_log.run_started(&options.client, 1, options.dry_run);
+    }
+
+    let result = process_file(path, options, false).and_then(|analyzed| {
+        // Emit a C header with the suggested function prototypes.
+        if let Some(headerpath) = options.emit_header.as_deref() {
+            if analyzed.declarations.is_empty() {
+                anyhow::bail!("Failed to find a function signature in the pseudocode");
+            }
+            write_c_header(headerpath, &analyzed.declarations, options, reporter)?;
+        }
+        if let Some(sarifpath) = options.sarif.as_deref() {
+            write_sarif(sarifpath, &analyzed.findings, options, reporter)?;
+        }
+        write_scripts(&analyzed.suggestions, options, reporter)?;
+        if let Some(patchpath) = options.emit_patch.as_deref() {
+            write_patch(patchpath, &analyzed.patch, options, reporter)?;
+        }
+        Ok(analyzed)
+    });
+    if let Some(audit_log) = options.audit_log.as_ref() {
+        audit_file(audit_log, path, options, &result, started, &[]);
+        audit_log.run_finished(
+            usize::from(result.is_ok()),
+            usize::from(result.is_err()),
+            0,
+            started.elapsed(),
+        );
+    }
+
+    let analyzed = result?;
+    if options.stats {
+        print_stats(
+            [&analyzed.report],
+            &[],
+            analyzed.report.duration(),
+            reporter,
+        );
+    }
+    Ok(analyzed.report)
+}
+
+/// Same as [`run_with_options`], but analyzes all pseudocode files in `filepaths`, in order.
+///
+/// A failure in one file does not stop the analysis of the others, unless
+/// [`RunOptions::fail_fast`] is set. Files already analyzed according to the
+/// [`RunOptions::state_file`] are skipped. If a C header is requested, the prototypes of all
+/// analyzed functions are aggregated into a single header. Returns a [`RunSummary`] with the
+/// outcome of each analysis.
+///
+/// # Errors
+///
+/// Returns [`anyhow::Error`] in case the options are inconsistent with multiple input files or
+/// with [`RunOptions::jsonl`], two input files would be saved as the same output file
+/// ([`OutputError::SharedOutput`], e.g., `foo.c` and `foo.h` without
+/// [`RunOptions::auto_number`]), or the state file or the C header cannot be accessed.
+pub fn run_all(filepaths: &[impl AsRef<Path>], options: &RunOptions) -> anyhow::Result<RunSummary> {
+    if filepaths.len() > 1 && options.output.is_some() {
+        anyhow::bail!("Cannot write the output of multiple input files to a single output file");
+    }
+    if options.jsonl
+        && filepaths
+            .iter()
+            .any(|filepath| options.outfilepath(filepath.as_ref()).is_none())
+    {
+        anyhow::bail!(
+            "Cannot write improved pseudocode to stdout together with JSON Lines reports"
+        );
+    }
+
+    check_shared_outputs(filepaths, options)?;
+
+    let started = Instant::now();
+    if let Some(audit_log) = options.audit_log.as_ref() {
+        audit_log.run_started(&options.client, filepaths.len(), options.dry_run);
+    }
+    let stdout_reserved = filepaths
+        .iter()
+        .any(|filepath| options.stdout_reserved(filepath.as_ref()));
+    let reporter = options.reporter(stdout_reserved, false);
+    let jobs: Vec<(&Path, Cow<'_, RunOptions>)> = filepaths
+        .iter()
+        .map(|filepath| (filepath.as_ref(), Cow::Borrowed(options)))
+        .collect();
+    let mut state = load_state(options)?;
+    let (summary, analyzed) = analyze_batch(&jobs, options, state.as_mut(), reporter);
+    finish_batch(
+        summary,
+        &analyzed,
+        jobs.len() > 1,
+        started,
+        options,
+        reporter,
+    )
+}
+
+/// Checks that no two distinct `filepaths` would be saved as the same output file with `options`
+/// (e.g., `foo.c` and `foo.h`, both saved as `foo.out.c`), unless output files are numbered.
+fn check_shared_outputs(
+    filepaths: &[impl AsRef<Path>],
+    options: &RunOptions,
+) -> Result<(), OutputError> {
+    if options.auto_number {
+        return Ok(());
+    }
+    let mut outputs: BTreeMap<PathBuf, &Path> = BTreeMap::new();
+    for filepath in filepaths.iter().map(AsRef::as_ref) {
+        let Some(outfilepath) = options.outfilepath(filepath) else {
+            continue;
+        };
+        match outputs.get(&outfilepath) {
+            Some(&other) if other != filepath => {
+                return Err(OutputError::SharedOutput(
+                    other.to_path_buf(),
+                    filepath.to_path_buf(),
+                    outfilepath,
+                ));
+            }
+            Some(_) => {}
+            None => {
+                outputs.insert(outfilepath, filepath);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Same as [`run_all`], but analyzes the pseudocode files in the `dirpath` directory written by
+/// [haruspex](https://github.com/0xdea/haruspex), in index order.
+///
+/// Improved pseudocode is saved with the same filenames in the [`IMPROVED_DIRNAME`] directory
+/// inside `dirpath`, together with an [`INDEX_FILENAME`] file that maps original function names
+/// (parsed from the filenames) to the recommended names. Files with unexpected names and empty
+/// files (i.e., failed decompilations) are skipped with a note. The output paths in `options` are
+/// ignored, and the batch index (if enabled with [`RunOptions::batch_index`]) is saved in the
+/// [`IMPROVED_DIRNAME`] directory as well.
+///
+/// # Errors
+///
+/// Returns [`anyhow::Error`] in case the directory cannot be read, contains no pseudocode files,
+/// or the state file, the index, or the C header cannot be accessed.
+pub fn run_haruspex(dirpath: impl AsRef<Path>, options: &RunOptions) -> anyhow::Result<RunSummary> {
+    let started = Instant::now();
+    let path = dirpath.as_ref();
+    let dir = read_haruspex_dir(path)
+        .with_context(|| format!("Failed to read haruspex directory `{}`", path.display()))?;
+    let reporter = options.reporter(false, false);
+    for skipped in dir.skipped() {
+        reporter.line(format_args!(
+            "[!] Skipping `{}`: {}",
+            skipped.0.display(),
+            skipped.1
+        ));
+    }
+    if dir.entries().is_empty() {
+        anyhow::bail!("No haruspex pseudocode files found in `{}`", path.display());
+    }
+    if let Some(audit_log) = options.audit_log.as_ref() {
+        audit_log.run_started(&options.client, dir.entries().len(), options.dry_run);
+    }
+
+    let improved_dir = path.join(IMPROVED_DIRNAME);
+    let jobs: Vec<(&Path, Cow<'_, RunOptions>)> = dir
+        .entries()
+        .iter()
+        .map(|entry| {
+            let outfilepath = entry.path().file_name().map(|filename| {
+                let improved = improved_dir.join(filename);
+                match options.report {
+                    Some(format) => improved.with_extension(format.extension()),
+                    None => improved,
+                }
+            });
+            let file_options = options
+                .clone()
+                .stdout(false)
+                .output_dir(None)
+                .output(outfilepath);
+            (entry.path(), Cow::Owned(file_options))
+        })
+        .collect();
+    let batch_options = options
+        .clone()
+        .batch_index(options.batch_index.as_ref().map(|_| improved_dir.clone()));
+    let mut state = load_state(options)?;
+    let (summary, analyzed) = analyze_batch(&jobs, &batch_options, state.as_mut(), reporter);
+
+    // Map original function names to recommended names.
+    let mapping = dir.entries().iter().filter_map(|entry| {
+        analyzed
+            .iter()
+            .find(|outcome| outcome.0 == entry.path())
+            .map(|outcome| (entry, outcome.1.function_names.as_slice()))
+    });
+    let indexpath = improved_dir.join(INDEX_FILENAME);
+    if options.dry_run {
+        reporter.line(format_args!(
+            "\n[*] Dry run: not saving function name index in `{}`",
+            indexpath.display()
+        ));
+    } else {
+        fs::create_dir_all(&improved_dir)
+            .map_err(|err| OutputError::CreateFailed(improved_dir.clone(), err))?;
+        write_atomically(&indexpath, format_index(mapping).as_bytes(), true)?;
+        reporter.line(format_args!(
+            "\n[+] Function name index saved to `{}`",
+            indexpath.display()
+        ));
+    }
+
+    finish_batch(
+        summary,
+        &analyzed,
+        jobs.len() > 1,
+        started,
+        options,
+        reporter,
+    )
+}
+
+/// Same as [`run_all`], but analyzes the pseudocode files in the `archivepath` archive (`.zip`,
+/// `.tar.gz`, or `.tgz`), selected as described in [`read_archive`] according to `discover`.
+///
+/// The selected files are extracted into a temporary directory and analyzed as a batch. Output
+/// files are saved mirroring the paths in the archive, in the directory set with
+/// [`RunOptions::output_dir`] or [`RunOptions::output`], or by default in the `<NAME>_improved`
+/// directory next to the archive (e.g., `bundle_improved` for `bundle.zip`). If the output path
+/// names an archive instead (e.g., `bundle_improved.zip`), output files are packed into a new
+/// archive of that format. Skipped files are reported with a note, and the paths in the returned
+/// [`RunSummary`] are the paths in the archive, prefixed by `archivepath`. The state file in
+/// `options` is ignored, and the batch index (if enabled with [`RunOptions::batch_index`]) is
+/// saved together with the output files, with the paths in the archive as input paths.
+///
+/// # Errors
+///
+/// Returns [`anyhow::Error`] in case the archive cannot be read, contains an unsafe path or no
+/// pseudocode files, improved pseudocode is written to stdout or in place, or the files cannot be
+/// extracted or the output archive cannot be saved.
+pub fn run_archive(
+    archivepath: impl AsRef<Path>,
+    discover: &DiscoverOptions,
+    options: &RunOptions,
+) -> anyhow::Result<RunSummary> {
+    let path = archivepath.as_ref();
+    if options.stdout {
+        anyhow::bail!("Cannot write the improved pseudocode of an archive to stdout");
+    }
+    if options.in_place {
+        anyhow::bail!("Cannot rewrite the files of an archive in place");
+    }
+    let contents = read_archive(path, discover)
+        .with_context(|| format!("Failed to read archive `{}`", path.display()))?;
+    let reporter = options.reporter(false, false);
+    for skipped in contents.skipped() {
+        reporter.line(format_args!(
+            "[!] Skipping `{}`: {}",
+            path.join(&skipped.0).display(),
+            skipped.1
+        ));
+    }
+    if contents.entries().is_empty() {
+        anyhow::bail!("No pseudocode files found in archive `{}`", path.display());
+    }
+
+    // Extract the selected files, to analyze them as a batch.
+    let inputs = tempfile::Builder::new()
+        .prefix(".oneiromancer-")
+        .tempdir()
+        .context("Failed to create a temporary directory")?;
+    let mut filepaths = Vec::with_capacity(contents.entries().len());
+    for entry in contents.entries() {
+        let filepath = inputs.path().join(entry.path());
+        if let Some(parent) = filepath.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|err| OutputError::CreateFailed(parent.to_path_buf(), err))?;
+        }
+        fs::write(&filepath, entry.code())
+            .map_err(|err| OutputError::WriteFailed(filepath.clone(), err))?;
+        filepaths.push(filepath);
+    }
+
+    // Save output files in a directory, or in a temporary directory to pack into an archive.
+    let target = options
+        .output
+        .clone()
+        .or_else(|| options.output_dir.clone())
+        .unwrap_or_else(|| path.with_file_name(format!("{}_improved", ArchiveFormat::stem(path))));
+    let packed = ArchiveFormat::detect(&target)
+        .map(|format| tempfile::tempdir().map(|dir| (format, dir)))
+        .transpose()
+        .context("Failed to create a temporary directory")?;
+    let output_dir = packed
+        .as_ref()
+        .map_or_else(|| target.clone(), |output| output.1.path().to_path_buf());
+    let batch_options = options
+        .clone()
+        .output(None)
+        .batch_index(options.batch_index.as_ref().map(|_| output_dir.clone()))
+        .output_dir(Some(output_dir))
+        .input_root(Some(inputs.path().to_path_buf()))
+        .state_file(None);
+    let summary = run_all(&filepaths, &batch_options)?;
+
+    if let Some((format, dir)) = packed {
+        if options.dry_run {
+            reporter.line(format_args!(
+                "\n[*] Dry run: not saving output archive `{}`",
+                target.display()
+            ));
+        } else {
+            let archive = pack_dir(dir.path(), format)
+                .with_context(|| format!("Failed to pack output archive `{}`", target.display()))?;
+            write_atomically(&target, &archive, true)?;
+            reporter.line(format_args!(
+                "\n[+] Output archive saved to `{}`",
+                target.display()
+            ));
+        }
+    }
+    Ok(summary
+        .map_paths(|filepath| path.join(filepath.strip_prefix(inputs.path()).unwrap_or(filepath))))
+}
+
+/// Analyzes the functions of the `binarypath` binary or IDA database selected by `selector`,
+/// returning the analysis results keyed by function address.
+///
+/// The functions are decompiled with idalib (see [`decompile_binary`]), and each one is analyzed
+/// with the client and context in `options`. Functions whose analysis fails are left out with a warning. To also save the improved
+/// pseudocode of each function, use [`run_binary`].
+///
+/// # Errors
+///
+/// Returns [`anyhow::Error`] in case the binary cannot be decompiled (with
+/// [`BinaryError::Unsupported`] if oneiromancer was built without the `idalib` feature).
+pub fn analyze_binary(
+    binarypath: impl AsRef<Path>,
+    options: &RunOptions,
+    selector: &FunctionSelector,
+) -> anyhow::Result<BTreeMap<u64, OneiromancerResults>> {
+    let path = binarypath.as_ref();
+    let functions = decompile_binary(path, selector)
+        .with_context(|| format!("Failed to decompile `{}`", path.display()))?;
+    let mut analyzed = BTreeMap::new();
+    for function in functions {
+        match options
+            .client
+            .analyze_code_with_context(function.pseudocode(), &options.context)
+        {
+            Ok(results) => {
+                analyzed.insert(function.address(), results);
+            }
+            Err(err) => log::warn!(
+                "Failed to analyze `{}` at {:#x}: {err}",
+                function.name(),
+                function.address()
+            ),
+        }
+    }
+    Ok(analyzed)
+}
+
+/// Same as [`run_all`], but analyzes the functions of the `binarypath` binary or IDA database
+/// selected by `selector`, decompiled with idalib (see [`decompile_binary`]).
+///
+/// The pseudocode of each function is saved in a temporary directory, named after
+/// [`DecompiledFunction::filename`], and analyzed as a batch. Output files are saved with the same
+/// names in the directory set with [`RunOptions::output_dir`] or [`RunOptions::output`], or by
+/// default in the `<NAME>_improved` directory next to the binary (e.g., `firmware_improved` for
+/// `firmware.bin`), and the paths in the returned [`RunSummary`] are the filenames prefixed by
+/// `binarypath`. The function selected with [`RunOptions::function`] and the state file in
+/// `options` are ignored.
+///
+/// # Errors
+///
+/// Returns [`anyhow::Error`] in case the binary cannot be decompiled (with
+/// [`BinaryError::Unsupported`] if oneiromancer was built without the `idalib` feature), no
+/// functions are decompiled, improved pseudocode is written to stdout or in place, or the
+/// decompiled functions cannot be saved.
+pub fn run_binary(
+    binarypath: impl AsRef<Path>,
+    selector: &FunctionSelector,
+    options: &RunOptions,
+) -> anyhow::Result<RunSummary> {
+    let path = binarypath.as_ref();
+    if options.stdout {
+        anyhow::bail!("Cannot write the improved pseudocode of a binary to stdout");
+    }
+    if options.in_place {
+        anyhow::bail!("Cannot rewrite the functions of a binary in place");
+    }
+    let reporter = options.reporter(false, false);
+    reporter.line(format_args!(
+        "[*] Decompiling `{}` with idalib",
+        path.display()
+    ));
+    let functions = decompile_binary(path, selector)
+        .with_context(|| format!("Failed to decompile `{}`", path.display()))?;
+    if functions.is_empty() {
+        anyhow::bail!("No functions decompiled in `{}`", path.display());
+    }
+    reporter.line(format_args!("[+] Decompiled {} functions", functions.len()));
+
+    // Save the decompiled functions, to analyze them as a batch.
+    let inputs = tempfile::Builder::new()
+        .prefix(".oneiromancer-")
+        .tempdir()
+        .context("Failed to create a temporary directory")?;
+    let mut filepaths = Vec::with_capacity(functions.len());
+    for function in &functions {
+        let filepath = inputs.path().join(function.filename());
+        fs::write(&filepath, function.pseudocode())
+            .map_err(|err| OutputError::WriteFailed(filepath.clone(), err))?;
+        filepaths.push(filepath);
+    }
+
+    let output_dir = options
+        .output
+        .clone()
+        .or_else(|| options.output_dir.clone())
+        .unwrap_or_else(|| {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            path.with_file_name(format!("{stem}_improved"))
+        });
+    let batch_options = options
+        .clone()
+        .output(None)
+        .function(None)
+        .batch_index(options.batch_index.as_ref().map(|_| output_dir.clone()))
+        .output_dir(Some(output_dir))
+        .input_root(Some(inputs.path().to_path_buf()))
+        .state_file(None);
+    let summary = run_all(&filepaths, &batch_options)?;
+    Ok(summary
+        .map_paths(|filepath| path.join(filepath.strip_prefix(inputs.path()).unwrap_or(filepath))))
+}
+
+/// Loads the state file in `options`, if any.
+///
+/// # Errors
+///
+/// Returns [`anyhow::Error`] in case the state file cannot be read, or [`RunOptions::resume`] is
+/// set and there is no state file to resume from.
+fn load_state(options: &RunOptions) -> anyhow::Result<Option<RunState>> {
+    if options.resume {
+        match options.state_file.as_deref() {
+            Some(filepath) if !filepath.is_file() => anyhow::bail!(
+                "Cannot resume: state file `{}` of an earlier batch not found",
+                filepath.display()
+            ),
+            Some(_) => (),
+            None => anyhow::bail!("Cannot resume: no state file is used for this run"),
+        }
+    }
+    options
+        .state_file
+        .as_deref()
+        .map(|filepath| {
+            RunState::load(filepath)
+                .with_context(|| format!("Failed to load state from `{}`", filepath.display()))
+        })
+        .transpose()
+}
+
+/// Analyzes each file in `jobs` with the corresponding options, stopping at the first failure if
+/// [`RunOptions::fail_fast`] is set in `batch_options`. Up to [`RunOptions::jobs`] files are
+/// analyzed concurrently, or all of them through a single job when a progress bar is shown. Files
+/// recorded as up to date in `state` are skipped, and successful analyses are recorded as they
+/// complete (and printed as JSON Lines if [`RunOptions::jsonl`] is set). No new files are analyzed
+/// once [`RunOptions::interrupt`] is requested. Returns the summary and the outcome of each successful or skipped analysis, in the order
+/// of `jobs` regardless of the order of completion.
+fn analyze_batch<'a>(
+    jobs: &[(&'a Path, Cow<'_, RunOptions>)],
+    batch_options: &RunOptions,
+    mut state: Option<&mut RunState>,
+    reporter: Reporter,
+) -> (RunSummary, Vec<(&'a Path, Analyzed)>) {
+    // Decide which files are up to date or duplicates before any analysis starts.
+    let plans = plan_batch(jobs, batch_options, state.as_deref());
+    report_resumed(&plans, reporter);
+    let progress = reporter.progress(plans.len());
+    let mut outcomes = Vec::with_capacity(plans.len());
+    let index_dir = batch_options.index_dir();
+    let mut batch_index = BatchIndex::default();
+    let mut complete = |index: usize, plan: &Plan<'a, '_>, result, started: Instant| {
+        let proceed = complete_job(
+            plan,
+            &result,
+            started,
+            batch_options,
+            state.as_deref_mut(),
+            reporter,
+        );
+        if let Some(dir) = index_dir {
+            batch_index.push(index_entry(plan, &result, started, batch_options, dir));
+            if let Err(err) = write_batch_index(dir, &batch_index, batch_options) {
+                log::warn!("Failed to update batch index: {err:#}");
+            }
+        }
+        outcomes.push((index, result));
+        proceed
+    };
+
+    let workers = batch_options.concurrency();
+    if workers == 1 && !progress.has_bar() {
+        for (index, plan) in plans.iter().enumerate() {
+            if batch_options.interrupt.is_requested() {
+                break;
+            }
+            if plans.len() > 1 {
+                reporter.line(format_args!(
+                    "\n[*] File {} of {}: `{}`",
+                    index.saturating_add(1),
+                    plans.len(),
+                    plan.filepath.display()
+                ));
+            }
+            let started = Instant::now();
+            let result = plan.skip(reporter).map_or_else(
+                || process_file(plan.filepath, &plan.options, plan.overwrite),
+                Ok,
+            );
+            if !progress.complete(result.is_err(), || complete(index, plan, result, started)) {
+                break;
+            }
+        }
+    } else {
+        analyze_concurrently(
+            &plans,
+            workers,
+            reporter,
+            &progress,
+            &batch_options.interrupt,
+            &mut complete,
+        );
+    }
+    progress.finish();
+    finish_batch_index(
+        index_dir,
+        batch_index,
+        outcomes.len() == plans.len(),
+        batch_options,
+        reporter,
+    );
+
+    // Present the outcomes in the order of the input files.
+    outcomes.sort_by_key(|outcome| outcome.0);
+    let mut summary = RunSummary::default();
+    if batch_options.interrupt.is_requested() {
+        summary.interrupted = plans
+            .iter()
+            .enumerate()
+            .filter(|entry| {
+                outcomes
+                    .binary_search_by_key(&entry.0, |outcome| outcome.0)
+                    .is_err()
+            })
+            .map(|entry| entry.1.filepath.to_path_buf())
+            .collect();
+    }
+    let mut analyzed = Vec::new();
+    for (index, result) in outcomes {
+        let Some(filepath) = plans.get(index).map(|plan| plan.filepath) else {
+            continue;
+        };
+        match result {
+            Ok(outcome) if outcome.report.is_skipped() => {
+                summary.skipped.push(filepath.to_path_buf());
+                analyzed.push((filepath, outcome));
+            }
+            Ok(outcome) => {
+                if plans.get(index).is_some_and(|plan| plan.duplicate) {
+                    summary.deduplicated.push(filepath.to_path_buf());
+                }
+                summary.succeeded.push(filepath.to_path_buf());
+                analyzed.push((filepath, outcome));
+            }
+            Err(err) => summary.failed.push((filepath.to_path_buf(), err)),
+        }
+    }
+    (summary, analyzed)
+}
+
+/// Reports how many of the files planned in `plans` were already analyzed by an earlier batch,
+/// if any.
+fn report_resumed(plans: &[Plan<'_, '_>], reporter: Reporter) {
+    let resumed = plans
+        .iter()
+        .filter(|plan| plan.up_to_date.is_some())
+        .count();
+    if resumed > 0 {
+        reporter.line(format_args!(
+            "[*] Resuming batch: {resumed} of {} files already analyzed",
+            plans.len()
+        ));
+    }
+}
+
+/// Plans the analysis of each file in `jobs`, based on `state`. Unless disabled in
+/// `batch_options`, the files of a batch share the results of identical prompts, and files with
+/// the same content as an earlier planned file are marked as duplicates.
+fn plan_batch<'a, 'o>(
+    jobs: &'o [(&'a Path, Cow<'_, RunOptions>)],
+    batch_options: &RunOptions,
+    state: Option<&RunState>,
+) -> Vec<Plan<'a, 'o>> {
+    let memo = (!batch_options.no_dedupe && jobs.len() > 1).then(ResponseMemo::default);
+    let mut plans: Vec<Plan<'a, 'o>> = jobs
+        .iter()
+        .map(|job| Plan::new(job.0, &job.1, batch_options, state, memo.as_ref()))
+        .collect();
+    if memo.is_some() {
+        let mut seen = HashSet::new();
+        for plan in plans.iter_mut().filter(|plan| plan.up_to_date.is_none()) {
+            plan.duplicate = plan
+                .hash
+                .as_ref()
+                .is_some_and(|hash| !seen.insert(hash.clone()));
+        }
+    }
+    plans
+}
+
+/// Analyzes the files planned in `plans` with up to `workers` concurrent jobs, calling
+/// `complete` on the current thread as each analysis completes, and stopping if it returns
+/// `false`. Skipped files are reported right away, and the status messages of the others are
+/// suppressed, so that the output of different files does not interleave. Status messages are
+/// printed above the progress bar of `progress`, if any. No new analyses are started once
+/// `interrupt` is requested.
+fn analyze_concurrently<'p, 'a: 'p, 'o: 'p>(
+    plans: &'p [Plan<'a, 'o>],
+    workers: usize,
+    reporter: Reporter,
+    progress: &Progress,
+    interrupt: &Interrupt,
+    complete: &mut impl FnMut(usize, &'p Plan<'a, 'o>, anyhow::Result<Analyzed>, Instant) -> bool,
+) {
+    let mut pending = Vec::with_capacity(plans.len());
+    for (index, plan) in plans.iter().enumerate() {
+        match progress.suspend(|| plan.skip(reporter)) {
+            Some(outcome) => {
+                _ = progress.complete(false, || complete(index, plan, Ok(outcome), Instant::now()));
+            }
+            None => pending.push((index, plan)),
+        }
+    }
+    if !pending.is_empty() {
+        progress.suspend(|| {
+            if workers == 1 {
+                reporter.line(format_args!("\n[*] Analyzing {} files", pending.len()));
+            } else {
+                reporter.line(format_args!(
+                    "\n[*] Analyzing {} files with up to {workers} concurrent jobs",
+                    pending.len()
+                ));
+            }
+        });
+    }
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..workers.min(pending.len()) {
+            let (worker_sender, next_job, stopped, queue) =
+                (sender.clone(), &next, &stop, &pending);
+            scope.spawn(move || {
+                while !stopped.load(Ordering::Relaxed) && !interrupt.is_requested() {
+                    let Some(&(index, plan)) = queue.get(next_job.fetch_add(1, Ordering::Relaxed))
+                    else {
+                        break;
+                    };
+                    progress.start(plan.filepath);
+                    let started = Instant::now();
+                    let options = (*plan.options).clone().quiet(true);
+                    let result = process_file(plan.filepath, &options, plan.overwrite);
+                    if worker_sender.send((index, plan, result, started)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        for (done, (index, plan, result, started)) in receiver.into_iter().enumerate() {
+            let (marker, verb) = if result.is_ok() {
+                ("[+]", "Analyzed")
+            } else {
+                ("[!]", "Failed to analyze")
+            };
+            let proceed = progress.complete(result.is_err(), || {
+                reporter.line(format_args!(
+                    "{marker} {verb} `{}` ({} of {})",
+                    plan.filepath.display(),
+                    done.saturating_add(1),
+                    pending.len()
+                ));
+                complete(index, plan, result, started)
+            });
+            if !proceed {
+                stop.store(true, Ordering::Relaxed);
+            }
+        }
+    });
+}
+
+/// Plan for the analysis of a file in a batch, decided before any analysis starts.
+struct Plan<'a, 'o> {
+    /// Path of the input file.
+    filepath: &'a Path,
+    /// Options used to analyze the file.
+    options: Cow<'o, RunOptions>,
+    /// Hash of the content of the input file, if state is tracked or duplicates are detected.
+    hash: Option<String>,
+    /// Recommended function names recorded in the state, if the file is up to date.
+    up_to_date: Option<Vec<String>>,
+    /// Whether an earlier file in the batch has the same content.
+    duplicate: bool,
+    /// Whether an existing output file can be replaced.
+    overwrite: bool,
+}
+
+impl<'a, 'o> Plan<'a, 'o> {
+    /// Plans the analysis of the `filepath` file with `options`, based on `state`. With `memo`,
+    /// the results of identical prompts in the batch are shared.
+    fn new(
+        filepath: &'a Path,
+        options: &'o RunOptions,
+        batch_options: &RunOptions,
+        state: Option<&RunState>,
+        memo: Option<&ResponseMemo>,
+    ) -> Self {
+        // Hash the input file, unless neither state nor duplicates are tracked or pseudocode is
+        // read from stdin.
+        let model = options.client.configured_model();
+        let hash = (state.is_some() || memo.is_some())
+            .then_some(filepath)
+            .filter(|path| *path != Path::new(STDIN_FILEPATH))
+            .and_then(|path| fs::read(path).ok())
+            .map(|content| content_hash(&content));
+        let up_to_date = state
+            .zip(hash.as_deref())
+            .filter(|_| !batch_options.re_analyze)
+            .and_then(|(recorded, content)| recorded.up_to_date(filepath, content, model))
+            .filter(|_| options.outputs_exist(filepath))
+            .map(<[String]>::to_vec);
+
+        // Output files of previous analyses recorded in the state can be replaced.
+        let overwrite =
+            batch_options.re_analyze || state.is_some_and(|recorded| recorded.contains(filepath));
+        Self {
+            filepath,
+            options: memo.map_or(Cow::Borrowed(options), |shared| {
+                let client = options.client.clone().memo(Some(shared.clone()));
+                Cow::Owned(options.clone().client(client))
+            }),
+            hash,
+            up_to_date,
+            duplicate: false,
+            overwrite,
+        }
+    }
+
+    /// Returns the outcome of skipping the file, if it is up to date.
+    fn skip(&self, reporter: Reporter) -> Option<Analyzed> {
+        let names = self.up_to_date.as_ref()?;
+        let model = self.options.client.configured_model();
+        reporter.line(format_args!(
+            "[*] Skipping `{}`: already analyzed with `{model}`",
+            self.filepath.display()
+        ));
+        Some(Analyzed {
+            declarations: Vec::new(),
+            function_names: names.clone(),
+            renames: Vec::new(),
+            findings: Vec::new(),
+            suggestions: Vec::new(),
+            patch: String::new(),
+            warnings: Vec::new(),
+            report: AnalysisReport::skipped(self.filepath, model),
+        })
+    }
+}
+
+/// Completes the analysis of a file in a batch as planned in `plan` and `started` earlier, by
+/// recording the successful outcome in `state`, printing the JSON Lines report, logging errors,
+/// and recording the outcome in the audit log. Returns `false` if the batch should stop.
+fn complete_job(
+    plan: &Plan<'_, '_>,
+    result: &anyhow::Result<Analyzed>,
+    started: Instant,
+    batch_options: &RunOptions,
+    state: Option<&mut RunState>,
+    reporter: Reporter,
+) -> bool {
+    let model = plan.options.client.configured_model();
+    let mut warnings = Vec::new();
+    let proceed = match result.as_ref() {
+        Ok(outcome) => {
+            if !outcome.report.is_skipped() {
+                if plan.options.emit_header.is_some() && outcome.declarations.is_empty() {
+                    let warning = "No function signature found, skipping prototype in the C header";
+                    reporter.line(format_args!("[!] {warning}"));
+                    warnings.push(warning.to_owned());
+                }
+                if let Some((recorded, content)) = state.zip(plan.hash.as_deref())
+                    && !batch_options.dry_run
+                    && let Err(err) =
+                        recorded.record(plan.filepath, content, model, &outcome.function_names)
+                {
+                    let warning = format!("Failed to update state file: {err}");
+                    log::warn!("{warning}");
+                    warnings.push(warning);
+                }
+            }
+            if batch_options.jsonl {
+                print_json_line(&outcome.report);
+            }
+            true
+        }
+        Err(err) => {
+            log::error!("{err:#}");
+            if batch_options.jsonl {
+                print_json_line(&AnalysisReport::failed(
+                    plan.filepath,
+                    model,
+                    started.elapsed(),
+                    err,
+                ));
+            }
+            !batch_options.fail_fast
+        }
+    };
+    if let Some(audit_log) = batch_options.audit_log.as_ref() {
+        audit_file(
+            audit_log,
+            plan.filepath,
+            &plan.options,
+            result,
+            started,
+            &warnings,
+        );
+    }
+    proceed
+}
+
+/// Returns the entry of the batch index with the outcome of the analysis planned in `plan`,
+/// started earlier, with the input path relative to [`RunOptions::input_root`] and the output path
+/// relative to the `dir` directory of the index.
+fn index_entry(
+    plan: &Plan<'_, '_>,
+    result: &anyhow::Result<Analyzed>,
+    started: Instant,
+    batch_options: &RunOptions,
+    dir: &Path,
+) -> IndexEntry {
+    let (report, renames_applied) = match result.as_ref() {
+        Ok(outcome) => (
+            outcome.report.clone(),
+            outcome
+                .renames
+                .iter()
+                .filter(|decision| decision.is_applied())
+                .count(),
+        ),
+        Err(err) => (
+            AnalysisReport::failed(
+                plan.filepath,
+                plan.options.client.configured_model(),
+                started.elapsed(),
+                err,
+            ),
+            0,
+        ),
+    };
+    let input = batch_options
+        .input_root
+        .as_deref()
+        .and_then(|root| report.input().strip_prefix(root).ok())
+        .unwrap_or_else(|| report.input())
+        .to_path_buf();
+    let output = report
+        .output()
+        .map(|path| path.strip_prefix(dir).unwrap_or(path).to_path_buf());
+    IndexEntry::new(report.with_paths(input, output), renames_applied)
+}
+
+/// Saves `batch_index` in the `dir` directory, also as Markdown if requested in `options`.
+fn write_batch_index(
+    dir: &Path,
+    batch_index: &BatchIndex,
+    options: &RunOptions,
+) -> Result<(), OutputError> {
+    fs::create_dir_all(dir).map_err(|err| OutputError::CreateFailed(dir.to_path_buf(), err))?;
+    write_atomically(
+        &dir.join(BATCH_REPORT_FILENAME),
+        batch_index.to_json().as_bytes(),
+        true,
+    )?;
+    if options.index_markdown {
+        write_atomically(
+            &dir.join(BATCH_INDEX_FILENAME),
+            batch_index.to_markdown().as_bytes(),
+            true,
+        )?;
+    }
+    Ok(())
+}
+
+/// Saves the final `batch_index` in the `dir` directory (if any), marked as `complete` if all the
+/// files of the batch were analyzed. In dry run mode, the index directory in `options` is only
+/// reported.
+fn finish_batch_index(
+    dir: Option<&Path>,
+    mut batch_index: BatchIndex,
+    complete: bool,
+    options: &RunOptions,
+    reporter: Reporter,
+) {
+    let Some(index_dir) = dir else {
+        if let Some(skipped) = options.batch_index.as_deref().filter(|_| options.dry_run) {
+            reporter.line(format_args!(
+                "\n[*] Dry run: not saving batch index in `{}`",
+                skipped.join(BATCH_REPORT_FILENAME).display()
+            ));
+        }
+        return;
+    };
+    batch_index.set_complete(complete);
+    match write_batch_index(index_dir, &batch_index, options) {
+        Ok(()) => reporter.line(format_args!(
+            "\n[+] Batch index saved to `{}`",
+            index_dir.join(BATCH_REPORT_FILENAME).display()
+        )),
+        Err(err) => log::warn!("Failed to save batch index: {err:#}"),
+    }
+}
+
+/// Records the outcome of the analysis of the `filepath` file with `options`, started earlier, in
+/// `audit_log`, together with the warnings raised by the analysis and any `extra_warnings`.
+fn audit_file(
+    audit_log: &AuditLog,
+    filepath: &Path,
+    options: &RunOptions,
+    result: &anyhow::Result<Analyzed>,
+    started: Instant,
+    extra_warnings: &[String],
+) {
+    match result.as_ref() {
+        Ok(outcome) => audit_log.file_completed(
+            &outcome.report,
+            &outcome.renames,
+            &[outcome.warnings.as_slice(), extra_warnings].concat(),
+        ),
+        Err(err) => audit_log.file_completed(
+            &AnalysisReport::failed(
+                filepath,
+                options.client.configured_model(),
+                started.elapsed(),
+                err,
+            ),
+            &[],
+            extra_warnings,
+        ),
+    }
+}
+
+/// Prints `report` to stdout as a line of JSON, flushing it right away.
+fn print_json_line(report: &impl Serialize) {
+    let result = serde_json::to_string(report)
+        .map_err(io::Error::from)
+        .and_then(|json| {
+            let mut stdout = io::stdout().lock();
+            writeln!(stdout, "{json}")?;
+            stdout.flush()
+        });
+    if let Err(err) = result {
+        log::error!("Failed to print JSON report: {err}");
+    }
+}
+
+/// Writes the aggregated C header of the `analyzed` files, if requested, and prints the final
+/// `summary` of a batch of `multiple` files and the statistics of the run `started` earlier, if
+/// requested.
+fn finish_batch(
+    summary: RunSummary,
+    analyzed: &[(&Path, Analyzed)],
+    multiple: bool,
+    started: Instant,
+    options: &RunOptions,
+    reporter: Reporter,
+) -> anyhow::Result<RunSummary> {
+    if let Some(audit_log) = options.audit_log.as_ref() {
+        audit_log.run_finished(
+            summary.succeeded.len(),
+            summary.failed.len(),
+            summary.skipped.len(),
+            started.elapsed(),
+        );
+    }
+    let declarations: Vec<Declaration> = analyzed
+        .iter()
+        .flat_map(|outcome| outcome.1.declarations.iter().cloned())
+        .collect();
+
+    // Emit a C header with the suggested prototypes of all analyzed functions.
+    if let Some(headerpath) = options.emit_header.as_deref()
+        && !declarations.is_empty()
+    {
+        write_c_header(headerpath, &declarations, options, reporter)?;
+    }
+
+    // Emit a SARIF log with the findings of all analyzed files.
+    if let Some(sarifpath) = options.sarif.as_deref() {
+        let findings: Vec<Finding> = analyzed
+            .iter()
+            .flat_map(|outcome| outcome.1.findings.iter().cloned())
+            .collect();
+        write_sarif(sarifpath, &findings, options, reporter)?;
+    }
+
+    // Emit disassembler scripts with the suggestions for all analyzed functions.
+    let suggestions: Vec<FunctionSuggestions> = analyzed
+        .iter()
+        .flat_map(|outcome| outcome.1.suggestions.iter().cloned())
+        .collect();
+    write_scripts(&suggestions, options, reporter)?;
+
+    // Emit a multi-file patch with the changes to all analyzed files.
+    if let Some(patchpath) = options.emit_patch.as_deref() {
+        let patch: String = analyzed
+            .iter()
+            .map(|outcome| outcome.1.patch.as_str())
+            .collect();
+        write_patch(patchpath, &patch, options, reporter)?;
+    }
+
+    // Emit an HTML index that links the reports of all analyzed files.
+    if multiple && options.report == Some(ReportFormat::Html) {
+        write_html_index(analyzed, options, reporter)?;
+    }
+
+    if multiple {
+        reporter.line(format_args!(
+            "\n[*] Batch summary ({:.1?} elapsed{}):",
+            started.elapsed(),
+            if options.dry_run {
+                ", dry run, no files written"
+            } else {
+                ""
+            }
+        ));
+        reporter.text(format_summary(&summary));
+    }
+    if multiple && !summary.failed.is_empty() {
+        reporter.line(format_args!(
+            "[!] Failed to analyze {} files:",
+            summary.failed.len()
+        ));
+        for failure in &summary.failed {
+            reporter.line(format_args!(
+                "    `{}`: {:#}",
+                failure.0.display(),
+                failure.1
+            ));
+        }
+    }
+    if summary.is_interrupted() {
+        reporter.line(format_args!(
+            "[!] Interrupted: {} files not analyzed, run again to resume",
+            summary.interrupted.len()
+        ));
+    }
+    if options.jsonl {
+        print_json_line(&BatchReport::new(&summary));
+    }
+
+    if options.stats {
+        print_stats(
+            analyzed.iter().map(|outcome| &outcome.1.report),
+            &summary.deduplicated,
+            started.elapsed(),
+            reporter,
+        );
+    }
+    Ok(summary)
+}
+
+/// Formats the outcomes of the files of a batch in `summary` as a table of indented lines, with
+/// the number of files analyzed (of which deduplicated), skipped, failed, and interrupted, if any.
+fn format_summary(summary: &RunSummary) -> String {
+    let mut table = format!("    Analyzed:    {}", summary.succeeded.len());
+    if !summary.deduplicated.is_empty() {
+        _ = write!(table, " ({} deduplicated)", summary.deduplicated.len());
+    }
+    _ = writeln!(table, "\n    Skipped:     {}", summary.skipped.len());
+    _ = writeln!(table, "    Failed:      {}", summary.failed.len());
+    if summary.is_interrupted() {
+        _ = writeln!(table, "    Interrupted: {}", summary.interrupted.len());
+    }
+    table
+}
+
+/// Prints the statistics of the analyses in `reports` (excluding skipped files, and counting the
+/// `deduplicated` ones) aggregated over a run that took `wall`.
+fn print_stats<'a>(
+    reports: impl IntoIterator<Item = &'a AnalysisReport>,
+    deduplicated: &[PathBuf],
+    wall: Duration,
+    reporter: Reporter,
+) {
+    let records: Vec<FileStats> = reports
+        .into_iter()
+        .filter(|report| !report.is_skipped())
+        .map(|report| {
+            FileStats::new(
+                report.input(),
+                report.duration(),
+                report.stats().copied().unwrap_or_default(),
+            )
+            .deduplicated(deduplicated.iter().any(|path| path == report.input()))
+        })
+        .collect();
+    reporter.line("\n[*] Statistics:");
+    reporter.text(format_stats(&RunStats::aggregate(&records, wall)));
+}
+
+/// Analyzes pseudocode in the `filepath` file and saves improved pseudocode, returning the
+/// declarations (whose signatures are found) and recommended names of the analyzed functions.
+/// An existing output file is replaced only if `overwrite` is set.
+fn process_file(
+    filepath: &Path,
+    options: &RunOptions,
+    overwrite: bool,
+) -> anyhow::Result<Analyzed> {
+    let started = Instant::now();
+    let from_stdin = filepath == Path::new(STDIN_FILEPATH);
+    let outfilepath = options.outfilepath(filepath);
+    let reporter = options.reporter(
+        options.stdout_reserved(filepath),
+        !from_stdin || io::stdin().is_terminal(),
+    );
+
+    // Read the target pseudocode from the input file or stdin, unwrapping aidapal payloads.
+    let input = read_input(filepath, reporter)?;
+    let hash = options
+        .history
+        .as_ref()
+        .map(|_| content_hash(input.as_bytes()));
+    let format = options.input_format.resolve(filepath);
+    let (pseudocode, file_options) = unwrap_input(filepath, input, format, options)?;
+
+    if file_options.show_prompt {
+        return show_plan(
+            filepath,
+            &pseudocode,
+            outfilepath.as_deref(),
+            &file_options,
+            started,
+            reporter,
+        );
+    }
+
+    // Submit pseudocode to the local LLM for analysis, either as a whole or function by function.
+    let improved = improve(&pseudocode, &file_options, reporter)?;
+
+    // Collect the findings and suggestions for the SARIF log and scripts, if requested.
+    let (findings, suggestions) = collect_exports(
+        filepath,
+        &pseudocode,
+        format,
+        &improved,
+        &file_options,
+        reporter,
+    );
+
+    // Assemble the improved pseudocode, or the report of the analysis.
+    let output = assemble_output(&improved, &pseudocode, &file_options);
+    let diff = patch_input(
+        filepath,
+        &pseudocode,
+        format,
+        &improved,
+        &file_options,
+        reporter,
+    )?;
+
+    if file_options.diff {
+        show_diff(filepath, &pseudocode, &improved, reporter);
+    }
+
+    // Write the improved pseudocode to stdout, or save it to an output file.
+    let savedpath = save_output(
+        outfilepath,
+        &output,
+        &improved,
+        &file_options,
+        overwrite,
+        reporter,
+    )?;
+
+    reporter.line("[+] Done analyzing pseudocode");
+    let report = AnalysisReport::succeeded(
+        filepath,
+        savedpath,
+        file_options.client.configured_model(),
+        started.elapsed(),
+        improved.results,
+        improved.functions,
+        file_options.stats.then_some(improved.metrics),
+    )
+    .with_locations(
+        file_options
+            .locations
+            .then(|| locate_matches(&pseudocode, &improved.matches)),
+    );
+    if let Some((history, content)) = options.history.as_ref().zip(hash.as_deref()) {
+        history.record(&report, content);
+    }
+    Ok(Analyzed {
+        declarations: improved.declarations,
+        function_names: improved.function_names,
+        renames: improved.renames,
+        findings,
+        suggestions,
+        patch: diff,
+        warnings: improved.warnings,
+        report,
+    })
+}
+
+/// Returns the pseudocode in the `input` read from the `filepath` file in the `format` input
+/// format, with `options` extended with the context of aidapal payloads.
+fn unwrap_input<'o>(
+    filepath: &Path,
+    input: String,
+    format: InputFormat,
+    options: &'o RunOptions,
+) -> anyhow::Result<(String, Cow<'o, RunOptions>)> {
+    match format {
+        InputFormat::AidapalJson => {
+            let payload = parse_aidapal_json(&input).with_context(|| {
+                format!(
+                    "Failed to parse aidapal payload in `{}`",
+                    filepath.display()
+                )
+            })?;
+            let context = payload.extend_context(options.context.clone());
+            Ok((
+                payload.code().to_owned(),
+                Cow::Owned(options.clone().context(context)),
+            ))
+        }
+        InputFormat::Auto | InputFormat::Pseudocode => Ok((input, Cow::Borrowed(options))),
+    }
+}
+
+/// Prints the prompts that the analysis of `pseudocode` in the `filepath` input file would submit,
+/// and the output files that it would save in `outfilepath` and next to it, instead of analyzing
+/// it (see [`RunOptions::show_prompt`]).
+fn show_plan(
+    filepath: &Path,
+    pseudocode: &str,
+    outfilepath: Option<&Path>,
+    options: &RunOptions,
+    started: Instant,
+    reporter: Reporter,
+) -> anyhow::Result<Analyzed> {
+    show_prompts(pseudocode, options, reporter)?;
+    show_planned_outputs(outfilepath, options, reporter);
+    Ok(Analyzed {
+        declarations: Vec::new(),
+        function_names: Vec::new(),
+        renames: Vec::new(),
+        findings: Vec::new(),
+        suggestions: Vec::new(),
+        patch: String::new(),
+        warnings: Vec::new(),
+        report: AnalysisReport::succeeded(
+            filepath,
+            None,
+            options.client.configured_model(),
+            started.elapsed(),
+            None,
+            Vec::new(),
+            None,
+        ),
+    })
+}
+
+/// Prints the prompts that the analysis of `pseudocode` in the mode set in `options` would submit
+/// (see [`improve`]), each after a status line that names it.
+fn show_prompts(pseudocode: &str, options: &RunOptions, reporter: Reporter) -> anyhow::Result<()> {
+    let client = &options.client;
+    let prompts: Vec<(String, Vec<String>)> = match options.function.as_deref() {
+        Some(query) => {
+            let (span, start, function_options) = selected_function(pseudocode, query, options)?;
+            let function = pseudocode.get(start..span.range().end).unwrap_or_default();
+            let prompts = client.prompts(function, &function_options.context);
+            vec![(format!("function `{}`", span.name()), prompts)]
+        }
+        None if options.chunk && !split_functions(pseudocode).is_empty() => client
+            .function_prompts(pseudocode, &options.context)
+            .into_iter()
+            .map(|(span, prompts)| (format!("function `{}`", span.name()), prompts))
+            .collect(),
+        None if options.joint && !split_functions(pseudocode).is_empty() => vec![(
+            "all functions".to_owned(),
+            vec![client.joint_prompt(pseudocode, &options.context)],
+        )],
+        None => vec![(
+            "pseudocode".to_owned(),
+            client.prompts(pseudocode, &options.context),
+        )],
+    };
+    for (label, windows) in prompts {
+        let count = windows.len();
+        for (window, prompt) in iter::zip(1..=count, windows) {
+            let part = if count > 1 {
+                format!(", window {window} of {count}")
+            } else {
+                String::new()
+            };
+            reporter.line(format_args!(
+                "[*] Prompt for {label} (about {} tokens{part}):",
+                estimated_tokens(prompt.len())
+            ));
+            reporter.prompt(&prompt);
+        }
+    }
+    Ok(())
+}
+
+/// Prints the output files that the analysis would save for the `outfilepath` output file, when
+/// only the prompts are shown.
+fn show_planned_outputs(outfilepath: Option<&Path>, options: &RunOptions, reporter: Reporter) {
+    let description = options.output_description();
+    reporter.line("");
+    let Some(path) = outfilepath else {
+        reporter.line(format_args!(
+            "[*] Dry run: not writing {description} to stdout"
+        ));
+        return;
+    };
+    if options.in_place {
+        reporter.line(format_args!(
+            "[*] Dry run: not backing up `{}`",
+            path.display()
+        ));
+    }
+    reporter.line(format_args!(
+        "[*] Dry run: not saving {description} in `{}`",
+        path.display()
+    ));
+    if options.emit_renames {
+        reporter.line(format_args!(
+            "[*] Dry run: not saving rename map in `{}`",
+            path.with_extension(RENAMES_EXTENSION).display()
+        ));
+    }
+}
+
+/// Writes `output` to stdout, or saves it in `outfilepath` along with the rename map of `improved`,
+/// returning the path of the saved file, if any. Dry runs save no files, and print `output` to
+/// stdout only if it is not reserved. An existing output file is replaced only if `overwrite` is
+/// set.
+fn save_output(
+    outfilepath: Option<PathBuf>,
+    output: &str,
+    improved: &Improved,
+    options: &RunOptions,
+    overwrite: bool,
+    reporter: Reporter,
+) -> anyhow::Result<Option<PathBuf>> {
+    let description = options.output_description();
+    reporter.line("");
+    match outfilepath {
+        Some(path) if options.dry_run => {
+            reporter.line(format_args!(
+                "[*] Dry run: not saving {description} in `{}`",
+                path.display()
+            ));
+            Ok(None)
+        }
+        // Stdout is reserved for the report, so a dry run has nowhere to print the output.
+        None if options.dry_run && options.reserve_stdout => {
+            reporter.line(format_args!(
+                "[*] Dry run: not writing {description} to stdout"
+            ));
+            Ok(None)
+        }
+        _ => {
+            let in_place = outfilepath.as_deref().filter(|_| options.in_place);
+            if let Some(path) = in_place {
+                back_up(path, reporter)?;
+            }
+            let replace = overwrite || in_place.is_some() || options.force;
+            let target = outfilepath.map(|path| {
+                if options.auto_number && !replace {
+                    free_path(path)
+                } else {
+                    path
+                }
+            });
+            write_output(target.as_deref(), output, description, replace, reporter)?;
+            write_rename_map(target.as_deref(), improved, options, reporter)?;
+            Ok(target)
+        }
+    }
+}
+
+/// Returns the findings of the analysis of `pseudocode` in the `filepath` input file, if a SARIF
+/// log is requested, and the suggestions for each analyzed function, if a disassembler script is
+/// requested.
+fn collect_exports(
+    filepath: &Path,
+    pseudocode: &str,
+    format: InputFormat,
+    improved: &Improved,
+    options: &RunOptions,
+    reporter: Reporter,
+) -> (Vec<Finding>, Vec<FunctionSuggestions>) {
+    let findings = if options.sarif.is_none() {
+        Vec::new()
+    } else if format == InputFormat::AidapalJson {
+        reporter.line(
+            "[!] Findings in aidapal payloads cannot be located, leaving them out of the SARIF log",
+        );
+        Vec::new()
+    } else {
+        locate_findings(
+            filepath,
+            pseudocode,
+            improved.results.as_ref(),
+            &improved.functions,
+            options.function.as_deref(),
+        )
+    };
+    let emits_scripts = options
+        .script_emitters()
+        .iter()
+        .any(|emitter| emitter.0.is_some());
+    let suggestions = if emits_scripts {
+        collect_suggestions(
+            pseudocode,
+            improved.results.as_ref(),
+            &improved.functions,
+            options.function.as_deref(),
+            options.address,
+        )
+    } else {
+        Vec::new()
+    };
+    (findings, suggestions)
+}
+
+/// Returns the unified diff from the `pseudocode` in the `filepath` input file to the improved
+/// pseudocode, if a patch is requested. The provenance annotation of the output file is left out
+/// of the patch, so that it does not pollute reviews. Pseudocode from stdin and aidapal payloads are
+/// left out of the patch, since there is no file to apply it to.
+fn patch_input(
+    filepath: &Path,
+    pseudocode: &str,
+    format: InputFormat,
+    improved: &Improved,
+    options: &RunOptions,
+    reporter: Reporter,
+) -> anyhow::Result<String> {
+    if options.emit_patch.is_none() {
+        return Ok(String::new());
+    }
+    if filepath == Path::new(STDIN_FILEPATH) || format == InputFormat::AidapalJson {
+        reporter
+            .line("[!] Only pseudocode files can be patched, leaving the input out of the patch");
+        return Ok(String::new());
+    }
+    let root = options
+        .patch_root
+        .as_deref()
+        .unwrap_or_else(|| Path::new("."));
+    let relpath = patch_relpath(filepath, root)?;
+    Ok(format_file_patch(
+        &relpath,
+        pseudocode,
+        &improved.pseudocode,
+    ))
+}
+
+/// Prints the unified diff from the `pseudocode` in the `filepath` input file to the improved
+/// pseudocode, leaving out the provenance annotation of the output file as [`patch_input`] does.
+fn show_diff(filepath: &Path, pseudocode: &str, improved: &Improved, reporter: Reporter) {
+    let diff = format_file_patch(
+        &filepath.display().to_string(),
+        pseudocode,
+        &improved.pseudocode,
+    );
+    if diff.is_empty() {
+        reporter.line(format_args!("[*] No changes to `{}`", filepath.display()));
+    } else {
+        reporter.diff(&diff);
+    }
+}
+
+/// Returns the path of `filepath` relative to the `root` directory, with `/` separators.
+fn patch_relpath(filepath: &Path, root: &Path) -> anyhow::Result<String> {
+    let absolute = fs::canonicalize(filepath)
+        .with_context(|| format!("Failed to resolve `{}`", filepath.display()))?;
+    let base = fs::canonicalize(root)
+        .with_context(|| format!("Failed to resolve patch root `{}`", root.display()))?;
+    let relative = absolute.strip_prefix(&base).with_context(|| {
+        format!(
+            "`{}` is not within the patch root `{}`",
+            filepath.display(),
+            root.display()
+        )
+    })?;
+    Ok(relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/"))
+}
+
+/// Returns the improved pseudocode, annotated with provenance fields if requested, or the report
+/// of the analysis of the `pseudocode` in the requested [`ReportFormat`].
+fn assemble_output(improved: &Improved, pseudocode: &str, options: &RunOptions) -> String {
+    let mut output = improved.pseudocode.clone();
+    if !options.annotation.is_empty() {
+        let provenance = Provenance::new(
+            options.client.configured_model(),
+            options.client.configured_baseurl(),
+        );
+        output.push('\n');
+        output.push_str(&format_provenance(
+            &provenance,
+            &options.annotation,
+            options.comment.effective_style(),
+        ));
+    }
+
+    let results: Vec<_> = improved
+        .results
+        .iter()
+        .chain(
+            improved
+                .functions
+                .iter()
+                .filter_map(FunctionReport::results),
+        )
+        .collect();
+    match options.report {
+        Some(ReportFormat::Markdown) => format_markdown_report(&results, &output),
+        Some(ReportFormat::Html) => format_html_report(&results, pseudocode, &output),
+        None => output,
+    }
+}
+
+/// Improved pseudocode, with the declarations and recommended names of the analyzed functions.
+pub struct Improved {
+    /// Pseudocode with descriptions and renames applied, according to [`ApplyOptions`].
+    pseudocode: String,
+    /// Declarations of the analyzed functions whose signatures are found.
+    declarations: Vec<Declaration>,
+    /// Recommended names of the analyzed functions, in order.
+    function_names: Vec<String>,
+    /// Analysis results of the whole pseudocode, if analyzed as a whole.
+    results: Option<OneiromancerResults>,
+    /// Analysis results of each function, if analyzed function by function.
+    functions: Vec<FunctionReport>,
+    /// Timing and token statistics of all LLM responses.
+    metrics: ResponseMetrics,
+    /// Decisions on the variable renaming suggestions.
+    renames: Vec<RenameDecision>,
+    /// Matches of the original names replaced by the renames, in the analyzed pseudocode.
+    matches: Vec<RenameMatch>,
+    /// Warnings raised during the analysis.
+    warnings: Vec<String>,
+}
+
+impl Improved {
+    /// Returns the improved pseudocode.
+    pub fn into_pseudocode(self) -> String {
+        self.pseudocode
+    }
+}
+
+/// Suggestions of the analysis of a function, applied to its pseudocode.
+struct Applied {
+    /// Pseudocode with the description and renames applied, according to [`ApplyOptions`].
+    pseudocode: String,
+    /// Declaration of the function, if requested and its signature is found.
+    declaration: Option<Declaration>,
+    /// Decisions on the variable renaming suggestions.
+    renames: Vec<RenameDecision>,
+    /// Matches of the original names replaced by the renames.
+    matches: Vec<RenameMatch>,
+}
+
+/// Outcome of the analysis of a pseudocode file.
+struct Analyzed {
+    /// Declarations of the analyzed functions whose signatures are found.
+    declarations: Vec<Declaration>,
+    /// Recommended names of the analyzed functions, in order.
+    function_names: Vec<String>,
+    /// Decisions on the variable renaming suggestions.
+    renames: Vec<RenameDecision>,
+    /// Findings of the analysis, located in the input file, if a SARIF log is requested.
+    findings: Vec<Finding>,
+    /// Suggestions for each analyzed function, if a disassembler script is requested.
+    suggestions: Vec<FunctionSuggestions>,
+    /// Unified diff from the input file to the improved pseudocode, if a patch is requested.
+    patch: String,
+    /// Warnings raised during the analysis.
+    warnings: Vec<String>,
+    /// Report of the analysis.
+    report: AnalysisReport,
+}
+
+/// Analyzes `pseudocode` in the mode set in `options` (the selected function only, function by
+/// function, all functions with a single prompt, or as a whole) and applies the suggestions.
+fn improve(pseudocode: &str, options: &RunOptions, reporter: Reporter) -> anyhow::Result<Improved> {
+    match options.function.as_deref() {
+        Some(query) => improve_selected(pseudocode, query, options, reporter),
+        None if options.chunk => improve_functions(pseudocode, options, reporter),
+        None if options.joint => improve_jointly(pseudocode, options, reporter),
+        None => improve_whole(pseudocode, options, reporter),
+    }
+}
+
+/// Analyzes `pseudocode` as a whole and applies the suggestions.
+pub fn improve_whole(
+    pseudocode: &str,
+    options: &RunOptions,
+    reporter: Reporter,
+) -> anyhow::Result<Improved> {
+    report_prompt_savings([pseudocode], options, reporter);
+    let activity = reporter.start("Querying the Oneiromancer");
+    let analysis_results = options
+        .client
+        .analyze_code_with_context(pseudocode, &options.context)
+        .context("Failed to analyze pseudocode")?;
+    activity.finish("[+] Successfully analyzed pseudocode");
+    let warnings = check_quality(&analysis_results, options, reporter)
+        .context("Failed to analyze pseudocode")?;
+    reporter.line("");
+
+    let applied = apply_results(pseudocode, &analysis_results, options, reporter);
+    if options.stats
+        && let Some(consensus) = analysis_results.consensus()
+    {
+        reporter.line(format_args!(
+            "\n[*] Consensus of {} samples:",
+            consensus.samples()
+        ));
+        reporter.text(format_consensus(
+            consensus,
+            analysis_results.function_name(),
+        ));
+    }
+    Ok(Improved {
+        pseudocode: applied.pseudocode,
+        declarations: applied.declaration.into_iter().collect(),
+        function_names: vec![analysis_results.function_name().to_owned()],
+        metrics: *analysis_results.metrics(),
+        renames: applied.renames,
+        matches: applied.matches,
+        warnings: warnings.into_iter().collect(),
+        results: Some(analysis_results),
+        functions: Vec::new(),
+    })
+}
+
+/// Analyzes only the function in `pseudocode` selected by `query` (see [`select_function`]) and
+/// applies the suggestions within its definition, leaving the rest of `pseudocode` untouched.
+fn improve_selected(
+    pseudocode: &str,
+    query: &str,
+    options: &RunOptions,
+    reporter: Reporter,
+) -> anyhow::Result<Improved> {
+    let (span, start, function_options) = selected_function(pseudocode, query, options)?;
+    let range = span.range();
+    reporter.line(format_args!("[*] Selected function `{}`", span.name()));
+    let improved = improve_whole(
+        pseudocode.get(start..range.end).unwrap_or_default(),
+        &function_options,
+        reporter,
+    )?;
+    Ok(Improved {
+        pseudocode: format!(
+            "{}{}{}",
+            pseudocode.get(..start).unwrap_or_default(),
+            improved.pseudocode,
+            pseudocode.get(range.end..).unwrap_or_default()
+        ),
+        // The renames were applied within the function only.
+        renames: improved
+            .renames
+            .into_iter()
+            .map(|decision| decision.function(span.name()))
+            .collect(),
+        matches: improved
+            .matches
+            .into_iter()
+            .map(|found| found.function(span.name()).offset(start))
+            .collect(),
+        ..improved
+    })
+}
+
+/// Returns the function in `pseudocode` selected by `query` (see [`select_function`]), the offset
+/// of the comments above it, where a previous description may be, and `options` with the lines
+/// that precede it in the context.
+fn selected_function<'o>(
+    pseudocode: &str,
+    query: &str,
+    options: &'o RunOptions,
+) -> anyhow::Result<(FunctionSpan, usize, Cow<'o, RunOptions>)> {
+    let functions = split_functions(pseudocode);
+    let span = select_function(&functions, query)?.clone();
+    let start = comments_start(pseudocode.get(..span.range().start).unwrap_or_default());
+
+    let leading = leading_lines(pseudocode, start, options.function_context);
+    let function_options = if leading.trim().is_empty() {
+        Cow::Borrowed(options)
+    } else {
+        Cow::Owned(
+            options
+                .clone()
+                .context(options.context.clone().preceding_code(leading)),
+        )
+    };
+    Ok((span, start, function_options))
+}
+
+/// Splits `pseudocode` into functions, analyzes each function separately, and applies the
+/// suggestions to each function. Text between functions is preserved, and functions whose
+/// analysis fails are left untouched.
+fn improve_functions(
+    pseudocode: &str,
+    options: &RunOptions,
+    reporter: Reporter,
+) -> anyhow::Result<Improved> {
+    report_prompt_savings(
+        split_functions(pseudocode)
+            .iter()
+            .map(|span| span.text(pseudocode)),
+        options,
+        reporter,
+    );
+    let activity = reporter.start("Querying the Oneiromancer (function by function)");
+    let analyses = options
+        .client
+        .analyze_functions_with_context(pseudocode, &options.context);
+    if analyses.is_empty() {
+        activity.finish("[!] No function definitions found, analyzing pseudocode as a whole");
+        return improve_whole(pseudocode, options, reporter);
+    }
+    activity.finish(&format!("[+] Analyzed {} functions", analyses.len()));
+
+    apply_to_functions(
+        pseudocode,
+        analyses.iter().map(|analysis| {
+            (
+                analysis.span(),
+                analysis.results().map_err(ToString::to_string),
+            )
+        }),
+        options,
+        reporter,
+    )
+}
+
+/// Splits `pseudocode` into functions, analyzes all of them with a single prompt, and applies the
+/// suggestions to each function. Functions that the response does not cover are left untouched,
+/// and results for unknown functions are ignored, both with a warning.
+fn improve_jointly(
+    pseudocode: &str,
+    options: &RunOptions,
+    reporter: Reporter,
+) -> anyhow::Result<Improved> {
+    if split_functions(pseudocode).is_empty() {
+        reporter.line("[!] No function definitions found, analyzing pseudocode as a whole");
+        return improve_whole(pseudocode, options, reporter);
+    }
+    report_prompt_savings([pseudocode], options, reporter);
+    let activity = reporter.start("Querying the Oneiromancer (all functions at once)");
+    let analysis = options
+        .client
+        .analyze_functions_jointly(pseudocode, &options.context)
+        .context("Failed to analyze pseudocode")?;
+    activity.finish(&format!(
+        "[+] Analyzed {} functions with a single prompt",
+        analysis.functions().len()
+    ));
+
+    let unknown: Vec<String> = analysis
+        .unknown()
+        .iter()
+        .map(|name| format!("Ignoring results for unknown function `{name}`"))
+        .collect();
+    for warning in &unknown {
+        reporter.line(format_args!("[!] {warning}"));
+    }
+    let mut improved = apply_to_functions(
+        pseudocode,
+        analysis.functions().iter().map(|function| {
+            (
+                &function.0,
+                function
+                    .1
+                    .as_ref()
+                    .ok_or_else(|| "missing from the response".to_owned()),
+            )
+        }),
+        options,
+        reporter,
+    )?;
+    improved.warnings.extend(unknown);
+    improved.metrics = *analysis.metrics();
+    Ok(improved)
+}
+
+/// Applies the analysis `outcomes` (results, or the reason why they are not available) to each
+/// function in `pseudocode`, after checking them against the quality policy. Text between
+/// functions is preserved, and functions without results are left untouched.
+fn apply_to_functions<'a>(
+    pseudocode: &str,
+    outcomes: impl ExactSizeIterator<Item = (&'a FunctionSpan, Result<&'a OneiromancerResults, String>)>,
+    options: &RunOptions,
+    reporter: Reporter,
+) -> anyhow::Result<Improved> {
+    let count = outcomes.len();
+    let mut improved = Improved {
+        pseudocode: String::with_capacity(pseudocode.len()),
+        declarations: Vec::new(),
+        function_names: Vec::new(),
+        results: None,
+        functions: Vec::with_capacity(count),
+        metrics: ResponseMetrics::default(),
+        renames: Vec::new(),
+        matches: Vec::new(),
+        warnings: Vec::new(),
+    };
+    let mut last: usize = 0;
+    let mut failures: usize = 0;
+    for (span, result) in outcomes {
+        // Include the comments above the function, where a previous description may be.
+        let gap = pseudocode.get(last..span.range().start).unwrap_or_default();
+        let start = last.saturating_add(comments_start(gap));
+        let text = pseudocode.get(start..span.range().end).unwrap_or_default();
+        improved
+            .pseudocode
+            .push_str(pseudocode.get(last..start).unwrap_or_default());
+        reporter.line(format_args!("\n[*] Function `{}`", span.name()));
+        let outcome = result.and_then(|results| {
+            let warning =
+                check_quality(results, options, reporter).map_err(|err| err.to_string())?;
+            improved
+                .warnings
+                .extend(warning.map(|issue| format!("Function `{}`: {issue}", span.name())));
+            Ok(results)
+        });
+        match outcome {
+            Ok(results) => {
+                let applied = apply_results(text, results, options, reporter);
+                improved.pseudocode.push_str(&applied.pseudocode);
+                improved.declarations.extend(applied.declaration);
+                improved.renames.extend(
+                    applied
+                        .renames
+                        .into_iter()
+                        .map(|decision| decision.function(span.name())),
+                );
+                improved.matches.extend(
+                    applied
+                        .matches
+                        .into_iter()
+                        .map(|found| found.function(span.name()).offset(start)),
+                );
+                improved
+                    .function_names
+                    .push(results.function_name().to_owned());
+                improved
+                    .functions
+                    .push(FunctionReport::new(span.name(), Ok(results)));
+                improved.metrics = improved.metrics.merge(*results.metrics());
+            }
+            Err(err) => {
+                reporter.line(format_args!("[!] Failed to analyze function: {err}"));
+                improved.warnings.push(format!(
+                    "Failed to analyze function `{}`: {err}",
+                    span.name()
+                ));
+                improved
+                    .functions
+                    .push(FunctionReport::new(span.name(), Err(err.clone())));
+                improved.pseudocode.push_str(text);
+                failures = failures.saturating_add(1);
+            }
+        }
+        last = span.range().end;
+    }
+    improved
+        .pseudocode
+        .push_str(pseudocode.get(last..).unwrap_or_default());
+
+    if failures == count {
+        anyhow::bail!("Failed to analyze all {failures} functions");
+    }
+    Ok(improved)
+}
+
+/// Checks analysis `results` against the quality policy in `options`. Results that do not comply
+/// are rejected in strict mode, and otherwise accepted with a warning, which is printed and
+/// returned.
+fn check_quality(
+    results: &OneiromancerResults,
+    options: &RunOptions,
+    reporter: Reporter,
+) -> Result<Option<String>, OneiromancerError> {
+    match options.quality.check(results) {
+        Ok(()) => Ok(None),
+        Err(issue) if options.strict => Err(OneiromancerError::LowQualityResult(issue)),
+        Err(issue) => {
+            let warning = format!("Low-quality analysis result: {issue}");
+            reporter.line(format_args!("[!] {warning}"));
+            Ok(Some(warning))
+        }
+    }
+}
+
+/// Prints how many bytes (and estimated tokens) of noise are stripped from the prompts built from
+/// `texts`, if any.
+fn report_prompt_savings<'a>(
+    texts: impl IntoIterator<Item = &'a str>,
+    options: &RunOptions,
+    reporter: Reporter,
+) {
+    let prompt_options = options.client.configured_prompt_options();
+    let saved = texts
+        .into_iter()
+        .map(|text| {
+            text.len()
+                .saturating_sub(prompt_options.prepare(text).len())
+        })
+        .fold(0, usize::saturating_add);
+    if saved > 0 {
+        reporter.line(format_args!(
+            "[-] Stripped {saved} bytes (~{} tokens) of noise from the prompt",
+            estimated_tokens(saved)
+        ));
+    }
+}
+
+/// Applies `results` to `pseudocode` according to [`ApplyOptions`] and prints them, returning the
+/// improved pseudocode, the declaration of the analyzed function (if requested and found), the
+/// decisions on the variable renaming suggestions, and the replaced matches. The comments at the start of `pseudocode`
+/// are taken as those above the function, with which the description is merged (see
+/// [`place_header`]).
+fn apply_results(
+    pseudocode: &str,
+    results: &OneiromancerResults,
+    options: &RunOptions,
+    reporter: Reporter,
+) -> Applied {
+    // Apply variable renaming suggestions.
+    let (renamed, decisions, matches) = if options.apply.apply_renames {
+        let protected = protected_renames(pseudocode, results.variables(), options);
+        let allowed: Vec<Variable> = results
+            .variables()
+            .iter()
+            .zip(&protected)
+            .map(|(variable, &protect)| {
+                if protect {
+                    Variable::new(variable.original_name(), variable.original_name())
+                } else {
+                    variable.clone()
+                }
+            })
+            .collect();
+        let (resolved, colliding) =
+            resolve_collisions(pseudocode, &allowed, options.apply.collisions);
+        let (renamed, applied, matches) = apply_renames(pseudocode, &resolved);
+        let decisions: Vec<RenameDecision> = applied
+            .into_iter()
+            .zip(
+                results
+                    .variables()
+                    .iter()
+                    .zip(protected.into_iter().zip(colliding)),
+            )
+            .map(|(decision, (variable, skip))| match skip {
+                (true, _) => RenameDecision::skipped(variable, RenameSkip::Protected),
+                (false, true) => RenameDecision::skipped(variable, RenameSkip::Collision),
+                (false, false) => decision,
+            })
+            .collect();
+        (renamed, decisions, matches)
+    } else {
+        (
+            pseudocode.to_owned(),
+            results
+                .variables()
+                .iter()
+                .map(|variable| RenameDecision::skipped(variable, RenameSkip::Disabled))
+                .collect(),
+            Vec::new(),
+        )
+    };
+
+    // Create a function description, based on the pseudocode with renames applied (if any).
+    let function_description = format_header(results, &renamed, &options.comment);
+    reporter.text(&function_description);
+
+    reporter.line(format_args!(
+        "[-] Variable renaming suggestions{}:",
+        if options.apply.apply_renames {
+            ""
+        } else {
+            " (not applied)"
+        }
+    ));
+    for decision in &decisions {
+        let (original, new) = (decision.original_name(), decision.new_name());
+        match decision.reason() {
+            None | Some(RenameSkip::SameName | RenameSkip::Disabled) => {
+                reporter.rename(original, new);
+            }
+            Some(reason) => reporter.skipped_rename(original, new, reason),
+        }
+    }
+
+    let declaration = options
+        .emit_header
+        .as_ref()
+        .and_then(|_| Declaration::new(results, &renamed, &options.comment));
+    let improved = if options.apply.write_comment {
+        place_header(&renamed, &function_description, options.apply.merge_comment)
+    } else {
+        renamed
+    };
+    Applied {
+        pseudocode: improved,
+        declaration,
+        renames: decisions,
+        matches,
+    }
+}
+
+/// Returns, for each renaming suggestion in `variables`, whether it is protected because it renames
+/// a name that the decompiler of `pseudocode` did not generate, unless enabled in `options`.
+fn protected_renames(pseudocode: &str, variables: &[Variable], options: &RunOptions) -> Vec<bool> {
+    let decompiler = (!options.apply.rename_user_names)
+        .then(|| {
+            options
+                .client
+                .configured_prompt_options()
+                .decompiler_of(pseudocode)
+        })
+        .flatten();
+    variables
+        .iter()
+        .map(|variable| {
+            let original = variable.original_name();
+            let protector = decompiler
+                .filter(|known| original != variable.new_name() && !known.is_generated(original));
+            if let Some(known) = protector {
+                log::warn!(
+                    "Skipping rename `{original}` -> `{}`: not generated by {}",
+                    variable.new_name(),
+                    known.name()
+                );
+            }
+            protector.is_some()
+        })
+        .collect()
+}
+
+/// Saves the [`RenameMap`] of the renames applied in `improved` next to the `outfilepath` output
+/// file, if requested in `options`. An existing rename map is replaced, so that it always matches
+/// the output file just saved.
+fn write_rename_map(
+    outfilepath: Option<&Path>,
+    improved: &Improved,
+    options: &RunOptions,
+    reporter: Reporter,
+) -> anyhow::Result<()> {
+    let Some(mappath) = outfilepath
+        .filter(|_| options.emit_renames)
+        .map(|path| path.with_extension(RENAMES_EXTENSION))
+    else {
+        return Ok(());
+    };
+    reporter.line(format_args!(
+        "[*] Saving rename map in `{}`",
+        mappath.display()
+    ));
+    write_atomically(
+        &mappath,
+        RenameMap::from_decisions(&improved.renames)
+            .to_json()
+            .as_bytes(),
+        true,
+    )
+    .map_err(Into::into)
+}
+
+/// Saves a C header that declares all `declarations` in the `headerpath` file, unless
+/// [`RunOptions::write_output`] is disabled in `options`.
+fn write_c_header(
+    headerpath: &Path,
+    declarations: &[Declaration],
+    options: &RunOptions,
+    reporter: Reporter,
+) -> anyhow::Result<()> {
+    if options.dry_run {
+        reporter.line(format_args!(
+            "[*] Dry run: not saving C header in `{}`",
+            headerpath.display()
+        ));
+        return Ok(());
+    }
+    reporter.line(format_args!(
+        "[*] Saving C header in `{}`",
+        headerpath.display()
+    ));
+    write_atomically(
+        headerpath,
+        format_c_header(headerpath, declarations).as_bytes(),
+        true,
+    )
+    .map_err(Into::into)
+}
+
+/// Saves an [`HTML_INDEX_FILENAME`] file that links the HTML reports of the `analyzed` files in
+/// the output directory (or the closest directory that holds all reports), unless
+/// [`RunOptions::write_output`] is disabled in `options`.
+fn write_html_index(
+    analyzed: &[(&Path, Analyzed)],
+    options: &RunOptions,
+    reporter: Reporter,
+) -> anyhow::Result<()> {
+    let reports: Vec<(&Path, &[String])> = analyzed
+        .iter()
+        .filter_map(|outcome| {
+            let path = outcome.1.report.output()?;
+            Some((path, outcome.1.function_names.as_slice()))
+        })
+        .collect();
+    let Some(dir) = options.output_dir.clone().or_else(|| {
+        reports
+            .iter()
+            .filter_map(|report| report.0.parent())
+            .map(Path::to_path_buf)
+            .reduce(|common, parent| {
+                common
+                    .ancestors()
+                    .find(|ancestor| parent.starts_with(ancestor))
+                    .map(Path::to_path_buf)
+                    .unwrap_or_default()
+            })
+    }) else {
+        return Ok(());
+    };
+    let indexpath = dir.join(HTML_INDEX_FILENAME);
+    if options.dry_run {
+        reporter.line(format_args!(
+            "[*] Dry run: not saving HTML index in `{}`",
+            indexpath.display()
+        ));
+        return Ok(());
+    }
+
+    let relative = reports
+        .iter()
+        .map(|report| (report.0.strip_prefix(&dir).unwrap_or(report.0), report.1));
+    reporter.line(format_args!(
+        "[*] Saving HTML index in `{}`",
+        indexpath.display()
+    ));
+    fs::create_dir_all(&dir).map_err(|err| OutputError::CreateFailed(dir.clone(), err))?;
+    write_atomically(&indexpath, format_html_index(relative).as_bytes(), true).map_err(Into::into)
+}
+
+/// Saves a SARIF log with all `findings` in the `sarifpath` file, unless
+/// [`RunOptions::write_output`] is disabled in `options`.
+fn write_sarif(
+    sarifpath: &Path,
+    findings: &[Finding],
+    options: &RunOptions,
+    reporter: Reporter,
+) -> anyhow::Result<()> {
+    if options.dry_run {
+        reporter.line(format_args!(
+            "[*] Dry run: not saving SARIF log in `{}`",
+            sarifpath.display()
+        ));
+        return Ok(());
+    }
+    reporter.line(format_args!(
+        "[*] Saving SARIF log in `{}`",
+        sarifpath.display()
+    ));
+    write_atomically(sarifpath, format_sarif(findings).as_bytes(), true).map_err(Into::into)
+}
+
+/// Saves the unified diff with the concatenated `patch` of all files in the `patchpath` file,
+/// unless [`RunOptions::write_output`] is disabled in `options`.
+fn write_patch(
+    patchpath: &Path,
+    patch: &str,
+    options: &RunOptions,
+    reporter: Reporter,
+) -> anyhow::Result<()> {
+    if options.dry_run {
+        reporter.line(format_args!(
+            "[*] Dry run: not saving patch in `{}`",
+            patchpath.display()
+        ));
+        return Ok(());
+    }
+    if patch.is_empty() {
+        reporter.line("[!] No changes to the input files, saving an empty patch");
+    }
+    reporter.line(format_args!(
+        "[*] Saving patch in `{}`",
+        patchpath.display()
+    ));
+    write_atomically(patchpath, patch.as_bytes(), true).map_err(Into::into)
+}
+
+/// Saves the disassembler scripts requested in `options` that apply all `suggestions`, unless
+/// [`RunOptions::write_output`] is disabled in `options`.
+fn write_scripts(
+    suggestions: &[FunctionSuggestions],
+    options: &RunOptions,
+    reporter: Reporter,
+) -> anyhow::Result<()> {
+    for (scriptpath, emitter) in options.script_emitters() {
+        let Some(path) = scriptpath else {
+            continue;
+        };
+        let description = emitter.description();
+        if options.dry_run {
+            reporter.line(format_args!(
+                "[*] Dry run: not saving {description} in `{}`",
+                path.display()
+            ));
+            continue;
+        }
+        reporter.line(format_args!(
+            "[*] Saving {description} in `{}`",
+            path.display()
+        ));
+        write_atomically(
+            path,
+            emitter
+                .format_script(suggestions, options.client.configured_model())
+                .as_bytes(),
+            true,
+        )?;
+    }
+    Ok(())
+}
+
+/// Reads pseudocode from the `filepath` file, or from stdin if `filepath` is [`STDIN_FILEPATH`].
+fn read_input(filepath: &Path, reporter: Reporter) -> anyhow::Result<String> {
+    if filepath == Path::new(STDIN_FILEPATH) {
+        reporter.line("[*] Analyzing pseudocode from stdin");
+        read_pseudocode(io::stdin().lock()).context("Failed to read from stdin")
+    } else {
+        reporter.line(format_args!(
+            "[*] Analyzing pseudocode in `{}`",
+            filepath.display()
+        ));
+        // Check the file here rather than upfront, so that a bad file fails on its own in a batch.
+        validate_input(filepath, &InputPolicy::new().force_ext(true))
+            .context("Invalid target file")?;
+        let file = File::open(filepath)
+            .with_context(|| format!("Failed to open `{}`", filepath.display()))?;
+        read_pseudocode(BufReader::new(file))
+            .with_context(|| format!("Failed to read from `{}`", filepath.display()))
+    }
+}
+
+/// Reads pseudocode from `reader`, refusing inputs larger than [`MAX_INPUT_SIZE`].
+fn read_pseudocode(reader: impl Read) -> anyhow::Result<String> {
+    let mut pseudocode = String::new();
+    let limit = u64::try_from(MAX_INPUT_SIZE)?.saturating_add(1);
+    reader.take(limit).read_to_string(&mut pseudocode)?;
+    if pseudocode.len() > MAX_INPUT_SIZE {
+        anyhow::bail!("Input exceeds the maximum size of {MAX_INPUT_SIZE} bytes");
+    }
+    Ok(pseudocode)
+}
+
+/// Saves `output` (with the `description` shown in status messages) in the `outfilepath` file,
+/// or writes it to stdout if `outfilepath` is `None`. An existing output file is replaced only if
+/// `overwrite` is set. Output files are written atomically, so that an interrupted run never
+/// leaves a truncated file behind.
+pub fn write_output(
+    outfilepath: Option<&Path>,
+    output: &str,
+    description: &str,
+    overwrite: bool,
+    reporter: Reporter,
+) -> anyhow::Result<()> {
+    if let Some(path) = outfilepath {
+        reporter.line(format_args!(
+            "[*] Saving {description} in `{}`",
+            path.display()
+        ));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|err| OutputError::CreateFailed(parent.to_path_buf(), err))?;
+        }
+        write_atomically(path, output.as_bytes(), overwrite).map_err(Into::into)
+    } else {
+        let mut stdout = io::stdout().lock();
+        stdout
+            .write_all(output.as_bytes())
+            .and_then(|()| stdout.flush())
+            .map_err(|err| OutputError::StdoutFailed(err).into())
+    }
+}
+
+/// Returns `path` if no file exists there, or the first path with a number inserted before its
+/// extension (e.g., `test.out.1.c` for `test.out.c`) where no file exists.
+fn free_path(path: PathBuf) -> PathBuf {
+    if fs::symlink_metadata(&path).is_err() {
+        return path;
+    }
+    let stem = path.file_stem().unwrap_or_default().to_owned();
+    let extension = path.extension().map(ToOwned::to_owned);
+    (1..=usize::MAX)
+        .map(|number| {
+            let mut name = stem.clone();
+            name.push(format!(".{number}"));
+            if let Some(ext) = extension.as_deref() {
+                name.push(".");
+                name.push(ext);
+            }
+            path.with_file_name(name)
+        })
+        .find(|numbered| fs::symlink_metadata(numbered).is_err())
+        .unwrap_or(path)
+}
+
+/// Copies the `path` input file next to it, with the [`BACKUP_SUFFIX`] appended to its name, before
+/// it is rewritten in place.
+fn back_up(path: &Path, reporter: Reporter) -> Result<(), OutputError> {
+    let mut name = path.as_os_str().to_owned();
+    name.push(BACKUP_SUFFIX);
+    let backup = PathBuf::from(name);
+    reporter.line(format_args!(
+        "[*] Backing up `{}` in `{}`",
+        path.display(),
+        backup.display()
+    ));
+    fs::copy(path, &backup)
+        .map(drop)
+        .map_err(|err| OutputError::WriteFailed(backup, err))
+}
+
+/// Writes `contents` in the `path` file through a temporary file in the same directory, which is
+/// then renamed, so that readers never see a partially written file. An existing file is replaced
+/// only if `overwrite` is set; otherwise the rename fails if the file exists, so that a file
+/// created in the meantime by another process is not replaced either.
+fn write_atomically(path: &Path, contents: &[u8], overwrite: bool) -> Result<(), OutputError> {
+    let parent = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let mut prefix = OsString::from(".");
+    prefix.push(path.file_name().unwrap_or_default());
+    prefix.push(".");
+    let mut builder = tempfile::Builder::new();
+    builder.prefix(&prefix).suffix(".tmp");
+    // Like `File::create`, leave the permissions of the output file to the umask.
+    #[cfg(unix)]
+    builder.permissions(fs::Permissions::from_mode(0o666));
+    let tmpfile = builder
+        .tempfile_in(parent)
+        .map_err(|err| OutputError::CreateFailed(path.to_path_buf(), err))?;
+
+    // The temporary file is removed when dropped on failure.
+    let mut writer = BufWriter::new(tmpfile);
+    writer
+        .write_all(contents)
+        .map_err(|err| OutputError::WriteFailed(path.to_path_buf(), err))?;
+    let written = writer
+        .into_inner()
+        .map_err(|err| OutputError::WriteFailed(path.to_path_buf(), err.into_error()))?;
+    let persisted = if overwrite {
+        written.persist(path)
+    } else {
+        written.persist_noclobber(path)
+    };
+    match persisted {
+        Ok(_) => Ok(()),
+        Err(err) if err.error.kind() == io::ErrorKind::AlreadyExists => {
+            Err(OutputError::OutputExists(path.to_path_buf()))
+        }
+        Err(err) => Err(OutputError::WriteFailed(path.to_path_buf(), err.error)),
+    }
+}
+
+/// Applies variable renaming suggestions to `pseudocode` by substituting identifier tokens,
+/// returning the renamed pseudocode, the decision on each suggestion, and the replaced matches.
+///
+/// All original names are matched against `pseudocode` by [`find_renames`] before any of them is
+/// replaced, so that renaming order cannot corrupt later replacements (e.g., with chained
+/// suggestions such as `a` to `b` and `b` to `c`).
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(suggested = variables.len()))
+)]
+fn apply_renames(
+    pseudocode: &str,
+    variables: &[Variable],
+) -> (String, Vec<RenameDecision>, Vec<RenameMatch>) {
+    let matches = find_renames(pseudocode, variables);
+    let result = splice_renames(pseudocode, variables, &matches);
+
+    let mut decisions = Vec::with_capacity(variables.len());
+    for (index, variable) in variables.iter().enumerate() {
+        let (original, new) = (variable.original_name(), variable.new_name());
+        if original == new {
+            log::debug!("Skipping rename `{original}` -> `{new}`: same name");
+            decisions.push(RenameDecision::skipped(variable, RenameSkip::SameName));
+        } else if matches.iter().any(|found| found.variable() == index) {
+            decisions.push(RenameDecision::applied(variable));
+        } else {
+            log::debug!("Skipping rename `{original}` -> `{new}`: `{original}` not found");
+            decisions.push(RenameDecision::skipped(variable, RenameSkip::NotFound));
+        }
+    }
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        applied = decisions
+            .iter()
+            .filter(|decision| decision.is_applied())
+            .count(),
+        skipped = decisions
+            .iter()
+            .filter(|decision| !decision.is_applied())
+            .count(),
+        "renames applied"
+    );
+    (result, decisions, matches)
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+#[expect(clippy::expect_used, reason = "tests can use `expect`")]
+mod tests {
+    /// Pure logic tests.
+    mod helpers {
+        use super::super::*;
+        use crate::{RenameOccurrence, locate_renames};
+
+        #[test]
+        fn read_pseudocode_rejects_oversized_input() {
+            let result = read_pseudocode(io::repeat(b'a'));
+
+            assert!(result.is_err(), "oversized input accepted");
+        }
+
+        #[test]
+        fn apply_renames_substitutes_whole_words() -> anyhow::Result<()> {
+            let variables: Vec<Variable> =
+                serde_json::from_str(r#"[{"original_name":"v1","new_name":"counter"}]"#)?;
+
+            let (result, _, _) = apply_renames("int v1 = 0; v1++;", &variables);
+
+            assert_eq!(result, "int counter = 0; counter++;");
+
+            Ok(())
+        }
+
+        #[test]
+        fn apply_renames_does_not_match_substrings() -> anyhow::Result<()> {
+            let variables: Vec<Variable> =
+                serde_json::from_str(r#"[{"original_name":"len","new_name":"length"}]"#)?;
+
+            let (result, _, _) = apply_renames("strlen(len)", &variables);
+
+            assert_eq!(result, "strlen(length)");
+
+            Ok(())
+        }
+
+        #[test]
+        fn apply_renames_with_no_variables_is_identity() {
+            let pseudocode = "int v1 = 0;";
+
+            let (result, _, _) = apply_renames(pseudocode, &[]);
+
+            assert_eq!(result, pseudocode);
+        }
+
+        #[test]
+        fn apply_renames_leaves_comments_and_literals_alone() -> anyhow::Result<()> {
+            let variables: Vec<Variable> =
+                serde_json::from_str(r#"[{"original_name":"v1","new_name":"len"}]"#)?;
+
+            let (result, _, _) = apply_renames(
+                "// v1\nv1 = strlen(\"v1\"); /* v1 */ return v1;",
+                &variables,
+            );
+
+            assert_eq!(result, "// v1\nlen = strlen(\"v1\"); /* v1 */ return len;");
+
+            Ok(())
+        }
+
+        #[test]
+        fn apply_renames_applies_multiple_variables_independently() -> anyhow::Result<()> {
+            let variables: Vec<Variable> = serde_json::from_str(
+                r#"[{"original_name":"v1","new_name":"index"},{"original_name":"v2","new_name":"count"}]"#,
+            )?;
+
+            let (result, _, _) = apply_renames("int v1 = 0; int v2 = 0; v1 += v2;", &variables);
+
+            assert_eq!(result, "int index = 0; int count = 0; index += count;");
+
+            Ok(())
+        }
+
+        #[test]
+        fn apply_renames_replaces_exactly_the_located_occurrences() -> anyhow::Result<()> {
+            let variables: Vec<Variable> = serde_json::from_str(
+                r#"[{"original_name":"a1","new_name":"v2"},{"original_name":"v2","new_name":"len"}]"#,
+            )?;
+            let pseudocode = "int f(int a1, int v2) { return a1 + v2; } // a1";
+
+            let (result, _, matches) = apply_renames(pseudocode, &variables);
+
+            assert_eq!(result, "int f(int v2, int len) { return v2 + len; } // a1");
+            let located: Vec<_> = locate_renames(pseudocode, &variables)
+                .iter()
+                .map(RenameOccurrence::range)
+                .collect();
+            let applied: Vec<_> = matches.iter().map(RenameMatch::range).collect();
+            assert_eq!(located, applied);
+
+            Ok(())
+        }
+
+        #[test]
+        fn apply_renames_records_skipped_suggestions() -> anyhow::Result<()> {
+            let variables: Vec<Variable> = serde_json::from_str(
+                r#"[{"original_name":"v1","new_name":"v1"},{"original_name":"v9","new_name":"count"},{"original_name":"v2","new_name":"index"}]"#,
+            )?;
+
+            let (result, decisions, _) = apply_renames("int v2 = 0;", &variables);
+
+            assert_eq!(result, "int index = 0;");
+            assert_eq!(
+                decisions,
+                [
+                    RenameDecision::skipped(variables.first().expect("v1"), RenameSkip::SameName),
+                    RenameDecision::skipped(variables.get(1).expect("v9"), RenameSkip::NotFound),
+                    RenameDecision::applied(variables.get(2).expect("v2")),
+                ]
+            );
+
+            Ok(())
+        }
+
+        #[test]
+        fn write_atomically_replaces_only_when_overwriting() -> anyhow::Result<()> {
+            let tmpdir = tempfile::tempdir()?;
+            let filepath = tmpdir.path().join("a.out.c");
+            fs::write(&filepath, "keep me")?;
+
+            let refused = write_atomically(&filepath, b"new", false);
+            write_atomically(&tmpdir.path().join("b.out.c"), b"new", false)?;
+
+            assert!(
+                matches!(refused, Err(OutputError::OutputExists(..))),
+                "existing file replaced"
+            );
+            assert_eq!(fs::read_to_string(&filepath)?, "keep me");
+            write_atomically(&filepath, b"replaced", true)?;
+            assert_eq!(fs::read_to_string(&filepath)?, "replaced");
+            let mut entries = fs::read_dir(tmpdir.path())?
+                .map(|entry| entry.map(|dirent| dirent.file_name()))
+                .collect::<Result<Vec<_>, _>>()?;
+            entries.sort();
+            assert_eq!(entries, ["a.out.c", "b.out.c"], "temporary files left");
+
+            Ok(())
+        }
+
+        #[cfg(unix)]
+        #[test]
+        fn write_atomically_does_not_replace_dangling_symlinks() -> anyhow::Result<()> {
+            use std::os::unix::fs::symlink;
+
+            let tmpdir = tempfile::tempdir()?;
+            let filepath = tmpdir.path().join("a.out.c");
+            symlink(tmpdir.path().join("missing"), &filepath)?;
+
+            let refused = write_atomically(&filepath, b"new", false);
+
+            assert!(
+                matches!(refused, Err(OutputError::OutputExists(..))),
+                "dangling symlink replaced"
+            );
+            assert!(fs::symlink_metadata(&filepath)?.is_symlink());
+            assert_eq!(
+                fs::read_dir(tmpdir.path())?.count(),
+                1,
+                "temporary file left"
+            );
+
+            Ok(())
+        }
+
+        #[test]
+        fn free_path_numbers_existing_outputs() -> anyhow::Result<()> {
+            let tmpdir = tempfile::tempdir()?;
+            let filepath = tmpdir.path().join("test.out.c");
+
+            assert_eq!(free_path(filepath.clone()), filepath);
+            fs::write(&filepath, "taken")?;
+            assert_eq!(
+                free_path(filepath.clone()),
+                tmpdir.path().join("test.out.1.c")
+            );
+            fs::write(tmpdir.path().join("test.out.1.c"), "taken")?;
+            assert_eq!(free_path(filepath), tmpdir.path().join("test.out.2.c"));
+            fs::write(tmpdir.path().join("report"), "taken")?;
+            assert_eq!(
+                free_path(tmpdir.path().join("report")),
+                tmpdir.path().join("report.1")
+            );
+
+            Ok(())
+        }
+
+        #[test]
+        fn format_summary_tabulates_file_outcomes() {
+            let mut summary = RunSummary {
+                succeeded: vec!["a.c".into(), "b.c".into(), "c.c".into()],
+                failed: vec![("d.c".into(), anyhow::anyhow!("failed"))],
+                skipped: vec!["e.c".into()],
+                deduplicated: vec!["c.c".into()],
+                interrupted: Vec::new(),
+            };
+
+            assert_eq!(
+                format_summary(&summary),
+                "    Analyzed:    3 (1 deduplicated)\n    Skipped:     1\n    Failed:      1\n"
+            );
+
+            summary.interrupted = vec!["f.c".into(), "g.c".into()];
+            assert!(
+                format_summary(&summary).ends_with("    Interrupted: 2\n"),
+                "interrupted files missing"
+            );
+        }
+
+        #[test]
+        fn batch_report_lists_errors_with_their_causes() -> anyhow::Result<()> {
+            let err =
+                anyhow::anyhow!("stream did not contain valid UTF-8").context("Failed to read");
+            let summary = RunSummary {
+                succeeded: vec![PathBuf::from("a.c")],
+                failed: vec![(PathBuf::from("b.c"), err)],
+                skipped: Vec::new(),
+                deduplicated: Vec::new(),
+                interrupted: vec![PathBuf::from("c.c")],
+            };
+
+            let (one, none): (usize, usize) = (1, 0);
+            let value = serde_json::to_value(BatchReport::new(&summary))?;
+            assert_eq!(
+                value,
+                serde_json::json!({
+                    "status": "summary",
+                    "succeeded": one,
+                    "failed": one,
+                    "skipped": none,
+                    "deduplicated": none,
+                    "interrupted": one,
+                    "errors": [{
+                        "input": "b.c",
+                        "error": "Failed to read: stream did not contain valid UTF-8",
+                        "causes": ["Failed to read", "stream did not contain valid UTF-8"],
+                    }],
+                })
+            );
+
+            Ok(())
+        }
+    }
+
+    /// Tests for the Ollama-dependent runs.
+    mod api {
+        use std::fs::{self, File};
+
+        use crate::run;
+
+        const VALID_PSEUDOCODE_FILEPATH: &str = "./tests/data/hello.c";
+
+        #[test]
+        #[ignore = "requires a live Ollama instance"]
+        fn run_works() -> anyhow::Result<()> {
+            let tmpdir = tempfile::tempdir()?;
+            let filepath = tmpdir.path().join("test.c");
+            fs::copy(VALID_PSEUDOCODE_FILEPATH, &filepath)?;
+            let outfile = tmpdir.path().join("test.out.c");
+
+            run(&filepath)?;
+
+            assert!(outfile.exists(), "output file {outfile:?} does not exist");
+            assert!(
+                outfile.metadata()?.len() > 0,
+                "output file {outfile:?} is empty"
+            );
+
+            Ok(())
+        }
+
+        #[test]
+        #[ignore = "requires a live Ollama instance"]
+        fn run_fails_if_output_file_already_exists() -> anyhow::Result<()> {
+            let tmpdir = tempfile::tempdir()?;
+            let filepath = tmpdir.path().join("test.c");
+            fs::copy(VALID_PSEUDOCODE_FILEPATH, &filepath)?;
+            let outfile = tmpdir.path().join("test.out.c");
+            File::create(&outfile)?;
+
+            let result = run(&filepath);
+
+            assert!(result.is_err(), "run succeeded unexpectedly");
+            assert!(
+                outfile.metadata()?.len() == 0,
+                "output file was overwritten"
+            );
+
+            Ok(())
+        }
+
+        #[test]
+        #[ignore = "requires a live Ollama instance"]
+        fn run_with_empty_file_fails() {
+            let tmpdir = tempfile::tempdir().expect("failed to create temporary directory");
+            let filepath = tmpdir.path().join("test.c");
+            File::create(&filepath).expect("failed to create test file");
+            let outfile = tmpdir.path().join("test.out.c");
+
+            let result = run(&filepath);
+
+            assert!(result.is_err(), "run succeeded unexpectedly");
+            assert!(!outfile.exists(), "output file {outfile:?} exists");
+        }
+
+        #[test]
+        fn run_with_invalid_input_filepath_fails() {
+            let tmpdir = tempfile::tempdir().expect("failed to create temporary directory");
+            let filepath = tmpdir.path().join("test.c");
+            let outfile = tmpdir.path().join("test.out.c");
+
+            let result = run(&filepath);
+
+            assert!(result.is_err(), "run succeeded unexpectedly");
+            assert!(!outfile.exists(), "output file {outfile:?} exists");
+        }
+    }
+}
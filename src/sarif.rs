@@ -0,0 +1,385 @@
+//! Export the findings of analyses as a SARIF 2.1.0 log, for code-review and triage tooling.
+
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+
+use serde_json::json;
+
+use crate::lexer::{TokenKind, tokenize};
+use crate::location::{LineIndex, Position};
+use crate::report::FunctionReport;
+use crate::{FunctionSpan, OneiromancerResults, select_function, split_functions};
+
+/// URI of the JSON schema of SARIF 2.1.0 logs.
+pub const SARIF_SCHEMA: &str =
+    "https://docs.oasis-open.org/sarif/sarif/v2.1.0/errata01/os/schemas/sarif-schema-2.1.0.json";
+/// Version of the SARIF format.
+const SARIF_VERSION: &str = "2.1.0";
+
+/// Rule that a [`Finding`] belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rule {
+    /// Description and suggested name of a function.
+    FunctionDescription,
+    /// Variable renaming suggestion.
+    VariableRename,
+}
+
+impl Rule {
+    /// All rules, in the order of the `rules` array of the log.
+    const ALL: [Self; 2] = [Self::FunctionDescription, Self::VariableRename];
+
+    /// Returns the identifier of the rule.
+    const fn id(self) -> &'static str {
+        match self {
+            Self::FunctionDescription => "function-description",
+            Self::VariableRename => "variable-rename",
+        }
+    }
+
+    /// Returns the index of the rule in the `rules` array of the log.
+    const fn index(self) -> usize {
+        match self {
+            Self::FunctionDescription => 0,
+            Self::VariableRename => 1,
+        }
+    }
+
+    /// Returns the SARIF `reportingDescriptor` object of the rule.
+    fn descriptor(self) -> serde_json::Value {
+        let (name, description) = match self {
+            Self::FunctionDescription => (
+                "FunctionDescription",
+                "Description and suggested name of a function.",
+            ),
+            Self::VariableRename => ("VariableRename", "Suggested name of a variable."),
+        };
+        json!({
+            "id": self.id(),
+            "name": name,
+            "shortDescription": { "text": description },
+        })
+    }
+}
+
+/// Finding of the analysis of a pseudocode file, located at an identifier in the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// Rule of the finding.
+    rule: Rule,
+    /// Path of the input file.
+    input: PathBuf,
+    /// Position of the first character of the identifier.
+    start: Position,
+    /// Position right after the last character of the identifier.
+    end: Position,
+    /// Message, with the suggested replacement.
+    message: String,
+}
+
+impl Finding {
+    /// Returns the SARIF `result` object of the finding.
+    fn to_result(&self) -> serde_json::Value {
+        json!({
+            "ruleId": self.rule.id(),
+            "ruleIndex": self.rule.index(),
+            "kind": "informational",
+            "level": "none",
+            "message": { "text": self.message },
+            "locations": [{
+                "physicalLocation": {
+                    "artifactLocation": { "uri": artifact_uri(&self.input) },
+                    "region": {
+                        "startLine": self.start.line(),
+                        "startColumn": self.start.column(),
+                        "endLine": self.end.line(),
+                        "endColumn": self.end.column(),
+                    },
+                },
+            }],
+        })
+    }
+}
+
+/// Locates the findings of the analysis of `pseudocode` in the `input` file: the description of
+/// each function, at its name, and each variable renaming suggestion, at the first occurrence of
+/// the original name.
+///
+/// Whole-file `results` are located within the function selected by `selected` (see
+/// [`select_function`]), if any, and the `functions` reports within the corresponding
+/// definitions. Suggestions whose original name is not found are left out.
+pub fn locate_findings(
+    input: &Path,
+    pseudocode: &str,
+    results: Option<&OneiromancerResults>,
+    functions: &[FunctionReport],
+    selected: Option<&str>,
+) -> Vec<Finding> {
+    let locator = Locator::new(input, pseudocode);
+    let spans = split_functions(pseudocode);
+    let mut findings = Vec::new();
+
+    if let Some(whole) = results {
+        let target = selected.map_or_else(
+            || spans.first(),
+            |query| select_function(&spans, query).ok(),
+        );
+        let scope = target.map_or(0..pseudocode.len(), FunctionSpan::range);
+        findings.extend(locator.locate(whole, target.map(FunctionSpan::name), scope));
+    }
+    for function in functions {
+        let target = spans.iter().find(|span| span.name() == function.name());
+        if let Some((analyzed, span)) = function.results().zip(target) {
+            findings.extend(locator.locate(analyzed, Some(span.name()), span.range()));
+        }
+    }
+    findings
+}
+
+/// Formats a SARIF 2.1.0 log with a single run of oneiromancer that holds all `findings`.
+pub fn format_sarif(findings: &[Finding]) -> String {
+    let log = json!({
+        "$schema": SARIF_SCHEMA,
+        "version": SARIF_VERSION,
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "rules": Rule::ALL.map(Rule::descriptor),
+                },
+            },
+            "columnKind": "unicodeCodePoints",
+            "results": findings.iter().map(Finding::to_result).collect::<Vec<_>>(),
+        }],
+    });
+    let mut formatted = serde_json::to_string_pretty(&log).unwrap_or_default();
+    formatted.push('\n');
+    formatted
+}
+
+/// Locates identifiers in the pseudocode of an input file.
+struct Locator<'a> {
+    /// Path of the input file.
+    input: &'a Path,
+    /// Pseudocode in the input file.
+    pseudocode: &'a str,
+    /// Byte ranges of the identifiers in the pseudocode, in order.
+    identifiers: Vec<Range<usize>>,
+    /// Index of the lines of the pseudocode.
+    lines: LineIndex<'a>,
+}
+
+impl<'a> Locator<'a> {
+    /// Creates a new [`Locator`] of the identifiers in `pseudocode`.
+    fn new(input: &'a Path, pseudocode: &'a str) -> Self {
+        Self {
+            input,
+            pseudocode,
+            identifiers: tokenize(pseudocode)
+                .into_iter()
+                .filter(|token| token.kind == TokenKind::Identifier)
+                .map(|token| token.range)
+                .collect(),
+            lines: LineIndex::new(pseudocode),
+        }
+    }
+
+    /// Returns the findings of `results` within the `scope` byte range, with the description
+    /// located at the function `name` (or at the start of `scope`, if not found).
+    fn locate(
+        &self,
+        results: &OneiromancerResults,
+        name: Option<&str>,
+        scope: Range<usize>,
+    ) -> Vec<Finding> {
+        let described = name.map_or_else(
+            || format!("Suggested function name `{}`", results.function_name()),
+            |original| {
+                format!(
+                    "Function `{original}` could be renamed to `{}`",
+                    results.function_name()
+                )
+            },
+        );
+        let anchor = name
+            .and_then(|original| self.find(original, &scope))
+            .unwrap_or(scope.start..scope.start);
+        let mut findings = vec![self.finding(
+            Rule::FunctionDescription,
+            anchor,
+            format!("{described}: {}", results.comment()),
+        )];
+
+        findings.extend(
+            results
+                .variables()
+                .iter()
+                .filter(|variable| variable.original_name() != variable.new_name())
+                .filter_map(|variable| {
+                    let range = self.find(variable.original_name(), &scope)?;
+                    Some(self.finding(
+                        Rule::VariableRename,
+                        range,
+                        format!(
+                            "Rename `{}` to `{}`",
+                            variable.original_name(),
+                            variable.new_name()
+                        ),
+                    ))
+                }),
+        );
+        findings
+    }
+
+    /// Returns the byte range of the first occurrence of the `name` identifier within `scope`.
+    fn find(&self, name: &str, scope: &Range<usize>) -> Option<Range<usize>> {
+        self.identifiers
+            .iter()
+            .find(|range| {
+                scope.contains(&range.start) && self.pseudocode.get((*range).clone()) == Some(name)
+            })
+            .cloned()
+    }
+
+    /// Returns a [`Finding`] of `rule` at the `range` bytes of the pseudocode.
+    fn finding(&self, rule: Rule, range: Range<usize>, message: String) -> Finding {
+        Finding {
+            rule,
+            input: self.input.to_path_buf(),
+            start: self.lines.position(range.start),
+            end: self.lines.position(range.end),
+            message,
+        }
+    }
+}
+
+/// Returns the URI of the `path` artifact: a `file` URI if `path` is absolute, or a relative
+/// reference otherwise, with reserved characters percent-encoded.
+fn artifact_uri(path: &Path) -> String {
+    let segments: Vec<String> = path
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(segment) => Some(percent_encode(&segment.to_string_lossy())),
+            Component::ParentDir => Some("..".to_owned()),
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => None,
+        })
+        .collect();
+    let relative = segments.join("/");
+    if path.is_absolute() {
+        format!("file:///{relative}")
+    } else {
+        relative
+    }
+}
+
+/// Percent-encodes all bytes of `segment` except unreserved URI characters.
+pub fn percent_encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+                char::from(byte).to_string()
+            } else {
+                format!("%{byte:02X}")
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locate_findings_anchors_renames_at_first_occurrence_in_function() -> anyhow::Result<()> {
+        let pseudocode = "int first(int a1)\n{\n  return a1;\n}\n\nint second(int a1)\n{\n  // v1\n  int v1 = a1;\n  return v1;\n}\n";
+        let results: OneiromancerResults = serde_json::from_str(
+            r#"{"function_name":"init","comment":"Initializes.","variables":[{"original_name":"a1","new_name":"ctx"},{"original_name":"v1","new_name":"len"},{"original_name":"v9","new_name":"gone"}]}"#,
+        )?;
+
+        let findings = locate_findings(
+            Path::new("dir/a b.c"),
+            pseudocode,
+            Some(&results),
+            &[],
+            Some("second"),
+        );
+        let located: Vec<_> = findings
+            .iter()
+            .map(|finding| {
+                (
+                    finding.rule,
+                    finding.start.line(),
+                    finding.start.column(),
+                    finding.end.column(),
+                    finding.message.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            located,
+            [
+                (
+                    Rule::FunctionDescription,
+                    6,
+                    5,
+                    11,
+                    "Function `second` could be renamed to `init`: Initializes."
+                ),
+                (Rule::VariableRename, 6, 16, 18, "Rename `a1` to `ctx`"),
+                (Rule::VariableRename, 9, 7, 9, "Rename `v1` to `len`"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn format_sarif_emits_rules_and_encoded_locations() -> anyhow::Result<()> {
+        let results: OneiromancerResults = serde_json::from_str(
+            r#"{"function_name":"main","comment":"Entry.","variables":[{"original_name":"v1","new_name":"count"}]}"#,
+        )?;
+        let findings = locate_findings(
+            Path::new("dir/a b.c"),
+            "int v1;\n",
+            Some(&results),
+            &[],
+            None,
+        );
+
+        let log: serde_json::Value = serde_json::from_str(&format_sarif(&findings))?;
+        assert_eq!(log.pointer("/version"), Some(&json!("2.1.0")));
+        assert_eq!(
+            log.pointer("/runs/0/tool/driver/rules/1/id"),
+            Some(&json!("variable-rename"))
+        );
+        let result = log
+            .pointer("/runs/0/results/1")
+            .ok_or_else(|| anyhow::anyhow!("missing result"))?;
+        assert_eq!(
+            result
+                .pointer("/ruleIndex")
+                .and_then(serde_json::Value::as_u64),
+            Some(1)
+        );
+        assert_eq!(
+            result.pointer("/locations/0/physicalLocation/artifactLocation/uri"),
+            Some(&json!("dir/a%20b.c"))
+        );
+        assert_eq!(
+            result
+                .pointer("/locations/0/physicalLocation/region/startColumn")
+                .and_then(serde_json::Value::as_u64),
+            Some(5)
+        );
+        assert_eq!(
+            artifact_uri(Path::new("/tmp/x/hello.c")),
+            "file:///tmp/x/hello.c"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,672 @@
+//! Serve analyses over HTTP, for editor plugins and web front ends.
+
+use std::io::{self, BufRead, BufReader, Read};
+use std::net::{TcpListener, TcpStream};
+use std::num::NonZeroUsize;
+use std::sync::mpsc::{self, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use serde_json::json;
+use thiserror::Error;
+
+use crate::{AnalysisContext, MAX_INPUT_SIZE, Oneiromancer, OneiromancerError};
+
+/// Maximum size of the request line and headers of a request, in bytes.
+pub const MAX_HEADER_SIZE: usize = 16 * 1024;
+/// Default number of requests handled at the same time.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+/// Default time allowed to clients for sending a request and receiving the response.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Options that control the HTTP server started with [`serve`].
+#[derive(Debug, Clone)]
+pub struct ServeOptions {
+    /// Oneiromancer client, shared by all requests.
+    client: Oneiromancer,
+    /// Number of requests handled at the same time.
+    concurrency: NonZeroUsize,
+    /// Maximum size of request bodies, in bytes.
+    max_body_size: usize,
+    /// Time allowed to clients for sending a request and receiving the response.
+    timeout: Duration,
+}
+
+impl ServeOptions {
+    /// Creates new [`ServeOptions`] with default values.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the Oneiromancer client used for all analyses. Its clones share the HTTP agent, so
+    /// that connections to the Ollama server are reused across requests.
+    #[must_use]
+    pub fn client(mut self, client: Oneiromancer) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Sets the number of requests handled at the same time (default: [`DEFAULT_CONCURRENCY`]).
+    /// As many more connections wait in a queue, and the ones beyond are answered with
+    /// `503 Service Unavailable`.
+    #[must_use]
+    pub const fn concurrency(mut self, concurrency: NonZeroUsize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Sets the maximum size of request bodies, in bytes (default: [`MAX_INPUT_SIZE`]). Larger
+    /// requests are answered with `413 Content Too Large`.
+    #[must_use]
+    pub const fn max_body_size(mut self, size: usize) -> Self {
+        self.max_body_size = size;
+        self
+    }
+
+    /// Sets the time allowed to clients for sending a whole request, and then for receiving the
+    /// response (default: [`DEFAULT_TIMEOUT`]). The analysis itself is not limited.
+    #[must_use]
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl Default for ServeOptions {
+    fn default() -> Self {
+        Self {
+            client: Oneiromancer::default(),
+            concurrency: NonZeroUsize::new(DEFAULT_CONCURRENCY).unwrap_or(NonZeroUsize::MIN),
+            max_body_size: MAX_INPUT_SIZE,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+/// Failure in reading an HTTP request, answered with the corresponding status code.
+#[derive(Error, Debug)]
+enum RequestError {
+    /// Malformed request.
+    #[error("Malformed request: {0}")]
+    Malformed(&'static str),
+    /// Request line and headers larger than [`MAX_HEADER_SIZE`].
+    #[error("Request headers exceed {MAX_HEADER_SIZE} bytes")]
+    HeadersTooLarge,
+    /// Request body with no `Content-Length`.
+    #[error("Missing `Content-Length` header")]
+    LengthRequired,
+    /// Request body larger than the maximum size.
+    #[error("Request body exceeds {0} bytes")]
+    BodyTooLarge(usize),
+    /// Request with a transfer coding, which is not supported.
+    #[error("Transfer codings are not supported")]
+    TransferCoding,
+    /// Request not received in time.
+    #[error("Request not received in time")]
+    Timeout,
+    /// Failure in reading the request.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl RequestError {
+    /// Returns the response to the failed request.
+    fn response(&self) -> Response {
+        let status = match *self {
+            Self::Malformed(_) | Self::Io(_) => 400,
+            Self::HeadersTooLarge => 431,
+            Self::LengthRequired => 411,
+            Self::BodyTooLarge(_) => 413,
+            Self::TransferCoding => 501,
+            Self::Timeout => 408,
+        };
+        Response::error(status, &self.to_string())
+    }
+}
+
+/// HTTP request.
+#[derive(Debug)]
+struct Request {
+    /// Request method (e.g., `POST`).
+    method: String,
+    /// Request path, without the query string.
+    path: String,
+    /// Media type of the body, if any, in lowercase and without parameters.
+    media_type: Option<String>,
+    /// Request body.
+    body: Vec<u8>,
+}
+
+/// HTTP response with a JSON body.
+#[derive(Debug)]
+struct Response {
+    /// Status code.
+    status: u16,
+    /// Methods allowed on the resource, for `405 Method Not Allowed` responses.
+    allow: Option<&'static str>,
+    /// JSON body.
+    body: serde_json::Value,
+}
+
+impl Response {
+    /// Creates a response with `status` and the JSON `body`.
+    const fn json(status: u16, body: serde_json::Value) -> Self {
+        Self {
+            status,
+            allow: None,
+            body,
+        }
+    }
+
+    /// Creates an error response with `status` and the `message` in the `error` field.
+    fn error(status: u16, message: &str) -> Self {
+        Self::json(status, json!({ "error": message }))
+    }
+
+    /// Returns the reason phrase of the status code.
+    const fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            408 => "Request Timeout",
+            411 => "Length Required",
+            413 => "Content Too Large",
+            431 => "Request Header Fields Too Large",
+            501 => "Not Implemented",
+            502 => "Bad Gateway",
+            503 => "Service Unavailable",
+            504 => "Gateway Timeout",
+            _ => "Internal Server Error",
+        }
+    }
+
+    /// Writes the response to `writer`, closing the connection afterwards.
+    fn write_to(&self, writer: &mut impl io::Write) -> io::Result<()> {
+        let body = self.body.to_string();
+        write!(
+            writer,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.status,
+            self.reason(),
+            body.len()
+        )?;
+        if let Some(methods) = self.allow {
+            write!(writer, "Allow: {methods}\r\n")?;
+        }
+        if self.status == 503 {
+            writer.write_all(b"Retry-After: 1\r\n")?;
+        }
+        write!(writer, "\r\n{body}")?;
+        writer.flush()
+    }
+}
+
+/// JSON payload of analysis requests.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct AnalyzeRequest {
+    /// Pseudocode to analyze.
+    code: String,
+    /// Language of the generated description, if not the model default.
+    #[serde(default)]
+    language: Option<String>,
+    /// Options passed through to the model (e.g., `temperature`).
+    #[serde(default)]
+    options: serde_json::Map<String, serde_json::Value>,
+    /// Extra context appended to the prompt.
+    #[serde(default)]
+    context: RequestContext,
+}
+
+/// Extra context in analysis requests (see [`AnalysisContext`]).
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct RequestContext {
+    /// Disassembly excerpt of the analyzed function.
+    disassembly: String,
+    /// String literals referenced by the analyzed function.
+    strings: Vec<String>,
+    /// Definitions of the types referenced by the analyzed function.
+    types: Vec<String>,
+    /// Free-form notes.
+    notes: String,
+}
+
+/// Payload of Ollama-compatible generation requests, such as the ones of the aidapal IDA plugin.
+/// Other Ollama fields (e.g., `stream` and `format`) are accepted and ignored.
+#[derive(Deserialize, Debug)]
+struct GenerateRequest {
+    /// Model to use, if not the configured one.
+    #[serde(default)]
+    model: Option<String>,
+    /// Pseudocode to analyze.
+    prompt: String,
+    /// Options passed through to the model (e.g., `temperature`).
+    #[serde(default)]
+    options: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Reader that fails with [`io::ErrorKind::TimedOut`] once `deadline` has passed, so that clients
+/// that send requests slowly cannot hold a worker indefinitely.
+struct DeadlineReader<'a> {
+    /// Connection to read from.
+    stream: &'a TcpStream,
+    /// Time by which the whole request must be read.
+    deadline: Instant,
+}
+
+#[expect(
+    clippy::missing_trait_methods,
+    reason = "the provided methods are built on `read`"
+)]
+impl Read for DeadlineReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        self.stream.set_read_timeout(Some(remaining))?;
+        self.stream.read(buf)
+    }
+}
+
+/// Serves analyses over HTTP on `listener` with `options`, until the process is terminated.
+///
+/// The server answers the following requests, with JSON bodies:
+///
+/// - `POST /analyze` analyzes the pseudocode in the body, either as plain text or as a JSON
+///   object with the `code` and optionally the `language`, model `options`, and `context`
+///   (`disassembly`, `strings`, `types`, and `notes`), and returns the
+///   [`OneiromancerResults`](crate::OneiromancerResults).
+/// - `POST /api/generate` accepts Ollama generation requests, such as the ones of the aidapal IDA
+///   plugin, analyzing the `prompt` as pseudocode and returning the results serialized in the
+///   `response` field of a single, non-streamed Ollama response. The `model` and `options` of the
+///   request are honored, while the responses go through the retries and cache of the client.
+/// - `GET /healthz` checks that the Ollama server is reachable and the model is available.
+///
+/// Each connection carries a single request. Requests are handled by a pool of worker threads,
+/// whose size is the concurrency limit set in `options`.
+pub fn serve(listener: &TcpListener, options: &ServeOptions) {
+    let workers = options.concurrency.get();
+    let (sender, queued) = mpsc::sync_channel::<TcpStream>(workers);
+    let receiver = Arc::new(Mutex::new(queued));
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            let queue = Arc::clone(&receiver);
+            scope.spawn(move || {
+                loop {
+                    let next = queue.lock().ok().and_then(|guard| guard.recv().ok());
+                    let Some(stream) = next else {
+                        break;
+                    };
+                    handle_connection(&stream, options);
+                }
+            });
+        }
+
+        for incoming in listener.incoming() {
+            let stream = match incoming {
+                Ok(stream) => stream,
+                Err(err) => {
+                    log::warn!("Failed to accept connection: {err}");
+                    continue;
+                }
+            };
+            match sender.try_send(stream) {
+                Ok(()) => (),
+                Err(TrySendError::Full(busy)) => {
+                    log::warn!("All workers busy, rejecting connection");
+                    _ = busy.set_write_timeout(Some(options.timeout));
+                    let response = Response::error(503, "Server busy, try again later");
+                    _ = response.write_to(&mut &busy);
+                }
+                Err(TrySendError::Disconnected(_)) => break,
+            }
+        }
+        drop(sender);
+    });
+}
+
+/// Reads a request from `stream`, handles it, and writes the response.
+fn handle_connection(stream: &TcpStream, options: &ServeOptions) {
+    let peer = stream
+        .peer_addr()
+        .map_or_else(|_| "unknown peer".to_owned(), |addr| addr.to_string());
+    let mut reader = BufReader::new(DeadlineReader {
+        stream,
+        deadline: Instant::now()
+            .checked_add(options.timeout)
+            .unwrap_or_else(Instant::now),
+    });
+    let (line, response) = match read_request(&mut reader, options.max_body_size) {
+        Ok(request) => (
+            format!("{} {}", request.method, request.path),
+            route(&request, options),
+        ),
+        Err(err) => ("invalid request".to_owned(), err.response()),
+    };
+    log::info!("{peer}: {line} -> {}", response.status);
+
+    _ = stream.set_write_timeout(Some(options.timeout));
+    if let Err(err) = response.write_to(&mut &*stream) {
+        log::warn!("{peer}: failed to send response: {err}");
+    }
+}
+
+/// Reads a request from `reader`, with a body of at most `max_body_size` bytes.
+fn read_request(reader: &mut impl BufRead, max_body_size: usize) -> Result<Request, RequestError> {
+    let mut head = reader
+        .by_ref()
+        .take(u64::try_from(MAX_HEADER_SIZE).unwrap_or(u64::MAX));
+    let request_line = read_line(&mut head)?;
+    let mut parts = request_line.split(' ');
+    let (Some(method), Some(target), Some(version), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(RequestError::Malformed("invalid request line"));
+    };
+    if !version.starts_with("HTTP/1.") {
+        return Err(RequestError::Malformed("unsupported HTTP version"));
+    }
+    let path = target.split_once('?').map_or(target, |(path, _)| path);
+
+    let mut content_length = None;
+    let mut media_type = None;
+    loop {
+        let line = read_line(&mut head)?;
+        if line.is_empty() {
+            break;
+        }
+        let (name, raw_value) = line
+            .split_once(':')
+            .ok_or(RequestError::Malformed("invalid header"))?;
+        let value = raw_value.trim();
+        match name.to_ascii_lowercase().as_str() {
+            "content-length" => {
+                let length = value
+                    .parse::<usize>()
+                    .ok()
+                    .ok_or(RequestError::Malformed("invalid Content-Length"))?;
+                if content_length.is_some_and(|previous| previous != length) {
+                    return Err(RequestError::Malformed("conflicting Content-Length"));
+                }
+                content_length = Some(length);
+            }
+            "transfer-encoding" => return Err(RequestError::TransferCoding),
+            "content-type" => {
+                let essence = value.split(';').next().unwrap_or_default();
+                media_type = Some(essence.trim().to_ascii_lowercase());
+            }
+            _ => (),
+        }
+    }
+
+    let length = match (content_length, method) {
+        (Some(length), _) => length,
+        (None, "POST" | "PUT") => return Err(RequestError::LengthRequired),
+        (None, _) => 0,
+    };
+    if length > max_body_size {
+        return Err(RequestError::BodyTooLarge(max_body_size));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).map_err(read_error)?;
+    Ok(Request {
+        method: method.to_owned(),
+        path: path.to_owned(),
+        media_type,
+        body,
+    })
+}
+
+/// Reads a line terminated by CRLF (or just LF) from the request head in `reader`, without the
+/// terminator.
+fn read_line(reader: &mut impl BufRead) -> Result<String, RequestError> {
+    let mut line = Vec::new();
+    reader.read_until(b'\n', &mut line).map_err(read_error)?;
+    if line.pop() != Some(b'\n') {
+        return Err(if line.is_empty() {
+            RequestError::Malformed("incomplete request")
+        } else {
+            RequestError::HeadersTooLarge
+        });
+    }
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    String::from_utf8(line)
+        .ok()
+        .ok_or(RequestError::Malformed("request head is not UTF-8"))
+}
+
+/// Returns the [`RequestError`] corresponding to the `err` read failure.
+fn read_error(err: io::Error) -> RequestError {
+    if matches!(
+        err.kind(),
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+    ) {
+        RequestError::Timeout
+    } else {
+        RequestError::Io(err)
+    }
+}
+
+/// Returns the response to `request`.
+fn route(request: &Request, options: &ServeOptions) -> Response {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/analyze") => analyze(request, &options.client),
+        ("POST", "/api/generate") => generate(request, &options.client),
+        ("GET" | "HEAD", "/healthz") => health(&options.client),
+        (_, "/analyze" | "/api/generate") => Response {
+            allow: Some("POST"),
+            ..Response::error(405, "Use POST to analyze pseudocode")
+        },
+        (_, "/healthz") => Response {
+            allow: Some("GET"),
+            ..Response::error(405, "Use GET to check the health of the server")
+        },
+        _ => Response::error(404, "Not found"),
+    }
+}
+
+/// Analyzes the pseudocode in the body of `request` with `client`.
+fn analyze(request: &Request, client: &Oneiromancer) -> Response {
+    let payload = if request.media_type.as_deref() == Some("application/json") {
+        match serde_json::from_slice::<AnalyzeRequest>(&request.body) {
+            Ok(payload) => payload,
+            Err(err) => return Response::error(400, &format!("Invalid JSON payload: {err}")),
+        }
+    } else {
+        match String::from_utf8(request.body.clone()) {
+            Ok(code) => AnalyzeRequest {
+                code,
+                language: None,
+                options: serde_json::Map::new(),
+                context: RequestContext::default(),
+            },
+            Err(_) => return Response::error(400, "Pseudocode is not valid UTF-8"),
+        }
+    };
+    if payload.code.trim().is_empty() {
+        return Response::error(400, "No pseudocode to analyze");
+    }
+
+    let mut configured = payload
+        .options
+        .into_iter()
+        .fold(client.clone(), |configured, option| {
+            configured.option(option.0, option.1)
+        });
+    if let Some(language) = payload.language {
+        configured = configured.language(language);
+    }
+    let context = AnalysisContext::new()
+        .disassembly(payload.context.disassembly)
+        .strings(&payload.context.strings)
+        .types(&payload.context.types)
+        .notes(payload.context.notes);
+
+    match configured.analyze_code_with_context(&payload.code, &context) {
+        Ok(results) => match serde_json::to_value(&results) {
+            Ok(body) => Response::json(200, body),
+            Err(err) => Response::error(500, &err.to_string()),
+        },
+        Err(err) => failure(&err),
+    }
+}
+
+/// Analyzes the pseudocode in the `prompt` of an Ollama-compatible generation `request` with
+/// `client`, answering as Ollama would with the results serialized in the `response` field.
+fn generate(request: &Request, client: &Oneiromancer) -> Response {
+    let payload = match serde_json::from_slice::<GenerateRequest>(&request.body) {
+        Ok(payload) => payload,
+        Err(err) => return Response::error(400, &format!("Invalid JSON payload: {err}")),
+    };
+    if payload.prompt.trim().is_empty() {
+        return Response::error(400, "No pseudocode to analyze");
+    }
+
+    let mut configured = payload
+        .options
+        .into_iter()
+        .fold(client.clone(), |configured, option| {
+            configured.option(option.0, option.1)
+        });
+    if let Some(model) = payload.model.filter(|model| !model.is_empty()) {
+        configured = configured.model(model);
+    }
+
+    match configured.analyze_code(&payload.prompt) {
+        Ok(results) => match serde_json::to_string(&results) {
+            Ok(response) => Response::json(
+                200,
+                json!({
+                    "model": configured.configured_model(),
+                    "response": response,
+                    "done": true,
+                    "done_reason": "stop",
+                }),
+            ),
+            Err(err) => Response::error(500, &err.to_string()),
+        },
+        Err(err) => failure(&err),
+    }
+}
+
+/// Returns the error response to a failed analysis.
+fn failure(err: &OneiromancerError) -> Response {
+    let status = match *err {
+        OneiromancerError::InvalidInput(_) => 400,
+        OneiromancerError::FileReadFailed(_) => 500,
+        OneiromancerError::OllamaQueryFailed(_)
+        | OneiromancerError::ResponseParseFailed(_)
+        | OneiromancerError::LowQualityResult(_)
+        | OneiromancerError::ServerUnreachable { .. }
+        | OneiromancerError::ModelNotFound { .. } => 502,
+        OneiromancerError::Timeout(_) => 504,
+    };
+    Response::error(status, &format!("Failed to analyze pseudocode: {err}"))
+}
+
+/// Checks that the Ollama server of `client` is reachable and that its model is available.
+fn health(client: &Oneiromancer) -> Response {
+    let model = client.configured_model();
+    let version = match client.server_version() {
+        Ok(version) => version,
+        Err(err) => {
+            return Response::json(
+                503,
+                json!({ "status": "unavailable", "error": format!("Ollama server unreachable: {err}") }),
+            );
+        }
+    };
+    match client.has_model() {
+        Ok(true) => Response::json(
+            200,
+            json!({ "status": "ok", "ollama_version": version, "model": model }),
+        ),
+        Ok(false) => Response::json(
+            503,
+            json!({ "status": "unavailable", "error": format!("Model `{model}` not found") }),
+        ),
+        Err(err) => Response::json(
+            503,
+            json!({ "status": "unavailable", "error": format!("Failed to list models: {err}") }),
+        ),
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_request_parses_head_and_body() -> anyhow::Result<()> {
+        let raw = b"POST /analyze?x=1 HTTP/1.1\r\nHost: localhost\r\nContent-Type: Application/JSON; charset=utf-8\r\ncontent-length: 4\r\n\r\nint;extra";
+
+        let request = read_request(&mut &raw[..], 16)?;
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/analyze");
+        assert_eq!(request.media_type.as_deref(), Some("application/json"));
+        assert_eq!(request.body, b"int;");
+
+        let get = read_request(&mut &b"GET /healthz HTTP/1.0\n\n"[..], 16)?;
+        assert_eq!((get.method.as_str(), get.body.len()), ("GET", 0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_request_enforces_limits() {
+        let status = |raw: &[u8]| {
+            read_request(&mut &*raw, 8).map_or_else(|err| err.response().status, |_| 200)
+        };
+
+        assert_eq!(status(b"POST /analyze HTTP/1.1\r\n\r\n"), 411);
+        assert_eq!(
+            status(b"POST /analyze HTTP/1.1\r\nContent-Length: 9\r\n\r\n123456789"),
+            413
+        );
+        assert_eq!(
+            status(b"POST /analyze HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n"),
+            501
+        );
+        let huge = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_HEADER_SIZE));
+        assert_eq!(status(huge.as_bytes()), 431);
+        assert_eq!(status(b"GET /\r\n\r\n"), 400);
+        assert_eq!(
+            status(b"POST /analyze HTTP/1.1\r\nContent-Length: 4\r\n\r\nab"),
+            400
+        );
+    }
+
+    #[test]
+    fn route_rejects_unknown_paths_and_methods() {
+        let request = |method: &str, path: &str| Request {
+            method: method.to_owned(),
+            path: path.to_owned(),
+            media_type: None,
+            body: Vec::new(),
+        };
+        let options = ServeOptions::new();
+
+        assert_eq!(route(&request("GET", "/"), &options).status, 404);
+        let wrong = route(&request("GET", "/analyze"), &options);
+        assert_eq!((wrong.status, wrong.allow), (405, Some("POST")));
+        let empty = route(&request("POST", "/analyze"), &options);
+        assert_eq!(empty.status, 400);
+        let generate = route(&request("GET", "/api/generate"), &options);
+        assert_eq!((generate.status, generate.allow), (405, Some("POST")));
+        assert_eq!(
+            route(&request("POST", "/api/generate"), &options).status,
+            400
+        );
+    }
+}
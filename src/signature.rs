@@ -0,0 +1,263 @@
+//! Parse function signatures in decompiler-style pseudocode.
+
+/// Function signature parsed from pseudocode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    /// Text that precedes the function name (return type and calling convention), verbatim.
+    prefix: String,
+    /// Text of the parameter list, verbatim.
+    params: String,
+    /// Names of the function parameters, in declaration order.
+    param_names: Vec<String>,
+}
+
+impl Signature {
+    /// Parses the signature of the first function definition in `pseudocode`.
+    ///
+    /// Comments and preprocessor lines that precede the definition are ignored. Returns `None`
+    /// if no function definition can be found.
+    pub fn parse(pseudocode: &str) -> Option<Self> {
+        let prototype = prototype(pseudocode)?;
+        let (head, params) = split_trailing_group(&prototype)?;
+        let name = last_identifier(head)?;
+        let prefix = head.get(..head.trim_end().len().saturating_sub(name.len()))?;
+
+        let param_names = split_top_level(params, ',')
+            .into_iter()
+            .map(str::trim)
+            .filter(|param| !param.is_empty() && *param != "void")
+            .filter_map(param_name)
+            .map(str::to_owned)
+            .collect();
+
+        Some(Self {
+            prefix: prefix.to_owned(),
+            params: params.trim().to_owned(),
+            param_names,
+        })
+    }
+
+    /// Returns the function prototype, declaring the function as `name`.
+    ///
+    /// Return type, calling convention and parameters are passed through verbatim.
+    pub fn prototype(&self, name: &str) -> String {
+        format!("{}{name}({})", self.prefix, self.params)
+    }
+
+    /// Returns the names of all named parameters, in declaration order.
+    pub fn param_names(&self) -> impl Iterator<Item = &str> {
+        self.param_names.iter().map(String::as_str)
+    }
+}
+
+/// Returns the name declared by a single parameter declaration, such as `char *a1` or
+/// `void (__fastcall *cb)(int)`, if any.
+fn param_name(decl: &str) -> Option<&str> {
+    if decl == "..." {
+        None
+    } else if let Some(open) = decl.find('(') {
+        // Function pointer: the name is the last identifier in the first group.
+        decl.get(open.saturating_add(1)..)
+            .and_then(|rest| rest.split(')').next())
+            .and_then(last_identifier)
+    } else {
+        // Plain declaration: skip array suffixes, then take the last identifier, if the
+        // declaration is made of more than a bare type.
+        let plain = decl.split('[').next().unwrap_or(decl).trim_end();
+        last_identifier(plain).filter(|name| plain.len() > name.len())
+    }
+}
+
+/// Extracts the prototype that precedes the first top-level `{` in `pseudocode`, skipping
+/// comments and preprocessor lines.
+fn prototype(pseudocode: &str) -> Option<String> {
+    let body_start = pseudocode.find('{')?;
+    let before = strip_block_comments(pseudocode.get(..body_start)?);
+
+    // The prototype starts after the last statement or declaration that precedes it.
+    let lines: Vec<&str> = before
+        .lines()
+        .map(|line| line.split("//").next().unwrap_or(line).trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+    let joined = lines.join(" ");
+    let start = joined
+        .rfind([';', '}'])
+        .map_or(0, |pos| pos.saturating_add(1));
+    let prototype = joined.get(start..)?.trim();
+
+    (!prototype.is_empty()).then(|| prototype.to_owned())
+}
+
+/// Removes `/* ... */` comments from `text`.
+fn strip_block_comments(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("/*") {
+        out.push_str(rest.get(..start).unwrap_or_default());
+        rest = rest
+            .get(start..)
+            .and_then(|r| {
+                let end = r.find("*/")?;
+                r.get(end.saturating_add(2)..)
+            })
+            .unwrap_or_default();
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Splits `text` that ends with a parenthesized group into the text before the group and the
+/// contents of the group.
+fn split_trailing_group(text: &str) -> Option<(&str, &str)> {
+    let inner = text.trim_end().strip_suffix(')')?;
+    let mut depth: usize = 0;
+    for (pos, c) in inner.char_indices().rev() {
+        match c {
+            ')' => depth = depth.saturating_add(1),
+            '(' if depth == 0 => {
+                return Some((inner.get(..pos)?, inner.get(pos.saturating_add(1)..)?));
+            }
+            '(' => depth = depth.saturating_sub(1),
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Splits `text` on `separator` occurrences that are not nested in parentheses or brackets.
+fn split_top_level(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth: usize = 0;
+    let mut start = 0;
+    for (pos, c) in text.char_indices() {
+        match c {
+            '(' | '[' | '<' => depth = depth.saturating_add(1),
+            ')' | ']' | '>' => depth = depth.saturating_sub(1),
+            _ if c == separator && depth == 0 => {
+                parts.push(text.get(start..pos).unwrap_or_default());
+                start = pos.saturating_add(c.len_utf8());
+            }
+            _ => (),
+        }
+    }
+    parts.push(text.get(start..).unwrap_or_default());
+    parts
+}
+
+/// Returns the last C identifier in `text`, if any.
+fn last_identifier(text: &str) -> Option<&str> {
+    let trimmed = text.trim_end();
+    let start = trimmed
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| c.is_ascii_alphanumeric() || c == '_')
+        .last()
+        .map(|(pos, _)| pos)?;
+    let ident = trimmed.get(start..)?;
+    ident
+        .chars()
+        .next()
+        .is_some_and(|c| !c.is_ascii_digit())
+        .then_some(ident)
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use super::*;
+
+    /// Parses `pseudocode` and returns the parameter names.
+    fn names(pseudocode: &str) -> Vec<String> {
+        Signature::parse(pseudocode)
+            .map(|sig| sig.param_names().map(str::to_owned).collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn signature_parse_handles_hexrays_fastcall() -> anyhow::Result<()> {
+        let sig = Signature::parse(
+            "__int64 __fastcall sub_401000(__int64 a1, int a2, char *a3)\n{\n  return 0;\n}\n",
+        )
+        .ok_or_else(|| anyhow::anyhow!("no signature"))?;
+
+        assert_eq!(
+            sig.param_names().collect::<Vec<_>>(),
+            ["a1", "a2", "a3"],
+            "wrong parameter names"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn signature_parse_handles_main_with_pointer_params() {
+        assert_eq!(
+            names("int __cdecl main(int argc, const char **argv, const char **envp)\n{\n}\n"),
+            ["argc", "argv", "envp"]
+        );
+    }
+
+    #[test]
+    fn signature_parse_handles_void_and_type_only_params() {
+        assert!(names("unsigned int *__stdcall bar(void)\n{\n}\n").is_empty());
+        assert_eq!(names("int foo(int, char *a2)\n{\n}\n"), ["a2"]);
+    }
+
+    #[test]
+    fn signature_parse_handles_function_pointer_params() {
+        assert_eq!(
+            names(
+                "void __fastcall foo(void (__fastcall *callback)(int, char *), __int64 a2)\n{\n}\n"
+            ),
+            ["callback", "a2"]
+        );
+    }
+
+    #[test]
+    fn signature_parse_handles_ghidra_style_and_arrays() {
+        assert_eq!(
+            names("undefined8 FUN_00101189(undefined8 param_1, char param_2[16], ...)\n{\n}\n"),
+            ["param_1", "param_2"]
+        );
+    }
+
+    #[test]
+    fn signature_parse_skips_leading_comments_and_preprocessor_lines() -> anyhow::Result<()> {
+        let sig = Signature::parse(
+            "#include <stdio.h>\n\
+             //----- (0000000140001000) ----\n\
+             /* attributes: thunk */\n\
+             // positive sp value has been detected\n\
+             int __fastcall handler(int a1) // comment\n\
+             {\n}\n",
+        )
+        .ok_or_else(|| anyhow::anyhow!("no signature"))?;
+
+        assert_eq!(sig.param_names().collect::<Vec<_>>(), ["a1"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn signature_prototype_passes_through_calling_convention_and_function_pointers()
+    -> anyhow::Result<()> {
+        let sig = Signature::parse(
+            "unsigned int *__fastcall sub_401000(void (__fastcall *cb)(int), char *a2)\n{\n}\n",
+        )
+        .ok_or_else(|| anyhow::anyhow!("no signature"))?;
+
+        assert_eq!(
+            sig.prototype("register_callback"),
+            "unsigned int *__fastcall register_callback(void (__fastcall *cb)(int), char *a2)"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn signature_parse_without_definition_returns_none() {
+        assert!(Signature::parse("int v1 = 0;").is_none(), "signature found");
+        assert!(Signature::parse("").is_none(), "signature found");
+    }
+}
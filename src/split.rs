@@ -0,0 +1,402 @@
+//! Split pseudocode into function definitions.
+
+use std::ops::Range;
+
+use thiserror::Error;
+
+use crate::lexer::{Token, TokenKind, tokenize};
+
+/// Qualifiers that may appear between the parameter list and the body of a function definition.
+const TRAILING_QUALIFIERS: &[&str] = &["const", "noexcept", "override", "final", "volatile"];
+
+/// Function definition found in pseudocode by [`split_functions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionSpan {
+    /// Function name, as it appears in the pseudocode.
+    name: String,
+    /// Byte range of the definition, from the start of the prototype to the closing brace.
+    range: Range<usize>,
+}
+
+impl FunctionSpan {
+    /// Returns the function name, as it appears in the pseudocode.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the byte range of the definition in the pseudocode, from the start of the prototype
+    /// to the closing brace (leading comments are not included).
+    #[must_use]
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// Returns the text of the definition in `pseudocode`.
+    #[must_use]
+    pub fn text<'a>(&self, pseudocode: &'a str) -> &'a str {
+        pseudocode.get(self.range()).unwrap_or_default()
+    }
+}
+
+/// Splits `pseudocode` into top-level function definitions, in order.
+///
+/// Definitions are found by brace matching: a top-level `{` that follows a parameter list starts
+/// a function body. Braces in strings, character literals, comments, and preprocessor directives
+/// are ignored. Text between definitions (e.g., includes, globals, and comment banners) is not
+/// part of any span.
+#[must_use]
+pub fn split_functions(pseudocode: &str) -> Vec<FunctionSpan> {
+    let tokens: Vec<Token> = tokenize(pseudocode)
+        .into_iter()
+        .filter(|token| !token.is_trivia())
+        .collect();
+
+    let mut functions = Vec::new();
+    let mut start_index: usize = 0;
+    let mut index: usize = 0;
+    while let Some(token) = tokens.get(index) {
+        match token.text(pseudocode) {
+            ";" => start_index = index.saturating_add(1),
+            "{" => {
+                let end_index = matching_close(&tokens, index, pseudocode);
+                let header = tokens.get(start_index..index).unwrap_or_default();
+                if let Some(name) = function_name(header, pseudocode)
+                    && let (Some(first), Some(last)) = (header.first(), tokens.get(end_index))
+                {
+                    functions.push(FunctionSpan {
+                        name: name.to_owned(),
+                        range: first.range.start..last.range.end,
+                    });
+                }
+                index = end_index;
+                start_index = index.saturating_add(1);
+            }
+            _ => (),
+        }
+        index = index.saturating_add(1);
+    }
+    functions
+}
+
+/// Failure in selecting a function with [`select_function`].
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum SelectError {
+    /// No function definition matches the query (the names of the functions found are listed).
+    #[error("Function `{0}` not found in the pseudocode ({found})", found = found_names(.1))]
+    NotFound(String, Vec<String>),
+    /// Malformed address in the query.
+    #[error("Invalid function address in `{0}`: expected `@0x<hex>`")]
+    InvalidAddress(String),
+}
+
+/// Selects the function definition in `functions` that matches `query`.
+///
+/// The query is a function name (e.g., `parse_packet`), optionally followed by an address suffix
+/// (e.g., `sub_401A2F@0x401A2F` or just `@0x401A2F`). An exact name match is preferred; otherwise,
+/// the address matches functions whose name ends with `_` and the same hex digits (e.g.,
+/// `sub_401a2f`), regardless of case and leading zeros.
+///
+/// # Errors
+///
+/// Returns the appropriate [`SelectError`] in case the address is malformed or no function
+/// matches.
+pub fn select_function<'a>(
+    functions: &'a [FunctionSpan],
+    query: &str,
+) -> Result<&'a FunctionSpan, SelectError> {
+    let (name, address) = match query.split_once('@') {
+        Some((name, suffix)) => {
+            let digits = suffix
+                .strip_prefix("0x")
+                .or_else(|| suffix.strip_prefix("0X"))
+                .filter(|hex| !hex.is_empty() && hex.bytes().all(|b| b.is_ascii_hexdigit()))
+                .ok_or_else(|| SelectError::InvalidAddress(query.to_owned()))?;
+            (name, Some(digits))
+        }
+        None => (query, None),
+    };
+
+    functions
+        .iter()
+        .find(|function| !name.is_empty() && function.name == name)
+        .or_else(|| {
+            let wanted = address?;
+            functions.iter().find(|function| {
+                function
+                    .name
+                    .rsplit_once('_')
+                    .is_some_and(|(_, digits)| same_address(digits, wanted))
+            })
+        })
+        .ok_or_else(|| {
+            SelectError::NotFound(
+                query.to_owned(),
+                functions
+                    .iter()
+                    .map(|function| function.name.clone())
+                    .collect(),
+            )
+        })
+}
+
+/// Returns up to `lines` lines of `pseudocode` that precede the `start` offset (e.g., the
+/// declarations before a function definition), or an empty string if `lines` is zero.
+#[cfg(feature = "cli")]
+pub fn leading_lines(pseudocode: &str, start: usize, lines: usize) -> &str {
+    let before = pseudocode.get(..start).unwrap_or_default();
+    let complete = before.strip_suffix('\n').unwrap_or(before);
+    let Some(skip) = lines.checked_sub(1) else {
+        return "";
+    };
+    let from = complete
+        .rmatch_indices('\n')
+        .nth(skip)
+        .map_or(0, |(pos, _)| pos.saturating_add(1));
+    complete.get(from..).unwrap_or_default()
+}
+
+/// Returns the functions in `functions` that `function` calls directly, in order of first call.
+///
+/// Calls are found syntactically (an identifier followed by `(`), ignoring calls in strings and
+/// comments. Recursive calls are ignored.
+pub fn direct_callees<'a>(
+    pseudocode: &str,
+    function: &FunctionSpan,
+    functions: &'a [FunctionSpan],
+) -> Vec<&'a FunctionSpan> {
+    let text = function.text(pseudocode);
+    let tokens: Vec<Token> = tokenize(text)
+        .into_iter()
+        .filter(|token| !token.is_trivia())
+        .collect();
+
+    let mut callees: Vec<&FunctionSpan> = Vec::new();
+    for pair in tokens.windows(2) {
+        if let (Some(name), Some(paren)) = (pair.first(), pair.get(1))
+            && name.kind == TokenKind::Identifier
+            && paren.is_punct(text, '(')
+            && let Some(callee) = functions.iter().find(|candidate| {
+                candidate.name == name.text(text) && candidate.range != function.range
+            })
+            && !callees.contains(&callee)
+        {
+            callees.push(callee);
+        }
+    }
+    callees
+}
+
+/// Returns the name of the function declared by `header` (the tokens that precede a top-level
+/// `{`), if `header` is a function prototype.
+fn function_name<'a>(header: &[Token], pseudocode: &'a str) -> Option<&'a str> {
+    // Skip trailing qualifiers, then expect the parameter list.
+    let params_end = header.iter().rposition(|token| {
+        !(token.kind == TokenKind::Identifier
+            && TRAILING_QUALIFIERS.contains(&token.text(pseudocode)))
+    })?;
+    if !header.get(params_end)?.is_punct(pseudocode, ')') {
+        return None;
+    }
+
+    let params_start = matching_open(header, params_end, pseudocode)?;
+    let name_index = params_start.checked_sub(1)?;
+    let name = header.get(name_index)?;
+    if name.is_punct(pseudocode, ')') {
+        // Function returning a function pointer (e.g., `void (*get(int a1))(int)`): the name is
+        // in the parenthesized declarator.
+        let declarator_start = matching_open(header, name_index, pseudocode)?;
+        function_name(
+            header.get(declarator_start.saturating_add(1)..name_index)?,
+            pseudocode,
+        )
+    } else {
+        (name.kind == TokenKind::Identifier).then(|| name.text(pseudocode))
+    }
+}
+
+/// Returns `true` if the `digits` suffix of a function name is the hex address `wanted`, ignoring
+/// case and leading zeros.
+fn same_address(digits: &str, wanted: &str) -> bool {
+    !digits.is_empty()
+        && digits.bytes().all(|b| b.is_ascii_hexdigit())
+        && digits
+            .trim_start_matches('0')
+            .eq_ignore_ascii_case(wanted.trim_start_matches('0'))
+}
+
+/// Formats the `names` of the functions found for [`SelectError::NotFound`].
+fn found_names(names: &[String]) -> String {
+    if names.is_empty() {
+        "no function definitions found".to_owned()
+    } else {
+        format!("found: {}", names.join(", "))
+    }
+}
+
+/// Returns the index of the `}` that closes the group opened at `open_index`, or the index of
+/// the last token if the group is unterminated.
+fn matching_close(tokens: &[Token], open_index: usize, source: &str) -> usize {
+    let mut depth: usize = 0;
+    for (index, token) in tokens.iter().enumerate().skip(open_index) {
+        match token.text(source) {
+            "{" => depth = depth.saturating_add(1),
+            "}" => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    return index;
+                }
+            }
+            _ => (),
+        }
+    }
+    tokens.len().saturating_sub(1)
+}
+
+/// Returns the index of the `(` that opens the group closed at `close_index`.
+fn matching_open(tokens: &[Token], close_index: usize, source: &str) -> Option<usize> {
+    let mut depth: usize = 0;
+    for index in (0..=close_index).rev() {
+        match tokens.get(index)?.text(source) {
+            ")" => depth = depth.saturating_add(1),
+            "(" => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    return Some(index);
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MULTI_FUNCTION_PSEUDOCODE: &str = r#"#include <stdio.h>
+
+struct point { int x; int y; };
+int table[] = { 1, 2, 3 };
+
+//----- (0000000140001000) ----------------------------------------------------
+__int64 __fastcall sub_140001000(int a1)
+{
+  if ( a1 )
+  {
+    puts("}");
+  }
+  return a1 + '{';
+}
+
+//----- (0000000140001040) ----------------------------------------------------
+void (__fastcall *get_handler(int a1))(int)
+{
+  return 0;
+}
+
+/* helper */
+int main(int argc, const char **argv, const char **envp)
+{
+  return sub_140001000(argc); // {
+}
+"#;
+
+    #[test]
+    fn split_functions_finds_definitions_in_order() {
+        let functions = split_functions(MULTI_FUNCTION_PSEUDOCODE);
+
+        assert_eq!(
+            functions.iter().map(FunctionSpan::name).collect::<Vec<_>>(),
+            ["sub_140001000", "get_handler", "main"]
+        );
+    }
+
+    #[test]
+    fn split_functions_spans_prototype_to_closing_brace() {
+        let functions = split_functions(MULTI_FUNCTION_PSEUDOCODE);
+
+        let first = functions.first().map(|f| f.text(MULTI_FUNCTION_PSEUDOCODE));
+        assert!(
+            first.is_some_and(
+                |text| text.starts_with("__int64 __fastcall sub_140001000(int a1)")
+                    && text.ends_with("return a1 + '{';\n}")
+            ),
+            "wrong span: {first:?}"
+        );
+        let last = functions.last().map(|f| f.text(MULTI_FUNCTION_PSEUDOCODE));
+        assert!(
+            last.is_some_and(|text| text.starts_with("int main(") && text.ends_with("// {\n}")),
+            "wrong span: {last:?}"
+        );
+    }
+
+    #[test]
+    fn split_functions_ignores_declarations_and_initializers() {
+        assert!(
+            split_functions("struct s { int a; };\nint t[] = { 1 };\nint f(int);\n").is_empty(),
+            "function found"
+        );
+    }
+
+    #[test]
+    fn direct_callees_ignores_strings_comments_and_recursion() {
+        let pseudocode = "int helper(int a1) { return helper(a1); }\nint unused() { return 0; }\nint main() {\n  puts(\"unused()\"); // unused()\n  return helper(1) + helper(2);\n}\n";
+        let functions = split_functions(pseudocode);
+
+        let callees: Vec<Vec<&str>> = functions
+            .iter()
+            .map(|function| {
+                direct_callees(pseudocode, function, &functions)
+                    .into_iter()
+                    .map(FunctionSpan::name)
+                    .collect()
+            })
+            .collect();
+
+        assert_eq!(callees, [vec![], vec![], vec!["helper"]]);
+    }
+
+    #[test]
+    fn select_function_matches_definitions_not_call_sites() {
+        let functions = split_functions(MULTI_FUNCTION_PSEUDOCODE);
+        let selected = |query| select_function(&functions, query).map(FunctionSpan::name);
+
+        assert_eq!(selected("sub_140001000").ok(), Some("sub_140001000"));
+        assert_eq!(selected("@0x140001000").ok(), Some("sub_140001000"));
+        assert_eq!(selected("renamed@0X0140001000").ok(), Some("sub_140001000"));
+        assert_eq!(selected("main@0x140001040").ok(), Some("main"));
+        assert!(
+            matches!(selected("main@401000"), Err(SelectError::InvalidAddress(_))),
+            "malformed address accepted"
+        );
+        let err = selected("puts").map_or_else(|err| err.to_string(), str::to_owned);
+        assert_eq!(
+            err,
+            "Function `puts` not found in the pseudocode (found: sub_140001000, get_handler, main)"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn leading_lines_returns_complete_lines_before_offset() {
+        let pseudocode = "int a;\nint b;\n// c\nint main() {}\n";
+        let start = pseudocode.find("int main").unwrap_or_default();
+
+        assert_eq!(leading_lines(pseudocode, start, 0), "");
+        assert_eq!(leading_lines(pseudocode, start, 2), "int b;\n// c");
+        assert_eq!(leading_lines(pseudocode, start, 10), "int a;\nint b;\n// c");
+    }
+
+    #[test]
+    fn split_functions_handles_cpp_qualifiers() {
+        let functions = split_functions("int Foo::get(void) const\n{\n  return 0;\n}\n");
+
+        assert_eq!(
+            functions.iter().map(FunctionSpan::name).collect::<Vec<_>>(),
+            ["get"]
+        );
+    }
+}
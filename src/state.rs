@@ -0,0 +1,156 @@
+//! Track which input files were already analyzed, to resume interrupted batches.
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead as _, BufReader, Write as _};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Default name of the state file, saved in the output directory of a batch.
+pub const STATE_FILENAME: &str = ".oneiromancer-state.jsonl";
+
+/// Record of an analyzed input file, stored as a line of the state file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct StateRecord {
+    /// Canonical path of the input file.
+    path: PathBuf,
+    /// SHA-256 hash of the content of the input file.
+    hash: String,
+    /// Model used for the analysis.
+    model: String,
+    /// Recommended names of the analyzed functions, in order.
+    #[serde(default)]
+    names: Vec<String>,
+}
+
+/// State of a batch, loaded from and appended to a JSON Lines state file.
+///
+/// Each successful analysis is appended to the state file as soon as it completes, so that the
+/// state survives interrupted runs.
+#[derive(Debug)]
+pub struct RunState {
+    /// Path of the state file.
+    filepath: PathBuf,
+    /// Latest record of each input file, keyed by canonical path.
+    records: HashMap<PathBuf, StateRecord>,
+}
+
+impl RunState {
+    /// Loads the state from `filepath`, or creates an empty state if the file does not exist.
+    /// Malformed lines (e.g., truncated by an interrupted run) are ignored.
+    pub fn load(filepath: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = filepath.into();
+        let mut records = HashMap::new();
+        match File::open(&path) {
+            Ok(file) => {
+                for line in BufReader::new(file).lines() {
+                    if let Ok(record) = serde_json::from_str::<StateRecord>(&line?) {
+                        records.insert(record.path.clone(), record);
+                    }
+                }
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+            Err(err) => return Err(err),
+        }
+        Ok(Self {
+            filepath: path,
+            records,
+        })
+    }
+
+    /// Returns `true` if the `input` file was analyzed before, regardless of its content and model.
+    pub fn contains(&self, input: &Path) -> bool {
+        self.records.contains_key(&canonical(input))
+    }
+
+    /// Returns the recommended function names if the `input` file with `hash` content was already
+    /// analyzed with `model`, or `None` if it must be analyzed.
+    pub fn up_to_date(&self, input: &Path, hash: &str, model: &str) -> Option<&[String]> {
+        self.records
+            .get(&canonical(input))
+            .filter(|record| record.hash == hash && record.model == model)
+            .map(|record| record.names.as_slice())
+    }
+
+    /// Records that the `input` file with `hash` content was analyzed with `model`, recommending
+    /// `names` for its functions, and appends the record to the state file.
+    pub fn record(
+        &mut self,
+        input: &Path,
+        hash: &str,
+        model: &str,
+        names: &[String],
+    ) -> io::Result<()> {
+        let record = StateRecord {
+            path: canonical(input),
+            hash: hash.to_owned(),
+            model: model.to_owned(),
+            names: names.to_vec(),
+        };
+        if let Some(dir) = self
+            .filepath
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+        {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.filepath)?;
+        writeln!(file, "{}", serde_json::to_string(&record)?)?;
+        self.records.insert(record.path.clone(), record);
+        Ok(())
+    }
+}
+
+/// Returns the canonical form of `path`, or `path` itself if it cannot be canonicalized.
+pub fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use super::*;
+    use crate::cache::content_hash;
+
+    #[test]
+    fn run_state_survives_reload_and_ignores_truncated_lines() -> anyhow::Result<()> {
+        let tmpdir = tempfile::tempdir()?;
+        let input = tmpdir.path().join("a.c");
+        fs::write(&input, "int main() {}")?;
+        let hash = content_hash(b"int main() {}");
+        let filepath = tmpdir.path().join("out").join(STATE_FILENAME);
+
+        let mut state = RunState::load(&filepath)?;
+        assert_eq!(
+            state.up_to_date(&input, &hash, "aidapal"),
+            None,
+            "empty state"
+        );
+        state.record(&input, &hash, "aidapal", &["init".to_owned()])?;
+        let mut file = OpenOptions::new().append(true).open(&filepath)?;
+        write!(file, "{{\"path\":\"trunc")?;
+
+        let reloaded = RunState::load(&filepath)?;
+        assert!(reloaded.contains(&input), "not recorded");
+        assert_eq!(
+            reloaded.up_to_date(&input, &hash, "aidapal"),
+            Some(["init".to_owned()].as_slice())
+        );
+        assert_eq!(
+            reloaded.up_to_date(&input, &hash, "other"),
+            None,
+            "model ignored"
+        );
+        assert_eq!(
+            reloaded.up_to_date(&input, &content_hash(b"changed"), "aidapal"),
+            None,
+            "content ignored"
+        );
+
+        Ok(())
+    }
+}
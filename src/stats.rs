@@ -0,0 +1,320 @@
+//! Collect and aggregate timing and token statistics of analyses.
+
+#[cfg(feature = "cli")]
+use std::fmt::Write as _;
+#[cfg(feature = "cli")]
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Timing and token statistics of one or more LLM responses, as reported by the Ollama API.
+///
+/// Fields that are not reported (e.g., by older servers, or for responses served from the cache)
+/// are `None` and omitted when serialized.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResponseMetrics {
+    /// Number of tokens in the prompt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prompt_tokens: Option<u64>,
+    /// Number of tokens in the response.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    completion_tokens: Option<u64>,
+    /// Time spent loading the model, in milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    load_duration_ms: Option<u64>,
+    /// Time spent generating the response, in milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    eval_duration_ms: Option<u64>,
+}
+
+impl ResponseMetrics {
+    /// Creates new [`ResponseMetrics`] from the token counts and durations of a response.
+    pub(crate) fn new(
+        prompt_tokens: Option<u64>,
+        completion_tokens: Option<u64>,
+        load_duration: Option<Duration>,
+        eval_duration: Option<Duration>,
+    ) -> Self {
+        Self {
+            prompt_tokens,
+            completion_tokens,
+            load_duration_ms: load_duration.map(millis),
+            eval_duration_ms: eval_duration.map(millis),
+        }
+    }
+
+    /// Gets the number of tokens in the prompt, if reported.
+    #[must_use]
+    pub const fn prompt_tokens(&self) -> Option<u64> {
+        self.prompt_tokens
+    }
+
+    /// Gets the number of tokens in the response, if reported.
+    #[must_use]
+    pub const fn completion_tokens(&self) -> Option<u64> {
+        self.completion_tokens
+    }
+
+    /// Gets the time spent loading the model, if reported.
+    #[must_use]
+    pub fn load_duration(&self) -> Option<Duration> {
+        self.load_duration_ms.map(Duration::from_millis)
+    }
+
+    /// Gets the time spent generating the response, if reported.
+    #[must_use]
+    pub fn eval_duration(&self) -> Option<Duration> {
+        self.eval_duration_ms.map(Duration::from_millis)
+    }
+
+    /// Returns `true` if no statistics were reported.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Returns the sum of these and `other` metrics. Fields reported by neither are `None`.
+    #[must_use]
+    pub(crate) const fn merge(self, other: Self) -> Self {
+        Self {
+            prompt_tokens: sum(self.prompt_tokens, other.prompt_tokens),
+            completion_tokens: sum(self.completion_tokens, other.completion_tokens),
+            load_duration_ms: sum(self.load_duration_ms, other.load_duration_ms),
+            eval_duration_ms: sum(self.eval_duration_ms, other.eval_duration_ms),
+        }
+    }
+
+    /// Returns the generation speed in tenths of tokens per second, if reported.
+    #[cfg(feature = "cli")]
+    fn decitokens_per_second(&self) -> Option<u64> {
+        let (tokens, ms) = self.completion_tokens.zip(self.eval_duration_ms)?;
+        tokens.saturating_mul(10_000).checked_div(ms)
+    }
+}
+
+/// Statistics of the analysis of a single file.
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStats {
+    /// Path of the input file.
+    input: PathBuf,
+    /// Wall-clock duration of the analysis.
+    duration: Duration,
+    /// Statistics reported by the Ollama API.
+    metrics: ResponseMetrics,
+    /// Whether the results of an identical earlier file were reused.
+    deduplicated: bool,
+}
+
+#[cfg(feature = "cli")]
+impl FileStats {
+    /// Creates new [`FileStats`] of the analysis of `input`.
+    pub fn new(input: &Path, duration: Duration, metrics: ResponseMetrics) -> Self {
+        Self {
+            input: input.to_path_buf(),
+            duration,
+            metrics,
+            deduplicated: false,
+        }
+    }
+
+    /// Sets whether the results of an identical earlier file were reused.
+    #[must_use]
+    pub const fn deduplicated(mut self, deduplicated: bool) -> Self {
+        self.deduplicated = deduplicated;
+        self
+    }
+}
+
+/// Aggregated statistics of a run.
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunStats {
+    /// Number of analyzed files.
+    files: usize,
+    /// Number of analyzed files that reused the results of an identical earlier file.
+    deduplicated: usize,
+    /// Wall-clock duration of the run.
+    wall: Duration,
+    /// Average wall-clock duration of the analysis of a file, if any files were analyzed.
+    average: Option<Duration>,
+    /// Slowest file and the duration of its analysis, if any files were analyzed.
+    slowest: Option<(PathBuf, Duration)>,
+    /// Sum of the statistics reported by the Ollama API.
+    metrics: ResponseMetrics,
+}
+
+#[cfg(feature = "cli")]
+impl RunStats {
+    /// Aggregates the statistics of the analyzed files in `records`, for a run that took `wall`.
+    pub fn aggregate(records: &[FileStats], wall: Duration) -> Self {
+        let total: Duration = records.iter().map(|record| record.duration).sum();
+        Self {
+            files: records.len(),
+            deduplicated: records.iter().filter(|record| record.deduplicated).count(),
+            wall,
+            average: u32::try_from(records.len())
+                .ok()
+                .and_then(|count| total.checked_div(count)),
+            slowest: records
+                .iter()
+                .max_by_key(|record| record.duration)
+                .map(|record| (record.input.clone(), record.duration)),
+            metrics: records
+                .iter()
+                .fold(ResponseMetrics::default(), |acc, record| {
+                    acc.merge(record.metrics)
+                }),
+        }
+    }
+}
+
+/// Formats `stats` as a compact block of indented lines, omitting statistics that were not
+/// reported. Per-file aggregates are only included for runs of multiple files.
+#[cfg(feature = "cli")]
+pub fn format_stats(stats: &RunStats) -> String {
+    let mut block = format!("    Wall time:          {:.2?}\n", stats.wall);
+    if stats.files > 1 {
+        _ = writeln!(block, "    Files analyzed:     {}", stats.files);
+        if stats.deduplicated > 0 {
+            _ = writeln!(block, "    Deduplicated files: {}", stats.deduplicated);
+        }
+        if let Some(average) = stats.average {
+            _ = writeln!(block, "    Average per file:   {average:.2?}");
+        }
+        if let Some(slowest) = stats.slowest.as_ref() {
+            _ = writeln!(
+                block,
+                "    Slowest file:       `{}` ({:.2?})",
+                slowest.0.display(),
+                slowest.1
+            );
+        }
+    }
+    if let Some(load) = stats.metrics.load_duration() {
+        _ = writeln!(block, "    Model load time:    {load:.2?}");
+    }
+    if let Some(tokens) = stats.metrics.prompt_tokens {
+        _ = writeln!(block, "    Prompt tokens:      {tokens}");
+    }
+    if let Some(tokens) = stats.metrics.completion_tokens {
+        _ = writeln!(block, "    Completion tokens:  {tokens}");
+    }
+    if let Some(speed) = stats.metrics.decitokens_per_second() {
+        _ = writeln!(
+            block,
+            "    Generation speed:   {}.{} tokens/s",
+            speed.checked_div(10).unwrap_or_default(),
+            speed.checked_rem(10).unwrap_or_default()
+        );
+    }
+    block
+}
+
+/// Returns the sum of `a` and `b`, or whichever is reported.
+const fn sum(a: Option<u64>, b: Option<u64>) -> Option<u64> {
+    match (a, b) {
+        (Some(x), Some(y)) => Some(x.saturating_add(y)),
+        (Some(value), None) | (None, Some(value)) => Some(value),
+        (None, None) => None,
+    }
+}
+
+/// Returns `duration` in whole milliseconds, saturating at [`u64::MAX`].
+pub fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+#[cfg(feature = "cli")]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn millis_truncates_and_saturates() {
+        assert_eq!(millis(Duration::from_micros(1_500_999)), 1500);
+        assert_eq!(millis(Duration::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn aggregate_sums_reported_metrics_counts_duplicates_and_finds_slowest_file() {
+        let fast: u64 = 200;
+        let slow: u64 = 600;
+        let records = [
+            FileStats::new(
+                Path::new("a.c"),
+                Duration::from_millis(fast),
+                ResponseMetrics::new(
+                    Some(100),
+                    Some(20),
+                    Some(Duration::from_millis(fast)),
+                    Some(Duration::from_millis(slow)),
+                ),
+            ),
+            FileStats::new(
+                Path::new("b.c"),
+                Duration::from_millis(slow),
+                ResponseMetrics::new(Some(50), Some(10), None, Some(Duration::from_millis(fast))),
+            ),
+            FileStats::new(
+                Path::new("c.c"),
+                Duration::from_millis(fast),
+                ResponseMetrics::default(),
+            )
+            .deduplicated(true),
+        ];
+
+        let stats = RunStats::aggregate(&records, Duration::from_secs(1));
+
+        assert_eq!(stats.files, 3);
+        assert_eq!(stats.deduplicated, 1);
+        assert_eq!(
+            stats.average,
+            Some(Duration::from_millis(fast.saturating_add(fast).saturating_add(slow)) / 3)
+        );
+        assert_eq!(
+            stats.slowest,
+            Some((PathBuf::from("b.c"), Duration::from_millis(slow)))
+        );
+        assert_eq!(
+            stats.metrics,
+            ResponseMetrics::new(
+                Some(150),
+                Some(30),
+                Some(Duration::from_millis(fast)),
+                Some(Duration::from_millis(fast.saturating_add(slow))),
+            )
+        );
+        assert_eq!(
+            format_stats(&stats),
+            "    Wall time:          1.00s\n    Files analyzed:     3\n    Deduplicated files: 1\n    Average per file:   333.33ms\n    Slowest file:       `b.c` (600.00ms)\n    Model load time:    200.00ms\n    Prompt tokens:      150\n    Completion tokens:  30\n    Generation speed:   37.5 tokens/s\n"
+        );
+    }
+
+    #[test]
+    fn format_stats_omits_missing_metrics() {
+        let duration: u64 = 1_500;
+        let records = [FileStats::new(
+            Path::new("a.c"),
+            Duration::from_millis(duration),
+            ResponseMetrics::default(),
+        )];
+
+        let stats = RunStats::aggregate(&records, Duration::from_millis(duration));
+
+        assert!(stats.metrics.is_empty(), "metrics not empty");
+        assert_eq!(format_stats(&stats), "    Wall time:          1.50s\n");
+        assert_eq!(
+            RunStats::aggregate(&[], Duration::ZERO),
+            RunStats {
+                files: 0,
+                deduplicated: 0,
+                wall: Duration::ZERO,
+                average: None,
+                slowest: None,
+                metrics: ResponseMetrics::default(),
+            }
+        );
+    }
+}
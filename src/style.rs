@@ -0,0 +1,198 @@
+//! Color terminal output consistently.
+
+use std::borrow::Cow;
+use std::env;
+
+use anstyle::{AnsiColor, Style};
+
+/// Environment variable that disables colors when set to a non-empty value (see
+/// <https://no-color.org/>).
+pub const NO_COLOR_VAR: &str = "NO_COLOR";
+
+/// When to use colors in terminal output.
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ColorChoice {
+    /// Use colors only when the output stream is a terminal and `NO_COLOR` is not set.
+    #[default]
+    Auto,
+    /// Always use colors.
+    Always,
+    /// Never use colors.
+    Never,
+}
+
+impl ColorChoice {
+    /// Returns `true` if colors should be used on an output stream, based on whether the stream
+    /// is a `terminal`.
+    #[must_use]
+    pub fn enabled(self, terminal: bool) -> bool {
+        match self {
+            Self::Auto => {
+                terminal && env::var_os(NO_COLOR_VAR).is_none_or(|value| value.is_empty())
+            }
+            Self::Always => true,
+            Self::Never => false,
+        }
+    }
+}
+
+/// Tone of a piece of terminal output, which determines its color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Tone {
+    /// Informational status (`[*]` and `[-]` markers).
+    Info,
+    /// Success (`[+]` markers).
+    Success,
+    /// Warning (`[!]` markers).
+    Warning,
+    /// Error (`[!] Error` markers).
+    Error,
+    /// Original name in a renaming suggestion.
+    OldName,
+    /// Suggested name in a renaming suggestion.
+    NewName,
+    /// Line added by a diff (`+`).
+    Added,
+    /// Line removed by a diff (`-`).
+    Removed,
+}
+
+impl Tone {
+    /// Returns the tone of a status `line`, based on its marker (e.g., `[+]`), if any. Leading
+    /// whitespace is ignored.
+    #[must_use]
+    pub fn of_status(line: &str) -> Option<Self> {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("[!] Error") {
+            Some(Self::Error)
+        } else if trimmed.starts_with("[!]") {
+            Some(Self::Warning)
+        } else if trimmed.starts_with("[+]") {
+            Some(Self::Success)
+        } else if trimmed.starts_with("[*]") || trimmed.starts_with("[-]") {
+            Some(Self::Info)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the terminal style of the tone.
+    fn style(self) -> Style {
+        let color = match self {
+            Self::Info => AnsiColor::Cyan,
+            Self::Success | Self::NewName | Self::Added => AnsiColor::Green,
+            Self::Warning => AnsiColor::Yellow,
+            Self::Error | Self::Removed => AnsiColor::Red,
+            Self::OldName => AnsiColor::Magenta,
+        };
+        let style = Style::new().fg_color(Some(color.into()));
+        match self {
+            Self::Error => style.bold(),
+            Self::Info
+            | Self::Success
+            | Self::Warning
+            | Self::OldName
+            | Self::NewName
+            | Self::Added
+            | Self::Removed => style,
+        }
+    }
+}
+
+/// Paints `text` with the color of `tone`, if `enabled`. This is the only place where escape codes
+/// are added to terminal output.
+#[must_use]
+pub fn paint(text: &str, tone: Tone, enabled: bool) -> Cow<'_, str> {
+    if enabled {
+        let style = tone.style();
+        Cow::Owned(format!("{style}{text}{style:#}"))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// Paints the marker of a status `line` (e.g., `[+]`) with the color of its tone, if `enabled`.
+/// Lines without a marker are returned unchanged.
+#[must_use]
+pub fn paint_status(line: &str, enabled: bool) -> Cow<'_, str> {
+    let marker_start = line.len().saturating_sub(line.trim_start().len());
+    Tone::of_status(line)
+        .filter(|_| enabled)
+        .map_or(Cow::Borrowed(line), |tone| {
+            let (leading, rest) = line.split_at(marker_start);
+            let (marker, message) = rest.split_at(3);
+            Cow::Owned(format!("{leading}{}{message}", paint(marker, tone, true)))
+        })
+}
+
+/// Paints each line of a unified `diff`, if `enabled`: added lines in green, removed lines in red,
+/// and hunk headers like informational status. File headers and context lines are unchanged.
+#[must_use]
+pub fn paint_diff(diff: &str, enabled: bool) -> Cow<'_, str> {
+    if !enabled {
+        return Cow::Borrowed(diff);
+    }
+    let painted = diff
+        .split_inclusive('\n')
+        .map(|line| {
+            let text = line.trim_end_matches('\n');
+            let newline = if line.ends_with('\n') { "\n" } else { "" };
+            let tone = match text.as_bytes().first().copied() {
+                _ if text.starts_with("+++") || text.starts_with("---") => None,
+                Some(b'+') => Some(Tone::Added),
+                Some(b'-') => Some(Tone::Removed),
+                Some(b'@') => Some(Tone::Info),
+                Some(_) | None => None,
+            };
+            tone.map_or(Cow::Borrowed(line), |color| {
+                Cow::Owned(format!("{}{newline}", paint(text, color, true)))
+            })
+        })
+        .collect();
+    Cow::Owned(painted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paint_status_colors_only_the_marker() {
+        assert_eq!(
+            paint_status("\n[+] Done", true),
+            "\n\u{1b}[32m[+]\u{1b}[0m Done"
+        );
+        assert_eq!(
+            paint_status("[!] Error: boom", true),
+            "\u{1b}[1m\u{1b}[31m[!]\u{1b}[0m Error: boom"
+        );
+        assert_eq!(
+            paint_status("    v1\t-> counter", true),
+            "    v1\t-> counter"
+        );
+    }
+
+    #[test]
+    fn disabled_colors_leave_text_unchanged() {
+        assert_eq!(paint_status("[+] Done", false), "[+] Done");
+        assert_eq!(paint("counter", Tone::NewName, false), "counter");
+        assert!(!ColorChoice::Never.enabled(true), "never ignored");
+        assert!(!ColorChoice::Auto.enabled(false), "non-terminal colored");
+        assert!(ColorChoice::Always.enabled(false), "always ignored");
+    }
+
+    #[test]
+    fn paint_diff_colors_changed_lines_and_hunks() {
+        let diff = "--- a/test.c\n+++ b/test.c\n@@ -1 +1 @@\n-int v1;\n+int counter;\n int x;\n";
+
+        assert_eq!(
+            paint_diff(diff, true),
+            "--- a/test.c\n+++ b/test.c\n\u{1b}[36m@@ -1 +1 @@\u{1b}[0m\n\
+             \u{1b}[31m-int v1;\u{1b}[0m\n\u{1b}[32m+int counter;\u{1b}[0m\n int x;\n"
+        );
+        assert_eq!(paint_diff(diff, false), diff);
+    }
+}
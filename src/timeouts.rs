@@ -0,0 +1,119 @@
+//! Limit how long requests to the LLM can take.
+
+use std::time::Duration;
+
+use ureq::Agent;
+
+/// Timeouts of the requests to the LLM, none by default.
+///
+/// Analyses of long pseudocode on machines without a GPU can take several minutes, so set the read
+/// timeout generously. A request that times out fails with
+/// [`OneiromancerError::Timeout`](crate::OneiromancerError::Timeout).
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use oneiromancer::{Oneiromancer, Timeouts};
+///
+/// let timeouts = Timeouts::new()
+///     .connect(Duration::from_secs(5))
+///     .read(Duration::from_secs(600));
+/// let client = Oneiromancer::new().timeouts(timeouts);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timeouts {
+    /// Maximum time to establish the connection to the server.
+    connect: Option<Duration>,
+    /// Maximum time to receive the response once the request is sent.
+    read: Option<Duration>,
+}
+
+impl Timeouts {
+    /// Creates new [`Timeouts`] with default values (no timeouts).
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum time to establish the connection to the server.
+    #[must_use]
+    pub const fn connect(mut self, timeout: Duration) -> Self {
+        self.connect = Some(timeout);
+        self
+    }
+
+    /// Sets the maximum time to receive the response once the request is sent, which includes the
+    /// time the model takes to generate it. With the async API, it is the maximum time between two
+    /// reads.
+    #[must_use]
+    pub const fn read(mut self, timeout: Duration) -> Self {
+        self.read = Some(timeout);
+        self
+    }
+
+    /// Returns a new HTTP agent that enforces the timeouts.
+    pub(crate) fn agent(self) -> Agent {
+        Agent::config_builder()
+            .timeout_connect(self.connect)
+            .timeout_recv_response(self.read)
+            .timeout_recv_body(self.read)
+            .build()
+            .into()
+    }
+
+    /// Returns a new async HTTP client that enforces the timeouts.
+    #[cfg(feature = "async")]
+    pub(crate) fn http_client(self) -> reqwest::Client {
+        let mut builder = reqwest::Client::builder();
+        if let Some(timeout) = self.connect {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.read {
+            builder = builder.read_timeout(timeout);
+        }
+        builder.build().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OneiromancerError;
+
+    #[test]
+    fn timeouts_are_unset_by_default() {
+        let unset = Timeouts::new();
+        assert_eq!(unset, Timeouts::default());
+        assert_eq!((unset.connect, unset.read), (None, None));
+
+        let read = unset.read(Duration::from_secs(90));
+        assert_eq!(
+            (read.connect, read.read),
+            (None, Some(Duration::from_secs(90)))
+        );
+    }
+
+    #[test]
+    fn ureq_timeout_converts_to_timeout_error() {
+        let timeout = OneiromancerError::from(ureq::Error::Timeout(ureq::Timeout::RecvResponse));
+        assert!(
+            matches!(
+                timeout,
+                OneiromancerError::Timeout(ureq::Timeout::RecvResponse)
+            ),
+            "wrong error type returned: {timeout:?}"
+        );
+        assert_eq!(
+            timeout.to_string(),
+            "Request timed out (receive response timeout)"
+        );
+
+        let err = OneiromancerError::from(ureq::Error::ConnectionFailed);
+        assert!(
+            matches!(err, OneiromancerError::OllamaQueryFailed(_)),
+            "wrong error type returned: {err:?}"
+        );
+    }
+}
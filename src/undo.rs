@@ -0,0 +1,243 @@
+//! Record the variable renames applied to an output file, and undo them.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+
+use crate::audit::RenameDecision;
+use crate::merge::comments_start;
+use crate::{Variable, rename_variables, split_functions};
+
+/// Extension of the rename maps saved next to output files (e.g., `test.out.renames.json`).
+pub const RENAMES_EXTENSION: &str = "renames.json";
+
+/// Variable rename applied to an output file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AppliedRename {
+    /// Function the variable belongs to, if analyzed function by function.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    function: Option<String>,
+    /// Name of the variable before the rename.
+    original_name: String,
+    /// Name of the variable after the rename.
+    new_name: String,
+}
+
+impl AppliedRename {
+    /// Creates a new [`AppliedRename`] from `original_name` to `new_name`, in `function` (or in the
+    /// whole file if `None`).
+    #[must_use]
+    pub fn new(
+        function: Option<&str>,
+        original_name: impl Into<String>,
+        new_name: impl Into<String>,
+    ) -> Self {
+        Self {
+            function: function.map(ToOwned::to_owned),
+            original_name: original_name.into(),
+            new_name: new_name.into(),
+        }
+    }
+
+    /// Gets the function the variable belongs to, if analyzed function by function.
+    #[must_use]
+    pub fn function(&self) -> Option<&str> {
+        self.function.as_deref()
+    }
+
+    /// Gets the name of the variable before the rename.
+    #[must_use]
+    pub fn original_name(&self) -> &str {
+        &self.original_name
+    }
+
+    /// Gets the name of the variable after the rename.
+    #[must_use]
+    pub fn new_name(&self) -> &str {
+        &self.new_name
+    }
+
+    /// Returns the rename that reverts this one.
+    fn inverse(&self) -> Self {
+        Self {
+            function: self.function.clone(),
+            original_name: self.new_name.clone(),
+            new_name: self.original_name.clone(),
+        }
+    }
+}
+
+/// Variable renames applied to an output file, in the order they were applied, along with the
+/// reverse renames (from new to original name) that undo them, in the order to apply them.
+///
+/// Only the renames actually applied are recorded, as opposed to the raw suggestions of the model,
+/// so that the reverse renames restore the original pseudocode. This is exact, unless a suggested
+/// name already occurred in the pseudocode before the rename (the reverse rename then also renames
+/// the previous occurrences).
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenameMap {
+    /// Renames applied to the output file, in order.
+    renames: Vec<AppliedRename>,
+    /// Reverse renames that undo them, in order.
+    reverse: Vec<AppliedRename>,
+}
+
+impl RenameMap {
+    /// Creates a new [`RenameMap`] of the `renames`, in the order they were applied.
+    #[must_use]
+    pub fn new(renames: Vec<AppliedRename>) -> Self {
+        let reverse = renames.iter().rev().map(AppliedRename::inverse).collect();
+        Self { renames, reverse }
+    }
+
+    /// Creates a new [`RenameMap`] of the renames applied according to `decisions`.
+    pub(crate) fn from_decisions(decisions: &[RenameDecision]) -> Self {
+        Self::new(
+            decisions
+                .iter()
+                .filter(|decision| decision.is_applied())
+                .map(|decision| {
+                    AppliedRename::new(
+                        decision.function_name(),
+                        decision.original_name(),
+                        decision.new_name(),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    /// Loads a [`RenameMap`] saved as JSON in the `filepath` file.
+    ///
+    /// # Errors
+    ///
+    /// Returns a generic error in case the file cannot be read or is not a valid rename map.
+    pub fn load(filepath: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = filepath.as_ref();
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read rename map `{}`", path.display()))?;
+        serde_json::from_str(&json)
+            .with_context(|| format!("Failed to parse rename map `{}`", path.display()))
+    }
+
+    /// Gets the renames applied to the output file, in order.
+    #[must_use]
+    pub fn renames(&self) -> &[AppliedRename] {
+        &self.renames
+    }
+
+    /// Gets the reverse renames that undo the applied renames, in order.
+    #[must_use]
+    pub fn reverse(&self) -> &[AppliedRename] {
+        &self.reverse
+    }
+
+    /// Returns `true` if no renames were applied.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.renames.is_empty()
+    }
+
+    /// Serializes the rename map as pretty-printed JSON.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let mut json = serde_json::to_string_pretty(self).unwrap_or_default();
+        json.push('\n');
+        json
+    }
+}
+
+/// Undoes the renames recorded in `map` in `pseudocode` (e.g., the output file it was saved with).
+///
+/// The reverse renames are applied with the same identifier substitution as the forward renames.
+/// Renames in a function are undone within the function and the comments above it, as they were
+/// applied, while renames in no function are undone in the whole pseudocode.
+///
+/// # Errors
+///
+/// Returns a generic error in case a function of the reverse renames is not found in `pseudocode`.
+pub fn undo_renames(pseudocode: &str, map: &RenameMap) -> anyhow::Result<String> {
+    let mut restored = pseudocode.to_owned();
+    for group in map.reverse.chunk_by(|a, b| a.function == b.function) {
+        let variables: Vec<Variable> = group
+            .iter()
+            .map(|rename| Variable::new(rename.original_name(), rename.new_name()))
+            .collect();
+        let Some(name) = group.first().and_then(AppliedRename::function) else {
+            restored = rename_variables(&restored, &variables);
+            continue;
+        };
+
+        let functions = split_functions(&restored);
+        let span = functions
+            .iter()
+            .find(|span| span.name() == name)
+            .with_context(|| format!("Function `{name}` not found"))?;
+        let range = span.range();
+        let start = comments_start(restored.get(..range.start).unwrap_or_default());
+        let function = rename_variables(
+            restored.get(start..range.end).unwrap_or_default(),
+            &variables,
+        );
+        restored = format!(
+            "{}{function}{}",
+            restored.get(..start).unwrap_or_default(),
+            restored.get(range.end..).unwrap_or_default()
+        );
+    }
+    Ok(restored)
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_reverses_renames_in_reverse_order() {
+        let map = RenameMap::new(vec![
+            AppliedRename::new(None, "v1", "len"),
+            AppliedRename::new(Some("main"), "a1", "argc"),
+        ]);
+
+        assert_eq!(
+            map.reverse(),
+            [
+                AppliedRename::new(Some("main"), "argc", "a1"),
+                AppliedRename::new(None, "len", "v1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn undo_renames_restores_renames_within_functions() -> anyhow::Result<()> {
+        let original = "int f(int a1)\n{\n  return a1;\n}\n\nint g(int a1)\n{\n  return a1;\n}\n";
+        let renamed =
+            "int f(int size)\n{\n  return size;\n}\n\nint g(int a1)\n{\n  return a1;\n}\n";
+        let map = RenameMap::new(vec![AppliedRename::new(Some("f"), "a1", "size")]);
+
+        assert_eq!(undo_renames(renamed, &map)?, original);
+        assert!(
+            undo_renames(
+                renamed,
+                &RenameMap::new(vec![AppliedRename::new(Some("h"), "a1", "size")])
+            )
+            .is_err_and(|err| err.to_string().contains("`h` not found"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rename_map_round_trips_through_json() -> anyhow::Result<()> {
+        let map = RenameMap::new(vec![AppliedRename::new(None, "v1", "len")]);
+
+        let json = map.to_json();
+
+        assert!(json.contains("\"reverse\""));
+        assert!(!json.contains("\"function\""));
+        assert_eq!(serde_json::from_str::<RenameMap>(&json)?, map);
+        Ok(())
+    }
+}
@@ -0,0 +1,194 @@
+//! Validate input files before analysis.
+
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::MAX_INPUT_SIZE;
+
+/// Default accepted extensions of pseudocode files.
+pub const DEFAULT_EXTENSIONS: &[&str] = &["c", "cpp", "cc", "h", "txt", "json"];
+
+/// Policy that input files must comply with to be analyzed.
+#[derive(Debug, Clone)]
+pub struct InputPolicy {
+    /// Accepted file extensions, without the leading dot (matched case-insensitively).
+    extensions: Vec<String>,
+    /// Whether to accept files with any extension.
+    force_ext: bool,
+}
+
+impl InputPolicy {
+    /// Creates a new [`InputPolicy`] that accepts [`DEFAULT_EXTENSIONS`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the accepted file extensions (e.g., `c` or `.c`), replacing the default ones.
+    #[must_use]
+    pub fn extensions(mut self, extensions: &[String]) -> Self {
+        self.extensions = extensions
+            .iter()
+            .map(|ext| ext.trim_start_matches('.').to_owned())
+            .collect();
+        self
+    }
+
+    /// Sets whether to accept files with any extension, bypassing the extension check.
+    #[must_use]
+    pub const fn force_ext(mut self, force_ext: bool) -> Self {
+        self.force_ext = force_ext;
+        self
+    }
+
+    /// Returns `true` if the extension of `filepath` is accepted.
+    fn accepts_extension(&self, filepath: &Path) -> bool {
+        self.force_ext
+            || filepath.extension().is_some_and(|ext| {
+                self.extensions
+                    .iter()
+                    .any(|accepted| ext.eq_ignore_ascii_case(accepted))
+            })
+    }
+
+    /// Returns the accepted extensions, formatted for error messages.
+    fn accepted_extensions(&self) -> String {
+        self.extensions
+            .iter()
+            .map(|ext| format!(".{ext}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl Default for InputPolicy {
+    fn default() -> Self {
+        Self {
+            extensions: DEFAULT_EXTENSIONS
+                .iter()
+                .map(|&ext| ext.to_owned())
+                .collect(),
+            force_ext: false,
+        }
+    }
+}
+
+/// Input validation error type.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum InputError {
+    /// The input file does not exist or is not a regular file.
+    #[error("`{}` does not exist or is not a file", .0.display())]
+    NotAFile(PathBuf),
+    /// The input file cannot be read.
+    #[error("`{}` is not readable: {err}", .0.display(), err = .1)]
+    Unreadable(PathBuf, #[source] io::Error),
+    /// The input file exceeds [`MAX_INPUT_SIZE`].
+    #[error("`{}` exceeds the maximum size of {MAX_INPUT_SIZE} bytes", .0.display())]
+    TooLarge(PathBuf),
+    /// The input file does not have an accepted extension.
+    #[error("`{}` does not have an accepted extension ({accepted})", .path.display())]
+    UnsupportedExtension {
+        /// Path of the input file.
+        path: PathBuf,
+        /// Accepted extensions, formatted as a comma-separated list.
+        accepted: String,
+    },
+}
+
+/// Checks that the `filepath` input file exists, is readable, does not exceed
+/// [`MAX_INPUT_SIZE`], and has an extension accepted by `policy`.
+///
+/// # Errors
+///
+/// Returns [`InputError`] in case the input file is not valid.
+pub fn validate_input(filepath: impl AsRef<Path>, policy: &InputPolicy) -> Result<(), InputError> {
+    let path = filepath.as_ref();
+    if !path.is_file() {
+        return Err(InputError::NotAFile(path.to_path_buf()));
+    }
+    if !policy.accepts_extension(path) {
+        return Err(InputError::UnsupportedExtension {
+            path: path.to_path_buf(),
+            accepted: policy.accepted_extensions(),
+        });
+    }
+
+    let len = File::open(path)
+        .and_then(|file| file.metadata())
+        .map_err(|err| InputError::Unreadable(path.to_path_buf(), err))?
+        .len();
+    if usize::try_from(len).map_or(true, |size| size > MAX_INPUT_SIZE) {
+        return Err(InputError::TooLarge(path.to_path_buf()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, reason = "panics are allowed in test code")]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn validate_input_accepts_default_extensions_case_insensitively() -> anyhow::Result<()> {
+        let tmpdir = tempfile::tempdir()?;
+        for name in ["a.c", "b.C", "c.cpp", "d.txt"] {
+            let filepath = tmpdir.path().join(name);
+            fs::write(&filepath, "int main() {}")?;
+
+            validate_input(&filepath, &InputPolicy::default())?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate_input_with_unsupported_extension_lists_accepted_ones() -> anyhow::Result<()> {
+        let tmpdir = tempfile::tempdir()?;
+        let filepath = tmpdir.path().join("dump.bin");
+        fs::write(&filepath, "int main() {}")?;
+
+        let result = validate_input(&filepath, &InputPolicy::default());
+
+        assert!(
+            matches!(result, Err(InputError::UnsupportedExtension { .. })),
+            "wrong error type returned: {result:?}"
+        );
+        assert!(
+            result.is_err_and(|err| err.to_string().contains(".c, .cpp, .cc, .h, .txt")),
+            "accepted extensions not listed"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate_input_with_custom_extensions_or_force_ext() -> anyhow::Result<()> {
+        let tmpdir = tempfile::tempdir()?;
+        let filepath = tmpdir.path().join("dump.pseudo");
+        fs::write(&filepath, "int main() {}")?;
+
+        validate_input(
+            &filepath,
+            &InputPolicy::new().extensions(&[".pseudo".to_owned()]),
+        )?;
+        validate_input(&filepath, &InputPolicy::new().force_ext(true))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate_input_with_missing_file_fails() {
+        let result = validate_input("./tests/data/missing.c", &InputPolicy::default());
+
+        assert!(
+            matches!(result, Err(InputError::NotAFile(_))),
+            "wrong error type returned: {result:?}"
+        );
+    }
+}
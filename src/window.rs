@@ -0,0 +1,151 @@
+//! Split pseudocode that exceeds the context window of the model into overlapping windows.
+
+use crate::prompt::estimated_tokens;
+use crate::{OneiromancerResults, ResponseMetrics, Variable};
+
+/// Splits `pseudocode` into windows of whole lines of up to `max_tokens` estimated tokens each,
+/// in order. Each window after the first starts with the last `overlap` lines of the previous
+/// one, so that variables used across a boundary are seen in context.
+///
+/// Lines longer than `max_tokens` get a window of their own. Pseudocode that fits in a single
+/// window is returned as is.
+pub fn split_windows(pseudocode: &str, max_tokens: usize, overlap: usize) -> Vec<&str> {
+    let mut lines = Vec::new();
+    let mut start: usize = 0;
+    for line in pseudocode.split_inclusive('\n') {
+        let end = start.saturating_add(line.len());
+        lines.push((start, end));
+        start = end;
+    }
+
+    let mut windows = Vec::new();
+    let mut first: usize = 0;
+    while let Some(&(window_start, first_end)) = lines.get(first) {
+        let mut window_end = first_end;
+        let mut next = first.saturating_add(1);
+        while let Some(&(_, end)) = lines.get(next) {
+            if estimated_tokens(end.saturating_sub(window_start)) > max_tokens {
+                break;
+            }
+            window_end = end;
+            next = next.saturating_add(1);
+        }
+        windows.push(pseudocode.get(window_start..window_end).unwrap_or_default());
+        if next >= lines.len() {
+            break;
+        }
+        first = next.saturating_sub(overlap).max(first.saturating_add(1));
+    }
+    if windows.len() <= 1 {
+        return vec![pseudocode];
+    }
+    windows
+}
+
+/// Merges the results of the analysis of each window of the same function, in order, returning
+/// `None` if there are no windows.
+///
+/// The function name and the comment are taken from the first window, which holds the signature
+/// of the function. Each variable gets the `new_name` suggested by the first window that renames
+/// it, and the merged results carry the sum of the [`ResponseMetrics`] of all windows.
+#[must_use]
+pub fn merge_windows(windows: &[OneiromancerResults]) -> Option<OneiromancerResults> {
+    let first = windows.first()?;
+    let mut variables: Vec<Variable> = Vec::new();
+    for variable in windows.iter().flat_map(OneiromancerResults::variables) {
+        if !variables
+            .iter()
+            .any(|merged| merged.original_name() == variable.original_name())
+        {
+            variables.push(variable.clone());
+        }
+    }
+    let metrics = windows
+        .iter()
+        .fold(ResponseMetrics::default(), |acc, window| {
+            acc.merge(*window.metrics())
+        });
+    Some(
+        OneiromancerResults::new(first.function_name(), first.comment(), variables)
+            .attach_metrics(metrics),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_windows_overlaps_whole_lines() {
+        let pseudocode = "int f()\n{\n  int v1;\n  int v2;\n  int v3;\n  return 0;\n}\n";
+
+        let windows = split_windows(pseudocode, 6, 1);
+
+        assert_eq!(
+            windows,
+            [
+                "int f()\n{\n  int v1;\n",
+                "  int v1;\n  int v2;\n",
+                "  int v2;\n  int v3;\n",
+                "  int v3;\n  return 0;\n}\n",
+            ]
+        );
+        assert!(
+            windows.iter().all(|window| pseudocode.contains(window)),
+            "windows are not slices of the pseudocode"
+        );
+    }
+
+    #[test]
+    fn split_windows_keeps_pseudocode_that_fits() {
+        let pseudocode = "int main() { return 0; }";
+
+        assert_eq!(split_windows(pseudocode, 100, 4), [pseudocode]);
+        assert_eq!(
+            split_windows(pseudocode, 1, 4),
+            [pseudocode],
+            "single line split"
+        );
+        assert_eq!(split_windows("", 1, 4), [""]);
+    }
+
+    #[test]
+    fn split_windows_progresses_when_overlap_covers_whole_window() {
+        let windows = split_windows("aaaaaaaa\nbbbbbbbb\ncccccccc\n", 1, 8);
+
+        assert_eq!(windows, ["aaaaaaaa\n", "bbbbbbbb\n", "cccccccc\n"]);
+    }
+
+    #[test]
+    fn merge_windows_keeps_first_name_and_first_rename_of_each_variable() {
+        assert!(merge_windows(&[]).is_none(), "empty windows merged");
+
+        let windows = [
+            OneiromancerResults::new(
+                "parse_header",
+                "Parses the header.",
+                vec![Variable::new("a1", "buf"), Variable::new("v1", "len")],
+            ),
+            OneiromancerResults::new(
+                "checksum",
+                "Computes a checksum.",
+                vec![Variable::new("v1", "sum"), Variable::new("v2", "crc")],
+            ),
+        ];
+
+        let merged = merge_windows(&windows);
+
+        let renames: Vec<_> = merged
+            .iter()
+            .flat_map(OneiromancerResults::variables)
+            .map(|variable| (variable.original_name(), variable.new_name()))
+            .collect();
+        assert_eq!(
+            merged
+                .as_ref()
+                .map(|results| (results.function_name(), results.comment())),
+            Some(("parse_header", "Parses the header."))
+        );
+        assert_eq!(renames, [("a1", "buf"), ("v1", "len"), ("v2", "crc")]);
+    }
+}
@@ -0,0 +1,116 @@
+# Binary Ninja script generated by oneiromancer: applies the suggested function names, comments,
+# and variable renames to the current binary view. Run it with File > Run Script... or paste it in
+# the Python console, where `bv` is the current binary view.
+#
+# Functions are looked up by start address, or by their original name if the address is unknown.
+# Binary Ninja names variables differently from other decompilers, so variables are only matched
+# by their current name. Suggestions that cannot be applied are reported at the end.
+
+FUNCTIONS = [
+    {
+        "address": 0x401000,
+        "name": "sub_401000",
+        "new_name": "parse_header",
+        "comment": "Parses the \"MZ\" header at C:\\input.\nReturns 0 on success.",
+        "renames": [
+            ("a1", "buf"),
+            ("v2", "header_len"),
+        ],
+    },
+    {
+        "address": None,
+        "name": "main",
+        "new_name": "run_caf\xe9",
+        "comment": "Entry point; it's where everything starts.",
+        "renames": [
+            ("argc", "count'\""),
+        ],
+    },
+]
+
+
+def attempt(failures, message, action):
+    """Runs action, appending message to failures if it raises."""
+    try:
+        action()
+    except Exception as err:  # keep applying the other suggestions
+        failures.append("%s: %s" % (message, err))
+
+
+def find_function(view, function):
+    """Returns the function starting at the suggested address, or with the original name."""
+    if function["address"] is not None:
+        return view.get_function_at(function["address"])
+    if not function["name"]:
+        return None
+    matches = view.get_functions_by_name(function["name"])
+    if not matches:
+        return None
+    return matches[0]
+
+
+def find_variable(func, name):
+    """Returns the variable of func with the current name, looking at HLIL variables last."""
+    for var in func.vars:
+        if var.name == name:
+            return var
+    try:
+        hlil_vars = func.hlil.vars
+    except Exception:  # HLIL not available for this function
+        return None
+    for var in hlil_vars:
+        if var.name == name:
+            return var
+    return None
+
+
+def apply(view, function, failures):
+    """Applies the suggestions for a function, appending the ones that fail to failures."""
+    label = function["name"] or function["new_name"]
+    func = find_function(view, function)
+    if func is None:
+        failures.append("%s: function not found" % label)
+        return
+    attempt(
+        failures,
+        "%s: cannot rename function to %s" % (label, function["new_name"]),
+        lambda: setattr(func, "name", function["new_name"]),
+    )
+    attempt(
+        failures,
+        "%s: cannot set function comment" % label,
+        lambda: setattr(func, "comment", function["comment"]),
+    )
+    for old, new in function["renames"]:
+        var = find_variable(func, old)
+        if var is None:
+            failures.append("%s: variable %s not found" % (label, old))
+            continue
+        attempt(
+            failures,
+            "%s: cannot rename variable %s to %s" % (label, old, new),
+            lambda: func.create_user_var(var, var.type, new),
+        )
+
+
+def main(view):
+    failures = []
+    for function in FUNCTIONS:
+        attempt(
+            failures,
+            function["name"] or function["new_name"],
+            lambda: apply(view, function, failures),
+        )
+    view.update_analysis()
+    if failures:
+        print("[!] oneiromancer: %d suggestions not applied:" % len(failures))
+        for failure in failures:
+            print("    " + failure)
+    else:
+        print("[+] oneiromancer: all suggestions applied")
+
+
+if "bv" in globals():
+    main(bv)
+else:
+    print("[!] oneiromancer: no binary view, run this script from Binary Ninja")
@@ -0,0 +1,128 @@
+# Ghidra script generated by oneiromancer: applies the suggested function names, comments,
+# and variable renames to the current program. Run it from the Script Manager (Jython or PyGhidra).
+#
+# Functions are looked up by address, or by their current name if the address is unknown.
+# Suggestions that cannot be applied are reported at the end instead of stopping the script.
+#@category oneiromancer
+
+from __future__ import print_function, unicode_literals
+
+from ghidra.app.decompiler import DecompInterface
+from ghidra.program.model.pcode import HighFunctionDBUtil
+from ghidra.program.model.symbol import SourceType
+from ghidra.util.task import ConsoleTaskMonitor
+from java.lang import Throwable
+
+FUNCTIONS = [
+    {
+        "address": 0x401000,
+        "name": "sub_401000",
+        "new_name": "parse_header",
+        "comment": "Parses the \"MZ\" header at C:\\input.\nReturns 0 on success.",
+        "renames": [
+            ("a1", "buf"),
+            ("v2", "header_len"),
+        ],
+    },
+    {
+        "address": None,
+        "name": "main",
+        "new_name": "run_caf\xe9",
+        "comment": "Entry point; it's where everything starts.",
+        "renames": [
+            ("argc", "count'\""),
+        ],
+    },
+]
+
+DECOMPILE_TIMEOUT = 60
+
+
+def attempt(failures, message, action):
+    """Runs action, appending message to failures if it returns False or raises."""
+    try:
+        if action() is False:
+            failures.append(message)
+    except (Exception, Throwable) as err:
+        failures.append("%s: %s" % (message, err))
+
+
+def find_function(function):
+    """Returns the function at the suggested address, or with the current name."""
+    if function["address"] is not None:
+        return getFunctionAt(toAddr(function["address"]))
+    if not function["name"]:
+        return None
+    matches = getGlobalFunctions(function["name"])
+    if not matches:
+        return None
+    return matches[0]
+
+
+def rename_variables(func, function, failures, label):
+    """Renames the local variables of func through the decompiler, reporting missing ones."""
+    decompiler = DecompInterface()
+    decompiler.openProgram(currentProgram)
+    try:
+        results = decompiler.decompileFunction(func, DECOMPILE_TIMEOUT, ConsoleTaskMonitor())
+        high = results.getHighFunction() if results is not None else None
+        if high is None:
+            failures.append("%s: cannot decompile function, variables not renamed" % label)
+            return
+        symbols = {}
+        for symbol in high.getLocalSymbolMap().getSymbols():
+            symbols[symbol.getName()] = symbol
+        for old, new in function["renames"]:
+            symbol = symbols.get(old)
+            if symbol is None:
+                failures.append("%s: variable %s not found" % (label, old))
+                continue
+            attempt(
+                failures,
+                "%s: cannot rename variable %s to %s" % (label, old, new),
+                lambda: HighFunctionDBUtil.updateDBVariable(
+                    symbol, new, None, SourceType.USER_DEFINED
+                ),
+            )
+    finally:
+        decompiler.dispose()
+
+
+def apply_suggestions(function, failures):
+    """Applies the suggestions for a function, appending the ones that fail to failures."""
+    label = function["name"] or function["new_name"]
+    func = find_function(function)
+    if func is None:
+        failures.append("%s: function not found" % label)
+        return
+    attempt(
+        failures,
+        "%s: cannot set plate comment" % label,
+        lambda: setPlateComment(func.getEntryPoint(), function["comment"]),
+    )
+    attempt(
+        failures,
+        "%s: cannot rename function to %s" % (label, function["new_name"]),
+        lambda: func.setName(function["new_name"], SourceType.USER_DEFINED),
+    )
+    if function["renames"]:
+        rename_variables(func, function, failures, label)
+
+
+def main():
+    failures = []
+    for function in FUNCTIONS:
+        attempt(
+            failures,
+            function["name"] or function["new_name"],
+            lambda: apply_suggestions(function, failures),
+        )
+    if failures:
+        print("[!] oneiromancer: %d suggestions not applied:" % len(failures))
+        for failure in failures:
+            print("    " + failure)
+    else:
+        print("[+] oneiromancer: all suggestions applied")
+
+
+main()
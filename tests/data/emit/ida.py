@@ -0,0 +1,82 @@
+# IDAPython script generated by oneiromancer: applies the suggested function names, comments,
+# and variable renames to the database. Run it with File > Script file... in IDA.
+#
+# Functions are looked up by address, or by their original name if the address is unknown.
+# Suggestions that cannot be applied are reported at the end instead of stopping the script.
+
+import ida_hexrays
+import idaapi
+import idc
+
+FUNCTIONS = [
+    {
+        "address": 0x401000,
+        "name": "sub_401000",
+        "new_name": "parse_header",
+        "comment": "Parses the \"MZ\" header at C:\\input.\nReturns 0 on success.",
+        "renames": [
+            ("a1", "buf"),
+            ("v2", "header_len"),
+        ],
+    },
+    {
+        "address": None,
+        "name": "main",
+        "new_name": "run_caf\xe9",
+        "comment": "Entry point; it's where everything starts.",
+        "renames": [
+            ("argc", "count'\""),
+        ],
+    },
+]
+
+
+def find_function(function):
+    """Returns the function at the suggested address, or with the original name."""
+    address = function["address"]
+    if address is None:
+        if not function["name"]:
+            return None
+        address = idc.get_name_ea_simple(function["name"])
+    if address == idc.BADADDR:
+        return None
+    return idaapi.get_func(address)
+
+
+def apply(function, failures):
+    """Applies the suggestions for a function, appending the ones that fail to failures."""
+    label = function["name"] or function["new_name"]
+    func = find_function(function)
+    if func is None:
+        failures.append("%s: function not found" % label)
+        return
+    if not idc.set_name(func.start_ea, function["new_name"], idc.SN_CHECK | idc.SN_NOWARN):
+        failures.append("%s: cannot rename function to %s" % (label, function["new_name"]))
+    if not idaapi.set_func_cmt(func, function["comment"], False):
+        failures.append("%s: cannot set function comment" % label)
+    if not function["renames"]:
+        return
+    if not ida_hexrays.init_hexrays_plugin():
+        failures.append("%s: Hex-Rays decompiler not available, variables not renamed" % label)
+        return
+    for old, new in function["renames"]:
+        if not ida_hexrays.rename_lvar(func.start_ea, old, new):
+            failures.append("%s: cannot rename variable %s to %s" % (label, old, new))
+
+
+def main():
+    failures = []
+    for function in FUNCTIONS:
+        try:
+            apply(function, failures)
+        except Exception as err:  # keep applying the other suggestions
+            failures.append("%s: %s" % (function["name"] or function["new_name"], err))
+    if failures:
+        print("[!] oneiromancer: %d suggestions not applied:" % len(failures))
+        for failure in failures:
+            print("    " + failure)
+    else:
+        print("[+] oneiromancer: all suggestions applied")
+
+
+main()
@@ -0,0 +1,64 @@
+/*
+ * Minimal C host for the C ABI of the `ffi` feature, built and run by the
+ * `ffi_harness_links_against_cdylib` integration test.
+ *
+ * Usage: harness <base_url>, where <base_url> is an Ollama API that answers
+ * with valid results. Exits with 0 if all checks pass.
+ */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "oneiromancer.h"
+
+static int failures = 0;
+
+static void check(int condition, const char *what)
+{
+    if (!condition) {
+        fprintf(stderr, "FAILED: %s\n", what);
+        failures++;
+    }
+}
+
+/* Analyzes `code` with the Ollama API at `base_url`, checks that the status is
+ * `expected`, and releases the outputs. */
+static char *analyze(const char *code, const char *base_url, int expected, const char *what)
+{
+    char *json = NULL;
+    char *err = NULL;
+    int status = oneiromancer_analyze(code, base_url, "test-model", &json, &err);
+
+    check(status == expected, what);
+    check((status == ONEIROMANCER_OK) == (json != NULL), "results set only on success");
+    check((status == ONEIROMANCER_OK) == (err == NULL), "error set only on failure");
+    if (err != NULL) {
+        fprintf(stderr, "%s: %s\n", what, err);
+    }
+    oneiromancer_free_string(err);
+    return json;
+}
+
+int main(int argc, char **argv)
+{
+    if (argc != 2) {
+        fprintf(stderr, "usage: %s <base_url>\n", argv[0]);
+        return 2;
+    }
+
+    char *json = analyze("int main() { return 0; }", argv[1], ONEIROMANCER_OK, "success");
+    check(json != NULL && strstr(json, "\"function_name\"") != NULL, "results are JSON");
+    oneiromancer_free_string(json);
+
+    oneiromancer_free_string(
+        analyze(NULL, argv[1], ONEIROMANCER_INVALID_ARGUMENT, "null code"));
+    oneiromancer_free_string(
+        analyze("\xff\xfe", argv[1], ONEIROMANCER_INVALID_ARGUMENT, "invalid UTF-8"));
+    oneiromancer_free_string(analyze("int main() { return 0; }", "http://127.0.0.1:1",
+                                     ONEIROMANCER_ANALYSIS_FAILED, "unreachable server"));
+
+    check(strlen(oneiromancer_version()) > 0, "version");
+    oneiromancer_free_string(NULL);
+
+    return failures == 0 ? 0 : 1;
+}